@@ -0,0 +1,5 @@
+fn main() {
+    // Node.js add-ons need the linker flags napi-build sets up
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
@@ -0,0 +1,68 @@
+#ifndef CARS_SCANNER_H
+#define CARS_SCANNER_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct cs_database cs_database;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open an error code database CSV file. Returns NULL on failure.
+ *
+ * # Safety
+ * `path` must be NULL or a NUL-terminated string.
+ */
+cs_database *cs_open(const char *path);
+
+/**
+ * Release a database returned by `cs_open`. NULL is ignored.
+ *
+ * # Safety
+ * `db` must be NULL or a handle from `cs_open` that has not been released.
+ */
+void cs_close(cs_database *db);
+
+/**
+ * Look up one code as a JSON object. Returns NULL when the code is unknown or on failure.
+ *
+ * # Safety
+ * `db` must be NULL or a live handle from `cs_open`, and `code` NULL or a NUL-terminated string.
+ */
+char *cs_lookup_json(const cs_database *db, const char *code);
+
+/**
+ * Search descriptions, causes and actions for a keyword, most relevant first.
+ * Returns a JSON object with "total", "offset" and "results"; a limit of 0 means no limit.
+ *
+ * # Safety
+ * `db` must be NULL or a live handle from `cs_open`, and `keyword` NULL or a NUL-terminated string.
+ */
+char *cs_search_json(const cs_database *db, const char *keyword, size_t offset, size_t limit);
+
+/**
+ * Release a string returned by this library. NULL is ignored.
+ *
+ * # Safety
+ * `text` must be NULL or a string returned by this library that has not been released.
+ */
+void cs_string_free(char *text);
+
+/**
+ * The message for the last failure on this thread, or NULL. Valid until the next call.
+ */
+const char *cs_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CARS_SCANNER_H */
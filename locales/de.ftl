@@ -0,0 +1,79 @@
+# Deutsch
+
+## Report and display sections
+label-error-code = Fehlercode:
+label-description = Beschreibung:
+label-severity = Schweregrad:
+label-system = System:
+label-source = Quelle:
+section-possible-causes = Mögliche Ursachen:
+section-recommended-actions = Empfohlene Maßnahmen:
+section-related-parts = Zugehörige Teile:
+section-warranty = Garantie und Serviceaktionen:
+label-emissions-warranty = Abgasgarantie:
+label-campaign = Aktion:
+label-link = Link:
+warranty-covered = Abgedeckt
+warranty-not-covered = Nicht abgedeckt
+warranty-unknown = Unbekannt
+section-labor = Arbeitspositionen:
+labor-op-code = Arbeitsposition
+labor-description = Beschreibung
+labor-hours = Stunden
+labor-total = Gesamt
+report-title = Fehlercode-Bericht
+label-customer = Kunde:
+label-repair-order = Auftrag:
+label-odometer = Kilometerstand:
+label-date = Datum:
+time-date-format = %d.%m.%Y
+time-datetime-format = %d.%m.%Y %H:%M %Z
+
+## Messages
+error-prefix = Fehler
+db-loaded = { $count } Fehlercodes aus der Datenbank geladen
+db-missing = Fehlercode-Datenbank unter { $path } nicht gefunden
+db-missing-hint = Bitte stellen Sie sicher, dass die Datei am richtigen Ort liegt.
+code-not-found = Fehlercode '{ $code }' nicht in der Datenbank gefunden
+export-failed = Bericht konnte nicht exportiert werden: { $error }
+report-exported = Bericht nach { $path } exportiert
+file-written = { $path } geschrieben
+found-system = { $count } Fehler für System gefunden: { $system }
+none-system = Keine Fehler für System gefunden: { $system }
+found-severity = { $count } Fehler mit Schweregrad gefunden: { $severity }
+none-severity = Keine Fehler mit Schweregrad gefunden: { $severity }
+found-keyword = { $count } Fehler mit Suchbegriff gefunden: { $keyword }
+none-keyword = Keine Fehler mit Suchbegriff gefunden: { $keyword }
+found-filter = { $count } Fehler entsprechen den Filtern
+none-filter = Keine Fehler entsprechen den Filtern
+batch-summary = { $count } Codes nachgeschlagen, { $missing } nicht gefunden
+parts-none = Keine zugehörigen Teile für { $code } erfasst
+parts-header = Zugehörige Teile für { $code }
+parts-pricing = Verfügbarkeit/Preis:
+quote-header = Kostenvoranschlag für { $code } - { $description }
+quote-none = Keine Arbeitspositionen für diesen Code erfasst
+quote-for-action = für: { $action }
+quote-total = Arbeitszeit gesamt:
+quote-cost = Geschätzte Arbeitskosten: { $cost } (bei { $rate }/h)
+
+## Interactive mode
+repl-banner = === Fahrzeugdiagnose – Interaktiver Modus ===
+repl-intro = Geben Sie '{ $help }' für verfügbare Befehle oder '{ $exit }' zum Beenden ein
+repl-commands = Verfügbare Befehle:
+repl-help-lookup = Details zu einem oder mehreren Fehlercodes anzeigen
+repl-help-bare = Allein eingegebene Codes werden ebenfalls nachgeschlagen
+repl-help-parts = Zugehörige Teile eines Fehlercodes auflisten
+repl-help-system = Alle Fehler eines bestimmten Systems auflisten
+repl-help-severity = Alle Fehler mit einem bestimmten Schweregrad auflisten
+repl-help-search = Nach Fehlern mit einem Suchbegriff suchen
+repl-help-transcript = Diese Sitzung als Markdown-Protokoll speichern
+repl-help-help = Diese Hilfe anzeigen
+repl-help-topic = Ein erweitertes Hilfethema anzeigen
+repl-help-exit = Interaktiven Modus beenden
+repl-usage = Verwendung: { $usage }
+repl-unknown-topic = Unbekanntes Hilfethema '{ $topic }'
+repl-unknown-command = Unbekannter Befehl.
+repl-help-hint = Geben Sie '{ $help }' für verfügbare Befehle ein.
+repl-exit = Interaktiver Modus wird beendet
+transcript-saved = { $count } Befehle in { $path } gespeichert
+transcript-failed = Protokoll konnte nicht gespeichert werden: { $error }
@@ -0,0 +1,627 @@
+# English messages. This file is the reference locale: every message used
+# by the program must exist here, other locales fall back to it.
+
+## Report and display sections
+label-error-code = Error Code:
+label-description = Description:
+label-severity = Severity:
+severity-adjusted = adjusted by policy from { $original }
+severity-adjusted-because = adjusted by policy from { $original }: { $reason }
+label-system = System:
+label-difficulty = Difficulty:
+section-safety = Safety Warnings:
+difficulty-diy = DIY: hand tools and the owner's manual
+difficulty-intermediate = Intermediate: needs a scan tool, a multimeter and some experience
+difficulty-professional = Professional: leave it to a workshop
+label-source = Source:
+section-possible-causes = Possible Causes:
+section-recommended-actions = Recommended Actions:
+section-related-parts = Related Parts:
+section-warranty = Warranty & Service Campaigns:
+label-emissions-warranty = Emissions Warranty:
+label-campaign = Campaign:
+label-link = Link:
+db-export-done = Exported { $codes } codes to { $path }
+contribute-nothing = No added or edited codes compared with { $baseline }.
+contribute-written = Bundled { $codes } codes into { $path }; review summary in { $summary }
+contribute-summary-title = Error code contribution
+contribute-summary-counts = { $added } added and { $edited } edited codes compared with { $baseline }, created { $created }.
+contribute-summary-added = Added codes
+contribute-summary-edited = Edited codes
+contribute-summary-private = Pack columns kept private and not included:
+section-set-context = When It Sets:
+label-set-conditions = Conditions:
+label-monitors = Monitors:
+label-translation = Description ({ $language }):
+label-aliases = Also known as:
+set-context-reproduce = To reproduce it: { $command }
+warranty-covered = Covered
+warranty-not-covered = Not covered
+warranty-unknown = Unknown
+section-labor = Labor Operations:
+labor-op-code = Op Code
+labor-description = Description
+labor-hours = Hours
+labor-total = Total
+report-title = Car Error Code Report
+report-dark-mode = Light / dark
+label-customer = Customer:
+label-repair-order = Repair Order:
+label-odometer = Odometer:
+label-date = Date:
+time-date-format = %Y-%m-%d
+time-datetime-format = %Y-%m-%d %H:%M %Z
+column-code = Code
+column-description = Description
+column-severity = Severity
+column-system = System
+
+## Messages
+error-prefix = Error
+warning-prefix = Warning
+node-stale = { $path } changed since it was loaded; lookups and searches answer from the codes read then until reload()
+interrupted = Interrupted
+feature-missing = This build leaves out the { $feature } feature; rebuild it with --features { $feature }
+db-loaded = Loaded { $count } error codes from database
+db-missing = Could not find error codes database at { $path }
+db-missing-hint = Please make sure the file exists in the correct location.
+db-pack-loaded = Loaded { $count } error codes from pack { $pack }
+sources-header = { $code } is defined by { $count ->
+    [one] one source
+   *[other] { $count } sources
+}:
+sources-in-use = (in use)
+sources-overridden = (overridden)
+sources-same = all:
+merge-header = { $code } merges { $count ->
+    [one] one definition
+   *[other] { $count } definitions
+}, highest precedence first:
+merge-precedence = precedence { $precedence }
+merge-unset = (unset)
+pack-not-found = No pack named '{ $pack }' in the configuration
+db-remove-action = Remove { $code } ({ $description }) from { $path }
+db-remove-missing = { $code } is not defined in { $path }
+db-remove-done = Removed { $code } from { $path }
+db-add-invalid-code = '{ $code }' is not a valid code; use a letter P, B, C or U followed by four hex digits
+db-add-exists = { $code } is already defined in { $path }
+db-add-no-suggestion = No similar codes to suggest a severity for { $code }; pass --severity
+db-add-suggested = Suggested severity: { $severity } ({ $confidence }% of similar codes: { $similar })
+db-add-done = Added { $code } ({ $severity }) to { $path }
+db-validate-no-description = Description is empty
+builder-no-severity = Severity is not set; use Low, Medium, High or Critical
+db-validate-bad-severity = Unknown severity '{ $severity }'; use Low, Medium, High or Critical
+db-validate-bad-difficulty = Unknown difficulty '{ $difficulty }'; use DIY, Intermediate or Professional, or leave it empty
+db-validate-inconsistent = Severity { $severity } differs from similar codes rated { $suggested }: { $similar }
+db-validate-summary = Checked { $codes } codes: { $errors } errors, { $warnings } warnings
+db-sample-bad-where = '{ $condition }' is not a condition; use system=<system> or severity=<level>
+db-sample-header = { $count } of { $total } codes, seed { $seed } (repeat with --seed { $seed })
+db-sample-none = No codes match
+db-import-summary = { $file } into { $path }: { $added } to add, { $updated } to update, { $unchanged } unchanged, { $conflicts } conflicting
+db-import-more = … and { $count } more
+db-import-conflict = defined more than once with different values; not imported
+db-import-unknown-column = Column not in the database; ignored
+db-import-nothing = Nothing to import into { $path }
+db-import-action = Import { $added } new and { $updated } changed codes into { $path }
+db-import-done = Imported { $added } new and { $updated } changed codes into { $path }
+db-sqlite-action = Write { $count } codes from { $file } and the configured packs to the SQLite database { $path }
+db-sqlite-done = Wrote { $count } codes to { $path }; set database = "{ $path }" in the config file to use it
+db-sqlite-current = { $path } is up to date with the files it was converted from
+db-sqlite-no-sources = { $path } does not record the files it was converted from; run 'db import <FILE> --sqlite { $path }' instead
+sqlite-unavailable = This build has no SQLite support; rebuild with --features sqlite
+sqlite-failed = Could not read or write the SQLite database { $path }: { $error }
+sqlite-newer = { $path } uses schema { $version }, newer than the { $supported } this version supports; upgrade cars-scanner
+freshness-bundled = bundled database
+freshness-sqlite = SQLite database
+freshness-file = { $name } ({ $path }): updated { $date }, { $days ->
+    [one] { $days } day ago
+   *[other] { $days } days ago
+}
+freshness-stale = { $file }, older than { $max_age }; see 'db update --check'
+freshness-sqlite-changed = { $path } was converted from { $source }, which has changed since; run 'db import --sqlite { $path } --refresh' to convert it again
+db-update-done = Marked { $path } as updated { $date }
+db-update-modified = (dated by modification time)
+db-update-fresh = Every file was updated within { $max_age }
+db-update-stale = { $count ->
+    [one] { $count } file is
+   *[other] { $count } files are
+} older than { $max_age }
+db-update-hint = Replace or import the newer codes, then run 'db update' (with --pack for a pack)
+db-update-never-stale = [freshness] max_age is 0: files are never reported as stale
+db-migrate-current = { $path } is already in the current schema ({ $version })
+db-migrate-plan = Upgrading { $path } from schema { $from } to { $to }:
+db-migrate-line = start the file with '{ $line }'
+db-migrate-step = schema { $version } adds { $columns }, left empty in every row
+db-migrate-action = Rewrite { $path } in schema { $version }
+db-migrate-done = Rewrote { $path } in schema { $version } ({ $codes ->
+    [one] one code
+   *[other] { $codes } codes
+})
+safety-dry-run = Dry run, nothing changed:
+safety-prompt = [y/N]
+safety-needs-yes = Not running in a terminal; pass --yes to confirm: { $action }
+safety-cancelled = Cancelled.
+troubleshoot-none = No decision tree for { $code }
+troubleshoot-header = Troubleshooting { $code }: { $title }
+troubleshoot-intro = Answer each step; press Enter or type q to stop.
+troubleshoot-yes-no = (y/n)
+troubleshoot-yes = yes
+troubleshoot-no = no
+troubleshoot-invalid = Please enter a valid answer.
+troubleshoot-conclusion = Conclusion:
+troubleshoot-abandoned = Stopped before reaching a conclusion.
+troubleshoot-loop = The decision tree did not reach a conclusion within { $steps } steps
+troubleshoot-recorded = Path recorded in { $path }
+code-not-found = Error code '{ $code }' not found in database
+lookup-last-seen-vehicle = Last seen on this vehicle { $date } ({ $count ->
+    [0] not
+    [one] once
+   *[other] { $count } times
+} in the past year)
+lookup-last-seen = Last seen { $date } on { $vin } ({ $count ->
+    [0] not
+    [one] once
+   *[other] { $count } times
+} in the past year)
+export-failed = Failed to export report: { $error }
+report-exported = Report exported to { $path }
+report-unchanged = { $path } is up to date: nothing it is made from changed since it was written (--force writes it again)
+report-unknown-format = No report format '{ $format }'; known: { $known }
+report-scan-caption = Scan of { $vin }, { $time }
+report-needs-code = Which code should the report be about? Give one, or use --type warranty-claim for all of a vehicle's codes
+warranty-claim-written = Wrote a warranty claim with { $codes ->
+    [one] { $codes } code
+   *[other] { $codes } codes
+} to { $path }
+warranty-unknown-oem = No warranty claim layout '{ $oem }'; known: { $known }
+repair-order-bad-odometer = '{ $value }' is not an odometer reading
+file-written = Wrote { $path }
+found-system = Found { $count } errors for system: { $system }
+none-system = No errors found for system: { $system }
+found-severity = Found { $count } errors with severity: { $severity }
+none-severity = No errors found with severity: { $severity }
+found-keyword = Found { $count } errors containing keyword: { $keyword }
+search-page = Showing { $first }-{ $last } of { $total }
+search-more = More results: add { $arguments }
+none-keyword = No errors found containing keyword: { $keyword }
+search-no-last = There is no previous search to refine; run search without --within-last first
+search-not-kept = Could not keep the results for --within-last: { $error }
+search-jsonl-export = --export writes a report of the results; with --format jsonl, redirect the output to a file instead
+search-jsonl-sort = --format jsonl writes each result as it is found, in code order; sort by relevance or severity downstream
+format-unsupported = { $command } does not print { $format }; --format json works with lookup, search, list, list-by-system, list-by-severity and query, jsonl with search, and csv with db export
+reverse-found = Closest descriptions to "{ $phrase }":
+reverse-none = No description shares a word with "{ $phrase }"
+reverse-hint = Run lookup <code> for the full entry
+found-filter = Found { $count } errors matching the given filters
+none-filter = No errors found matching the given filters
+query-no-rows = No codes match the query
+query-rows = { $count ->
+    [one] 1 row
+   *[other] { $count } rows
+}
+query-expected = Query error: expected { $expected }, found { $found }
+query-end = the end of the query
+query-a-column = a column
+query-a-value = a quoted value or a number
+query-a-number = a number
+query-a-comparison = =, !=, <, <=, >, >=, LIKE, IN or IS NULL
+query-unknown-column = Query error: unknown column '{ $column }'; the columns are { $columns }
+query-unknown-table = Query error: unknown table '{ $table }'; the one table is codes
+query-unterminated = Query error: a quoted value is not closed
+query-unexpected = Query error: unexpected character '{ $found }'
+batch-summary = Looked up { $count } codes, { $missing } not found
+net-offline = Not fetching { $url }: running with --offline and no cached copy
+net-fetching = Fetching { $url }...
+extract-reading = Reading { $path }...
+extract-none = No trouble codes found in { $path }
+extract-found = Found { $codes } OBD-II codes and { $faults } SPN/FMI faults in { $path }
+extract-spn-header = J1939 faults (not in the OBD-II database):
+parts-none = No related parts recorded for { $code }
+parts-header = Related parts for { $code }
+parts-pricing = Availability/pricing:
+quote-header = Quote for { $code } - { $description }
+quote-none = No labor operations recorded for this code
+quote-for-action = for: { $action }
+quote-total = Total labor:
+quote-cost = Estimated labor cost: { $cost } (at { $rate }/h)
+
+## Interactive mode
+repl-banner = === Car Diagnostic Tool Interactive Mode ===
+repl-intro = Type '{ $help }' for available commands or '{ $exit }' to quit
+repl-commands = Available commands:
+repl-help-lookup = Look up details for one or more error codes
+repl-help-bare = Typing codes on their own also looks them up
+repl-help-parts = List the parts related to an error code
+repl-help-compare = Show two codes side by side with their differences highlighted
+repl-help-troubleshoot = Walk through the decision tree for a code
+repl-help-system = List all errors for a specific system
+repl-help-severity = List all errors with a specific severity
+repl-help-search = Search for errors containing a keyword
+repl-help-reverse = Find codes by the text shown on a dashboard or app
+repl-help-connect = Attach to an OBD-II adapter (port from the configuration by default)
+repl-help-disconnect = Release the adapter
+repl-help-status = Show the adapter, protocol, battery voltage and ECUs found
+repl-help-drive-cycle = Drive cycle steps; tracks readiness live when connected
+repl-help-analyze = Sample fuel trims at idle and 2500 rpm and explain the pattern
+repl-help-scan = Read the MIL and the stored, pending and permanent codes with their status
+repl-help-o2-tests = Oxygen sensor and catalyst test results with pass/fail
+repl-help-monitor = Show live data until Enter is pressed, alerting on configured thresholds; --tui shows gauges until q
+repl-help-transcript = Save this session as a Markdown transcript
+repl-help-help = Display this help message
+repl-help-topic = Show how to use a command, with examples, or an extended help topic
+repl-help-exit = Exit the interactive mode
+repl-usage = Usage: { $usage }
+repl-unknown-topic = Unknown command or help topic '{ $topic }'
+help-usage = Usage:
+help-options = Options:
+help-examples = Examples:
+repl-unknown-command = Unknown command.
+repl-help-hint = Type '{ $help }' for available commands.
+repl-exit = Exiting interactive mode
+repl-status = { $database } | { $vehicle } | { $adapter } | { $language } | { $profile }
+repl-status-db = { $codes } codes
+repl-status-db-packs = { $codes } codes (bundled + { $packs ->
+    [one] one pack
+   *[other] { $packs } packs
+})
+repl-status-no-vehicle = no vehicle
+repl-help-pick = Find a code by typing parts of its code or description
+pick-prompt = pick>
+pick-count = { $matches } of { $total } codes · ↑/↓ select · Enter show · Esc cancel
+pick-needs-terminal = pick needs an interactive terminal; use search <keyword> instead
+repl-help-vehicle-set = Work on one vehicle: filter packs by its make and keep its history
+repl-help-vehicle-clear = Stop working on the vehicle
+repl-help-garage-unlink = Take a scan out of its vehicle's history
+repl-help-garage-move = File a scan under another vehicle
+vehicle-set = Working on { $vehicle }: { $codes } codes apply. Lookups and scans are added to its history.
+vehicle-current = Working on { $vehicle } ({ $codes } codes apply)
+vehicle-none = No vehicle set; use { $set } to choose one
+vehicle-cleared = Vehicle cleared; every code applies again
+vehicle-recorded = Added to the history of { $vehicle } as scan { $id }
+repl-help-results = Show the working result set: the codes of the last lookup, search, list or scan
+repl-help-note = Add a note to the work in progress
+repl-help-notes = Show the notes taken
+repl-help-workspace-save = Keep the vehicle, result set and notes under a name (the loaded workspace by default)
+repl-help-workspace-load = Pick up a saved workspace
+repl-help-workspace-list = List the saved workspaces
+repl-help-resume = Pick up the session saved before the idle screen
+repl-idle-screen = Nobody has typed anything for { $minutes ->
+    [one] a minute
+   *[other] { $minutes } minutes
+}; the session was saved. Type a command to start a new one, or '{ $resume }' to continue it.
+repl-idle-new-session = New session. The previous one is saved as workspace { $name }; '{ $resume }' brings it back.
+repl-autosave-failed = The session could not be saved: { $error }
+results-none = No result set yet; look up, search, list or scan some codes first
+results-header = { $count ->
+    [one] One code
+   *[other] { $count } codes
+} from '{ $query }'
+note-added = Note { $count } added
+notes-header = Notes
+notes-none = No notes yet; add one with { $note }
+workspace-saved = Saved workspace { $name }: { $results ->
+    [one] one code
+   *[other] { $results } codes
+}, { $notes ->
+    [one] one note
+   *[other] { $notes } notes
+}
+workspace-loaded = Loaded workspace { $name }, saved { $saved }
+workspace-list-header = Saved workspaces
+workspace-contents = { $results ->
+    [one] one code
+   *[other] { $results } codes
+}, { $notes ->
+    [one] one note
+   *[other] { $notes } notes
+}
+workspace-none = No workspaces saved yet; use { $save } <name>
+vehicle-record-failed = Could not add to the vehicle history: { $error }
+vehicle-invalid-vin = '{ $vin }' is not a valid VIN: I, O and Q are never used
+vehicle-set-nothing = Give a VIN or a make, e.g. vehicle set Toyota Corolla 2015
+repl-status-adapter = { $port }: { $protocol }
+repl-status-disconnected = no adapter
+repl-status-defaults = default settings
+transcript-saved = Saved { $count } commands to { $path }
+transcript-failed = Could not save the transcript: { $error }
+
+## Adapter
+adapter-connecting = Connecting to { $port }...
+adapter-connected = Connected to { $port }
+adapter-attached = Connected to { $port } through the adapter daemon
+adapter-already-connected = Already connected to { $port }; type '{ $disconnect }' first
+adapter-disconnected = Disconnected from { $port }
+adapter-not-connected = No adapter connected; type '{ $connect }' to attach one
+adapter-no-port = No port given and no port set in the [adapter] table
+adapter-label-adapter = Adapter:
+adapter-label-protocol = Protocol:
+adapter-label-voltage = Voltage:
+adapter-label-ecus = ECUs:
+adapter-voltage-unavailable = unavailable ({ $error })
+adapter-label-missing = Not supported:
+adapter-capability-voltage = battery voltage
+adapter-capability-protocol-number = protocol number, read from the description instead
+adapter-capability-mode06 = on-board monitoring results
+adapter-capability-vin = VIN
+adapter-unsupported = The adapter or vehicle does not support { $what } ({ $command })
+
+## Monitor
+monitor-started = Reading { $pids } every { $interval } ms; press Enter to stop
+monitor-stopped = Stopped after { $samples } samples
+monitor-unknown-pid = Unknown PID '{ $pid }'; use one of { $known }
+monitor-unknown-signal = No signal '{ $signal }' in the DBC file; it has { $known }
+adapterd-listening = Holding { $port } for other commands on { $socket }; press Ctrl-C to stop
+adapterd-started = Adapter daemon started (pid { $pid }); commands using { $port } now go through it
+adapterd-running = Adapter daemon running (pid { $pid }) since { $since }
+adapterd-not-running = The adapter daemon is not running
+adapterd-stopped = Adapter daemon stopped
+adapterd-already-running = The adapter daemon is already running (pid { $pid }) and holds { $port }; stop it first
+adapterd-busy = The adapter daemon is busy with another command; try again when it ends
+adapterd-waiting = Waiting for the adapter daemon to finish another command (Ctrl-C to give up)
+adapterd-failed = The adapter daemon did not start: { $reason } (see { $log })
+adapterd-no-answer = it did not answer in time
+adapterd-label-clients = Clients:
+emulate-listening = Emulating an ELM327 on { $address }; press Ctrl-C to stop
+emulate-connected = Client connected from { $peer }
+emulate-disconnected = Client disconnected
+monitor-alert = ALERT
+monitor-ok = OK
+monitor-alert-above = { $pid } is { $value }, above { $limit }
+monitor-alert-below = { $pid } is { $value }, below { $limit }
+monitor-alert-cleared = { $pid } is back within limits at { $value }
+monitor-log-failed = Could not write { $path }: { $error }
+monitor-summary-header = Summary of { $samples } samples over { $minutes }m { $seconds }s
+monitor-summary-min = min
+monitor-summary-avg = avg
+monitor-summary-max = max
+monitor-summary-stddev = stddev
+monitor-summary-violations = Threshold violations: { $count }
+monitor-summary-codes = Trouble codes during the session:
+monitor-summary-codes-unchanged = No change
+monitor-summary-code-added = set during the session
+monitor-summary-code-cleared = cleared during the session
+monitor-summary-saved = Summary saved to the scan archive as { $id }
+gauges-title = Live data
+gauges-paused = PAUSED
+gauges-new-code = NEW { $code }
+gauges-keys = space pause/resume   p choose PIDs   q quit
+gauges-menu = Choose the PIDs to show
+gauges-menu-keys = up/down move   space pick   Enter apply   Esc cancel
+gauges-needs-terminal = The gauge screen needs a terminal; leave out --tui to print a line per sample
+gauges-unsupported = The gauge screen is only available on Linux; leave out --tui to print a line per sample
+
+## Drive cycles
+readiness-header = Readiness: { $complete } of { $total } monitors complete, MIL { $mil }, { $codes } stored codes
+readiness-mil-on = on
+readiness-mil-off = off
+readiness-complete = complete
+readiness-incomplete = incomplete
+drive-cycle-preconditions = Before driving
+drive-cycle-generic = (generic)
+drive-cycle-make = ({ $make })
+drive-cycle-no-steps = No drive cycle is known for this monitor
+drive-cycle-unknown-monitor = Unknown monitor '{ $monitor }'; use one of { $known }
+drive-cycle-none-incomplete = Every supported monitor is complete; no drive cycle is needed
+drive-cycle-watching = Checking readiness every { $seconds } s while you drive; press Enter to stop
+drive-cycle-completed = { $monitor } monitor complete
+drive-cycle-all-complete = All monitors complete
+drive-cycle-stopped = Stopped with { $remaining } monitors still incomplete
+
+## Fuel trims
+fuel-trim-header = Fuel trims (short + long term)
+fuel-trim-idle = Idle
+fuel-trim-cruise = 2500 rpm
+fuel-trim-bank = Bank { $bank }
+fuel-trim-samples = { $samples } samples
+fuel-trim-no-trims = No fuel trim readings were found
+fuel-trim-prompt-idle = Start the engine and let it idle at operating temperature, then press Enter
+fuel-trim-prompt-cruise = Hold the engine steady at 2500 rpm, then press Enter
+fuel-trim-normal = Trims are within ±10% at idle and at 2500 rpm; the mixture is under control
+fuel-trim-vacuum-leak = Lean at idle but not at 2500 rpm: unmetered air, most likely a vacuum leak (intake gaskets, PCV, brake booster hose)
+fuel-trim-maf = Lean at idle and at 2500 rpm: the MAF sensor is probably under-reporting airflow; clean or test it before suspecting fuel delivery
+fuel-trim-fuel-delivery = Lean at 2500 rpm but not at idle: fuel delivery cannot keep up; check fuel pressure, the pump and the filter
+fuel-trim-rich = Rich: check for leaking injectors, high fuel pressure, a stuck-open purge valve or a MAF over-reporting airflow
+fuel-trim-not-enough = Needs at least { $samples } samples at idle and at 2000-3000 rpm
+fuel-trim-related = Related codes
+
+## Inspection
+scan-header = Trouble codes
+scan-label-mil = MIL:
+scan-label-reported = Codes reported by the ECUs:
+scan-label-cleared = Since the codes were cleared:
+scan-label-mil-on-for = Since the MIL came on:
+scan-distance = { $km } km
+scan-running-time = { $minutes } min of engine running
+scan-advice-recently-cleared = The codes were cleared recently, too recently for the monitors to have run. A fault may have been erased to hide it, as before a sale: check the readiness monitors, and drive the vehicle and scan again before trusting a clean result.
+scan-recently-cleared = recently cleared
+scan-mil-on = ON
+scan-mil-off = OFF
+scan-none = No trouble codes stored
+scan-confirmed = confirmed
+scan-pending = pending
+scan-permanent = permanent
+scan-test-failed = failing now
+scan-failed-this-cycle = failed this drive cycle
+scan-failed-since-clear = failed since codes were cleared
+scan-warning-indicator = warning lamp requested
+scan-occurrences = failed on { $count } drive cycles
+scan-aging = passed on { $count } since
+scan-pattern-hard = Hard fault: failing now, on this drive cycle. It will be there to find; repair before clearing.
+scan-pattern-intermittent = Intermittent: failed on several drive cycles but passing now. Look for loose connectors, chafed wiring or conditions that come and go.
+scan-pattern-once = One-off: failed on a single drive cycle. Possibly a glitch; note it and check whether it returns before replacing parts.
+scan-advice-mil-off = Stored with the MIL off: not an emission fault, or it passed on recent drive cycles. Check it, but the vehicle can usually be driven.
+scan-advice-pending = Pending only: the test failed once. It is confirmed and lights the MIL if it fails again on the next drive cycle; verify before replacing parts.
+scan-advice-permanent = Permanent only: repaired or cleared, but kept until the monitor passes again. An inspection fails until then.
+scan-advice-mil-no-codes = The MIL is on but no confirmed code was read; look in the other modules.
+scan-count-mismatch = The ECUs report { $reported } codes but { $listed } were read; some may be in modules that did not answer.
+scan-target-reading = Reading the codes over { $port }...
+scan-target-header = { $port } - VIN { $vin }
+scan-target-failed = scan failed
+scan-target-crashed = The scan stopped unexpectedly
+scan-combined-header = All vehicles
+scan-combined-count = { $scanned } scanned, { $failed } failed
+scan-saved = Saved { $count } scans to the archive
+scan-label-module = Module:
+module-unknown = No module '{ $module }'; known: { $known }, or give a CAN address such as 7E2
+module-invalid-address = '{ $address }' is not an 11-bit CAN address such as 7E0
+module-needs-can = Selecting the { $module } module needs a CAN vehicle; this one talks { $protocol }
+clear-action-module = Clear the codes, freeze frame and MIL of the { $module } module
+clear-action-all = Clear the codes, freeze frame and MIL of every module
+clear-refused = ECU { $ecu } refused to clear its codes (reason { $reason }); switch the engine off with the ignition on and try again
+clear-no-answer = No ECU answered the clear request; check the module and that the ignition is on
+clear-done = Cleared { $codes } codes on { $ecus }
+inspection-unknown-jurisdiction = Unknown jurisdiction '{ $jurisdiction }'; templates exist for { $known }
+inspection-vin = VIN:
+inspection-vin-year = { $vin } (model year { $year })
+inspection-vin-unknown = not reported
+inspection-protocol = Protocol:
+inspection-mil-on = ON
+inspection-mil-off = OFF
+inspection-readiness-summary = { $complete } of { $total } complete ({ $allowed } incomplete allowed)
+inspection-complete = Complete
+inspection-incomplete = Not complete
+inspection-stored = Stored:
+inspection-pending = Pending:
+inspection-permanent = Permanent:
+inspection-none = none
+inspection-reason-mil = The MIL is commanded on
+inspection-reason-incomplete = { $incomplete } readiness monitors are not complete; at most { $allowed } are allowed
+inspection-reason-permanent = { $count } permanent codes are stored
+
+## Oxygen sensor tests
+o2-header = Oxygen sensor and catalyst test results
+o2-no-mode06 = The vehicle does not report on-board monitoring results (mode 06), where CAN vehicles keep their oxygen sensor and catalyst tests
+o2-none = The vehicle reported no oxygen sensor test results; drive until the O2 sensor monitor completes and try again
+o2-location-sensor = Bank { $bank } Sensor { $sensor }
+o2-location-catalyst = Catalyst Bank { $bank }
+o2-pass = PASS
+o2-fail = FAIL
+o2-all-passed = All tests are within their limits
+o2-related = Related codes
+o2-test-rich-lean-threshold = Rich to lean threshold voltage
+o2-test-lean-rich-threshold = Lean to rich threshold voltage
+o2-test-low-switch-voltage = Low voltage for switch time
+o2-test-high-switch-voltage = High voltage for switch time
+o2-test-rich-lean-time = Rich to lean switch time
+o2-test-lean-rich-time = Lean to rich switch time
+o2-test-min-voltage = Minimum sensor voltage
+o2-test-max-voltage = Maximum sensor voltage
+o2-test-transition-time = Time between transitions
+o2-test-period = Sensor period
+o2-test-manufacturer = Manufacturer test { $test }
+o2-code-low = O2 Sensor Circuit Low Voltage (Bank { $bank } Sensor { $sensor })
+o2-code-high = O2 Sensor Circuit High Voltage (Bank { $bank } Sensor { $sensor })
+o2-code-slow = O2 Sensor Circuit Slow Response (Bank { $bank } Sensor { $sensor })
+o2-code-catalyst = Catalyst System Efficiency Below Threshold (Bank { $bank })
+
+## Doctor
+doctor-config = Configuration
+doctor-config-default = { $path } not found, using defaults
+doctor-config-fix = Correct or remove the invalid settings in { $path } or the CARS_SCANNER_* variables
+doctor-database = Error code database
+doctor-database-fix = Restore src/data/error_codes.csv from the repository or run from the project directory
+doctor-database-empty = { $path } contains no error codes
+doctor-database-invalid-fix = Fix the reported row; see 'help database' for the expected columns
+doctor-freshness = Database age
+doctor-freshness-ok = Updated within { $max_age }
+doctor-freshness-fix = Bring the files up to date and run 'db update', or raise [freshness] max_age
+doctor-labor = Labor guide
+doctor-labor-fix = Fix the labor guide CSV or remove labor_guide from the configuration
+doctor-decision-trees = Decision trees
+doctor-decision-trees-loaded = { $count } codes have a decision tree
+doctor-decision-trees-fix = Fix the decision tree YAML or remove decision_trees from the configuration
+doctor-logo = Report logo
+doctor-logo-fix = Point branding.logo at a PNG, JPEG, GIF, SVG or WebP file
+doctor-pack = Pack { $pack }
+doctor-pack-loaded = { $count } codes in { $path }
+doctor-pack-fix = Fix or remove the [[packs]] entry in cars-scanner.toml
+doctor-data-dir = Data directory
+doctor-data-dir-fix = Make the directory writable or set data_dir in the configuration
+doctor-endpoint = Endpoint { $key }
+doctor-endpoint-url-fix = Set { $key } to a full http:// or https:// URL
+doctor-endpoint-fix = Check the network connection and DNS settings, or the host name in the configuration
+doctor-endpoint-offline = { $host } (not checked with --offline)
+doctor-fix = Fix:
+doctor-passed = All checks passed
+doctor-failed = { $count } checks failed
+
+## Fleet
+fleet-importing = Importing scans
+fleet-imported = Imported scans for { $vehicles } vehicles from { $path }
+fleet-summary = Fleet summary: { $codes } codes across { $vehicles } vehicles
+fleet-top-codes = Top codes (code, affected vehicles, description):
+fleet-unknown-codes = { $count } codes not in the database:
+fleet-no-critical = No critical codes found
+health-line = Health score: { $score }/100 - { $verdict }
+health-good = Good: no significant faults
+health-fair = Fair: schedule a service visit
+health-poor = Poor: repairs needed soon
+health-critical = Critical: inspect before driving
+export-app-written = Wrote { $codes } codes from { $scans } scans to { $path }
+history-none = No archived scans for { $vin }
+history-header = Scan history for { $vin } ({ $scans } scans):
+garage-unlinked = Scan { $id } taken out of the history of { $vin }
+garage-moved = Scan { $id } moved from { $from } to { $to }
+garage-unknown-scan = No archived scan has the id '{ $id }'; 'fleet history <VIN>' shows the ids
+garage-not-linked = Scan { $id } is not in the history of any vehicle
+garage-already-linked = Scan { $id } is already in the history of { $vin }
+garage-no-scan = No scan was added to a vehicle history in this session; give its id
+history-improving = Improving since the first scan. Now:
+history-worsening = Worse than the first scan. Now:
+history-stable = Unchanged since the first scan. Now:
+fleet-critical = { $count } critical alerts:
+fleet-report-title = Fleet Maintenance Dashboard
+fleet-report-period = Scans from the last { $period }: { $scans }
+fleet-report-vehicles = Vehicles
+fleet-report-occurrences = Occurrences
+fleet-report-distinct = Distinct codes
+fleet-report-critical = Critical alerts
+fleet-report-frequency = Codes by frequency
+fleet-report-repeats = Vehicles with repeat issues
+fleet-report-no-repeats = No code was seen in more than one scan of the same vehicle.
+fleet-report-scans = Scans
+fleet-report-heat-map = Severity by system
+fleet-report-chart-severity = Codes by severity
+fleet-report-chart-systems = Codes by system
+fleet-report-chart-recurrence = When repeat issues were seen
+fleet-report-appendix = CSV appendix
+fleet-report-written = Wrote a dashboard of { $scans } scans to { $path } with appendix { $appendix }
+fleet-report-group = Vehicle group: { $group }
+group-added = Added { $vehicles ->
+    [one] { $vehicles } vehicle
+   *[other] { $vehicles } vehicles
+} to group { $group }
+group-removed = Removed { $vehicles ->
+    [one] { $vehicles } vehicle
+   *[other] { $vehicles } vehicles
+} from group { $group }
+group-cohort = Group { $group } holds every vehicle of model years { $years }
+group-deleted = Deleted group { $group }
+group-none = No vehicle groups yet; use fleet group add <name> <vin>...
+group-vehicles = { $vehicles ->
+    [one] { $vehicles } archived vehicle
+   *[other] { $vehicles } archived vehicles
+}
+group-years = Model years { $years }
+group-invalid-vin = '{ $vin }' is not a VIN: a VIN has 17 letters and digits and never uses I, O or Q
+
+config-resolved-header = Effective configuration ({ $path }, then { $prefix }* variables)
+config-no-file = No configuration file at { $path }; the defaults are used
+
+## Usage statistics
+usage-header = Your use of cars-scanner over the past { $days ->
+    [one] day
+   *[other] { $days } days
+}
+usage-local = From { $path }; recorded on this machine only and never sent anywhere
+usage-lookups-per-day = Lookups per day
+usage-systems = Most-queried systems
+usage-commands = Most-used commands
+usage-nothing = none yet
+usage-sessions = { $sessions ->
+    [one] One interactive session
+   *[other] { $sessions } interactive sessions
+}, { $minutes } min on average
+usage-no-sessions = No interactive sessions
+events-none = No events recorded
+events-not-recorded = The event was not written to the event log: { $error }
@@ -0,0 +1,79 @@
+# Español
+
+## Report and display sections
+label-error-code = Código de error:
+label-description = Descripción:
+label-severity = Gravedad:
+label-system = Sistema:
+label-source = Fuente:
+section-possible-causes = Causas posibles:
+section-recommended-actions = Acciones recomendadas:
+section-related-parts = Piezas relacionadas:
+section-warranty = Garantía y campañas de servicio:
+label-emissions-warranty = Garantía de emisiones:
+label-campaign = Campaña:
+label-link = Enlace:
+warranty-covered = Cubierto
+warranty-not-covered = No cubierto
+warranty-unknown = Desconocido
+section-labor = Operaciones de mano de obra:
+labor-op-code = Código de operación
+labor-description = Descripción
+labor-hours = Horas
+labor-total = Total
+report-title = Informe de códigos de error
+label-customer = Cliente:
+label-repair-order = Orden de reparación:
+label-odometer = Odómetro:
+label-date = Fecha:
+time-date-format = %d/%m/%Y
+time-datetime-format = %d/%m/%Y %H:%M %Z
+
+## Messages
+error-prefix = Error
+db-loaded = Se cargaron { $count } códigos de error de la base de datos
+db-missing = No se encontró la base de datos de códigos de error en { $path }
+db-missing-hint = Asegúrese de que el archivo exista en la ubicación correcta.
+code-not-found = El código de error '{ $code }' no está en la base de datos
+export-failed = No se pudo exportar el informe: { $error }
+report-exported = Informe exportado a { $path }
+file-written = Se escribió { $path }
+found-system = Se encontraron { $count } errores para el sistema: { $system }
+none-system = No se encontraron errores para el sistema: { $system }
+found-severity = Se encontraron { $count } errores con gravedad: { $severity }
+none-severity = No se encontraron errores con gravedad: { $severity }
+found-keyword = Se encontraron { $count } errores que contienen la palabra clave: { $keyword }
+none-keyword = No se encontraron errores que contengan la palabra clave: { $keyword }
+found-filter = Se encontraron { $count } errores que coinciden con los filtros
+none-filter = Ningún error coincide con los filtros
+batch-summary = Se consultaron { $count } códigos, { $missing } no encontrados
+parts-none = No hay piezas relacionadas registradas para { $code }
+parts-header = Piezas relacionadas para { $code }
+parts-pricing = Disponibilidad/precio:
+quote-header = Presupuesto para { $code } - { $description }
+quote-none = No hay operaciones de mano de obra registradas para este código
+quote-for-action = para: { $action }
+quote-total = Mano de obra total:
+quote-cost = Costo estimado de mano de obra: { $cost } (a { $rate }/h)
+
+## Interactive mode
+repl-banner = === Diagnóstico de vehículos – Modo interactivo ===
+repl-intro = Escriba '{ $help }' para ver los comandos o '{ $exit }' para salir
+repl-commands = Comandos disponibles:
+repl-help-lookup = Ver detalles de uno o más códigos de error
+repl-help-bare = Los códigos escritos solos también se consultan
+repl-help-parts = Listar las piezas relacionadas con un código de error
+repl-help-system = Listar todos los errores de un sistema
+repl-help-severity = Listar todos los errores con una gravedad
+repl-help-search = Buscar errores que contengan una palabra clave
+repl-help-transcript = Guardar esta sesión como transcripción en Markdown
+repl-help-help = Mostrar esta ayuda
+repl-help-topic = Mostrar un tema de ayuda ampliado
+repl-help-exit = Salir del modo interactivo
+repl-usage = Uso: { $usage }
+repl-unknown-topic = Tema de ayuda desconocido '{ $topic }'
+repl-unknown-command = Comando desconocido.
+repl-help-hint = Escriba '{ $help }' para ver los comandos disponibles.
+repl-exit = Saliendo del modo interactivo
+transcript-saved = Se guardaron { $count } comandos en { $path }
+transcript-failed = No se pudo guardar la transcripción: { $error }
@@ -0,0 +1,79 @@
+# Français
+
+## Report and display sections
+label-error-code = Code d'erreur :
+label-description = Description :
+label-severity = Gravité :
+label-system = Système :
+label-source = Source :
+section-possible-causes = Causes possibles :
+section-recommended-actions = Actions recommandées :
+section-related-parts = Pièces associées :
+section-warranty = Garantie et campagnes de rappel :
+label-emissions-warranty = Garantie antipollution :
+label-campaign = Campagne :
+label-link = Lien :
+warranty-covered = Couvert
+warranty-not-covered = Non couvert
+warranty-unknown = Inconnu
+section-labor = Opérations de main-d'œuvre :
+labor-op-code = Code opération
+labor-description = Description
+labor-hours = Heures
+labor-total = Total
+report-title = Rapport de codes d'erreur
+label-customer = Client :
+label-repair-order = Ordre de réparation :
+label-odometer = Kilométrage :
+label-date = Date :
+time-date-format = %d/%m/%Y
+time-datetime-format = %d/%m/%Y %H:%M %Z
+
+## Messages
+error-prefix = Erreur
+db-loaded = { $count } codes d'erreur chargés depuis la base de données
+db-missing = Base de données des codes d'erreur introuvable : { $path }
+db-missing-hint = Vérifiez que le fichier se trouve au bon emplacement.
+code-not-found = Le code d'erreur '{ $code }' est absent de la base de données
+export-failed = Échec de l'export du rapport : { $error }
+report-exported = Rapport exporté vers { $path }
+file-written = { $path } écrit
+found-system = { $count } erreurs trouvées pour le système : { $system }
+none-system = Aucune erreur trouvée pour le système : { $system }
+found-severity = { $count } erreurs trouvées avec la gravité : { $severity }
+none-severity = Aucune erreur trouvée avec la gravité : { $severity }
+found-keyword = { $count } erreurs trouvées contenant le mot-clé : { $keyword }
+none-keyword = Aucune erreur trouvée contenant le mot-clé : { $keyword }
+found-filter = { $count } erreurs correspondent aux filtres
+none-filter = Aucune erreur ne correspond aux filtres
+batch-summary = { $count } codes recherchés, { $missing } introuvables
+parts-none = Aucune pièce associée enregistrée pour { $code }
+parts-header = Pièces associées pour { $code }
+parts-pricing = Disponibilité/prix :
+quote-header = Devis pour { $code } - { $description }
+quote-none = Aucune opération de main-d'œuvre enregistrée pour ce code
+quote-for-action = pour : { $action }
+quote-total = Main-d'œuvre totale :
+quote-cost = Coût estimé de la main-d'œuvre : { $cost } (à { $rate }/h)
+
+## Interactive mode
+repl-banner = === Diagnostic automobile – Mode interactif ===
+repl-intro = Tapez '{ $help }' pour la liste des commandes ou '{ $exit }' pour quitter
+repl-commands = Commandes disponibles :
+repl-help-lookup = Afficher le détail d'un ou plusieurs codes d'erreur
+repl-help-bare = Les codes saisis seuls sont aussi recherchés
+repl-help-parts = Lister les pièces associées à un code d'erreur
+repl-help-system = Lister les erreurs d'un système
+repl-help-severity = Lister les erreurs d'une gravité donnée
+repl-help-search = Rechercher les erreurs contenant un mot-clé
+repl-help-transcript = Enregistrer cette session en transcription Markdown
+repl-help-help = Afficher cette aide
+repl-help-topic = Afficher une rubrique d'aide détaillée
+repl-help-exit = Quitter le mode interactif
+repl-usage = Utilisation : { $usage }
+repl-unknown-topic = Rubrique d'aide inconnue '{ $topic }'
+repl-unknown-command = Commande inconnue.
+repl-help-hint = Tapez '{ $help }' pour la liste des commandes.
+repl-exit = Fermeture du mode interactif
+transcript-saved = { $count } commandes enregistrées dans { $path }
+transcript-failed = Impossible d'enregistrer la transcription : { $error }
@@ -0,0 +1,79 @@
+# Svenska
+
+## Report and display sections
+label-error-code = Felkod:
+label-description = Beskrivning:
+label-severity = Allvarlighetsgrad:
+label-system = System:
+label-source = Källa:
+section-possible-causes = Möjliga orsaker:
+section-recommended-actions = Rekommenderade åtgärder:
+section-related-parts = Relaterade delar:
+section-warranty = Garanti och servicekampanjer:
+label-emissions-warranty = Emissionsgaranti:
+label-campaign = Kampanj:
+label-link = Länk:
+warranty-covered = Omfattas
+warranty-not-covered = Omfattas inte
+warranty-unknown = Okänt
+section-labor = Arbetsmoment:
+labor-op-code = Momentkod
+labor-description = Beskrivning
+labor-hours = Timmar
+labor-total = Totalt
+report-title = Felkodsrapport
+label-customer = Kund:
+label-repair-order = Arbetsorder:
+label-odometer = Mätarställning:
+label-date = Datum:
+time-date-format = %Y-%m-%d
+time-datetime-format = %Y-%m-%d %H:%M %Z
+
+## Messages
+error-prefix = Fel
+db-loaded = Läste in { $count } felkoder från databasen
+db-missing = Hittade inte felkodsdatabasen på { $path }
+db-missing-hint = Kontrollera att filen finns på rätt plats.
+code-not-found = Felkoden '{ $code }' finns inte i databasen
+export-failed = Kunde inte exportera rapporten: { $error }
+report-exported = Rapporten exporterades till { $path }
+file-written = Skrev { $path }
+found-system = Hittade { $count } fel för systemet: { $system }
+none-system = Inga fel hittades för systemet: { $system }
+found-severity = Hittade { $count } fel med allvarlighetsgrad: { $severity }
+none-severity = Inga fel hittades med allvarlighetsgrad: { $severity }
+found-keyword = Hittade { $count } fel som innehåller nyckelordet: { $keyword }
+none-keyword = Inga fel hittades som innehåller nyckelordet: { $keyword }
+found-filter = Hittade { $count } fel som matchar filtren
+none-filter = Inga fel matchar filtren
+batch-summary = Slog upp { $count } koder, { $missing } hittades inte
+parts-none = Inga relaterade delar registrerade för { $code }
+parts-header = Relaterade delar för { $code }
+parts-pricing = Tillgänglighet/pris:
+quote-header = Offert för { $code } - { $description }
+quote-none = Inga arbetsmoment registrerade för denna kod
+quote-for-action = för: { $action }
+quote-total = Total arbetstid:
+quote-cost = Beräknad arbetskostnad: { $cost } ({ $rate }/h)
+
+## Interactive mode
+repl-banner = === Bildiagnos – interaktivt läge ===
+repl-intro = Skriv '{ $help }' för tillgängliga kommandon eller '{ $exit }' för att avsluta
+repl-commands = Tillgängliga kommandon:
+repl-help-lookup = Visa detaljer för en eller flera felkoder
+repl-help-bare = Felkoder som skrivs ensamma slås också upp
+repl-help-parts = Lista delar relaterade till en felkod
+repl-help-system = Lista alla fel för ett visst system
+repl-help-severity = Lista alla fel med en viss allvarlighetsgrad
+repl-help-search = Sök efter fel som innehåller ett nyckelord
+repl-help-transcript = Spara sessionen som en Markdown-logg
+repl-help-help = Visa denna hjälptext
+repl-help-topic = Visa ett utökat hjälpavsnitt
+repl-help-exit = Avsluta interaktivt läge
+repl-usage = Användning: { $usage }
+repl-unknown-topic = Okänt hjälpavsnitt '{ $topic }'
+repl-unknown-command = Okänt kommando.
+repl-help-hint = Skriv '{ $help }' för tillgängliga kommandon.
+repl-exit = Avslutar interaktivt läge
+transcript-saved = Sparade { $count } kommandon till { $path }
+transcript-failed = Kunde inte spara loggen: { $error }
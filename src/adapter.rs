@@ -0,0 +1,770 @@
+// ELM327-compatible OBD-II adapters, on a serial port or over TCP.
+//
+// `Adapter::connect` resets the adapter, lets it search for the vehicle's
+// protocol and records every ECU that answers the first request. USB and
+// Bluetooth adapters appear as serial ports (/dev/ttyUSB0, /dev/rfcomm0,
+// COM3); Wi-Fi adapters are reached as host:port, usually 192.168.0.10:35000.
+//
+// Clones often lack AT commands, and vehicles services, that a request
+// would otherwise wait on until it times out. `connect` probes them with a
+// short timeout and keeps the answers as `Capabilities`: the protocol is
+// worked out from ATDP when ATDPN is missing, the VIN is not asked of a
+// vehicle without mode 09 PID 02, and `o2-tests` says when a CAN vehicle
+// has no mode 06 rather than timing out.
+//
+// Requests go to every ECU at once. On CAN, `select` aims them at one ECU
+// instead, for `scan --module` and `clear --module`: the standard engine and
+// transmission addresses are known by name, and `[adapter.modules]` names
+// the others, which vary by make (see `Module`).
+//
+// When the adapter daemon (adapterd.rs) holds the port, `connect` attaches
+// to it instead and skips all of this: the daemon hands over what it found
+// as an `Attachment`, then passes bytes to and from the adapter.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{ErrorKind, Read, Write};
+#[cfg(feature = "adapter")]
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "adapter")]
+use crate::emulator::Elm327;
+use crate::i18n::tr;
+use crate::interrupt;
+use crate::pids::{self, Pid};
+use crate::scenario::FreezeFrame;
+#[cfg(feature = "adapter")]
+use crate::scenario::Scenario;
+
+// How long a single read waits before the overall deadline is checked again
+#[cfg(feature = "adapter")]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Longest wait for the adapter while closing, so a dead link cannot hold up an exit
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+// Longest wait for an answer to a probe; an adapter that knows the command answers at once
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The `[adapter]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdapterSettings {
+    // Port used by `connect` without an argument
+    pub port: Option<String>,
+
+    // Serial speed; most USB adapters use 38400, some clones 9600 or 115200
+    pub baud: u32,
+
+    // Longest wait for one response, including the protocol search
+    pub timeout_secs: u64,
+
+    // ECUs by name, with the CAN address requests to them go to, e.g. abs = "7B0"
+    pub modules: BTreeMap<String, String>,
+}
+
+impl Default for AdapterSettings {
+    fn default() -> Self {
+        AdapterSettings { port: None, baud: 38400, timeout_secs: 10, modules: BTreeMap::new() }
+    }
+}
+
+impl AdapterSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, address) in &self.modules {
+            parse_address(address).map_err(|e| format!("modules.{}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    // The ECU `name` refers to: one of `[adapter.modules]`, a standard module, or a request
+    // address such as 7E2
+    pub fn module(&self, name: &str) -> Result<Module, String> {
+        let configured = self.modules.iter().find(|(module, _)| module.eq_ignore_ascii_case(name)).map(|(_, address)| address.as_str());
+        let standard = STANDARD_MODULES.iter().find(|(module, _)| module.eq_ignore_ascii_case(name)).map(|(_, address)| *address);
+        let request = match configured.or(standard) {
+            Some(address) => parse_address(address)?,
+            None => parse_address(name).map_err(|_| {
+                let known: Vec<&str> = self.modules.keys().map(String::as_str).chain(STANDARD_MODULES.iter().map(|(module, _)| *module)).collect();
+                tr!("module-unknown", module = name, known = known.join(", "))
+            })?,
+        };
+        Ok(Module { name: name.to_lowercase(), request, response: request + RESPONSE_OFFSET })
+    }
+}
+
+// The modules whose CAN addresses ISO 15765-4 fixes
+const STANDARD_MODULES: &[(&str, &str)] = &[("engine", "7E0"), ("transmission", "7E1")];
+
+// An ECU answers 8 above the address requests to it go to: 7E0 answers on 7E8
+const RESPONSE_OFFSET: u16 = 8;
+
+// Every ECU listens on the functional address; requests go there unless a module is selected
+const FUNCTIONAL_ADDRESS: &str = "7DF";
+
+// One ECU on the CAN bus
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    // As given to --module, e.g. "abs"
+    pub name: String,
+    // 11-bit CAN identifiers of the requests to it and of its answers
+    pub request: u16,
+    pub response: u16,
+}
+
+impl Module {
+    // E.g. "abs (7B0)"
+    pub fn describe(&self) -> String {
+        format!("{} ({:03X})", self.name, self.request)
+    }
+}
+
+// An 11-bit CAN identifier in hex, e.g. 7E0
+fn parse_address(address: &str) -> Result<u16, String> {
+    u16::from_str_radix(address.trim(), 16)
+        .ok()
+        .filter(|address| *address + RESPONSE_OFFSET <= 0x7FF)
+        .ok_or_else(|| tr!("module-invalid-address", address = address))
+}
+
+// A byte stream to an adapter
+pub trait Link: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Link for T {}
+
+// What the adapter and the vehicle answered when probed on connect
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Capabilities {
+    // ATRV: the battery voltage
+    pub voltage: bool,
+    // ATDPN: the protocol as a number; without it the protocol is read from ATDP
+    pub protocol_number: bool,
+    // Mode 06 on-board monitoring results; only asked of CAN vehicles
+    pub mode06: bool,
+    // Mode 09 PID 02: the VIN
+    pub vin: bool,
+}
+
+impl Default for Capabilities {
+    // Everything, until probed
+    fn default() -> Self {
+        Capabilities { voltage: true, protocol_number: true, mode06: true, vin: true }
+    }
+}
+
+impl Capabilities {
+    // What is missing, as "what (command)" for the status lines
+    pub fn missing(&self, can: bool) -> Vec<String> {
+        [
+            (self.voltage, "adapter-capability-voltage", "ATRV"),
+            (self.protocol_number, "adapter-capability-protocol-number", "ATDPN"),
+            (self.mode06 || !can, "adapter-capability-mode06", "0600"),
+            (self.vin, "adapter-capability-vin", "0900"),
+        ]
+        .into_iter()
+        .filter(|(supported, _, _)| !supported)
+        .map(|(_, what, command)| format!("{} ({})", tr!(what), command))
+        .collect()
+    }
+}
+
+pub struct Adapter {
+    link: Box<dyn Link>,
+    timeout: Duration,
+    // The port or address given to `connect`
+    pub port: String,
+    // Identification reported on reset, e.g. "ELM327 v1.5"
+    pub version: String,
+    // The protocol the adapter settled on, e.g. "ISO 15765-4 (CAN 11/500)"
+    pub protocol: String,
+    // Header of every ECU that answered, e.g. ["7E8", "7E9"]
+    pub ecus: Vec<String>,
+    // Characters of header at the start of each response line
+    header_length: usize,
+    // CAN frames carry a length byte after the header; older protocols a checksum at the end
+    can: bool,
+    // A command was interrupted, or timed out, before the adapter answered it
+    busy: bool,
+    pub capabilities: Capabilities,
+    // Reached through the adapter daemon, which keeps the protocol open after we are done
+    attached: bool,
+}
+
+// What `initialize` found, for a client of the adapter daemon to start from
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Attachment {
+    pub version: String,
+    pub protocol: String,
+    pub ecus: Vec<String>,
+    header_length: usize,
+    can: bool,
+    pub capabilities: Capabilities,
+}
+
+// The reassembled message one ECU sent in answer to a request
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub ecu: String,
+    pub data: Vec<u8>,
+}
+
+// Port prefix selecting the simulator, e.g. sim:misfire-intermittent or sim:vehicle.yaml
+#[cfg(feature = "adapter")]
+pub const SIMULATOR_PREFIX: &str = "sim:";
+
+// host:port rather than a device path
+#[cfg(feature = "adapter")]
+fn is_network_address(port: &str) -> bool {
+    !port.starts_with('/') && port.contains(':') && port.to_socket_addrs().is_ok()
+}
+
+#[cfg(feature = "adapter")]
+fn open_link(port: &str, settings: &AdapterSettings) -> Result<Box<dyn Link>, Box<dyn Error>> {
+    // sim:<scenario> plays a simulated vehicle behind an emulated ELM327, with no hardware
+    if let Some(scenario) = port.strip_prefix(SIMULATOR_PREFIX) {
+        return Ok(Box::new(Elm327::new(Scenario::find(scenario)?)));
+    }
+    if is_network_address(port) {
+        let address = port.to_socket_addrs()?.next().ok_or_else(|| format!("Could not resolve {}", port))?;
+        let stream = TcpStream::connect_timeout(&address, Duration::from_secs(settings.timeout_secs))
+            .map_err(|e| format!("Could not connect to {}: {}", port, e))?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        return Ok(Box::new(stream));
+    }
+    let serial = serialport::new(port, settings.baud)
+        .timeout(POLL_INTERVAL)
+        .open()
+        .map_err(|e| format!("Could not open {}: {}", port, e))?;
+    Ok(Box::new(serial))
+}
+
+// Without the adapter feature no port can be opened, not even the simulator,
+// so every command that reads a vehicle stops here
+#[cfg(not(feature = "adapter"))]
+fn open_link(_port: &str, _settings: &AdapterSettings) -> Result<Box<dyn Link>, Box<dyn Error>> {
+    Err(tr!("feature-missing", feature = "adapter").into())
+}
+
+// The ATDPN number of a protocol as ATDP describes it, for adapters without ATDPN;
+// CAN 11/500, the most common, when the description is missing or unknown
+fn protocol_number(description: &str) -> &'static str {
+    let description = description.to_uppercase();
+    match () {
+        _ if description.contains("CAN") && description.contains("29/250") => "9",
+        _ if description.contains("CAN") && description.contains("29/") => "7",
+        _ if description.contains("CAN") && description.contains("11/250") => "8",
+        _ if description.contains("J1850 PWM") => "1",
+        _ if description.contains("J1850 VPW") => "2",
+        _ if description.contains("9141") => "3",
+        _ if description.contains("14230") && description.contains("5 BAUD") => "4",
+        _ if description.contains("14230") => "5",
+        _ => "6",
+    }
+}
+
+// Length of the header at the start of each response line for an ATDPN protocol number
+fn header_length(protocol_number: &str) -> usize {
+    match protocol_number.trim_start_matches('A') {
+        "7" | "9" => 8,
+        "1" | "2" | "3" | "4" | "5" => 6,
+        _ => 3,
+    }
+}
+
+// Bytes from hex digits, ignoring spaces
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+// A trouble code from its two-byte form: two bits for the system letter, then four digits
+pub fn decode_dtc(high: u8, low: u8) -> String {
+    let letter = ['P', 'C', 'B', 'U'][usize::from(high >> 6)];
+    format!("{}{}{:X}{:02X}", letter, (high >> 4) & 0x03, high & 0x0F, low)
+}
+
+// The two-byte form of a trouble code such as P0420, for the emulator
+#[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+pub fn encode_dtc(code: &str) -> Option<[u8; 2]> {
+    let code = code.to_ascii_uppercase();
+    let letter = ['P', 'C', 'B', 'U'].iter().position(|letter| code.starts_with(*letter))?;
+    let digits = code.get(1..).filter(|digits| digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()))?;
+    let first = u8::from_str_radix(&digits[..1], 16).ok().filter(|first| *first < 4)?;
+    let high = (letter as u8) << 6 | first << 4 | u8::from_str_radix(&digits[1..2], 16).ok()?;
+    Some([high, u8::from_str_radix(&digits[2..], 16).ok()?])
+}
+
+impl Adapter {
+    // Open a port and bring the adapter up, or take it over from the adapter daemon when it holds the port
+    pub fn connect(port: &str, settings: &AdapterSettings) -> Result<Self, Box<dyn Error>> {
+        #[cfg(feature = "adapter")]
+        if let Some(adapter) = crate::adapterd::attach(port, settings)? {
+            return Ok(adapter);
+        }
+        Adapter::connect_directly(port, settings)
+    }
+
+    // Open a port and bring the adapter up, whether or not the daemon runs
+    pub fn connect_directly(port: &str, settings: &AdapterSettings) -> Result<Self, Box<dyn Error>> {
+        let link = open_link(port, settings)?;
+        Adapter::initialize(link, port, Duration::from_secs(settings.timeout_secs))
+    }
+
+    // Reset the adapter on an open link, find the protocol and the ECUs
+    pub fn initialize(link: Box<dyn Link>, port: &str, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let mut adapter = Adapter {
+            link,
+            timeout,
+            port: port.to_string(),
+            version: String::new(),
+            protocol: String::new(),
+            ecus: Vec::new(),
+            header_length: 3,
+            can: true,
+            busy: false,
+            capabilities: Capabilities::default(),
+            attached: false,
+        };
+        adapter.version = adapter.query("ATZ")?.last().cloned().unwrap_or_default();
+        // Echo, line feeds and spaces off, headers on, automatic protocol
+        for command in ["ATE0", "ATL0", "ATS0", "ATH1", "ATSP0"] {
+            adapter.query(command)?;
+        }
+
+        let answers = adapter.query("0100")?;
+        if answers.is_empty() {
+            return Err("No ECU answered; check that the ignition is on".into());
+        }
+        let protocol = adapter.probe("ATDP")?.map(|lines| lines.concat()).unwrap_or_default();
+        adapter.protocol = protocol.trim_start_matches("AUTO, ").to_string();
+        let protocol_number = match adapter.probe("ATDPN")? {
+            Some(lines) => lines.concat(),
+            None => {
+                adapter.capabilities.protocol_number = false;
+                protocol_number(&protocol).to_string()
+            },
+        };
+
+        adapter.header_length = header_length(&protocol_number);
+        adapter.can = !matches!(protocol_number.trim_start_matches('A'), "1" | "2" | "3" | "4" | "5");
+        for answer in answers {
+            let Some(header) = answer.get(..adapter.header_length) else { continue };
+            if !adapter.ecus.iter().any(|ecu| ecu == header) {
+                adapter.ecus.push(header.to_string());
+            }
+        }
+
+        adapter.capabilities.voltage = adapter.probe("ATRV")?.is_some();
+        adapter.capabilities.mode06 = adapter.can && adapter.supported_pids(0x06)?.is_some();
+        // PID 02 is the second bit of the first byte
+        adapter.capabilities.vin = adapter.supported_pids(0x09)?.is_some_and(|bits| bits[0] & 0x40 != 0);
+        Ok(adapter)
+    }
+
+    // Take over an adapter the daemon brought up, on a link that reaches it through the daemon
+    #[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+    pub fn attach(link: Box<dyn Link>, port: &str, timeout: Duration, attachment: Attachment) -> Self {
+        Adapter {
+            link,
+            timeout,
+            port: port.to_string(),
+            version: attachment.version,
+            protocol: attachment.protocol,
+            ecus: attachment.ecus,
+            header_length: attachment.header_length,
+            can: attachment.can,
+            busy: false,
+            capabilities: attachment.capabilities,
+            attached: true,
+        }
+    }
+
+    // What a client of the adapter daemon needs to take this adapter over
+    #[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+    pub fn attachment(&self) -> Attachment {
+        Attachment {
+            version: self.version.clone(),
+            protocol: self.protocol.clone(),
+            ecus: self.ecus.clone(),
+            header_length: self.header_length,
+            can: self.can,
+            capabilities: self.capabilities.clone(),
+        }
+    }
+
+    // Whether this adapter is reached through the adapter daemon
+    pub fn attached(&self) -> bool {
+        self.attached
+    }
+
+    // The link itself, for the daemon to pass a client's bytes over
+    #[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+    pub fn link(&mut self) -> &mut dyn Link {
+        &mut *self.link
+    }
+
+    // Send a command the adapter or vehicle may not know, with a short timeout;
+    // None when it is refused or not answered in time
+    fn probe(&mut self, command: &str) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        match self.exchange(command, PROBE_TIMEOUT, true) {
+            Ok(lines) => Ok(Some(lines)),
+            Err(e) if e.is::<interrupt::Interrupted>() => Err(e),
+            Err(_) => {
+                // A late answer would be taken as the answer to the next command
+                if self.busy {
+                    self.stop();
+                }
+                Ok(None)
+            },
+        }
+    }
+
+
+    // Which of PIDs 01-20 of a service the first ECU to answer supports, as
+    // four bytes, highest PID last; None when no ECU knows the service
+    fn supported_pids(&mut self, service: u8) -> Result<Option<[u8; 4]>, Box<dyn Error>> {
+        let Some(lines) = self.probe(&format!("{:02X}00", service))? else { return Ok(None) };
+        Ok(self.responses(lines)
+            .iter()
+            .find(|response| response.data.starts_with(&[service + 0x40, 0x00]))
+            .and_then(|response| response.data.get(2..6))
+            .map(|bits| [bits[0], bits[1], bits[2], bits[3]]))
+    }
+
+    // Send an OBD request and collect the message of every ECU that answered.
+    // Multi-frame CAN answers are put back together.
+    pub fn request(&mut self, command: &str) -> Result<Vec<Response>, Box<dyn Error>> {
+        let lines = self.query(command)?;
+        Ok(self.responses(lines))
+    }
+
+    fn responses(&self, lines: Vec<String>) -> Vec<Response> {
+        let mut responses: Vec<(Response, usize)> = Vec::new();
+        for line in lines {
+            let (Some(ecu), Some(hex)) = (line.get(..self.header_length), line.get(self.header_length..)) else { continue };
+            let Some(bytes) = decode_hex(hex) else { continue };
+            if !self.can {
+                // One complete message per line, followed by a checksum
+                let data = bytes[..bytes.len().saturating_sub(1)].to_vec();
+                responses.push((Response { ecu: ecu.to_string(), data }, usize::MAX));
+                continue;
+            }
+            let Some((&pci, rest)) = bytes.split_first() else { continue };
+            match pci >> 4 {
+                // Single frame: the low nibble is the length
+                0 => {
+                    let length = usize::from(pci & 0x0F).min(rest.len());
+                    responses.push((Response { ecu: ecu.to_string(), data: rest[..length].to_vec() }, length));
+                },
+                // First frame: a 12-bit length, then the start of the data
+                1 => {
+                    let Some((&low, rest)) = rest.split_first() else { continue };
+                    let length = usize::from(pci & 0x0F) << 8 | usize::from(low);
+                    responses.push((Response { ecu: ecu.to_string(), data: rest.to_vec() }, length));
+                },
+                // Consecutive frame: more data for the ECU's message
+                2 => {
+                    if let Some((response, _)) = responses.iter_mut().rev().find(|(response, _)| response.ecu == ecu) {
+                        response.data.extend_from_slice(rest);
+                    }
+                },
+                _ => {},
+            }
+        }
+        responses
+            .into_iter()
+            .map(|(mut response, length)| {
+                response.data.truncate(length);
+                response
+            })
+            .collect()
+    }
+
+    // Send the requests from now on to one ECU only (ATSH) and listen only to its answers
+    // (ATCRA), or to every ECU again with None. Older protocols have no addresses to select.
+    pub fn select(&mut self, module: Option<&Module>) -> Result<(), Box<dyn Error>> {
+        match module {
+            Some(module) if !self.can || self.header_length != 3 => Err(tr!("module-needs-can", module = module.name.as_str(), protocol = self.protocol.as_str()).into()),
+            Some(module) => {
+                self.query(&format!("ATSH{:03X}", module.request))?;
+                self.query(&format!("ATCRA{:03X}", module.response))?;
+                Ok(())
+            },
+            None if !self.can || self.header_length != 3 => Ok(()),
+            None => {
+                self.query(&format!("ATSH{}", FUNCTIONAL_ADDRESS))?;
+                self.query("ATCRA")?;
+                Ok(())
+            },
+        }
+    }
+
+    // Clear the codes, freeze frame and MIL (mode 04) of every ECU the requests go to.
+    // Returns the ECUs that cleared; one that refuses, usually because the engine is
+    // running, is an error.
+    pub fn clear_codes(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut cleared = Vec::new();
+        for response in self.request("04")? {
+            match *response.data.as_slice() {
+                [0x44, ..] => cleared.push(response.ecu),
+                [0x7F, 0x04, reason, ..] => return Err(tr!("clear-refused", ecu = response.ecu, reason = format!("{:02X}", reason)).into()),
+                _ => {},
+            }
+        }
+        Ok(cleared)
+    }
+
+    // Whether the vehicle talks CAN, which moved several services (mode 05 to mode 06)
+    pub fn uses_can(&self) -> bool {
+        self.can
+    }
+
+    // The current value of a mode 01 PID, or None when the vehicle does not report it
+    pub fn read_pid(&mut self, pid: &Pid) -> Result<Option<f64>, Box<dyn Error>> {
+        let responses = self.request(&pid.request())?;
+        let value = responses
+            .iter()
+            .find(|response| response.data.starts_with(&[0x41, pid.number]))
+            .map(|response| pid.decode(&response.data[2..]));
+        Ok(value)
+    }
+
+    // Trouble codes from mode 03 (stored), 07 (pending) or 0A (permanent), from every ECU
+    pub fn read_codes(&mut self, mode: u8) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut codes = Vec::new();
+        for response in self.request(&format!("{:02X}", mode))? {
+            let Some((&service, rest)) = response.data.split_first() else { continue };
+            if service != mode + 0x40 {
+                continue;
+            }
+            // CAN answers start with the number of codes; older protocols pad with zeros instead
+            let pairs = if self.can { rest.get(1..).unwrap_or_default() } else { rest };
+            for pair in pairs.chunks_exact(2) {
+                if pair == [0, 0] {
+                    continue;
+                }
+                let code = decode_dtc(pair[0], pair[1]);
+                if !codes.contains(&code) {
+                    codes.push(code);
+                }
+            }
+        }
+        Ok(codes)
+    }
+
+    // The freeze frame (mode 02, frame 0): the code that stored it and the value of
+    // every known PID kept with it, or None when no code has stored one
+    pub fn read_freeze_frame(&mut self) -> Result<Option<FreezeFrame>, Box<dyn Error>> {
+        let mut read = |number: u8| -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+            Ok(self.request(&format!("02{:02X}00", number))?
+                .into_iter()
+                .find(|response| response.data.starts_with(&[0x42, number, 0x00]))
+                .map(|response| response.data[3..].to_vec()))
+        };
+        let Some(code) = read(0x02)?.filter(|data| data.len() >= 2 && data[..2] != [0, 0]).map(|data| decode_dtc(data[0], data[1])) else {
+            return Ok(None);
+        };
+        let mut values = BTreeMap::new();
+        for pid in pids::PIDS {
+            if let Some(data) = read(pid.number)? {
+                values.insert(pid.name.to_string(), pid.decode(&data));
+            }
+        }
+        Ok(Some(FreezeFrame { code, pids: values }))
+    }
+
+    // The vehicle identification number from mode 09, when the vehicle reports it
+    pub fn read_vin(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        if !self.capabilities.vin {
+            return Ok(None);
+        }
+        let characters: String = self
+            .request("0902")?
+            .iter()
+            .filter(|response| response.data.starts_with(&[0x49, 0x02]))
+            .flat_map(|response| response.data.get(3..).unwrap_or_default().iter().copied())
+            .filter(u8::is_ascii_alphanumeric)
+            .map(char::from)
+            .collect();
+        Ok(characters.get(characters.len().saturating_sub(17)..).filter(|vin| vin.len() == 17).map(str::to_string))
+    }
+
+    // Battery voltage measured at the OBD-II socket
+    pub fn voltage(&mut self) -> Result<f64, Box<dyn Error>> {
+        if !self.capabilities.voltage {
+            return Err(tr!("adapter-unsupported", what = tr!("adapter-capability-voltage"), command = "ATRV").into());
+        }
+        let reply = self.query("ATRV")?.concat();
+        reply
+            .trim_end_matches(['V', 'v'])
+            .parse::<f64>()
+            .map_err(|_| format!("Unexpected voltage reading '{}'", reply).into())
+    }
+
+    // Send one command and collect the lines of its response.
+    // An empty list means the vehicle had no data for the request.
+    pub fn query(&mut self, command: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        self.exchange(command, self.timeout, true)
+    }
+
+    // Stop a command left running by an interrupt and close the protocol (ATPC), so the
+    // ECUs end their diagnostic session; the next request opens it again. Through the
+    // daemon the protocol stays open for the next client, and the daemon closes it.
+    pub fn close(&mut self) {
+        if self.busy {
+            self.stop();
+        }
+        if !self.attached {
+            let _ = self.exchange("ATPC", CLOSE_TIMEOUT, false);
+        }
+    }
+
+    // Stop a command that may still be running and wait for the prompt after it
+    pub fn stop(&mut self) {
+        // Any character stops an ELM327 in the middle of a command
+        let _ = self.link.write_all(b"\r").and_then(|_| self.link.flush());
+        let _ = self.read_prompt("", CLOSE_TIMEOUT, false);
+        self.busy = false;
+    }
+
+    fn exchange(&mut self, command: &str, timeout: Duration, interruptible: bool) -> Result<Vec<String>, Box<dyn Error>> {
+        self.link.write_all(format!("{}\r", command).as_bytes())?;
+        self.link.flush()?;
+        let response = self.read_prompt(command, timeout, interruptible)?;
+        let text = String::from_utf8_lossy(&response);
+        let mut lines = Vec::new();
+        for line in text.split(['\r', '\n', '>']).map(str::trim) {
+            // The echo of the command, if echo is still on, and progress messages
+            if line.is_empty() || line.eq_ignore_ascii_case(command) || line.starts_with("SEARCHING") {
+                continue;
+            }
+            match line {
+                "NO DATA" => return Ok(Vec::new()),
+                "?" => return Err(format!("The adapter does not understand '{}'", command).into()),
+                _ if line.contains("UNABLE TO CONNECT") || line.contains("ERROR") || line.starts_with("STOPPED") => {
+                    return Err(format!("The adapter reported '{}' for '{}'", line, command).into());
+                },
+                _ => lines.push(line.to_string()),
+            }
+        }
+        Ok(lines)
+    }
+
+    // Read up to the '>' prompt that ends every response
+    fn read_prompt(&mut self, command: &str, timeout: Duration, interruptible: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+        let deadline = Instant::now() + timeout;
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 256];
+        while !response.contains(&b'>') {
+            if interruptible && interrupt::interrupted() {
+                self.busy = true;
+                return Err(Box::new(interrupt::Interrupted));
+            }
+            if Instant::now() >= deadline {
+                self.busy = true;
+                return Err(format!("The adapter did not answer '{}' in time", command).into());
+            }
+            match self.link.read(&mut buffer) {
+                Ok(0) => return Err("The adapter closed the connection".into()),
+                Ok(count) => response.extend_from_slice(&buffer[..count]),
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted) => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(response)
+    }
+}
+
+impl Drop for Adapter {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::time::Duration;
+    #[cfg(feature = "adapter")]
+    use crate::emulator::Elm327;
+    use crate::interrupt::{self, Interrupted};
+    use crate::pids;
+    #[cfg(feature = "adapter")]
+    use crate::scenario::Scenario;
+    use crate::testing::FakeElm;
+    #[cfg(feature = "adapter")]
+    use super::Capabilities;
+    use super::{Adapter, Response};
+
+    #[test]
+    fn adapter_finds_protocol_and_ecus() {
+        let mut adapter = Adapter::initialize(Box::new(FakeElm::car()), "fake", Duration::from_secs(1)).unwrap();
+        assert_eq!(adapter.version, "ELM327 v1.5");
+        assert_eq!(adapter.protocol, "ISO 15765-4 (CAN 11/500)");
+        assert_eq!(adapter.ecus, ["7E8", "7E9"]);
+        assert_eq!(adapter.voltage().unwrap(), 12.6);
+        assert!(adapter.query("0902").is_err());
+
+        let silent = FakeElm::car().reply("0100", "SEARCHING...\rUNABLE TO CONNECT");
+        assert!(Adapter::initialize(Box::new(silent), "fake", Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn adapter_decodes_pids_and_multi_frame_answers() {
+        // Six stored codes from the engine: a first frame and one consecutive frame
+        let elm = FakeElm::car().reply("03", "7E8100E430601000171\r7E82101740300030403050000");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        assert_eq!(adapter.read_pid(pids::find("coolant").unwrap()).unwrap(), Some(80.0));
+        assert_eq!(adapter.read_pid(pids::find("rpm").unwrap()).unwrap(), Some(1726.0));
+        assert_eq!(adapter.read_pid(pids::find("voltage").unwrap()).unwrap(), None);
+        assert_eq!(adapter.request("03").unwrap(), [Response {
+            ecu: "7E8".to_string(),
+            data: vec![0x43, 0x06, 0x01, 0x00, 0x01, 0x71, 0x01, 0x74, 0x03, 0x00, 0x03, 0x04, 0x03, 0x05],
+        }]);
+    }
+
+    #[test]
+    fn closing_an_adapter_leaves_it_usable_and_interrupts_are_recognized() {
+        let elm = FakeElm::car().reply("ATPC", "OK");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        adapter.close();
+        // Nothing of the close is left to be read as the answer to the next request
+        assert_eq!(adapter.read_pid(pids::find("coolant").unwrap()).unwrap(), Some(80.0));
+        adapter.close();
+
+        let error: Box<dyn Error> = Box::new(Interrupted);
+        assert!(interrupt::is_interrupted(error.as_ref()));
+        assert_eq!(error.to_string(), "Interrupted");
+        let other: Box<dyn Error> = "The adapter reported 'STOPPED' for '0100'".into();
+        assert!(!interrupt::is_interrupted(other.as_ref()));
+    }
+
+    #[cfg(feature = "adapter")]
+    #[test]
+    fn missing_adapter_commands_and_services_are_probed_and_skipped() {
+        let full = Adapter::initialize(Box::new(Elm327::new(Scenario::default())), "emulator", Duration::from_secs(1)).unwrap();
+        // The emulator has no mode 06
+        assert_eq!(full.capabilities, Capabilities { mode06: false, ..Capabilities::default() });
+
+        let scenario: Scenario = serde_yaml::from_str("
+unsupported: [ATRV, AT DPN]
+vin: ~
+pids: { rpm: 812 }
+").unwrap();
+        let mut adapter = Adapter::initialize(Box::new(Elm327::new(scenario)), "emulator", Duration::from_secs(1)).unwrap();
+        assert_eq!(adapter.capabilities, Capabilities { voltage: false, protocol_number: false, mode06: false, vin: false });
+        // The protocol comes from ATDP instead, so CAN answers still decode
+        assert!(adapter.uses_can());
+        assert_eq!(adapter.ecus, ["7E8"]);
+        assert_eq!(adapter.read_pid(crate::pids::find("rpm").unwrap()).unwrap(), Some(812.0));
+        assert!(adapter.voltage().unwrap_err().to_string().contains("ATRV"));
+        assert_eq!(adapter.read_vin().unwrap(), None);
+        assert!(crate::o2_tests::read(&mut adapter).unwrap_err().to_string().contains("mode 06"));
+        assert_eq!(adapter.capabilities.missing(true).len(), 4);
+        assert_eq!(adapter.capabilities.missing(false).len(), 3);
+    }
+}
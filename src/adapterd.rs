@@ -0,0 +1,439 @@
+// A background process that keeps one adapter connected (`adapterd start`).
+//
+// Resetting an adapter and letting it search for the protocol takes seconds,
+// on every command. The daemon does it once, then listens on a Unix socket,
+// <data_dir>/adapterd.sock. `Adapter::connect` tries the socket first: when
+// the daemon holds the port asked for, the adapter is taken over as the
+// daemon found it and every byte goes through the daemon; any other port, or
+// no daemon, is opened directly as before. Clients are served one at a time,
+// so a second command waits for the first, however long it takes (a monitor
+// session holds the adapter until it ends) or until Ctrl-C, and the protocol
+// stays open between them.
+//
+// A connection starts with one JSON line from the client, a `Request`, and
+// one back, a `Reply`. After `attached` the socket carries the ELM327
+// conversation as is; a command the client leaves running is stopped when
+// it goes, so the next client starts at the prompt.
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::adapter::{Adapter, AdapterSettings, Attachment};
+
+pub const SOCKET_FILE: &str = "adapterd.sock";
+// Where the daemon started in the background writes what it would print
+pub const LOG_FILE: &str = "adapterd.log";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "kebab-case")]
+enum Request {
+    // Take over the adapter on this port
+    Attach { port: String },
+    Status,
+    Stop,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "kebab-case")]
+enum Reply {
+    Attached(Attachment),
+    // The daemon holds a different port
+    OtherPort { port: String },
+    Status(Status),
+    Stopping,
+}
+
+// What `adapterd status` shows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub port: String,
+    pub pid: u32,
+    // When the daemon connected to the adapter
+    pub since: u64,
+    // Clients that attached so far
+    pub clients: u64,
+    pub adapter: Attachment,
+}
+
+static SOCKET: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn socket_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SOCKET_FILE)
+}
+
+// Look for a daemon under <data_dir> from now on when connecting; the first call wins
+pub fn init(data_dir: &Path) {
+    let _ = SOCKET.set(socket_path(data_dir));
+}
+
+// The adapter on `port` through the daemon set up by `init`, or None when no daemon holds it
+pub fn attach(port: &str, settings: &AdapterSettings) -> Result<Option<Adapter>, Box<dyn Error>> {
+    match SOCKET.get() {
+        Some(socket) => attach_at(socket, port, settings),
+        None => Ok(None),
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::{attach_at, run, spawn, status, stop};
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::{self, File};
+    use std::io::{self, ErrorKind, Read, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::archive::{Clock, SystemClock};
+    use crate::i18n::tr;
+    use crate::interrupt;
+    use crate::presentation::{Style, Styled};
+    use crate::transcript::outln;
+    use super::*;
+
+    // How often the daemon looks for clients and Ctrl-C, and a client for bytes from the daemon
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    // Longest wait for a client's request line, so a stuck client cannot hold up the others
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+    // Longest wait for a daemon that is not serving anyone to answer `status` or `stop`
+    const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+    // How long a command waits its turn before saying so
+    const WAIT_NOTICE: Duration = Duration::from_secs(1);
+
+    // Send one request and read the reply, waiting at most `patience` for the daemon to get
+    // to it, or until it does, or Ctrl-C, when None; None when no daemon listens on the socket
+    fn ask(socket: &Path, request: &Request, patience: Option<Duration>) -> Result<Option<(Reply, UnixStream)>, Box<dyn Error>> {
+        let mut stream = match UnixStream::connect(socket) {
+            Ok(stream) => stream,
+            // No daemon, or one that ended without removing its socket
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => return Ok(None),
+            Err(e) => return Err(format!("Could not reach the adapter daemon at {}: {}", socket.display(), e).into()),
+        };
+        writeln!(stream, "{}", serde_json::to_string(request)?)?;
+        // Queued behind another client until the daemon accepts this one; short reads
+        // keep an eye on the clock and Ctrl-C meanwhile
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let asked = Instant::now();
+        let mut told = false;
+        let mut read = Vec::new();
+        let line = loop {
+            match read_line(&mut stream, &mut read) {
+                Ok(line) => break line,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => match patience {
+                    Some(patience) if asked.elapsed() >= patience => return Err(tr!("adapterd-busy").into()),
+                    Some(_) => {},
+                    None if interrupt::interrupted() => return Err(interrupt::Interrupted.into()),
+                    None if !told && asked.elapsed() >= WAIT_NOTICE => {
+                        eprintln!("{}", tr!("adapterd-waiting").styled(Style::Muted));
+                        told = true;
+                    },
+                    None => {},
+                },
+                Err(e) => return Err(e.into()),
+            }
+        };
+        let reply = serde_json::from_str(&line).map_err(|e| format!("Unexpected answer from the adapter daemon: {}", e))?;
+        Ok(Some((reply, stream)))
+    }
+
+    // One line, read a byte at a time so nothing after it is taken from the stream; what
+    // was read before a timeout stays in `read` for the next call
+    fn read_line(stream: &mut UnixStream, read: &mut Vec<u8>) -> io::Result<String> {
+        let mut byte = [0u8];
+        loop {
+            match stream.read(&mut byte)? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                _ if byte[0] == b'\n' => return Ok(String::from_utf8_lossy(&std::mem::take(read)).to_string()),
+                _ => read.push(byte[0]),
+            }
+        }
+    }
+
+    fn reply(stream: &mut UnixStream, reply: &Reply) -> Result<(), Box<dyn Error>> {
+        writeln!(stream, "{}", serde_json::to_string(reply)?)?;
+        Ok(())
+    }
+
+    // The adapter on `port` through the daemon listening on `socket`, or None when no
+    // daemon listens there or it holds another port
+    pub fn attach_at(socket: &Path, port: &str, settings: &AdapterSettings) -> Result<Option<Adapter>, Box<dyn Error>> {
+        let timeout = Duration::from_secs(settings.timeout_secs);
+        let Some((answer, stream)) = ask(socket, &Request::Attach { port: port.to_string() }, None)? else { return Ok(None) };
+        match answer {
+            Reply::Attached(attachment) => {
+                stream.set_read_timeout(Some(POLL_INTERVAL))?;
+                Ok(Some(Adapter::attach(Box::new(stream), port, timeout, attachment)))
+            },
+            Reply::OtherPort { .. } => Ok(None),
+            answer => Err(format!("Unexpected answer from the adapter daemon: {:?}", answer).into()),
+        }
+    }
+
+    // The state of the daemon listening on `socket`, or None when none is running
+    pub fn status(socket: &Path) -> Result<Option<Status>, Box<dyn Error>> {
+        match ask(socket, &Request::Status, Some(ANSWER_TIMEOUT))? {
+            Some((Reply::Status(status), _)) => Ok(Some(status)),
+            Some((answer, _)) => Err(format!("Unexpected answer from the adapter daemon: {:?}", answer).into()),
+            None => Ok(None),
+        }
+    }
+
+    // Ask the daemon to close the adapter and end; false when none is running
+    pub fn stop(socket: &Path) -> Result<bool, Box<dyn Error>> {
+        match ask(socket, &Request::Stop, Some(ANSWER_TIMEOUT))? {
+            Some((Reply::Stopping, mut stream)) => {
+                // The daemon hangs up once the adapter is closed and the socket gone
+                let _ = stream.read(&mut [0u8]);
+                Ok(true)
+            },
+            Some((answer, _)) => Err(format!("Unexpected answer from the adapter daemon: {:?}", answer).into()),
+            None => Ok(false),
+        }
+    }
+
+    // Connect to the adapter on `port` and serve it on `socket` until stopped or interrupted
+    pub fn run(socket: &Path, port: &str, settings: &AdapterSettings) -> Result<(), Box<dyn Error>> {
+        if let Some(status) = status(socket)? {
+            return Err(tr!("adapterd-already-running", port = status.port, pid = status.pid).into());
+        }
+        let adapter = Adapter::connect_directly(port, settings)?;
+        if let Some(dir) = socket.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Left behind by a daemon that did not end cleanly; no daemon answered on it above
+        let _ = fs::remove_file(socket);
+        let listener = UnixListener::bind(socket).map_err(|e| format!("Could not listen on {}: {}", socket.display(), e))?;
+        // Only this user may talk to the adapter
+        fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+        outln!("{}", tr!("adapterd-listening", port = port, socket = socket.display().to_string()).styled(Style::Success));
+        serve(socket, listener, adapter)
+    }
+
+    fn serve(socket: &Path, listener: UnixListener, mut adapter: Adapter) -> Result<(), Box<dyn Error>> {
+        listener.set_nonblocking(true)?;
+        // The client that asked the daemon to stop, told once it has
+        let mut stopping = None;
+        let mut status = Status { port: adapter.port.clone(), pid: std::process::id(), since: SystemClock.now(), clients: 0, adapter: adapter.attachment() };
+        while !interrupt::interrupted() {
+            let mut stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                },
+                Err(e) => {
+                    let _ = fs::remove_file(socket);
+                    return Err(e.into());
+                },
+            };
+            stream.set_nonblocking(false)?;
+            match handle(&mut adapter, &mut stream, &mut status) {
+                Ok(true) => {
+                    stopping = Some(stream);
+                    break;
+                },
+                Ok(false) => {},
+                // The client is gone or confused; the next one is served as usual
+                Err(e) => eprintln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+            }
+        }
+        // Closing the adapter ends the protocol with ATPC
+        drop(adapter);
+        drop(listener);
+        let _ = fs::remove_file(socket);
+        drop(stopping);
+        Ok(())
+    }
+
+    // Serve one connection; true when it asked the daemon to stop
+    fn handle(adapter: &mut Adapter, stream: &mut UnixStream, status: &mut Status) -> Result<bool, Box<dyn Error>> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let request: Request = serde_json::from_str(&read_line(stream, &mut Vec::new())?)?;
+        match request {
+            Request::Stop => {
+                reply(stream, &Reply::Stopping)?;
+                Ok(true)
+            },
+            Request::Status => {
+                reply(stream, &Reply::Status(status.clone()))?;
+                Ok(false)
+            },
+            Request::Attach { port } if port != adapter.port => {
+                reply(stream, &Reply::OtherPort { port: adapter.port.clone() })?;
+                Ok(false)
+            },
+            Request::Attach { .. } => {
+                status.clients += 1;
+                reply(stream, &Reply::Attached(adapter.attachment()))?;
+                let mut running = false;
+                let result = relay(adapter, stream, &mut running);
+                if running {
+                    adapter.stop();
+                }
+                result.map(|_| false).map_err(Into::into)
+            },
+        }
+    }
+
+    // Pass bytes between the client and the adapter until the client goes or Ctrl-C is
+    // pressed. `running` says whether the adapter is still answering a command.
+    fn relay(adapter: &mut Adapter, client: &mut UnixStream, running: &mut bool) -> io::Result<()> {
+        let mut buffer = [0u8; 256];
+        while !interrupt::interrupted() {
+            // Between commands only the client has something to say; the read wakes up
+            // now and then for Ctrl-C
+            client.set_nonblocking(*running)?;
+            client.set_read_timeout(Some(POLL_INTERVAL))?;
+            match client.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(count) => {
+                    adapter.link().write_all(&buffer[..count])?;
+                    adapter.link().flush()?;
+                    *running = true;
+                },
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {},
+                Err(e) => return Err(e),
+            }
+            if !*running {
+                continue;
+            }
+            match adapter.link().read(&mut buffer) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "The adapter closed the connection")),
+                Ok(count) => {
+                    // Every answer ends with the prompt
+                    if buffer[..count].contains(&b'>') {
+                        *running = false;
+                    }
+                    client.write_all(&buffer[..count])?;
+                },
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    // Run this command again as `adapterd start --foreground` in a process group of its
+    // own, so Ctrl-C in this terminal does not reach it, with its output going to
+    // <data_dir>/adapterd.log; wait for it to connect and answer on the socket
+    pub fn spawn(data_dir: &Path, settings: &AdapterSettings) -> Result<Status, Box<dyn Error>> {
+        let socket = socket_path(data_dir);
+        if let Some(status) = status(&socket)? {
+            return Err(tr!("adapterd-already-running", port = status.port, pid = status.pid).into());
+        }
+        fs::create_dir_all(data_dir)?;
+        let log_path = data_dir.join(LOG_FILE);
+        let log = File::create(&log_path)?;
+        let mut child = Command::new(std::env::current_exe()?)
+            .args(std::env::args_os().skip(1))
+            .arg("--foreground")
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .process_group(0)
+            .spawn()?;
+        // The adapter reset and protocol search, plus the time to start
+        let deadline = Instant::now() + Duration::from_secs(settings.timeout_secs) * 2 + ANSWER_TIMEOUT;
+        loop {
+            if child.try_wait()?.is_some() {
+                let output = fs::read_to_string(&log_path).unwrap_or_default();
+                let reason = output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default().to_string();
+                return Err(tr!("adapterd-failed", reason = reason, log = log_path.display().to_string()).into());
+            }
+            if let Some(status) = status(&socket)? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                return Err(tr!("adapterd-failed", reason = tr!("adapterd-no-answer"), log = log_path.display().to_string()).into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+// Without Unix sockets there is no daemon: every command opens the port itself
+#[cfg(not(unix))]
+pub fn attach_at(_socket: &Path, _port: &str, _settings: &AdapterSettings) -> Result<Option<Adapter>, Box<dyn Error>> {
+    Ok(None)
+}
+
+#[cfg(not(unix))]
+pub fn status(_socket: &Path) -> Result<Option<Status>, Box<dyn Error>> {
+    Ok(None)
+}
+
+#[cfg(not(unix))]
+pub fn stop(_socket: &Path) -> Result<bool, Box<dyn Error>> {
+    Ok(false)
+}
+
+#[cfg(not(unix))]
+pub fn run(_socket: &Path, _port: &str, _settings: &AdapterSettings) -> Result<(), Box<dyn Error>> {
+    Err("The adapter daemon needs Unix sockets".into())
+}
+
+#[cfg(not(unix))]
+pub fn spawn(_data_dir: &Path, _settings: &AdapterSettings) -> Result<Status, Box<dyn Error>> {
+    Err("The adapter daemon needs Unix sockets".into())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::{Duration, Instant};
+    use std::thread;
+    use crate::adapter::AdapterSettings;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn adapter_daemon_holds_the_port_for_successive_commands() {
+        let dir = TempDir::new("adapterd");
+        let socket = super::socket_path(&dir.join("data"));
+        let settings = AdapterSettings { timeout_secs: 2, ..AdapterSettings::default() };
+        // Without a daemon the port is opened as usual
+        assert!(super::attach_at(&socket, "sim:", &settings).unwrap().is_none());
+        assert!(super::status(&socket).unwrap().is_none());
+
+        let daemon = {
+            let (socket, settings) = (socket.clone(), settings.clone());
+            thread::spawn(move || super::run(&socket, "sim:", &settings).map_err(|e| e.to_string()))
+        };
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while super::status(&socket).unwrap().is_none() {
+            assert!(Instant::now() < deadline, "the daemon did not start");
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let rpm = crate::pids::find("rpm").unwrap();
+        for clients in 1..=2 {
+            let mut adapter = super::attach_at(&socket, "sim:", &settings).unwrap().unwrap();
+            assert!(adapter.attached());
+            assert_eq!(adapter.ecus, ["7E8"]);
+            assert!(adapter.read_pid(rpm).unwrap().is_some());
+            drop(adapter);
+            assert_eq!(super::status(&socket).unwrap().unwrap().clients, clients);
+        }
+        // A second command waits its turn, longer than the adapter timeout if need be
+        let first = super::attach_at(&socket, "sim:", &settings).unwrap().unwrap();
+        let second = {
+            let (socket, settings) = (socket.clone(), AdapterSettings { timeout_secs: 1, ..settings.clone() });
+            thread::spawn(move || super::attach_at(&socket, "sim:", &settings).map(|adapter| adapter.is_some()).map_err(|e| e.to_string()))
+        };
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!second.is_finished());
+        drop(first);
+        assert_eq!(second.join().unwrap(), Ok(true));
+        assert_eq!(super::status(&socket).unwrap().unwrap().clients, 4);
+
+        // Another port is left to the client to open
+        assert!(super::attach_at(&socket, "sim:catalyst", &settings).unwrap().is_none());
+
+        assert!(super::stop(&socket).unwrap());
+        daemon.join().unwrap().unwrap();
+        assert!(!socket.exists());
+        assert!(!super::stop(&socket).unwrap());
+    }
+}
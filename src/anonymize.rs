@@ -0,0 +1,190 @@
+// Personal data taken out of reports meant to be shared (`--anonymize`).
+//
+// Reports posted on a forum or attached to a bug report should show the
+// codes and readings, not whose car it was. With --anonymize, `report`,
+// `inspection`, `fleet report` and `fleet export-app` pass the VIN, customer,
+// repair order number and odometer through the rules of the `[anonymize]`
+// table, then replace whatever the configured patterns match (number
+// plates, phone numbers, names in free text) in the finished report:
+//
+//   [anonymize]
+//   vin = "mask"                 # keep, remove, hash or mask
+//   customer = "remove"
+//   repair_order = "hash"
+//   odometer = "mask"            # mask rounds down to a thousand
+//   salt = "our-shop"            # changes every hash, so VINs cannot be guessed
+//   patterns = ["\\b[A-Z]{3}[- ]?\\d{3,4}\\b"]
+//
+// Masking keeps enough to stay useful: the first 11 characters of a VIN
+// still give the manufacturer, model and model year, only the serial number
+// goes. Hashes are stable for a salt, so the same vehicle keeps the same
+// pseudonym across the scans of a fleet report.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::archive::ScanRecord;
+use crate::repair_order::RepairOrder;
+
+// Text put in place of removed values and pattern matches
+pub const REDACTED: &str = "[redacted]";
+
+// Characters of a VIN kept by "mask": world manufacturer, vehicle descriptor,
+// check digit, model year and plant
+const VIN_KEPT: usize = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Redaction {
+    Keep,
+    Remove,
+    // A salted pseudonym, the same for the same value
+    Hash,
+    // Partly hidden; what is kept depends on the field
+    Mask,
+}
+
+// The `[anonymize]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnonymizeSettings {
+    pub vin: Redaction,
+    pub customer: Redaction,
+    pub repair_order: Redaction,
+    pub odometer: Redaction,
+    // Mixed into every hash
+    pub salt: String,
+    // Regular expressions replaced in the finished report
+    pub patterns: Vec<String>,
+}
+
+impl Default for AnonymizeSettings {
+    fn default() -> Self {
+        AnonymizeSettings {
+            vin: Redaction::Mask,
+            customer: Redaction::Remove,
+            repair_order: Redaction::Hash,
+            odometer: Redaction::Mask,
+            salt: String::new(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl AnonymizeSettings {
+    // Check that every pattern compiles
+    pub fn validate(&self) -> Result<(), String> {
+        self.compile().map(|_| ())
+    }
+
+    fn compile(&self) -> Result<Vec<Regex>, String> {
+        self.patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("anonymize.patterns: invalid pattern '{}': {}", pattern, e)))
+            .collect()
+    }
+}
+
+// The settings ready to apply to a report
+pub struct Anonymizer {
+    settings: AnonymizeSettings,
+    patterns: Vec<Regex>,
+}
+
+// 64-bit FNV-1a; stable across platforms and Rust versions, unlike the standard hasher
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01B3))
+}
+
+impl Anonymizer {
+    pub fn new(settings: &AnonymizeSettings) -> Result<Self, String> {
+        Ok(Anonymizer { settings: settings.clone(), patterns: settings.compile()? })
+    }
+
+    fn hash(&self, value: &str) -> String {
+        let hash = fnv1a(format!("{}\0{}", self.settings.salt, value).as_bytes());
+        format!("anon-{:010x}", hash & 0xFF_FFFF_FFFF)
+    }
+
+    // A text field under a rule; None when it is removed
+    fn field(&self, rule: Redaction, value: &str, mask: impl Fn(&str) -> String) -> Option<String> {
+        match rule {
+            Redaction::Keep => Some(value.to_string()),
+            Redaction::Remove => None,
+            Redaction::Hash => Some(self.hash(value)),
+            Redaction::Mask => Some(mask(value)),
+        }
+    }
+
+    // The VIN as it may be shown; a removed VIN still leaves a marker, as reports need a value
+    pub fn vin(&self, vin: &str) -> String {
+        let mask = |vin: &str| {
+            let kept: String = vin.chars().take(VIN_KEPT).collect();
+            format!("{}{}", kept, "*".repeat(vin.chars().count().saturating_sub(VIN_KEPT)))
+        };
+        self.field(self.settings.vin, vin, mask).unwrap_or_else(|| REDACTED.to_string())
+    }
+
+    // The repair order with its customer, number and odometer under their rules; the date stays
+    pub fn repair_order(&self, order: RepairOrder) -> RepairOrder {
+        let initial = |value: &str| format!("{}***", value.chars().next().unwrap_or_default());
+        RepairOrder {
+            customer: order.customer.and_then(|customer| self.field(self.settings.customer, &customer, initial)),
+            number: order.number.and_then(|number| self.field(self.settings.repair_order, &number, initial)),
+            odometer: order.odometer.and_then(|odometer| match self.settings.odometer {
+                Redaction::Keep => Some(odometer),
+                Redaction::Mask => Some(odometer / 1000 * 1000),
+                // A pseudonym means nothing for a reading
+                Redaction::Remove | Redaction::Hash => None,
+            }),
+            date: order.date,
+        }
+    }
+
+    // Archived scans with their VINs anonymized
+    pub fn records(&self, records: Vec<ScanRecord>) -> Vec<ScanRecord> {
+        records.into_iter().map(|record| ScanRecord { vin: self.vin(&record.vin), ..record }).collect()
+    }
+
+    // A finished report with the given VINs replaced and every pattern match redacted
+    pub fn text(&self, text: &str, vins: &[&str]) -> String {
+        let mut text = text.to_string();
+        for vin in vins.iter().filter(|vin| !vin.is_empty()) {
+            text = text.replace(vin, &self.vin(vin));
+        }
+        for pattern in &self.patterns {
+            text = pattern.replace_all(&text, REDACTED).into_owned();
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repair_order::RepairOrder;
+    use super::{AnonymizeSettings, Anonymizer, Redaction};
+
+    #[test]
+    fn anonymizer_masks_hashes_and_redacts_shared_reports() {
+        let mut settings = AnonymizeSettings { patterns: vec![r"\bABC ?123\b".to_string()], ..AnonymizeSettings::default() };
+        let anonymizer = Anonymizer::new(&settings).unwrap();
+        assert_eq!(anonymizer.vin("1FTFW1ET5DFC10312"), "1FTFW1ET5DF******");
+        let order = RepairOrder { customer: Some("Jane Doe".to_string()), number: Some("RO-1001".to_string()), odometer: Some(84_512), date: Some(0) };
+        let order = anonymizer.repair_order(order);
+        assert_eq!(order.customer, None);
+        assert!(order.number.as_deref().unwrap().starts_with("anon-"));
+        assert_eq!(order.odometer, Some(84_000));
+        assert_eq!(
+            anonymizer.text("VIN 1FTFW1ET5DFC10312, plate ABC 123", &["1FTFW1ET5DFC10312"]),
+            "VIN 1FTFW1ET5DF******, plate [redacted]"
+        );
+
+        // Hashes are stable for a salt and change with it
+        settings.vin = Redaction::Hash;
+        let first = Anonymizer::new(&settings).unwrap().vin("1FTFW1ET5DFC10312");
+        assert_eq!(Anonymizer::new(&settings).unwrap().vin("1FTFW1ET5DFC10312"), first);
+        settings.salt = "shop".to_string();
+        assert_ne!(Anonymizer::new(&settings).unwrap().vin("1FTFW1ET5DFC10312"), first);
+
+        settings.patterns = vec!["(".to_string()];
+        assert!(settings.validate().is_err());
+    }
+}
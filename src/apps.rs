@@ -0,0 +1,83 @@
+// Fault code lists in the CSV layouts of mobile OBD-II apps.
+//
+// Archived scans can be written for Torque or Car Scanner, one row per code,
+// so a shop's findings can be opened next to the app's own logs. `fleet
+// import` reads both layouts back, which makes the round trip lossless for
+// VINs and codes.
+use std::error::Error;
+use std::io::Write;
+use clap::ValueEnum;
+use csv::WriterBuilder;
+use crate::archive::ScanRecord;
+use crate::DiagnosticsDatabase;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum AppFormat {
+    /// Torque Pro: comma-separated, device time like 14-Nov-2023 22:13:20
+    Torque,
+    /// Car Scanner ELM OBD2: semicolon-separated
+    CarScanner,
+}
+
+impl AppFormat {
+    pub fn default_file(self) -> &'static str {
+        match self {
+            AppFormat::Torque => "torque-dtcs.csv",
+            AppFormat::CarScanner => "carscanner-dtcs.csv",
+        }
+    }
+}
+
+// Write one row per code of every scan; returns the number of rows
+pub fn write_csv(records: &[ScanRecord], db: &DiagnosticsDatabase, format: AppFormat, writer: impl Write) -> Result<usize, Box<dyn Error>> {
+    let (delimiter, header, time_pattern) = match format {
+        AppFormat::Torque => (b',', ["Device Time", "VIN", "DTC", "Description", "Severity"], "%d-%b-%Y %H:%M:%S"),
+        AppFormat::CarScanner => (b';', ["Date", "VIN", "Code", "Description", "Status"], "%Y-%m-%d %H:%M:%S"),
+    };
+    let mut csv = WriterBuilder::new().delimiter(delimiter).from_writer(writer);
+    csv.write_record(header)?;
+
+    let mut rows = 0;
+    for record in records {
+        let time = crate::time::format_local(record.timestamp, time_pattern);
+        for code in &record.codes {
+            let error = db.lookup_error(code);
+            let description = error.map(|error| error.description.as_str()).unwrap_or_default();
+            let last = match format {
+                AppFormat::Torque => error.map(|error| error.severity.as_str()).unwrap_or_default(),
+                // Archived codes were read as stored codes
+                AppFormat::CarScanner => "Confirmed",
+            };
+            csv.write_record([time.as_str(), record.vin.as_str(), code.as_str(), description, last])?;
+            rows += 1;
+        }
+    }
+    csv.flush()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::archive::Archive;
+    use crate::testing::{FIXED_TIME, FixedClock, TempDir, sample_database, scan, init, assert_golden};
+    use crate::fleet;
+
+    #[test]
+    fn app_exports_round_trip() {
+        init();
+        let db = sample_database();
+        let records = [scan("a-1", "VIN001", 10, &["P0300", "U9999"]), scan("a-2", "VIN002", 9, &["B0001"])];
+        let mut torque = Vec::new();
+        super::write_csv(&records, &db, super::AppFormat::Torque, &mut torque).unwrap();
+        assert_golden("torque-dtcs.csv", &String::from_utf8(torque).unwrap());
+
+        let dir = TempDir::new("apps");
+        let export = dir.join("carscanner.csv");
+        super::write_csv(&records, &db, super::AppFormat::CarScanner, fs::File::create(&export).unwrap()).unwrap();
+        let imported = fleet::import(export.to_str().unwrap(), &Archive::open(dir.path()).unwrap(), &FixedClock(FIXED_TIME)).unwrap();
+
+        let vehicles: Vec<_> = imported.iter().map(|record| (record.vin.as_str(), record.codes.clone())).collect();
+        assert_eq!(vehicles, [("VIN001", vec!["P0300".to_string(), "U9999".to_string()]), ("VIN002", vec!["B0001".to_string()])]);
+    }
+}
@@ -0,0 +1,253 @@
+// Archive of scan results, one JSON file per scan under <data_dir>/archive
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::events::{self, Event, Kind};
+use crate::i18n::tr;
+use crate::monitor::Summary;
+
+// VIN recorded for scans of a vehicle that does not report one
+pub const UNKNOWN_VIN: &str = "unknown";
+
+// The codes read from one vehicle at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub id: String,
+    pub vin: String,
+    // Seconds since the Unix epoch, stored as ISO-8601
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub timestamp: u64,
+    // Where the scan came from, e.g. "fleet-import:dump.csv"
+    pub source: String,
+    pub codes: Vec<String>,
+    // Statistics of a monitor session, for records saved by `monitor --summary`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+}
+
+pub struct Archive {
+    dir: PathBuf,
+}
+
+// Source of the current time; tests inject a fixed clock so output is reproducible
+pub trait Clock {
+    // Current time in seconds since the Unix epoch
+    fn now(&self) -> u64;
+
+    // A prefix for the ids of scans stored together, unique per invocation
+    fn batch_id(&self) -> String;
+}
+
+// The system's wall clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+
+    fn batch_id(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        format!("{:x}", nanos)
+    }
+}
+
+impl Archive {
+    // Open the archive inside a data directory, creating it when needed
+    pub fn open(data_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let dir = data_dir.join("archive");
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Could not create archive directory {}: {}", dir.display(), e))?;
+        Ok(Archive { dir })
+    }
+
+    // Store a scan record, replacing any record with the same id
+    pub fn save(&self, record: &ScanRecord) -> Result<(), Box<dyn Error>> {
+        let path = self.dir.join(format!("{}.json", record.id));
+        fs::write(&path, serde_json::to_string_pretty(record)?)?;
+        // "fleet-import:dump.csv" is logged as the action fleet-import with dump.csv as detail
+        let (action, detail) = record.source.split_once(':').unwrap_or((&record.source, ""));
+        let mut event = Event::new(Kind::Scan, action)
+            .vin(Some(record.vin.as_str()).filter(|vin| *vin != UNKNOWN_VIN))
+            .codes(&record.codes)
+            .path(&path);
+        if !detail.is_empty() {
+            event = event.detail(detail);
+        }
+        events::record(event);
+        Ok(())
+    }
+
+    // Load one scan record by its id
+    pub fn load(&self, id: &str) -> Result<ScanRecord, Box<dyn Error>> {
+        // Ids are file names inside the archive; anything that could leave it is no id
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(tr!("garage-unknown-scan", id = id).into());
+        }
+        let path = self.dir.join(format!("{}.json", id));
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(tr!("garage-unknown-scan", id = id).into()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(serde_json::from_str(&content).map_err(|e| format!("Invalid scan record {}: {}", path.display(), e))?)
+    }
+
+    // File a stored scan under another vehicle, or under none with UNKNOWN_VIN;
+    // returns the VIN it was filed under before
+    pub fn relink(&self, id: &str, vin: &str, action: &str) -> Result<String, Box<dyn Error>> {
+        let mut record = self.load(id)?;
+        if record.vin.eq_ignore_ascii_case(vin) {
+            return Err(match vin {
+                UNKNOWN_VIN => tr!("garage-not-linked", id = id),
+                _ => tr!("garage-already-linked", id = id, vin = record.vin.as_str()),
+            }.into());
+        }
+        let previous = std::mem::replace(&mut record.vin, vin.to_string());
+        let path = self.dir.join(format!("{}.json", record.id));
+        fs::write(&path, serde_json::to_string_pretty(&record)?)?;
+        events::record(Event::new(Kind::Scan, action)
+            .vin(Some(vin).filter(|vin| *vin != UNKNOWN_VIN))
+            .codes(&record.codes)
+            .path(&path)
+            .detail(format!("{} from {}", record.id, previous)));
+        Ok(previous)
+    }
+
+    // Load every scan record, oldest first
+    pub fn load_all(&self) -> Result<Vec<ScanRecord>, Box<dyn Error>> {
+        let mut records = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                let content = fs::read_to_string(&path)?;
+                let record: ScanRecord = serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid scan record {}: {}", path.display(), e))?;
+                records.push(record);
+            }
+        }
+        records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        Ok(records)
+    }
+}
+
+// Seconds looked back for how often a code was seen
+const SIGHTING_WINDOW: u64 = 365 * 24 * 60 * 60;
+
+// The last archived scan with a code, for annotating lookups
+#[derive(Debug, PartialEq)]
+pub struct Sighting {
+    pub timestamp: u64,
+    pub vin: String,
+    // Scans with the code in the past year
+    pub count: usize,
+}
+
+// When `code` was last read, on the vehicle with `vin` or on any vehicle
+pub fn last_seen(records: &[ScanRecord], code: &str, vin: Option<&str>, now: u64) -> Option<Sighting> {
+    let matching: Vec<&ScanRecord> = records
+        .iter()
+        .filter(|record| vin.is_none_or(|vin| record.vin.eq_ignore_ascii_case(vin)))
+        .filter(|record| record.codes.iter().any(|seen| seen.eq_ignore_ascii_case(code)))
+        .collect();
+    let last = matching.iter().max_by_key(|record| record.timestamp)?;
+    let since = now.saturating_sub(SIGHTING_WINDOW);
+    Some(Sighting {
+        timestamp: last.timestamp,
+        vin: last.vin.clone(),
+        count: matching.iter().filter(|record| record.timestamp >= since).count(),
+    })
+}
+
+// Parse a period such as "30d", "12h", "2w" or "90m" into seconds
+pub fn parse_period(period: &str) -> Result<u64, String> {
+    let period = period.trim();
+    let split = period.find(|c: char| !c.is_ascii_digit()).unwrap_or(period.len());
+    let (amount, unit) = period.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| format!("Invalid period '{}'", period))?;
+    let unit_seconds = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("Invalid period '{}': use a number followed by m, h, d or w", period)),
+    };
+    amount.checked_mul(unit_seconds).ok_or_else(|| format!("Invalid period '{}'", period))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::fleet;
+    use crate::testing::{init, scan, FIXED_TIME, FixedClock, TempDir};
+    use super::{last_seen, parse_period, Archive, Sighting, UNKNOWN_VIN};
+
+    #[test]
+    fn periods_are_read_in_seconds() {
+        assert_eq!(parse_period("90m"), Ok(90 * 60));
+        assert_eq!(parse_period(" 12h "), Ok(12 * 60 * 60));
+        assert_eq!(parse_period("30d"), Ok(30 * 24 * 60 * 60));
+        assert_eq!(parse_period("30"), Ok(30 * 24 * 60 * 60));
+        assert_eq!(parse_period("2w"), Ok(2 * 7 * 24 * 60 * 60));
+        assert_eq!(parse_period("0"), Ok(0));
+        assert!(parse_period("3y").unwrap_err().contains("m, h, d or w"));
+        assert!(parse_period("d").is_err());
+        assert!(parse_period("-1d").is_err());
+    }
+
+    #[test]
+    fn a_period_too_long_to_count_is_invalid_rather_than_wrapped() {
+        assert_eq!(parse_period("99999999999999999w"), Err("Invalid period '99999999999999999w'".to_string()));
+        assert_eq!(parse_period("99999999999999999999"), Err("Invalid period '99999999999999999999'".to_string()));
+        assert_eq!(parse_period(&format!("{}m", u64::MAX / 60)), Ok(u64::MAX / 60 * 60));
+    }
+
+    #[test]
+    fn last_seen_counts_scans_of_the_past_year_per_vehicle() {
+        let records = vec![
+            scan("a", "VIN1", 400, &["P0420"]),
+            scan("b", "VIN1", 30, &["P0420", "P0171"]),
+            scan("c", "VIN2", 10, &["p0420"]),
+            scan("d", "VIN1", 5, &["P0300"]),
+        ];
+        let day = 24 * 60 * 60;
+        assert_eq!(last_seen(&records, "P0420", Some("vin1"), FIXED_TIME), Some(Sighting { timestamp: FIXED_TIME - 30 * day, vin: "VIN1".to_string(), count: 1 }));
+        assert_eq!(last_seen(&records, "P0420", None, FIXED_TIME), Some(Sighting { timestamp: FIXED_TIME - 10 * day, vin: "VIN2".to_string(), count: 2 }));
+        assert_eq!(last_seen(&records, "P0420", Some("VIN1"), FIXED_TIME + 400 * day).unwrap().count, 0);
+        assert_eq!(last_seen(&records, "P0171", Some("VIN2"), FIXED_TIME), None);
+    }
+
+    #[test]
+    fn garage_files_a_scan_under_another_vehicle_or_none() {
+        init();
+        let dir = TempDir::new("garage");
+        let dump = dir.join("dump.csv");
+        fs::write(&dump, "vin,codes\nVIN001,P0101\nVIN002,P0300\n").unwrap();
+        let archive = Archive::open(dir.path()).unwrap();
+        fleet::import(dump.to_str().unwrap(), &archive, &FixedClock(FIXED_TIME)).unwrap();
+
+        assert_eq!(archive.relink("6553f100-00001", "VIN002", "garage move").unwrap(), "VIN001");
+        let vins = |archive: &Archive| archive.load_all().unwrap().iter().map(|record| record.vin.clone()).collect::<Vec<_>>();
+        assert_eq!(vins(&archive), ["VIN002", "VIN002"]);
+        assert_eq!(archive.relink("6553f100-00001", "vin002", "garage move").unwrap_err().to_string(), "Scan 6553f100-00001 is already in the history of VIN002");
+
+        assert_eq!(archive.relink("6553f100-00002", UNKNOWN_VIN, "garage unlink").unwrap(), "VIN002");
+        assert_eq!(vins(&archive), ["VIN002", UNKNOWN_VIN]);
+        assert!(archive.relink("6553f100-00002", UNKNOWN_VIN, "garage unlink").is_err());
+        // The codes stay with the scan wherever it is filed
+        assert_eq!(archive.load("6553f100-00002").unwrap().codes, ["P0300"]);
+
+        for id in ["6553f100-00009", "../dump", ""] {
+            assert!(archive.load(id).unwrap_err().to_string().starts_with("No archived scan has the id"));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// Shop branding printed at the top and bottom of exported reports.
+//
+// The `[branding]` table in cars-scanner.toml names the shop and points at a
+// logo; the logo is read once and embedded in HTML reports as a data URI so
+// the exported file stands on its own.
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use crate::escape_html;
+
+// The `[branding]` table; every setting is optional
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Branding {
+    pub shop_name: Option<String>,
+
+    // Postal address; may span several lines
+    pub address: Option<String>,
+
+    // PNG, JPEG, GIF, SVG or WebP image shown in HTML reports
+    pub logo: Option<String>,
+
+    // Printed at the end of every report
+    pub disclaimer: Option<String>,
+}
+
+// Branding ready to be rendered, with the logo already read
+#[derive(Debug, Clone, Default)]
+pub struct Letterhead {
+    pub shop_name: Option<String>,
+    pub address: Option<String>,
+    // The logo as a data URI
+    pub logo: Option<String>,
+    pub disclaimer: Option<String>,
+}
+
+fn logo_media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+impl Branding {
+    // Read the logo and prepare the letterhead
+    pub fn load(&self) -> Result<Letterhead, Box<dyn Error>> {
+        let logo = match &self.logo {
+            Some(path) => {
+                let media_type = logo_media_type(Path::new(path))
+                    .ok_or_else(|| format!("Unsupported logo format {}: use PNG, JPEG, GIF, SVG or WebP", path))?;
+                let bytes = fs::read(path).map_err(|e| format!("Could not read logo {}: {}", path, e))?;
+                Some(format!("data:{};base64,{}", media_type, STANDARD.encode(bytes)))
+            },
+            None => None,
+        };
+        Ok(Letterhead {
+            shop_name: self.shop_name.clone(),
+            address: self.address.clone(),
+            logo,
+            disclaimer: self.disclaimer.clone(),
+        })
+    }
+}
+
+impl Letterhead {
+    pub fn is_empty(&self) -> bool {
+        self.shop_name.is_none() && self.address.is_none() && self.logo.is_none() && self.disclaimer.is_none()
+    }
+
+    fn address_lines(&self) -> impl Iterator<Item = &str> {
+        self.address.iter().flat_map(|address| address.lines()).map(str::trim).filter(|line| !line.is_empty())
+    }
+
+    // Shop name and address above a plain text report
+    pub fn text_header(&self) -> String {
+        let mut lines: Vec<&str> = self.shop_name.iter().map(String::as_str).collect();
+        lines.extend(self.address_lines());
+        if lines.is_empty() {
+            return String::new();
+        }
+        format!("{}\n{}\n\n", lines.join("\n"), "-".repeat(32))
+    }
+
+    // Disclaimer below a plain text report
+    pub fn text_footer(&self) -> String {
+        match &self.disclaimer {
+            Some(disclaimer) => format!("\n{}\n{}\n", "-".repeat(32), disclaimer.trim()),
+            None => String::new(),
+        }
+    }
+
+    // Logo, shop name and address above an HTML report
+    pub fn html_header(&self) -> String {
+        if self.shop_name.is_none() && self.address.is_none() && self.logo.is_none() {
+            return String::new();
+        }
+        let mut html = String::from("<header class='letterhead'>\n");
+        if let Some(logo) = &self.logo {
+            let alt = self.shop_name.as_deref().unwrap_or_default();
+            html.push_str(&format!("<img class='logo' src='{}' alt='{}'>\n", logo, escape_html(alt)));
+        }
+        html.push_str("<div>\n");
+        if let Some(name) = &self.shop_name {
+            html.push_str(&format!("<strong>{}</strong><br>\n", escape_html(name)));
+        }
+        let address: Vec<String> = self.address_lines().map(escape_html).collect();
+        if !address.is_empty() {
+            html.push_str(&format!("<address>{}</address>\n", address.join("<br>")));
+        }
+        html.push_str("</div>\n</header>\n");
+        html
+    }
+
+    // Styles for the header and footer, empty without branding
+    pub fn stylesheet(&self) -> &'static str {
+        if self.is_empty() { "" } else { STYLESHEET }
+    }
+
+    // Disclaimer below an HTML report
+    pub fn html_footer(&self) -> String {
+        match &self.disclaimer {
+            Some(disclaimer) => format!("<footer class='disclaimer'><p>{}</p></footer>\n", escape_html(disclaimer.trim())),
+            None => String::new(),
+        }
+    }
+}
+
+// Styles for the letterhead in HTML reports
+const STYLESHEET: &str = "\
+.letterhead { display: flex; align-items: center; gap: 15px; border-bottom: 2px solid #ddd; padding-bottom: 10px; margin-bottom: 20px; }
+.letterhead .logo { max-height: 80px; max-width: 200px; }
+.letterhead address { font-style: normal; color: #555; }
+.disclaimer { border-top: 1px solid #ddd; margin-top: 20px; padding-top: 10px; font-size: 0.85em; color: #777; }
+";
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn branding_embeds_logo() {
+        let dir = TempDir::new("branding");
+        let logo = dir.join("logo.svg");
+        fs::write(&logo, "<svg/>").unwrap();
+
+        let branding = super::Branding { logo: Some(logo.display().to_string()), ..Default::default() };
+        let letterhead = branding.load().unwrap();
+        assert_eq!(letterhead.logo.as_deref(), Some("data:image/svg+xml;base64,PHN2Zy8+"));
+
+        let branding = super::Branding { logo: Some("logo.bmp".to_string()), ..Default::default() };
+        assert!(branding.load().is_err());
+    }
+}
@@ -0,0 +1,197 @@
+// Building error code records field by field, with every field checked.
+//
+// `db add` and anything else that creates records goes through
+// `ErrorCodeBuilder`, so a record that reaches a database file always has a
+// well-formed code, a known severity and a description. The same checks are
+// available for existing records through `check`, which `db validate` uses.
+use std::error::Error;
+use std::fmt;
+use crate::i18n::tr;
+use crate::{looks_like_code, ErrorCode, DIFFICULTIES, SEVERITIES};
+
+// A field that failed validation
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    Code(String),
+    Severity(String),
+    MissingSeverity,
+    Difficulty(String),
+    EmptyDescription,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::Code(code) => write!(f, "{}", tr!("db-add-invalid-code", code = code.as_str())),
+            FieldError::Severity(severity) => write!(f, "{}", tr!("db-validate-bad-severity", severity = severity.as_str())),
+            FieldError::MissingSeverity => write!(f, "{}", tr!("builder-no-severity")),
+            FieldError::Difficulty(difficulty) => write!(f, "{}", tr!("db-validate-bad-difficulty", difficulty = difficulty.as_str())),
+            FieldError::EmptyDescription => write!(f, "{}", tr!("db-validate-no-description")),
+        }
+    }
+}
+
+// Every field that failed validation for one record
+#[derive(Debug)]
+pub struct InvalidErrorCode(pub Vec<FieldError>);
+
+impl fmt::Display for InvalidErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let messages: Vec<String> = self.0.iter().map(FieldError::to_string).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl Error for InvalidErrorCode {}
+
+// The fields of a record that break the database rules
+pub fn check(error: &ErrorCode) -> Vec<FieldError> {
+    let mut problems = Vec::new();
+    if !looks_like_code(&error.code) {
+        problems.push(FieldError::Code(error.code.clone()));
+    }
+    if error.description.trim().is_empty() {
+        problems.push(FieldError::EmptyDescription);
+    }
+    if !SEVERITIES.iter().any(|severity| severity.eq_ignore_ascii_case(&error.severity)) {
+        problems.push(FieldError::Severity(error.severity.clone()));
+    }
+    // Unrated is allowed; a rating has to be one the list filter knows
+    if !error.difficulty.trim().is_empty() && !DIFFICULTIES.iter().any(|difficulty| difficulty.eq_ignore_ascii_case(error.difficulty.trim())) {
+        problems.push(FieldError::Difficulty(error.difficulty.clone()));
+    }
+    problems
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ErrorCodeBuilder {
+    code: String,
+    description: String,
+    severity: Option<String>,
+    system: String,
+    causes: Vec<String>,
+    actions: Vec<String>,
+    difficulty: String,
+}
+
+// Add the entries of a '|' separated list, trimmed and without blanks or repeats
+fn push_items(list: &mut Vec<String>, items: &str) {
+    for item in items.split('|').map(str::trim).filter(|item| !item.is_empty()) {
+        if !list.iter().any(|existing| existing == item) {
+            list.push(item.to_string());
+        }
+    }
+}
+
+impl ErrorCodeBuilder {
+    pub fn new(code: &str) -> Self {
+        ErrorCodeBuilder { code: code.trim().to_uppercase(), ..Default::default() }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.trim().to_string();
+        self
+    }
+
+    // Matched without regard to case and stored as Low, Medium, High or Critical
+    pub fn severity(mut self, severity: &str) -> Self {
+        let severity = severity.trim();
+        let known = SEVERITIES.iter().find(|known| known.eq_ignore_ascii_case(severity));
+        self.severity = Some(known.map(|known| known.to_string()).unwrap_or_else(|| severity.to_string()));
+        self
+    }
+
+    pub fn has_severity(&self) -> bool {
+        self.severity.is_some()
+    }
+
+    pub fn system(mut self, system: &str) -> Self {
+        self.system = system.trim().to_string();
+        self
+    }
+
+    // One cause, or several separated by '|'
+    pub fn causes(mut self, causes: &str) -> Self {
+        push_items(&mut self.causes, causes);
+        self
+    }
+
+    // One action, or several separated by '|'
+    pub fn actions(mut self, actions: &str) -> Self {
+        push_items(&mut self.actions, actions);
+        self
+    }
+
+    // DIY, Intermediate or Professional
+    pub fn difficulty(mut self, difficulty: &str) -> Self {
+        self.difficulty = difficulty.trim().to_string();
+        self
+    }
+
+    // The record as it stands, without any checks, e.g. to suggest a severity for it
+    pub fn draft(&self) -> ErrorCode {
+        ErrorCode {
+            code: self.code.clone(),
+            description: self.description.clone(),
+            severity: self.severity.clone().unwrap_or_default(),
+            system: self.system.clone(),
+            possible_causes: self.causes.clone(),
+            recommended_actions: self.actions.clone(),
+            emissions_warranty: None,
+            service_campaigns: String::new(),
+            campaign_links: String::new(),
+            related_parts: String::new(),
+            set_conditions: String::new(),
+            monitors: String::new(),
+            aliases: String::new(),
+            difficulty: self.difficulty.clone(),
+            safety_warnings: String::new(),
+            translation: None,
+            source: None,
+            adjusted: None,
+        }
+    }
+
+    // The record, or every field that is wrong with it
+    pub fn build(self) -> Result<ErrorCode, InvalidErrorCode> {
+        let error = self.draft();
+        let mut problems = check(&error);
+        if self.severity.is_none() {
+            problems.retain(|problem| !matches!(problem, FieldError::Severity(_)));
+            problems.push(FieldError::MissingSeverity);
+        }
+        if problems.is_empty() {
+            Ok(error)
+        } else {
+            Err(InvalidErrorCode(problems))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::init;
+    use super::{ErrorCodeBuilder, FieldError};
+
+    #[test]
+    fn builder_normalizes_and_rejects_fields() {
+        init();
+        let error = ErrorCodeBuilder::new(" p0442 ")
+            .description("EVAP System Small Leak Detected")
+            .severity("medium")
+            .system("Emissions")
+            .causes(" Loose gas cap || Cracked EVAP hose ")
+            .causes("Loose gas cap")
+            .actions("Smoke test the EVAP system")
+            .build()
+            .unwrap();
+        assert_eq!(error.code, "P0442");
+        assert_eq!(error.severity, "Medium");
+        assert_eq!(error.possible_causes, ["Loose gas cap", "Cracked EVAP hose"]);
+
+        let invalid = ErrorCodeBuilder::new("X123").severity("Urgent").build().unwrap_err();
+        assert_eq!(invalid.0, [FieldError::Code("X123".to_string()), FieldError::EmptyDescription, FieldError::Severity("Urgent".to_string())]);
+        let missing = ErrorCodeBuilder::new("P0442").description("Leak").build().unwrap_err();
+        assert_eq!(missing.0, [FieldError::MissingSeverity]);
+    }
+}
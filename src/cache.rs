@@ -0,0 +1,129 @@
+// Results of repeated queries, kept for long-running embedders.
+//
+// A server that answers the same searches over and over keeps their results
+// in a `QueryCache`: at most `capacity` entries, the least recently used
+// evicted first, each one expiring `ttl` after it was stored. Queries are
+// normalized first, so "Misfire" and " misfire " share an entry; callers run
+// the normalized query too, so an entry holds what either would get. The owner
+// clears the cache whenever the data it was computed from is reloaded.
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Lookups answered from the cache and lookups that had to run the query
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+pub struct QueryCache<V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<String, (Instant, V)>,
+    // Keys from least to most recently used
+    order: VecDeque<String>,
+    hits: u64,
+    misses: u64,
+}
+
+// The cache key of a query: trimmed, lower case, runs of whitespace as one space
+pub fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+impl<V: Clone> QueryCache<V> {
+    // A capacity of 0 disables caching; every lookup runs the query
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        QueryCache { capacity, ttl, entries: HashMap::new(), order: VecDeque::new(), hits: 0, misses: 0 }
+    }
+
+    // The cached result of `query`, or the result of `run`, which is then cached
+    pub fn get_or_insert_with(&mut self, query: &str, run: impl FnOnce() -> V) -> V {
+        self.get_or_insert_at(query, Instant::now(), run)
+    }
+
+    // As `get_or_insert_with`, with the current time given
+    pub fn get_or_insert_at(&mut self, query: &str, now: Instant, run: impl FnOnce() -> V) -> V {
+        let key = normalize(query);
+        if let Some((stored, value)) = self.entries.get(&key) {
+            if now.duration_since(*stored) < self.ttl {
+                let value = value.clone();
+                self.hits += 1;
+                self.touch(&key);
+                return value;
+            }
+            self.remove(&key);
+        }
+
+        self.misses += 1;
+        let value = run();
+        if self.capacity > 0 {
+            while self.entries.len() >= self.capacity {
+                let Some(oldest) = self.order.pop_front() else { break };
+                self.entries.remove(&oldest);
+            }
+            self.entries.insert(key.clone(), (now, value.clone()));
+            self.order.push_back(key);
+        }
+        value
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(position) = self.order.iter().position(|entry| entry == key) {
+            let key = self.order.remove(position).expect("position is in range");
+            self.order.push_back(key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+        self.order.retain(|entry| entry != key);
+    }
+
+    // Drop every entry, e.g. after the database is reloaded; the counters are kept
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats { hits: self.hits, misses: self.misses, entries: self.entries.len() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::{CacheStats, QueryCache};
+
+    #[test]
+    fn query_cache_evicts_least_recently_used_and_expired_entries() {
+        let start = Instant::now();
+        let mut cache = QueryCache::new(2, Duration::from_secs(60));
+        let mut runs = 0;
+        let mut search = |cache: &mut QueryCache<String>, query: &str, seconds: u64| {
+            cache.get_or_insert_at(query, start + Duration::from_secs(seconds), || {
+                runs += 1;
+                super::normalize(query)
+            })
+        };
+
+        assert_eq!(search(&mut cache, "Misfire", 0), "misfire");
+        assert_eq!(search(&mut cache, "  misfire ", 1), "misfire");
+        search(&mut cache, "sensor", 2);
+        search(&mut cache, "misfire", 3);
+        // "sensor" is the least recently used entry, so it makes room for "coil"
+        search(&mut cache, "coil", 4);
+        search(&mut cache, "misfire", 5);
+        search(&mut cache, "sensor", 6);
+        // Past the TTL the query runs again
+        search(&mut cache, "sensor", 120);
+        assert_eq!(cache.stats(), CacheStats { hits: 3, misses: 5, entries: 2 });
+
+        cache.clear();
+        search(&mut cache, "sensor", 121);
+        assert_eq!(runs, 6);
+    }
+}
@@ -0,0 +1,117 @@
+// Inline SVG charts for HTML reports.
+//
+// Charts are drawn as plain SVG elements inside the page, so a report stays
+// a single file that opens offline and prints as it looks on screen. Every
+// chart has a title and an aria-label, and each bar or point a <title> with
+// its exact value, shown as a tooltip by browsers and read by screen readers.
+use crate::escape_html;
+
+const WIDTH: usize = 560;
+// Room for the labels left of the bars or timeline rows
+const LABEL_WIDTH: usize = 170;
+const ROW_HEIGHT: usize = 24;
+const BAR_HEIGHT: usize = 16;
+// Fallback fill for bars without a colour of their own
+const DEFAULT_COLOR: &str = "#5bc0de";
+
+pub struct Bar {
+    pub label: String,
+    pub value: usize,
+    pub color: Option<&'static str>,
+}
+
+// One row of a timeline: its label and the times of its events
+pub struct Row {
+    pub label: String,
+    pub times: Vec<u64>,
+}
+
+fn open(title: &str, height: usize) -> String {
+    format!(
+        "<svg class='chart' xmlns='http://www.w3.org/2000/svg' width='{}' height='{}' viewBox='0 0 {} {}' role='img' aria-label='{}'>\n<title>{}</title>\n",
+        WIDTH, height, WIDTH, height, escape_html(title), escape_html(title)
+    )
+}
+
+// Horizontal bars, longest scaled to the full width; None when every value is zero
+pub fn bar_chart(title: &str, bars: &[Bar]) -> Option<String> {
+    let max = bars.iter().map(|bar| bar.value).max().filter(|max| *max > 0)?;
+    // Room for the value printed after the longest bar
+    let scale = (WIDTH - LABEL_WIDTH - 50) as f64 / max as f64;
+    let mut svg = open(title, bars.len() * ROW_HEIGHT + 4);
+    for (index, bar) in bars.iter().enumerate() {
+        let y = index * ROW_HEIGHT + 2;
+        let length = (bar.value as f64 * scale).round() as usize;
+        let text_y = y + BAR_HEIGHT - 4;
+        svg.push_str(&format!(
+            "<text x='{}' y='{}' text-anchor='end' font-size='12'>{}</text>\n",
+            LABEL_WIDTH - 8, text_y, escape_html(&bar.label)
+        ));
+        svg.push_str(&format!(
+            "<rect x='{}' y='{}' width='{}' height='{}' fill='{}'><title>{}: {}</title></rect>\n",
+            LABEL_WIDTH, y, length, BAR_HEIGHT, bar.color.unwrap_or(DEFAULT_COLOR), escape_html(&bar.label), bar.value
+        ));
+        svg.push_str(&format!("<text x='{}' y='{}' font-size='12'>{}</text>\n", LABEL_WIDTH + length + 6, text_y, bar.value));
+    }
+    svg.push_str("</svg>\n");
+    Some(svg)
+}
+
+// A dot for each event on a shared time axis from `start` to `end`, with the dates
+// of both ends underneath; None without rows or without a span of time to draw
+pub fn timeline(title: &str, rows: &[Row], start: u64, end: u64) -> Option<String> {
+    if rows.is_empty() || end <= start {
+        return None;
+    }
+    let axis = (WIDTH - LABEL_WIDTH - 20) as f64;
+    let x = |time: u64| LABEL_WIDTH as f64 + axis * (time.clamp(start, end) - start) as f64 / (end - start) as f64;
+    let bottom = rows.len() * ROW_HEIGHT;
+    let mut svg = open(title, bottom + 24);
+    for (index, row) in rows.iter().enumerate() {
+        let middle = index * ROW_HEIGHT + ROW_HEIGHT / 2;
+        svg.push_str(&format!(
+            "<text x='{}' y='{}' text-anchor='end' font-size='12'>{}</text>\n",
+            LABEL_WIDTH - 8, middle + 4, escape_html(&row.label)
+        ));
+        svg.push_str(&format!(
+            "<line x1='{}' y1='{}' x2='{:.1}' y2='{}' stroke='#ddd'/>\n",
+            LABEL_WIDTH, middle, x(end), middle
+        ));
+        for time in &row.times {
+            svg.push_str(&format!(
+                "<circle cx='{:.1}' cy='{}' r='5' fill='{}'><title>{}</title></circle>\n",
+                x(*time), middle, DEFAULT_COLOR, crate::time::format_datetime(*time)
+            ));
+        }
+    }
+    svg.push_str(&format!(
+        "<text x='{}' y='{}' font-size='11' fill='#888'>{}</text>\n<text x='{:.1}' y='{}' text-anchor='end' font-size='11' fill='#888'>{}</text>\n",
+        LABEL_WIDTH, bottom + 16, crate::time::format_date(start), x(end), bottom + 16, crate::time::format_date(end)
+    ));
+    svg.push_str("</svg>\n");
+    Some(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bar, Row};
+
+    #[test]
+    fn charts_scale_bars_and_skip_empty_data() {
+        let bars = [
+            Bar { label: "<Engine>".to_string(), value: 4, color: None },
+            Bar { label: "Airbag".to_string(), value: 2, color: Some("#d9534f") },
+        ];
+        let svg = super::bar_chart("Codes", &bars).unwrap();
+        assert!(svg.contains("&lt;Engine&gt;"));
+        assert!(svg.contains("width='340' height='16' fill='#5bc0de'"));
+        assert!(svg.contains("width='170' height='16' fill='#d9534f'"));
+        assert!(super::bar_chart("Codes", &[Bar { label: "Engine".to_string(), value: 0, color: None }]).is_none());
+
+        let rows = [Row { label: "VIN001 P0300".to_string(), times: vec![100, 200] }];
+        assert_eq!(super::timeline("Seen", &rows, 100, 200).unwrap().matches("<circle").count(), 2);
+        // A single scan has no span of time to draw
+        assert!(super::timeline("Seen", &rows, 100, 100).is_none());
+        assert_eq!(crate::presentation::Style::SeverityMedium.color(), Some("#d4a017"));
+    }
+}
@@ -0,0 +1,615 @@
+// What the command line and interactive mode both show: codes, their sources and parts,
+// quotes, and the adapter and monitor screens
+use std::error::Error;
+use std::fs;
+use std::io;
+use crate::adapter::{self, Adapter};
+use crate::anonymize::Anonymizer;
+use crate::archive::{self, Clock, SystemClock};
+use crate::config::{self, Config};
+use crate::groups::{self, Groups};
+use crate::i18n::{self, tr};
+use crate::labor::LaborGuide;
+use crate::layout::{self, Field};
+use crate::merge::{self, MergePolicy};
+use crate::presentation::{self, Style, Styled};
+use crate::search::{self, Highlights, SearchPage};
+use crate::session::Session;
+use crate::transcript::outln;
+use crate::vehicle::{self, Vehicle};
+use crate::workspace::{Note, Workspace};
+use crate::{compare, drive_cycle, help, monitor, net, pids, pipe_list, porcelain, progress, readiness, time, troubleshoot, DiagnosticsDatabase, ErrorCode};
+
+// Display error information with color
+pub fn display_error(error: &ErrorCode) {
+    display_error_highlighted(error, &Highlights::default());
+}
+
+// Display a single error, through the pager when it is taller than the terminal
+pub fn display_error_paged(error: &ErrorCode) -> Result<(), Box<dyn Error>> {
+    help::page_if_long(&format_error(error, &Highlights::default()))
+}
+
+// Display error information with search matches highlighted
+pub fn display_error_highlighted(error: &ErrorCode, highlights: &Highlights) {
+    outln!("{}", format_error(error, highlights));
+}
+
+// Format error information for the terminal in its layout, one line per entry
+pub fn format_error(error: &ErrorCode, highlights: &Highlights) -> String {
+    let mut lines = Vec::new();
+    lines.push(format!("{}", "================================".styled(Style::Banner)));
+    lines.push(format!("{} {}", tr!("label-error-code").styled(Style::Label), error.code.styled(Style::Code)));
+    if pipe_list(&error.safety_warnings).next().is_some() {
+        lines.push(format!("{}", tr!("section-safety").styled(Style::Error)));
+        for warning in pipe_list(&error.safety_warnings) {
+            lines.push(format!("  {} {}", "!".styled(Style::Error), warning.styled(Style::Warning)));
+        }
+        lines.push(String::new());
+    }
+    // A label line after a section is set apart from it by a blank line, as sections are
+    let mut after_section = false;
+    for field in layout::screen() {
+        let severity_style = Style::for_severity(&error.severity).unwrap_or(Style::Plain);
+        let line = match field {
+            Field::Description => Some((tr!("label-description"), search::mark_terminal(&error.description, &highlights.description))),
+            Field::Severity => {
+                let severity = presentation::severity_text(&error.severity).styled(severity_style).to_string();
+                Some((tr!("label-severity"), match error.adjustment_note() {
+                    Some(note) => format!("{} {}", severity, format!("({})", note).styled(Style::Muted)),
+                    None => severity,
+                }))
+            },
+            Field::Difficulty => error.difficulty_label().map(|(text, style)| (tr!("label-difficulty"), text.styled(style).to_string())),
+            Field::System => Some((tr!("label-system"), error.system.styled(Style::Category).to_string())),
+            Field::Source => error.source.as_ref().map(|source| (tr!("label-source"), source.to_string().styled(Style::Muted).to_string())),
+            _ => None,
+        };
+        if let Some((label, value)) = line {
+            let gap = if std::mem::take(&mut after_section) { "\n" } else { "" };
+            lines.push(format!("{}{} {}", gap, label.styled(Style::Label), value));
+            if *field == Field::Description {
+                if let Some(translation) = &error.translation {
+                    let label = tr!("label-translation", language = i18n::language());
+                    lines.push(format!("{} {}", label.styled(Style::Label), search::mark_terminal(translation, &highlights.translation)));
+                }
+                let aliases: Vec<String> = pipe_list(&error.aliases)
+                    .enumerate()
+                    .map(|(index, alias)| search::mark_terminal(alias, Highlights::item(&highlights.aliases, index)))
+                    .collect();
+                if !aliases.is_empty() {
+                    lines.push(format!("{} {}", tr!("label-aliases").styled(Style::Label), aliases.join(", ")));
+                }
+            }
+            continue;
+        }
+        
+        let start = lines.len();
+        match field {
+            Field::PossibleCauses => {
+                lines.push(format!("\n{}", tr!("section-possible-causes").styled(Style::Section)));
+                for (index, cause) in error.possible_causes.iter().enumerate() {
+                    lines.push(format!("  - {}", search::mark_terminal(cause, Highlights::item(&highlights.possible_causes, index))));
+                }
+            },
+            Field::RecommendedActions => {
+                lines.push(format!("\n{}", tr!("section-recommended-actions").styled(Style::Section)));
+                for (index, action) in error.recommended_actions.iter().enumerate() {
+                    lines.push(format!("  - {}", search::mark_terminal(action, Highlights::item(&highlights.recommended_actions, index))));
+                }
+            },
+            Field::SetContext if error.has_set_context() => {
+                lines.push(format!("\n{}", tr!("section-set-context").styled(Style::Section)));
+                if !error.set_conditions.trim().is_empty() {
+                    lines.push(format!("  {} {}", tr!("label-set-conditions").styled(Style::Label), error.set_conditions.trim()));
+                }
+                let ids: Vec<&str> = pipe_list(&error.monitors).collect();
+                if !ids.is_empty() {
+                    lines.push(format!("  {} {}", tr!("label-monitors").styled(Style::Label), error.monitor_names().join(", ")));
+                    let command = format!("drive-cycle {}", ids.iter().map(|id| format!("--monitor {}", id)).collect::<Vec<_>>().join(" "));
+                    lines.push(format!("  {}", tr!("set-context-reproduce", command = command.styled(Style::Command)).styled(Style::Hint)));
+                }
+            },
+            Field::RelatedParts if !error.parts().is_empty() => {
+                lines.push(format!("\n{}", tr!("section-related-parts").styled(Style::Section)));
+                for part in error.parts() {
+                    lines.push(format!("  - {}", part));
+                }
+            },
+            Field::Warranty if error.has_warranty_info() => {
+                lines.push(format!("\n{}", tr!("section-warranty").styled(Style::Section)));
+                let warranty = match error.emissions_warranty {
+                    Some(true) => error.warranty_label().styled(Style::Success),
+                    _ => error.warranty_label().styled(Style::Plain),
+                };
+                lines.push(format!("  {} {}", tr!("label-emissions-warranty").styled(Style::Label), warranty));
+                for campaign in pipe_list(&error.service_campaigns) {
+                    lines.push(format!("  {} {}", tr!("label-campaign").styled(Style::Label), campaign));
+                }
+                for link in pipe_list(&error.campaign_links) {
+                    lines.push(format!("  {} {}", tr!("label-link").styled(Style::Label), link.styled(Style::Link)));
+                }
+            },
+            _ => {},
+        }
+        after_section |= lines.len() > start;
+    }
+    lines.push(format!("{}", "================================\n".styled(Style::Banner)));
+    lines.join("\n")
+}
+
+// The closest matches of a reverse lookup, one line each, with the matching words highlighted
+pub fn display_reverse(phrase: &str, page: &SearchPage) {
+    if page.hits.is_empty() {
+        outln!("{}", tr!("reverse-none", phrase = phrase));
+        return;
+    }
+    outln!("{}", tr!("reverse-found", phrase = phrase).styled(Style::Heading));
+    for hit in &page.hits {
+        let error = &hit.error;
+        let details = format!("[{}, {}]", presentation::severity_text(&error.severity), error.system);
+        outln!("  {:<7} {}  {}", error.code.styled(Style::Code), search::mark_terminal(&error.description, &hit.highlights.description), details.styled(Style::Muted));
+    }
+    outln!("{}", tr!("reverse-hint").styled(Style::Hint));
+}
+
+// The working result set of interactive mode, one line per code
+pub fn display_results(db: &DiagnosticsDatabase, query: Option<&str>, codes: &[String]) {
+    let Some(query) = query else {
+        outln!("{}", tr!("results-none"));
+        return;
+    };
+    outln!("{}", tr!("results-header", count = codes.len(), query = query).styled(Style::Heading));
+    for code in codes {
+        let description = db.lookup_error(code).map(|error| error.description.as_str()).unwrap_or_default();
+        outln!("  {:<7} {}", code.styled(Style::Code), description);
+    }
+}
+
+pub fn display_notes(notes: &[Note]) {
+    if notes.is_empty() {
+        outln!("{}", tr!("notes-none", note = "note".styled(Style::Command)));
+        return;
+    }
+    outln!("{}", tr!("notes-header").styled(Style::Heading));
+    for note in notes {
+        outln!("  {}  {}", time::format_datetime(note.timestamp).styled(Style::Muted), note.text);
+    }
+}
+
+// Saved workspaces, most recent first
+pub fn display_workspaces(workspaces: &[(String, Workspace)]) {
+    if workspaces.is_empty() {
+        outln!("{}", tr!("workspace-none", save = "workspace save".styled(Style::Command)));
+        return;
+    }
+    outln!("{}", tr!("workspace-list-header").styled(Style::Heading));
+    let width = workspaces.iter().map(|(name, _)| name.chars().count()).max().unwrap_or_default();
+    for (name, workspace) in workspaces {
+        let vehicle = workspace.vehicle.as_ref().map(Vehicle::to_string).unwrap_or_else(|| tr!("repl-status-no-vehicle"));
+        let contents = tr!("workspace-contents", results = workspace.results.len(), notes = workspace.notes.len());
+        outln!("  {:<width$}  {}  {}, {}", name.styled(Style::Category), time::format_datetime(workspace.saved).styled(Style::Muted), vehicle, contents, width = width);
+    }
+}
+
+// Compare every definition of a code field by field, numbered by source
+pub fn display_all_sources(db: &DiagnosticsDatabase, code: &str) {
+    let definitions = db.definitions_of(code);
+    let Some(effective) = db.lookup_error(code) else {
+        outln!("{}", tr!("code-not-found", code = code.styled(Style::Error)));
+        return;
+    };
+    
+    outln!("{}", tr!("sources-header", code = code.styled(Style::Code), count = definitions.len()).styled(Style::Heading));
+    for (index, definition) in definitions.iter().enumerate() {
+        let source = definition.source.as_ref().map(ToString::to_string).unwrap_or_default();
+        let status = if definition.source == effective.source { tr!("sources-in-use") } else { tr!("sources-overridden") };
+        outln!("  [{}] {} {}", index + 1, source, status.styled(Style::Muted));
+    }
+    
+    let fields: Vec<Vec<(String, String)>> = definitions.iter().map(|definition| definition.fields()).collect();
+    for (position, (label, first)) in fields[0].iter().enumerate() {
+        let same = fields.iter().all(|values| values[position].1 == *first);
+        if same && first.is_empty() {
+            continue;
+        }
+        outln!("\n{}", label.styled(Style::Label));
+        if same {
+            outln!("  {} {}", tr!("sources-same").styled(Style::Muted), first);
+        } else {
+            for (index, values) in fields.iter().enumerate() {
+                outln!("  [{}] {}", index + 1, values[position].1);
+            }
+        }
+    }
+}
+
+// Show two codes side by side with their differences highlighted
+pub fn display_comparison(db: &DiagnosticsDatabase, first: &str, second: &str) {
+    let (first, second) = (first.to_uppercase(), second.to_uppercase());
+    match (db.lookup_error(&first), db.lookup_error(&second)) {
+        (Some(first), Some(second)) => {
+            for line in compare::render(first, second, compare::terminal_width()) {
+                outln!("{}", line);
+            }
+        },
+        (None, _) => outln!("{}", tr!("code-not-found", code = first.styled(Style::Error))),
+        (_, None) => outln!("{}", tr!("code-not-found", code = second.styled(Style::Error))),
+    }
+}
+
+// Show, field by field, which definitions a merged code was built from
+pub fn explain_merge(db: &DiagnosticsDatabase, policy: &MergePolicy, code: &str) {
+    let definitions = db.definitions_of(code);
+    let Some((_, decisions)) = merge::merge(definitions, policy) else {
+        outln!("{}", tr!("code-not-found", code = code.styled(Style::Error)));
+        return;
+    };
+    
+    outln!("{}", tr!("merge-header", code = code.styled(Style::Code), count = definitions.len()).styled(Style::Heading));
+    for (position, definition) in merge::by_precedence(definitions).iter().enumerate() {
+        if let Some(source) = &definition.source {
+            outln!("  [{}] {} {}", position + 1, source, tr!("merge-precedence", precedence = source.precedence).styled(Style::Muted));
+        }
+    }
+    
+    outln!();
+    for decision in decisions {
+        let from = if decision.contributors.is_empty() {
+            tr!("merge-unset")
+        } else {
+            decision.contributors.iter().map(|position| format!("[{}]", position + 1)).collect::<Vec<_>>().join(" ")
+        };
+        outln!("  {:<20} {:<16} {:<8} {}", decision.field.styled(Style::Label), decision.strategy.to_string().styled(Style::Category), from, decision.value.replace('|', " | "));
+    }
+}
+
+// Print the configuration file, or every effective setting with its origin
+pub fn show_config(resolved: bool) -> Result<(), Box<dyn Error>> {
+    let layers = Config::resolve()?;
+    if resolved {
+        let settings = layers.settings()?;
+        if porcelain::enabled() {
+            for setting in &settings {
+                println!("{}", porcelain::line("setting", &[&setting.key, &setting.value, &setting.origin]));
+            }
+            return Ok(());
+        }
+        outln!("{}", tr!("config-resolved-header", path = layers.path.display().to_string(), prefix = config::ENV_PREFIX).styled(Style::Heading));
+        let width = settings.iter().map(|setting| setting.key.len() + setting.value.len() + 3).max().unwrap_or_default();
+        for setting in &settings {
+            let assignment = format!("{} = {}", setting.key, setting.value);
+            outln!("{:<width$}  {}", assignment, format!("# {}", setting.origin).styled(Style::Muted), width = width);
+        }
+    } else if layers.file_exists() {
+        outln!("{}", format!("# {}", layers.path.display()).styled(Style::Muted));
+        outln!("{}", fs::read_to_string(&layers.path)?.trim_end());
+    } else {
+        outln!("{}", tr!("config-no-file", path = layers.path.display().to_string()));
+    }
+    Ok(())
+}
+
+// Walk the decision tree for a code and record the path in the session notes
+pub fn troubleshoot_code(config: &Config, code: &str) -> Result<(), Box<dyn Error>> {
+    let code = code.to_uppercase();
+    let trees = config.load_decision_trees()?;
+    let Some(tree) = trees.get(&code) else {
+        outln!("{}", tr!("troubleshoot-none", code = code.styled(Style::Error)));
+        return Ok(());
+    };
+    
+    outln!("{}", tr!("troubleshoot-header", code = code.styled(Style::Code), title = tree.title.as_str()).styled(Style::Heading));
+    outln!("{}", tr!("troubleshoot-intro"));
+    let walk = troubleshoot::walk(tree, &mut io::stdin().lock())?;
+    let notes = troubleshoot::record_notes(&config.data_dir(), &code, tree, &walk)?;
+    outln!("{}", tr!("troubleshoot-recorded", path = notes.display()));
+    Ok(())
+}
+
+// Display the parts related to an error code
+pub fn display_parts(error: &ErrorCode, config: &Config, client: Option<&net::Client>) {
+    let parts = error.parts();
+    if parts.is_empty() {
+        outln!("{}", tr!("parts-none", code = error.code.styled(Style::Code)));
+        return;
+    }
+    
+    outln!("{}", tr!("parts-header", code = error.code.styled(Style::Code)).styled(Style::Heading));
+    for part in parts {
+        if part.number.is_empty() {
+            outln!("  - {}", part.name);
+        } else {
+            outln!("  - {} ({} {})", part.name, "OEM".styled(Style::Category), part.number.styled(Style::Code));
+        }
+        if let Some(url) = config.parts_url(part.name, part.number) {
+            outln!("      {} {}", tr!("parts-pricing").styled(Style::Success), url.styled(Style::Link));
+            if let Some(client) = client {
+                let spinner = progress::spinner(tr!("net-fetching", url = url.as_str()));
+                let result = client.get_text(&url);
+                spinner.finish_and_clear();
+                match result {
+                    Ok(body) => outln!("      {}", summarize_response(&body)),
+                    Err(e) => outln!("      {}: {}", tr!("error-prefix").styled(Style::Error), e),
+                }
+            }
+        }
+    }
+}
+
+// The first lines of a response body, for showing next to a part
+pub fn summarize_response(body: &str) -> String {
+    let text: String = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(160) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text,
+    }
+}
+
+// Display a labor quote for one or more error codes
+pub fn display_quote(db: &DiagnosticsDatabase, labor: &LaborGuide, codes: &[String], rate: Option<f64>) {
+    let mut total_hours = 0.0;
+    for code in codes {
+        let code = code.to_uppercase();
+        let Some(error) = db.lookup_error(&code) else {
+            outln!("{}", tr!("code-not-found", code = code.styled(Style::Error)));
+            continue;
+        };
+        
+        outln!("{}", tr!("quote-header", code = error.code.styled(Style::Code), description = error.description).styled(Style::Heading));
+        let operations = labor.operations_for(&error.code);
+        if operations.is_empty() {
+            outln!("  {}", tr!("quote-none"));
+            continue;
+        }
+        
+        for operation in operations {
+            outln!("  {:<12} {:<45} {:>5.1} h", operation.op_code.styled(Style::Category), operation.description, operation.hours);
+            if !operation.action.is_empty() {
+                outln!("  {:<12} {}", "", tr!("quote-for-action", action = operation.action).styled(Style::Muted));
+            }
+        }
+        total_hours += labor.total_hours(&error.code);
+    }
+    
+    outln!("{} {:.1} h", tr!("quote-total").styled(Style::Success), total_hours);
+    if let Some(rate) = rate {
+        let cost = format!("{:.2}", total_hours * rate);
+        outln!("{}", tr!("quote-cost", cost = cost, rate = format!("{:.2}", rate)).styled(Style::Success));
+    }
+}
+
+// The group named by a fleet command's --group
+pub fn fleet_group(session: &Session, name: Option<&str>) -> Result<Option<groups::Group>, Box<dyn Error>> {
+    let Some(name) = name else { return Ok(None) };
+    Ok(Some(Groups::open(&session.config()?.data_dir())?.get(name)?.clone()))
+}
+
+// The anonymizer for a command's --anonymize flag, None when it is not given
+pub fn anonymizer(config: &Config, anonymize: bool) -> Result<Option<Anonymizer>, Box<dyn Error>> {
+    Ok(match anonymize {
+        true => Some(Anonymizer::new(&config.anonymize)?),
+        false => None,
+    })
+}
+
+// A code's definition as a report input: every field, and the database or pack it came from
+pub fn definition(error: &ErrorCode) -> String {
+    let source = error.source.as_ref().map(|source| source.to_string()).unwrap_or_default();
+    format!("{}\0{:?}\0{}\0{:?}", serde_json::to_string(error).unwrap_or_default(), error.translation, source, error.adjusted)
+}
+
+// The [anonymize] rules as a report input; empty without --anonymize
+pub fn anonymized_by(config: &Config, anonymize: bool) -> Result<String, Box<dyn Error>> {
+    Ok(match anonymize {
+        true => serde_json::to_string(&config.anonymize)?,
+        false => String::new(),
+    })
+}
+
+// One porcelain code record per error, in code order so runs can be diffed
+pub fn print_porcelain(errors: &[&ErrorCode]) {
+    let mut errors = errors.to_vec();
+    errors.sort_by(|a, b| a.code.cmp(&b.code));
+    for error in errors {
+        println!("{}", porcelain::code(error));
+    }
+}
+
+// Look up one or more codes and display each result
+// Each code is followed by when the archive last saw it, on the vehicle with `vin` when one is known
+pub fn lookup_codes(db: &DiagnosticsDatabase, codes: &[&str], scans: &[archive::ScanRecord], vin: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut missing = Vec::new();
+    for code in codes {
+        let code = code.to_uppercase();
+        match db.lookup_error(&code) {
+            Some(error) if codes.len() == 1 => display_error_paged(error)?,
+            Some(error) => display_error(error),
+            None => missing.push(code.clone()),
+        }
+        if let Some(line) = last_seen_line(scans, &code, vin) {
+            outln!("{}", line.styled(Style::Muted));
+        }
+    }
+
+    for code in &missing {
+        outln!("{}", tr!("code-not-found", code = code.styled(Style::Error)));
+    }
+    if codes.len() > 1 {
+        outln!("{}", tr!("batch-summary", count = codes.len().styled(Style::Success), missing = missing.len().styled(Style::Error)));
+    }
+    Ok(())
+}
+
+// File an archived scan under the vehicle `to`, or under none; returns what was done
+pub fn relink_scan(config: &Config, id: &str, to: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let archive = archive::Archive::open(&config.data_dir())?;
+    match to {
+        Some(vin) => {
+            if !vehicle::looks_like_vin(vin) {
+                return Err(tr!("vehicle-invalid-vin", vin = vin).into());
+            }
+            let vin = vin.to_uppercase();
+            let from = archive.relink(id, &vin, "garage move")?;
+            Ok(tr!("garage-moved", id = id, from = from, to = vin))
+        },
+        None => {
+            let from = archive.relink(id, archive::UNKNOWN_VIN, "garage unlink")?;
+            Ok(tr!("garage-unlinked", id = id, vin = from))
+        },
+    }
+}
+
+// Archived scans for the last-seen lines; a lookup still works when the archive cannot be read
+pub fn archived_scans(config: &Config) -> Vec<archive::ScanRecord> {
+    archive::Archive::open(&config.data_dir()).and_then(|archive| archive.load_all()).unwrap_or_default()
+}
+
+// "Last seen on this vehicle 2024-11-02 (3 times in the past year)", or on which vehicle without a VIN
+pub fn last_seen_line(scans: &[archive::ScanRecord], code: &str, vin: Option<&str>) -> Option<String> {
+    let seen = archive::last_seen(scans, code, vin, SystemClock.now())?;
+    let date = time::format_date(seen.timestamp);
+    Some(match vin {
+        Some(_) => tr!("lookup-last-seen-vehicle", date = date, count = seen.count),
+        None => tr!("lookup-last-seen", date = date, vin = seen.vin, count = seen.count),
+    })
+}
+
+// Connect to an adapter with a spinner, then show what was found
+pub fn connect_adapter(port: &str, settings: &adapter::AdapterSettings) -> Result<Adapter, Box<dyn Error>> {
+    let spinner = progress::spinner(tr!("adapter-connecting", port = port));
+    let result = Adapter::connect(port, settings);
+    spinner.finish_and_clear();
+    let mut adapter = result?;
+    match adapter.attached() {
+        true => outln!("{}", tr!("adapter-attached", port = port).styled(Style::Success)),
+        false => outln!("{}", tr!("adapter-connected", port = port).styled(Style::Success)),
+    }
+    display_adapter_status(&mut adapter);
+    Ok(adapter)
+}
+
+// Watch the named PIDs, or the configured ones when none are named
+// With `summary`, the codes are read before and after the session and the summary is archived;
+// with `gauges`, the values are shown as gauges rather than a line per sample, new codes
+// described from that database
+pub fn monitor_vehicle(adapter: &mut Adapter, config: &Config, client: Option<&net::Client>, names: &[String], window: monitor::Window, summary: bool, gauges: Option<&DiagnosticsDatabase>) -> Result<(), Box<dyn Error>> {
+    let names = if names.is_empty() { &config.monitor.pids } else { names };
+    let known: Vec<&str> = pids::PIDS.iter().map(|pid| pid.name).collect();
+    let selected = names
+        .iter()
+        .map(|name| pids::find(name).ok_or_else(|| tr!("monitor-unknown-pid", pid = name.as_str(), known = known.join(", "))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let notifier = monitor::Notifier {
+        log: config.data_dir().join("monitor.log"),
+        webhook: config.monitor.webhook.clone(),
+        mqtt: config.monitor.mqtt.clone(),
+        client,
+    };
+    let codes_before = match summary {
+        true => monitor::read_codes(adapter)?,
+        false => Vec::new(),
+    };
+    let recorder = match gauges {
+        #[cfg(feature = "tui")]
+        Some(db) => crate::gauges::run(adapter, &config.monitor, &selected, &notifier, window, db)?,
+        #[cfg(not(feature = "tui"))]
+        Some(_) => return Err(tr!("feature-missing", feature = "tui").into()),
+        None => monitor::run(adapter, &config.monitor, &selected, &notifier, window)?,
+    };
+    if summary {
+        let codes_after = monitor::read_codes(adapter)?;
+        let summary = recorder.finish(SystemClock.now(), &codes_before, &codes_after);
+        monitor::display(&summary);
+        let id = monitor::save(&summary, adapter.read_vin()?, &codes_after, &config.data_dir())?;
+        outln!("{}", tr!("monitor-summary-saved", id = id));
+    }
+    Ok(())
+}
+
+// Watch DBC signals on a SocketCAN interface; a summary has no codes, as nothing is requested
+#[cfg(feature = "adapter")]
+pub fn monitor_signals(config: &Config, interface: &str, dbc: &str, names: &[String], window: monitor::Window, summary: bool) -> Result<(), Box<dyn Error>> {
+    let dbc = crate::dbc::Dbc::load(dbc)?;
+    let signals = names
+        .iter()
+        .map(|name| dbc.find(name).ok_or_else(|| tr!("monitor-unknown-signal", signal = name.as_str(), known = dbc.signal_names().join(", "))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let socket = crate::socketcan::CanSocket::open(interface, std::time::Duration::from_millis(100))?;
+    let recorder = monitor::run_signals(&socket, &config.monitor, &signals, window)?;
+    if summary {
+        let summary = recorder.finish(SystemClock.now(), &[], &[]);
+        monitor::display(&summary);
+        let id = monitor::save(&summary, None, &[], &config.data_dir())?;
+        outln!("{}", tr!("monitor-summary-saved", id = id));
+    }
+    Ok(())
+}
+
+// Show drive cycle steps for the named monitors; when connected, read readiness
+// first and default to the incomplete monitors, optionally tracking them live
+pub fn show_drive_cycle(config: &Config, adapter: Option<&mut Adapter>, make: Option<&str>, names: &[String], watch: bool) -> Result<(), Box<dyn Error>> {
+    let cycles = config.load_drive_cycles()?;
+    let mut ids: Vec<&str> = Vec::new();
+    for monitor in readiness::MONITORS {
+        if !ids.contains(&monitor.id) {
+            ids.push(monitor.id);
+        }
+    }
+    let mut selected = names
+        .iter()
+        .map(|name| readiness::find(name).ok_or_else(|| tr!("drive-cycle-unknown-monitor", monitor = name.as_str(), known = ids.join(", "))))
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    let Some(adapter) = adapter else {
+        if selected.is_empty() {
+            selected = ids.iter().filter_map(|id| readiness::find(id)).collect();
+        }
+        drive_cycle::display(&cycles, &selected, make);
+        return Ok(());
+    };
+    let status = readiness::read(adapter)?;
+    drive_cycle::display_readiness(&status);
+    if selected.is_empty() {
+        selected = status.incomplete().collect();
+    }
+    if selected.is_empty() {
+        outln!("{}", tr!("drive-cycle-none-incomplete"));
+        return Ok(());
+    }
+    outln!();
+    drive_cycle::display(&cycles, &selected, make);
+    if watch {
+        outln!();
+        drive_cycle::watch(adapter, status)?;
+    }
+    Ok(())
+}
+
+// Show the link to an adapter, reading the battery voltage afresh
+pub fn display_adapter_status(adapter: &mut Adapter) {
+    let voltage = match adapter.voltage() {
+        Ok(volts) => format!("{:.1} V", volts),
+        Err(e) => tr!("adapter-voltage-unavailable", error = e),
+    };
+    outln!("{} {} ({})", tr!("adapter-label-adapter").styled(Style::Label), adapter.port, adapter.version);
+    outln!("{} {}", tr!("adapter-label-protocol").styled(Style::Label), adapter.protocol);
+    if adapter.capabilities.voltage {
+        outln!("{} {}", tr!("adapter-label-voltage").styled(Style::Label), voltage);
+    }
+    outln!("{} {} ({})", tr!("adapter-label-ecus").styled(Style::Label), adapter.ecus.len(), adapter.ecus.join(", "));
+    let missing = adapter.capabilities.missing(adapter.uses_can());
+    if !missing.is_empty() {
+        outln!("{} {}", tr!("adapter-label-missing").styled(Style::Label), missing.join(", ").styled(Style::Warning));
+    }
+}
+
+// What the adapter daemon holds, as `display_adapter_status` shows an adapter
+#[cfg(feature = "adapter")]
+pub fn display_daemon_status(status: &crate::adapterd::Status) {
+    let adapter = &status.adapter;
+    outln!("{} {} ({})", tr!("adapter-label-adapter").styled(Style::Label), status.port, adapter.version);
+    outln!("{} {}", tr!("adapter-label-protocol").styled(Style::Label), adapter.protocol);
+    outln!("{} {} ({})", tr!("adapter-label-ecus").styled(Style::Label), adapter.ecus.len(), adapter.ecus.join(", "));
+    outln!("{} {}", tr!("adapterd-label-clients").styled(Style::Label), status.clients);
+}
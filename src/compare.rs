@@ -0,0 +1,128 @@
+// Side-by-side comparison of two codes (`compare P0171 P0174`).
+//
+// Each field is shown as a row with one column per code. List entries and
+// values found on only one side are highlighted, and rows that differ are
+// marked with '*' so the difference is visible without colors too.
+use std::env;
+use crate::presentation::{Style, Styled};
+use crate::ErrorCode;
+
+// Width used when the terminal does not report one
+const DEFAULT_WIDTH: usize = 100;
+
+// Narrowest column before the text is wrapped regardless of the terminal
+const MIN_COLUMN: usize = 20;
+
+const SEPARATOR: &str = " | ";
+
+// A wrapped line of a cell and whether it is a difference
+type Line = (String, bool);
+
+pub fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+// Wrap text at word boundaries to lines of at most `width` characters
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// Lay out the entries of one side, marking those the other side lacks
+fn cell(entries: &[&str], other: &[&str], width: usize, bullets: bool) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for entry in entries {
+        let differs = !other.iter().any(|candidate| candidate.eq_ignore_ascii_case(entry));
+        let text = if bullets { format!("- {}", entry) } else { entry.to_string() };
+        for (index, line) in wrap(&text, width).into_iter().enumerate() {
+            let line = if bullets && index > 0 { format!("  {}", line) } else { line };
+            lines.push((line, differs));
+        }
+    }
+    lines
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.chars().count())))
+}
+
+// A line of a cell padded to the column width, highlighted when it is a difference
+fn styled_cell(line: Option<&Line>, width: usize) -> String {
+    match line {
+        Some((text, true)) => format!("{}{}", text.styled(Style::Match), " ".repeat(width.saturating_sub(text.chars().count()))),
+        Some((text, false)) => pad(text, width),
+        None => pad("", width),
+    }
+}
+
+// Render two codes side by side for a terminal `width` characters wide
+pub fn render(first: &ErrorCode, second: &ErrorCode, width: usize) -> Vec<String> {
+    let first_fields = first.fields();
+    let second_fields = second.fields();
+    // Room for the "* " marker, the longest label and a gap
+    let label_width = first_fields.iter().map(|(label, _)| label.chars().count()).max().unwrap_or_default() + 4;
+    let column = (width.saturating_sub(label_width + SEPARATOR.len()) / 2).max(MIN_COLUMN);
+
+    let mut output = vec![
+        format!("{}{}{}{}", pad("", label_width), pad(&first.code, column).styled(Style::Code), SEPARATOR, second.code.styled(Style::Code)),
+        "-".repeat(label_width + column * 2 + SEPARATOR.len()).styled(Style::Muted).to_string(),
+    ];
+
+    for ((label, left), (_, right)) in first_fields.iter().zip(&second_fields) {
+        if left.is_empty() && right.is_empty() {
+            continue;
+        }
+        let left_entries: Vec<&str> = left.split(" | ").filter(|entry| !entry.is_empty()).collect();
+        let right_entries: Vec<&str> = right.split(" | ").filter(|entry| !entry.is_empty()).collect();
+        let bullets = left_entries.len() > 1 || right_entries.len() > 1;
+        let left_lines = cell(&left_entries, &right_entries, column, bullets);
+        let right_lines = cell(&right_entries, &left_entries, column, bullets);
+        let differs = left_lines.iter().chain(&right_lines).any(|(_, differs)| *differs);
+
+        for row in 0..left_lines.len().max(right_lines.len()) {
+            let label_cell = match row {
+                0 => pad(&format!("{}{}", if differs { "* " } else { "  " }, label), label_width).styled(Style::Label).to_string(),
+                _ => pad("", label_width),
+            };
+            let line = format!("{}{}{}{}", label_cell, styled_cell(left_lines.get(row), column), SEPARATOR, styled_cell(right_lines.get(row), column));
+            output.push(line.trim_end().to_string());
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{init, assert_golden};
+    use crate::ErrorCode;
+
+    #[test]
+    fn compare_side_by_side() {
+        init();
+        let lean = |code: &str, bank: &str| ErrorCode {
+            description: format!("System Too Lean (Bank {})", bank),
+            possible_causes: vec!["Vacuum leak".to_string(), "Faulty MAF sensor".to_string(), format!("Exhaust leak before bank {} oxygen sensor", bank)],
+            ..ErrorCode::fixture_with(code, "Medium", "Fuel")
+        };
+        let first = lean("P0171", "1");
+        let second = ErrorCode { recommended_actions: vec!["First action".to_string()], ..lean("P0174", "2") };
+        let lines = super::render(&first, &second, 80);
+        assert_golden("compare.txt", &(lines.join("\n") + "\n"));
+    }
+}
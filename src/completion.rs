@@ -0,0 +1,70 @@
+use rustyline::completion::{Completer, Pair};
+use rustyline::{Context, Helper, Highlighter, Hinter, Validator};
+use crate::DiagnosticsDatabase;
+
+// Commands understood by the interactive prompt
+pub const COMMANDS: &[&str] = &["lookup", "pick", "parts", "compare", "system", "severity", "search", "reverse", "troubleshoot", "transcript", "connect", "disconnect", "status", "monitor", "scan", "vehicle", "garage", "results", "note", "notes", "workspace", "resume", "drive-cycle", "analyze", "o2-tests", "help", "exit", "quit"];
+
+// Tab completion for interactive mode, backed by the loaded database
+#[derive(Helper, Hinter, Highlighter, Validator)]
+pub struct ReplHelper<'a> {
+    db: &'a DiagnosticsDatabase,
+}
+
+impl<'a> ReplHelper<'a> {
+    pub fn new(db: &'a DiagnosticsDatabase) -> Self {
+        ReplHelper { db }
+    }
+
+    // Candidate values for the argument of a command
+    fn argument_candidates(&self, command: &str) -> Vec<String> {
+        match command {
+            "system" => self.db.systems(),
+            "severity" => self.db.severities(),
+            "lookup" | "parts" | "compare" | "troubleshoot" => self.db.codes(),
+            "transcript" => vec!["save".to_string()],
+            "workspace" => ["save", "load", "list"].map(str::to_string).to_vec(),
+            "garage" => ["unlink", "move"].map(str::to_string).to_vec(),
+            "analyze" => vec!["fuel-trims".to_string()],
+            "monitor" => crate::pids::PIDS.iter().map(|pid| pid.name.to_string()).collect(),
+            "help" => {
+                let mut names: Vec<String> = crate::help::INTERACTIVE_COMMANDS.iter().map(|command| command.name.to_string()).collect();
+                for topic in crate::help::TOPICS {
+                    if !names.iter().any(|name| name == topic.name) {
+                        names.push(topic.name.to_string());
+                    }
+                }
+                names
+            },
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Completer for ReplHelper<'_> {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map(|index| index + 1).unwrap_or(0);
+        let word = before[start..].to_lowercase();
+
+        let previous: Vec<&str> = before[..start].split_whitespace().collect();
+        let candidates = match previous.as_slice() {
+            [] => COMMANDS.iter().map(|command| command.to_string()).collect(),
+            [command] => self.argument_candidates(&command.to_lowercase()),
+            _ => Vec::new(),
+        };
+
+        let matches = candidates
+            .into_iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word))
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+
+        Ok((start, matches))
+    }
+}
@@ -0,0 +1,472 @@
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+use crate::adapter::AdapterSettings;
+use crate::anonymize::AnonymizeSettings;
+use crate::branding::Branding;
+use crate::drive_cycle::{DriveCycles, DRIVE_CYCLES_FILE};
+use crate::events::EventSettings;
+use crate::freshness::FreshnessSettings;
+use crate::health::HealthWeights;
+use crate::idle::InteractiveSettings;
+use crate::inspection::{InspectionTemplates, INSPECTION_TEMPLATES_FILE};
+use crate::labor::{LaborGuide, LABOR_GUIDE_FILE};
+use crate::layout::LayoutSettings;
+use crate::usage::UsageSettings;
+use crate::warranty::{ClaimTemplates, WARRANTY_TEMPLATES_FILE};
+use crate::merge::MergePolicy;
+use crate::monitor::MonitorSettings;
+use crate::net::NetworkSettings;
+use crate::presentation::Theme;
+use crate::time::TimeSettings;
+use crate::troubleshoot::{DecisionTrees, DECISION_TREES_FILE};
+use crate::Source;
+
+// Default location of the configuration file, relative to the working directory
+pub const CONFIG_FILE: &str = "cars-scanner.toml";
+
+// Default directory for data written by the tool, relative to the working directory
+pub const DATA_DIR: &str = ".cars-scanner";
+
+// Environment variables starting with this override settings; `__` separates
+// table names, so CARS_SCANNER_ADAPTER__PORT sets `port` in `[adapter]`
+pub const ENV_PREFIX: &str = "CARS_SCANNER_";
+
+// Set by --config
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+// The configuration file in use: the one given with --config, or cars-scanner.toml
+pub fn path() -> &'static Path {
+    CONFIG_PATH.get().map(PathBuf::as_path).unwrap_or(Path::new(CONFIG_FILE))
+}
+
+// User configuration; every setting is optional
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Parts lookup URL with {part_number} and {name} placeholders
+    pub parts_api_url: Option<String>,
+
+    // Labor guide CSV replacing the bundled one
+    pub labor_guide: Option<String>,
+
+    // Decision tree YAML file replacing the bundled one
+    pub decision_trees: Option<String>,
+
+    // TOML file of rules giving codes the fleet's own severities
+    pub severity_policy: Option<String>,
+
+    // Drive cycle YAML file replacing the bundled one
+    pub drive_cycles: Option<String>,
+
+    // Inspection template YAML file replacing the bundled one
+    pub inspection_templates: Option<String>,
+
+    // Template used by `inspection` without --jurisdiction, e.g. "california"
+    pub jurisdiction: Option<String>,
+
+    // Warranty claim layout YAML file replacing the bundled one
+    pub warranty_templates: Option<String>,
+
+    // Portal layout used by `report --type warranty-claim` without --oem, e.g. "generic-xml"
+    pub warranty_oem: Option<String>,
+
+    // Shop labor rate per flat-rate hour used by `quote`
+    pub labor_rate: Option<f64>,
+
+    // Directory for data written by the tool
+    pub data_dir: Option<String>,
+
+    // Code databases layered over the bundled one; later packs override earlier ones
+    pub packs: Vec<PackConfig>,
+
+    // SQLite file from `db import --sqlite` to read the codes from instead of the bundled
+    // database and packs
+    pub database: Option<String>,
+
+    // How fields are chosen when several databases define the same code
+    pub merge: MergePolicy,
+
+    // How old the database and packs may get before commands warn about them
+    pub freshness: FreshnessSettings,
+
+    // Shop name, address, logo and disclaimer printed on exported reports
+    pub branding: Branding,
+
+    // Time zone and date formats for times shown to people
+    pub time: TimeSettings,
+
+    // Proxy, rate limit, retries and caching for online lookups
+    pub network: NetworkSettings,
+
+    // Points lost per severity and system in the vehicle health score
+    pub health: HealthWeights,
+
+    // Port, speed and timeout for the OBD-II adapter
+    pub adapter: AdapterSettings,
+
+    // PIDs, interval, alert thresholds and notifications for `monitor`
+    pub monitor: MonitorSettings,
+
+    // Colors and severity markers: "default" or "colorblind"
+    pub theme: Theme,
+
+    // What --anonymize removes, hashes or masks in shared reports
+    pub anonymize: AnonymizeSettings,
+
+    // Order and visibility of a code's fields on screen and in reports
+    pub layout: LayoutSettings,
+
+    // Whether commands are recorded for `usage`
+    pub usage: UsageSettings,
+
+    // Whether scans, database changes and reports are logged, and when the log is rotated
+    pub events: EventSettings,
+
+    // Idle timeout and auto-save of interactive mode
+    pub interactive: InteractiveSettings,
+}
+
+// A `[[packs]]` entry: a CSV file in the error code database format
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackConfig {
+    pub path: String,
+
+    // Name shown as the source of its definitions; defaults to the file name
+    pub name: Option<String>,
+
+    pub version: Option<String>,
+
+    // Defaults to the pack's position in the list (1 for the first); the bundled database is 0
+    pub precedence: Option<i32>,
+
+    // Makes the pack applies to, e.g. ["Toyota", "Lexus"]; every make when empty
+    #[serde(default)]
+    pub makes: Vec<String>,
+}
+
+impl PackConfig {
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            Path::new(&self.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.clone())
+        })
+    }
+
+    // The source of this pack's definitions, given its position in the list
+    pub fn source(&self, position: usize) -> Source {
+        Source {
+            pack: self.display_name(),
+            file: self.path.clone(),
+            version: self.version.clone(),
+            precedence: self.precedence.unwrap_or(position as i32 + 1),
+        }
+    }
+}
+
+// A configuration and the layers it was built from: the defaults, the file
+// and then the environment
+pub struct Resolved {
+    pub config: Config,
+    pub path: PathBuf,
+    file: Option<Table>,
+    // (variable, key) of every environment override, in the order applied
+    overrides: Vec<(String, Vec<String>)>,
+}
+
+// One effective setting and where its value came from
+#[derive(Debug, PartialEq)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+    pub origin: String,
+}
+
+impl Resolved {
+    pub fn file_exists(&self) -> bool {
+        self.file.is_some()
+    }
+
+    // Every effective setting in key order; tables are flattened into dotted keys
+    pub fn settings(&self) -> Result<Vec<Setting>, Box<dyn Error>> {
+        let mut settings = Vec::new();
+        if let Value::Table(table) = Value::try_from(&self.config)? {
+            self.flatten(&table, &mut Vec::new(), &mut settings);
+        }
+        Ok(settings)
+    }
+
+    fn flatten(&self, table: &Table, key: &mut Vec<String>, settings: &mut Vec<Setting>) {
+        for (name, value) in table {
+            key.push(name.clone());
+            match value {
+                Value::Table(inner) if !inner.is_empty() => self.flatten(inner, key, settings),
+                Value::Table(_) => settings.push(Setting { key: key.join("."), value: "{}".to_string(), origin: self.origin(key) }),
+                value => settings.push(Setting { key: key.join("."), value: value.to_string(), origin: self.origin(key) }),
+            }
+            key.pop();
+        }
+    }
+
+    // The variable, file or "default" a setting came from
+    fn origin(&self, key: &[String]) -> String {
+        if let Some((name, _)) = self.overrides.iter().rev().find(|(_, overridden)| key.starts_with(overridden)) {
+            return name.clone();
+        }
+        let Some((last, tables)) = key.split_last() else { return "default".to_string() };
+        let mut table = self.file.as_ref();
+        for name in tables {
+            table = match table.and_then(|table| table.get(name)) {
+                Some(Value::Table(inner)) => Some(inner),
+                _ => None,
+            };
+        }
+        match table.is_some_and(|table| table.contains_key(last)) {
+            true => self.path.display().to_string(),
+            false => "default".to_string(),
+        }
+    }
+}
+
+impl Config {
+    // Load the configuration file and environment overrides, falling back to defaults
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Ok(Self::resolve()?.config)
+    }
+
+    // The configuration with its layers, for `config show --resolved`
+    pub fn resolve() -> Result<Resolved, Box<dyn Error>> {
+        if CONFIG_PATH.get().is_some() && !path().exists() {
+            return Err(format!("Configuration file {} does not exist", path().display()).into());
+        }
+        Self::resolve_from(path(), env::vars_os())
+    }
+
+    // Layer the file at `path`, when it exists, and the CARS_SCANNER_* variables among `vars` over the defaults
+    pub fn resolve_from(path: &Path, vars: impl IntoIterator<Item = (OsString, OsString)>) -> Result<Resolved, Box<dyn Error>> {
+        let content = match path.exists() {
+            true => Some(fs::read_to_string(path)?),
+            false => None,
+        };
+        let file = content
+            .as_deref()
+            .map(toml::from_str::<Table>)
+            .transpose()
+            .map_err(|e| format!("Invalid configuration in {}: {}", path.display(), e))?;
+
+        let mut merged = file.clone().unwrap_or_default();
+        let mut overrides = Vec::new();
+        // Other programs' variables may hold anything; only ours have to be text
+        let mut vars = vars
+            .into_iter()
+            .filter(|(name, _)| name.to_string_lossy().starts_with(ENV_PREFIX))
+            .map(|(name, raw)| match (name.into_string(), raw.into_string()) {
+                (Ok(name), Ok(raw)) => Ok((name, raw)),
+                (Ok(name), Err(_)) => Err(format!("Invalid configuration variable {}: not valid UTF-8", name)),
+                (Err(name), _) => Err(format!("Invalid configuration variable {}: not valid UTF-8", name.to_string_lossy())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Whole tables first, so a variable for one of their keys still applies
+        vars.sort();
+        for (name, raw) in vars {
+            let key: Vec<String> = name[ENV_PREFIX.len()..].split("__").map(str::to_lowercase).collect();
+            if key.iter().any(String::is_empty) {
+                return Err(format!("Invalid configuration variable {}: empty key", name).into());
+            }
+            set(&mut merged, &key, env_value(&raw))
+                .map_err(|e| format!("Invalid configuration variable {}: {}", name, e))?;
+            overrides.push((name, key));
+        }
+
+        let source = match overrides.is_empty() {
+            true => path.display().to_string(),
+            false => {
+                let names: Vec<&str> = overrides.iter().map(|(name, _)| name.as_str()).collect();
+                format!("{} with {}", path.display(), names.join(", "))
+            },
+        };
+        // Parse the file itself when nothing overrides it, so errors point at its lines
+        let config: Config = match (&content, overrides.is_empty()) {
+            (Some(content), true) => toml::from_str(content),
+            _ => Value::Table(merged).try_into(),
+        }
+        .map_err(|e| format!("Invalid configuration in {}: {}", source, e))?;
+        config.merge.validate()
+            .and_then(|_| config.time.validate())
+            .and_then(|_| config.monitor.validate())
+            .and_then(|_| config.anonymize.validate())
+            .and_then(|_| config.freshness.validate())
+            .and_then(|_| config.adapter.validate())
+            .and_then(|_| config.interactive.validate())
+            .map_err(|e| format!("Invalid configuration in {}: {}", source, e))?;
+        Ok(Resolved { config, path: path.to_path_buf(), file, overrides })
+    }
+
+    // Directory for data written by the tool
+    pub fn data_dir(&self) -> PathBuf {
+        PathBuf::from(self.data_dir.as_deref().unwrap_or(DATA_DIR))
+    }
+
+    // Configured network endpoints as (setting name, URL)
+    pub fn endpoints(&self) -> Vec<(&'static str, &str)> {
+        let mut endpoints = Vec::new();
+        if let Some(url) = &self.parts_api_url {
+            endpoints.push(("parts_api_url", url.as_str()));
+        }
+        if let Some(url) = &self.monitor.webhook {
+            endpoints.push(("monitor.webhook", url.as_str()));
+        }
+        endpoints
+    }
+
+    // Load the configured labor guide, or the bundled one when present
+    pub fn load_labor_guide(&self) -> Result<LaborGuide, Box<dyn Error>> {
+        match &self.labor_guide {
+            Some(path) => LaborGuide::load_from_csv(path),
+            None if Path::new(LABOR_GUIDE_FILE).exists() => LaborGuide::load_from_csv(LABOR_GUIDE_FILE),
+            None => Ok(LaborGuide::default()),
+        }
+    }
+
+    // Load the configured decision trees, or the bundled ones when present
+    pub fn load_decision_trees(&self) -> Result<DecisionTrees, Box<dyn Error>> {
+        match &self.decision_trees {
+            Some(path) => DecisionTrees::load(path),
+            None if Path::new(DECISION_TREES_FILE).exists() => DecisionTrees::load(DECISION_TREES_FILE),
+            None => Ok(DecisionTrees::default()),
+        }
+    }
+
+    // Load the configured drive cycles, or the bundled ones when present
+    pub fn load_drive_cycles(&self) -> Result<DriveCycles, Box<dyn Error>> {
+        match &self.drive_cycles {
+            Some(path) => DriveCycles::load(path),
+            None if Path::new(DRIVE_CYCLES_FILE).exists() => DriveCycles::load(DRIVE_CYCLES_FILE),
+            None => Ok(DriveCycles::default()),
+        }
+    }
+
+    // Load the configured inspection templates, or the bundled ones when present
+    pub fn load_inspection_templates(&self) -> Result<InspectionTemplates, Box<dyn Error>> {
+        match &self.inspection_templates {
+            Some(path) => InspectionTemplates::load(path),
+            None if Path::new(INSPECTION_TEMPLATES_FILE).exists() => InspectionTemplates::load(INSPECTION_TEMPLATES_FILE),
+            None => Ok(InspectionTemplates::default()),
+        }
+    }
+
+    // Load the configured warranty claim layouts, or the bundled ones when present
+    pub fn load_warranty_templates(&self) -> Result<ClaimTemplates, Box<dyn Error>> {
+        match &self.warranty_templates {
+            Some(path) => ClaimTemplates::load(path),
+            None if Path::new(WARRANTY_TEMPLATES_FILE).exists() => ClaimTemplates::load(WARRANTY_TEMPLATES_FILE),
+            None => Ok(ClaimTemplates::default()),
+        }
+    }
+
+    // Build the parts lookup URL for a part, if a template is configured
+    pub fn parts_url(&self, name: &str, number: &str) -> Option<String> {
+        self.parts_api_url.as_ref().map(|template| {
+            template
+                .replace("{part_number}", &url_encode(number))
+                .replace("{name}", &url_encode(name))
+        })
+    }
+}
+
+// An environment value read as TOML (numbers, booleans, arrays, inline tables), or else as text
+fn env_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+// Set a dotted key in a table, creating the tables on the way
+fn set(table: &mut Table, key: &[String], value: Value) -> Result<(), String> {
+    let Some((last, parents)) = key.split_last() else { return Ok(()) };
+    let mut table = table;
+    for (depth, name) in parents.iter().enumerate() {
+        table = match table.entry(name.clone()).or_insert_with(|| Value::Table(Table::new())) {
+            Value::Table(inner) => inner,
+            _ => return Err(format!("'{}' is not a table", key[..=depth].join("."))),
+        };
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+// Percent-encode a value for use in a URL query
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    #[cfg(unix)]
+    use std::os::unix::ffi::OsStringExt;
+    use std::fs;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn config_layers_environment_over_file_over_defaults() {
+        let dir = TempDir::new("config");
+        let path = dir.join("shop.toml");
+        fs::write(&path, "labor_rate = 80\n[adapter]\nbaud = 9600\n").unwrap();
+        let vars = |pairs: &[(&str, &str)]| pairs.iter().map(|(name, value)| (OsString::from(name), OsString::from(value))).collect::<Vec<_>>();
+
+        let resolved = super::Config::resolve_from(&path, vars(&[
+            ("CARS_SCANNER_ADAPTER__PORT", "/dev/ttyUSB1"),
+            ("CARS_SCANNER_LABOR_RATE", "95.5"),
+            ("CARS_SCANNER_MONITOR__PIDS", "[\"rpm\"]"),
+            ("HOME", "/root"),
+        ])).unwrap();
+        let rejected = super::Config::resolve_from(&path, vars(&[("CARS_SCANNER_ADAPTER__SPEED", "1")]));
+
+        assert_eq!(resolved.config.labor_rate, Some(95.5));
+        assert_eq!(resolved.config.adapter.baud, 9600);
+        assert_eq!(resolved.config.adapter.port.as_deref(), Some("/dev/ttyUSB1"));
+        assert_eq!(resolved.config.monitor.pids, ["rpm"]);
+        let settings = resolved.settings().unwrap();
+        let origin = |key: &str| settings.iter().find(|setting| setting.key == key).map(|setting| setting.origin.clone()).unwrap();
+        assert_eq!(origin("adapter.baud"), path.display().to_string());
+        assert_eq!(origin("adapter.port"), "CARS_SCANNER_ADAPTER__PORT");
+        assert_eq!(origin("adapter.timeout_secs"), "default");
+        assert_eq!(origin("labor_rate"), "CARS_SCANNER_LABOR_RATE");
+        assert!(rejected.err().unwrap().to_string().contains("CARS_SCANNER_ADAPTER__SPEED: unknown field `speed`"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn variables_that_are_not_text_are_ignored_unless_they_are_ours() {
+        let dir = TempDir::new("no-config");
+        let path = dir.join("cars-scanner.toml");
+        let junk = OsString::from_vec(vec![0xff]);
+
+        let resolved = super::Config::resolve_from(&path, [(OsString::from("JUNK"), junk.clone())]).unwrap();
+        let rejected = super::Config::resolve_from(&path, [(OsString::from("CARS_SCANNER_LABOR_RATE"), junk)]);
+
+        assert_eq!(resolved.config.labor_rate, None);
+        assert_eq!(rejected.err().unwrap().to_string(), "Invalid configuration variable CARS_SCANNER_LABOR_RATE: not valid UTF-8");
+    }
+}
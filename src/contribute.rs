@@ -0,0 +1,236 @@
+// Packaging local codes for review by the shared community database (`db contribute`).
+//
+// Codes added or changed in packs are compared with a baseline, the bundled
+// database unless `--against` names a fresh copy of the upstream file, and
+// written as a bundle of two files: a JSON document a maintainer can apply
+// with tooling, and a Markdown summary with one diff per edited code for the
+// review itself. Only the database columns are exported. Other pack columns,
+// such as shop notes, the pack file paths and the session notes stay local.
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use serde::Serialize;
+use crate::archive::{Clock, SystemClock};
+use crate::builder;
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::merge;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+use crate::{DiagnosticsDatabase, ErrorCode};
+
+// Identifies the JSON document; `version` changes when its layout does
+const FORMAT: &str = "cars-scanner-contribution";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Added,
+    Edited,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+// One code to submit, with the full local record so it can be applied as is
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub change: Kind,
+    pub code: String,
+    // Name of the pack the local definition comes from, when not the bundled database
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack: Option<String>,
+    pub record: Arc<ErrorCode>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bundle {
+    pub format: &'static str,
+    pub version: u32,
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub created: u64,
+    // File name of the baseline the changes were computed against
+    pub baseline: String,
+    pub changes: Vec<Change>,
+    // Codes left out because they break the database rules, with the reasons
+    #[serde(skip)]
+    pub rejected: Vec<(String, String)>,
+    // Pack columns that are not part of the database and were not exported, per pack
+    #[serde(skip)]
+    pub private_columns: Vec<(String, Vec<String>)>,
+}
+
+// The codes whose effective definition differs from the baseline, sorted by code.
+// With `packs`, only codes defined by one of those packs are considered.
+pub fn collect(db: &DiagnosticsDatabase, baseline: &DiagnosticsDatabase, packs: &[String]) -> (Vec<Change>, Vec<(String, String)>) {
+    let mut codes: Vec<&String> = db.errors.keys().collect();
+    codes.sort();
+
+    let mut changes = Vec::new();
+    let mut rejected = Vec::new();
+    for code in codes {
+        let record = &db.errors[code];
+        let pack = merge::by_precedence(db.definitions_of(code))
+            .into_iter()
+            .filter_map(|definition| definition.source.as_ref())
+            .map(|source| source.pack.clone())
+            .find(|pack| pack != "bundled");
+        if !packs.is_empty() && !pack.as_ref().is_some_and(|pack| packs.iter().any(|name| name.eq_ignore_ascii_case(pack))) {
+            continue;
+        }
+
+        let (change, fields) = match baseline.lookup_error(code) {
+            Some(upstream) => {
+                let fields: Vec<FieldChange> = merge::differences(upstream, record)
+                    .into_iter()
+                    .map(|(field, from, to)| FieldChange { field, from, to })
+                    .collect();
+                if fields.is_empty() {
+                    continue;
+                }
+                (Kind::Edited, fields)
+            },
+            None => (Kind::Added, Vec::new()),
+        };
+
+        let problems = builder::check(record);
+        if !problems.is_empty() {
+            let reasons: Vec<String> = problems.iter().map(ToString::to_string).collect();
+            rejected.push((code.clone(), reasons.join("; ")));
+            continue;
+        }
+        changes.push(Change { change, code: code.clone(), pack, record: Arc::clone(record), fields });
+    }
+    (changes, rejected)
+}
+
+// Columns of each pack file that the database format does not define
+fn private_columns(config: &Config) -> Vec<(String, Vec<String>)> {
+    let known: Vec<&str> = std::iter::once("code").chain(merge::field_names()).collect();
+    config.packs.iter()
+        .filter_map(|pack| {
+            let mut reader = csv::Reader::from_path(&pack.path).ok()?;
+            let columns: Vec<String> = reader.headers().ok()?
+                .iter()
+                .filter(|column| !known.contains(column))
+                .map(str::to_string)
+                .collect();
+            (!columns.is_empty()).then(|| (pack.display_name(), columns))
+        })
+        .collect()
+}
+
+// The review summary: counts, then each added code and a diff of each edited one
+pub fn summary(bundle: &Bundle) -> String {
+    let count = |kind: Kind| bundle.changes.iter().filter(|change| change.change == kind).count();
+    let mut text = format!("# {}\n\n", tr!("contribute-summary-title"));
+    text.push_str(&format!("{}\n", tr!(
+        "contribute-summary-counts",
+        added = count(Kind::Added),
+        edited = count(Kind::Edited),
+        baseline = bundle.baseline.as_str(),
+        created = crate::time::iso8601(bundle.created)
+    )));
+
+    let added: Vec<&Change> = bundle.changes.iter().filter(|change| change.change == Kind::Added).collect();
+    if !added.is_empty() {
+        text.push_str(&format!("\n## {}\n\n", tr!("contribute-summary-added")));
+        for change in added {
+            let record = &change.record;
+            text.push_str(&format!("- **{}** ({}, {}): {}\n", record.code, record.severity, record.system, record.description));
+        }
+    }
+
+    let edited: Vec<&Change> = bundle.changes.iter().filter(|change| change.change == Kind::Edited).collect();
+    if !edited.is_empty() {
+        text.push_str(&format!("\n## {}\n", tr!("contribute-summary-edited")));
+        for change in edited {
+            text.push_str(&format!("\n### {} {}\n\n```diff\n", change.code, change.record.description));
+            for field in &change.fields {
+                text.push_str(&format!("- {}: {}\n+ {}: {}\n", field.field, field.from, field.field, field.to));
+            }
+            text.push_str("```\n");
+        }
+    }
+
+    if !bundle.private_columns.is_empty() {
+        text.push_str(&format!("\n{}\n", tr!("contribute-summary-private")));
+        for (pack, columns) in &bundle.private_columns {
+            text.push_str(&format!("- {}: {}\n", pack, columns.join(", ")));
+        }
+    }
+    text
+}
+
+// Write the bundle to `output` and its summary next to it with a .md extension
+pub fn contribute(db: &DiagnosticsDatabase, config: &Config, against: &str, packs: &[String], output: &str) -> Result<(), Box<dyn Error>> {
+    let mut baseline = DiagnosticsDatabase::new();
+    baseline.read_csv(against).map_err(|e| format!("Could not read {}: {}", against, e))?;
+
+    let (changes, rejected) = collect(db, &baseline, packs);
+    let bundle = Bundle {
+        format: FORMAT,
+        version: VERSION,
+        created: SystemClock.now(),
+        baseline: Path::new(against).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+        changes,
+        rejected,
+        private_columns: private_columns(config),
+    };
+
+    for (code, reason) in &bundle.rejected {
+        outln!("{} {:<7} {}", "[SKIP]".styled(Style::Warning), code, reason);
+    }
+    if bundle.changes.is_empty() {
+        outln!("{}", tr!("contribute-nothing", baseline = against));
+        return Ok(());
+    }
+
+    let summary_path = Path::new(output).with_extension("md");
+    fs::write(output, serde_json::to_string_pretty(&bundle)?)?;
+    fs::write(&summary_path, summary(&bundle))?;
+    outln!("{}", tr!("contribute-written", codes = bundle.changes.len(), path = output, summary = summary_path.display()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DiagnosticsDatabase, ErrorCode};
+
+    #[test]
+    fn contribution_lists_added_and_edited_pack_codes() {
+        let baseline = DiagnosticsDatabase::from_records([
+            ErrorCode::fixture_with("P0101", "Medium", "Engine"),
+            ErrorCode::fixture_with("P0300", "High", "Engine"),
+        ]);
+        let from_pack = |error: ErrorCode| ErrorCode {
+            source: Some(std::sync::Arc::new(crate::Source { pack: "shop".to_string(), file: "shop.csv".to_string(), version: None, precedence: 1 })),
+            ..error
+        };
+        let db = DiagnosticsDatabase::from_records([
+            ErrorCode::fixture_with("P0101", "Medium", "Engine"),
+            from_pack(ErrorCode::fixture_with("P0300", "Critical", "Engine")),
+            from_pack(ErrorCode::fixture_with("P1999", "Low", "Body")),
+            from_pack(ErrorCode::fixture_with("P1998", "Bogus", "Body")),
+        ]);
+
+        let (changes, rejected) = super::collect(&db, &baseline, &[]);
+        let summary: Vec<_> = changes.iter().map(|change| (change.code.as_str(), change.change, change.pack.as_deref())).collect();
+        assert_eq!(summary, [
+            ("P0300", super::Kind::Edited, Some("shop")),
+            ("P1999", super::Kind::Added, Some("shop")),
+        ]);
+        assert_eq!(changes[0].fields, [super::FieldChange { field: "severity", from: "High".to_string(), to: "Critical".to_string() }]);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0, "P1998");
+        assert!(super::collect(&db, &baseline, &["other".to_string()]).0.is_empty());
+    }
+}
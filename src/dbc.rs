@@ -0,0 +1,214 @@
+// Manufacturer CAN signals described by a DBC file (`monitor --dbc`).
+//
+// OBD-II PIDs only cover what emissions law requires; everything else on the
+// bus (wheel speeds, steering angle, gear, oil temperature on many cars) is
+// broadcast in frames whose layout only the manufacturer's DBC file gives.
+// Only the parts needed to decode values are read: messages (`BO_`) and
+// their signals (`SG_`) with start bit, length, byte order, sign, factor,
+// offset and unit. Multiplexed signals are decoded only from frames whose
+// multiplexer signal carries their value.
+use std::error::Error;
+use std::fs;
+use std::sync::OnceLock;
+use regex::Regex;
+
+// Set on 29-bit identifiers, both in DBC message ids and SocketCAN frame ids
+pub const EXTENDED_FLAG: u32 = 0x8000_0000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Multiplex {
+    None,
+    // The signal selecting which multiplexed signals a frame carries
+    Multiplexer,
+    // Present when the multiplexer has this value
+    Value(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub name: String,
+    pub start: u32,
+    pub length: u32,
+    // Intel (little-endian) byte order; Motorola otherwise
+    pub little_endian: bool,
+    pub signed: bool,
+    pub factor: f64,
+    pub offset: f64,
+    pub unit: String,
+    pub multiplex: Multiplex,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    // The CAN identifier without the extended flag
+    pub id: u32,
+    pub extended: bool,
+    pub name: String,
+    pub signals: Vec<Signal>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Dbc {
+    pub messages: Vec<Message>,
+}
+
+fn message_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^BO_\s+(\d+)\s+(\w+)\s*:").expect("valid pattern"))
+}
+
+fn signal_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"^SG_\s+(\w+)\s*(M|m\d+)?\s*:\s*(\d+)\|(\d+)@([01])([+-])\s*\(([^,]+),([^)]+)\)\s*\[[^\]]*\]\s*"([^"]*)""#)
+            .expect("valid pattern")
+    })
+}
+
+impl Dbc {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        Ok(Self::parse(&content).map_err(|e| format!("{}: {}", path, e))?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut dbc = Dbc::default();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if let Some(captures) = message_pattern().captures(line) {
+                let raw: u32 = captures[1].parse().map_err(|_| format!("line {}: invalid message id", number + 1))?;
+                dbc.messages.push(Message {
+                    id: raw & !EXTENDED_FLAG,
+                    extended: raw & EXTENDED_FLAG != 0,
+                    name: captures[2].to_string(),
+                    signals: Vec::new(),
+                });
+            } else if let Some(captures) = signal_pattern().captures(line) {
+                let invalid = || format!("line {}: invalid signal", number + 1);
+                let message = dbc.messages.last_mut().ok_or_else(|| format!("line {}: signal outside a message", number + 1))?;
+                let multiplex = match captures.get(2).map(|found| found.as_str()) {
+                    None => Multiplex::None,
+                    Some("M") => Multiplex::Multiplexer,
+                    Some(value) => Multiplex::Value(value[1..].parse().map_err(|_| invalid())?),
+                };
+                message.signals.push(Signal {
+                    name: captures[1].to_string(),
+                    start: captures[3].parse().map_err(|_| invalid())?,
+                    length: captures[4].parse().map_err(|_| invalid())?,
+                    little_endian: &captures[5] == "1",
+                    signed: &captures[6] == "-",
+                    factor: captures[7].trim().parse().map_err(|_| invalid())?,
+                    offset: captures[8].trim().parse().map_err(|_| invalid())?,
+                    unit: captures[9].to_string(),
+                    multiplex,
+                });
+            }
+        }
+        Ok(dbc)
+    }
+
+    // A signal by name, case-insensitively, with the message carrying it
+    pub fn find(&self, name: &str) -> Option<(&Message, &Signal)> {
+        self.messages.iter().find_map(|message| {
+            message.signals.iter().find(|signal| signal.name.eq_ignore_ascii_case(name)).map(|signal| (message, signal))
+        })
+    }
+
+    // Every signal name, for error messages
+    pub fn signal_names(&self) -> Vec<&str> {
+        self.messages.iter().flat_map(|message| message.signals.iter().map(|signal| signal.name.as_str())).collect()
+    }
+}
+
+impl Message {
+    // Whether a frame id, as SocketCAN reports it, is this message
+    pub fn matches(&self, frame_id: u32) -> bool {
+        frame_id & !EXTENDED_FLAG == self.id && (frame_id & EXTENDED_FLAG != 0) == self.extended
+    }
+
+    // The value of `signal` in a frame of this message; None when the frame is too short
+    // or carries another multiplexed value
+    pub fn decode(&self, signal: &Signal, data: &[u8]) -> Option<f64> {
+        if let Multiplex::Value(wanted) = signal.multiplex {
+            let multiplexer = self.signals.iter().find(|signal| signal.multiplex == Multiplex::Multiplexer)?;
+            if multiplexer.raw(data)? != wanted {
+                return None;
+            }
+        }
+        signal.value(data)
+    }
+}
+
+impl Signal {
+    fn bit(data: &[u8], position: u32) -> Option<u64> {
+        let byte = data.get(usize::try_from(position / 8).ok()?)?;
+        Some(u64::from(byte >> (position % 8) & 1))
+    }
+
+    // The unscaled bits of the signal
+    fn raw(&self, data: &[u8]) -> Option<u64> {
+        let mut raw = 0;
+        match self.little_endian {
+            true => {
+                for index in 0..self.length {
+                    raw |= Self::bit(data, self.start + index)? << index;
+                }
+            },
+            // Motorola: the start bit is the most significant one, and the bits run down
+            // each byte, then on to the top of the next
+            false => {
+                let mut position = self.start;
+                for _ in 0..self.length {
+                    raw = raw << 1 | Self::bit(data, position)?;
+                    position = match position % 8 {
+                        0 => position + 15,
+                        _ => position - 1,
+                    };
+                }
+            },
+        }
+        Some(raw)
+    }
+
+    // The scaled value in `unit`
+    pub fn value(&self, data: &[u8]) -> Option<f64> {
+        let raw = self.raw(data)?;
+        let raw = match self.signed && (1..64).contains(&self.length) && raw >> (self.length - 1) & 1 == 1 {
+            true => raw as i64 - (1_i64 << self.length),
+            false => raw as i64,
+        };
+        Some(raw as f64 * self.factor + self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dbc, EXTENDED_FLAG};
+
+    #[test]
+    fn dbc_signals_decode_in_both_byte_orders_and_by_multiplexer() {
+        let dbc = Dbc::parse(r#"
+VERSION ""
+BO_ 2024 Engine: 8 ECU
+ SG_ EngineSpeed : 24|16@1+ (0.125,0) [0|8031.875] "rpm" Vector__XXX
+ SG_ Torque : 7|12@0- (0.5,0) [-1024|1023.5] "Nm" Vector__XXX
+BO_ 2147484000 Body: 8 BCM
+ SG_ Page M : 0|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ OilTemp m1 : 8|8@1+ (1,-40) [-40|215] "degC" Vector__XXX
+"#).unwrap();
+
+        let (engine, speed) = dbc.find("enginespeed").unwrap();
+        assert!(engine.matches(0x7E8) && !engine.matches(0x7E8 | EXTENDED_FLAG));
+        let frame = [0xFD, 0x80, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00];
+        assert_eq!(engine.decode(speed, &frame), Some(800.0));
+        let (_, torque) = dbc.find("Torque").unwrap();
+        assert_eq!(engine.decode(torque, &frame), Some(-20.0));
+        assert_eq!(engine.decode(speed, &frame[..4]), None);
+
+        let (body, oil) = dbc.find("OilTemp").unwrap();
+        assert!(body.matches(0x160 | EXTENDED_FLAG));
+        assert_eq!(body.decode(oil, &[1, 130]), Some(90.0));
+        assert_eq!(body.decode(oil, &[2, 130]), None);
+        assert!(Dbc::parse(" SG_ Orphan : 0|8@1+ (1,0) [0|1] \"\" X").is_err());
+    }
+}
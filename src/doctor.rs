@@ -0,0 +1,195 @@
+// Installation self-check run by the `doctor` command
+use std::fs;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use crate::archive::{Clock, SystemClock};
+use crate::config::{self, Config};
+use crate::freshness;
+use crate::i18n::tr;
+use crate::net;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+use crate::{DiagnosticsDatabase, DATABASE_FILE};
+
+enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+// The outcome of a single check, with a suggested fix when it did not pass
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: String, detail: String) -> Self {
+        Check { name, status: Status::Ok, detail, fix: None }
+    }
+
+    fn warning(name: String, detail: String, fix: String) -> Self {
+        Check { name, status: Status::Warning, detail, fix: Some(fix) }
+    }
+
+    fn failed(name: String, detail: String, fix: String) -> Self {
+        Check { name, status: Status::Failed, detail, fix: Some(fix) }
+    }
+}
+
+// Run every check and print the results; returns the number of failed checks
+pub fn run() -> usize {
+    let mut checks = Vec::new();
+
+    let config = match Config::load() {
+        Ok(config) => {
+            let path = config::path().display().to_string();
+            let detail = if config::path().exists() {
+                path
+            } else {
+                tr!("doctor-config-default", path = path)
+            };
+            checks.push(Check::ok(tr!("doctor-config"), detail));
+            Some(config)
+        },
+        Err(e) => {
+            checks.push(Check::failed(tr!("doctor-config"), e.to_string(), tr!("doctor-config-fix", path = config::path().display().to_string())));
+            None
+        },
+    };
+
+    checks.push(check_database());
+
+    if let Some(config) = &config {
+        checks.extend(check_packs(config));
+        checks.push(check_freshness(config));
+        checks.push(check_labor_guide(config));
+        checks.push(check_decision_trees(config));
+        checks.extend(check_logo(config));
+        checks.push(check_data_dir(config));
+        checks.extend(check_endpoints(config));
+    }
+
+    let mut failed = 0;
+    for check in &checks {
+        let status = match check.status {
+            Status::Ok => "[ OK ]".styled(Style::Success),
+            Status::Warning => "[WARN]".styled(Style::Warning),
+            Status::Failed => {
+                failed += 1;
+                "[FAIL]".styled(Style::Error)
+            },
+        };
+        outln!("{} {:<22} {}", status, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            outln!("       {} {}", tr!("doctor-fix").styled(Style::Hint), fix);
+        }
+    }
+
+    if failed == 0 {
+        outln!("\n{}", tr!("doctor-passed").styled(Style::Success));
+    } else {
+        outln!("\n{}", tr!("doctor-failed", count = failed).styled(Style::Error));
+    }
+    failed
+}
+
+fn check_database() -> Check {
+    let name = tr!("doctor-database");
+    if !Path::new(DATABASE_FILE).exists() {
+        return Check::failed(name, tr!("db-missing", path = DATABASE_FILE), tr!("doctor-database-fix"));
+    }
+
+    let mut db = DiagnosticsDatabase::new();
+    match db.read_csv(DATABASE_FILE) {
+        Ok(()) if db.errors.is_empty() => Check::warning(name, tr!("doctor-database-empty", path = DATABASE_FILE), tr!("doctor-database-fix")),
+        Ok(()) => Check::ok(name, tr!("db-loaded", count = db.errors.len())),
+        Err(e) => Check::failed(name, e.to_string(), tr!("doctor-database-invalid-fix")),
+    }
+}
+
+fn check_packs(config: &Config) -> Vec<Check> {
+    config.packs.iter().enumerate().map(|(position, pack)| {
+        let name = tr!("doctor-pack", pack = pack.display_name());
+        let mut db = DiagnosticsDatabase::new();
+        match fs::File::open(&pack.path).map_err(|e| e.into()).and_then(|file| db.read_records(file, pack.source(position))) {
+            Ok(count) => Check::ok(name, tr!("doctor-pack-loaded", count = count, path = pack.path.as_str())),
+            Err(e) => Check::failed(name, format!("{}: {}", pack.path, e), tr!("doctor-pack-fix")),
+        }
+    }).collect()
+}
+
+fn check_freshness(config: &Config) -> Check {
+    let name = tr!("doctor-freshness");
+    let now = SystemClock.now();
+    let stale = freshness::stale(config, now);
+    if stale.is_empty() {
+        return Check::ok(name, tr!("doctor-freshness-ok", max_age = config.freshness.max_age.as_str()));
+    }
+    let files: Vec<String> = stale.iter().map(|file| freshness::describe(file, now)).collect();
+    Check::warning(name, files.join("; "), tr!("doctor-freshness-fix"))
+}
+
+fn check_labor_guide(config: &Config) -> Check {
+    let name = tr!("doctor-labor");
+    match config.load_labor_guide() {
+        Ok(_) => Check::ok(name, config.labor_guide.clone().unwrap_or_else(|| crate::labor::LABOR_GUIDE_FILE.to_string())),
+        Err(e) => Check::failed(name, e.to_string(), tr!("doctor-labor-fix")),
+    }
+}
+
+fn check_decision_trees(config: &Config) -> Check {
+    let name = tr!("doctor-decision-trees");
+    match config.load_decision_trees() {
+        Ok(trees) => Check::ok(name, tr!("doctor-decision-trees-loaded", count = trees.len())),
+        Err(e) => Check::failed(name, e.to_string(), tr!("doctor-decision-trees-fix")),
+    }
+}
+
+// The logo is only checked when one is configured
+fn check_logo(config: &Config) -> Option<Check> {
+    let path = config.branding.logo.as_ref()?;
+    let name = tr!("doctor-logo");
+    Some(match config.branding.load() {
+        Ok(_) => Check::ok(name, path.clone()),
+        Err(e) => Check::failed(name, e.to_string(), tr!("doctor-logo-fix")),
+    })
+}
+
+fn check_data_dir(config: &Config) -> Check {
+    let name = tr!("doctor-data-dir");
+    let dir = config.data_dir();
+    let probe = dir.join(".doctor-write-test");
+    let result = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe));
+
+    match result {
+        Ok(()) => Check::ok(name, dir.display().to_string()),
+        Err(e) => Check::failed(name, format!("{}: {}", dir.display(), e), tr!("doctor-data-dir-fix")),
+    }
+}
+
+// Check that the host of every configured URL resolves
+fn check_endpoints(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for (key, url) in config.endpoints() {
+        let name = tr!("doctor-endpoint", key = key);
+        let Some(host) = net::url_host(url) else {
+            checks.push(Check::failed(name, url.to_string(), tr!("doctor-endpoint-url-fix", key = key)));
+            continue;
+        };
+
+        if net::is_offline() {
+            checks.push(Check::ok(name, tr!("doctor-endpoint-offline", host = host.as_str())));
+            continue;
+        }
+        match (host.as_str(), 443).to_socket_addrs() {
+            Ok(_) => checks.push(Check::ok(name, host)),
+            Err(e) => checks.push(Check::warning(name, format!("{}: {}", host, e), tr!("doctor-endpoint-fix"))),
+        }
+    }
+    checks
+}
@@ -0,0 +1,153 @@
+// Drive cycles that complete readiness monitors after codes are cleared.
+//
+// Steps are read from a YAML file keyed by monitor (see readiness.rs), with
+// generic steps for every monitor and make-specific ones where the
+// manufacturer documents its own cycle. When an adapter is connected, `watch`
+// re-reads the readiness monitors while the vehicle is driven and reports
+// each one as it completes.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+use serde::Deserialize;
+use crate::adapter::Adapter;
+use crate::i18n::tr;
+use crate::interrupt;
+use crate::monitor;
+use crate::presentation::{Style, Styled};
+use crate::readiness::{self, Monitor, Readiness};
+use crate::transcript::outln;
+
+// Default location of the bundled drive cycles
+pub const DRIVE_CYCLES_FILE: &str = "src/data/drive_cycles.yaml";
+
+// Time between readiness reads while watching
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DriveCycles {
+    // Conditions to meet before any drive cycle
+    pub preconditions: Vec<String>,
+    monitors: BTreeMap<String, MonitorCycle>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MonitorCycle {
+    generic: Vec<String>,
+    #[serde(default)]
+    makes: BTreeMap<String, Vec<String>>,
+}
+
+// The steps for one monitor, and the make they were written for
+pub struct Steps<'a> {
+    pub make: Option<&'a str>,
+    pub steps: &'a [String],
+}
+
+impl DriveCycles {
+    // Load and check a drive cycle file
+    pub fn load(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("Could not open drive cycles {}: {}", file_path, e))?;
+        let cycles: DriveCycles = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid drive cycles in {}: {}", file_path, e))?;
+
+        for (id, cycle) in &cycles.monitors {
+            if readiness::find(id).is_none() {
+                return Err(format!("Drive cycles in {}: unknown monitor '{}'", file_path, id).into());
+            }
+            if cycle.generic.is_empty() {
+                return Err(format!("Drive cycle for {} in {} has no generic steps", id, file_path).into());
+            }
+        }
+        Ok(cycles)
+    }
+
+    // The make's own steps for a monitor when there are any, otherwise the generic ones
+    pub fn steps(&self, monitor: &Monitor, make: Option<&str>) -> Option<Steps<'_>> {
+        let cycle = self.monitors.get(monitor.id)?;
+        let specific = make.and_then(|make| cycle.makes.iter().find(|(name, _)| name.eq_ignore_ascii_case(make)));
+        Some(match specific {
+            Some((name, steps)) => Steps { make: Some(name), steps },
+            None => Steps { make: None, steps: &cycle.generic },
+        })
+    }
+}
+
+// Print the preconditions and the steps for each monitor
+pub fn display(cycles: &DriveCycles, monitors: &[&'static Monitor], make: Option<&str>) {
+    if !cycles.preconditions.is_empty() {
+        outln!("{}", tr!("drive-cycle-preconditions").styled(Style::Heading));
+        for condition in &cycles.preconditions {
+            outln!("  - {}", condition);
+        }
+    }
+    let mut separate = !cycles.preconditions.is_empty();
+    for &monitor in monitors {
+        if separate {
+            outln!();
+        }
+        separate = true;
+        let Some(steps) = cycles.steps(monitor, make) else {
+            outln!("{}", monitor.name.styled(Style::Heading));
+            outln!("  {}", tr!("drive-cycle-no-steps").styled(Style::Muted));
+            continue;
+        };
+        match steps.make {
+            Some(make) => outln!("{} {}", monitor.name.styled(Style::Heading), tr!("drive-cycle-make", make = make).styled(Style::Muted)),
+            None => outln!("{} {}", monitor.name.styled(Style::Heading), tr!("drive-cycle-generic").styled(Style::Muted)),
+        }
+        for (index, step) in steps.steps.iter().enumerate() {
+            outln!("  {}. {}", index + 1, step);
+        }
+    }
+}
+
+// Print the MIL, stored code count and the state of every supported monitor
+pub fn display_readiness(readiness: &Readiness) {
+    let mil = match readiness.mil {
+        true => tr!("readiness-mil-on").styled(Style::Error),
+        false => tr!("readiness-mil-off").styled(Style::Success),
+    };
+    outln!("{}", tr!("readiness-header", complete = readiness.complete_count(), total = readiness.monitors.len(), mil = mil, codes = readiness.stored_codes).styled(Style::Heading));
+    for status in &readiness.monitors {
+        let state = match status.complete {
+            true => tr!("readiness-complete").styled(Style::Success),
+            false => tr!("readiness-incomplete").styled(Style::Warning),
+        };
+        outln!("  {:<26} {}", status.monitor.name, state);
+    }
+}
+
+// Re-read readiness until every monitor completes or Enter is pressed
+pub fn watch(adapter: &mut Adapter, mut readiness: Readiness) -> Result<Readiness, Box<dyn Error>> {
+    outln!("{}", tr!("drive-cycle-watching", seconds = WATCH_INTERVAL.as_secs()));
+    let stop = monitor::stop_on_enter();
+    while readiness.incomplete().next().is_some() && !stop.requested() {
+        stop.wait(WATCH_INTERVAL);
+        if stop.requested() {
+            break;
+        }
+        let latest = match readiness::read(adapter) {
+            Err(_) if stop.requested() => break,
+            result => result?,
+        };
+        for monitor in readiness.incomplete() {
+            if !latest.incomplete().any(|pending| pending == monitor) {
+                outln!("{}", tr!("drive-cycle-completed", monitor = monitor.name).styled(Style::Success));
+            }
+        }
+        readiness = latest;
+    }
+    if interrupt::interrupted() {
+        adapter.close();
+        interrupt::handled();
+    }
+    match readiness.incomplete().next() {
+        None => outln!("{}", tr!("drive-cycle-all-complete").styled(Style::Success)),
+        Some(_) => outln!("{}", tr!("drive-cycle-stopped", remaining = readiness.incomplete().count())),
+    }
+    Ok(readiness)
+}
@@ -0,0 +1,499 @@
+// An ELM327 attached to a scripted vehicle (`emulate`), for testing end to end.
+//
+// `Elm327` takes commands byte by byte and produces what a real adapter
+// would print, prompt included, so it can stand behind a TCP port, a pseudo
+// terminal, the `sim:` port or, in tests, directly behind `Adapter`. The
+// vehicle is a `Scenario` (see scenario.rs), played from the moment the
+// adapter is created. It answers on CAN 11/500 from a single engine ECU
+// (7E8), in single or multi-frame ISO-TP messages, with the AT settings for
+// echo, line feeds, spaces and headers that OBD tools change. Requests sent
+// with ATSH to another ECU's address than 7E0 or the functional 7DF go
+// unanswered, as no such ECU is on the bus. Mode 04 clears
+// the stored and pending codes, the freeze frame and the MIL as the scenario's
+// `clear` settings say; permanent codes stay, as on a real vehicle.
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+use crate::adapter;
+use crate::i18n::tr;
+use crate::pids;
+use crate::presentation::{Style, Styled};
+use crate::readiness::{self, MonitorStatus, Readiness};
+use crate::scenario::{FreezeFrame, Scenario, Signal};
+use crate::transcript::outln;
+
+const VERSION: &str = "ELM327 v1.5";
+const PROTOCOL: &str = "ISO 15765-4 (CAN 11/500)";
+const HEADER: &str = "7E8";
+// Addresses the engine ECU takes requests on: its own and the functional one every ECU listens to
+const REQUEST_ADDRESSES: &[&str] = &["7E0", "7DF"];
+// Filler after the data of a short CAN frame
+const PADDING: u8 = 0x55;
+// Monitors a spark-ignition engine commonly supports; the scenario's `incomplete` ones are added
+const SUPPORTED_MONITORS: &[&str] = &["misfire", "fuel-system", "components", "catalyst", "evap", "o2-sensor", "o2-heater"];
+
+// Bitmap of the PIDs among `numbers` in the 32 after `base`, including the next range when it has any
+fn bitmap(numbers: &[u8], base: u8) -> [u8; 4] {
+    let mut bitmap = [0; 4];
+    for &number in numbers {
+        if number > base && number <= base.saturating_add(0x20) {
+            let bit = usize::from(number - base - 1);
+            bitmap[bit / 8] |= 0x80 >> (bit % 8);
+        }
+    }
+    if numbers.iter().any(|&number| number > base.saturating_add(0x20)) {
+        bitmap[3] |= 0x01;
+    }
+    bitmap
+}
+
+// The adapter's state: its AT settings and the vehicle as changed by earlier commands
+pub struct Elm327 {
+    vehicle: Scenario,
+    // Time since the simulation started
+    clock: Box<dyn Fn() -> Duration + Send>,
+    // When codes were last cleared, in seconds into the simulation; faults are timed from it
+    cleared_at: f64,
+    // Faults that have set since then, by index
+    fired: Vec<usize>,
+    echo: bool,
+    linefeeds: bool,
+    spaces: bool,
+    headers: bool,
+    // Address set with ATSH; None for the functional address
+    request_address: Option<String>,
+    command: String,
+    output: Vec<u8>,
+}
+
+// Split a message into the data bytes of its ISO-TP frames, padded to eight
+fn frames(message: &[u8]) -> Vec<Vec<u8>> {
+    let pad = |mut frame: Vec<u8>| {
+        frame.resize(8, PADDING);
+        frame
+    };
+    if message.len() <= 7 {
+        return vec![pad([&[message.len() as u8], message].concat())];
+    }
+    let (first, rest) = message.split_at(6);
+    let length = message.len().min(0xFFF) as u16;
+    let mut frames = vec![[&[0x10 | (length >> 8) as u8, length as u8], first].concat()];
+    for (index, chunk) in rest.chunks(7).enumerate() {
+        frames.push(pad([&[0x20 | ((index + 1) % 16) as u8], chunk].concat()));
+    }
+    frames
+}
+
+impl Elm327 {
+    pub fn new(vehicle: Scenario) -> Self {
+        let started = Instant::now();
+        Self::with_clock(vehicle, move || started.elapsed())
+    }
+
+    // An adapter whose simulation time comes from `clock`, for tests
+    pub fn with_clock(vehicle: Scenario, clock: impl Fn() -> Duration + Send + 'static) -> Self {
+        Elm327 {
+            vehicle,
+            clock: Box::new(clock),
+            cleared_at: 0.0,
+            fired: Vec::new(),
+            echo: true,
+            linefeeds: false,
+            spaces: true,
+            headers: false,
+            request_address: None,
+            command: String::new(),
+            output: Vec::new(),
+        }
+    }
+
+    fn secs(&self) -> f64 {
+        (self.clock)().as_secs_f64()
+    }
+
+    // Set the faults that are due; the first stored code also gets a freeze frame of the current values
+    fn update(&mut self) {
+        let now = self.secs();
+        for (index, fault) in self.vehicle.faults.iter().enumerate() {
+            if self.fired.contains(&index) || now - self.cleared_at < fault.after_secs {
+                continue;
+            }
+            self.fired.push(index);
+            let codes = match fault.pending {
+                true => &mut self.vehicle.pending,
+                false => &mut self.vehicle.stored,
+            };
+            if !codes.contains(&fault.code) {
+                codes.push(fault.code.clone());
+            }
+            if !fault.pending && self.vehicle.freeze_frame.is_none() {
+                let pids = self.vehicle.pids.iter().map(|(name, signal)| (name.clone(), signal.value(now, 0))).collect();
+                self.vehicle.freeze_frame = Some(FreezeFrame { code: fault.code.clone(), pids });
+            }
+        }
+    }
+
+    // Mode 04, as the scenario's `clear` settings say
+    fn clear(&mut self) -> Vec<u8> {
+        if self.vehicle.clear.refuse {
+            // Negative response: conditions not correct
+            return vec![0x7F, 0x04, 0x22];
+        }
+        self.vehicle.stored.clear();
+        self.vehicle.pending.clear();
+        self.vehicle.freeze_frame = None;
+        self.vehicle.mil = None;
+        // The distance and time counters start again, for the codes and the MIL alike
+        for (name, signal) in self.vehicle.pids.iter_mut() {
+            if pids::find(name).is_some_and(|pid| matches!(pid.number, 0x21 | 0x31 | 0x4D | 0x4E)) {
+                *signal = Signal::Constant(0.0);
+            }
+        }
+        if self.vehicle.clear.reset_monitors {
+            self.vehicle.incomplete = SUPPORTED_MONITORS.iter().map(|name| name.to_string()).collect();
+        }
+        if self.vehicle.clear.faults_return {
+            self.cleared_at = self.secs();
+            self.fired.clear();
+        }
+        vec![0x44]
+    }
+
+    // The value of a PID now, from the scenario's signal for it
+    fn pid_value(&self, number: u8) -> Option<(&'static pids::Pid, f64)> {
+        let (name, signal) = self.vehicle.pids.iter().find(|(name, _)| pids::find(name).is_some_and(|pid| pid.number == number))?;
+        Some((pids::find(name)?, signal.value(self.secs(), u64::from(number))))
+    }
+
+    // Mode 02 for frame 0: the code that caused the freeze frame, or a PID kept with it
+    fn freeze_frame(&self, number: u8) -> Option<Vec<u8>> {
+        let frame = self.vehicle.freeze_frame.as_ref()?;
+        if number == 0x00 {
+            let numbers: Vec<u8> = frame.pids.keys().filter_map(|name| pids::find(name)).map(|pid| pid.number).chain([0x02]).collect();
+            return Some([&[0x42, 0x00, 0x00][..], &bitmap(&numbers, 0)].concat());
+        }
+        if number == 0x02 {
+            return Some([&[0x42, 0x02, 0x00][..], &adapter::encode_dtc(&frame.code)?].concat());
+        }
+        let (name, value) = frame.pids.iter().find(|(name, _)| pids::find(name).is_some_and(|pid| pid.number == number))?;
+        Some([vec![0x42, number, 0x00], pids::find(name)?.encode(*value)].concat())
+    }
+
+    // Back to power-on settings, as after ATZ or a new connection; the vehicle keeps its state
+    pub fn reset(&mut self) {
+        self.echo = true;
+        self.linefeeds = false;
+        self.spaces = true;
+        self.headers = false;
+        self.request_address = None;
+        self.command.clear();
+    }
+
+    fn hex(&self, bytes: &[u8]) -> String {
+        let separator = if self.spaces { " " } else { "" };
+        bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(separator)
+    }
+
+    // The lines an ELM327 prints for an ECU's message, with or without CAN headers
+    fn message(&self, message: &[u8]) -> Vec<String> {
+        let frames = frames(message);
+        let separator = if self.spaces { " " } else { "" };
+        match (self.headers, frames.len()) {
+            (true, _) => frames.iter().map(|frame| format!("{}{}{}", HEADER, separator, self.hex(frame))).collect(),
+            (false, 1) => vec![self.hex(message)],
+            // Without headers, a multi-frame message is shown as its length, then numbered lines
+            (false, _) => {
+                let (first, rest) = message.split_at(6);
+                let chunks = std::iter::once(first).chain(rest.chunks(7));
+                let numbered = chunks.enumerate().map(|(index, chunk)| format!("{:X}:{}{}", index % 16, separator, self.hex(chunk)));
+                std::iter::once(format!("{:03X}", message.len())).chain(numbered).collect()
+            },
+        }
+    }
+
+    fn mil(&self) -> bool {
+        self.vehicle.mil.unwrap_or(!self.vehicle.stored.is_empty())
+    }
+
+    fn readiness(&self) -> Readiness {
+        let mut monitors: Vec<MonitorStatus> = Vec::new();
+        let names = SUPPORTED_MONITORS.iter().copied().chain(self.vehicle.incomplete.iter().map(String::as_str));
+        for monitor in names.filter_map(readiness::find) {
+            if !monitors.iter().any(|status| status.monitor == monitor) {
+                let complete = !self.vehicle.incomplete.iter().any(|name| name.eq_ignore_ascii_case(monitor.id));
+                monitors.push(MonitorStatus { monitor, complete });
+            }
+        }
+        Readiness { mil: self.mil(), stored_codes: self.vehicle.stored.len() as u8, compression: false, monitors }
+    }
+
+    // Bitmap of the mode 01 PIDs supported in the 32 after `base`
+    fn supported(&self, base: u8) -> [u8; 4] {
+        let numbers: Vec<u8> = self.vehicle.pids.keys().filter_map(|name| pids::find(name)).map(|pid| pid.number).chain([0x01]).collect();
+        bitmap(&numbers, base)
+    }
+
+    fn codes(&self, mode: u8) -> Vec<u8> {
+        let codes = match mode {
+            0x03 => &self.vehicle.stored,
+            0x07 => &self.vehicle.pending,
+            _ => &self.vehicle.permanent,
+        };
+        let mut message = vec![mode + 0x40, codes.len() as u8];
+        message.extend(codes.iter().filter_map(|code| adapter::encode_dtc(code)).flatten());
+        message
+    }
+
+    // The ECU's answer to an OBD request, or None for NO DATA
+    fn obd(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        self.update();
+        match *request {
+            [0x01, pid] if pid % 0x20 == 0 && pid <= 0x40 => Some([&[0x41, pid][..], &self.supported(pid)].concat()),
+            [0x01, 0x01] => Some([&[0x41, 0x01][..], &self.readiness().encode()].concat()),
+            [0x01, number] => {
+                let (pid, value) = self.pid_value(number)?;
+                Some([vec![0x41, number], pid.encode(value)].concat())
+            },
+            [0x02, number, 0x00] => self.freeze_frame(number),
+            [mode @ (0x03 | 0x07 | 0x0A)] => Some(self.codes(mode)),
+            [0x04] => Some(self.clear()),
+            // PID 02, the VIN, when the vehicle has one
+            [0x09, 0x00] => Some(vec![0x49, 0x00, 0x40 * u8::from(self.vehicle.vin.is_some()), 0x00, 0x00, 0x00]),
+            [0x09, 0x02] => {
+                let vin = self.vehicle.vin.as_ref()?;
+                Some([&[0x49, 0x02, 0x01][..], vin.as_bytes()].concat())
+            },
+            _ => None,
+        }
+    }
+
+    // The lines printed for one command
+    fn respond(&mut self, command: &str) -> Vec<String> {
+        let command = command.replace(' ', "").to_ascii_uppercase();
+        let ok = || vec!["OK".to_string()];
+        if self.vehicle.unsupported.iter().any(|unsupported| unsupported.replace(' ', "").eq_ignore_ascii_case(&command)) {
+            return vec!["?".to_string()];
+        }
+        if let Some(setting) = command.strip_prefix("AT") {
+            let flag = setting.ends_with('1');
+            return match setting {
+                "Z" | "WS" => {
+                    self.reset();
+                    vec![String::new(), String::new(), VERSION.to_string()]
+                },
+                "I" => vec![VERSION.to_string()],
+                "@1" => vec!["OBDII to RS232 Interpreter".to_string()],
+                "RV" => vec![format!("{:.1}V", self.vehicle.battery)],
+                "DP" => vec![format!("AUTO, {}", PROTOCOL)],
+                "DPN" => vec!["A6".to_string()],
+                "D" => {
+                    self.reset();
+                    ok()
+                },
+                "E0" | "E1" => {
+                    self.echo = flag;
+                    ok()
+                },
+                "L0" | "L1" => {
+                    self.linefeeds = flag;
+                    ok()
+                },
+                "S0" | "S1" => {
+                    self.spaces = flag;
+                    ok()
+                },
+                "H0" | "H1" => {
+                    self.headers = flag;
+                    ok()
+                },
+                _ if setting.starts_with("SH") => {
+                    self.request_address = Some(setting["SH".len()..].to_string());
+                    ok()
+                },
+                // Protocol, timing and formatting settings that change nothing here
+                _ if !setting.is_empty() => ok(),
+                _ => vec!["?".to_string()],
+            };
+        }
+
+        let request = command.as_bytes().chunks(2).map(|pair| std::str::from_utf8(pair).ok().and_then(|pair| u8::from_str_radix(pair, 16).ok()));
+        let request: Option<Vec<u8>> = request.collect();
+        match request.filter(|request| !request.is_empty() && command.len().is_multiple_of(2)) {
+            Some(_) if self.request_address.as_ref().is_some_and(|address| !REQUEST_ADDRESSES.contains(&address.as_str())) => vec!["NO DATA".to_string()],
+            Some(request) => match self.obd(&request) {
+                Some(message) => self.message(&message),
+                None => vec!["NO DATA".to_string()],
+            },
+            None => vec!["?".to_string()],
+        }
+    }
+
+    // Take bytes sent to the adapter; the answers are read back with `Read`
+    pub fn receive(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            match byte {
+                b'\r' => {
+                    let command = std::mem::take(&mut self.command);
+                    let command = command.trim();
+                    let newline = if self.linefeeds { "\r\n" } else { "\r" };
+                    let mut lines = Vec::new();
+                    if self.echo {
+                        lines.push(command.to_string());
+                    }
+                    // An empty line repeats nothing here, but still gets a prompt
+                    if !command.is_empty() {
+                        lines.extend(self.respond(command));
+                    }
+                    let text = format!("{}{}{}>", lines.join(newline), newline, newline);
+                    self.output.extend_from_slice(text.as_bytes());
+                },
+                b'\n' | b'\0' => {},
+                _ => self.command.push(char::from(byte)),
+            }
+        }
+    }
+}
+
+impl Write for Elm327 {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.receive(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Elm327 {
+    // Like a serial port with a timeout: nothing to read is an error the adapter retries
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_empty() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        let count = buffer.len().min(self.output.len());
+        buffer[..count].copy_from_slice(&self.output[..count]);
+        self.output.drain(..count);
+        Ok(count)
+    }
+}
+
+// Answer one client until it disconnects, showing each command it sends
+fn serve(elm: &mut Elm327, stream: &mut (impl Read + Write)) -> io::Result<()> {
+    let mut buffer = [0u8; 256];
+    loop {
+        let count = match stream.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(count) => count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for command in String::from_utf8_lossy(&buffer[..count]).split('\r').map(str::trim).filter(|command| !command.is_empty()) {
+            outln!("{} {}", ">".styled(Style::Muted), command);
+        }
+        elm.receive(&buffer[..count]);
+        let output = std::mem::take(&mut elm.output);
+        stream.write_all(&output)?;
+        stream.flush()?;
+    }
+}
+
+// Listen on host:port, serving one client at a time until interrupted
+pub fn listen(address: &str, scenario: Scenario) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(address).map_err(|e| format!("Could not listen on {}: {}", address, e))?;
+    let address = listener.local_addr()?.to_string();
+    outln!("{}", tr!("emulate-listening", address = address.as_str()).styled(Style::Success));
+    let mut elm = Elm327::new(scenario);
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        outln!("{}", tr!("emulate-connected", peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_default()));
+        elm.reset();
+        if let Err(e) = serve(&mut elm, &mut stream) {
+            outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e);
+        }
+        outln!("{}", tr!("emulate-disconnected"));
+    }
+    Ok(())
+}
+
+// Open a pseudo terminal that tools can use as a serial port, serving it until interrupted
+#[cfg(target_os = "linux")]
+pub fn pty(scenario: Scenario) -> Result<(), Box<dyn Error>> {
+    use std::ffi::CStr;
+    use std::fs::File;
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    // SAFETY: plain libc calls on descriptors this function owns; ptsname_r writes a
+    // NUL-terminated path into the buffer it is given
+    let (master, _slave, path) = unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        if master < 0 || libc::grantpt(master) != 0 || libc::unlockpt(master) != 0 {
+            return Err(format!("Could not open a pseudo terminal: {}", io::Error::last_os_error()).into());
+        }
+        let master = OwnedFd::from_raw_fd(master);
+        let mut name = [0 as libc::c_char; 128];
+        if libc::ptsname_r(std::os::fd::AsRawFd::as_raw_fd(&master), name.as_mut_ptr(), name.len()) != 0 {
+            return Err(format!("Could not name the pseudo terminal: {}", io::Error::last_os_error()).into());
+        }
+        let path = CStr::from_ptr(name.as_ptr()).to_string_lossy().to_string();
+
+        // Hold the terminal open in raw mode, so it neither echoes nor hangs up between clients
+        let slave = libc::open(name.as_ptr(), libc::O_RDWR | libc::O_NOCTTY);
+        if slave < 0 {
+            return Err(format!("Could not open {}: {}", path, io::Error::last_os_error()).into());
+        }
+        let slave = OwnedFd::from_raw_fd(slave);
+        let mut settings: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(std::os::fd::AsRawFd::as_raw_fd(&slave), &mut settings) == 0 {
+            libc::cfmakeraw(&mut settings);
+            libc::tcsetattr(std::os::fd::AsRawFd::as_raw_fd(&slave), libc::TCSANOW, &settings);
+        }
+        (master, slave, path)
+    };
+
+    outln!("{}", tr!("emulate-listening", address = path.as_str()).styled(Style::Success));
+    let mut elm = Elm327::new(scenario);
+    serve(&mut elm, &mut File::from(master))?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pty(_scenario: Scenario) -> Result<(), Box<dyn Error>> {
+    Err("Pseudo terminals are only available on Linux; use --listen".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::adapter::Adapter;
+    use crate::scenario::Scenario;
+    use super::Elm327;
+
+    #[test]
+    fn emulator_answers_the_adapter_layer_end_to_end() {
+        let scenario: Scenario = serde_yaml::from_str("
+vin: 1FTFW1ET5DFC10312
+stored: [P0420, P0171, P0300]
+permanent: [P0420]
+incomplete: [catalyst]
+pids: { rpm: 812, coolant: 71 }
+").unwrap();
+        scenario.validate().unwrap();
+        let mut adapter = Adapter::initialize(Box::new(Elm327::new(scenario)), "emulator", Duration::from_secs(1)).unwrap();
+        assert_eq!(adapter.ecus, ["7E8"]);
+        assert_eq!(adapter.read_vin().unwrap().as_deref(), Some("1FTFW1ET5DFC10312"));
+        assert_eq!(adapter.read_codes(0x03).unwrap(), ["P0420", "P0171", "P0300"]);
+        assert_eq!(adapter.read_pid(crate::pids::find("rpm").unwrap()).unwrap(), Some(812.0));
+        assert_eq!(adapter.read_pid(crate::pids::find("oil").unwrap()).unwrap(), None);
+        let status = crate::readiness::read(&mut adapter).unwrap();
+        assert!(status.mil);
+        assert_eq!(status.incomplete().map(|monitor| monitor.id).collect::<Vec<_>>(), ["catalyst"]);
+
+        // Clearing keeps the permanent code and turns the MIL off
+        adapter.request("04").unwrap();
+        assert!(adapter.read_codes(0x03).unwrap().is_empty());
+        assert_eq!(adapter.read_codes(0x0A).unwrap(), ["P0420"]);
+        assert!(!crate::readiness::read(&mut adapter).unwrap().mil);
+    }
+}
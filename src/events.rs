@@ -0,0 +1,427 @@
+// The event log: every scan, clear, database change and report (`events`).
+//
+// When a laptop dies in the middle of a job, the shop wants to know what was
+// read and what was written before it did; an auditor wants to know who
+// changed the database and when; an analytics pipeline wants a feed of
+// scans. Each of these is appended as one line of JSON to
+// <data_dir>/events.jsonl, numbered in order and written with a single
+// write that is flushed to disk before the command goes on. A line cut
+// short by a crash is skipped when the log is read, and the next event
+// starts on a line of its own, so nothing before or after it is lost.
+//
+// The log is rotated when it grows past max_size: events.jsonl becomes
+// events.1.jsonl, the one before that events.2.jsonl, and the oldest
+// beyond `keep` is deleted. Numbering carries on across rotations.
+//
+//   [events]
+//   record = true
+//   max_size = 10000000   # bytes
+//   keep = 5
+//
+// `events tail` shows the latest events and, with --follow, those recorded
+// from then on; `events query` picks events by kind, action, VIN, code and
+// age from every file. Both print the lines as written with --jsonl.
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use crate::archive::{Clock, SystemClock};
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+use crate::{interrupt, time};
+
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+// How often `events tail --follow` looks for new events
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+// Bytes read from the end of the log to find the last event's number
+const TAIL_BYTES: u64 = 64 * 1024;
+
+// The `[events]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventSettings {
+    pub record: bool,
+    // Size in bytes past which the log is rotated
+    pub max_size: u64,
+    // Rotated files kept besides the current one
+    pub keep: usize,
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        EventSettings { record: true, max_size: 10_000_000, keep: 5 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    // A scan stored in the archive
+    Scan,
+    // A code database or pack changed by a `db` command
+    Database,
+    // A report or export written to a file
+    Report,
+    // Trouble codes cleared on a vehicle
+    Clear,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Scan => "scan",
+            Kind::Database => "database",
+            Kind::Report => "report",
+            Kind::Clear => "clear",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    // Position in the log, from 1; given when the event is appended
+    pub seq: u64,
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub timestamp: u64,
+    pub kind: Kind,
+    // E.g. "fleet-import", "db import" or "warranty-claim"
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vin: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codes: Vec<String>,
+    // The file written or changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    // E.g. the id of an archived scan or the counts of an import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Event {
+    pub fn new(kind: Kind, action: &str) -> Self {
+        Event {
+            seq: 0,
+            timestamp: SystemClock.now(),
+            kind,
+            action: action.to_string(),
+            vin: None,
+            codes: Vec::new(),
+            path: None,
+            detail: None,
+        }
+    }
+
+    pub fn vin(mut self, vin: Option<&str>) -> Self {
+        self.vin = vin.map(str::to_string);
+        self
+    }
+
+    pub fn codes(mut self, codes: &[String]) -> Self {
+        self.codes = codes.to_vec();
+        self
+    }
+
+    pub fn path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().display().to_string());
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+pub struct Log {
+    dir: PathBuf,
+    max_size: u64,
+    keep: usize,
+    // Number of the next event, found on the first append
+    next: Option<u64>,
+}
+
+// The events of complete lines in `bytes`; a line cut short or not JSON is skipped
+fn parse(bytes: &[u8]) -> Vec<Event> {
+    String::from_utf8_lossy(bytes).lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+impl Log {
+    pub fn open(data_dir: &Path, settings: &EventSettings) -> Self {
+        Log { dir: data_dir.to_path_buf(), max_size: settings.max_size, keep: settings.keep, next: None }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(EVENTS_FILE)
+    }
+
+    // events.1.jsonl, the newest rotated file, and so on
+    fn rotated(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("events.{}.jsonl", index))
+    }
+
+    // The files of the log, oldest first
+    fn files(&self) -> Vec<PathBuf> {
+        (1..=self.keep).rev().map(|index| self.rotated(index)).chain([self.path()]).filter(|path| path.is_file()).collect()
+    }
+
+    // The number of the last event in a file, from its last complete line
+    fn last_seq(path: &Path) -> Result<Option<u64>, Box<dyn Error>> {
+        let Ok(mut file) = File::open(path) else { return Ok(None) };
+        let length = file.metadata()?.len();
+        file.seek(SeekFrom::Start(length.saturating_sub(TAIL_BYTES)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        Ok(parse(&tail).last().map(|event| event.seq))
+    }
+
+    fn rotate(&self) -> Result<(), Box<dyn Error>> {
+        if self.keep == 0 {
+            fs::remove_file(self.path())?;
+            return Ok(());
+        }
+        let _ = fs::remove_file(self.rotated(self.keep));
+        for index in (1..self.keep).rev() {
+            if self.rotated(index).is_file() {
+                fs::rename(self.rotated(index), self.rotated(index + 1))?;
+            }
+        }
+        fs::rename(self.path(), self.rotated(1))?;
+        Ok(())
+    }
+
+    // Number the event and add it to the log, rotating it first when it is full; returns the number
+    pub fn append(&mut self, mut event: Event) -> Result<u64, Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path();
+        let next = match self.next {
+            Some(next) => next,
+            None => Self::last_seq(&path)?.or(Self::last_seq(&self.rotated(1))?).unwrap_or_default() + 1,
+        };
+        let length = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or_default();
+        if length > 0 && length >= self.max_size {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&path)?;
+        // A line left unfinished by a crash must not swallow this one
+        let mut last = [b'\n'];
+        if file.metadata()?.len() > 0 {
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+        }
+        event.seq = next;
+        let mut line = match last[0] {
+            b'\n' => String::new(),
+            _ => "\n".to_string(),
+        };
+        line.push_str(&serde_json::to_string(&event)?);
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        self.next = Some(next + 1);
+        Ok(next)
+    }
+
+    // Every event in the log, oldest first
+    pub fn read(&self) -> Result<Vec<Event>, Box<dyn Error>> {
+        let mut events = Vec::new();
+        for path in self.files() {
+            let bytes = fs::read(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            events.extend(parse(&bytes));
+        }
+        Ok(events)
+    }
+
+    // The events of the complete lines written to the current file after
+    // `offset`, and the offset after them; a rotation is followed into
+    // events.1.jsonl so the events written just before it are not missed
+    fn read_from(&self, offset: u64) -> Result<(Vec<Event>, u64), Box<dyn Error>> {
+        let read_complete = |path: &Path, offset: u64| -> Result<(Vec<Event>, u64), Box<dyn Error>> {
+            let Ok(mut file) = File::open(path) else { return Ok((Vec::new(), 0)) };
+            file.seek(SeekFrom::Start(offset))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            let complete = bytes.iter().rposition(|byte| *byte == b'\n').map_or(0, |end| end + 1);
+            Ok((parse(&bytes[..complete]), offset + complete as u64))
+        };
+        let length = fs::metadata(self.path()).map(|metadata| metadata.len()).unwrap_or_default();
+        if length >= offset {
+            return read_complete(&self.path(), offset);
+        }
+        let (mut events, _) = read_complete(&self.rotated(1), offset)?;
+        let (current, offset) = read_complete(&self.path(), 0)?;
+        events.extend(current);
+        Ok((events, offset))
+    }
+}
+
+static LOG: OnceLock<Mutex<Log>> = OnceLock::new();
+
+// Log events to <data_dir>/events.jsonl from now on, unless recording is off; the first call wins
+pub fn init(settings: &EventSettings, data_dir: &Path) {
+    if settings.record {
+        let _ = LOG.set(Mutex::new(Log::open(data_dir, settings)));
+    }
+}
+
+// Append an event to the log set up by `init`; the command goes on when it
+// cannot be written, with a warning, since what it did is done either way
+pub fn record(event: Event) {
+    let Some(log) = LOG.get() else { return };
+    let mut log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Err(e) = log.append(event) {
+        eprintln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("events-not-recorded", error = e.to_string()));
+    }
+}
+
+// What `events query` picks; every filter given must match
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub kind: Option<Kind>,
+    pub action: Option<String>,
+    pub vin: Option<String>,
+    pub code: Option<String>,
+    // Only events at or after this time
+    pub since: Option<u64>,
+}
+
+impl Filter {
+    pub fn matches(&self, event: &Event) -> bool {
+        self.kind.is_none_or(|kind| kind == event.kind)
+            && self.action.as_ref().is_none_or(|action| action.eq_ignore_ascii_case(&event.action))
+            && self.vin.as_ref().is_none_or(|vin| event.vin.as_ref().is_some_and(|event_vin| event_vin.eq_ignore_ascii_case(vin)))
+            && self.code.as_ref().is_none_or(|code| event.codes.iter().any(|event_code| event_code.eq_ignore_ascii_case(code)))
+            && self.since.is_none_or(|since| event.timestamp >= since)
+    }
+}
+
+pub fn query(log: &Log, filter: &Filter) -> Result<Vec<Event>, Box<dyn Error>> {
+    Ok(log.read()?.into_iter().filter(|event| filter.matches(event)).collect())
+}
+
+fn print(event: &Event, jsonl: bool) {
+    if jsonl {
+        if let Ok(line) = serde_json::to_string(event) {
+            println!("{}", line);
+        }
+        return;
+    }
+    let mut line = format!(
+        "{:>6}  {}  {:<8}  {}",
+        event.seq.styled(Style::Muted),
+        time::format_datetime(event.timestamp),
+        event.kind.name(),
+        event.action.as_str().styled(Style::Heading)
+    );
+    if let Some(vin) = &event.vin {
+        line.push_str(&format!("  {}", vin));
+    }
+    if !event.codes.is_empty() {
+        line.push_str(&format!("  {}", event.codes.join(" ").styled(Style::Code)));
+    }
+    if let Some(path) = &event.path {
+        line.push_str(&format!("  {}", path));
+    }
+    if let Some(detail) = &event.detail {
+        line.push_str(&format!("  {}", detail.as_str().styled(Style::Muted)));
+    }
+    outln!("{}", line);
+}
+
+pub fn display(events: &[Event], jsonl: bool) {
+    if events.is_empty() && !jsonl {
+        outln!("{}", tr!("events-none"));
+    }
+    for event in events {
+        print(event, jsonl);
+    }
+}
+
+// Show the last `count` events and, when following, every event recorded
+// after them until Ctrl-C
+pub fn tail(log: &Log, count: usize, follow: bool, jsonl: bool) -> Result<(), Box<dyn Error>> {
+    // Taken first, so an event appended while the log is read is shown once it is followed
+    let mut offset = fs::metadata(log.path()).map(|metadata| metadata.len()).unwrap_or_default();
+    let events = log.read()?;
+    let shown = &events[events.len().saturating_sub(count)..];
+    if !follow {
+        display(shown, jsonl);
+        return Ok(());
+    }
+    shown.iter().for_each(|event| print(event, jsonl));
+    let mut last = events.last().map(|event| event.seq).unwrap_or_default();
+    let _watch = interrupt::watch();
+    while interrupt::sleep(FOLLOW_INTERVAL) {
+        let (events, next) = log.read_from(offset)?;
+        for event in events {
+            if event.seq > last {
+                print(&event, jsonl);
+                last = event.seq;
+            }
+        }
+        offset = next;
+    }
+    interrupt::handled();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::fs;
+    use crate::testing::{TempDir, FIXED_TIME};
+    use super::{Event, EventSettings, Filter, Kind, Log};
+
+    #[test]
+    fn event_log_numbers_rotates_and_survives_a_torn_line() {
+        let temp = TempDir::new("events");
+        // The log creates its directory
+        let dir = temp.join("data");
+        let settings = EventSettings { record: true, max_size: 400, keep: 2 };
+        let mut log = Log::open(&dir, &settings);
+        let scan = |vin: &str, codes: &[&str]| {
+            let codes: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
+            Event { timestamp: FIXED_TIME, ..Event::new(Kind::Scan, "fleet-import").vin(Some(vin)).codes(&codes).detail("dump.csv") }
+        };
+        assert_eq!(log.append(scan("1HGCM82633A004352", &["P0420"])).unwrap(), 1);
+        assert_eq!(log.append(Event::new(Kind::Database, "db add").codes(&["P1234".to_string()])).unwrap(), 2);
+
+        // A crash in the middle of a write leaves half a line behind
+        let mut file = fs::OpenOptions::new().append(true).open(log.path()).unwrap();
+        file.write_all(b"{\"seq\":3,\"timestamp\":\"2024-").unwrap();
+        drop(file);
+        let mut log = Log::open(&dir, &settings);
+        assert_eq!(log.append(Event::new(Kind::Report, "report").path("P0420.html")).unwrap(), 3);
+        let read = log.read().unwrap();
+        assert_eq!(read.iter().map(|event| event.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(read[0], Event { seq: 1, ..scan("1HGCM82633A004352", &["P0420"]) });
+
+        for _ in 0..12 {
+            log.append(scan("2T1BURHE0JC123456", &["P0300", "C0035"])).unwrap();
+        }
+        assert!(dir.join("events.1.jsonl").is_file() && dir.join("events.2.jsonl").is_file());
+        assert!(!dir.join("events.3.jsonl").exists());
+        assert!(fs::metadata(log.path()).unwrap().len() < 400 + 200);
+        let kept = log.read().unwrap();
+        assert!(kept.len() < 15);
+        assert!(kept.windows(2).all(|pair| pair[1].seq == pair[0].seq + 1));
+        assert_eq!(kept.last().unwrap().seq, 15);
+
+        // Numbering goes on in the next run
+        assert_eq!(Log::open(&dir, &settings).append(Event::new(Kind::Report, "fleet report")).unwrap(), 16);
+
+        let filter = Filter { kind: Some(Kind::Scan), vin: Some("2t1burhe0jc123456".to_string()), code: Some("c0035".to_string()), ..Filter::default() };
+        let found = super::query(&log, &filter).unwrap();
+        assert!(!found.is_empty() && found.iter().all(|event| event.action == "fleet-import"));
+        let filter = Filter { since: Some(FIXED_TIME + 1), ..Filter::default() };
+        assert_eq!(super::query(&log, &filter).unwrap().iter().map(|event| event.action.as_str()).collect::<Vec<_>>(), vec!["fleet report"]);
+    }
+}
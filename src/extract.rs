@@ -0,0 +1,81 @@
+// Pulling trouble codes out of printouts from other shops and tools.
+//
+// The text is scanned for OBD-II codes (P0420, b1234, ...) and for J1939
+// SPN/FMI pairs used by heavy-duty vehicles. Each finding is kept once, in
+// the order it first appears. PDF text extraction is best effort: scanned
+// images and unusual encodings yield no text.
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use regex::Regex;
+#[cfg(not(feature = "pdf"))]
+use crate::i18n::tr;
+
+// A J1939 fault: suspect parameter number and failure mode identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpnFmi {
+    pub spn: u32,
+    pub fmi: u8,
+}
+
+#[derive(Debug, Default)]
+pub struct Extracted {
+    // OBD-II codes, upper case
+    pub codes: Vec<String>,
+    pub spn_fmi: Vec<SpnFmi>,
+}
+
+// Find every code in a block of text
+pub fn extract(text: &str) -> Extracted {
+    let code_pattern = Regex::new(r"(?i)\b[PBCU][0-3][0-9A-F]{3}\b").expect("valid pattern");
+    let spn_pattern = Regex::new(r"(?i)\bSPN\W{0,3}(\d{1,6})\W{1,10}FMI\W{0,3}(\d{1,2})\b").expect("valid pattern");
+
+    let mut extracted = Extracted::default();
+    for found in code_pattern.find_iter(text) {
+        let code = found.as_str().to_uppercase();
+        if !extracted.codes.contains(&code) {
+            extracted.codes.push(code);
+        }
+    }
+    for captures in spn_pattern.captures_iter(text) {
+        let (Ok(spn), Ok(fmi)) = (captures[1].parse::<u32>(), captures[2].parse::<u8>()) else { continue };
+        // FMI is a 5-bit field
+        let fault = SpnFmi { spn, fmi };
+        if fmi <= 31 && !extracted.spn_fmi.contains(&fault) {
+            extracted.spn_fmi.push(fault);
+        }
+    }
+    extracted
+}
+
+// Read the text of a plain text or PDF file
+pub fn read_text(path: &str) -> Result<String, Box<dyn Error>> {
+    let bytes = fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let is_pdf = bytes.starts_with(b"%PDF")
+        || Path::new(path).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        #[cfg(feature = "pdf")]
+        return pdf_extract::extract_text_from_mem(&bytes)
+            .map_err(|e| format!("Could not read the text of {}: {}", path, e).into());
+        #[cfg(not(feature = "pdf"))]
+        return Err(tr!("feature-missing", feature = "pdf").into());
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn extract_finds_each_code_once() {
+        let text = "\
+Scan results 2024-03-02, VIN 1HGCM82633A004352
+  P0420 Catalyst efficiency below threshold (pending)
+  p0171, P0420 (history)   C0035 ABS
+Engine ECU: SPN 110 FMI 0; spn: 110 / fmi: 0; SPN 3251 FMI 16
+Part number 90919-P0300X is not a code";
+        let found = super::extract(text);
+        assert_eq!(found.codes, ["P0420", "P0171", "C0035"]);
+        let faults: Vec<_> = found.spn_fmi.iter().map(|fault| (fault.spn, fault.fmi)).collect();
+        assert_eq!(faults, [(110, 0), (3251, 16)]);
+    }
+}
@@ -0,0 +1,223 @@
+// C API for embedding the lookup engine in other programs (feature "ffi").
+//
+// Databases are opaque handles from `cs_open`. Lookups and searches return
+// JSON strings owned by the caller, which must be released with
+// `cs_string_free`. Functions that fail return NULL and leave a message for
+// `cs_last_error`. The matching header is include/cars_scanner.h, generated
+// with `cbindgen --config cbindgen.toml`.
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use serde_json::{json, Value};
+use crate::search::SearchOptions;
+use crate::{DiagnosticsDatabase, ErrorCode};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Borrow a C string argument, recording an error for NULL or invalid UTF-8
+unsafe fn argument<'a>(text: *const c_char, name: &str) -> Option<&'a str> {
+    if text.is_null() {
+        set_last_error(format!("{} is NULL", name));
+        return None;
+    }
+    match CStr::from_ptr(text).to_str() {
+        Ok(text) => Some(text),
+        Err(_) => {
+            set_last_error(format!("{} is not valid UTF-8", name));
+            None
+        },
+    }
+}
+
+fn into_c_string(value: Value) -> *mut c_char {
+    match CString::new(value.to_string()) {
+        Ok(text) => text.into_raw(),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        },
+    }
+}
+
+fn error_json(error: &ErrorCode) -> Value {
+    json!({
+        "code": error.code,
+        "description": error.description,
+        "severity": error.severity,
+        "system": error.system,
+        "possible_causes": error.possible_causes,
+        "recommended_actions": error.recommended_actions,
+        "related_parts": error.parts().iter().map(|part| json!({ "name": part.name, "number": part.number })).collect::<Vec<_>>(),
+        "emissions_warranty": error.emissions_warranty,
+        "difficulty": error.difficulty,
+        "safety_warnings": crate::pipe_list(&error.safety_warnings).collect::<Vec<_>>(),
+    })
+}
+
+/// Open an error code database CSV file. Returns NULL on failure.
+///
+/// # Safety
+/// `path` must be NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cs_open(path: *const c_char) -> *mut DiagnosticsDatabase {
+    let Some(path) = argument(path, "path") else { return ptr::null_mut() };
+    let mut db = DiagnosticsDatabase::new();
+    match db.read_csv(path) {
+        Ok(()) => Box::into_raw(Box::new(db)),
+        Err(e) => {
+            set_last_error(format!("Could not open {}: {}", path, e));
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Release a database returned by `cs_open`. NULL is ignored.
+///
+/// # Safety
+/// `db` must be NULL or a handle from `cs_open` that has not been released.
+#[no_mangle]
+pub unsafe extern "C" fn cs_close(db: *mut DiagnosticsDatabase) {
+    if !db.is_null() {
+        drop(Box::from_raw(db));
+    }
+}
+
+/// Look up one code as a JSON object. Returns NULL when the code is unknown or on failure.
+///
+/// # Safety
+/// `db` must be NULL or a live handle from `cs_open`, and `code` NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cs_lookup_json(db: *const DiagnosticsDatabase, code: *const c_char) -> *mut c_char {
+    let (Some(db), Some(code)) = (db.as_ref(), argument(code, "code")) else {
+        if db.is_null() {
+            set_last_error("db is NULL".to_string());
+        }
+        return ptr::null_mut();
+    };
+    match db.lookup_error(&code.trim().to_uppercase()) {
+        Some(error) => into_c_string(error_json(error)),
+        None => {
+            set_last_error(format!("Error code {} not found", code));
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Search descriptions, causes and actions for a keyword, most relevant first.
+/// Returns a JSON object with "total", "offset" and "results"; a limit of 0 means no limit.
+///
+/// # Safety
+/// `db` must be NULL or a live handle from `cs_open`, and `keyword` NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cs_search_json(db: *const DiagnosticsDatabase, keyword: *const c_char, offset: usize, limit: usize) -> *mut c_char {
+    let (Some(db), Some(keyword)) = (db.as_ref(), argument(keyword, "keyword")) else {
+        if db.is_null() {
+            set_last_error("db is NULL".to_string());
+        }
+        return ptr::null_mut();
+    };
+    let options = SearchOptions { offset, limit: (limit > 0).then_some(limit), ..Default::default() };
+    let page = db.search(keyword, &options);
+    into_c_string(json!({
+        "total": page.total,
+        "offset": page.offset,
+        "results": page.hits.iter().map(|hit| error_json(&hit.error)).collect::<Vec<_>>(),
+    }))
+}
+
+/// Release a string returned by this library. NULL is ignored.
+///
+/// # Safety
+/// `text` must be NULL or a string returned by this library that has not been released.
+#[no_mangle]
+pub unsafe extern "C" fn cs_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// The message for the last failure on this thread, or NULL. Valid until the next call.
+#[no_mangle]
+pub extern "C" fn cs_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map(|message| message.as_ptr()).unwrap_or(ptr::null()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::path::PathBuf;
+    use std::{fs, ptr};
+    use super::*;
+
+    // The header comment cbindgen writes for a function's doc comment
+    fn header_comment(docs: &[&str]) -> String {
+        let lines: String = docs.iter().map(|line| match line.is_empty() {
+            true => " *\n".to_string(),
+            false => format!(" * {}\n", line),
+        }).collect();
+        format!("/**\n{} */\n", lines)
+    }
+
+    #[test]
+    fn the_header_declares_every_function_with_its_doc_comment() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let source = fs::read_to_string(root.join("src/ffi.rs")).unwrap();
+        let header = fs::read_to_string(root.join("include/cars_scanner.h")).unwrap();
+        let mut docs: Vec<&str> = Vec::new();
+        let mut functions = 0;
+        for line in source.lines().map(str::trim) {
+            if let Some(doc) = line.strip_prefix("///") {
+                docs.push(doc.strip_prefix(' ').unwrap_or(doc));
+                continue;
+            }
+            if line.starts_with("#[") {
+                continue;
+            }
+            if let Some((_, rest)) = line.split_once("extern \"C\" fn ") {
+                let name = &rest[..rest.find('(').unwrap()];
+                let comment = header_comment(&docs);
+                let declared = header.find(&comment).and_then(|at| header[at + comment.len()..].lines().next());
+                assert!(
+                    declared.is_some_and(|declaration| declaration.contains(&format!(" *{}(", name)) || declaration.contains(&format!(" {}(", name))),
+                    "include/cars_scanner.h is out of date for {}; regenerate it with cbindgen --config cbindgen.toml --output include/cars_scanner.h",
+                    name
+                );
+                functions += 1;
+            }
+            docs.clear();
+        }
+        assert_eq!(functions, header.matches(");\n").count());
+    }
+
+    #[test]
+    fn c_api_returns_json() {
+        let path = CString::new(crate::DATABASE_FILE).unwrap();
+        let code = CString::new("p0300").unwrap();
+        let keyword = CString::new("misfire").unwrap();
+        unsafe {
+            let db = cs_open(path.as_ptr());
+            assert!(!db.is_null());
+            let found = cs_lookup_json(db, code.as_ptr());
+            let json: serde_json::Value = serde_json::from_str(CStr::from_ptr(found).to_str().unwrap()).unwrap();
+            assert_eq!(json["code"], "P0300");
+            assert!(json["possible_causes"].is_array());
+            cs_string_free(found);
+
+            let page = cs_search_json(db, keyword.as_ptr(), 0, 1);
+            let json: serde_json::Value = serde_json::from_str(CStr::from_ptr(page).to_str().unwrap()).unwrap();
+            assert_eq!(json["results"].as_array().unwrap().len(), 1);
+            cs_string_free(page);
+
+            assert!(cs_lookup_json(db, ptr::null()).is_null());
+            assert!(!cs_last_error().is_null());
+            cs_close(db);
+        }
+    }
+}
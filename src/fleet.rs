@@ -0,0 +1,478 @@
+// Fleet telematics imports and summaries
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use csv::ReaderBuilder;
+use serde::Deserialize;
+use crate::archive::{Archive, Clock, ScanRecord};
+use crate::branding::Letterhead;
+use crate::charts::{self, Bar, Row};
+use crate::health::HealthWeights;
+use crate::i18n::tr;
+use crate::presentation::{self, Style, Styled};
+use crate::progress;
+use crate::render::Stream;
+use crate::transcript::outln;
+use crate::{escape_html, DiagnosticsDatabase, SEVERITIES};
+
+// Number of entries shown in the top codes list
+const TOP_CODES: usize = 10;
+
+// One entry of a JSON fleet dump; either `code` or `codes` may be used
+#[derive(Deserialize)]
+struct DumpEntry {
+    vin: String,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    codes: Vec<String>,
+}
+
+// Codes found across a set of scans
+pub struct FleetSummary {
+    pub vehicles: usize,
+    pub occurrences: usize,
+    // (code, number of affected vehicles), most frequent first
+    pub top_codes: Vec<(String, usize)>,
+    // (VIN, code) for every critical code found
+    pub critical: Vec<(String, String)>,
+    pub unknown_codes: BTreeSet<String>,
+}
+
+// Split a field holding one or more codes separated by spaces, commas, semicolons or pipes
+fn split_codes(field: &str) -> impl Iterator<Item = String> + '_ {
+    field
+        .split([' ', ',', ';', '|'])
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_uppercase)
+}
+
+// Read a fleet dump as VIN -> codes, from CSV or (by extension) JSON
+fn read_dump(file_path: &str) -> Result<BTreeMap<String, Vec<String>>, Box<dyn Error>> {
+    let mut vehicles: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    if file_path.ends_with(".json") {
+        let content = fs::read_to_string(file_path)?;
+        let entries: Vec<DumpEntry> = serde_json::from_str(&content)?;
+        for entry in entries {
+            let codes = vehicles.entry(entry.vin.trim().to_uppercase()).or_default();
+            codes.extend(entry.code.iter().flat_map(|code| split_codes(code)));
+            codes.extend(entry.codes.iter().flat_map(|code| split_codes(code)));
+        }
+    } else {
+        // Apps such as Car Scanner write semicolon-separated files
+        let content = fs::read_to_string(file_path)?;
+        let header = content.lines().next().unwrap_or_default();
+        let delimiter = if header.contains(';') && !header.contains(',') { b';' } else { b',' };
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(delimiter)
+            .from_reader(content.as_bytes());
+        let headers = reader.headers()?.clone();
+        let column = |names: &[&str]| headers.iter().position(|header| names.iter().any(|name| header.trim().eq_ignore_ascii_case(name)));
+        let vin_column = column(&["vin"]).ok_or("Fleet dump has no 'vin' column")?;
+        let code_column = column(&["code", "codes", "dtc", "dtcs"]).ok_or("Fleet dump has no 'code' or 'codes' column")?;
+
+        for result in reader.records() {
+            let record = result?;
+            let vin = record.get(vin_column).unwrap_or_default().trim().to_uppercase();
+            if vin.is_empty() {
+                continue;
+            }
+            let codes = vehicles.entry(vin).or_default();
+            codes.extend(split_codes(record.get(code_column).unwrap_or_default()));
+        }
+    }
+
+    for codes in vehicles.values_mut() {
+        codes.sort();
+        codes.dedup();
+    }
+    Ok(vehicles)
+}
+
+// Import a fleet dump into the archive, one scan record per vehicle
+pub fn import(file_path: &str, archive: &Archive, clock: &dyn Clock) -> Result<Vec<ScanRecord>, Box<dyn Error>> {
+    let vehicles = read_dump(file_path)?;
+    let timestamp = clock.now();
+    let batch = clock.batch_id();
+    let file_name = std::path::Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_path.to_string());
+
+    let mut records = Vec::new();
+    let bar = progress::bar(vehicles.len() as u64, tr!("fleet-importing"));
+    for (index, (vin, codes)) in vehicles.into_iter().enumerate() {
+        let record = ScanRecord {
+            id: format!("{}-{:05}", batch, index + 1),
+            vin,
+            timestamp,
+            source: format!("fleet-import:{}", file_name),
+            codes,
+            summary: None,
+        };
+        archive.save(&record)?;
+        records.push(record);
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+    Ok(records)
+}
+
+// Summarize the codes found in a set of scans
+pub fn summarize(records: &[ScanRecord], db: &DiagnosticsDatabase) -> FleetSummary {
+    let mut vehicles_per_code: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    let mut vehicles = BTreeSet::new();
+    let mut critical = BTreeSet::new();
+    let mut unknown_codes = BTreeSet::new();
+    let mut occurrences = 0;
+
+    for record in records {
+        vehicles.insert(record.vin.as_str());
+        for code in &record.codes {
+            occurrences += 1;
+            vehicles_per_code.entry(code).or_default().insert(&record.vin);
+            match db.lookup_error(code) {
+                Some(error) if error.severity.eq_ignore_ascii_case("Critical") => {
+                    critical.insert((record.vin.clone(), code.clone()));
+                },
+                Some(_) => {},
+                None => {
+                    unknown_codes.insert(code.clone());
+                },
+            }
+        }
+    }
+
+    let mut top_codes: Vec<(String, usize)> = vehicles_per_code
+        .into_iter()
+        .map(|(code, vins)| (code.to_string(), vins.len()))
+        .collect();
+    top_codes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    FleetSummary {
+        vehicles: vehicles.len(),
+        occurrences,
+        top_codes,
+        critical: critical.into_iter().collect(),
+        unknown_codes,
+    }
+}
+
+// Print a fleet summary: top codes, unknown codes and critical alerts
+pub fn display_summary(summary: &FleetSummary, db: &DiagnosticsDatabase) {
+    outln!("{}", tr!("fleet-summary", vehicles = summary.vehicles, codes = summary.occurrences).styled(Style::Banner));
+
+    if !summary.top_codes.is_empty() {
+        outln!("\n{}", tr!("fleet-top-codes").styled(Style::Heading));
+        for (code, count) in summary.top_codes.iter().take(TOP_CODES) {
+            let description = db.lookup_error(code).map(|error| error.description.as_str()).unwrap_or("?");
+            outln!("  {:<7} {:>6}  {}", code.styled(Style::Code), count, description);
+        }
+    }
+
+    if !summary.unknown_codes.is_empty() {
+        let codes: Vec<&str> = summary.unknown_codes.iter().map(String::as_str).collect();
+        outln!("\n{} {}", tr!("fleet-unknown-codes", count = codes.len()).styled(Style::Warning), codes.join(", "));
+    }
+
+    if summary.critical.is_empty() {
+        outln!("\n{}", tr!("fleet-no-critical").styled(Style::Success));
+    } else {
+        outln!("\n{}", tr!("fleet-critical", count = summary.critical.len()).styled(Style::SeverityCritical));
+        for (vin, code) in &summary.critical {
+            let description = db.lookup_error(code).map(|error| error.description.as_str()).unwrap_or_default();
+            outln!("  {}  {}  {}", vin, code.styled(Style::Error), description);
+        }
+    }
+}
+
+// Health score of every archived scan of one vehicle, oldest first, with the change since the previous scan
+pub fn display_history(records: &[ScanRecord], vin: &str, db: &DiagnosticsDatabase, weights: &HealthWeights) {
+    let scans: Vec<&ScanRecord> = records.iter().filter(|record| record.vin.eq_ignore_ascii_case(vin)).collect();
+    if scans.is_empty() {
+        outln!("{}", tr!("history-none", vin = vin));
+        return;
+    }
+
+    outln!("{}", tr!("history-header", vin = vin.to_uppercase(), scans = scans.len()).styled(Style::Heading));
+    let mut previous: Option<u8> = None;
+    for record in &scans {
+        let health = weights.score(&record.codes, db);
+        let change = match previous {
+            Some(before) if health.score > before => format!("+{}", health.score - before).styled(Style::Success),
+            Some(before) if health.score < before => format!("-{}", before - health.score).styled(Style::Error),
+            Some(_) => "=".styled(Style::Muted),
+            None => "".styled(Style::Plain),
+        };
+        let score = format!("{:>3}", health.score);
+        outln!("  {}  {} {:>4}  {}  {}", crate::time::format_datetime(record.timestamp), score.styled(health.style), change, record.codes.join(" "), record.id.styled(Style::Muted));
+        previous = Some(health.score);
+    }
+
+    let first = weights.score(&scans[0].codes, db).score;
+    let last = weights.score(&scans[scans.len() - 1].codes, db);
+    let trend = match last.score.cmp(&first) {
+        std::cmp::Ordering::Greater => tr!("history-improving"),
+        std::cmp::Ordering::Less => tr!("history-worsening"),
+        std::cmp::Ordering::Equal => tr!("history-stable"),
+    };
+    outln!("\n{} {}", trend, last.verdict.styled(last.style));
+}
+
+// A code seen in more than one scan of the same vehicle
+pub struct RepeatIssue {
+    pub vin: String,
+    pub code: String,
+    pub scans: usize,
+}
+
+// Codes seen in at least two scans of the same vehicle, most repeated first
+pub fn repeat_issues(records: &[ScanRecord]) -> Vec<RepeatIssue> {
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for record in records {
+        for code in &record.codes {
+            *counts.entry((&record.vin, code)).or_default() += 1;
+        }
+    }
+
+    let mut issues: Vec<RepeatIssue> = counts
+        .into_iter()
+        .filter(|(_, scans)| *scans > 1)
+        .map(|((vin, code), scans)| RepeatIssue { vin: vin.to_string(), code: code.to_string(), scans })
+        .collect();
+    issues.sort_by(|a, b| b.scans.cmp(&a.scans).then_with(|| a.vin.cmp(&b.vin)));
+    issues
+}
+
+// Write the fleet dashboard as a standalone HTML page; the rows of the code and
+// repeat issue tables are rendered in parallel
+pub fn write_dashboard<W: Write>(records: &[ScanRecord], db: &DiagnosticsDatabase, letterhead: &Letterhead, period: &str, group: Option<&str>, appendix: &str, out: &mut Stream<W>) -> io::Result<()> {
+    let summary = summarize(records, db);
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    let mut heat_map: BTreeMap<String, HashMap<String, usize>> = BTreeMap::new();
+    for record in records {
+        for code in &record.codes {
+            *occurrences.entry(code).or_default() += 1;
+            if let Some(error) = db.lookup_error(code) {
+                *heat_map.entry(error.system.clone()).or_default().entry(error.severity.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    out.push("<!DOCTYPE html>\n<html>\n<head>\n<meta charset='utf-8'>\n")?;
+    out.push(&format!("<title>{}</title>\n", tr!("fleet-report-title")))?;
+    out.push("<style>\n")?;
+    out.push("body { font-family: Arial, sans-serif; margin: 20px; }\n")?;
+    out.push("table { border-collapse: collapse; margin-bottom: 20px; }\n")?;
+    out.push("th, td { border: 1px solid #ddd; padding: 4px 10px; text-align: left; }\n")?;
+    out.push(".cards div { display: inline-block; border: 1px solid #ddd; padding: 10px 20px; margin-right: 10px; }\n")?;
+    out.push(".cards strong { display: block; font-size: 1.6em; }\n")?;
+    out.push(&presentation::stylesheet())?;
+    out.push("h2 { color: #5bc0de; }\n")?;
+    out.push(letterhead.stylesheet())?;
+    out.push(presentation::page_stylesheet())?;
+    out.push("</style>\n")?;
+    out.push(presentation::theme_script())?;
+    out.push("</head>\n<body>\n")?;
+    out.push(&presentation::theme_toggle(&escape_html(&tr!("report-dark-mode"))))?;
+    out.push(&letterhead.html_header())?;
+    out.push(&format!("<h1>{}</h1>\n", tr!("fleet-report-title")))?;
+    out.push(&format!("<p>{}</p>\n", escape_html(&tr!("fleet-report-period", period = period, scans = records.len()))))?;
+    if let Some(group) = group {
+        out.push(&format!("<p>{}</p>\n", escape_html(&tr!("fleet-report-group", group = group))))?;
+    }
+
+    out.push("<div class='cards'>\n")?;
+    for (label, value) in [
+        (tr!("fleet-report-vehicles"), summary.vehicles),
+        (tr!("fleet-report-occurrences"), summary.occurrences),
+        (tr!("fleet-report-distinct"), summary.top_codes.len()),
+        (tr!("fleet-report-critical"), summary.critical.len()),
+    ] {
+        out.push(&format!("<div><strong>{}</strong>{}</div>\n", value, escape_html(&label)))?;
+    }
+    out.push("</div>\n")?;
+    out.push(&charts_html(records, &heat_map))?;
+
+    out.push(&format!("<h2>{}</h2>\n<table>\n", tr!("fleet-report-frequency")))?;
+    out.push(&format!(
+        "<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+        tr!("column-code"), tr!("column-description"), tr!("column-severity"), tr!("column-system"),
+        tr!("fleet-report-occurrences"), tr!("fleet-report-vehicles")
+    ))?;
+    out.blocks(&summary.top_codes, "", |(code, vehicles)| {
+        let error = db.lookup_error(code);
+        let severity = error.map(|error| error.severity.as_str()).unwrap_or_default();
+        let class = Style::for_severity(severity)
+            .map(|style| format!(" class='{}'", style.css_class()))
+            .unwrap_or_default();
+        format!(
+            "<tr><td>{}</td><td>{}</td><td{}>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(code),
+            escape_html(error.map(|error| error.description.as_str()).unwrap_or("?")),
+            class,
+            escape_html(&crate::presentation::severity_text(severity)),
+            escape_html(error.map(|error| error.system.as_str()).unwrap_or_default()),
+            occurrences.get(code.as_str()).copied().unwrap_or_default(),
+            vehicles
+        )
+    })?;
+    out.push("</table>\n")?;
+
+    let repeats = repeat_issues(records);
+    out.push(&format!("<h2>{}</h2>\n", tr!("fleet-report-repeats")))?;
+    if repeats.is_empty() {
+        out.push(&format!("<p>{}</p>\n", tr!("fleet-report-no-repeats")))?;
+    } else {
+        out.push(&format!(
+            "<table>\n<tr><th>VIN</th><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+            tr!("column-code"), tr!("fleet-report-scans"), tr!("column-description")
+        ))?;
+        out.blocks(&repeats, "", |issue| {
+            let description = db.lookup_error(&issue.code).map(|error| error.description.as_str()).unwrap_or("?");
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&issue.vin), escape_html(&issue.code), issue.scans, escape_html(description)
+            )
+        })?;
+        out.push("</table>\n")?;
+    }
+
+    out.push(&format!("<h2>{}</h2>\n<table>\n<tr><th>{}</th>", tr!("fleet-report-heat-map"), tr!("column-system")))?;
+    for severity in SEVERITIES {
+        out.push(&format!("<th>{}</th>", severity))?;
+    }
+    out.push("</tr>\n")?;
+    let max = heat_map.values().flat_map(|row| row.values()).copied().max().unwrap_or(1).max(1);
+    for (system, row) in &heat_map {
+        out.push(&format!("<tr><th>{}</th>", escape_html(system)))?;
+        for severity in SEVERITIES {
+            let count = row.get(*severity).copied().unwrap_or_default();
+            let alpha = count as f64 / max as f64;
+            out.push(&format!("<td style='background: rgba(217, 83, 79, {:.2})'>{}</td>", alpha, count))?;
+        }
+        out.push("</tr>\n")?;
+    }
+    out.push("</table>\n")?;
+
+    out.push(&format!(
+        "<h2>{}</h2>\n<p><a href='{}'>{}</a></p>\n",
+        tr!("fleet-report-appendix"), escape_html(appendix), escape_html(appendix)
+    ))?;
+    out.push(&letterhead.html_footer())?;
+    out.push("</body>\n</html>\n")
+}
+
+// Most repeat issues drawn on the recurrence timeline
+const TIMELINE_ROWS: usize = 10;
+
+// The dashboard's charts: occurrences by severity and by system and, when vehicles
+// were scanned more than once, when their repeat issues were seen
+fn charts_html(records: &[ScanRecord], heat_map: &BTreeMap<String, HashMap<String, usize>>) -> String {
+    let mut html = String::new();
+    let severities: Vec<Bar> = SEVERITIES
+        .iter()
+        .map(|severity| Bar {
+            label: presentation::severity_text(severity),
+            value: heat_map.values().filter_map(|row| row.get(*severity)).sum(),
+            color: Style::for_severity(severity).and_then(Style::color),
+        })
+        .collect();
+    if let Some(chart) = charts::bar_chart(&tr!("fleet-report-chart-severity"), &severities) {
+        html.push_str(&format!("<h2>{}</h2>\n{}", tr!("fleet-report-chart-severity"), chart));
+    }
+
+    let mut systems: Vec<Bar> = heat_map
+        .iter()
+        .map(|(system, row)| Bar { label: system.clone(), value: row.values().sum(), color: None })
+        .collect();
+    systems.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.label.cmp(&b.label)));
+    if let Some(chart) = charts::bar_chart(&tr!("fleet-report-chart-systems"), &systems) {
+        html.push_str(&format!("<h2>{}</h2>\n{}", tr!("fleet-report-chart-systems"), chart));
+    }
+
+    let rows: Vec<Row> = repeat_issues(records)
+        .into_iter()
+        .take(TIMELINE_ROWS)
+        .map(|issue| Row {
+            times: records
+                .iter()
+                .filter(|record| record.vin == issue.vin && record.codes.contains(&issue.code))
+                .map(|record| record.timestamp)
+                .collect(),
+            label: format!("{} {}", issue.vin, issue.code),
+        })
+        .collect();
+    let start = records.iter().map(|record| record.timestamp).min().unwrap_or_default();
+    let end = records.iter().map(|record| record.timestamp).max().unwrap_or_default();
+    if let Some(chart) = charts::timeline(&tr!("fleet-report-chart-recurrence"), &rows, start, end) {
+        html.push_str(&format!("<h2>{}</h2>\n{}", tr!("fleet-report-chart-recurrence"), chart));
+    }
+    html
+}
+
+// Write every code of every scan as CSV rows for further analysis
+pub fn write_appendix_csv(records: &[ScanRecord], db: &DiagnosticsDatabase, writer: impl Write) -> Result<(), Box<dyn Error>> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(["scan_id", "vin", "timestamp", "source", "code", "severity", "system", "description"])?;
+    for record in records {
+        for code in &record.codes {
+            let error = db.lookup_error(code);
+            csv.write_record([
+                record.id.as_str(),
+                record.vin.as_str(),
+                crate::time::iso8601(record.timestamp).as_str(),
+                record.source.as_str(),
+                code.as_str(),
+                error.map(|error| error.severity.as_str()).unwrap_or_default(),
+                error.map(|error| error.system.as_str()).unwrap_or_default(),
+                error.map(|error| error.description.as_str()).unwrap_or_default(),
+            ])?;
+        }
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::archive::Archive;
+    use crate::branding::Letterhead;
+    use crate::testing::{FIXED_TIME, FixedClock, TempDir, sample_database, scan, init, assert_golden, streamed};
+
+    #[test]
+    fn fleet_dashboard() {
+        init();
+        let db = sample_database();
+        let records = [
+            scan("a-1", "VIN001", 10, &["P0300", "P0101"]),
+            scan("a-2", "VIN002", 9, &["P0300", "B0001"]),
+            scan("b-1", "VIN001", 2, &["P0300", "U9999"]),
+        ];
+        assert_golden("fleet-report.html", &streamed(|out| super::write_dashboard(&records, &db, &Letterhead::default(), "30d", None, "fleet-report.csv", out)));
+
+        let mut appendix = Vec::new();
+        super::write_appendix_csv(&records, &db, &mut appendix).unwrap();
+        assert_golden("fleet-report.csv", &String::from_utf8(appendix).unwrap());
+    }
+
+    #[test]
+    fn fleet_import_uses_clock() {
+        init();
+        let dir = TempDir::new("import");
+        let dump = dir.join("dump.csv");
+        fs::write(&dump, "vin,codes\nVIN002,P0300\nVIN001,P0101 p0300\n").unwrap();
+
+        let archive = Archive::open(dir.path()).unwrap();
+        let records = super::import(dump.to_str().unwrap(), &archive, &FixedClock(FIXED_TIME)).unwrap();
+
+        let ids: Vec<_> = records.iter().map(|record| (record.id.as_str(), record.vin.as_str(), record.timestamp)).collect();
+        assert_eq!(ids, [("6553f100-00001", "VIN001", FIXED_TIME), ("6553f100-00002", "VIN002", FIXED_TIME)]);
+        assert_eq!(records[0].codes, ["P0101", "P0300"]);
+    }
+}
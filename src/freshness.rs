@@ -0,0 +1,352 @@
+// How old the error code database and packs are (`db update`, `doctor` and
+// the warning printed when a command loads them).
+//
+// A database file records the day its codes were last brought up to date on a
+// comment line after the schema line, written by `db update`:
+//
+//   # cars-scanner schema 5
+//   # cars-scanner updated 2026-09-30
+//
+// Files without the line are dated by when they were last modified. A file
+// older than `max_age` in the `[freshness]` table is stale: commands still
+// use it, but say so once.
+//
+// A copy of the codes, such as a SQLite file converted from the database or a
+// Node.js handle holding them in memory, is also out of date once a file it
+// was made from is edited. A `Fingerprint` of each file read, its content
+// hash, tells when that happened.
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::anonymize::fnv1a;
+use crate::archive::{self, Clock, SystemClock};
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::sqlite;
+use crate::DATABASE_FILE;
+
+const MARKER: &str = "# cars-scanner updated ";
+
+const DAY: u64 = 24 * 60 * 60;
+
+// Set once the stale files have been reported
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+// The `[freshness]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FreshnessSettings {
+    // Age after which a database file is stale, e.g. "180d" or "26w"; "0" never is
+    pub max_age: String,
+}
+
+impl Default for FreshnessSettings {
+    fn default() -> Self {
+        FreshnessSettings { max_age: "180d".to_string() }
+    }
+}
+
+impl FreshnessSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        archive::parse_period(&self.max_age).map(|_| ())
+    }
+
+    // The age in seconds, None when files never go stale
+    pub fn max_age(&self) -> Option<u64> {
+        archive::parse_period(&self.max_age).ok().filter(|age| *age > 0)
+    }
+}
+
+// A database file in use and the day it was last brought up to date
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dated {
+    pub name: String,
+    pub path: String,
+    pub updated: u64,
+    // Whether the date is from the file's `updated` line rather than its modification time
+    pub stamped: bool,
+}
+
+impl Dated {
+    pub fn days_old(&self, now: u64) -> u64 {
+        now.saturating_sub(self.updated) / DAY
+    }
+
+    pub fn is_stale(&self, settings: &FreshnessSettings, now: u64) -> bool {
+        settings.max_age().is_some_and(|max_age| now.saturating_sub(self.updated) > max_age)
+    }
+}
+
+// The line recording that a file was brought up to date on the day of `timestamp`
+pub fn line(timestamp: u64) -> String {
+    format!("{}{}", MARKER, date(timestamp))
+}
+
+// The day of `timestamp` as written on the line, e.g. 2026-09-30
+pub fn date(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default().format("%Y-%m-%d").to_string()
+}
+
+// The date on a file's `updated` line, looked for among the comment lines it starts with
+pub fn stamped(content: &str) -> Result<Option<u64>, String> {
+    let lines = content.trim_start_matches('\u{feff}').lines().take_while(|line| line.starts_with('#'));
+    for line in lines {
+        let Some(day) = line.strip_prefix(MARKER) else { continue };
+        return parse_date(day).map(Some).map_err(|_| format!("invalid updated line '{}'", line.trim()));
+    }
+    Ok(None)
+}
+
+// The start of a day written as by `date`
+pub fn parse_date(day: &str) -> Result<u64, String> {
+    let day = NaiveDate::parse_from_str(day.trim(), "%Y-%m-%d").map_err(|_| format!("invalid date '{}'", day.trim()))?;
+    let timestamp = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp();
+    Ok(timestamp.max(0) as u64)
+}
+
+// When the file at `path` was last brought up to date: its `updated` line, otherwise its modification time
+pub fn dated(name: String, path: &str) -> Result<Dated, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    if let Some(updated) = stamped(&content).map_err(|e| format!("{}: {}", path, e))? {
+        return Ok(Dated { name, path: path.to_string(), updated, stamped: true });
+    }
+    Ok(Dated { name, path: path.to_string(), updated: modified(path)?, stamped: false })
+}
+
+// When the file at `path` was last modified
+fn modified(path: &str) -> Result<u64, String> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| format!("Could not read {}: {}", path, e))?;
+    Ok(modified.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default())
+}
+
+// When the codes of a SQLite file written by `db import --sqlite` were last brought up to
+// date: as recorded from the file converted, otherwise when it was converted
+pub fn dated_sqlite(name: String, path: &str) -> Result<Dated, String> {
+    if let Some(updated) = sqlite::updated(path).map_err(|e| format!("{}: {}", path, e))? {
+        return Ok(Dated { name, path: path.to_string(), updated, stamped: true });
+    }
+    Ok(Dated { name, path: path.to_string(), updated: modified(path)?, stamped: false })
+}
+
+// The database files in use, in loading order: the SQLite database when one is set, otherwise
+// the bundled database and every pack. Files that cannot be read are left out, as loading
+// them reports the error.
+pub fn survey(config: &Config) -> Vec<Dated> {
+    if let Some(path) = &config.database {
+        return dated_sqlite(tr!("freshness-sqlite"), path).into_iter().collect();
+    }
+    let bundled = std::iter::once((tr!("freshness-bundled"), DATABASE_FILE.to_string()));
+    let packs = config.packs.iter().map(|pack| (pack.display_name(), pack.path.clone()));
+    bundled.chain(packs).filter_map(|(name, path)| dated(name, &path).ok()).collect()
+}
+
+// The files older than the configured age
+pub fn stale(config: &Config, now: u64) -> Vec<Dated> {
+    survey(config).into_iter().filter(|file| file.is_stale(&config.freshness, now)).collect()
+}
+
+// Describe a stale file, e.g. "bundled database (src/data/error_codes.csv): updated 2026-01-05, 285 days ago"
+pub fn describe(file: &Dated, now: u64) -> String {
+    tr!("freshness-file", name = file.name.as_str(), path = file.path.as_str(), date = date(file.updated), days = file.days_old(now))
+}
+
+// The files the SQLite database in use was converted from that changed since. Nothing when no
+// SQLite database is set or it cannot be read, as loading it reports the error.
+pub fn changed_sources(config: &Config) -> Vec<Fingerprint> {
+    let Some(path) = &config.database else { return Vec::new() };
+    sqlite::sources(path).unwrap_or_default().into_iter().filter(Fingerprint::changed).collect()
+}
+
+// Print a warning about the stale files, and the files the SQLite database was converted from
+// that changed, to standard error, once per run
+pub fn warn(config: &Config) {
+    if WARNED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let now = SystemClock.now();
+    for file in stale(config, now) {
+        eprintln!("{}: {}", tr!("warning-prefix").styled(Style::Warning), tr!("freshness-stale", file = describe(&file, now), max_age = config.freshness.max_age.as_str()));
+    }
+    if let Some(path) = &config.database {
+        for source in changed_sources(config) {
+            eprintln!("{}: {}", tr!("warning-prefix").styled(Style::Warning), tr!("freshness-sqlite-changed", path = path.as_str(), source = source.path.as_str()));
+        }
+    }
+}
+
+// A file as it was read, by the hash of its content
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    pub path: String,
+    pub hash: u64,
+}
+
+impl Fingerprint {
+    pub fn new(path: &str, content: &[u8]) -> Self {
+        Fingerprint { path: path.to_string(), hash: fnv1a(content) }
+    }
+
+    // The file at `path` as it is now
+    pub fn read(path: &str) -> Result<Self, String> {
+        let content = fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        Ok(Fingerprint::new(path, &content))
+    }
+
+    // Whether the file no longer holds what was read; one that cannot be read counts
+    pub fn changed(&self) -> bool {
+        Fingerprint::read(&self.path).map_or(true, |now| now.hash != self.hash)
+    }
+}
+
+// A file read once by a long-running process, which warns once for each new content.
+// The file is only hashed again after its size or modification time moved.
+#[derive(Debug)]
+pub struct Watched {
+    read: Fingerprint,
+    // Size and modification time when last looked at
+    metadata: Option<(u64, Option<SystemTime>)>,
+    // Hash of the content last reported; 0 for a file that could not be read
+    reported: Option<u64>,
+}
+
+impl Watched {
+    pub fn new(read: Fingerprint) -> Self {
+        let metadata = metadata(&read.path);
+        Watched { read, metadata, reported: None }
+    }
+
+    pub fn changed(&self) -> bool {
+        self.read.changed()
+    }
+
+    // Whether the file now holds a content other than the one read and not reported yet
+    pub fn newly_changed(&mut self) -> bool {
+        let seen = metadata(&self.read.path);
+        if seen.is_some() && seen == self.metadata {
+            return false;
+        }
+        self.metadata = seen;
+        let hash = Fingerprint::read(&self.read.path).map_or(0, |now| now.hash);
+        if hash == self.read.hash {
+            self.reported = None;
+            return false;
+        }
+        if self.reported == Some(hash) {
+            return false;
+        }
+        self.reported = Some(hash);
+        true
+    }
+}
+
+fn metadata(path: &str) -> Option<(u64, Option<SystemTime>)> {
+    fs::metadata(path).ok().map(|metadata| (metadata.len(), metadata.modified().ok()))
+}
+
+// `content` with its updated line set to the day of `timestamp`, after the schema line
+pub fn restamp(content: &str, timestamp: u64) -> String {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut lines: Vec<&str> = content.lines().collect();
+    let comments = lines.iter().take_while(|line| line.starts_with('#')).count();
+    if let Some(position) = lines[..comments].iter().position(|line| line.starts_with(MARKER)) {
+        lines.remove(position);
+    }
+    let stamp = line(timestamp);
+    let at = usize::from(lines.first().is_some_and(|first| first.starts_with(crate::schema::MARKER)));
+    lines.insert(at, &stamp);
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    #[cfg(feature = "sqlite")]
+    use crate::config::Config;
+    use crate::schema;
+    #[cfg(feature = "sqlite")]
+    use crate::testing::sample_database;
+    use crate::testing::TempDir;
+    #[cfg(feature = "sqlite")]
+    use super::changed_sources;
+    use super::{Dated, Fingerprint, FreshnessSettings, Watched};
+
+    #[test]
+    fn database_files_are_stamped_and_stale_after_the_configured_age() {
+        let day = 24 * 60 * 60;
+        let september = 1_790_726_400; // 2026-09-30
+        let content = "# cars-scanner schema 5\ncode,description\nP0420,Catalyst\n";
+        assert_eq!(super::stamped(content), Ok(None));
+
+        // The line goes after the schema line and replaces an earlier one
+        let stamped = super::restamp(content, september + 3600);
+        assert_eq!(stamped, "# cars-scanner schema 5\n# cars-scanner updated 2026-09-30\ncode,description\nP0420,Catalyst\n");
+        assert_eq!(super::stamped(&stamped), Ok(Some(september)));
+        assert_eq!(schema::declared(&stamped), Ok(Some(5)));
+        let restamped = super::restamp(&stamped, september + 17 * day);
+        assert_eq!(restamped.matches("updated").count(), 1);
+        assert_eq!(super::stamped(&restamped), Ok(Some(september + 17 * day)));
+        let mut reader = schema::reader(&restamped);
+        assert_eq!(reader.records().count(), 1);
+        // Files without a schema line are still dated by their columns
+        assert!(super::restamp("code,description\n", september).starts_with("# cars-scanner updated 2026-09-30\ncode"));
+        assert!(super::stamped("# cars-scanner updated soon\ncode\n").is_err());
+
+        let file = Dated { name: "shop".to_string(), path: "shop.csv".to_string(), updated: september, stamped: true };
+        let settings = |max_age: &str| FreshnessSettings { max_age: max_age.to_string() };
+        assert!(!file.is_stale(&FreshnessSettings::default(), september + 180 * day));
+        assert!(file.is_stale(&FreshnessSettings::default(), september + 181 * day));
+        assert!(file.is_stale(&settings("2w"), september + 15 * day));
+        assert!(!file.is_stale(&settings("0"), september + 10_000 * day));
+        assert_eq!(file.days_old(september + 181 * day + 5), 181);
+        assert!(settings("half a year").validate().is_err());
+    }
+
+    #[test]
+    fn an_edited_file_is_reported_once_for_each_new_content() {
+        let dir = TempDir::new("fingerprint");
+        let path = &dir.file("codes.csv");
+        fs::write(path, "code,description\nP0420,Catalyst\n").unwrap();
+        let mut watched = Watched::new(Fingerprint::read(path).unwrap());
+        assert!(!watched.changed());
+        assert!(!watched.newly_changed());
+
+        fs::write(path, "code,description\nP0420,Catalyst Below Threshold\n").unwrap();
+        assert!(watched.changed());
+        assert!(watched.newly_changed());
+        assert!(!watched.newly_changed());
+
+        fs::write(path, "code,description\nP0420,Catalyst\n").unwrap();
+        assert!(!watched.changed());
+        assert!(!watched.newly_changed());
+
+        fs::remove_file(path).unwrap();
+        assert!(watched.changed());
+        assert!(watched.newly_changed());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn the_files_a_sqlite_database_was_converted_from_are_reported_once_changed() {
+        let dir = TempDir::new("sources");
+        let source = dir.file("codes.csv");
+        let database = dir.file("codes.sqlite");
+        fs::write(&source, "code,description\nP0420,Catalyst\n").unwrap();
+        sample_database().save_to_sqlite(&database, None, &[Fingerprint::read(&source).unwrap()]).unwrap();
+        let config = Config { database: Some(database), ..Config::default() };
+        let unchanged = changed_sources(&config);
+        fs::write(&source, "code,description\nP0420,Catalyst Below Threshold\n").unwrap();
+        let changed = changed_sources(&config);
+
+        assert!(unchanged.is_empty());
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].path, source);
+        assert!(changed_sources(&Config::default()).is_empty());
+    }
+}
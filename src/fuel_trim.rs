@@ -0,0 +1,287 @@
+// Fuel trim analysis: where a lean or rich condition comes from.
+//
+// The engine computer corrects the fuel mixture with short-term (STFT) and
+// long-term (LTFT) fuel trims. Their sum at idle compared with 2500 rpm
+// tells the usual faults apart: unmetered air from a vacuum leak matters most
+// at idle, a weak fuel supply shows up under load, and a dirty MAF sensor
+// under-reports air everywhere. Samples come from a connected vehicle or a
+// drive log with rpm and trim columns.
+use std::error::Error;
+use std::fs;
+use std::io::BufRead;
+use std::thread;
+use std::time::Duration;
+use csv::ReaderBuilder;
+use crate::adapter::Adapter;
+use crate::i18n::tr;
+use crate::pids;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+use crate::DiagnosticsDatabase;
+
+// Total trim beyond which a bank counts as lean (or, negated, rich)
+const LIMIT: f64 = 10.0;
+
+// Fewest samples needed in a condition for a verdict
+const MIN_SAMPLES: usize = 3;
+
+// Time between live samples
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+// One reading of engine speed and the fuel trims of up to two banks
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub rpm: f64,
+    pub stft: [Option<f64>; 2],
+    pub ltft: [Option<f64>; 2],
+}
+
+impl Sample {
+    // STFT + LTFT, when the bank reports both
+    fn total(&self, bank: usize) -> Option<f64> {
+        Some(self.stft[bank]? + self.ltft[bank]?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Condition {
+    Idle,
+    Cruise,
+}
+
+fn condition(rpm: f64) -> Option<Condition> {
+    match rpm {
+        rpm if (400.0..1200.0).contains(&rpm) => Some(Condition::Idle),
+        rpm if (2000.0..=3000.0).contains(&rpm) => Some(Condition::Cruise),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    Normal,
+    VacuumLeak,
+    Maf,
+    FuelDelivery,
+    Rich,
+    NotEnoughData,
+}
+
+impl Pattern {
+    fn from_trims(idle: Option<f64>, cruise: Option<f64>) -> Self {
+        let (Some(idle), Some(cruise)) = (idle, cruise) else { return Pattern::NotEnoughData };
+        match (idle > LIMIT, cruise > LIMIT) {
+            _ if idle < -LIMIT || cruise < -LIMIT => Pattern::Rich,
+            (true, false) => Pattern::VacuumLeak,
+            (false, true) => Pattern::FuelDelivery,
+            (true, true) => Pattern::Maf,
+            (false, false) => Pattern::Normal,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Pattern::Normal => tr!("fuel-trim-normal"),
+            Pattern::VacuumLeak => tr!("fuel-trim-vacuum-leak"),
+            Pattern::Maf => tr!("fuel-trim-maf"),
+            Pattern::FuelDelivery => tr!("fuel-trim-fuel-delivery"),
+            Pattern::Rich => tr!("fuel-trim-rich"),
+            Pattern::NotEnoughData => tr!("fuel-trim-not-enough", samples = MIN_SAMPLES),
+        }
+    }
+
+    // Codes the engine computer sets for this pattern on a bank (0 or 1)
+    pub fn related_codes(&self, bank: usize) -> Vec<&'static str> {
+        let lean = ["P0171", "P0174"][bank];
+        match self {
+            Pattern::VacuumLeak => vec![lean],
+            Pattern::Maf => vec![lean, "P0101"],
+            Pattern::FuelDelivery => vec![lean, "P0087"],
+            Pattern::Rich => vec![["P0172", "P0175"][bank]],
+            Pattern::Normal | Pattern::NotEnoughData => Vec::new(),
+        }
+    }
+}
+
+// SAE titles for the related codes, used when the database has no entry
+const CODE_TITLES: &[(&str, &str)] = &[
+    ("P0087", "Fuel Rail/System Pressure - Too Low"),
+    ("P0101", "Mass or Volume Air Flow Circuit Range/Performance"),
+    ("P0171", "System Too Lean (Bank 1)"),
+    ("P0172", "System Too Rich (Bank 1)"),
+    ("P0174", "System Too Lean (Bank 2)"),
+    ("P0175", "System Too Rich (Bank 2)"),
+];
+
+// Average total trim of one bank at idle and at 2500 rpm
+#[derive(Debug, Clone, PartialEq)]
+pub struct BankTrims {
+    pub bank: usize,
+    pub idle: Option<f64>,
+    pub idle_samples: usize,
+    pub cruise: Option<f64>,
+    pub cruise_samples: usize,
+    pub pattern: Pattern,
+}
+
+// Average the samples of each bank per condition and name the pattern
+pub fn analyze(samples: &[Sample]) -> Vec<BankTrims> {
+    (0..2)
+        .filter(|&bank| samples.iter().any(|sample| sample.total(bank).is_some()))
+        .map(|bank| {
+            let average = |wanted: Condition| {
+                let totals: Vec<f64> = samples
+                    .iter()
+                    .filter(|sample| condition(sample.rpm) == Some(wanted))
+                    .filter_map(|sample| sample.total(bank))
+                    .collect();
+                let mean = (totals.len() >= MIN_SAMPLES).then(|| totals.iter().sum::<f64>() / totals.len() as f64);
+                (mean, totals.len())
+            };
+            let (idle, idle_samples) = average(Condition::Idle);
+            let (cruise, cruise_samples) = average(Condition::Cruise);
+            BankTrims { bank, idle, idle_samples, cruise, cruise_samples, pattern: Pattern::from_trims(idle, cruise) }
+        })
+        .collect()
+}
+
+// Read samples from a drive log CSV exported by a logging app. Columns are matched
+// by PID name (rpm, stft1, ltft1, stft2, ltft2) or the headings Torque uses.
+pub fn read_log(file_path: &str) -> Result<Vec<Sample>, Box<dyn Error>> {
+    let content = fs::read_to_string(file_path)
+        .map_err(|e| format!("Could not open drive log {}: {}", file_path, e))?;
+    let header = content.lines().next().unwrap_or_default();
+    let delimiter = if header.contains(';') && !header.contains(',') { b';' } else { b',' };
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let column = |names: &[&str]| headers.iter().position(|header| names.iter().any(|name| header.trim().eq_ignore_ascii_case(name)));
+    let rpm = column(&["rpm", "engine rpm", "engine rpm(rpm)"])
+        .ok_or_else(|| format!("Drive log {} has no 'rpm' column", file_path))?;
+    let trims = [1, 2].map(|bank| {
+        let short = format!("stft{}", bank);
+        let long = format!("ltft{}", bank);
+        let short_app = format!("fuel trim bank {} short term(%)", bank);
+        let long_app = format!("fuel trim bank {} long term(%)", bank);
+        (column(&[&short, &short_app]), column(&[&long, &long_app]))
+    });
+    if trims[0].0.is_none() || trims[0].1.is_none() {
+        return Err(format!("Drive log {} needs 'stft1' and 'ltft1' columns", file_path).into());
+    }
+
+    let mut samples = Vec::new();
+    for result in reader.records() {
+        let record = result?;
+        let value = |column: Option<usize>| column.and_then(|column| record.get(column)?.trim().trim_end_matches('%').parse::<f64>().ok());
+        let Some(rpm) = value(Some(rpm)) else { continue };
+        samples.push(Sample {
+            rpm,
+            stft: [value(trims[0].0), value(trims[1].0)],
+            ltft: [value(trims[0].1), value(trims[1].1)],
+        });
+    }
+    Ok(samples)
+}
+
+fn read_sample(adapter: &mut Adapter) -> Result<Sample, Box<dyn Error>> {
+    let mut read = |name: &str| match pids::find(name) {
+        Some(pid) => adapter.read_pid(pid),
+        None => Ok(None),
+    };
+    Ok(Sample {
+        rpm: read("rpm")?.unwrap_or_default(),
+        stft: [read("stft1")?, read("stft2")?],
+        ltft: [read("ltft1")?, read("ltft2")?],
+    })
+}
+
+// Sample a running engine at idle and then at 2500 rpm, waiting for Enter before each
+pub fn sample_live(adapter: &mut Adapter, input: &mut impl BufRead, count: usize) -> Result<Vec<Sample>, Box<dyn Error>> {
+    let mut samples = Vec::new();
+    for prompt in [tr!("fuel-trim-prompt-idle"), tr!("fuel-trim-prompt-cruise")] {
+        outln!("{}", prompt);
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+        for _ in 0..count {
+            samples.push(read_sample(adapter)?);
+            thread::sleep(SAMPLE_INTERVAL);
+        }
+    }
+    Ok(samples)
+}
+
+// An average right-aligned in a column, or how few samples there were
+fn format_trim(trim: Option<f64>, samples: usize) -> String {
+    let (text, style) = match trim {
+        Some(trim) if trim.abs() > LIMIT => (format!("{:+.1}%", trim), Style::Warning),
+        Some(trim) => (format!("{:+.1}%", trim), Style::Success),
+        None => (tr!("fuel-trim-samples", samples = samples), Style::Muted),
+    };
+    format!("{:>12}", text).styled(style).to_string()
+}
+
+// Print the averages, the verdict for each bank and the codes it points to
+pub fn display(banks: &[BankTrims], db: &DiagnosticsDatabase) {
+    if banks.is_empty() {
+        outln!("{}", tr!("fuel-trim-no-trims"));
+        return;
+    }
+    outln!("{}", tr!("fuel-trim-header").styled(Style::Heading));
+    outln!("  {:<8} {:>12} {:>12}", "", tr!("fuel-trim-idle"), tr!("fuel-trim-cruise"));
+    for bank in banks {
+        outln!("  {:<8} {} {}", tr!("fuel-trim-bank", bank = bank.bank + 1), format_trim(bank.idle, bank.idle_samples), format_trim(bank.cruise, bank.cruise_samples));
+    }
+
+    let mut related = Vec::new();
+    for bank in banks {
+        outln!();
+        outln!("{} {}", format!("{}:", tr!("fuel-trim-bank", bank = bank.bank + 1)).styled(Style::Label), bank.pattern.message());
+        for code in bank.pattern.related_codes(bank.bank) {
+            if !related.contains(&code) {
+                related.push(code);
+            }
+        }
+    }
+    if related.is_empty() {
+        return;
+    }
+    outln!();
+    outln!("{}", tr!("fuel-trim-related").styled(Style::Section));
+    for code in related {
+        let title = match db.lookup_error(code) {
+            Some(error) => error.description.as_str(),
+            None => CODE_TITLES.iter().find(|(known, _)| *known == code).map(|(_, title)| *title).unwrap_or_default(),
+        };
+        outln!("  {}  {}", code.styled(Style::Code), title);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::testing::{init, TempDir};
+    use super::Pattern;
+
+    #[test]
+    fn fuel_trims_tell_leaks_from_fuel_delivery() {
+        init();
+        let dir = TempDir::new("trims");
+        let log = dir.join("drive.csv");
+        // Torque headings; bank 1 lean only at idle, bank 2 lean only at 2500 rpm
+        let mut content = "Engine RPM(rpm),Fuel Trim Bank 1 Short Term(%),Fuel Trim Bank 1 Long Term(%),Fuel Trim Bank 2 Short Term(%),Fuel Trim Bank 2 Long Term(%)\n".to_string();
+        for _ in 0..3 {
+            content.push_str("750,8.0,6.0,1.0,0.5\n2500,1.0,2.0,9.0,7.5\n1600,30,30,30,30\n");
+        }
+        fs::write(&log, content).unwrap();
+        let samples = super::read_log(log.to_str().unwrap()).unwrap();
+
+        let banks = super::analyze(&samples);
+        assert_eq!(banks.iter().map(|bank| bank.pattern).collect::<Vec<_>>(), [Pattern::VacuumLeak, Pattern::FuelDelivery]);
+        assert_eq!(banks[0].idle, Some(14.0));
+        assert_eq!(banks[1].pattern.related_codes(banks[1].bank), ["P0174", "P0087"]);
+        assert_eq!(super::analyze(&samples[..2])[0].pattern, Pattern::NotEnoughData);
+    }
+}
@@ -0,0 +1,480 @@
+// Live data as gauges (`monitor --tui`).
+//
+// The line per sample of `monitor` scrolls away; on a laptop propped up on
+// the bench a technician wants a screen that stays put. The gauge screen
+// draws each PID as a bar between the ends of its usual range, with a
+// sparkline of its recent values, from the same sampling, thresholds,
+// notifications and summary as `monitor`. A banner across the top shows
+// every threshold currently crossed and the trouble codes set since the
+// screen opened; the codes are read again every CODE_INTERVAL.
+//
+// Space pauses and resumes sampling, p opens the PID menu (up and down
+// move, space picks, Enter applies, Escape cancels), and q, Escape or
+// Ctrl-C leave. Reading single keys while sampling needs the Linux
+// terminal interface, like SocketCAN.
+use std::collections::VecDeque;
+use std::error::Error;
+use std::time::Duration;
+use console::Key;
+use crate::i18n::tr;
+use crate::monitor::{Event, Sample};
+use crate::pids::{self, Pid};
+use crate::presentation::{Style, Styled};
+use crate::{time, DiagnosticsDatabase};
+
+// Values kept per PID for its sparkline
+const HISTORY: usize = 40;
+
+// Width of a gauge in characters
+const GAUGE_WIDTH: usize = 24;
+
+// How often the trouble codes are read again for the banner
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const CODE_INTERVAL: Duration = Duration::from_secs(10);
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Ends of the gauge of a PID: the values usually seen rather than what the
+// encoding allows; PIDs without a range, such as distances, are scaled to
+// the largest value seen
+const RANGES: &[(&str, f64, f64)] = &[
+    ("load", 0.0, 100.0),
+    ("coolant", -40.0, 130.0),
+    ("stft1", -25.0, 25.0),
+    ("ltft1", -25.0, 25.0),
+    ("stft2", -25.0, 25.0),
+    ("ltft2", -25.0, 25.0),
+    ("map", 0.0, 255.0),
+    ("rpm", 0.0, 8000.0),
+    ("speed", 0.0, 200.0),
+    ("intake", -40.0, 80.0),
+    ("maf", 0.0, 250.0),
+    ("throttle", 0.0, 100.0),
+    ("fuel", 0.0, 100.0),
+    ("voltage", 10.0, 16.0),
+    ("oil", -40.0, 150.0),
+];
+
+// A bar `width` characters wide, filled as far as `value` is from `low` to `high`
+pub fn gauge(value: f64, low: f64, high: f64, width: usize) -> String {
+    let share = match high > low {
+        true => ((value - low) / (high - low)).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    let filled = (share * width as f64).round() as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+// One block per value, from the lowest to the highest of them
+pub fn sparkline(values: &[f64]) -> String {
+    let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values.iter()
+        .map(|value| match high > low {
+            true => SPARKS[(((value - low) / (high - low)) * (SPARKS.len() - 1) as f64).round() as usize],
+            false => SPARKS[0],
+        })
+        .collect()
+}
+
+// A PID on the screen
+struct Trace {
+    pid: &'static Pid,
+    values: VecDeque<f64>,
+    // None until read, and when the vehicle gave no value
+    latest: Option<f64>,
+}
+
+impl Trace {
+    fn new(pid: &'static Pid) -> Self {
+        Trace { pid, values: VecDeque::new(), latest: None }
+    }
+
+    fn range(&self) -> (f64, f64) {
+        match RANGES.iter().find(|(name, _, _)| *name == self.pid.name) {
+            Some((_, low, high)) => (*low, *high),
+            None => (0.0, self.values.iter().copied().fold(1.0, f64::max)),
+        }
+    }
+}
+
+// The PID menu: every known PID, with the ones picked
+struct Menu {
+    cursor: usize,
+    picked: Vec<bool>,
+}
+
+// What a key asks of the session
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Continue,
+    Quit,
+}
+
+pub struct Screen {
+    traces: Vec<Trace>,
+    // Thresholds crossed and not yet back within limits
+    alerts: Vec<Event>,
+    codes_at_start: Vec<String>,
+    // Codes read since the screen opened that were not there at the start
+    new_codes: Vec<String>,
+    // Notifications that could not be delivered, from the last sample
+    failures: Vec<String>,
+    paused: bool,
+    menu: Option<Menu>,
+    updated: Option<u64>,
+}
+
+impl Screen {
+    pub fn new(pids: &[&'static Pid], codes_at_start: Vec<String>) -> Self {
+        Screen {
+            traces: pids.iter().map(|pid| Trace::new(pid)).collect(),
+            alerts: Vec::new(),
+            codes_at_start,
+            new_codes: Vec::new(),
+            failures: Vec::new(),
+            paused: false,
+            menu: None,
+            updated: None,
+        }
+    }
+
+    pub fn pids(&self) -> Vec<&'static Pid> {
+        self.traces.iter().map(|trace| trace.pid).collect()
+    }
+
+    // Whether samples are being taken: not while paused or picking PIDs
+    pub fn sampling(&self) -> bool {
+        !self.paused && self.menu.is_none()
+    }
+
+    pub fn update(&mut self, sample: &Sample, failures: Vec<String>) {
+        for (pid, value) in &sample.values {
+            let Some(trace) = self.traces.iter_mut().find(|trace| trace.pid.name == pid.name) else { continue };
+            trace.latest = *value;
+            if let Some(value) = value {
+                trace.values.push_back(*value);
+                if trace.values.len() > HISTORY {
+                    trace.values.pop_front();
+                }
+            }
+        }
+        for event in &sample.events {
+            self.alerts.retain(|alert| alert.pid != event.pid);
+            if event.limit.is_some() {
+                self.alerts.push(event.clone());
+            }
+        }
+        self.failures = failures;
+        self.updated = Some(sample.timestamp);
+    }
+
+    pub fn codes(&mut self, codes: &[String]) {
+        self.new_codes = codes.iter().filter(|code| !self.codes_at_start.contains(code)).cloned().collect();
+    }
+
+    pub fn key(&mut self, key: Key) -> Action {
+        let Some(menu) = &mut self.menu else {
+            return match key {
+                Key::Char('q' | 'Q') | Key::Escape | Key::CtrlC => Action::Quit,
+                Key::Char(' ') => {
+                    self.paused = !self.paused;
+                    Action::Continue
+                },
+                Key::Char('p' | 'P') => {
+                    let picked = pids::PIDS.iter().map(|pid| self.traces.iter().any(|trace| trace.pid.name == pid.name)).collect();
+                    self.menu = Some(Menu { cursor: 0, picked });
+                    Action::Continue
+                },
+                _ => Action::Continue,
+            };
+        };
+        match key {
+            Key::ArrowUp => menu.cursor = menu.cursor.saturating_sub(1),
+            Key::ArrowDown => menu.cursor = (menu.cursor + 1).min(pids::PIDS.len() - 1),
+            Key::Char(' ') => menu.picked[menu.cursor] = !menu.picked[menu.cursor],
+            Key::Escape => self.menu = None,
+            Key::CtrlC => return Action::Quit,
+            // At least one PID stays on the screen
+            Key::Enter if menu.picked.iter().any(|picked| *picked) => {
+                let picked: Vec<&'static Pid> = pids::PIDS.iter().zip(&menu.picked).filter(|(_, picked)| **picked).map(|(pid, _)| pid).collect();
+                let mut traces = std::mem::take(&mut self.traces);
+                self.traces = picked.into_iter()
+                    .map(|pid| match traces.iter().position(|trace| trace.pid.name == pid.name) {
+                        Some(index) => traces.swap_remove(index),
+                        None => Trace::new(pid),
+                    })
+                    .collect();
+                self.alerts.retain(|alert| self.traces.iter().any(|trace| trace.pid.name == alert.pid));
+                self.menu = None;
+            },
+            _ => {},
+        }
+        Action::Continue
+    }
+
+    // The lines of the screen for a terminal `width` characters wide
+    pub fn render(&self, db: &DiagnosticsDatabase, width: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut title = tr!("gauges-title").styled(Style::Heading).to_string();
+        if let Some(updated) = self.updated {
+            title.push_str(&format!("  {}", time::format_local(updated, "%H:%M:%S").styled(Style::Muted)));
+        }
+        if self.paused {
+            title.push_str(&format!("  {}", tr!("gauges-paused").styled(Style::Warning)));
+        }
+        lines.push(title);
+
+        for alert in &self.alerts {
+            lines.push(format!("{} {}", tr!("monitor-alert").styled(Style::Error), alert.message()));
+        }
+        for code in &self.new_codes {
+            let description = db.lookup_error(code).map(|error| error.description.as_str()).unwrap_or_default();
+            lines.push(format!("{} {}", tr!("gauges-new-code", code = code.as_str()).styled(Style::Error), description));
+        }
+        for failure in &self.failures {
+            lines.push(format!("{}: {}", tr!("error-prefix").styled(Style::Error), failure));
+        }
+        lines.push(String::new());
+
+        match &self.menu {
+            Some(menu) => {
+                lines.push(tr!("gauges-menu").styled(Style::Section).to_string());
+                for (index, (pid, picked)) in pids::PIDS.iter().zip(&menu.picked).enumerate() {
+                    let marker = if *picked { "[x]" } else { "[ ]" };
+                    let line = format!("{} {:<14} {}", marker, pid.name, pid.unit);
+                    lines.push(match index == menu.cursor {
+                        true => format!("{} {}", ">".styled(Style::Hint), line.styled(Style::Match)),
+                        false => format!("  {}", line),
+                    });
+                }
+                lines.push(String::new());
+                lines.push(tr!("gauges-menu-keys").styled(Style::Muted).to_string());
+            },
+            None => {
+                let name_width = self.traces.iter().map(|trace| trace.pid.name.len()).max().unwrap_or_default();
+                // Name, value, unit and gauge come first; the sparkline gets what is left
+                let spark_width = width.saturating_sub(name_width + 10 + 6 + GAUGE_WIDTH + 4).min(HISTORY);
+                for trace in &self.traces {
+                    let alerting = self.alerts.iter().any(|alert| alert.pid == trace.pid.name);
+                    let value = match trace.latest {
+                        Some(value) => format!("{:>9.1}", value),
+                        None => format!("{:>9}", "n/a"),
+                    };
+                    let value = match alerting {
+                        true => value.styled(Style::Error).to_string(),
+                        false => value,
+                    };
+                    let (low, high) = trace.range();
+                    let gauge = match trace.latest {
+                        Some(latest) => gauge(latest, low, high, GAUGE_WIDTH),
+                        None => " ".repeat(GAUGE_WIDTH),
+                    };
+                    let recent: Vec<f64> = trace.values.iter().skip(trace.values.len().saturating_sub(spark_width)).copied().collect();
+                    lines.push(format!(
+                        "{:<name_width$} {} {:<5} {} {}",
+                        trace.pid.name.styled(Style::Label),
+                        value,
+                        trace.pid.unit,
+                        gauge.styled(if alerting { Style::Error } else { Style::Hint }),
+                        sparkline(&recent),
+                        name_width = name_width
+                    ));
+                }
+                lines.push(String::new());
+                lines.push(tr!("gauges-keys").styled(Style::Muted).to_string());
+            },
+        }
+        lines
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod terminal {
+    use std::io;
+
+    // Keys reach the program one at a time, without Enter, until dropped;
+    // Ctrl-C still raises SIGINT
+    pub struct SingleKeys {
+        original: libc::termios,
+    }
+
+    impl SingleKeys {
+        pub fn enable() -> io::Result<Self> {
+            let mut termios = std::mem::MaybeUninit::uninit();
+            // SAFETY: tcgetattr fills the termios structure it is given
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: initialized by the successful tcgetattr above
+            let original = unsafe { termios.assume_init() };
+            let mut single = original;
+            single.c_lflag &= !(libc::ICANON | libc::ECHO);
+            single.c_cc[libc::VMIN] = 1;
+            single.c_cc[libc::VTIME] = 0;
+            // SAFETY: sets the attributes of stdin from a valid termios structure
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &single) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(SingleKeys { original })
+        }
+    }
+
+    impl Drop for SingleKeys {
+        fn drop(&mut self) {
+            // SAFETY: restores the attributes read in `enable`
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+        }
+    }
+}
+
+// Show the gauge screen until q, Escape or Ctrl-C, or until the window
+// closes; returns the recorder for the summary, as `monitor::run` does
+#[cfg(target_os = "linux")]
+pub fn run(
+    adapter: &mut crate::adapter::Adapter,
+    settings: &crate::monitor::MonitorSettings,
+    pids: &[&'static Pid],
+    notifier: &crate::monitor::Notifier,
+    window: crate::monitor::Window,
+    db: &DiagnosticsDatabase,
+) -> Result<crate::monitor::Recorder, Box<dyn Error>> {
+    use std::io::IsTerminal;
+    use std::time::Instant;
+    use console::Term;
+    use crate::archive::{Clock, SystemClock};
+    use crate::monitor::{self, Guard, Recorder};
+    use crate::{interrupt, transcript::outln};
+
+    let term = Term::stdout();
+    if !term.is_term() || !std::io::stdin().is_terminal() {
+        return Err(tr!("gauges-needs-terminal").into());
+    }
+    let mut guard = Guard::new(&settings.thresholds);
+    let mut recorder = Recorder::new(SystemClock.now());
+    let mut screen = Screen::new(pids, monitor::read_codes(adapter)?);
+    let interval = Duration::from_millis(settings.interval_ms);
+    let deadline = window.duration.map(|duration| Instant::now() + duration);
+    let mut next_sample = Instant::now();
+    let mut next_codes = Instant::now() + CODE_INTERVAL;
+
+    let keys = terminal::SingleKeys::enable()?;
+    term.hide_cursor()?;
+    let mut drawn = 0;
+    let result: Result<(), Box<dyn Error>> = (|| {
+        loop {
+            if interrupt::interrupted()
+                || window.samples.is_some_and(|samples| recorder.samples() >= samples)
+                || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Ok(());
+            }
+            if screen.sampling() && Instant::now() >= next_sample {
+                // Ctrl-C in the middle of a sample ends the session
+                let Some(sample) = monitor::sample(adapter, &screen.pids(), &mut guard, &mut recorder)? else { return Ok(()) };
+                let failures = sample.events.iter().flat_map(|event| notifier.deliver(event, sample.timestamp)).collect();
+                screen.update(&sample, failures);
+                if Instant::now() >= next_codes {
+                    screen.codes(&monitor::read_codes(adapter)?);
+                    next_codes = Instant::now() + CODE_INTERVAL;
+                }
+                next_sample = Instant::now() + interval;
+            }
+
+            term.clear_last_lines(drawn)?;
+            let lines = screen.render(db, term.size().1 as usize);
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            drawn = lines.len();
+
+            let wait = match screen.sampling() {
+                true => next_sample.saturating_duration_since(Instant::now()),
+                false => Duration::from_millis(100),
+            };
+            if monitor::stdin_ready(wait) && screen.key(term.read_key_raw()?) == Action::Quit {
+                return Ok(());
+            }
+        }
+    })();
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    drop(keys);
+    if interrupt::interrupted() {
+        adapter.close();
+        interrupt::handled();
+    }
+    result?;
+    outln!("{}", tr!("monitor-stopped", samples = recorder.samples()));
+    Ok(recorder)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn run(
+    _adapter: &mut crate::adapter::Adapter,
+    _settings: &crate::monitor::MonitorSettings,
+    _pids: &[&'static Pid],
+    _notifier: &crate::monitor::Notifier,
+    _window: crate::monitor::Window,
+    _db: &DiagnosticsDatabase,
+) -> Result<crate::monitor::Recorder, Box<dyn Error>> {
+    Err(tr!("gauges-unsupported").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use console::Key;
+    use crate::monitor::{Event, Limit, Sample};
+    use crate::pids;
+    use crate::testing::{sample_database, FIXED_TIME};
+    use super::{Action, Screen};
+
+    #[test]
+    fn gauge_screen_shows_values_alerts_new_codes_and_the_pid_menu() {
+        assert_eq!(super::gauge(50.0, 0.0, 100.0, 10), "█████░░░░░");
+        assert_eq!(super::gauge(150.0, 0.0, 100.0, 4), "████");
+        assert_eq!(super::gauge(-60.0, -40.0, 130.0, 4), "░░░░");
+        assert_eq!(super::sparkline(&[1.0, 2.0, 3.0, 8.0]), "▁▂▃█");
+        assert_eq!(super::sparkline(&[5.0, 5.0]), "▁▁");
+
+        let rpm = pids::find("rpm").unwrap();
+        let coolant = pids::find("coolant").unwrap();
+        let db = sample_database();
+        let mut screen = Screen::new(&[rpm, coolant], vec!["P0101".to_string()]);
+        let hot = Event { pid: "coolant", value: 118.0, unit: coolant.unit, limit: Some(Limit::Above(110.0)) };
+        let sample = Sample { timestamp: FIXED_TIME, values: vec![(rpm, Some(4000.0)), (coolant, Some(118.0))], events: vec![hot] };
+        screen.update(&sample, Vec::new());
+        screen.codes(&["P0101".to_string(), "P0300".to_string()]);
+        let lines = screen.render(&db, 100).join("\n");
+        assert!(lines.contains("ALERT coolant is 118.0"), "{}", lines);
+        assert!(lines.contains("NEW P0300 Random/Multiple Cylinder Misfire Detected"), "{}", lines);
+        assert!(!lines.contains("NEW P0101"));
+        assert!(lines.contains("4000.0 rpm   ████████████░░░░░░░░░░░░"), "{}", lines);
+
+        // Back within limits clears the alert
+        let cooled = Event { pid: "coolant", value: 95.0, unit: coolant.unit, limit: None };
+        screen.update(&Sample { timestamp: FIXED_TIME + 1, values: vec![(coolant, Some(95.0))], events: vec![cooled] }, Vec::new());
+        assert!(!screen.render(&db, 100).join("\n").contains("ALERT"));
+
+        assert!(screen.sampling());
+        assert_eq!(screen.key(Key::Char(' ')), Action::Continue);
+        assert!(!screen.sampling());
+        assert!(screen.render(&db, 100)[0].contains("PAUSED"));
+        screen.key(Key::Char(' '));
+
+        // The menu starts on the first PID, load; picking it and dropping rpm
+        screen.key(Key::Char('p'));
+        assert!(!screen.sampling());
+        assert!(screen.render(&db, 100).iter().any(|line| line.contains("[x] rpm")));
+        screen.key(Key::Char(' '));
+        let rpm_index = pids::PIDS.iter().position(|pid| pid.name == "rpm").unwrap();
+        for _ in 0..rpm_index {
+            screen.key(Key::ArrowDown);
+        }
+        screen.key(Key::Char(' '));
+        screen.key(Key::Enter);
+        assert_eq!(screen.pids().iter().map(|pid| pid.name).collect::<Vec<_>>(), vec!["load", "coolant"]);
+        assert!(screen.sampling());
+        assert_eq!(screen.key(Key::Char('q')), Action::Quit);
+    }
+}
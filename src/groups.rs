@@ -0,0 +1,204 @@
+// Vehicle groups: the parts of a fleet looked at separately (`fleet group`).
+//
+// A fleet manager asks about the vans of the north region, the ones on the
+// airport route, or every vehicle built 2015 to 2018, not about the whole
+// fleet at once. A group names a set of vehicles: VINs added to it one by
+// one or on import (`fleet import dump.csv --group north-region`), a range
+// of model years read from the VIN, or both. `--group` limits every fleet
+// command that reads the archive to the vehicles of that group. Groups are
+// kept in <data_dir>/groups.json.
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::archive::ScanRecord;
+use crate::i18n::tr;
+use crate::inspection;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+
+pub const GROUPS_FILE: &str = "groups.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Group {
+    pub description: Option<String>,
+    pub vins: BTreeSet<String>,
+    // Model-year cohort: vehicles whose VIN gives a year in this range belong to the group
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_year: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_year: Option<u16>,
+}
+
+pub struct Groups {
+    path: PathBuf,
+    pub groups: BTreeMap<String, Group>,
+}
+
+// Names are used on the command line, so they are kept to letters, digits, '-' and '_'
+fn check_name(name: &str) -> Result<String, String> {
+    match !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        true => Ok(name.to_lowercase()),
+        false => Err(format!("Invalid group name '{}'; use letters, digits, '-' and '_'", name)),
+    }
+}
+
+// Parse a model-year range such as "2015-2018", or a single year
+pub fn parse_years(years: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("Invalid model years '{}': use a year or a range such as 2015-2018", years);
+    let (first, last) = years.split_once('-').unwrap_or((years, years));
+    let first: u16 = first.trim().parse().map_err(|_| invalid())?;
+    let last: u16 = last.trim().parse().map_err(|_| invalid())?;
+    match (1980..=2100).contains(&first) && first <= last && last <= 2100 {
+        true => Ok((first, last)),
+        false => Err(invalid()),
+    }
+}
+
+impl Group {
+    // Whether the vehicle with `vin` belongs to the group; `now` decides the
+    // model year of VINs whose year code is used twice
+    pub fn contains(&self, vin: &str, now: u64) -> bool {
+        if self.vins.contains(&vin.to_uppercase()) {
+            return true;
+        }
+        let (Some(first), Some(last)) = (self.first_year, self.last_year) else { return false };
+        inspection::model_year(vin, now).is_some_and(|year| (first..=last).contains(&year))
+    }
+
+    // The model years of the cohort, e.g. "2015-2018"
+    pub fn years(&self) -> Option<String> {
+        match (self.first_year?, self.last_year?) {
+            (first, last) if first == last => Some(first.to_string()),
+            (first, last) => Some(format!("{}-{}", first, last)),
+        }
+    }
+}
+
+impl Groups {
+    pub fn open(data_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = data_dir.join(GROUPS_FILE);
+        let groups = match path.exists() {
+            true => serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+            false => BTreeMap::new(),
+        };
+        Ok(Groups { path, groups })
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.groups)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Group, Box<dyn Error>> {
+        self.groups.get(&check_name(name)?).ok_or_else(|| self.missing(name))
+    }
+
+    fn missing(&self, name: &str) -> Box<dyn Error> {
+        match self.groups.is_empty() {
+            true => format!("No vehicle group '{}'; none has been defined yet", name).into(),
+            false => format!(
+                "No vehicle group '{}'; defined: {}",
+                name, self.groups.keys().cloned().collect::<Vec<_>>().join(", ")
+            ).into(),
+        }
+    }
+
+    // The group with this name, created when it does not exist yet
+    pub fn entry(&mut self, name: &str) -> Result<&mut Group, Box<dyn Error>> {
+        Ok(self.groups.entry(check_name(name)?).or_default())
+    }
+
+    // Remove VINs from a group, or the whole group when no VIN is given;
+    // returns how many VINs were removed
+    pub fn remove(&mut self, name: &str, vins: &[String]) -> Result<usize, Box<dyn Error>> {
+        let key = check_name(name)?;
+        if vins.is_empty() {
+            let group = self.groups.remove(&key).ok_or_else(|| self.missing(name))?;
+            return Ok(group.vins.len());
+        }
+        let Some(group) = self.groups.get_mut(&key) else { return Err(self.missing(name)) };
+        let before = group.vins.len();
+        for vin in vins {
+            group.vins.remove(&vin.to_uppercase());
+        }
+        Ok(before - group.vins.len())
+    }
+}
+
+// The scans of vehicles in `group`, or every scan without one
+pub fn filter(records: Vec<ScanRecord>, group: Option<&Group>, now: u64) -> Vec<ScanRecord> {
+    match group {
+        Some(group) => records.into_iter().filter(|record| group.contains(&record.vin, now)).collect(),
+        None => records,
+    }
+}
+
+// List every group with the VINs added to it and how many archived vehicles it holds
+pub fn display(groups: &Groups, records: &[ScanRecord], now: u64) {
+    if groups.groups.is_empty() {
+        outln!("{}", tr!("group-none"));
+        return;
+    }
+    let archived: BTreeSet<&str> = records.iter().map(|record| record.vin.as_str()).collect();
+    for (name, group) in &groups.groups {
+        let vehicles = archived.iter().filter(|vin| group.contains(vin, now)).count();
+        outln!("{}  {}", name.styled(Style::Heading), tr!("group-vehicles", vehicles = vehicles).styled(Style::Muted));
+        if let Some(description) = &group.description {
+            outln!("  {}", description);
+        }
+        if let Some(years) = group.years() {
+            outln!("  {}", tr!("group-years", years = years));
+        }
+        if !group.vins.is_empty() {
+            outln!("  {}", group.vins.iter().cloned().collect::<Vec<_>>().join(" "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{scan, TempDir, FIXED_TIME};
+    use super::Groups;
+
+    #[test]
+    fn vehicle_groups_hold_vins_and_model_year_cohorts() {
+        let temp = TempDir::new("groups");
+        let dir = temp.join("data");
+        let mut defined = Groups::open(&dir).unwrap();
+        assert!(defined.get("north").unwrap_err().to_string().contains("none has been defined yet"));
+        defined.entry("North").unwrap().vins.insert("1HGCM82633A004352".to_string());
+        let cohort = defined.entry("cohort-2015").unwrap();
+        (cohort.first_year, cohort.last_year) = (Some(2015), Some(2018));
+        assert!(defined.entry("north region").is_err());
+        defined.save().unwrap();
+
+        let groups = Groups::open(&dir).unwrap();
+        let records = vec![
+            scan("1", "1HGCM82633A004352", 1, &["P0420"]),
+            scan("2", "2T1BURHE5FC123456", 2, &["P0300"]),
+            scan("3", "3VWDX7AJ9BM000001", 3, &["C0035"]),
+        ];
+        let vins = |group: &str| -> Vec<String> {
+            super::filter(records.clone(), Some(groups.get(group).unwrap()), FIXED_TIME).into_iter().map(|record| record.vin).collect()
+        };
+        assert_eq!(vins("north"), ["1HGCM82633A004352"]);
+        assert_eq!(vins("cohort-2015"), ["2T1BURHE5FC123456"]);
+        assert_eq!(super::filter(records.clone(), None, FIXED_TIME).len(), 3);
+        assert_eq!(groups.get("cohort-2015").unwrap().years().as_deref(), Some("2015-2018"));
+        assert!(groups.get("south").unwrap_err().to_string().contains("defined: cohort-2015, north"));
+
+        assert_eq!(super::parse_years("2016"), Ok((2016, 2016)));
+        assert!(super::parse_years("2018-2015").is_err());
+        let mut groups = groups;
+        assert_eq!(groups.remove("north", &["1hgcm82633a004352".to_string()]).unwrap(), 1);
+        assert_eq!(groups.remove("cohort-2015", &[]).unwrap(), 0);
+        assert_eq!(groups.groups.keys().collect::<Vec<_>>(), ["north"]);
+    }
+}
@@ -0,0 +1,90 @@
+// Vehicle health score: 100 for a clean scan, less for every code found.
+//
+// Each code costs the weight of its severity, multiplied by the weight of its
+// system, and codes missing from the database cost a fixed amount. The
+// `[health]` table in cars-scanner.toml overrides any of these weights.
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+use crate::presentation::Style;
+use crate::DiagnosticsDatabase;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthWeights {
+    // Points lost per code of each severity
+    pub severity: HashMap<String, f64>,
+
+    // Multipliers for systems that matter more or less, e.g. Airbag = 1.5
+    pub system: HashMap<String, f64>,
+
+    // Points lost per code that is not in the database
+    pub unknown: f64,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        let severity = [("Low", 5.0), ("Medium", 15.0), ("High", 30.0), ("Critical", 60.0)]
+            .into_iter()
+            .map(|(severity, weight)| (severity.to_string(), weight))
+            .collect();
+        HealthWeights { severity, system: HashMap::new(), unknown: 10.0 }
+    }
+}
+
+pub struct Health {
+    // 0 to 100
+    pub score: u8,
+    pub verdict: String,
+    pub style: Style,
+}
+
+fn lookup_weight(weights: &HashMap<String, f64>, key: &str) -> Option<f64> {
+    weights.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, weight)| *weight)
+}
+
+impl HealthWeights {
+    // Score the codes read in one scan
+    pub fn score(&self, codes: &[String], db: &DiagnosticsDatabase) -> Health {
+        let penalty: f64 = codes
+            .iter()
+            .map(|code| match db.lookup_error(code) {
+                Some(error) => {
+                    let severity = lookup_weight(&self.severity, &error.severity).unwrap_or(self.unknown);
+                    severity * lookup_weight(&self.system, &error.system).unwrap_or(1.0)
+                },
+                None => self.unknown,
+            })
+            .sum();
+        let score = (100.0 - penalty).clamp(0.0, 100.0).round() as u8;
+
+        let (verdict, style) = match score {
+            90.. => (tr!("health-good"), Style::Success),
+            70.. => (tr!("health-fair"), Style::SeverityMedium),
+            40.. => (tr!("health-poor"), Style::SeverityHigh),
+            _ => (tr!("health-critical"), Style::SeverityCritical),
+        };
+        Health { score, verdict, style }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{sample_database, init};
+
+    #[test]
+    fn health_score_weights_severity_and_system() {
+        init();
+        let db = sample_database();
+        let codes = |codes: &[&str]| codes.iter().map(|code| code.to_string()).collect::<Vec<_>>();
+        let mut weights = super::HealthWeights::default();
+        assert_eq!(weights.score(&[], &db).score, 100);
+        // Medium 15 + High 30 + unknown 10
+        assert_eq!(weights.score(&codes(&["P0101", "P0300", "U9999"]), &db).score, 45);
+
+        weights.system.insert("airbag".to_string(), 2.0);
+        let health = weights.score(&codes(&["B0001"]), &db);
+        assert_eq!(health.score, 0);
+        assert_eq!(health.verdict, "Critical: inspect before driving");
+    }
+}
@@ -0,0 +1,1056 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command as Process, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use clap::Command;
+use clap_mangen::Man;
+use clap_mangen::roff::{bold, italic, roman, Roff};
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+
+// An extended help topic shown by `help <topic>` and emitted as a man page
+pub struct HelpTopic {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub body: &'static str,
+}
+
+pub const TOPICS: &[HelpTopic] = &[
+    HelpTopic {
+        name: "database",
+        title: "Error code database format",
+        body: "\
+The diagnostics database is a CSV file with a header row. It is loaded
+from src/data/error_codes.csv relative to the working directory.
+
+Columns:
+  code                 The diagnostic trouble code, e.g. P0420
+  description          Short description of the fault
+  severity             One of Low, Medium, High or Critical
+  system               Affected vehicle system, e.g. Engine, Fuel, Emissions
+  possible_causes      Causes separated by '|'
+  recommended_actions  Actions separated by '|'
+
+Optional columns (may be left out of the header entirely):
+  emissions_warranty   true or false; empty when unknown
+  service_campaigns    Service campaign numbers separated by '|'
+  campaign_links       Campaign or bulletin URLs separated by '|'
+  related_parts        Parts as 'name:OEM part number' separated by '|'
+  set_conditions       Operating conditions under which the code typically sets
+  monitors             Readiness monitors that run the test, e.g. catalyst|o2-sensor
+  aliases              Other names for the code separated by '|', e.g. cat code
+  difficulty           DIY, Intermediate or Professional; empty when not rated.
+                       'list --max-difficulty diy' leaves out the others
+  safety_warnings      Hazards of the work separated by '|', e.g. Relieve fuel
+                       pressure first; shown in a block of their own before the
+                       other fields in every output, whatever [layout] hides
+
+Example row:
+  P0420,\"Catalyst System Efficiency Below Threshold\",Medium,Emissions,\"Failing catalytic converter|Faulty O2 sensor\",\"Check O2 sensors|Inspect catalytic converter\"
+
+Fields containing commas must be quoted. Codes are matched exactly, so
+keep them upper case.
+
+The first line names the layout the file uses, '# cars-scanner schema 5'.
+Schema 2 added set_conditions and monitors, schema 3 aliases, schema 4
+difficulty and schema 5 safety_warnings. A file from a newer version of
+cars-scanner is refused with an error instead of being read with its new
+columns dropped. 'db migrate' (with --pack for a pack) upgrades an older
+file: it shows the steps, then adds the schema line and the missing
+columns.
+
+A second line, '# cars-scanner updated 2026-09-30', records the day the
+codes were last brought up to date; 'db update' (with --pack for a pack)
+sets it to today. Files without it are dated by when they were last
+modified. Commands that load a file older than [freshness] max_age
+(default 180d; 0 turns the warning off) print a warning, 'doctor' lists
+it, and 'db update --check' shows the age of every file and exits with
+status 1 when one is stale.
+
+Descriptions in other languages live in src/data/translations/<lang>.csv
+with the columns code and description. With --lang sv, each code shows
+its Swedish description below the English one, and 'search' and
+'reverse' match both, as well as the aliases. Matching ignores case in
+any alphabet, so FELTÄNDNING finds feltändning.
+
+'db add <code> --description <text> --system <system>' appends a code.
+Without --severity, one is suggested from the codes with the most similar
+descriptions. 'db validate' checks every code and warns about severities
+that disagree with similar codes. 'db sample' shows 25 codes picked at
+random (--n for more) to read through after an import; --where narrows
+the pick to a system or severity, and --seed repeats an earlier sample:
+
+  cars-scanner db sample --n 10 --seed 42 --where system=Engine --where severity=High
+
+'db export --canonical' writes the database (or --pack <name>) sorted by
+code with every value normalized: trimmed, lists without spaces around '|',
+severities spelled as above and prose columns always quoted. Exporting a
+file kept in git this way after an edit keeps the diff to the rows that
+actually changed:
+
+  cars-scanner db export --canonical --pack toyota -o packs/toyota.csv
+
+'db import <file>' reads CSV, or JSON as 'db export' writes it, into the
+database (or --pack <name>): new codes are added and existing ones
+updated, keeping the columns the file does not have. It first lists how
+many codes would be added, updated or left alone, a sample of each, codes
+the file defines twice in different ways, and rows that fail validation,
+which are not imported. Add --dry-run to stop after the preview.
+
+For databases of tens of thousands of codes, 'db import <file> --sqlite
+codes.sqlite' converts the file, with the configured packs layered over
+it, to an indexed SQLite database (in builds with the sqlite feature).
+With database = \"codes.sqlite\" in cars-scanner.toml, lookup, list,
+query, list-by-system and list-by-severity read only the codes they show
+from it, and search only the codes holding its keyword. reverse and
+other commands still load every code into memory, from it instead of
+the bundled database and packs.
+Commands warn when one of those changed since; 'db import --sqlite
+codes.sqlite --refresh' converts them again.
+
+Setting parts_api_url in cars-scanner.toml adds an availability and
+pricing link to every part listed by 'parts <code>'. The {part_number}
+and {name} placeholders are replaced with the URL-encoded values:
+
+  parts_api_url = \"https://parts.example.com/search?pn={part_number}\"
+
+'parts <code> --fetch' requests each link and shows the start of the
+response. Online requests share one client: responses are cached in
+<data_dir>/http-cache, requests to a host are spaced out and temporary
+failures are retried. The [network] table tunes this, and --offline
+guarantees that nothing is fetched (cached responses are still shown):
+
+  [network]
+  proxy = \"http://proxy.example.com:3128\"
+  min_interval_ms = 1000
+  retries = 3
+  timeout_secs = 15
+  cache_ttl_secs = 86400
+
+Packs are further CSV files in the same format, layered over the bundled
+database in the order listed. A code defined by a later pack replaces the
+earlier definition; 'lookup --code <code> --all-sources' compares them:
+
+  [[packs]]
+  path = \"packs/toyota.csv\"
+  name = \"toyota\"
+  version = \"2024.1\"
+  precedence = 10
+  makes = [\"Toyota\", \"Lexus\"]
+
+A pack with makes only applies to those makes once 'vehicle set' in
+interactive mode names a vehicle; without makes it applies to every one.
+
+When several sources define a code, each field is taken from the source
+with the highest precedence that sets it (the bundled database is 0, packs
+default to their position in the list). The [merge] table overrides this
+per field with \"highest\", \"prefer:<pack>\" or, for '|' lists, \"union\";
+'db explain-merge <code>' shows which source supplied each field:
+
+  [merge]
+  description = \"prefer:toyota\"
+  possible_causes = \"union\"
+
+'db contribute' bundles the codes your packs add or change into
+contribution.json, with a diff of every edited code in contribution.md for
+review. --against compares with a fresh copy of the upstream database
+instead of the bundled one, and --pack limits the bundle to one pack. Only
+the columns above are exported; other pack columns, such as shop notes,
+stay local.",
+    },
+    HelpTopic {
+        name: "adapter",
+        title: "Adapter setup",
+        body: "\
+ELM327-compatible adapters are supported over USB, Bluetooth (as a serial
+port) and Wi-Fi. In interactive mode:
+
+  connect /dev/ttyUSB0      USB adapter; COM3 on Windows
+  connect /dev/rfcomm0      Bluetooth adapter bound with rfcomm
+  connect 192.168.0.10:35000
+                            Wi-Fi adapter
+  status                    Protocol, battery voltage, ECUs found, and what
+                            the adapter or vehicle does not support
+  monitor coolant rpm       Live data until Enter is pressed
+  monitor --tui rpm map     The same as gauges, with alerts and new codes
+                            in a banner; space pauses, p chooses PIDs
+  drive-cycle toyota        Readiness, then drive cycle steps until done
+  analyze fuel-trims        Fuel trims at idle and 2500 rpm, explained
+  scan                      MIL, then each code as confirmed, pending or
+                            permanent, with its UDS status and counters
+                            on CAN
+  o2-tests                  Oxygen sensor and catalyst test results
+  disconnect                Release the adapter
+
+'connect' resets the adapter and lets it find the vehicle's protocol,
+which takes a few seconds with the ignition on. Most USB adapters talk at
+38400 baud; pass another speed as 'connect <port> <baud>'. The default
+port, speed and response timeout are set in cars-scanner.toml:
+
+  [adapter]
+  port = \"/dev/ttyUSB0\"
+  baud = 38400
+  timeout_secs = 10
+
+Outside interactive mode, 'scan --connect <port>' reads the codes of one
+vehicle and saves them to the scan archive. Repeat --connect to scan
+several vehicles plugged into a fleet gateway at once: each adapter is
+read on its own, with a spinner per vehicle, and a combined list shows
+every vehicle's VIN, MIL and codes at the end.
+
+A scan reads every ECU that answers. On CAN, 'scan --module <name>' reads
+one, and 'clear --module <name>' clears the codes, freeze frame and MIL of
+that one only, leaving the evidence on the others; 'clear --all' clears
+them all. The codes erased are logged as a 'clear' event. engine (7E0)
+and transmission (7E1) are the same on every vehicle; the addresses of
+other modules vary by make, so name them, or give the address itself:
+
+  [adapter.modules]
+  abs = \"7B0\"
+  hybrid = \"7E2\"
+
+On CAN vehicles with UDS diagnostics, each code also shows on how many
+drive cycles its test failed and on how many it has passed since, and
+whether it is a hard fault (failing on this drive cycle), intermittent
+(failed on several but passing now) or a one-off that has not come back.
+
+A scan also shows the distance driven and the engine running time since
+the codes were cleared, and since the MIL came on (PIDs 31, 4E, 21 and
+4D). Codes cleared less than 200 km, or three hours of running, ago are
+flagged: the monitors cannot have run since, and a clean result may hide
+a fault erased before a sale. 'connect sim:recently-cleared' shows one.
+
+Ctrl-C stops a scan, monitor session, drive cycle watch or download
+without leaving interactive mode: a monitor session still shows its
+summary, and the adapter closes the protocol (ATPC) so the ECUs end
+their diagnostic session. A second Ctrl-C quits at once.
+
+'monitor' (also 'cars-scanner monitor --port <port>') can guard a dyno
+run or test drive. A value crossing a threshold is shown as an ALERT,
+logged to .cars-scanner/monitor.log and sent to the webhook and MQTT
+topic when they are set:
+
+  [monitor]
+  pids = [\"rpm\", \"speed\", \"coolant\", \"voltage\"]
+  interval_ms = 1000
+  webhook = \"https://hooks.example.com/dyno\"
+  mqtt = \"mqtt://broker.local/shop/dyno\"
+
+  [[monitor.thresholds]]
+  pid = \"coolant\"
+  above = 110
+
+  [[monitor.thresholds]]
+  pid = \"voltage\"
+  below = 12.0
+
+PIDs: load, coolant, stft1, ltft1, stft2, ltft2, map, rpm, speed, intake,
+maf, throttle, fuel, voltage, oil.
+
+After codes are cleared, the readiness monitors must run again before an
+inspection. 'drive-cycle' reads which ones are incomplete and shows the
+drive cycle for each, using the make's own steps where they are known
+(--make). With --watch it checks readiness every few seconds while you
+drive. Without an adapter it lists the steps for every monitor, or the
+ones named with --monitor. Steps come from src/data/drive_cycles.yaml;
+set drive_cycles in cars-scanner.toml to use another file.
+
+'analyze fuel-trims' adds short and long term fuel trims at idle and at
+2500 rpm. Lean only at idle points to a vacuum leak, lean only at 2500 rpm
+to fuel delivery, lean at both to the MAF sensor; the lean or rich codes
+each pattern sets are listed. Instead of connecting, pass --log with a
+drive log CSV whose columns are rpm, stft1 and ltft1 (stft2 and ltft2 for
+bank 2); Torque's column headings work as well.
+
+'o2-tests' reads the results of the last oxygen sensor and catalyst
+monitor runs: mode 06 on CAN vehicles, mode 05 on older protocols. Each
+test is shown per bank and sensor with its limits and PASS or FAIL, and
+the code a failure leads to (e.g. P0133 for a slow sensor, P0420 for the
+catalyst) is listed with its description.",
+    },
+    HelpTopic {
+        name: "reports",
+        title: "Report templates",
+        body: "\
+Reports are produced with 'lookup --code <code> --export <file>'. The
+format is chosen from the file extension:
+
+  .html   A styled HTML page with one section per error code
+  other   Plain text with the same sections
+
+HTML pages open light or dark as the viewer's system is set, and a button
+at the top switches between the two; the browser remembers the choice.
+Printed, they are black on white without the button, and each error code
+starts on a new page.
+
+'report <code> --output <file>' writes the same report for a repair order,
+with the customer, repair order number, odometer reading and date at the
+top. Pass them as --customer, --ro and --odometer; in a terminal, any that
+are missing are asked for, and an empty answer leaves them out.
+
+'report --type warranty-claim --output <file>' reads the vehicle on the
+adapter (--port or the [adapter] port) and writes a claim for the
+manufacturer's warranty portal: the VIN, the --odometer mileage, --ro and
+the date, every code with its state (confirmed, pending, permanent), the
+freeze frame with the code that stored it, and the notes of --workspace
+<name> as the repair narrative. --oem picks the portal's layout from
+src/data/warranty_templates.yaml (generic CSV or generic-xml); add a
+layout for another portal there, or in your own file named by
+warranty_templates, and set warranty_oem to make it the default.
+
+Both formats contain the code, description, severity, system, possible
+causes and recommended actions. When the labor guide has operations for
+the code, a labor section with op codes and flat-rate hours follows.
+
+The labor guide is a CSV file with the columns code, action, op_code,
+description and hours. The bundled guide is src/data/labor_ops.csv; set
+labor_guide in cars-scanner.toml to use your own. Leave action empty for
+operations that apply to the code as a whole.
+
+'report' and 'fleet report' only write their file when something it is
+made from changed: the code's definition and its database or pack, the
+archived scans, the labor guide, branding, layout, repair order details,
+language or version of cars-scanner. The inputs of every file written are
+kept as a tag in report-state.json in the data directory; a nightly run
+with nothing new leaves the reports untouched. --force writes them anyway.
+
+Every exported report, including search results and the fleet dashboard,
+can carry the shop's details. Set them in the [branding] table of
+cars-scanner.toml; the logo is embedded in HTML reports so the file can be
+sent on its own:
+
+  [branding]
+  shop_name = \"Main Street Auto\"
+  address = \"\"\"
+  12 Main Street
+  Springfield\"\"\"
+  logo = \"logo.png\"
+  disclaimer = \"Estimates are valid for 30 days.\"
+
+The [layout] table orders and hides the fields of a code wherever it is
+shown in full. Listed fields come first, the rest follow as usual; a
+[layout.report] table gives text and HTML reports their own layout, e.g.
+part numbers first at the counter and no recommended actions for
+customers. The fields are description, severity, system, source,
+possible_causes, recommended_actions, set_context, related_parts and
+warranty:
+
+  [layout]
+  order = [\"related_parts\"]
+
+  [layout.report]
+  hide = [\"recommended_actions\", \"source\"]
+
+The safety warnings of a code are not a layout field: they always follow
+the code itself, in a block of their own.
+
+'inspection --port <port>' reads the MIL, readiness monitors, stored,
+pending and permanent codes and the VIN, and lays them out as an
+emissions inspection report with an overall PASS or FAIL. The wording,
+the number of incomplete monitors allowed per model year and whether
+permanent codes fail the vehicle come from a template per jurisdiction:
+generic, california, eu and sweden are bundled in
+src/data/inspection_templates.yaml. Choose one with --jurisdiction or
+set a default, and point inspection_templates at your own file:
+
+  jurisdiction = \"california\"
+  inspection_templates = \"inspection.yaml\"
+
+Reports meant to be shared, on a forum or with a bug report, can leave
+out personal data: pass --anonymize to report, inspection, fleet report or
+fleet export-app. By default the VIN keeps only its first 11 characters
+(make, model and model year), the customer is left out, the repair order
+number becomes a pseudonym and the odometer is rounded down to a thousand.
+Each field can be kept, removed, hashed or masked in the [anonymize]
+table, and patterns redact anything else, such as number plates:
+
+  [anonymize]
+  vin = \"hash\"
+  salt = \"our-shop\"
+  patterns = [\"\\\\b[A-Z]{3} ?\\\\d{3}\\\\b\"]
+
+All of these settings live in cars-scanner.toml, or the file given with
+--config. An environment variable CARS_SCANNER_<KEY> overrides a setting,
+with '__' between table names (CARS_SCANNER_ADAPTER__BAUD=9600); values
+are read as TOML and otherwise as text. 'config show --resolved' prints
+every effective setting and whether it came from the defaults, the file or
+a variable.",
+    },
+    HelpTopic {
+        name: "fleet",
+        title: "Fleet imports",
+        body: "\
+'fleet import <file>' reads a telematics dump and stores one scan per
+vehicle in the archive (<data_dir>/archive, .cars-scanner by default).
+
+CSV dumps need a 'vin' column and a 'code', 'codes', 'dtc' or 'dtcs'
+column. A vehicle may appear on many rows, and a row may hold several
+codes separated by spaces, commas, semicolons or '|':
+
+  vin,code
+  1HGCM82633A004352,P0420
+  1HGCM82633A004352,P0300 P0171
+
+JSON dumps (files ending in .json) are an array of objects:
+
+  [{\"vin\": \"1HGCM82633A004352\", \"codes\": [\"P0420\", \"P0300\"]}]
+
+'fleet export-app torque' or 'fleet export-app car-scanner' writes the
+archived scans as a fault code CSV in the layout of that app (add --vin to
+export one vehicle). Both layouts can be imported again, including Car
+Scanner's semicolon-separated files.
+
+Vehicle groups split a fleet into the parts looked at separately, such as
+regions, routes or model-year cohorts. 'fleet group add <name> <vin>...'
+adds vehicles to a group (creating it), 'fleet import <file> --group
+<name>' adds every imported vehicle, and --years 2015-2018 makes the group
+hold every vehicle whose VIN gives a model year in that range. 'fleet
+group remove <name> [<vin>...]' takes vehicles out or deletes the group,
+and 'fleet group list' shows them all. 'fleet report --group <name>' and
+'fleet export-app --group <name>' only look at the group's vehicles.
+
+After importing, a summary lists the most frequent codes, codes missing
+from the database and every vehicle with a critical code.
+
+'fleet history <vin>' lists every archived scan of a vehicle with a health
+score from 0 to 100 and the change since the previous scan; 'scan' ends
+with the score of the codes it read. Each code costs points by
+severity, multiplied per system; the [health] table overrides the weights:
+
+  [health]
+  unknown = 10
+  severity = { Low = 5, Medium = 15, High = 30, Critical = 60 }
+  system = { Airbag = 1.5, Brakes = 1.5 }
+
+A fleet can rate codes its own way with a severity policy: a TOML file
+named by severity_policy in cars-scanner.toml. The first rule matching a
+code, by code pattern (* for any characters), by system or both, sets its
+severity wherever it is used, including health scores and reports, and
+the code says it was adjusted by policy. The database is left unchanged:
+
+  [[rule]]
+  codes = [\"P044*\", \"P045*\"]
+  severity = \"Low\"
+  reason = \"EVAP faults do not take a van off the road\"
+
+  [[rule]]
+  codes = [\"U*\"]
+  severity = \"High\"
+
+Every looked-up code also says when the archive last saw it and how
+often in the past year: on the vehicle set with 'vehicle set' or the
+connected one, else on any vehicle. On the command line, 'lookup --code
+<code> --vin <vin>' asks about one vehicle.
+
+Every scan stored, clear, database change and report written is also
+appended to an event log, <data_dir>/events.jsonl, one line of JSON each,
+flushed to disk at once. 'events tail' shows the latest (--follow keeps going)
+and 'events query --kind scan --vin <vin> --since 7d' finds them; --jsonl
+prints the lines as they are. The [events] table sets max_size (bytes)
+and keep, the number of rotated files kept; record = false turns it off.
+
+Scan times are stored and exported in ISO-8601 (UTC). Times shown to
+people, such as report dates and session notes, use the system time zone
+and the language's date format; the [time] table in cars-scanner.toml
+overrides both with an IANA zone name and strftime patterns:
+
+  [time]
+  timezone = \"Europe/Stockholm\"
+  date_format = \"%d %b %Y\"
+  datetime_format = \"%d %b %Y %H:%M %Z\"",
+    },
+    HelpTopic {
+        name: "troubleshoot",
+        title: "Decision trees",
+        body: "\
+'troubleshoot <code>' walks through the decision tree for a code, asking
+for measurements and yes/no answers until it reaches a conclusion. The
+path taken is appended to <data_dir>/session-notes.md.
+
+Trees are read from src/data/decision_trees.yaml, or from the file set as
+decision_trees in cars-scanner.toml. The file maps codes to a title and a
+list of steps; the walk starts at the first step:
+
+  P0171:
+    title: System Too Lean (Bank 1)
+    steps:
+      - id: fuel-trim
+        measure: Long-term fuel trim at idle
+        unit: \"%\"
+        threshold: 10
+        below: intermittent     # reading under the threshold
+        otherwise: vacuum-leak
+      - id: vacuum-leak
+        ask: Does the trim drop when propane is sprayed around the intake?
+        yes: fix-vacuum-leak
+        no: intermittent
+      - id: fix-vacuum-leak
+        conclusion: Repair the vacuum leak
+      - id: intermittent
+        conclusion: Look for an intermittent fault",
+    },
+    HelpTopic {
+        name: "query",
+        title: "Querying the codes with SQL",
+        body: "\
+'query' takes one SELECT statement over a table named codes, whose columns
+are those of the database file (see 'help database'):
+
+  SELECT <columns | *> FROM codes
+    [WHERE <condition>] [ORDER BY <column> [ASC | DESC], ...] [LIMIT <n>]
+
+A condition compares a column with a value in single quotes, or a number:
+
+  =  !=  <>  <  <=  >  >=   system = 'Engine', severity >= 'High'
+  LIKE                      description LIKE '%o2%heater%'; % is any text,
+                            _ one character
+  IN (...)                  severity IN ('High', 'Critical')
+  IS NULL, IS NOT NULL      the column is empty, or not
+
+NOT, AND, OR and parentheses combine conditions. Keywords, column names
+and comparisons ignore case. Severities and difficulties compare and sort
+by rank (Low to Critical, DIY to Professional), numbers as numbers and
+other values as text; empty values sort last. List columns such as
+possible_causes hold their entries separated by '|', so use LIKE to find
+one entry. Without ORDER BY the rows come in code order.
+
+  cars-scanner query \"SELECT code, severity FROM codes WHERE system = 'Engine'
+    AND severity IN ('High', 'Critical') ORDER BY code LIMIT 20\"
+  cars-scanner query \"SELECT code, difficulty FROM codes
+    WHERE difficulty <= 'Intermediate' AND safety_warnings IS NULL\"
+
+The rows are printed as a table; with --porcelain each is a 'row' record,
+and with --format json an object of the selected columns.",
+    },
+    HelpTopic {
+        name: "porcelain",
+        title: "Output for scripts",
+        body: "\
+--porcelain prints every result as one line of tab-separated fields,
+without colors, headings, counts or hints, for use in shell pipelines. It
+works with lookup, search, list, list-by-system, list-by-severity, query,
+extract, parts and config show --resolved. The first field names the
+record:
+
+  code     code, severity, system, description, safety warnings (joined by \" | \")
+  missing  code
+  part     code, part name, OEM part number (may be empty)
+  spn      SPN, FMI
+  setting  key, value, origin (config show --resolved)
+  row      the selected columns, in the order of the SELECT (query)
+
+The format is stable: within a major version a record keeps its name and
+field order, and new fields are only ever added at the end, so split on
+tabs and ignore fields you do not know. Tabs and line breaks inside a
+field are printed as spaces.
+
+  cars-scanner --porcelain list --severity critical | cut -f2,5",
+    },
+    HelpTopic {
+        name: "json",
+        title: "JSON output",
+        body: "\
+--format json prints results as JSON instead of colored text, for jq and
+dashboards. lookup prints the code as one object, or null when it is not
+in the database; search, list, list-by-system, list-by-severity and query
+print an array. Nothing else goes to standard output, and messages such
+as 'Report exported' go to standard error. A code has the fields
+
+  code, description, severity, system     text
+  possible_causes, recommended_actions    arrays of text
+  aliases, safety_warnings                arrays of text
+  translation, source                     text, left out when there is none
+
+Search results add their relevance 'score' and the fields a keyword was
+'matched' in; a query row has the selected columns as text. New fields
+may be added, but a field keeps its name and type within a major version.
+
+--format jsonl makes search write each result as a line of JSON as soon
+as it is found, in code order. db export takes --format csv or json.
+
+  cars-scanner --format json list --severity critical | jq -r '.[].code'
+  cars-scanner search --keyword sensor --format jsonl | jq -r .code",
+    },
+];
+
+// Find a help topic by name (case-insensitive)
+pub fn find_topic(name: &str) -> Option<&'static HelpTopic> {
+    TOPICS.iter().find(|topic| topic.name.eq_ignore_ascii_case(name))
+}
+
+// An interactive command as `help` lists it and `help <command>` explains it;
+// the interactive man page is written from the same entries
+pub struct CommandHelp {
+    pub name: &'static str,
+    pub forms: &'static [Form],
+    // Arguments and what they take
+    pub options: &'static [(&'static str, &'static str)],
+    // Lines as typed at the prompt, and what they do
+    pub examples: &'static [(&'static str, &'static str)],
+}
+
+// One way of calling a command
+pub struct Form {
+    // The words typed, e.g. "vehicle set"; empty for codes typed on their own
+    pub command: &'static str,
+    pub arguments: &'static str,
+    // Message id of the one-line description
+    pub summary: &'static str,
+}
+
+const fn form(command: &'static str, arguments: &'static str, summary: &'static str) -> Form {
+    Form { command, arguments, summary }
+}
+
+pub const INTERACTIVE_COMMANDS: &[CommandHelp] = &[
+    CommandHelp {
+        name: "lookup",
+        forms: &[form("lookup", "<code>...", "repl-help-lookup"), form("", "<code>...", "repl-help-bare")],
+        options: &[("<code>", "A trouble code such as P0420, in any case; with a vehicle set, its history is shown too")],
+        examples: &[
+            ("lookup P0420", "The catalyst code with its causes and recommended actions"),
+            ("p0300 p0301", "Two misfire codes, without typing lookup"),
+        ],
+    },
+    CommandHelp {
+        name: "pick",
+        forms: &[form("pick", "", "repl-help-pick")],
+        options: &[],
+        examples: &[("pick", "Type 'catalyst' and choose P0420 or P0430 from the list")],
+    },
+    CommandHelp {
+        name: "parts",
+        forms: &[form("parts", "<code>", "repl-help-parts")],
+        options: &[],
+        examples: &[("parts P0101", "Parts for the mass air flow code, with OEM numbers when a pack lists them")],
+    },
+    CommandHelp {
+        name: "compare",
+        forms: &[form("compare", "<code> <code>", "repl-help-compare")],
+        options: &[],
+        examples: &[
+            ("compare P0420 P0430", "Bank 1 and bank 2 catalyst codes, differences highlighted"),
+            ("compare P0300 P0301", "A random misfire next to a cylinder 1 misfire"),
+        ],
+    },
+    CommandHelp {
+        name: "troubleshoot",
+        forms: &[form("troubleshoot", "<code>", "repl-help-troubleshoot")],
+        options: &[],
+        examples: &[("troubleshoot P0300", "Answer yes or no at each step until a cause is found")],
+    },
+    CommandHelp {
+        name: "system",
+        forms: &[form("system", "<system_name>", "repl-help-system")],
+        options: &[("<system_name>", "Engine, Fuel, Emissions, Network, Transmission, Airbag, ...")],
+        examples: &[("system Emissions", "Every emissions code"), ("system network", "U codes for lost communication")],
+    },
+    CommandHelp {
+        name: "severity",
+        forms: &[form("severity", "<level>", "repl-help-severity")],
+        options: &[("<level>", "Low, Medium, High or Critical")],
+        examples: &[("severity critical", "Codes that should stop the car being driven")],
+    },
+    CommandHelp {
+        name: "search",
+        forms: &[form("search", "<keyword>", "repl-help-search")],
+        options: &[("<keyword>", "Matched in descriptions, causes, actions, aliases and translations")],
+        examples: &[("search misfire", "Every misfire code"), ("search oxygen", "O2 sensor circuits and heaters")],
+    },
+    CommandHelp {
+        name: "reverse",
+        forms: &[form("reverse", "<text>", "repl-help-reverse")],
+        options: &[("<text>", "The words shown, as many as were on the screen")],
+        examples: &[
+            ("reverse check engine flashing", "The likeliest codes behind a flashing MIL"),
+            ("reverse emission system problem", "What a dashboard message usually means"),
+        ],
+    },
+    CommandHelp {
+        name: "connect",
+        forms: &[form("connect", "[port] [baud]", "repl-help-connect")],
+        options: &[
+            ("[port]", "A serial device such as /dev/ttyUSB0 or COM3, host:port for a Wi-Fi adapter, or sim:<scenario>"),
+            ("[baud]", "Serial speed; [adapter] baud or 38400 by default"),
+        ],
+        examples: &[
+            ("connect /dev/ttyUSB0", "A USB ELM327 adapter"),
+            ("connect 192.168.0.10:35000", "A Wi-Fi adapter, after joining its network"),
+            ("connect sim:misfire-intermittent", "Practice on a car with an intermittent misfire"),
+        ],
+    },
+    CommandHelp {
+        name: "disconnect",
+        forms: &[form("disconnect", "", "repl-help-disconnect")],
+        options: &[],
+        examples: &[("disconnect", "Close the protocol and free the port")],
+    },
+    CommandHelp {
+        name: "status",
+        forms: &[form("status", "", "repl-help-status")],
+        options: &[],
+        examples: &[("status", "Check the battery voltage before a scan")],
+    },
+    CommandHelp {
+        name: "monitor",
+        forms: &[form("monitor", "[--tui] [pid]...", "repl-help-monitor")],
+        options: &[
+            ("[pid]", "rpm, speed, coolant, load, maf, throttle, stft1, ltft1, ...; [monitor] pids by default"),
+            ("--tui", "Gauges and sparklines; space pauses, p chooses PIDs, q leaves"),
+        ],
+        examples: &[
+            ("monitor", "The configured PIDs with their thresholds"),
+            ("monitor rpm coolant", "Engine speed and temperature while warming up"),
+            ("monitor stft1 ltft1", "Fuel trims of bank 1 under load"),
+            ("monitor --tui rpm coolant map", "Gauges on the bench, with new codes in a banner"),
+        ],
+    },
+    CommandHelp {
+        name: "scan",
+        forms: &[form("scan", "", "repl-help-scan")],
+        options: &[],
+        examples: &[("scan", "The MIL, codes and how long ago they were cleared")],
+    },
+    CommandHelp {
+        name: "vehicle",
+        forms: &[
+            form("vehicle set", "<VIN | make [model] [year]>", "repl-help-vehicle-set"),
+            form("vehicle clear", "", "repl-help-vehicle-clear"),
+        ],
+        options: &[],
+        examples: &[
+            ("vehicle set 1FTFW1ET5DFC10312", "Decode the make and year from the VIN"),
+            ("vehicle set Toyota Corolla 2015", "A car without its VIN at hand"),
+        ],
+    },
+    CommandHelp {
+        name: "garage",
+        forms: &[
+            form("garage unlink", "[scan-id]", "repl-help-garage-unlink"),
+            form("garage move", "[scan-id] --to <VIN>", "repl-help-garage-move"),
+        ],
+        options: &[("[scan-id]", "As 'fleet history' shows it; by default the scan last added in this session")],
+        examples: &[
+            ("garage unlink", "Take back the lookup just added to the wrong car"),
+            ("garage move 18f2c4a9e01b7d30 --to 1FTFW1ET5DFC10312", "File a scan under the right VIN"),
+        ],
+    },
+    CommandHelp {
+        name: "results",
+        forms: &[form("results", "", "repl-help-results")],
+        options: &[],
+        examples: &[("results", "The codes the last search found, again")],
+    },
+    CommandHelp {
+        name: "note",
+        forms: &[form("note", "<text>", "repl-help-note"), form("notes", "", "repl-help-notes")],
+        options: &[],
+        examples: &[("note replaced the MAF sensor, waiting for parts", "A note kept with the workspace")],
+    },
+    CommandHelp {
+        name: "workspace",
+        forms: &[
+            form("workspace save", "[name]", "repl-help-workspace-save"),
+            form("workspace load", "<name>", "repl-help-workspace-load"),
+            form("workspace list", "", "repl-help-workspace-list"),
+        ],
+        options: &[("[name]", "Letters, digits, '-' and '_'")],
+        examples: &[
+            ("workspace save bay3", "Keep the car on bay 3 for tomorrow"),
+            ("workspace load bay3", "Pick it up again; connect to the adapter afterwards"),
+        ],
+    },
+    CommandHelp {
+        name: "resume",
+        forms: &[form("resume", "", "repl-help-resume")],
+        options: &[],
+        examples: &[("resume", "Back to the session the idle screen put away")],
+    },
+    CommandHelp {
+        name: "o2-tests",
+        forms: &[form("o2-tests", "", "repl-help-o2-tests")],
+        options: &[],
+        examples: &[("o2-tests", "Check the downstream sensor before replacing a catalyst")],
+    },
+    CommandHelp {
+        name: "analyze",
+        forms: &[form("analyze fuel-trims", "", "repl-help-analyze")],
+        options: &[],
+        examples: &[("analyze fuel-trims", "Tell a vacuum leak from a weak fuel pump")],
+    },
+    CommandHelp {
+        name: "drive-cycle",
+        forms: &[form("drive-cycle", "[make]", "repl-help-drive-cycle")],
+        options: &[("[make]", "Steps from the manufacturer; the vehicle's make by default")],
+        examples: &[("drive-cycle", "Generic steps, with readiness when connected"), ("drive-cycle toyota", "Toyota's own steps")],
+    },
+    CommandHelp {
+        name: "transcript",
+        forms: &[form("transcript save", "<file.md>", "repl-help-transcript")],
+        options: &[],
+        examples: &[("transcript save visit.md", "The session so far, for the repair order")],
+    },
+    CommandHelp {
+        name: "help",
+        forms: &[form("help", "", "repl-help-help"), form("help", "<command | topic>", "repl-help-topic")],
+        options: &[],
+        examples: &[("help connect", "How to attach to an adapter"), ("help database", "The database file format")],
+    },
+    CommandHelp {
+        name: "exit",
+        forms: &[form("exit", "", "repl-help-exit")],
+        options: &[],
+        examples: &[],
+    },
+];
+
+// Find an interactive command by name or by the words of one of its forms
+pub fn find_command(name: &str) -> Option<&'static CommandHelp> {
+    let name = name.trim();
+    INTERACTIVE_COMMANDS.iter().find(|command| {
+        command.name.eq_ignore_ascii_case(name) || command.forms.iter().any(|form| form.command.eq_ignore_ascii_case(name))
+    })
+}
+
+// A form as typed, with the command and its arguments styled
+fn styled_form(form: &Form) -> String {
+    match (form.command.is_empty(), form.arguments.is_empty()) {
+        (true, _) => form.arguments.styled(Style::Argument).to_string(),
+        (false, true) => form.command.styled(Style::Command).to_string(),
+        (false, false) => format!("{} {}", form.command.styled(Style::Command), form.arguments.styled(Style::Argument)),
+    }
+}
+
+// The flat list shown by `help`
+pub fn display_commands() {
+    outln!("{}", tr!("repl-commands").styled(Style::Heading));
+    for command in INTERACTIVE_COMMANDS {
+        for form in command.forms {
+            outln!("  {} - {}", styled_form(form), tr!(form.summary));
+        }
+        if command.name == "help" {
+            for topic in TOPICS {
+                outln!("      {} - {}", topic.name.styled(Style::Argument), topic.title);
+            }
+        }
+    }
+}
+
+// Syntax, options and examples of one command, as `help <command>` shows them
+pub fn display_command(command: &CommandHelp) {
+    outln!("{}", tr!("help-usage").styled(Style::Section));
+    for form in command.forms {
+        outln!("  {}", styled_form(form));
+        outln!("      {}", tr!(form.summary));
+    }
+    if !command.options.is_empty() {
+        outln!();
+        outln!("{}", tr!("help-options").styled(Style::Section));
+        let width = command.options.iter().map(|(option, _)| option.chars().count()).max().unwrap_or_default();
+        for (option, text) in command.options {
+            outln!("  {}  {}", format!("{:<width$}", option, width = width).styled(Style::Argument), text);
+        }
+    }
+    if !command.examples.is_empty() {
+        outln!();
+        outln!("{}", tr!("help-examples").styled(Style::Section));
+        for (line, text) in command.examples {
+            outln!("  {} {}", ">".styled(Style::Hint), line.styled(Style::Command));
+            outln!("      {}", text.styled(Style::Muted));
+        }
+    }
+}
+
+// Set by --no-pager
+static PAGER_DISABLED: AtomicBool = AtomicBool::new(false);
+
+// Print everything directly instead of paging
+pub fn disable_pager() {
+    PAGER_DISABLED.store(true, Ordering::Relaxed);
+}
+
+fn terminal_height() -> usize {
+    env::var("LINES")
+        .ok()
+        .and_then(|lines| lines.parse::<usize>().ok())
+        .unwrap_or(24)
+        .max(2)
+}
+
+// Page text only when it is taller than the terminal
+pub fn page_if_long(text: &str) -> Result<(), Box<dyn Error>> {
+    if text.lines().count() < terminal_height() {
+        crate::transcript::record(text);
+        println!("{}", text);
+        return Ok(());
+    }
+    page(text)
+}
+
+// Show text through $PAGER, or page it internally when stdout is a terminal
+pub fn page(text: &str) -> Result<(), Box<dyn Error>> {
+    crate::transcript::record(text);
+    
+    if PAGER_DISABLED.load(Ordering::Relaxed) || !io::stdout().is_terminal() {
+        println!("{}", text);
+        return Ok(());
+    }
+
+    if let Ok(pager) = env::var("PAGER") {
+        let mut parts = pager.split_whitespace();
+        if let Some(program) = parts.next() {
+            let mut command = Process::new(program);
+            command.args(parts).stdin(Stdio::piped());
+            // Let less show colors and quit when everything fits on one screen
+            if env::var_os("LESS").is_none() {
+                command.env("LESS", "FRX");
+            }
+            if let Ok(mut child) = command.spawn() {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // The pager may quit before reading everything
+                    let _ = stdin.write_all(text.as_bytes());
+                }
+                child.wait()?;
+                return Ok(());
+            }
+        }
+    }
+
+    let height = terminal_height();
+
+    let stdin = io::stdin();
+    let lines: Vec<&str> = text.lines().collect();
+    for (index, chunk) in lines.chunks(height - 1).enumerate() {
+        for line in chunk {
+            println!("{}", line);
+        }
+
+        if (index + 1) * (height - 1) < lines.len() {
+            print!("-- More -- (Enter to continue, q to quit) ");
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            stdin.lock().read_line(&mut answer)?;
+            if answer.trim().eq_ignore_ascii_case("q") {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Render a help topic as a section 7 man page
+fn topic_man_page(bin_name: &str, topic: &HelpTopic) -> String {
+    let mut roff = Roff::new();
+    roff.control("TH", [format!("{}-{}", bin_name, topic.name).to_uppercase().as_str(), "7"]);
+    roff.control("SH", ["NAME"]);
+    roff.text([roman(format!("{}-{} - {}", bin_name, topic.name, topic.title))]);
+    roff.control("SH", ["DESCRIPTION"]);
+    for paragraph in topic.body.split("\n\n") {
+        roff.control("PP", []);
+        roff.control("nf", []);
+        roff.text([roman(paragraph)]);
+        roff.control("fi", []);
+    }
+    roff.render()
+}
+
+// Render the interactive commands as a section 7 man page
+fn interactive_man_page(bin_name: &str) -> String {
+    let mut roff = Roff::new();
+    roff.control("TH", [format!("{}-interactive", bin_name).to_uppercase().as_str(), "7"]);
+    roff.control("SH", ["NAME"]);
+    roff.text([roman(format!("{}-interactive - Commands of interactive mode", bin_name))]);
+    roff.control("SH", ["SYNOPSIS"]);
+    roff.text([bold(format!("{} interactive", bin_name))]);
+    roff.control("SH", ["COMMANDS"]);
+    for command in INTERACTIVE_COMMANDS {
+        for form in command.forms {
+            roff.control("TP", []);
+            roff.text([bold(form.command), roman(" "), italic(form.arguments)]);
+            roff.text([roman(tr!(form.summary))]);
+        }
+        for (option, text) in command.options {
+            roff.control("RS", []);
+            roff.control("TP", []);
+            roff.text([italic(*option)]);
+            roff.text([roman(*text)]);
+            roff.control("RE", []);
+        }
+    }
+    roff.control("SH", ["EXAMPLES"]);
+    for command in INTERACTIVE_COMMANDS {
+        for (line, text) in command.examples {
+            roff.control("TP", []);
+            roff.text([bold(format!("> {}", line))]);
+            roff.text([roman(*text)]);
+        }
+    }
+    roff.render()
+}
+
+// Write man pages for the command, every subcommand and every help topic
+pub fn write_man_pages(cmd: Command, out_dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let mut written = Vec::new();
+
+    let bin_name = cmd.get_name().to_string();
+    let mut pages = vec![(bin_name.clone(), cmd.clone())];
+    for sub in cmd.get_subcommands() {
+        let name = format!("{}-{}", bin_name, sub.get_name());
+        let page_cmd = sub.clone().display_name(name.clone()).bin_name(format!("{} {}", bin_name, sub.get_name()));
+        pages.push((name, page_cmd));
+    }
+
+    for (name, page_cmd) in pages {
+        let mut buffer = Vec::new();
+        Man::new(page_cmd).render(&mut buffer)?;
+        let file = out_dir.join(format!("{}.1", name));
+        fs::write(&file, buffer)?;
+        written.push(file.display().to_string());
+    }
+
+    let file = out_dir.join(format!("{}-interactive.7", bin_name));
+    fs::write(&file, interactive_man_page(&bin_name))?;
+    written.push(file.display().to_string());
+
+    for topic in TOPICS {
+        let file = out_dir.join(format!("{}-{}.7", bin_name, topic.name));
+        fs::write(&file, topic_man_page(&bin_name, topic))?;
+        written.push(file.display().to_string());
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::{completion, i18n};
+    use crate::testing::{init, TempDir};
+
+    #[test]
+    fn every_interactive_command_has_help_with_examples() {
+        init();
+        for name in completion::COMMANDS.iter().filter(|name| **name != "quit") {
+            let command = super::find_command(name).unwrap_or_else(|| panic!("no help for {}", name));
+            assert!(command.forms.iter().all(|form| !i18n::translate(form.summary, None).eq(form.summary)), "{}", name);
+            if *name != "exit" {
+                assert!(!command.examples.is_empty(), "no examples for {}", name);
+            }
+        }
+        assert_eq!(super::find_command("workspace load").map(|command| command.name), Some("workspace"));
+        assert!(super::find_command("database").is_none());
+
+        let dir = TempDir::new("man");
+        let written = super::write_man_pages(clap::Command::new("cars-scanner"), dir.path()).unwrap();
+        let page = fs::read_to_string(dir.join("cars-scanner-interactive.7")).unwrap();
+        assert!(written.iter().any(|path| path.ends_with("cars-scanner-interactive.7")));
+        assert!(page.contains("connect sim:misfire\\-intermittent"));
+        assert!(page.contains("Attach to an OBD\\-II adapter"));
+    }
+}
@@ -0,0 +1,129 @@
+// Translations of the program's own messages, using Fluent.
+//
+// Each locale is a Fluent file in locales/<code>.ftl, compiled into the
+// binary. To add a locale, copy locales/en.ftl, translate the messages and
+// add an entry to LOCALES below. Missing messages fall back to English.
+use std::env;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::types::FluentNumber;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+// Bundled locales as (language code, Fluent source)
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("sv", include_str!("../locales/sv.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+];
+
+const FALLBACK: &str = "en";
+
+struct Translator {
+    language: &'static str,
+    bundle: FluentBundle<FluentResource>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+static TRANSLATOR: OnceLock<Translator> = OnceLock::new();
+
+// Language codes of all bundled locales
+pub fn available_languages() -> impl Iterator<Item = &'static str> {
+    LOCALES.iter().map(|(code, _)| *code)
+}
+
+// Select the language from `--lang`, or from LC_ALL/LC_MESSAGES/LANG.
+// Unknown languages fall back to English. Only the first call has an effect.
+pub fn init(lang: Option<&str>) {
+    TRANSLATOR.get_or_init(|| Translator::new(lang.map(str::to_string).or_else(detect_locale)));
+}
+
+// Code of the language messages are shown in
+pub fn language() -> &'static str {
+    translator().language
+}
+
+// Translate a message, substituting the given arguments
+pub fn translate(id: &str, args: Option<&FluentArgs>) -> String {
+    let translator = translator();
+    format_message(&translator.bundle, id, args)
+        .or_else(|| format_message(&translator.fallback, id, args))
+        .unwrap_or_else(|| id.to_string())
+}
+
+// A message argument; numbers stay numbers, so variants such as [one] can match them
+pub fn arg(text: String) -> FluentValue<'static> {
+    match FluentNumber::from_str(&text) {
+        Ok(number) if number.as_string() == text => FluentValue::Number(number),
+        _ => FluentValue::String(text.into()),
+    }
+}
+
+// Translate a message: `tr!("code-not-found", code = code)`
+#[macro_export]
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::translate($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $crate::i18n::arg($value.to_string()));)+
+        $crate::i18n::translate($id, Some(&args))
+    }};
+}
+pub use tr;
+
+fn translator() -> &'static Translator {
+    TRANSLATOR.get_or_init(|| Translator::new(None))
+}
+
+fn detect_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+fn format_message(bundle: &FluentBundle<FluentResource>, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    Some(bundle.format_pattern(pattern, args, &mut errors).into_owned())
+}
+
+fn load_bundle(code: &'static str) -> FluentBundle<FluentResource> {
+    let source = LOCALES
+        .iter()
+        .find(|(locale, _)| *locale == code)
+        .map(|(_, source)| *source)
+        .unwrap_or_default();
+    let resource = FluentResource::try_new(source.to_string())
+        .unwrap_or_else(|(resource, _)| resource);
+
+    let langid: LanguageIdentifier = code.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Output goes to terminals and plain text files, so skip bidi isolation marks
+    bundle.set_use_isolating(false);
+    // Keys are unique within a locale file, so this cannot fail
+    let _ = bundle.add_resource(resource);
+    bundle
+}
+
+impl Translator {
+    fn new(requested: Option<String>) -> Self {
+        // Accept forms like "sv", "sv-SE" and "sv_SE.UTF-8"
+        let language = requested
+            .as_deref()
+            .map(|value| value.split(['_', '-', '.']).next().unwrap_or_default().to_lowercase())
+            .and_then(|value| available_languages().find(|code| *code == value))
+            .unwrap_or(FALLBACK);
+
+        Translator {
+            language,
+            bundle: load_bundle(language),
+            fallback: load_bundle(FALLBACK),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// Interactive mode left unattended: auto-save and an idle screen (`[interactive]`).
+//
+// A terminal in the customer lounge or on the shop floor is walked away from
+// in the middle of a session, and the next person should neither see nor
+// lose what was on it. With idle_timeout set, interactive mode saves what it
+// is working on (the vehicle, result set and notes) as a workspace after
+// every command, and when nobody has typed for that many minutes it clears
+// the screen and shows an idle screen. The next command starts a fresh
+// session, with an empty transcript and command history; `resume` brings the saved one back as `workspace load` would.
+// The adapter stays connected.
+//
+//   [interactive]
+//   idle_timeout = 10      # minutes
+//   autosave = "lounge"    # workspace name, "autosave" by default
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+// The `[interactive]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InteractiveSettings {
+    // Minutes without input before the idle screen; never when unset or 0
+    pub idle_timeout: Option<u64>,
+    // Workspace the session is saved to
+    pub autosave: String,
+}
+
+impl Default for InteractiveSettings {
+    fn default() -> Self {
+        InteractiveSettings { idle_timeout: None, autosave: "autosave".to_string() }
+    }
+}
+
+impl InteractiveSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.idle_timeout {
+            Some(minutes) if minutes.checked_mul(60).is_none() => Err(format!("interactive.idle_timeout: {} minutes is too long", minutes)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.idle_timeout.filter(|minutes| *minutes > 0).and_then(|minutes| minutes.checked_mul(60)).map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Prompt {
+    // Waiting for the next command
+    Shown,
+    // A command is running
+    Answered,
+}
+
+// Times the prompt from a thread of its own, since reading a line cannot be
+// given a timeout; `show` draws the idle screen when it runs out
+pub struct Watchdog {
+    sender: Sender<Prompt>,
+    idle: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    pub fn start(timeout: Duration, mut show: impl FnMut() + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let idle = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&idle);
+        thread::spawn(move || {
+            let mut waiting = false;
+            loop {
+                let next = match waiting {
+                    true => receiver.recv_timeout(timeout),
+                    false => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match next {
+                    Ok(prompt) => waiting = prompt == Prompt::Shown,
+                    Err(RecvTimeoutError::Timeout) => {
+                        flag.store(true, Ordering::SeqCst);
+                        show();
+                        waiting = false;
+                    },
+                    // Interactive mode has ended
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+        Watchdog { sender, idle }
+    }
+
+    // The prompt is shown; the time runs from now
+    pub fn waiting(&self) {
+        let _ = self.sender.send(Prompt::Shown);
+    }
+
+    // A line was entered; true when the idle screen was shown before it, once
+    pub fn answered(&self) -> bool {
+        let _ = self.sender.send(Prompt::Answered);
+        self.idle.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use super::{InteractiveSettings, Watchdog};
+
+    #[test]
+    fn idle_watchdog_fires_once_per_unanswered_prompt() {
+        let settings: InteractiveSettings = toml::from_str("idle_timeout = 10").unwrap();
+        assert_eq!(settings.timeout(), Some(Duration::from_secs(600)));
+        assert_eq!(settings.autosave, "autosave");
+        assert_eq!(InteractiveSettings { idle_timeout: Some(0), ..settings }.timeout(), None);
+
+        let shown = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&shown);
+        let watchdog = Watchdog::start(Duration::from_millis(40), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        // Answered in time
+        watchdog.waiting();
+        assert!(!watchdog.answered());
+        std::thread::sleep(Duration::from_millis(120));
+        assert_eq!(shown.load(Ordering::SeqCst), 0);
+
+        // Left alone: the idle screen is shown once, however long nobody types
+        watchdog.waiting();
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(shown.load(Ordering::SeqCst), 1);
+        assert!(watchdog.answered());
+        assert!(!watchdog.answered());
+    }
+
+    #[test]
+    fn an_idle_timeout_too_long_to_count_is_rejected() {
+        let settings: InteractiveSettings = toml::from_str("idle_timeout = 999999999999999999").unwrap();
+        assert_eq!(settings.validate(), Err("interactive.idle_timeout: 999999999999999999 minutes is too long".to_string()));
+        assert_eq!(settings.timeout(), None);
+    }
+}
@@ -0,0 +1,299 @@
+// Emissions inspection (smog check) reports.
+//
+// An inspection looks at what the vehicle reports over OBD-II: the MIL, the
+// readiness monitors and the stored, pending and permanent codes, tied to the
+// VIN. How many incomplete monitors are accepted, whether permanent codes
+// fail a vehicle and the wording on the report differ between jurisdictions,
+// so those come from a template file keyed by jurisdiction.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use serde::Deserialize;
+use crate::adapter::Adapter;
+use crate::branding::Letterhead;
+use crate::i18n::tr;
+use crate::readiness::{self, Readiness};
+use crate::{escape_html, html_document_titled, time, DiagnosticsDatabase};
+
+// Default location of the bundled templates
+pub const INSPECTION_TEMPLATES_FILE: &str = "src/data/inspection_templates.yaml";
+
+// Jurisdiction used when none is configured
+pub const DEFAULT_JURISDICTION: &str = "generic";
+
+// Model year letters at VIN position 10, from 1980 (and again from 2010)
+const MODEL_YEAR_CODES: &str = "ABCDEFGHJKLMNPRSTVWXY123456789";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InspectionTemplate {
+    pub title: String,
+    pub mil: String,
+    pub readiness: String,
+    pub codes: String,
+    pub result: String,
+    pub pass: String,
+    pub fail: String,
+    #[serde(default)]
+    pub statement: String,
+    pub incomplete_allowed: BTreeMap<u16, usize>,
+    #[serde(default)]
+    pub fail_on_permanent: bool,
+}
+
+impl InspectionTemplate {
+    // Incomplete monitors accepted for a model year; the strictest rule when it is unknown
+    pub fn allowed(&self, model_year: Option<u16>) -> usize {
+        match model_year {
+            Some(year) => self.incomplete_allowed.range(..=year).next_back().map(|(_, allowed)| *allowed),
+            None => self.incomplete_allowed.values().min().copied(),
+        }
+        .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InspectionTemplates {
+    templates: BTreeMap<String, InspectionTemplate>,
+}
+
+impl InspectionTemplates {
+    // Load a template file
+    pub fn load(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("Could not open inspection templates {}: {}", file_path, e))?;
+        let templates: BTreeMap<String, InspectionTemplate> = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid inspection templates in {}: {}", file_path, e))?;
+        let templates = templates.into_iter().map(|(name, template)| (name.to_lowercase(), template)).collect();
+        Ok(InspectionTemplates { templates })
+    }
+
+    pub fn get(&self, jurisdiction: &str) -> Result<&InspectionTemplate, String> {
+        self.templates.get(&jurisdiction.to_lowercase()).ok_or_else(|| {
+            let known: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+            tr!("inspection-unknown-jurisdiction", jurisdiction = jurisdiction, known = known.join(", "))
+        })
+    }
+}
+
+// What the vehicle reported
+pub struct Inspection {
+    pub vin: Option<String>,
+    pub protocol: String,
+    pub readiness: Readiness,
+    pub stored: Vec<String>,
+    pub pending: Vec<String>,
+    pub permanent: Vec<String>,
+    pub date: u64,
+}
+
+// Why a vehicle does not pass
+#[derive(Debug, PartialEq)]
+pub enum Reason {
+    MilOn,
+    Incomplete { incomplete: usize, allowed: usize },
+    Permanent(usize),
+}
+
+impl Reason {
+    fn message(&self) -> String {
+        match self {
+            Reason::MilOn => tr!("inspection-reason-mil"),
+            Reason::Incomplete { incomplete, allowed } => tr!("inspection-reason-incomplete", incomplete = incomplete, allowed = allowed),
+            Reason::Permanent(count) => tr!("inspection-reason-permanent", count = count),
+        }
+    }
+}
+
+// Read everything an inspection looks at from a connected vehicle
+pub fn read(adapter: &mut Adapter, date: u64) -> Result<Inspection, Box<dyn Error>> {
+    Ok(Inspection {
+        vin: adapter.read_vin()?,
+        protocol: adapter.protocol.clone(),
+        readiness: readiness::read(adapter)?,
+        stored: adapter.read_codes(0x03)?,
+        pending: adapter.read_codes(0x07)?,
+        permanent: adapter.read_codes(0x0A)?,
+        date,
+    })
+}
+
+// The model year from VIN position 10, choosing the most recent of the two 30-year cycles
+// that is not in the future at `now`
+pub fn model_year(vin: &str, now: u64) -> Option<u16> {
+    let code = vin.chars().nth(9)?;
+    let index = MODEL_YEAR_CODES.find(code)? as u16;
+    let this_year: u16 = time::format_local(now, "%Y").parse().ok()?;
+    [2010 + index, 1980 + index].into_iter().find(|year| *year <= this_year + 1)
+}
+
+impl Inspection {
+    pub fn model_year(&self) -> Option<u16> {
+        model_year(self.vin.as_ref()?, self.date)
+    }
+
+    // Reasons the vehicle fails under a template; empty when it passes
+    pub fn verdict(&self, template: &InspectionTemplate) -> Vec<Reason> {
+        let mut reasons = Vec::new();
+        if self.readiness.mil {
+            reasons.push(Reason::MilOn);
+        }
+        let incomplete = self.readiness.incomplete().count();
+        let allowed = template.allowed(self.model_year());
+        if incomplete > allowed {
+            reasons.push(Reason::Incomplete { incomplete, allowed });
+        }
+        if template.fail_on_permanent && !self.permanent.is_empty() {
+            reasons.push(Reason::Permanent(self.permanent.len()));
+        }
+        reasons
+    }
+
+    fn vin_line(&self) -> String {
+        match (&self.vin, self.model_year()) {
+            (Some(vin), Some(year)) => tr!("inspection-vin-year", vin = vin.as_str(), year = year),
+            (Some(vin), None) => vin.clone(),
+            (None, _) => tr!("inspection-vin-unknown"),
+        }
+    }
+
+    // (label, codes with descriptions) for stored, pending and permanent codes
+    fn code_groups(&self, db: &DiagnosticsDatabase) -> Vec<(String, Vec<String>)> {
+        let describe = |codes: &[String]| {
+            codes
+                .iter()
+                .map(|code| match db.lookup_error(code) {
+                    Some(error) => format!("{} {}", code, error.description),
+                    None => code.clone(),
+                })
+                .collect()
+        };
+        vec![
+            (tr!("inspection-stored"), describe(&self.stored)),
+            (tr!("inspection-pending"), describe(&self.pending)),
+            (tr!("inspection-permanent"), describe(&self.permanent)),
+        ]
+    }
+}
+
+fn mil_status(on: bool) -> String {
+    if on { tr!("inspection-mil-on") } else { tr!("inspection-mil-off") }
+}
+
+fn monitor_status(complete: bool) -> String {
+    if complete { tr!("inspection-complete") } else { tr!("inspection-incomplete") }
+}
+
+// The report as plain text or HTML
+pub fn render(inspection: &Inspection, template: &InspectionTemplate, db: &DiagnosticsDatabase, letterhead: &Letterhead, html: bool) -> String {
+    let reasons = inspection.verdict(template);
+    let result = if reasons.is_empty() { &template.pass } else { &template.fail };
+    let readiness = &inspection.readiness;
+    let summary = tr!("inspection-readiness-summary", complete = readiness.complete_count(), total = readiness.monitors.len(), allowed = template.allowed(inspection.model_year()));
+    let details = [
+        (tr!("inspection-vin"), inspection.vin_line()),
+        (tr!("label-date"), time::format_date(inspection.date)),
+        (tr!("inspection-protocol"), inspection.protocol.clone()),
+    ];
+
+    if html {
+        let mut body = String::from("<table class='repair-order'>\n");
+        for (label, value) in &details {
+            body.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", escape_html(label.trim_end_matches(':')), escape_html(value)));
+        }
+        body.push_str("</table>\n");
+        body.push_str(&format!("<h2>{}</h2>\n<p>{}</p>\n", escape_html(&template.mil), mil_status(readiness.mil)));
+        body.push_str(&format!("<h2>{}</h2>\n<p>{}</p>\n<table>\n", escape_html(&template.readiness), summary));
+        for status in &readiness.monitors {
+            body.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", status.monitor.name, monitor_status(status.complete)));
+        }
+        body.push_str("</table>\n");
+        body.push_str(&format!("<h2>{}</h2>\n", escape_html(&template.codes)));
+        for (label, codes) in inspection.code_groups(db) {
+            let codes = match codes.is_empty() {
+                true => tr!("inspection-none"),
+                false => codes.iter().map(|code| escape_html(code)).collect::<Vec<_>>().join("<br>"),
+            };
+            body.push_str(&format!("<p><strong>{}</strong> {}</p>\n", label, codes));
+        }
+        body.push_str(&format!("<h2>{}: {}</h2>\n", escape_html(&template.result), escape_html(result)));
+        if !reasons.is_empty() {
+            body.push_str("<ul>\n");
+            for reason in &reasons {
+                body.push_str(&format!("<li>{}</li>\n", reason.message()));
+            }
+            body.push_str("</ul>\n");
+        }
+        if !template.statement.is_empty() {
+            body.push_str(&format!("<p><em>{}</em></p>\n", escape_html(&template.statement)));
+        }
+        return html_document_titled(&template.title, &body, letterhead);
+    }
+
+    let mut text = letterhead.text_header();
+    text.push_str(&format!("{}\n{}\n", template.title, "=".repeat(template.title.chars().count())));
+    for (label, value) in &details {
+        text.push_str(&format!("{:<16}{}\n", label, value));
+    }
+    text.push_str(&format!("\n{}: {}\n", template.mil, mil_status(readiness.mil)));
+    text.push_str(&format!("\n{}: {}\n", template.readiness, summary));
+    for status in &readiness.monitors {
+        text.push_str(&format!("  {:<28}{}\n", status.monitor.name, monitor_status(status.complete)));
+    }
+    text.push_str(&format!("\n{}\n", template.codes));
+    for (label, codes) in inspection.code_groups(db) {
+        match codes.split_first() {
+            None => text.push_str(&format!("  {:<14}{}\n", label, tr!("inspection-none"))),
+            Some((first, rest)) => {
+                text.push_str(&format!("  {:<14}{}\n", label, first));
+                for code in rest {
+                    text.push_str(&format!("  {:<14}{}\n", "", code));
+                }
+            },
+        }
+    }
+    text.push_str(&format!("\n{}: {}\n", template.result, result));
+    for reason in &reasons {
+        text.push_str(&format!("  - {}\n", reason.message()));
+    }
+    if !template.statement.is_empty() {
+        text.push_str(&format!("\n{}\n", template.statement));
+    }
+    text.push_str(&letterhead.text_footer());
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::adapter::Adapter;
+    use crate::branding::Letterhead;
+    use crate::testing::{assert_golden, init, sample_database, FIXED_TIME, FakeElm};
+    use super::{InspectionTemplates, Reason};
+
+    #[test]
+    fn inspection_report_applies_jurisdiction_rules() {
+        init();
+        let elm = FakeElm::car()
+            .reply("0902", "7E81014490201314847\r7E821434D3832363333\r7E82241303034333532")
+            .reply("03", "7E80443010300")
+            .reply("07", "7E8024700")
+            .reply("0A", "7E8044A010300");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        let result = super::read(&mut adapter, FIXED_TIME).unwrap();
+        assert_eq!(result.vin.as_deref(), Some("1HGCM82633A004352"));
+        assert_eq!(result.model_year(), Some(2003));
+        assert_eq!(result.stored, ["P0300"]);
+
+        let templates = InspectionTemplates::load(super::INSPECTION_TEMPLATES_FILE).unwrap();
+        // Catalyst and EVAP are incomplete; one is allowed from 2001 on
+        let generic = templates.get("generic").unwrap();
+        assert_eq!(result.verdict(generic), [Reason::Incomplete { incomplete: 2, allowed: 1 }]);
+        let california = templates.get("California").unwrap();
+        assert_eq!(result.verdict(california).len(), 2);
+        assert!(templates.get("mars").is_err());
+
+        let report = super::render(&result, california, &sample_database(), &Letterhead::default(), false);
+        assert_golden("inspection-california.txt", &report);
+    }
+}
@@ -0,0 +1,100 @@
+// Ctrl-C for long operations.
+//
+// Outside an operation Ctrl-C ends the program as usual. While one is being
+// watched (every interactive command, and the adapter commands on the
+// command line) it only raises a flag: adapter queries and network retries
+// stop with an `Interrupted` error, monitor sessions and drive cycle watches
+// end as if Enter was pressed, and interactive mode goes back to its prompt.
+// The adapter then closes the protocol with ATPC, so the ECUs end their
+// diagnostic session. A second Ctrl-C before the operation stops ends the
+// program.
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::i18n::tr;
+
+// Exit status of a program ended by SIGINT
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+// How often interruptible waits look at the flag
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static WATCHERS: AtomicUsize = AtomicUsize::new(0);
+
+// Take over Ctrl-C; without a watched operation it still ends the program
+pub fn install() {
+    // Only fails when a handler is already set, which leaves Ctrl-C working as before
+    let _ = ctrlc::set_handler(|| {
+        if WATCHERS.load(Ordering::SeqCst) == 0 || INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+}
+
+// Ctrl-C stops the operation instead of the program while this is alive
+pub struct Watch;
+
+// Start watching an operation; an earlier interrupt is forgotten
+pub fn watch() -> Watch {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    WATCHERS.fetch_add(1, Ordering::SeqCst);
+    Watch
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        WATCHERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Forget an interrupt the operation has dealt with, e.g. by ending a session early
+// and going on to its summary; another Ctrl-C interrupts again
+pub fn handled() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+// Whether Ctrl-C was pressed since the operation started
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+// Sleep for `duration`, waking early on Ctrl-C; false when interrupted
+pub fn sleep(duration: Duration) -> bool {
+    let end = Instant::now() + duration;
+    while !interrupted() {
+        let remaining = end.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+    false
+}
+
+// The error of an operation stopped with Ctrl-C
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", tr!("interrupted"))
+    }
+}
+
+impl Error for Interrupted {}
+
+// Fail with `Interrupted` once Ctrl-C was pressed
+#[cfg_attr(not(feature = "online"), allow(dead_code))]
+pub fn check() -> Result<(), Interrupted> {
+    match interrupted() {
+        true => Err(Interrupted),
+        false => Ok(()),
+    }
+}
+
+pub fn is_interrupted(error: &(dyn Error + 'static)) -> bool {
+    error.is::<Interrupted>()
+}
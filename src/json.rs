@@ -0,0 +1,129 @@
+// Machine-readable output as JSON, selected with the global --format flag.
+//
+// With --format json, lookup prints the code as one object (null when it is
+// not in the database), and search, list, list-by-system, list-by-severity
+// and query print an array, so the output can go straight into jq. As with
+// --porcelain, nothing else is written to standard output: no colors,
+// headings, counts or hints. A code has the fields
+//
+//   code, description, severity, system     text
+//   possible_causes, recommended_actions    arrays of text
+//   aliases, safety_warnings                arrays of text
+//   translation, source                     text, left out when there is none
+//
+// and search results add their relevance `score` and the fields a keyword
+// was `matched` in. New fields may be added, but existing ones keep their
+// name and type within a major version.
+use std::io::{self, Write};
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::{pipe_list, ErrorCode};
+
+// How a command prints its results; which formats a command takes besides text is up to it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    // One JSON object per line, written as each result is found (search)
+    Jsonl,
+    // The database file format (db export)
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+// A code as printed in JSON
+#[derive(Debug, Serialize)]
+pub struct Code<'a> {
+    pub code: &'a str,
+    pub description: &'a str,
+    pub severity: &'a str,
+    pub system: &'a str,
+    pub possible_causes: &'a [String],
+    pub recommended_actions: &'a [String],
+    pub aliases: Vec<&'a str>,
+    pub safety_warnings: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl<'a> From<&'a ErrorCode> for Code<'a> {
+    fn from(error: &'a ErrorCode) -> Self {
+        Code {
+            code: &error.code,
+            description: &error.description,
+            severity: &error.severity,
+            system: &error.system,
+            possible_causes: &error.possible_causes,
+            recommended_actions: &error.recommended_actions,
+            aliases: pipe_list(&error.aliases).collect(),
+            safety_warnings: pipe_list(&error.safety_warnings).collect(),
+            translation: error.translation.as_deref(),
+            source: error.source.as_ref().map(|source| source.to_string()),
+        }
+    }
+}
+
+// The codes as an array
+pub fn codes<'a>(errors: impl IntoIterator<Item = &'a ErrorCode>) -> Vec<Code<'a>> {
+    errors.into_iter().map(Code::from).collect()
+}
+
+// Print `value` as indented JSON on standard output; a reader that stops early, such as
+// `head`, is not an error
+pub fn print(value: &impl Serialize) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    let result = serde_json::to_writer_pretty(&mut out, value)
+        .map_err(io::Error::from)
+        .and_then(|_| writeln!(out))
+        .and_then(|_| out.flush());
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{self, Query};
+    use crate::search::JsonHit;
+    use crate::testing::{init, sample_database};
+
+    #[test]
+    fn json_output_has_the_same_fields_for_lookups_lists_and_search_hits() {
+        init();
+        let db = sample_database();
+        let code = serde_json::to_value(super::Code::from(db.lookup_error("P0101").unwrap())).unwrap();
+        assert_eq!(code["code"], "P0101");
+        assert_eq!(code["possible_causes"][0], "Dirty MAF sensor");
+        assert!(code["aliases"].is_array());
+        assert!(code.get("translation").is_none());
+
+        let list = serde_json::to_value(super::codes(db.list_errors_by_system("Engine"))).unwrap();
+        assert!(list.as_array().unwrap().contains(&code));
+
+        let results = db.search_all("sensor");
+        let hit = results.hits.iter().find(|hit| hit.error.code == "P0101").unwrap();
+        let hit = serde_json::to_value(JsonHit::new(hit)).unwrap();
+        for (field, value) in code.as_object().unwrap() {
+            assert_eq!(&hit[field], value, "{}", field);
+        }
+        assert!(hit["score"].as_u64().unwrap() > 0);
+
+        let query = Query::parse("SELECT code, severity FROM codes WHERE code = 'P0101'").unwrap();
+        let rows = serde_json::to_value(query::json_rows(&query, &query.run(&db))).unwrap();
+        assert_eq!(rows, serde_json::json!([{ "code": "P0101", "severity": code["severity"] }]));
+    }
+}
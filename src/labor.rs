@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use serde::Deserialize;
+use csv::ReaderBuilder;
+use crate::i18n::tr;
+
+// Default location of the bundled labor guide
+pub const LABOR_GUIDE_FILE: &str = "src/data/labor_ops.csv";
+
+// A labor operation for an error code, optionally tied to one recommended action
+#[derive(Debug, Deserialize, Clone)]
+pub struct LaborOperation {
+    pub code: String,
+    #[serde(default)]
+    pub action: String,
+    pub op_code: String,
+    pub description: String,
+    pub hours: f64,
+}
+
+// Labor operations and flat-rate times, keyed by error code
+#[derive(Default)]
+pub struct LaborGuide {
+    operations: HashMap<String, Vec<LaborOperation>>,
+}
+
+impl LaborGuide {
+    // Load a labor guide from a CSV file
+    pub fn load_from_csv(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let file = fs::File::open(file_path)
+            .map_err(|e| format!("Could not open labor guide {}: {}", file_path, e))?;
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(file);
+
+        let operations = reader.deserialize().collect::<Result<Vec<LaborOperation>, _>>()?;
+        Ok(LaborGuide::from_operations(operations))
+    }
+
+    // Build a labor guide from operations already in memory
+    pub fn from_operations(operations: impl IntoIterator<Item = LaborOperation>) -> Self {
+        let mut guide = LaborGuide::default();
+        for operation in operations {
+            guide.operations.entry(operation.code.clone()).or_default().push(operation);
+        }
+        guide
+    }
+
+    // Labor operations recorded for an error code
+    pub fn operations_for(&self, code: &str) -> &[LaborOperation] {
+        self.operations.get(code).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // Total flat-rate hours for an error code
+    pub fn total_hours(&self, code: &str) -> f64 {
+        self.operations_for(code).iter().map(|operation| operation.hours).sum()
+    }
+}
+
+// Render labor operations as a plain text report section
+pub fn to_text(operations: &[LaborOperation]) -> String {
+    let mut output = format!("\n{}\n", tr!("section-labor"));
+    for operation in operations {
+        output.push_str(&format!("  {} - {} ({:.1} h)\n", operation.op_code, operation.description, operation.hours));
+    }
+    let total: f64 = operations.iter().map(|operation| operation.hours).sum();
+    output.push_str(&format!("  {}: {:.1} h\n", tr!("labor-total"), total));
+    output
+}
+
+// Render labor operations as an HTML report section
+pub fn to_html(operations: &[LaborOperation]) -> String {
+    let mut output = format!("<h3>{}</h3>\n<table>\n", tr!("section-labor"));
+    output.push_str(&format!(
+        "<tr><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+        tr!("labor-op-code"), tr!("labor-description"), tr!("labor-hours")
+    ));
+    for operation in operations {
+        output.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{:.1}</td></tr>\n",
+            operation.op_code, operation.description, operation.hours
+        ));
+    }
+    let total: f64 = operations.iter().map(|operation| operation.hours).sum();
+    output.push_str(&format!("<tr><th colspan='2'>{}</th><th>{:.1}</th></tr>\n</table>\n", tr!("labor-total"), total));
+    output
+}
@@ -0,0 +1,152 @@
+// Which fields of a code are shown, and in what order (`[layout]`).
+//
+// A parts counter wants the part numbers first; a report handed to a
+// customer should not list the shop's recommended actions. The `[layout]`
+// table orders and hides fields wherever a code is shown in full: the
+// terminal, `compare`, and text and HTML reports. A `[layout.report]` table
+// gives exported reports a layout of their own:
+//
+//   [layout]
+//   order = ["related_parts", "description"]   # these first, the rest as usual
+//   hide = ["source"]
+//
+//   [layout.report]
+//   hide = ["recommended_actions", "source"]
+//
+// The code itself always comes first, then its safety warnings, which no
+// layout can hide.
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Description,
+    Severity,
+    // DIY, Intermediate or Professional, when the code is rated
+    Difficulty,
+    System,
+    // The database or pack the definition came from
+    Source,
+    PossibleCauses,
+    RecommendedActions,
+    // When the code sets and the monitors that run its test
+    SetContext,
+    RelatedParts,
+    // Emissions warranty and service campaigns
+    Warranty,
+}
+
+// Fields in the order they are shown without a `[layout]` table
+pub const DEFAULT_ORDER: [Field; 10] = [
+    Field::Description,
+    Field::Severity,
+    Field::Difficulty,
+    Field::System,
+    Field::Source,
+    Field::PossibleCauses,
+    Field::RecommendedActions,
+    Field::SetContext,
+    Field::RelatedParts,
+    Field::Warranty,
+];
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldLayout {
+    // Fields shown first, in this order
+    pub order: Vec<Field>,
+    pub hide: Vec<Field>,
+}
+
+impl FieldLayout {
+    // The fields to show: the ordered ones, then the others in their usual order, less the hidden ones
+    pub fn fields(&self) -> Vec<Field> {
+        let mut fields: Vec<Field> = Vec::new();
+        for field in self.order.iter().chain(&DEFAULT_ORDER) {
+            if !fields.contains(field) && !self.hide.contains(field) {
+                fields.push(*field);
+            }
+        }
+        fields
+    }
+}
+
+// The `[layout]` table
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutSettings {
+    pub order: Vec<Field>,
+    pub hide: Vec<Field>,
+    // Exported reports; the layout above when not set
+    pub report: Option<FieldLayout>,
+}
+
+struct Layouts {
+    screen: Vec<Field>,
+    report: Vec<Field>,
+}
+
+static LAYOUTS: OnceLock<Layouts> = OnceLock::new();
+
+// Use these layouts from now on; the first call wins
+pub fn init(settings: &LayoutSettings) {
+    let screen = FieldLayout { order: settings.order.clone(), hide: settings.hide.clone() };
+    let report = settings.report.as_ref().unwrap_or(&screen).fields();
+    let _ = LAYOUTS.set(Layouts { screen: screen.fields(), report });
+}
+
+fn layouts() -> &'static Layouts {
+    LAYOUTS.get_or_init(|| Layouts { screen: DEFAULT_ORDER.to_vec(), report: DEFAULT_ORDER.to_vec() })
+}
+
+// Fields shown in the terminal
+pub fn screen() -> &'static [Field] {
+    &layouts().screen
+}
+
+// Fields written to text and HTML reports
+pub fn report() -> &'static [Field] {
+    &layouts().report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DiagnosticsDatabase;
+    use crate::testing::init;
+    use super::{Field, FieldLayout};
+
+    #[test]
+    fn layout_orders_and_hides_fields_in_text_reports() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,related_parts\n\
+             P0420,Catalyst Efficiency,Medium,Emissions,Catalyst,Replace,Catalytic converter:18160-5AA-A00\n",
+        );
+        let counter = FieldLayout { order: vec![Field::RelatedParts, Field::Description], hide: vec![Field::RecommendedActions, Field::Source] };
+        assert_eq!(counter.fields(), [Field::RelatedParts, Field::Description, Field::Severity, Field::Difficulty, Field::System, Field::PossibleCauses, Field::SetContext, Field::Warranty]);
+
+        let text = db.lookup_error("P0420").unwrap().to_text_with(&counter.fields());
+        assert_eq!(text, "Error Code: P0420\n\nRelated Parts:\n  - Catalytic converter (OEM 18160-5AA-A00)\n\nDescription: Catalyst Efficiency\nSeverity: Medium\nSystem: Emissions\n\nPossible Causes:\n  - Catalyst\n");
+    }
+
+    #[test]
+    fn safety_warnings_are_shown_whatever_the_layout_hides() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,safety_warnings\n\
+             P0001,Fuel Volume Regulator,Medium,Fuel,Regulator,Replace,Relieve fuel pressure first|No open flames\n\
+             P0101,MAF Range,Medium,Engine,MAF,Clean,\n",
+        );
+        let error = db.lookup_error("P0001").unwrap();
+        let nothing = FieldLayout { order: Vec::new(), hide: super::DEFAULT_ORDER.to_vec() };
+        assert_eq!(error.to_text_with(&nothing.fields()), "Error Code: P0001\nSafety Warnings:\n  ! Relieve fuel pressure first\n  ! No open flames\n\n");
+        assert!(error.to_html().contains("<div class='safety-warnings'>\n<h3>Safety Warnings:</h3>\n<ul>\n<li>Relieve fuel pressure first</li>\n<li>No open flames</li>\n</ul>\n</div>\n"));
+        assert!(crate::porcelain::code(error).ends_with("\tRelieve fuel pressure first | No open flames"));
+        assert!(error.fields().iter().any(|(label, value)| label == "Safety Warnings:" && value.contains("No open flames")));
+
+        let quiet = db.lookup_error("P0101").unwrap();
+        assert!(!quiet.to_text().contains("Safety"));
+        assert!(!quiet.to_html().contains("safety-warnings"));
+    }
+}
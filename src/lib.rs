@@ -0,0 +1,1156 @@
+// cars-scanner as a library, for embedding the error code database in other programs.
+//
+// `DiagnosticsDatabase` loads the bundled CSV and the configured packs and looks up
+// `ErrorCode`s; `search` ranks codes by keywords, `render_report` and `report` render
+// them as text or HTML, and `maintenance` edits and exports the database files. The
+// `cars-scanner` binary is a thin command line over this crate; `commands` and `repl`
+// hold what its commands and interactive mode show.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use clap::ValueEnum;
+use csv::ReaderBuilder;
+
+pub mod adapter;
+#[cfg(feature = "adapter")]
+pub mod adapterd;
+pub mod anonymize;
+pub mod apps;
+pub mod archive;
+pub mod branding;
+pub mod builder;
+pub mod cache;
+pub mod charts;
+pub mod compare;
+pub mod commands;
+pub mod completion;
+pub mod config;
+pub mod contribute;
+#[cfg(feature = "adapter")]
+pub mod dbc;
+pub mod doctor;
+pub mod drive_cycle;
+#[cfg(feature = "adapter")]
+pub mod emulator;
+pub mod events;
+pub mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fleet;
+pub mod freshness;
+pub mod fuel_trim;
+#[cfg(feature = "tui")]
+pub mod gauges;
+pub mod groups;
+pub mod health;
+pub mod help;
+pub mod i18n;
+pub mod idle;
+pub mod inspection;
+pub mod interrupt;
+pub mod json;
+pub mod labor;
+pub mod layout;
+pub mod maintenance;
+pub mod merge;
+pub mod monitor;
+pub mod o2_tests;
+pub mod net;
+#[cfg(feature = "node")]
+pub mod node;
+pub mod pids;
+pub mod picker;
+pub mod policy;
+pub mod porcelain;
+pub mod presentation;
+pub mod progress;
+pub mod query;
+pub mod readiness;
+pub mod regenerate;
+pub mod render;
+pub mod repl;
+pub mod repair_order;
+pub mod report;
+pub mod safety;
+pub mod scan;
+// Without the adapter feature nothing plays a scenario; warranty claims still use FreezeFrame
+#[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+pub mod scenario;
+pub mod schema;
+pub mod search;
+pub mod severity;
+pub mod session;
+#[cfg(feature = "adapter")]
+pub mod socketcan;
+pub mod sqlite;
+#[cfg(any(test, feature = "testing"))]
+#[cfg_attr(not(test), allow(dead_code))]
+pub mod testing;
+pub mod time;
+pub mod transcript;
+pub mod troubleshoot;
+pub mod usage;
+pub mod vehicle;
+pub mod warranty;
+pub mod workspace;
+
+use anonymize::Anonymizer;
+use branding::Letterhead;
+use config::PackConfig;
+use report::ReportBuilder;
+use labor::LaborGuide;
+use layout::Field;
+use merge::MergePolicy;
+use policy::Adjustment;
+use presentation::Style;
+use repair_order::RepairOrder;
+use search::{Highlights, LastSearch, SearchHit, SearchOptions, SearchPage, SearchResults};
+use sqlite::{Column, Filter};
+
+// Default location of the error code database, relative to the working directory
+pub const DATABASE_FILE: &str = "src/data/error_codes.csv";
+
+// Translated descriptions, one CSV file of code and description per language
+pub const TRANSLATIONS_DIR: &str = "src/data/translations";
+
+// Severity levels, least severe first
+pub const SEVERITIES: &[&str] = &["Low", "Medium", "High", "Critical"];
+
+// Difficulty levels as the database spells them, easiest first
+pub const DIFFICULTIES: &[&str] = &["DIY", "Intermediate", "Professional"];
+
+// How hard a code is to diagnose and repair, easiest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Difficulty {
+    // An owner with hand tools
+    Diy,
+    // A scan tool, a multimeter and some experience
+    Intermediate,
+    // A workshop: special tools, or work that is dangerous to get wrong, such as airbags
+    // and high-voltage batteries
+    Professional,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Diy, Difficulty::Intermediate, Difficulty::Professional];
+    
+    // A level as the database spells it, matched without regard to case
+    pub fn parse(text: &str) -> Option<Self> {
+        DIFFICULTIES.iter().position(|name| name.eq_ignore_ascii_case(text.trim())).map(|index| Self::ALL[index])
+    }
+    
+    // The level with what it asks of whoever does the work, e.g. "Professional: leave it to a workshop"
+    pub fn text(self) -> String {
+        match self {
+            Difficulty::Diy => tr!("difficulty-diy"),
+            Difficulty::Intermediate => tr!("difficulty-intermediate"),
+            Difficulty::Professional => tr!("difficulty-professional"),
+        }
+    }
+    
+    pub fn style(self) -> Style {
+        match self {
+            Difficulty::Diy => Style::Success,
+            Difficulty::Intermediate => Style::Warning,
+            Difficulty::Professional => Style::Error,
+        }
+    }
+}
+
+// Define the error code structure
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ErrorCode {
+    pub code: String,
+    pub description: String,
+    pub severity: String,
+    pub system: String,
+    // Lists, stored in the CSV as entries separated by '|'
+    #[serde(with = "pipe_list_serde")]
+    pub possible_causes: Vec<String>,
+    #[serde(with = "pipe_list_serde")]
+    pub recommended_actions: Vec<String>,
+    
+    // Optional warranty and service campaign metadata
+    #[serde(default)]
+    pub emissions_warranty: Option<bool>,
+    #[serde(default)]
+    pub service_campaigns: String,
+    #[serde(default)]
+    pub campaign_links: String,
+    
+    // Related parts as `name:OEM part number` entries separated by '|'
+    #[serde(default)]
+    pub related_parts: String,
+    
+    // Operating conditions under which the code typically sets, to reproduce it
+    #[serde(default)]
+    pub set_conditions: String,
+    
+    // Readiness monitors that run the failing test, as monitor ids separated by '|'
+    #[serde(default)]
+    pub monitors: String,
+    
+    // Other names the code goes by, such as "cat code", separated by '|'; searched like the description
+    #[serde(default)]
+    pub aliases: String,
+    
+    // DIY, Intermediate or Professional; empty when not rated
+    #[serde(default)]
+    pub difficulty: String,
+    
+    // Hazards of working on the fault, such as fuel pressure or airbag squibs, separated by '|';
+    // shown wherever the code is, whatever the layout hides
+    #[serde(default)]
+    pub safety_warnings: String,
+    
+    // The description in the language messages are shown in, from src/data/translations
+    #[serde(skip)]
+    pub translation: Option<String>,
+    
+    // The database or pack this definition was read from
+    #[serde(skip)]
+    pub source: Option<Arc<Source>>,
+    
+    // Set when the fleet's severity policy replaced the severity the database gives
+    #[serde(skip)]
+    pub adjusted: Option<Adjustment>,
+}
+
+// Where a definition came from: the bundled database or a configured pack
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub pack: String,
+    pub file: String,
+    pub version: Option<String>,
+    // Higher precedence wins when definitions are merged
+    pub precedence: i32,
+}
+
+impl Source {
+    pub fn bundled(file: &str) -> Self {
+        Source { pack: "bundled".to_string(), file: file.to_string(), version: None, precedence: 0 }
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} {} ({})", self.pack, version, self.file),
+            None => write!(f, "{} ({})", self.pack, self.file),
+        }
+    }
+}
+
+// A part cross-referenced from an error code
+pub struct Part<'a> {
+    pub name: &'a str,
+    pub number: &'a str,
+}
+
+impl std::fmt::Display for Part<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.number.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} (OEM {})", self.name, self.number)
+        }
+    }
+}
+
+// Split an optional pipe-separated field, skipping empty entries
+pub fn pipe_list(field: &str) -> impl Iterator<Item = &str> {
+    field.split('|').map(str::trim).filter(|entry| !entry.is_empty())
+}
+
+// Serde adapter reading a '|' separated field into a list and writing it back
+// the same way; a JSON or YAML array is read as well
+mod pipe_list_serde {
+    use std::fmt;
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(entries: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&entries.join("|"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        struct ListVisitor;
+
+        impl<'de> Visitor<'de> for ListVisitor {
+            type Value = Vec<String>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("entries separated by '|' or a list of strings")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<String>, E> {
+                Ok(super::pipe_list(value).map(str::to_string).collect())
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<String>, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = seq.next_element::<String>()? {
+                    entries.extend(super::pipe_list(&entry).map(str::to_string));
+                }
+                Ok(entries)
+            }
+        }
+
+        deserializer.deserialize_any(ListVisitor)
+    }
+}
+
+// Escape text for inclusion in HTML
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Whether a link is an http or https address, safe to put in a report's href
+fn is_web_url(link: &str) -> bool {
+    let lower = link.to_ascii_lowercase();
+    lower.starts_with("https://") || lower.starts_with("http://")
+}
+
+impl ErrorCode {
+    // Whether any warranty or service campaign information is present
+    pub fn has_warranty_info(&self) -> bool {
+        self.emissions_warranty.is_some()
+            || pipe_list(&self.service_campaigns).next().is_some()
+            || pipe_list(&self.campaign_links).next().is_some()
+    }
+    
+    // Parse the related parts; entries without a part number keep an empty number
+    pub fn parts(&self) -> Vec<Part<'_>> {
+        pipe_list(&self.related_parts)
+            .map(|entry| match entry.rsplit_once(':') {
+                Some((name, number)) => Part { name: name.trim(), number: number.trim() },
+                None => Part { name: entry, number: "" },
+            })
+            .collect()
+    }
+    
+    // Names of the monitors that run the test; unknown ids are kept as written
+    pub fn monitor_names(&self) -> Vec<String> {
+        pipe_list(&self.monitors)
+            .map(|id| readiness::find(id).map(|monitor| monitor.name.to_string()).unwrap_or_else(|| id.to_string()))
+            .collect()
+    }
+    
+    pub fn has_set_context(&self) -> bool {
+        !self.set_conditions.trim().is_empty() || pipe_list(&self.monitors).next().is_some()
+    }
+    
+    pub fn warranty_label(&self) -> String {
+        match self.emissions_warranty {
+            Some(true) => tr!("warranty-covered"),
+            Some(false) => tr!("warranty-not-covered"),
+            None => tr!("warranty-unknown"),
+        }
+    }
+    
+    pub fn difficulty_level(&self) -> Option<Difficulty> {
+        Difficulty::parse(&self.difficulty)
+    }
+    
+    // The difficulty as shown, with its style; a level the tool does not know is shown as written
+    pub fn difficulty_label(&self) -> Option<(String, Style)> {
+        match self.difficulty_level() {
+            Some(level) => Some((level.text(), level.style())),
+            None if !self.difficulty.trim().is_empty() => Some((self.difficulty.trim().to_string(), Style::Plain)),
+            None => None,
+        }
+    }
+    
+    // The severity as shown, noting a change made by the severity policy
+    pub fn severity_label(&self) -> String {
+        match self.adjustment_note() {
+            Some(note) => format!("{} ({})", presentation::severity_text(&self.severity), note),
+            None => presentation::severity_text(&self.severity),
+        }
+    }
+    
+    // "adjusted by policy from High: ...", when the severity policy changed the severity
+    pub fn adjustment_note(&self) -> Option<String> {
+        let adjusted = self.adjusted.as_ref()?;
+        let original = presentation::severity_text(&adjusted.original);
+        Some(match &adjusted.reason {
+            Some(reason) => tr!("severity-adjusted-because", original = original, reason = reason.as_str()),
+            None => tr!("severity-adjusted", original = original),
+        })
+    }
+    
+    // The safety warnings as plain text lines, with a blank line after them; empty without any
+    pub fn safety_text(&self) -> String {
+        if pipe_list(&self.safety_warnings).next().is_none() {
+            return String::new();
+        }
+        let mut text = format!("{}\n", tr!("section-safety"));
+        for warning in pipe_list(&self.safety_warnings) {
+            text.push_str(&format!("  ! {}\n", warning));
+        }
+        text.push('\n');
+        text
+    }
+    
+    // The descriptive fields as (label, value), the safety warnings and then the terminal's
+    // layout, with lists joined by " | "
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let list = |field: &str| pipe_list(field).collect::<Vec<_>>().join(" | ");
+        let safety = (tr!("section-safety"), list(&self.safety_warnings));
+        std::iter::once(safety).chain(layout::screen().iter().flat_map(|field| match field {
+            Field::Description => vec![
+                (tr!("label-description"), self.description.clone()),
+                (tr!("label-aliases"), pipe_list(&self.aliases).collect::<Vec<_>>().join(" | ")),
+            ],
+            Field::Severity => vec![(tr!("label-severity"), self.severity_label())],
+            Field::Difficulty => vec![(tr!("label-difficulty"), self.difficulty_label().map(|(text, _)| text).unwrap_or_default())],
+            Field::System => vec![(tr!("label-system"), self.system.clone())],
+            // Differs by where the definition was loaded, not by the code
+            Field::Source => vec![],
+            Field::PossibleCauses => vec![(tr!("section-possible-causes"), self.possible_causes.join(" | "))],
+            Field::RecommendedActions => vec![(tr!("section-recommended-actions"), self.recommended_actions.join(" | "))],
+            Field::RelatedParts => vec![(tr!("section-related-parts"), list(&self.related_parts))],
+            Field::Warranty => vec![
+                (tr!("label-emissions-warranty"), self.warranty_label()),
+                (tr!("label-campaign"), list(&self.service_campaigns)),
+            ],
+            Field::SetContext => vec![
+                (tr!("label-set-conditions"), self.set_conditions.trim().to_string()),
+                (tr!("label-monitors"), self.monitor_names().join(" | ")),
+            ],
+        })).collect()
+    }
+    
+    pub fn to_text(&self) -> String {
+        self.to_text_with(layout::report())
+    }
+    
+    // Render as plain text with the given fields, in their order, after the safety warnings
+    pub fn to_text_with(&self, fields: &[Field]) -> String {
+        let mut output = String::new();
+        output.push_str(&format!("{} {}\n", tr!("label-error-code"), self.code));
+        output.push_str(&self.safety_text());
+        // A label line after a section is set apart from it by a blank line, as sections are
+        let mut after_section = false;
+        for field in fields {
+            let label = |label: String, value: &dyn std::fmt::Display| format!("{} {}\n", label, value);
+            let line = match field {
+                Field::Description => {
+                    let mut lines = label(tr!("label-description"), &self.description);
+                    if let Some(translation) = &self.translation {
+                        lines.push_str(&label(tr!("label-translation", language = i18n::language()), translation));
+                    }
+                    if pipe_list(&self.aliases).next().is_some() {
+                        lines.push_str(&label(tr!("label-aliases"), &pipe_list(&self.aliases).collect::<Vec<_>>().join(", ")));
+                    }
+                    Some(lines)
+                },
+                Field::Severity => Some(label(tr!("label-severity"), &self.severity_label())),
+                Field::Difficulty => self.difficulty_label().map(|(text, _)| label(tr!("label-difficulty"), &text)),
+                Field::System => Some(label(tr!("label-system"), &self.system)),
+                Field::Source => self.source.as_ref().map(|source| label(tr!("label-source"), source)),
+                _ => None,
+            };
+            if let Some(line) = line {
+                if after_section {
+                    output.push('\n');
+                    after_section = false;
+                }
+                output.push_str(&line);
+                continue;
+            }
+            
+            let mut section = String::new();
+            match field {
+                Field::PossibleCauses => {
+                    section.push_str(&format!("\n{}\n", tr!("section-possible-causes")));
+                    for cause in &self.possible_causes {
+                        section.push_str(&format!("  - {}\n", cause));
+                    }
+                },
+                Field::RecommendedActions => {
+                    section.push_str(&format!("\n{}\n", tr!("section-recommended-actions")));
+                    for action in &self.recommended_actions {
+                        section.push_str(&format!("  - {}\n", action));
+                    }
+                },
+                Field::SetContext if self.has_set_context() => {
+                    section.push_str(&format!("\n{}\n", tr!("section-set-context")));
+                    if !self.set_conditions.trim().is_empty() {
+                        section.push_str(&format!("  {} {}\n", tr!("label-set-conditions"), self.set_conditions.trim()));
+                    }
+                    if !self.monitors.trim().is_empty() {
+                        section.push_str(&format!("  {} {}\n", tr!("label-monitors"), self.monitor_names().join(", ")));
+                    }
+                },
+                Field::RelatedParts if !self.parts().is_empty() => {
+                    section.push_str(&format!("\n{}\n", tr!("section-related-parts")));
+                    for part in self.parts() {
+                        section.push_str(&format!("  - {}\n", part));
+                    }
+                },
+                Field::Warranty if self.has_warranty_info() => {
+                    section.push_str(&format!("\n{}\n", tr!("section-warranty")));
+                    section.push_str(&format!("  {} {}\n", tr!("label-emissions-warranty"), self.warranty_label()));
+                    for campaign in pipe_list(&self.service_campaigns) {
+                        section.push_str(&format!("  {} {}\n", tr!("label-campaign"), campaign));
+                    }
+                    for link in pipe_list(&self.campaign_links) {
+                        section.push_str(&format!("  {} {}\n", tr!("label-link"), link));
+                    }
+                },
+                _ => {},
+            }
+            after_section |= !section.is_empty();
+            output.push_str(&section);
+        }
+        
+        output
+    }
+    
+    pub fn to_html(&self) -> String {
+        self.to_html_highlighted(&Highlights::default())
+    }
+    
+    // Render as HTML with search matches wrapped in <mark>
+    pub fn to_html_highlighted(&self, highlights: &Highlights) -> String {
+        let mut output = String::new();
+        output.push_str("<div class='error-code'>\n");
+        output.push_str(&format!("<h2>{} {}</h2>\n", tr!("label-error-code"), self.code));
+        if pipe_list(&self.safety_warnings).next().is_some() {
+            output.push_str(&format!("<div class='safety-warnings'>\n<h3>{}</h3>\n<ul>\n", escape_html(&tr!("section-safety"))));
+            for warning in pipe_list(&self.safety_warnings) {
+                output.push_str(&format!("<li>{}</li>\n", escape_html(warning)));
+            }
+            output.push_str("</ul>\n</div>\n");
+        }
+        for field in layout::report() {
+            match field {
+                Field::Description => {
+                    output.push_str(&format!("<p><strong>{}</strong> {}</p>\n", tr!("label-description"), search::mark_html(&self.description, &highlights.description)));
+                    if let Some(translation) = &self.translation {
+                        let label = tr!("label-translation", language = i18n::language());
+                        output.push_str(&format!("<p><strong>{}</strong> {}</p>\n", escape_html(&label), search::mark_html(translation, &highlights.translation)));
+                    }
+                    // Escaped one by one by mark_html, so the joined list is safe as it is
+                    let aliases: Vec<String> = pipe_list(&self.aliases)
+                        .enumerate()
+                        .map(|(index, alias)| search::mark_html(alias, Highlights::item(&highlights.aliases, index)))
+                        .collect();
+                    if !aliases.is_empty() {
+                        output.push_str(&format!("<p><strong>{}</strong> {}</p>\n", tr!("label-aliases"), aliases.join(", ")));
+                    }
+                },
+                Field::Severity => {
+                    let severity = match Style::for_severity(&self.severity) {
+                        Some(style) => presentation::html_span(&escape_html(&presentation::severity_text(&self.severity)), style),
+                        None => escape_html(&self.severity),
+                    };
+                    let note = self.adjustment_note()
+                        .map(|note| format!(" <em>({})</em>", escape_html(&note)))
+                        .unwrap_or_default();
+                    output.push_str(&format!("<p><strong>{}</strong> {}{}</p>\n", tr!("label-severity"), severity, note));
+                },
+                Field::Difficulty => if let Some((text, style)) = self.difficulty_label() {
+                    output.push_str(&format!("<p><strong>{}</strong> {}</p>\n", tr!("label-difficulty"), presentation::html_span(&escape_html(&text), style)));
+                },
+                Field::System => {
+                    output.push_str(&format!("<p><strong>{}</strong> {}</p>\n", tr!("label-system"), self.system));
+                },
+                Field::Source => if let Some(source) = &self.source {
+                    output.push_str(&format!("<p><strong>{}</strong> {}</p>\n", tr!("label-source"), escape_html(&source.to_string())));
+                },
+                Field::PossibleCauses => {
+                    output.push_str(&format!("<h3>{}</h3>\n<ul>\n", tr!("section-possible-causes")));
+                    for (index, cause) in self.possible_causes.iter().enumerate() {
+                        output.push_str(&format!("<li>{}</li>\n", search::mark_html(cause, Highlights::item(&highlights.possible_causes, index))));
+                    }
+                    output.push_str("</ul>\n");
+                },
+                Field::RecommendedActions => {
+                    output.push_str(&format!("<h3>{}</h3>\n<ul>\n", tr!("section-recommended-actions")));
+                    for (index, action) in self.recommended_actions.iter().enumerate() {
+                        output.push_str(&format!("<li>{}</li>\n", search::mark_html(action, Highlights::item(&highlights.recommended_actions, index))));
+                    }
+                    output.push_str("</ul>\n");
+                },
+                Field::SetContext => if self.has_set_context() {
+                    output.push_str(&format!("<h3>{}</h3>\n<ul>\n", tr!("section-set-context")));
+                    if !self.set_conditions.trim().is_empty() {
+                        output.push_str(&format!("<li><strong>{}</strong> {}</li>\n", tr!("label-set-conditions"), escape_html(self.set_conditions.trim())));
+                    }
+                    if !self.monitors.trim().is_empty() {
+                        output.push_str(&format!("<li><strong>{}</strong> {}</li>\n", tr!("label-monitors"), escape_html(&self.monitor_names().join(", "))));
+                    }
+                    output.push_str("</ul>\n");
+                },
+                Field::RelatedParts => {
+                    let parts = self.parts();
+                    if !parts.is_empty() {
+                        output.push_str(&format!("<h3>{}</h3>\n<ul>\n", tr!("section-related-parts")));
+                        for part in parts {
+                            output.push_str(&format!("<li>{}</li>\n", escape_html(&part.to_string())));
+                        }
+                        output.push_str("</ul>\n");
+                    }
+                },
+                Field::Warranty => if self.has_warranty_info() {
+                    output.push_str(&format!("<h3>{}</h3>\n<ul>\n", tr!("section-warranty").replace('&', "&amp;")));
+                    output.push_str(&format!("<li><strong>{}</strong> {}</li>\n", tr!("label-emissions-warranty"), self.warranty_label()));
+                    for campaign in pipe_list(&self.service_campaigns) {
+                        output.push_str(&format!("<li><strong>{}</strong> {}</li>\n", tr!("label-campaign"), escape_html(campaign)));
+                    }
+                    // Packs come from third parties: only web pages are linked
+                    for link in pipe_list(&self.campaign_links) {
+                        match is_web_url(link) {
+                            true => output.push_str(&format!("<li><a href='{0}'>{0}</a></li>\n", escape_html(link))),
+                            false => output.push_str(&format!("<li>{}</li>\n", escape_html(link))),
+                        }
+                    }
+                    output.push_str("</ul>\n");
+                },
+            }
+        }
+        output.push_str("</div>\n");
+        
+        output
+    }
+}
+
+// Filters for the `list` command; unset fields match everything
+#[derive(Default)]
+pub struct ListFilter {
+    pub system: Option<String>,
+    pub severity: Option<String>,
+    pub warranty_covered: bool,
+    // Codes rated this hard or easier; unrated codes are left out
+    pub max_difficulty: Option<Difficulty>,
+}
+
+impl ListFilter {
+    pub fn matches(&self, error: &ErrorCode) -> bool {
+        self.system.as_ref().is_none_or(|system| error.system.eq_ignore_ascii_case(system))
+            && self.severity.as_ref().is_none_or(|severity| error.severity.eq_ignore_ascii_case(severity))
+            && (!self.warranty_covered || error.emissions_warranty == Some(true))
+            && self.max_difficulty.is_none_or(|max| error.difficulty_level().is_some_and(|level| level <= max))
+    }
+
+    // The same filter in SQL, for reading only the listed codes from a SQLite database
+    pub fn narrowing(&self) -> Filter {
+        let column = |column, value: &Option<String>| match value {
+            Some(value) => Filter::In(column, vec![value.clone()]),
+            None => Filter::Any,
+        };
+        let mut filter = column(Column::System, &self.system).and(column(Column::Severity, &self.severity));
+        if self.warranty_covered {
+            filter = filter.and(Filter::WarrantyCovered);
+        }
+        if let Some(max) = self.max_difficulty {
+            let levels = DIFFICULTIES.iter().zip(Difficulty::ALL).filter(|(_, level)| *level <= max);
+            filter = filter.and(Filter::In(Column::Difficulty, levels.map(|(name, _)| name.to_string()).collect()));
+        }
+        filter
+    }
+}
+
+// Define the diagnostics database
+#[derive(Clone, Default)]
+pub struct DiagnosticsDatabase {
+    // The definition in effect for each code; later packs override earlier ones. Records are
+    // shared with `definitions` and with callers that keep them, so a lookup never copies one.
+    pub errors: HashMap<String, Arc<ErrorCode>>,
+    // Every definition of each code, in load order
+    pub definitions: HashMap<String, Vec<Arc<ErrorCode>>>,
+}
+
+impl DiagnosticsDatabase {
+    // Create a new database
+    pub fn new() -> Self {
+        DiagnosticsDatabase {
+            errors: HashMap::new(),
+            definitions: HashMap::new(),
+        }
+    }
+
+    // Load data from a CSV file; returns how many codes it holds
+    pub fn load_from_csv(&mut self, file_path: &str) -> Result<usize, Box<dyn Error>> {
+        self.read_csv(file_path)?;
+        Ok(self.errors.len())
+    }
+    
+    // Load every code from a SQLite file written by `save_to_sqlite`; returns how many codes
+    pub fn load_from_sqlite(&mut self, file_path: &str) -> Result<usize, Box<dyn Error>> {
+        *self = sqlite::read(file_path, sqlite::Subset::All)?;
+        Ok(self.errors.len())
+    }
+    
+    // Write the codes and all their definitions to a new SQLite file, indexed for lookups;
+    // `updated` is the day they were last brought up to date and `sources` the files they
+    // were read from, for the warning when one changes. Returns how many codes.
+    pub fn save_to_sqlite(&self, file_path: &str, updated: Option<u64>, sources: &[freshness::Fingerprint]) -> Result<usize, Box<dyn Error>> {
+        sqlite::write(self, file_path, updated, sources)
+    }
+    
+    // Read records from a CSV file without reporting progress
+    pub fn read_csv(&mut self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        self.read_records(fs::File::open(file_path)?, Source::bundled(file_path))?;
+        Ok(())
+    }
+    
+    // Layer a pack over the definitions loaded so far; returns the number of codes it defines
+    pub fn load_pack(&mut self, pack: &PackConfig, source: Source) -> Result<usize, Box<dyn Error>> {
+        let file = fs::File::open(&pack.path)
+            .map_err(|e| format!("Could not open pack {}: {}", pack.path, e))?;
+        let count = self.read_records(file, source)
+            .map_err(|e| format!("Invalid pack {}: {}", pack.path, e))?;
+        Ok(count)
+    }
+    
+    // Read CSV records from any source, such as a file or an in-memory literal;
+    // files in a newer schema than this build reads are refused
+    pub fn read_records(&mut self, mut file: impl io::Read, source: Source) -> Result<usize, Box<dyn Error>> {
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        schema::declared(&content)?;
+        let mut reader = schema::reader(&content);
+        
+        let source = Arc::new(source);
+        let mut count = 0;
+        for result in reader.deserialize() {
+            let mut record: ErrorCode = result?;
+            record.source = Some(Arc::clone(&source));
+            self.insert(Arc::new(record));
+            count += 1;
+        }
+        
+        Ok(count)
+    }
+    
+    // Add a definition, in effect until a later one of the same code
+    pub fn insert(&mut self, record: Arc<ErrorCode>) {
+        self.definitions.entry(record.code.clone()).or_default().push(Arc::clone(&record));
+        self.errors.insert(record.code.clone(), record);
+    }
+    
+    // Add the descriptions of a language's translation file, when there is one; returns how many
+    pub fn load_translations(&mut self, language: &str) -> Result<usize, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct Translation {
+            code: String,
+            description: String,
+        }
+        
+        let path = Path::new(TRANSLATIONS_DIR).join(format!("{}.csv", language));
+        if !path.exists() {
+            return Ok(0);
+        }
+        let mut reader = ReaderBuilder::new().has_headers(true).from_path(&path)?;
+        let mut count = 0;
+        for result in reader.deserialize() {
+            let translation: Translation = result.map_err(|e| format!("Invalid translation file {}: {}", path.display(), e))?;
+            if let Some(error) = self.errors.get_mut(translation.code.trim()) {
+                Arc::make_mut(error).translation = Some(translation.description.trim().to_string()).filter(|description| !description.is_empty());
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    
+    // Replace each code's definition with the merge of all its definitions
+    pub fn apply_merge_policy(&mut self, policy: &MergePolicy) {
+        for (code, definitions) in &self.definitions {
+            if let Some((merged, _)) = merge::merge(definitions, policy) {
+                self.errors.insert(code.clone(), merged);
+            }
+        }
+    }
+    
+    // Every definition of a code, in load order
+    pub fn definitions_of(&self, code: &str) -> &[Arc<ErrorCode>] {
+        self.definitions.get(code).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // Look up an error code
+    pub fn lookup_error(&self, code: &str) -> Option<&ErrorCode> {
+        self.errors.get(code).map(Arc::as_ref)
+    }
+    
+    // Look up an error code as a handle to keep beyond the database borrow, such as in a
+    // report or a cache; cloning it copies no record
+    pub fn shared_error(&self, code: &str) -> Option<Arc<ErrorCode>> {
+        self.errors.get(code).cloned()
+    }
+    
+    // Every code's definition in effect, in no particular order
+    pub fn records(&self) -> impl Iterator<Item = &ErrorCode> {
+        self.errors.values().map(Arc::as_ref)
+    }
+    
+    // List errors by system
+    pub fn list_errors_by_system(&self, system: &str) -> Vec<&ErrorCode> {
+        self.records()
+            .filter(|error| error.system.to_lowercase() == system.to_lowercase())
+            .collect()
+    }
+    
+    // List errors by severity
+    pub fn list_errors_by_severity(&self, severity: &str) -> Vec<&ErrorCode> {
+        self.records()
+            .filter(|error| error.severity.to_lowercase() == severity.to_lowercase())
+            .collect()
+    }
+    
+    // List errors matching a filter, sorted by code
+    pub fn list_errors(&self, filter: &ListFilter) -> Vec<&ErrorCode> {
+        let mut errors: Vec<&ErrorCode> = self.records()
+            .filter(|error| filter.matches(error))
+            .collect();
+        errors.sort_by(|a, b| a.code.cmp(&b.code));
+        errors
+    }
+    
+    // Distinct systems present in the database, sorted
+    pub fn systems(&self) -> Vec<String> {
+        Self::distinct(self.records().map(|error| error.system.clone()))
+    }
+
+    // Distinct severities present in the database, sorted
+    pub fn severities(&self) -> Vec<String> {
+        Self::distinct(self.records().map(|error| error.severity.clone()))
+    }
+
+    // All error codes in the database, sorted
+    pub fn codes(&self) -> Vec<String> {
+        Self::distinct(self.errors.keys().cloned())
+    }
+
+    pub fn distinct(values: impl Iterator<Item = String>) -> Vec<String> {
+        let mut values: Vec<String> = values.collect();
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    // Search by keyword
+    // Returns one page of hits, each carrying the spans that matched
+    pub fn search(&self, keyword: &str, options: &SearchOptions) -> SearchPage<'_> {
+        self.search_all(keyword).page(options)
+    }
+    
+    // Every hit of a keyword, for refining before a page is taken
+    pub fn search_all(&self, keyword: &str) -> SearchResults<'_> {
+        SearchResults::new(keyword, self.records())
+    }
+    
+    // Hits of every keyword in code order, one at a time, among the codes of a kept result set
+    // or the whole database
+    pub fn search_stream<'a>(&'a self, keywords: &'a [String], within: Option<&'a LastSearch>) -> impl Iterator<Item = SearchHit<'a>> + 'a {
+        let candidates: Box<dyn Iterator<Item = &ErrorCode>> = match within {
+            Some(last) => Box::new(last.codes.iter().filter_map(|code| self.lookup_error(code))),
+            None => {
+                let mut codes: Vec<&String> = self.errors.keys().collect();
+                codes.sort();
+                Box::new(codes.into_iter().filter_map(|code| self.lookup_error(code)))
+            },
+        };
+        search::stream(keywords, candidates)
+    }
+    
+    // Codes ranked by how closely their description matches a quoted phrase
+    pub fn reverse_lookup(&self, phrase: &str) -> SearchResults<'_> {
+        SearchResults::phrase(phrase, self.records())
+    }
+    
+    // A kept result set searched again with its keywords; codes no longer in the database drop out
+    pub fn search_within(&self, last: &LastSearch) -> SearchResults<'_> {
+        let candidates = last.codes.iter().filter_map(|code| self.lookup_error(code));
+        let Some((first, rest)) = last.keywords.split_first() else { return SearchResults::default() };
+        rest.iter().fold(SearchResults::new(first, candidates), |results, keyword| results.refine(keyword))
+    }
+}
+
+// Wrap a report body in a complete HTML page with its own title
+pub fn html_document_titled(title: &str, body: &str, letterhead: &Letterhead) -> String {
+    format!("{}{}{}", html_document_start(title, letterhead), body, html_document_end(letterhead))
+}
+
+// Everything of an HTML report page before its body
+pub fn html_document_start(title: &str, letterhead: &Letterhead) -> String {
+    let title = escape_html(title);
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
+    html.push_str(&format!("<title>{}</title>\n", title));
+    html.push_str("<style>\n");
+    html.push_str("body { font-family: Arial, sans-serif; margin: 20px; }\n");
+    html.push_str(".error-code { border: 1px solid #ddd; padding: 15px; margin-bottom: 20px; }\n");
+    html.push_str("h2 { color: #d9534f; }\n");
+    html.push_str("h3 { color: #5bc0de; }\n");
+    html.push_str(".safety-warnings { border: 2px solid #d9534f; background: #fdf2f2; padding: 0 15px; margin-bottom: 15px; }\n");
+    html.push_str(".safety-warnings h3 { color: #d9534f; }\n");
+    html.push_str(&presentation::stylesheet());
+    html.push_str(letterhead.stylesheet());
+    html.push_str(presentation::page_stylesheet());
+    html.push_str("</style>\n");
+    html.push_str(presentation::theme_script());
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&presentation::theme_toggle(&escape_html(&tr!("report-dark-mode"))));
+    html.push_str(&letterhead.html_header());
+    html.push_str(&format!("<h1>{}</h1>\n", title));
+    html
+}
+
+// Everything of an HTML report page after its body
+pub fn html_document_end(letterhead: &Letterhead) -> String {
+    format!("{}</body>\n</html>", letterhead.html_footer())
+}
+
+// Export search results, with matches marked in HTML output
+pub fn export_search_results(hits: &[SearchHit], letterhead: &Letterhead, file_path: &str) -> Result<(), Box<dyn Error>> {
+    let mut out = render::Stream::new(io::BufWriter::new(fs::File::create(file_path)?), None);
+    write_search_results(hits, letterhead, file_path.ends_with(".html"), &mut out)?;
+    out.finish()?;
+    Ok(())
+}
+
+// Write search results as an HTML document or plain text, each code rendered in parallel
+pub fn write_search_results<W: Write>(hits: &[SearchHit], letterhead: &Letterhead, html: bool, out: &mut render::Stream<W>) -> io::Result<()> {
+    if html {
+        out.push(&html_document_start(&tr!("report-title"), letterhead))?;
+        out.blocks(hits, "", |hit| hit.error.to_html_highlighted(&hit.highlights))?;
+        out.push(&html_document_end(letterhead))
+    } else {
+        out.push(&letterhead.text_header())?;
+        out.blocks(hits, "\n", |hit| hit.error.to_text())?;
+        out.push(&letterhead.text_footer())
+    }
+}
+
+// Render the report for one error code as an HTML document or plain text
+pub fn render_report(error: &Arc<ErrorCode>, labor: &LaborGuide, letterhead: &Letterhead, order: &RepairOrder, html: bool) -> String {
+    ReportBuilder::new(tr!("report-title"))
+        .letterhead(letterhead.clone())
+        .repair_order(order.clone())
+        .codes([Arc::clone(error)])
+        .labor(labor.operations_for(&error.code))
+        .render(if html { "html" } else { "text" })
+        .unwrap_or_default()
+}
+
+// Function to export error to file
+pub fn export_to_file(error: &Arc<ErrorCode>, labor: &LaborGuide, letterhead: &Letterhead, order: &RepairOrder, anonymizer: Option<&Anonymizer>, file_path: &str) -> Result<(), Box<dyn Error>> {
+    let mut content = render_report(error, labor, letterhead, order, file_path.ends_with(".html"));
+    if let Some(anonymizer) = anonymizer {
+        content = anonymizer.text(&content, &[]);
+    }
+    let mut file = fs::File::create(file_path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+// Check whether a token is shaped like a diagnostic trouble code (e.g. P0420)
+pub fn looks_like_code(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!(chars.next(), Some('P' | 'B' | 'C' | 'U' | 'p' | 'b' | 'c' | 'u'))
+        && token.len() == 5
+        && chars.all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::fs;
+    use crate::branding::Letterhead;
+    use crate::labor::LaborGuide;
+    use crate::repair_order::RepairOrder;
+    use crate::search::{SearchOptions, SortOrder};
+    use crate::{builder, render_report, write_search_results, DiagnosticsDatabase, Difficulty, ErrorCode, ListFilter, Source};
+    use crate::testing::{assert_golden, init, sample_database, sample_labor_guide, sample_letterhead, streamed, TempDir, FIXED_TIME, SAMPLE_DATABASE};
+
+    #[test]
+    fn report_text() {
+        init();
+        let report = render_report(&Arc::new(ErrorCode::fixture()), &sample_labor_guide(), &Letterhead::default(), &RepairOrder::default(), false);
+        assert_golden("report.txt", &report);
+    }
+
+    #[test]
+    fn report_html() {
+        init();
+        let report = render_report(&Arc::new(ErrorCode::fixture()), &sample_labor_guide(), &Letterhead::default(), &RepairOrder::default(), true);
+        assert_golden("report.html", &report);
+    }
+
+    #[test]
+    fn branded_repair_order_report() {
+        init();
+        let letterhead = sample_letterhead();
+        let order = RepairOrder {
+            customer: Some("Jane Doe".to_string()),
+            number: Some("12345".to_string()),
+            odometer: Some(88_300),
+            date: Some(FIXED_TIME),
+        };
+        assert_golden("report-branded.txt", &render_report(&Arc::new(ErrorCode::fixture()), &LaborGuide::default(), &letterhead, &order, false));
+        assert_golden("report-branded.html", &render_report(&Arc::new(ErrorCode::fixture()), &LaborGuide::default(), &letterhead, &order, true));
+    }
+
+    #[test]
+    fn search_results_html() {
+        init();
+        let db = sample_database();
+        let page = db.search("sensor", &SearchOptions { sort: SortOrder::Code, ..Default::default() });
+        assert_eq!(page.hits.iter().map(|hit| hit.error.code.as_str()).collect::<Vec<_>>(), ["C0035", "P0101"]);
+        assert_golden("search-sensor.html", &streamed(|out| write_search_results(&page.hits, &Letterhead::default(), true, out)));
+    }
+
+    #[test]
+    fn list_filter_is_sorted_by_code() {
+        init();
+        let db = DiagnosticsDatabase::from_records([
+            ErrorCode::fixture_with("P0420", "Medium", "Exhaust"),
+            ErrorCode::fixture_with("P0171", "Medium", "Engine"),
+            ErrorCode::fixture_with("B0001", "Critical", "Airbag"),
+        ]);
+        let filter = crate::ListFilter { system: None, severity: Some("medium".to_string()), warranty_covered: false, max_difficulty: None };
+        let codes: Vec<_> = db.list_errors(&filter).iter().map(|error| error.code.as_str()).collect();
+        assert_eq!(codes, ["P0171", "P0420"]);
+    }
+
+    #[test]
+    fn list_fields_round_trip_through_csv() {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(ErrorCode::fixture()).unwrap();
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert!(written.contains(",Spark plug issues|Ignition coil problems,"));
+
+        let read: ErrorCode = csv::Reader::from_reader(written.as_bytes()).deserialize().next().unwrap().unwrap();
+        assert_eq!(read.possible_causes, ErrorCode::fixture().possible_causes);
+        assert_eq!(read.recommended_actions, ErrorCode::fixture().recommended_actions);
+    }
+
+    #[test]
+    fn later_pack_overrides_earlier_definition() {
+        init();
+        let mut db = sample_database();
+        let pack = Source { pack: "toyota".to_string(), file: "toyota.csv".to_string(), version: None, precedence: 1 };
+        let csv = "code,description,severity,system,possible_causes,recommended_actions\nP0300,Toyota misfire,High,Engine,Coils,Replace coils\n";
+        db.read_records(csv.as_bytes(), pack.clone()).unwrap();
+
+        let effective = db.lookup_error("P0300").unwrap();
+        assert_eq!(effective.description, "Toyota misfire");
+        assert_eq!(effective.source.as_deref(), Some(&pack));
+        let sources: Vec<_> = db.definitions_of("P0300").iter().map(|error| error.source.as_ref().unwrap().pack.as_str()).collect();
+        assert_eq!(sources, ["fixture", "toyota"]);
+    }
+
+    #[test]
+    fn set_conditions_and_monitors_appear_in_lookup_text() {
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,set_conditions,monitors\n\
+             P0420,Catalyst Efficiency,Medium,Emissions,Catalyst,Replace,Steady cruise when warm,catalyst|o2-sensor|custom\n\
+             P0101,MAF Range,Medium,Engine,MAF,Clean,,\n",
+        );
+
+        let text = db.lookup_error("P0420").unwrap().to_text();
+        assert!(text.contains("When It Sets:\n  Conditions: Steady cruise when warm\n  Monitors: Catalyst, Oxygen sensor, custom\n"));
+        assert!(!db.lookup_error("P0101").unwrap().to_text().contains("When It Sets"));
+    }
+
+    #[test]
+    fn campaigns_from_a_pack_cannot_put_markup_or_scripts_in_reports() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,service_campaigns,campaign_links\n\
+             P0420,Catalyst Efficiency,Medium,Emissions,Catalyst,Replace,<script>alert(1)</script>,javascript:alert(1)|https://example.com/tsb?a=1&b='2'\n",
+        );
+
+        let html = db.lookup_error("P0420").unwrap().to_html();
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<li>javascript:alert(1)</li>"));
+        assert!(!html.contains("href='javascript:"));
+        assert!(html.contains("<a href='https://example.com/tsb?a=1&amp;b=&#39;2&#39;'>"));
+    }
+
+    #[test]
+    fn aliases_from_a_pack_are_escaped_in_reports() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,aliases\n\
+             P0420,Catalyst Efficiency,Medium,Emissions,Catalyst,Replace,<b>cat</b>|cat & O2\n",
+        );
+
+        let html = db.lookup_error("P0420").unwrap().to_html();
+        assert!(html.contains("&lt;b&gt;cat&lt;/b&gt;, cat &amp; O2</p>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn related_parts_from_a_pack_are_escaped_in_reports() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,related_parts\n\
+             P0420,Catalyst Efficiency,Medium,Emissions,Catalyst,Replace,<img src=x onerror=alert(1)>:18-0001|Gasket & bolts\n",
+        );
+
+        let html = db.lookup_error("P0420").unwrap().to_html();
+        assert!(html.contains("<li>&lt;img src=x onerror=alert(1)&gt; (OEM 18-0001)</li>"));
+        assert!(html.contains("<li>Gasket &amp; bolts</li>"));
+        assert!(!html.contains("<img"));
+    }
+
+    #[test]
+    fn difficulty_is_shown_checked_and_filters_the_list() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,difficulty\n\
+             P0455,EVAP Gross Leak,Medium,Emissions,Loose cap,Tighten cap,diy\n\
+             P0420,Catalyst Efficiency,Medium,Emissions,Catalyst,Replace,Intermediate\n\
+             B0001,Driver Airbag Circuit,Critical,Airbag,Squib,Replace,Professional\n\
+             P0101,MAF Range,Medium,Engine,MAF,Clean,\n\
+             P0171,System Too Lean,Medium,Engine,Vacuum leak,Smoke test,Easy\n",
+        );
+
+        let listed = |max: Option<Difficulty>| {
+            db.list_errors(&ListFilter { max_difficulty: max, ..Default::default() }).iter().map(|error| error.code.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(listed(Some(Difficulty::Diy)), ["P0455"]);
+        assert_eq!(listed(Some(Difficulty::Intermediate)), ["P0420", "P0455"]);
+        // Unrated codes, and ratings the tool does not know, are only listed without the filter
+        assert_eq!(listed(Some(Difficulty::Professional)), ["B0001", "P0420", "P0455"]);
+        assert_eq!(listed(None).len(), 5);
+
+        let text = db.lookup_error("B0001").unwrap().to_text();
+        assert!(text.contains("Severity: Critical\nDifficulty: Professional: leave it to a workshop\nSystem: Airbag\n"));
+        assert!(!db.lookup_error("P0101").unwrap().to_text().contains("Difficulty"));
+        assert!(builder::check(db.lookup_error("P0455").unwrap()).is_empty());
+        assert_eq!(builder::check(db.lookup_error("P0171").unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn library_loads_looks_up_searches_and_exports_a_database_file() {
+        init();
+        let dir = TempDir::new("library");
+        let path = dir.file("codes.csv");
+        fs::write(&path, SAMPLE_DATABASE).unwrap();
+        let mut db = DiagnosticsDatabase::new();
+        assert_eq!(db.load_from_csv(&path).unwrap(), 4);
+
+        assert_eq!(db.lookup_error("P0300").map(|error| error.description.as_str()), Some("Random/Multiple Cylinder Misfire Detected"));
+        let hits: Vec<String> = db.search("wiring", &SearchOptions::default()).hits.iter().map(|hit| hit.error.code.clone()).collect();
+        assert_eq!(hits, ["B0001", "C0035"]);
+
+        let error = db.shared_error("C0035").unwrap();
+        let html = crate::render_report(&error, &LaborGuide::default(), &Letterhead::default(), &RepairOrder::default(), true);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Left Front Wheel Speed Sensor Circuit"));
+    }
+}
@@ -1,416 +1,1610 @@
-use std::collections::HashMap;
+// The cars-scanner command line: arguments are parsed here and every command runs
+// through the library in lib.rs
+use std::collections::BTreeSet;
 use std::error::Error;
 use std::fs;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
-use serde::{Deserialize, Serialize};
-use clap::{Parser, Subcommand};
-use csv::ReaderBuilder;
-use colored::*;
-
-// Define the error code structure
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct ErrorCode {
-    code: String,
-    description: String,
-    severity: String,
-    system: String,
-    possible_causes: String,
-    recommended_actions: String,
-}
-
-impl ErrorCode {
-    fn to_text(&self) -> String {
-        let mut output = String::new();
-        output.push_str(&format!("Error Code: {}\n", self.code));
-        output.push_str(&format!("Description: {}\n", self.description));
-        output.push_str(&format!("Severity: {}\n", self.severity));
-        output.push_str(&format!("System: {}\n", self.system));
-        
-        output.push_str("\nPossible Causes:\n");
-        for cause in self.possible_causes.split('|') {
-            output.push_str(&format!("  - {}\n", cause.trim()));
-        }
-        
-        output.push_str("\nRecommended Actions:\n");
-        for action in self.recommended_actions.split('|') {
-            output.push_str(&format!("  - {}\n", action.trim()));
-        }
-        
-        output
-    }
-    
-    fn to_html(&self) -> String {
-        let mut output = String::new();
-        output.push_str("<div class='error-code'>\n");
-        output.push_str(&format!("<h2>Error Code: {}</h2>\n", self.code));
-        output.push_str(&format!("<p><strong>Description:</strong> {}</p>\n", self.description));
-        output.push_str(&format!("<p><strong>Severity:</strong> {}</p>\n", self.severity));
-        output.push_str(&format!("<p><strong>System:</strong> {}</p>\n", self.system));
-        
-        output.push_str("<h3>Possible Causes:</h3>\n<ul>\n");
-        for cause in self.possible_causes.split('|') {
-            output.push_str(&format!("<li>{}</li>\n", cause.trim()));
-        }
-        output.push_str("</ul>\n");
-        
-        output.push_str("<h3>Recommended Actions:</h3>\n<ul>\n");
-        for action in self.recommended_actions.split('|') {
-            output.push_str(&format!("<li>{}</li>\n", action.trim()));
-        }
-        output.push_str("</ul>\n");
-        output.push_str("</div>\n");
-        
-        output
-    }
-}
-
-// Define the diagnostics database
-struct DiagnosticsDatabase {
-    errors: HashMap<String, ErrorCode>,
-}
-
-impl DiagnosticsDatabase {
-    // Create a new database
-    fn new() -> Self {
-        DiagnosticsDatabase {
-            errors: HashMap::new(),
-        }
-    }
-
-    // Load data from a CSV file
-    fn load_from_csv(&mut self, file_path: &str) -> Result<(), Box<dyn Error>> {
-        let file = fs::File::open(file_path)?;
-        let mut reader = ReaderBuilder::new()
-            .has_headers(true)
-            .from_reader(file);
-        
-        for result in reader.deserialize() {
-            let record: ErrorCode = result?;
-            self.errors.insert(record.code.clone(), record);
-        }
-        
-        println!("Loaded {} error codes from database", self.errors.len());
-        Ok(())
-    }
-
-    // Look up an error code
-    fn lookup_error(&self, code: &str) -> Option<&ErrorCode> {
-        self.errors.get(code)
-    }
-    
-    // List errors by system
-    fn list_errors_by_system(&self, system: &str) -> Vec<&ErrorCode> {
-        self.errors.values()
-            .filter(|error| error.system.to_lowercase() == system.to_lowercase())
-            .collect()
-    }
-    
-    // List errors by severity
-    fn list_errors_by_severity(&self, severity: &str) -> Vec<&ErrorCode> {
-        self.errors.values()
-            .filter(|error| error.severity.to_lowercase() == severity.to_lowercase())
-            .collect()
-    }
-    
-    // Search by keyword
-    fn search_by_keyword(&self, keyword: &str) -> Vec<&ErrorCode> {
-        let keyword_lower = keyword.to_lowercase();
-        self.errors.values()
-            .filter(|error| {
-                error.description.to_lowercase().contains(&keyword_lower) || 
-                error.possible_causes.to_lowercase().contains(&keyword_lower) ||
-                error.recommended_actions.to_lowercase().contains(&keyword_lower)
-            })
-            .collect()
-    }
-}
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use clap::error::ErrorKind;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use cars_scanner::anonymize::Anonymizer;
+use cars_scanner::archive::{self, Clock, SystemClock};
+use cars_scanner::branding::Letterhead;
+use cars_scanner::builder::ErrorCodeBuilder;
+use cars_scanner::events::{self, Event, Kind};
+use cars_scanner::groups::{self, Groups};
+use cars_scanner::i18n::{self, tr};
+use cars_scanner::labor::LaborGuide;
+use cars_scanner::transcript::outln;
+use cars_scanner::warranty::{self, ReportType};
+use cars_scanner::workspace::Workspaces;
+use cars_scanner::json::{self, OutputFormat};
+use cars_scanner::maintenance::{self, ExportFormat};
+use cars_scanner::presentation::{self, Style, Styled, Theme};
+use cars_scanner::regenerate::{self, ReportState};
+use cars_scanner::repair_order::{RepairOrder, RepairOrderArgs};
+use cars_scanner::safety::SafetyArgs;
+use cars_scanner::search::{self, JsonHit, LastSearch, SearchHit, SearchOptions, SortOrder};
+use cars_scanner::session::{MissingDatabase, Session};
+use cars_scanner::sqlite::Subset;
+use cars_scanner::commands::{anonymized_by, anonymizer, archived_scans, connect_adapter, definition, display_all_sources, display_comparison, display_error, display_error_highlighted, display_error_paged, display_parts, display_quote, display_reverse, explain_merge, fleet_group, last_seen_line, lookup_codes, monitor_vehicle, print_porcelain, relink_scan, show_config, show_drive_cycle, troubleshoot_code};
+use cars_scanner::repl::{run_interactive_mode, ReplInput};
+use cars_scanner::{apps, config, contribute, doctor, extract, fleet, fuel_trim, help, inspection, interrupt, layout, monitor, net, o2_tests, picker, porcelain, progress, query, render, scan, usage, vehicle, export_search_results, export_to_file, Difficulty, ErrorCode, ListFilter, DATABASE_FILE, DIFFICULTIES};
+#[cfg(feature = "adapter")]
+use cars_scanner::commands::{display_daemon_status, monitor_signals};
+#[cfg(feature = "adapter")]
+use cars_scanner::{adapterd, emulator, scenario, time};
 
 // Define the command line interface
 #[derive(Parser)]
 #[command(author = "Abdul Wahed", version = "1.0", about = "Car Diagnostic Tool", long_about = None)]
 struct Cli {
+    /// Language for messages and reports (en, sv, de, es, fr); defaults to the system locale
+    #[arg(long, global = true)]
+    lang: Option<String>,
+    
+    /// Configuration file to use instead of cars-scanner.toml
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+    
+    /// Print long output directly instead of through a pager
+    #[arg(long, global = true)]
+    no_pager: bool,
+    
+    /// Never use the network; online features only use cached responses
+    #[arg(long, global = true)]
+    offline: bool,
+    
+    /// Do not draw progress bars or spinners
+    #[arg(long, global = true)]
+    no_progress: bool,
+    
+    /// Print one stable tab-separated record per result, for scripts (see `help porcelain`)
+    #[arg(long, global = true)]
+    porcelain: bool,
+    
+    /// json for lookup, search, list, list-by-system, list-by-severity and query; jsonl streams search results; csv or json for db export
+    #[arg(long, global = true, value_enum)]
+    format: Option<OutputFormat>,
+    
+    /// Colors and severity markers; colorblind avoids red/green and marks severities [-] to [!!!]
+    #[arg(long, global = true, value_enum)]
+    theme: Option<Theme>,
+    
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    // Command to look up an error code
+    /// Look up an error code
     Lookup {
         #[arg(short, long)]
         code: String,
         
         #[arg(short, long)]
         export: Option<String>,
+        
+        /// Compare the definitions of the code from every database and pack
+        #[arg(long)]
+        all_sources: bool,
+        
+        /// Say when the code was last read from this vehicle, instead of from any vehicle in the archive
+        #[arg(long)]
+        vin: Option<String>,
     },
     
-    // Command to list errors by system
+    /// Export a report for a repair order, with customer and vehicle details in the header
+    Report {
+        /// The code to report on; a warranty claim reports every code the vehicle holds
+        code: Option<String>,
+        
+        /// Report file; .html for HTML, anything else for plain text (a warranty claim uses its portal's format)
+        #[arg(short, long)]
+        output: String,
+        
+        /// warranty-claim reads the vehicle and writes a claim for the manufacturer's warranty portal
+        #[arg(long = "type", value_enum, default_value_t)]
+        kind: ReportType,
+        
+        /// Warranty portal layout from the warranty templates, e.g. generic-xml (default: warranty_oem setting)
+        #[arg(long)]
+        oem: Option<String>,
+        
+        /// Serial port or host:port of the adapter a warranty claim is read from (default: [adapter] port)
+        #[arg(long)]
+        port: Option<String>,
+        
+        /// Workspace whose notes are the repair narrative of a warranty claim
+        #[arg(long)]
+        workspace: Option<String>,
+        
+        #[command(flatten)]
+        order: RepairOrderArgs,
+        
+        /// Leave out personal data as the [anonymize] table says, for sharing the report
+        #[arg(long)]
+        anonymize: bool,
+        
+        /// Write the report even when nothing it is made from changed since it was last written
+        #[arg(long)]
+        force: bool,
+    },
+    
+    /// List errors by system
     ListBySystem {
         #[arg(short, long)]
         system: String,
     },
     
-    // Command to list errors by severity
+    /// List errors by severity
     ListBySeverity {
         #[arg(short, long)]
         severity: String,
     },
     
-    // Command to search by keyword
+    /// List errors matching all given filters
+    List {
+        #[arg(long)]
+        system: Option<String>,
+        
+        #[arg(long)]
+        severity: Option<String>,
+        
+        /// Only codes covered under the emissions warranty
+        #[arg(long)]
+        warranty_covered: bool,
+        
+        /// Only codes rated this hard or easier, e.g. diy; codes without a rating are left out
+        #[arg(long, value_enum)]
+        max_difficulty: Option<Difficulty>,
+    },
+    
+    /// Query the codes with a subset of SQL, e.g. "SELECT code, severity FROM codes WHERE system = 'Engine'"
+    Query {
+        /// SELECT <columns | *> FROM codes [WHERE ...] [ORDER BY ...] [LIMIT n]; see `help query`
+        sql: String,
+    },
+    
+    /// Search by keyword
     Search {
         #[arg(short, long)]
         keyword: String,
+        
+        /// Export the results with matches highlighted (.html or text)
+        #[arg(short, long)]
+        export: Option<String>,
+        
+        /// Order of the results [default: relevance]
+        #[arg(long, value_enum)]
+        sort: Option<SortOrder>,
+        
+        /// Number of results to skip
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        
+        /// Show at most this many results
+        #[arg(long)]
+        limit: Option<usize>,
+        
+        /// Search only the results of the previous search, narrowing it down
+        #[arg(long)]
+        within_last: bool,
+    },
+    
+    /// Find a code by typing parts of its code or description, then show it
+    Pick,
+    
+    /// Find codes by the text a dashboard or app shows, e.g. "catalyst efficiency below threshold bank 1"
+    Reverse {
+        /// The text as the customer quotes it
+        phrase: String,
+        
+        /// Show at most this many codes
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+    
+    /// Read live data from a vehicle and alert on configured thresholds
+    Monitor {
+        /// Serial port or host:port of the adapter (default: [adapter] port)
+        #[arg(long)]
+        port: Option<String>,
+        
+        /// PID to read, e.g. coolant or rpm; repeat for more (default: [monitor] pids)
+        #[arg(long = "pid")]
+        pids: Vec<String>,
+        
+        /// Stop after this many samples instead of waiting for Enter
+        #[arg(long)]
+        samples: Option<usize>,
+        
+        /// Stop after this long, e.g. 90s, 10m or 1h
+        #[arg(long, value_parser = monitor::parse_duration)]
+        duration: Option<Duration>,
+        
+        /// Print min/avg/max/stddev per PID, threshold violations and code changes at the end,
+        /// and save them to the scan archive
+        #[arg(long)]
+        summary: bool,
+        
+        /// Show the PIDs as gauges with sparklines on a screen that stays put, with
+        /// alerts and new trouble codes in a banner
+        #[arg(long)]
+        tui: bool,
+        
+        /// SocketCAN interface to read raw frames from, e.g. can0, instead of an adapter
+        #[cfg(feature = "adapter")]
+        #[arg(long, requires_all = ["dbc", "signals"], conflicts_with_all = ["port", "pids", "tui"])]
+        can: Option<String>,
+        
+        /// DBC file describing the manufacturer signals on the bus
+        #[cfg(feature = "adapter")]
+        #[arg(long, requires = "can")]
+        dbc: Option<String>,
+        
+        /// Signal from the DBC file to show, e.g. EngineSpeed; repeat for more
+        #[cfg(feature = "adapter")]
+        #[arg(long = "signal", requires = "can")]
+        signals: Vec<String>,
+    },
+    
+    /// Keep an adapter connected in the background, so each command starts at once
+    #[cfg(feature = "adapter")]
+    Adapterd {
+        #[command(subcommand)]
+        action: AdapterdCommand,
+    },
+    
+    /// Act as an ELM327 adapter attached to a scripted vehicle, for testing OBD tools
+    #[cfg(feature = "adapter")]
+    Emulate {
+        /// Vehicle to emulate: a bundled scenario such as misfire-intermittent, or a YAML file
+        /// (default: a healthy car idling)
+        #[arg(long, default_value = "")]
+        scenario: String,
+        
+        /// Address to accept TCP connections on, e.g. 127.0.0.1:35000
+        #[arg(long, default_value = "127.0.0.1:35000", conflicts_with = "pty")]
+        listen: String,
+        
+        /// Open a pseudo terminal to use as a serial port instead (Linux)
+        #[arg(long)]
+        pty: bool,
+    },
+    
+    /// Interpret live data or drive logs
+    Analyze {
+        #[command(subcommand)]
+        action: AnalyzeCommand,
+    },
+    
+    /// Write an emissions inspection report: MIL, readiness monitors, codes and VIN
+    Inspection {
+        /// Serial port or host:port of the adapter (default: [adapter] port)
+        #[arg(long)]
+        port: Option<String>,
+        
+        /// Template for the report wording and pass rules, e.g. california or eu (default: jurisdiction setting)
+        #[arg(long)]
+        jurisdiction: Option<String>,
+        
+        /// Write the report to a file (.html for HTML) instead of showing it
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Leave out personal data as the [anonymize] table says, for sharing the report
+        #[arg(long)]
+        anonymize: bool,
+    },
+    
+    /// Read the trouble codes of a vehicle and save them to the scan archive
+    Scan {
+        /// Serial port or host:port of an adapter, e.g. /dev/ttyUSB0 or COM3; repeat to scan
+        /// several vehicles at once (default: [adapter] port)
+        #[arg(long = "connect", visible_alias = "port", value_name = "PORT")]
+        ports: Vec<String>,
+        
+        /// Read only this ECU: engine, transmission, a name from [adapter.modules] or a CAN address such as 7E2
+        #[arg(long)]
+        module: Option<String>,
+    },
+    
+    /// Clear the trouble codes, freeze frame and MIL of one ECU, or of every ECU with --all
+    Clear {
+        /// Serial port or host:port of the adapter (default: [adapter] port)
+        #[arg(long)]
+        port: Option<String>,
+        
+        /// The ECU to clear: engine, transmission, a name from [adapter.modules] or a CAN address such as 7E2
+        #[arg(long, required_unless_present = "all")]
+        module: Option<String>,
+        
+        /// Clear every ECU that answers, erasing the codes of the modules not being worked on too
+        #[arg(long, conflicts_with = "module")]
+        all: bool,
+        
+        #[command(flatten)]
+        safety: SafetyArgs,
+    },
+    
+    /// Read oxygen sensor and catalyst test results and check them against their limits
+    O2Tests {
+        /// Serial port or host:port of the adapter (default: [adapter] port)
+        #[arg(long)]
+        port: Option<String>,
+    },
+    
+    /// Show the drive cycles that complete readiness monitors after codes are cleared
+    DriveCycle {
+        /// Vehicle make, for the manufacturer's own drive cycles where known, e.g. Toyota
+        #[arg(long)]
+        make: Option<String>,
+        
+        /// Monitor to show, e.g. catalyst or evap; repeat for more (default: all, or the incomplete ones when connected)
+        #[arg(long = "monitor")]
+        monitors: Vec<String>,
+        
+        /// Serial port or host:port of an adapter, to read which monitors are incomplete
+        #[arg(long)]
+        port: Option<String>,
+        
+        /// Keep reading readiness while driving until every monitor completes (default port: [adapter] port)
+        #[arg(long)]
+        watch: bool,
+    },
+    
+    /// Show two codes side by side with their differences highlighted
+    Compare {
+        first: String,
+        
+        second: String,
+    },
+    
+    /// Find the codes in a text or PDF printout and look them all up
+    Extract {
+        #[arg(short, long)]
+        file: String,
+    },
+    
+    /// List the parts related to an error code
+    Parts {
+        code: String,
+        
+        /// Fetch availability and pricing for each part from parts_api_url
+        #[arg(long)]
+        fetch: bool,
+    },
+    
+    /// Quote flat-rate labor for one or more error codes
+    Quote {
+        #[arg(required = true)]
+        codes: Vec<String>,
+        
+        /// Labor rate per hour; defaults to labor_rate from the configuration
+        #[arg(long)]
+        rate: Option<f64>,
     },
     
-    // Command to start interactive mode
-    Interactive,
+    /// Walk through the decision tree for an error code
+    Troubleshoot {
+        code: String,
+    },
+    
+    /// Start interactive mode
+    Interactive {
+        /// Run the commands in this file, one per line, instead of prompting
+        /// (commands are also read from stdin when it is not a terminal)
+        #[arg(long)]
+        script: Option<String>,
+    },
+    
+    /// Check the installation and suggest fixes for any problems
+    Doctor,
+    
+    /// Summarize your own use of the tool from the local usage log; nothing is ever sent
+    Usage {
+        /// Days to look back, up to ten years
+        #[arg(long, default_value_t = 14, value_parser = clap::value_parser!(u64).range(1..=3650))]
+        days: u64,
+    },
+    
+    /// The log of scans, database changes and reports
+    Events {
+        #[command(subcommand)]
+        action: EventsCommand,
+    },
+    
+    /// Fleet telematics imports and summaries
+    Fleet {
+        #[command(subcommand)]
+        action: FleetCommand,
+    },
+    
+    /// Correct which vehicle archived scans belong to
+    Garage {
+        #[command(subcommand)]
+        action: GarageCommand,
+    },
+
+    /// Generate documentation
+    Docs {
+        #[command(subcommand)]
+        action: DocsCommand,
+    },
+    
+    /// Inspect the error code databases and packs
+    Db {
+        #[command(subcommand)]
+        action: DbCommand,
+    },
+    
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
 }
 
-// Display error information with color
-fn display_error(error: &ErrorCode) {
-    println!("{}", "================================".bright_blue());
-    println!("{} {}", "Error Code:".bright_yellow(), error.code.bright_white());
-    println!("{} {}", "Description:".bright_yellow(), error.description);
-    
-    // Color code the severity
-    let severity_colored = match error.severity.as_str() {
-        "Low" => error.severity.bright_green(),
-        "Medium" => error.severity.bright_yellow(),
-        "High" => error.severity.bright_red(),
-        "Critical" => error.severity.on_red().bright_white(),
-        _ => error.severity.normal(),
-    };
-    
-    println!("{} {}", "Severity:".bright_yellow(), severity_colored);
-    println!("{} {}", "System:".bright_yellow(), error.system.bright_cyan());
-    
-    println!("\n{}", "Possible Causes:".bright_magenta());
-    for cause in error.possible_causes.split('|') {
-        println!("  - {}", cause.trim());
-    }
+#[cfg(feature = "adapter")]
+#[derive(Subcommand)]
+enum AdapterdCommand {
+    /// Connect to the adapter and hold it for the commands that use that port
+    Start {
+        /// Serial port or host:port of the adapter (default: [adapter] port)
+        #[arg(long)]
+        port: Option<String>,
+        
+        /// Stay in the foreground, printing errors, until Ctrl-C
+        #[arg(long)]
+        foreground: bool,
+    },
     
-    println!("\n{}", "Recommended Actions:".bright_green());
-    for action in error.recommended_actions.split('|') {
-        println!("  - {}", action.trim());
-    }
-    println!("{}", "================================\n".bright_blue());
+    /// Close the adapter and end the daemon
+    Stop,
+    
+    /// Show the port the daemon holds and what it found there
+    Status,
 }
 
-// Function to export error to file
-fn export_to_file(error: &ErrorCode, file_path: &str) -> Result<(), Box<dyn Error>> {
-    let content = if file_path.ends_with(".html") {
-        // Create an HTML document
-        let mut html = String::new();
-        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
-        html.push_str("<title>Car Error Code Report</title>\n");
-        html.push_str("<style>\n");
-        html.push_str("body { font-family: Arial, sans-serif; margin: 20px; }\n");
-        html.push_str(".error-code { border: 1px solid #ddd; padding: 15px; margin-bottom: 20px; }\n");
-        html.push_str("h2 { color: #d9534f; }\n");
-        html.push_str("h3 { color: #5bc0de; }\n");
-        html.push_str("</style>\n");
-        html.push_str("</head>\n<body>\n");
-        html.push_str("<h1>Car Error Code Report</h1>\n");
-        html.push_str(&error.to_html());
-        html.push_str("</body>\n</html>");
-        html
-    } else {
-        // Default to text format
-        error.to_text()
-    };
-    
-    let mut file = fs::File::create(file_path)?;
-    file.write_all(content.as_bytes())?;
-    
-    println!("Report exported to {}", file_path);
-    Ok(())
+#[derive(Subcommand)]
+enum AnalyzeCommand {
+    /// Compare fuel trims at idle and 2500 rpm to tell vacuum leaks, MAF and fuel delivery faults apart
+    FuelTrims {
+        /// Serial port or host:port of an adapter to sample from (default: [adapter] port)
+        #[arg(long, conflicts_with = "log")]
+        port: Option<String>,
+        
+        /// Drive log CSV with rpm, stft1 and ltft1 columns (stft2 and ltft2 for bank 2)
+        #[arg(long)]
+        log: Option<String>,
+        
+        /// Samples to take at each engine speed when connected
+        #[arg(long, default_value_t = 20)]
+        samples: usize,
+    },
 }
 
-// Run interactive mode
-fn run_interactive_mode(db: &DiagnosticsDatabase) -> Result<(), Box<dyn Error>> {
-    println!("{}", "=== Car Diagnostic Tool Interactive Mode ===".bright_blue());
-    println!("Type '{}' for available commands or '{}' to quit", "help".bright_green(), "exit".bright_red());
+#[derive(Subcommand)]
+enum FleetCommand {
+    /// Import a CSV or JSON dump of VINs and codes into the scan archive
+    Import {
+        file: String,
+        
+        /// Add the imported vehicles to this group, creating it when needed
+        #[arg(long)]
+        group: Option<String>,
+    },
     
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
-    let mut input = String::new();
+    /// Write an HTML dashboard of archived scans, with charts and a CSV appendix
+    Report {
+        /// How far back to look, e.g. 30d, 12h or 4w
+        #[arg(long, default_value = "30d")]
+        period: String,
+        
+        #[arg(short, long, default_value = "fleet-report.html")]
+        output: String,
+        
+        /// Only the vehicles of this group
+        #[arg(long)]
+        group: Option<String>,
+        
+        /// Leave out personal data as the [anonymize] table says, for sharing the report
+        #[arg(long)]
+        anonymize: bool,
+        
+        /// Write the dashboard even when no scan or code in it changed since it was last written
+        #[arg(long)]
+        force: bool,
+    },
     
-    loop {
-        print!("{} ", ">".bright_cyan());
-        io::stdout().flush()?;
+    /// Show the health score of every archived scan of a vehicle
+    History {
+        vin: String,
+    },
+    
+    /// Write archived scans as a fault code CSV for a mobile OBD-II app
+    ExportApp {
+        #[arg(value_enum)]
+        app: apps::AppFormat,
         
-        input.clear();
-        handle.read_line(&mut input)?;
-        let input = input.trim();
+        /// Only the scans of this vehicle
+        #[arg(long)]
+        vin: Option<String>,
         
-        if input.is_empty() {
-            continue;
-        }
+        /// Only the scans of vehicles in this group
+        #[arg(long)]
+        group: Option<String>,
         
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        let command = parts[0].to_lowercase();
-        
-        match command.as_str() {
-            "exit" | "quit" => break,
-            
-            "help" => {
-                println!("{}", "Available commands:".bright_blue());
-                println!("  {} {} - Look up details for an error code", "lookup".bright_green(), "<code>".bright_yellow());
-                println!("  {} {} - List all errors for a specific system", "system".bright_green(), "<system_name>".bright_yellow());
-                println!("  {} {} - List all errors with a specific severity", "severity".bright_green(), "<level>".bright_yellow());
-                println!("  {} {} - Search for errors containing a keyword", "search".bright_green(), "<keyword>".bright_yellow());
-                println!("  {} - Display this help message", "help".bright_green());
-                println!("  {} - Exit the interactive mode", "exit".bright_red());
-            },
-            
-            "lookup" => {
-                if parts.len() < 2 {
-                    println!("Usage: {} {}", "lookup".bright_green(), "<code>".bright_yellow());
-                    continue;
-                }
-                
-                let code = parts[1];
-                match db.lookup_error(code) {
-                    Some(error) => display_error(error),
-                    None => println!("Error code '{}' not found in database", code.bright_red()),
-                }
-            },
-            
-            "system" => {
-                if parts.len() < 2 {
-                    println!("Usage: {} {}", "system".bright_green(), "<system_name>".bright_yellow());
-                    continue;
-                }
-                
-                let system = parts[1];
-                let errors = db.list_errors_by_system(system);
-                if errors.is_empty() {
-                    println!("No errors found for system: {}", system.bright_red());
-                } else {
-                    println!("Found {} errors for system: {}", errors.len().to_string().bright_green(), system.bright_cyan());
-                    for error in errors {
-                        display_error(error);
-                    }
-                }
-            },
-            
-            "severity" => {
-                if parts.len() < 2 {
-                    println!("Usage: {} {}", "severity".bright_green(), "<level>".bright_yellow());
-                    continue;
-                }
-                
-                let severity = parts[1];
-                let errors = db.list_errors_by_severity(severity);
-                if errors.is_empty() {
-                    println!("No errors found with severity: {}", severity.bright_red());
-                } else {
-                    println!("Found {} errors with severity: {}", errors.len().to_string().bright_green(), severity.bright_cyan());
-                    for error in errors {
-                        display_error(error);
-                    }
-                }
-            },
-            
-            "search" => {
-                if parts.len() < 2 {
-                    println!("Usage: {} {}", "search".bright_green(), "<keyword>".bright_yellow());
-                    continue;
-                }
-                
-                let keyword = parts[1];
-                let errors = db.search_by_keyword(keyword);
-                if errors.is_empty() {
-                    println!("No errors found containing keyword: {}", keyword.bright_red());
-                } else {
-                    println!("Found {} errors containing keyword: {}", errors.len().to_string().bright_green(), keyword.bright_cyan());
-                    for error in errors {
-                        display_error(error);
-                    }
-                }
-            },
-            
-            _ => println!("{} Type '{}' for available commands.", "Unknown command.".bright_red(), "help".bright_green()),
-        }
-    }
+        /// Output file (default: <app>-dtcs.csv)
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Leave out personal data as the [anonymize] table says, for sharing the file
+        #[arg(long)]
+        anonymize: bool,
+    },
     
-    println!("Exiting interactive mode");
-    Ok(())
+    /// Define vehicle groups such as regions, routes or model-year cohorts
+    Group {
+        #[command(subcommand)]
+        action: GroupCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum GarageCommand {
+    /// Take a scan out of its vehicle's history, keeping it in the archive
+    Unlink {
+        /// The id 'fleet history' shows for the scan
+        scan_id: String,
+    },
+    
+    /// File a scan under another vehicle
+    Move {
+        scan_id: String,
+        
+        #[arg(long)]
+        to: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum GroupCommand {
+    /// Add vehicles to a group, creating it when needed
+    Add {
+        name: String,
+        
+        vins: Vec<String>,
+        
+        /// Also every vehicle whose VIN gives a model year in this range, e.g. 2015-2018
+        #[arg(long)]
+        years: Option<String>,
+        
+        #[arg(long)]
+        description: Option<String>,
+    },
+    
+    /// Take vehicles out of a group, or delete the group when no VIN is given
+    Remove {
+        name: String,
+        
+        vins: Vec<String>,
+    },
+    
+    /// List the groups with their vehicles
+    List,
+}
+
+#[derive(Subcommand)]
+enum EventsCommand {
+    /// Show the latest events
+    Tail {
+        /// Number of events to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+        
+        /// Keep showing events as they are recorded, until Ctrl-C
+        #[arg(short, long)]
+        follow: bool,
+        
+        /// Print each event as its line of JSON
+        #[arg(long)]
+        jsonl: bool,
+    },
+    
+    /// Find events in the log and its rotated files, oldest first
+    Query {
+        #[arg(long, value_enum)]
+        kind: Option<Kind>,
+        
+        /// E.g. scan, fleet-import, "db import" or warranty-claim
+        #[arg(long)]
+        action: Option<String>,
+        
+        #[arg(long)]
+        vin: Option<String>,
+        
+        /// Only events with this trouble code
+        #[arg(long)]
+        code: Option<String>,
+        
+        /// Only events of this period, e.g. 12h, 7d or 4w
+        #[arg(long)]
+        since: Option<String>,
+        
+        /// Print each event as its line of JSON
+        #[arg(long)]
+        jsonl: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DocsCommand {
+    /// Write roff man pages for every command, the interactive commands and every help topic
+    Man {
+        #[arg(short, long, default_value = "man")]
+        out_dir: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the configuration file, or with --resolved every effective setting and where it came from
+    Show {
+        /// Merge the defaults, the file and CARS_SCANNER_* variables
+        #[arg(long)]
+        resolved: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Show how the definitions of a code were merged across packs
+    ExplainMerge {
+        code: String,
+    },
+    
+    /// Add a code to the bundled database or a pack
+    Add {
+        code: String,
+        
+        #[arg(long)]
+        description: String,
+        
+        #[arg(long)]
+        system: String,
+        
+        /// Low, Medium, High or Critical; suggested from similar codes when omitted
+        #[arg(long)]
+        severity: Option<String>,
+        
+        /// Possible causes separated by '|'
+        #[arg(long, default_value = "")]
+        causes: String,
+        
+        /// Recommended actions separated by '|'
+        #[arg(long, default_value = "")]
+        actions: String,
+        
+        /// How hard the code is to diagnose and repair (default: not rated)
+        #[arg(long, value_enum)]
+        difficulty: Option<Difficulty>,
+        
+        /// Name of the pack to add the code to (default: the bundled database)
+        #[arg(long)]
+        pack: Option<String>,
+    },
+    
+    /// Check every code and flag severities that disagree with similar codes
+    Validate,
+    
+    /// Show codes picked at random, the same ones for the same seed, to spot-check the data
+    Sample {
+        /// How many codes to show
+        #[arg(long, default_value_t = 25)]
+        n: usize,
+        
+        /// Seed of the random pick (default: a new one, shown so the sample can be repeated)
+        #[arg(long)]
+        seed: Option<u64>,
+        
+        /// Only codes matching, e.g. system=Engine or severity=High; repeat a field for
+        /// either value, another field for both
+        #[arg(long = "where", value_parser = maintenance::parse_condition)]
+        conditions: Vec<maintenance::Condition>,
+    },
+    
+    /// Remove a code from the bundled database or a pack
+    Remove {
+        code: String,
+        
+        /// Name of the pack to remove the code from (default: the bundled database)
+        #[arg(long)]
+        pack: Option<String>,
+        
+        #[command(flatten)]
+        safety: SafetyArgs,
+    },
+    
+    /// Add and update codes from a CSV or JSON file, showing the changes first
+    Import {
+        /// CSV file, or JSON as written by `db export --format json`
+        #[arg(required_unless_present = "refresh")]
+        file: Option<String>,
+        
+        /// Name of the pack to import into (default: the bundled database)
+        #[arg(long)]
+        pack: Option<String>,
+        
+        /// Convert the file to a new SQLite database instead, for `database` in the config file
+        #[arg(long, value_name = "DATABASE", conflicts_with = "pack")]
+        sqlite: Option<String>,
+        
+        /// Convert the SQLite database again from the files it was converted from, if one changed
+        #[arg(long, requires = "sqlite")]
+        refresh: bool,
+        
+        #[command(flatten)]
+        safety: SafetyArgs,
+    },
+    
+    /// Upgrade the bundled database or a pack to the current schema, showing the steps first
+    Migrate {
+        /// Name of the pack to upgrade (default: the bundled database)
+        #[arg(long)]
+        pack: Option<String>,
+        
+        #[command(flatten)]
+        safety: SafetyArgs,
+    },
+    
+    /// Record that the bundled database or a pack was brought up to date today
+    Update {
+        /// Only show how old the database and every pack are; exits with status 1 when one is
+        /// older than [freshness] max_age
+        #[arg(long, conflicts_with = "pack")]
+        check: bool,
+        
+        /// Name of the pack brought up to date (default: the bundled database)
+        #[arg(long)]
+        pack: Option<String>,
+    },
+    
+    /// Write the bundled database or a pack as CSV or JSON
+    Export {
+        /// Output file (default: standard output)
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Sort by code and normalize every value, for minimal diffs when the file is kept in git
+        #[arg(long)]
+        canonical: bool,
+        
+        /// Name of the pack to export (default: the bundled database)
+        #[arg(long)]
+        pack: Option<String>,
+    },
+    
+    /// Bundle locally added and edited codes for review upstream
+    Contribute {
+        /// Bundle file; a Markdown summary is written next to it
+        #[arg(short, long, default_value = "contribution.json")]
+        output: String,
+        
+        /// Upstream database file to compare with (default: the bundled database)
+        #[arg(long)]
+        against: Option<String>,
+        
+        /// Only include codes from this pack (repeatable)
+        #[arg(long)]
+        pack: Vec<String>,
+    },
 }
 
+
 fn main() -> Result<(), Box<dyn Error>> {
-    // Initialize the database
-    let mut db = DiagnosticsDatabase::new();
-    
-    // Define the CSV file path
-    let csv_file = "src/data/error_codes.csv";
-    
-    // Check if the file exists and load it
-    if Path::new(csv_file).exists() {
-        db.load_from_csv(csv_file)?;
-    } else {
-        println!("{}: Could not find error codes database at {}", "Error".bright_red(), csv_file);
-        println!("Please make sure the file exists in the correct location.");
-        return Ok(());
+    // Parse command line arguments
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    i18n::init(cli.lang.as_deref());
+    if let Some(path) = &cli.config {
+        config::set_path(path.clone());
+    }
+    if cli.no_pager {
+        help::disable_pager();
+    }
+    if cli.offline {
+        net::set_offline();
+    }
+    if cli.no_progress {
+        progress::disable_progress();
+    }
+    let format = cli.format.unwrap_or_default();
+    if !cli.command.formats().contains(&format) {
+        Cli::command().error(ErrorKind::InvalidValue, tr!("format-unsupported", format = format.name(), command = command_name(&matches))).exit();
+    }
+    // JSON is for programs as much as porcelain is: nothing else goes to stdout
+    if cli.porcelain
+        || matches!(format, OutputFormat::Json | OutputFormat::Jsonl)
+        || matches!(cli.command, Commands::Events { action: EventsCommand::Tail { jsonl: true, .. } | EventsCommand::Query { jsonl: true, .. } })
+    {
+        porcelain::enable();
     }
+    if let Some(theme) = cli.theme {
+        presentation::set_theme(theme);
+    }
+    interrupt::install();
     
-    // Parse command line arguments
-    let cli = Cli::parse();
+    // The self-check must work even when the database or configuration is broken
+    if let Commands::Doctor = cli.command {
+        let failed = doctor::run();
+        std::process::exit(if failed == 0 { 0 } else { 1 });
+    }
     
-    // Execute the appropriate command
-    match &cli.command {
-        Commands::Lookup { code, export } => {
-            match db.lookup_error(code) {
+    let session = Session::default();
+    let result = run(&cli.command, format, &session);
+    // Only commands that loaded the configuration anyway; interactive mode records its own commands
+    if let (Some(config), false) = (session.loaded_config(), matches!(cli.command, Commands::Interactive { .. })) {
+        let event = usage::Event { timestamp: SystemClock.now(), session: SystemClock.batch_id(), command: command_name(&matches), codes: cli.command.looked_up() };
+        usage::record(config, &event);
+    }
+    match result {
+        Err(e) if e.is::<MissingDatabase>() => {
+            outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e);
+            outln!("{}", tr!("db-missing-hint"));
+            Ok(())
+        },
+        Err(e) if interrupt::is_interrupted(e.as_ref()) => {
+            outln!("{}", tr!("interrupted").styled(Style::Warning));
+            std::process::exit(interrupt::EXIT_INTERRUPTED);
+        },
+        result => result,
+    }
+}
+
+// The subcommands given, e.g. "fleet report"
+// Write a report on one code, log it and say where it went
+fn export_report(error: &Arc<ErrorCode>, labor: &LaborGuide, letterhead: &Letterhead, order: &RepairOrder, anonymizer: Option<&Anonymizer>, file_path: &str) -> Result<(), Box<dyn Error>> {
+    export_to_file(error, labor, letterhead, order, anonymizer, file_path)?;
+    events::record(Event::new(Kind::Report, "report").codes(std::slice::from_ref(&error.code)).path(file_path));
+    report_exported(file_path);
+    Ok(())
+}
+
+// Write search results, log them and say where they went
+fn export_search(hits: &[SearchHit], letterhead: &Letterhead, file_path: &str) -> Result<(), Box<dyn Error>> {
+    export_search_results(hits, letterhead, file_path)?;
+    let codes: Vec<String> = hits.iter().map(|hit| hit.error.code.clone()).collect();
+    events::record(Event::new(Kind::Report, "search export").codes(&codes).path(file_path));
+    report_exported(file_path);
+    Ok(())
+}
+
+// Say where a report went; on standard error when standard output is for programs
+fn report_exported(file_path: &str) {
+    match porcelain::enabled() {
+        true => eprintln!("{}", tr!("report-exported", path = file_path)),
+        false => outln!("{}", tr!("report-exported", path = file_path)),
+    }
+}
+
+fn command_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, subcommand)) = current.subcommand() {
+        names.push(name);
+        current = subcommand;
+    }
+    names.join(" ")
+}
+
+impl Commands {
+    // The codes a command looks up, for the usage statistics
+    fn looked_up(&self) -> Vec<String> {
+        let codes: Vec<&String> = match self {
+            Commands::Lookup { code, .. } | Commands::Parts { code, .. } | Commands::Troubleshoot { code } => vec![code],
+            Commands::Compare { first, second } => vec![first, second],
+            Commands::Quote { codes, .. } => codes.iter().collect(),
+            _ => Vec::new(),
+        };
+        codes.into_iter().map(|code| code.to_uppercase()).collect()
+    }
+    
+    // The output formats a command takes with --format
+    fn formats(&self) -> &'static [OutputFormat] {
+        match self {
+            Commands::Lookup { .. } | Commands::List { .. } | Commands::ListBySystem { .. } | Commands::ListBySeverity { .. } | Commands::Query { .. } => &[OutputFormat::Text, OutputFormat::Json],
+            Commands::Search { .. } => &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Jsonl],
+            Commands::Db { action: DbCommand::Export { .. } } => &[OutputFormat::Text, OutputFormat::Csv, OutputFormat::Json],
+            _ => &[OutputFormat::Text],
+        }
+    }
+}
+
+// Execute a command, loading only the resources it uses
+fn run(command: &Commands, format: OutputFormat, session: &Session) -> Result<(), Box<dyn Error>> {
+    match command {
+        Commands::Lookup { code, all_sources: true, .. } if format == OutputFormat::Json => {
+            json::print(&json::codes(session.db_subset(Subset::Code(code))?.definitions_of(code).iter().map(Arc::as_ref)))?;
+        },
+        Commands::Lookup { code, export, .. } if format == OutputFormat::Json => {
+            let error = session.db_subset(Subset::Code(code))?.shared_error(code);
+            if let (Some(error), Some(file_path)) = (&error, export) {
+                export_report(error, session.labor()?, session.letterhead()?, &RepairOrder::default(), None, file_path)?;
+            }
+            json::print(&error.as_deref().map(json::Code::from))?;
+        },
+        Commands::Lookup { code, all_sources: true, .. } => {
+            display_all_sources(&*session.db_subset(Subset::Code(code))?, code);
+        },
+        Commands::Lookup { code, export, vin, .. } => {
+            match session.db_subset(Subset::Code(code))?.shared_error(code) {
+                Some(error) if porcelain::enabled() => println!("{}", porcelain::code(&error)),
+                None if porcelain::enabled() => println!("{}", porcelain::missing(code)),
                 Some(error) => {
-                    display_error(error);
+                    display_error_paged(&error)?;
+                    if let Some(line) = last_seen_line(&archived_scans(session.config()?), code, vin.as_deref()) {
+                        outln!("{}", line.styled(Style::Muted));
+                    }
                     
                     if let Some(file_path) = export {
-                        if let Err(e) = export_to_file(error, file_path) {
-                            eprintln!("{}: Failed to export report: {}", "Error".bright_red(), e);
+                        if let Err(e) = export_report(&error, session.labor()?, session.letterhead()?, &RepairOrder::default(), None, file_path) {
+                            eprintln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("export-failed", error = e));
                         }
                     }
                 },
-                None => println!("Error code '{}' not found in database", code),
+                None => outln!("{}", tr!("code-not-found", code = code)),
+            }
+        },
+        Commands::Report { kind: ReportType::WarrantyClaim, output, oem, port, workspace, order, anonymize, .. } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let templates = config.load_warranty_templates()?;
+            let template = templates.get(oem.as_deref().or(config.warranty_oem.as_deref()).unwrap_or(warranty::DEFAULT_OEM))?;
+            let workspace = workspace.as_deref().map(|name| Workspaces::open(&config.data_dir())?.load(name)).transpose()?;
+            let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+            let mut adapter = connect_adapter(&port, &config.adapter)?;
+            let vin = adapter.read_vin()?.or_else(|| workspace.as_ref()?.vehicle.as_ref()?.vin.clone());
+            let scan = scan::read(&mut adapter)?;
+            let freeze_frame = adapter.read_freeze_frame()?;
+            let narrative = workspace.map(|workspace| workspace.notes.into_iter().map(|note| note.text).collect::<Vec<_>>().join("\n")).unwrap_or_default();
+            let claim = warranty::Claim::new(vin, order.resolve(&SystemClock)?, &scan, freeze_frame, narrative);
+            let mut text = claim.render(template, session.db()?)?;
+            if let Some(anonymizer) = anonymizer(config, *anonymize)? {
+                text = anonymizer.text(&text, &[claim.vin.as_deref().unwrap_or_default()]);
+            }
+            fs::write(output, text)?;
+            let codes: Vec<String> = claim.codes.iter().map(|code| code.code.clone()).collect();
+            events::record(Event::new(Kind::Report, "warranty-claim").vin(claim.vin.as_deref()).codes(&codes).path(output));
+            outln!("{}", tr!("warranty-claim-written", codes = claim.codes.len(), path = output.as_str()));
+        },
+        Commands::Report { code, output, order, anonymize, force, .. } => {
+            let code = code.as_deref().ok_or_else(|| tr!("report-needs-code"))?;
+            let db = session.db()?;
+            let error = db.shared_error(&code.to_uppercase()).ok_or_else(|| tr!("code-not-found", code = code))?;
+            let order = order.resolve(&SystemClock)?;
+            let config = session.config()?;
+            let anonymizer = anonymizer(config, *anonymize)?;
+            let order = match &anonymizer {
+                Some(anonymizer) => anonymizer.repair_order(order),
+                None => order,
+            };
+            let mut state = ReportState::open(&config.data_dir())?;
+            let tag = regenerate::tag(&[
+                &definition(&error),
+                &format!("{:?}", session.labor()?.operations_for(&error.code)),
+                &format!("{:?}", session.letterhead()?),
+                &format!("{:?}", layout::report()),
+                &format!("{:?} {:?} {:?}", order.customer, order.number, order.odometer),
+                &anonymized_by(config, *anonymize)?,
+            ]);
+            if !force && state.is_current(Path::new(output), &tag) {
+                outln!("{}", tr!("report-unchanged", path = output.as_str()));
+            } else {
+                export_report(&error, session.labor()?, session.letterhead()?, &order, anonymizer.as_ref(), output)?;
+                state.record(Path::new(output), &tag)?;
             }
         },
         Commands::ListBySystem { system } => {
+            let db = session.db_subset(Subset::System(system))?;
             let errors = db.list_errors_by_system(system);
-            if errors.is_empty() {
-                println!("No errors found for system: {}", system);
+            if format == OutputFormat::Json {
+                json::print(&json::codes(errors))?;
+            } else if porcelain::enabled() {
+                print_porcelain(&errors);
+            } else if errors.is_empty() {
+                outln!("{}", tr!("none-system", system = system));
             } else {
-                println!("Found {} errors for system: {}", errors.len(), system);
+                outln!("{}", tr!("found-system", count = errors.len(), system = system));
                 for error in errors {
                     display_error(error);
                 }
             }
         },
         Commands::ListBySeverity { severity } => {
+            let db = session.db_subset(Subset::Severity(severity))?;
             let errors = db.list_errors_by_severity(severity);
-            if errors.is_empty() {
-                println!("No errors found with severity: {}", severity);
+            if format == OutputFormat::Json {
+                json::print(&json::codes(errors))?;
+            } else if porcelain::enabled() {
+                print_porcelain(&errors);
+            } else if errors.is_empty() {
+                outln!("{}", tr!("none-severity", severity = severity));
             } else {
-                println!("Found {} errors with severity: {}", errors.len(), severity);
+                outln!("{}", tr!("found-severity", count = errors.len(), severity = severity));
                 for error in errors {
                     display_error(error);
                 }
             }
         },
-        Commands::Search { keyword } => {
-            let errors = db.search_by_keyword(keyword);
-            if errors.is_empty() {
-                println!("No errors found containing keyword: {}", keyword);
+        Commands::Query { sql } => {
+            let query = query::Query::parse(sql)?;
+            let db = session.db_subset(Subset::Where(&query.narrowing()))?;
+            let errors = query.run(&db);
+            match format {
+                OutputFormat::Json => json::print(&query::json_rows(&query, &errors))?,
+                _ => query::display(&query, &errors),
+            }
+        },
+        Commands::List { system, severity, warranty_covered, max_difficulty } => {
+            let filter = ListFilter {
+                system: system.clone(),
+                severity: severity.clone(),
+                warranty_covered: *warranty_covered,
+                max_difficulty: *max_difficulty,
+            };
+            let db = session.db_subset(Subset::Where(&filter.narrowing()))?;
+            let errors = db.list_errors(&filter);
+            if format == OutputFormat::Json {
+                json::print(&json::codes(errors))?;
+            } else if porcelain::enabled() {
+                print_porcelain(&errors);
+            } else if errors.is_empty() {
+                outln!("{}", tr!("none-filter"));
             } else {
-                println!("Found {} errors containing keyword: {}", errors.len(), keyword);
+                outln!("{}", tr!("found-filter", count = errors.len()));
                 for error in errors {
                     display_error(error);
                 }
             }
         },
-        Commands::Interactive => {
-            run_interactive_mode(&db)?;
+        #[cfg(feature = "adapter")]
+        Commands::Monitor { can: Some(interface), dbc: Some(dbc), signals, samples, duration, summary, .. } => {
+            let _watch = interrupt::watch();
+            let window = monitor::Window { samples: *samples, duration: *duration };
+            monitor_signals(session.config()?, interface, dbc, signals, window, *summary)?;
+        },
+        Commands::Monitor { port, pids, samples, duration, summary, tui, .. } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+            let mut adapter = connect_adapter(&port, &config.adapter)?;
+            let client = config.monitor.webhook.is_some().then(|| session.client()).transpose()?;
+            let window = monitor::Window { samples: *samples, duration: *duration };
+            let gauges = tui.then(|| session.db()).transpose()?;
+            monitor_vehicle(&mut adapter, config, client, pids, window, *summary, gauges)?;
+        },
+        #[cfg(feature = "adapter")]
+        Commands::Adapterd { action } => {
+            let config = session.config()?;
+            let socket = adapterd::socket_path(&config.data_dir());
+            match action {
+                AdapterdCommand::Start { port, foreground } => {
+                    let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+                    if *foreground {
+                        let _watch = interrupt::watch();
+                        adapterd::run(&socket, &port, &config.adapter)?;
+                        return Ok(());
+                    }
+                    let spinner = progress::spinner(tr!("adapter-connecting", port = port.as_str()));
+                    let result = adapterd::spawn(&config.data_dir(), &config.adapter);
+                    spinner.finish_and_clear();
+                    let status = result?;
+                    outln!("{}", tr!("adapterd-started", port = port.as_str(), pid = status.pid).styled(Style::Success));
+                    display_daemon_status(&status);
+                },
+                AdapterdCommand::Stop => match adapterd::stop(&socket)? {
+                    true => outln!("{}", tr!("adapterd-stopped")),
+                    false => outln!("{}", tr!("adapterd-not-running")),
+                },
+                AdapterdCommand::Status => match adapterd::status(&socket)? {
+                    Some(status) => {
+                        outln!("{}", tr!("adapterd-running", pid = status.pid, since = time::format_datetime(status.since)).styled(Style::Success));
+                        display_daemon_status(&status);
+                    },
+                    None => outln!("{}", tr!("adapterd-not-running")),
+                },
+            }
+        },
+        #[cfg(feature = "adapter")]
+        Commands::Emulate { scenario, listen, pty } => {
+            let scenario = scenario::Scenario::find(scenario)?;
+            if let Some(description) = &scenario.description {
+                outln!("{}", description.styled(Style::Hint));
+            }
+            match pty {
+                true => emulator::pty(scenario)?,
+                false => emulator::listen(listen, scenario)?,
+            }
+        },
+        Commands::Analyze { action: AnalyzeCommand::FuelTrims { port, log, samples } } => {
+            let _watch = interrupt::watch();
+            let samples = match log {
+                Some(log) => fuel_trim::read_log(log)?,
+                None => {
+                    let config = session.config()?;
+                    let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+                    let mut adapter = connect_adapter(&port, &config.adapter)?;
+                    fuel_trim::sample_live(&mut adapter, &mut io::stdin().lock(), *samples)?
+                },
+            };
+            fuel_trim::display(&fuel_trim::analyze(&samples), session.db()?);
+        },
+        Commands::Inspection { port, jurisdiction, output, anonymize } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let templates = config.load_inspection_templates()?;
+            let jurisdiction = jurisdiction.as_deref().or(config.jurisdiction.as_deref()).unwrap_or(inspection::DEFAULT_JURISDICTION);
+            let template = templates.get(jurisdiction)?;
+            let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+            let mut adapter = connect_adapter(&port, &config.adapter)?;
+            let result = inspection::read(&mut adapter, SystemClock.now())?;
+            let html = output.as_deref().is_some_and(|path| path.ends_with(".html"));
+            let mut report = inspection::render(&result, template, session.db()?, session.letterhead()?, html);
+            if let Some(anonymizer) = anonymizer(config, *anonymize)? {
+                report = anonymizer.text(&report, &[result.vin.as_deref().unwrap_or_default()]);
+            }
+            match output {
+                Some(path) => {
+                    fs::write(path, report)?;
+                    events::record(Event::new(Kind::Report, "inspection").vin(result.vin.as_deref()).path(path));
+                    outln!("{}", tr!("report-exported", path = path.as_str()));
+                },
+                None => outln!("{}", report.trim_end()),
+            }
+        },
+        Commands::Scan { ports, module } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let ports = match ports.is_empty() {
+                true => vec![config.adapter.port.clone().ok_or_else(|| tr!("adapter-no-port"))?],
+                false => ports.clone(),
+            };
+            let module = module.as_deref().map(|name| config.adapter.module(name)).transpose()?;
+            let targets = scan::read_all(&ports, &config.adapter, module.as_ref());
+            scan::display_all(&targets, session.db()?, &config.health);
+            let saved = scan::save_all(&targets, &SystemClock, &config.data_dir())?;
+            outln!("{}", tr!("scan-saved", count = saved).styled(Style::Muted));
+        },
+        Commands::Clear { port, module, safety, .. } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let module = module.as_deref().map(|name| config.adapter.module(name)).transpose()?;
+            let action = match &module {
+                Some(module) => tr!("clear-action-module", module = module.describe()),
+                None => tr!("clear-action-all"),
+            };
+            if !safety.confirm(&action)? {
+                return Ok(());
+            }
+            let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+            let mut adapter = connect_adapter(&port, &config.adapter)?;
+            let vin = adapter.read_vin().unwrap_or_default();
+            let cleared = scan::clear(&mut adapter, module.as_ref())?;
+            let detail = module.as_ref().map(|module| module.name.clone()).unwrap_or_else(|| "all".to_string());
+            events::record(Event::new(Kind::Clear, "clear").vin(vin.as_deref()).codes(&cleared.codes).detail(detail));
+            outln!("{}", tr!("clear-done", ecus = cleared.ecus.join(", "), codes = cleared.codes.len()).styled(Style::Success));
+            if !cleared.codes.is_empty() {
+                outln!("  {}", cleared.codes.join(" ").styled(Style::Code));
+            }
+        },
+        Commands::O2Tests { port } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let port = port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?;
+            let mut adapter = connect_adapter(&port, &config.adapter)?;
+            o2_tests::display(&o2_tests::read(&mut adapter)?, session.db()?);
+        },
+        Commands::DriveCycle { make, monitors, port, watch } => {
+            let _watch = interrupt::watch();
+            let config = session.config()?;
+            let port = match watch {
+                true => Some(port.clone().or_else(|| config.adapter.port.clone()).ok_or_else(|| tr!("adapter-no-port"))?),
+                false => port.clone(),
+            };
+            let mut adapter = port.map(|port| connect_adapter(&port, &config.adapter)).transpose()?;
+            show_drive_cycle(config, adapter.as_mut(), make.as_deref(), monitors, *watch)?;
+        },
+        Commands::Compare { first, second } => {
+            display_comparison(session.db()?, first, second);
+        },
+        Commands::Extract { file } => {
+            let spinner = progress::spinner(tr!("extract-reading", path = file.as_str()));
+            let text = extract::read_text(file);
+            spinner.finish_and_clear();
+            let found = extract::extract(&text?);
+            if porcelain::enabled() {
+                let db = session.db()?;
+                for code in &found.codes {
+                    match db.lookup_error(code) {
+                        Some(error) => println!("{}", porcelain::code(error)),
+                        None => println!("{}", porcelain::missing(code)),
+                    }
+                }
+                for fault in &found.spn_fmi {
+                    println!("{}", porcelain::line("spn", &[&fault.spn.to_string(), &fault.fmi.to_string()]));
+                }
+                return Ok(());
+            }
+            if found.codes.is_empty() && found.spn_fmi.is_empty() {
+                outln!("{}", tr!("extract-none", path = file.as_str()));
+                return Ok(());
+            }
+            outln!("{}", tr!("extract-found", codes = found.codes.len(), faults = found.spn_fmi.len(), path = file.as_str()));
+            if !found.codes.is_empty() {
+                let codes: Vec<&str> = found.codes.iter().map(String::as_str).collect();
+                let config = session.config()?;
+                lookup_codes(session.db()?, &codes, &archived_scans(config), None)?;
+            }
+            if !found.spn_fmi.is_empty() {
+                outln!("\n{}", tr!("extract-spn-header").styled(Style::Section));
+                for fault in &found.spn_fmi {
+                    outln!("  SPN {} FMI {}", fault.spn.styled(Style::Code), fault.fmi);
+                }
+            }
+        },
+        Commands::Search { keyword, export, sort, offset, limit, within_last } if format == OutputFormat::Jsonl => {
+            if export.is_some() {
+                return Err(tr!("search-jsonl-export").into());
+            }
+            if sort.is_some_and(|sort| sort != SortOrder::Code) {
+                return Err(tr!("search-jsonl-sort").into());
+            }
+            let db = session.db_subset(Subset::Matching(keyword))?;
+            let data_dir = session.config()?.data_dir();
+            let last = match within_last {
+                true => Some(LastSearch::load(&data_dir)?.ok_or_else(|| tr!("search-no-last"))?),
+                false => None,
+            };
+            let mut keywords = last.as_ref().map(|last| last.keywords.clone()).unwrap_or_default();
+            keywords.push(keyword.clone());
+            let codes = search::write_jsonl(db.search_stream(&keywords, last.as_ref()), *offset, *limit, io::stdout().lock())?;
+            if !codes.is_empty() {
+                if let Err(e) = (LastSearch { keywords, codes }).save(&data_dir) {
+                    eprintln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("search-not-kept", error = e));
+                }
+            }
+        },
+        Commands::Search { keyword, export, sort, offset, limit, within_last } => {
+            let options = SearchOptions { offset: *offset, limit: *limit, sort: sort.unwrap_or_default() };
+            let db = session.db_subset(Subset::Matching(keyword))?;
+            let data_dir = session.config()?.data_dir();
+            let results = match within_last {
+                true => {
+                    let last = LastSearch::load(&data_dir)?.ok_or_else(|| tr!("search-no-last"))?;
+                    db.search_within(&last).refine(keyword)
+                },
+                false => db.search_all(keyword),
+            };
+            // A search without hits keeps the previous result set to refine
+            if !results.is_empty() {
+                if let Err(e) = results.last_search().save(&data_dir) {
+                    eprintln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("search-not-kept", error = e));
+                }
+            }
+            let keywords = results.keywords.join(" > ");
+            let page = results.page(&options);
+            if format == OutputFormat::Json {
+                json::print(&page.hits.iter().map(JsonHit::new).collect::<Vec<_>>())?;
+                if let Some(file_path) = export {
+                    export_search(&page.hits, session.letterhead()?, file_path)?;
+                }
+            } else if porcelain::enabled() {
+                for hit in &page.hits {
+                    println!("{}", porcelain::code(&hit.error));
+                }
+            } else if page.total == 0 {
+                outln!("{}", tr!("none-keyword", keyword = keywords));
+            } else {
+                outln!("{}", tr!("found-keyword", count = page.total, keyword = keywords));
+                if page.hits.len() < page.total {
+                    outln!("{}", tr!("search-page", first = page.offset + 1, last = page.offset + page.hits.len(), total = page.total));
+                }
+                for hit in &page.hits {
+                    display_error_highlighted(&hit.error, &hit.highlights);
+                }
+                if page.has_more() {
+                    let next = match page.limit {
+                        Some(limit) => format!("--offset {} --limit {}", page.offset + page.hits.len(), limit),
+                        None => format!("--offset {}", page.offset + page.hits.len()),
+                    };
+                    outln!("{}", tr!("search-more", arguments = next).styled(Style::Hint));
+                }
+                
+                if let Some(file_path) = export {
+                    if let Err(e) = export_search(&page.hits, session.letterhead()?, file_path) {
+                        eprintln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("export-failed", error = e));
+                    }
+                }
+            }
+        },
+        Commands::Reverse { phrase, limit } => {
+            let page = session.db()?.reverse_lookup(phrase).page(&SearchOptions { limit: Some(*limit), ..SearchOptions::default() });
+            match porcelain::enabled() {
+                true => page.hits.iter().for_each(|hit| println!("{}", porcelain::code(&hit.error))),
+                false => display_reverse(phrase, &page),
+            }
+        },
+        Commands::Pick => {
+            match picker::pick(session.db()?)? {
+                Some(error) if porcelain::enabled() => println!("{}", porcelain::code(error)),
+                Some(error) => display_error_paged(error)?,
+                None => {},
+            }
+        },
+        Commands::Parts { code, fetch } => {
+            match session.db()?.lookup_error(&code.to_uppercase()) {
+                Some(error) if porcelain::enabled() => {
+                    for part in error.parts() {
+                        println!("{}", porcelain::line("part", &[&error.code, part.name, part.number]));
+                    }
+                },
+                None if porcelain::enabled() => println!("{}", porcelain::missing(&code.to_uppercase())),
+                Some(error) => display_parts(error, session.config()?, fetch.then(|| session.client()).transpose()?),
+                None => outln!("{}", tr!("code-not-found", code = code)),
+            }
+        },
+        Commands::Quote { codes, rate } => {
+            display_quote(session.db()?, session.labor()?, codes, rate.or(session.config()?.labor_rate));
+        },
+        Commands::Troubleshoot { code } => {
+            troubleshoot_code(session.config()?, code)?;
+        },
+        Commands::Interactive { script } => {
+            let input = match script {
+                Some(path) => ReplInput::Script(Box::new(io::BufReader::new(fs::File::open(path).map_err(|e| format!("Could not open {}: {}", path, e))?))),
+                None if !io::stdin().is_terminal() => ReplInput::Stdin,
+                None => ReplInput::editor(session.db()?)?,
+            };
+            run_interactive_mode(session.db()?, session.config()?, input)?;
+        },
+        Commands::Fleet { action } => match action {
+            FleetCommand::Import { file, group } => {
+                let db = session.db()?;
+                let archive = archive::Archive::open(&session.config()?.data_dir())?;
+                let records = fleet::import(file, &archive, &SystemClock)?;
+                outln!("{}", tr!("fleet-imported", vehicles = records.len(), path = file));
+                if let Some(name) = group {
+                    let mut groups = Groups::open(&session.config()?.data_dir())?;
+                    groups.entry(name)?.vins.extend(records.iter().map(|record| record.vin.clone()));
+                    groups.save()?;
+                    outln!("{}", tr!("group-added", vehicles = records.len(), group = name.as_str()));
+                }
+                fleet::display_summary(&fleet::summarize(&records, db), db);
+            },
+            FleetCommand::History { vin } => {
+                let config = session.config()?;
+                let records = archive::Archive::open(&config.data_dir())?.load_all()?;
+                fleet::display_history(&records, vin, session.db()?, &config.health);
+            },
+            FleetCommand::ExportApp { app, vin, group, output, anonymize } => {
+                let records: Vec<archive::ScanRecord> = archive::Archive::open(&session.config()?.data_dir())?
+                    .load_all()?
+                    .into_iter()
+                    .filter(|record| vin.as_ref().is_none_or(|vin| record.vin.eq_ignore_ascii_case(vin)))
+                    .collect();
+                let mut records = groups::filter(records, fleet_group(session, group.as_deref())?.as_ref(), SystemClock.now());
+                if let Some(anonymizer) = anonymizer(session.config()?, *anonymize)? {
+                    records = anonymizer.records(records);
+                }
+                let output = output.as_deref().unwrap_or(app.default_file());
+                let rows = apps::write_csv(&records, session.db()?, *app, fs::File::create(output)?)?;
+                events::record(Event::new(Kind::Report, "fleet export-app").vin(vin.as_deref()).path(output).detail(format!("{} scans", records.len())));
+                outln!("{}", tr!("export-app-written", codes = rows, scans = records.len(), path = output));
+            },
+            FleetCommand::Report { period, output, group, anonymize, force } => {
+                let seconds = archive::parse_period(period)?;
+                let since = SystemClock.now().saturating_sub(seconds);
+                let db = session.db()?;
+                let archive = archive::Archive::open(&session.config()?.data_dir())?;
+                let records: Vec<archive::ScanRecord> = archive.load_all()?
+                    .into_iter()
+                    .filter(|record| record.timestamp >= since)
+                    .collect();
+                let mut records = groups::filter(records, fleet_group(session, group.as_deref())?.as_ref(), SystemClock.now());
+                let anonymizer = anonymizer(session.config()?, *anonymize)?;
+                if let Some(anonymizer) = &anonymizer {
+                    records = anonymizer.records(records);
+                }
+                
+                let appendix = Path::new(output).with_extension("csv");
+                let appendix_name = appendix.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                let mut state = ReportState::open(&session.config()?.data_dir())?;
+                let codes: BTreeSet<&str> = records.iter().flat_map(|record| record.codes.iter().map(String::as_str)).collect();
+                let definitions: Vec<String> = codes.iter().filter_map(|code| db.lookup_error(code)).map(definition).collect();
+                let tag = regenerate::tag(&[
+                    &serde_json::to_string(&records)?,
+                    &definitions.join("\n"),
+                    &format!("{:?}", session.letterhead()?),
+                    period,
+                    group.as_deref().unwrap_or_default(),
+                    &anonymized_by(session.config()?, *anonymize)?,
+                ]);
+                if !force && state.is_current(Path::new(output), &tag) && appendix.is_file() {
+                    outln!("{}", tr!("report-unchanged", path = output.as_str()));
+                } else {
+                    let mut dashboard = render::Stream::new(io::BufWriter::new(fs::File::create(output)?), anonymizer.as_ref());
+                    fleet::write_dashboard(&records, db, session.letterhead()?, period, group.as_deref(), &appendix_name, &mut dashboard)?;
+                    dashboard.finish()?;
+                    fleet::write_appendix_csv(&records, db, io::BufWriter::new(fs::File::create(&appendix)?))?;
+                    state.record(Path::new(output), &tag)?;
+                    let detail = match group {
+                        Some(group) => format!("{} scans over {}, group {}", records.len(), period, group),
+                        None => format!("{} scans over {}", records.len(), period),
+                    };
+                    events::record(Event::new(Kind::Report, "fleet report").path(output).detail(detail));
+                    outln!("{}", tr!("fleet-report-written", scans = records.len(), path = output, appendix = appendix.display()));
+                }
+            },
+            FleetCommand::Group { action } => {
+                let config = session.config()?;
+                let mut groups = Groups::open(&config.data_dir())?;
+                match action {
+                    GroupCommand::Add { name, vins, years, description } => {
+                        let cohort = years.as_deref().map(groups::parse_years).transpose()?;
+                        let invalid: Vec<&String> = vins.iter().filter(|vin| !vehicle::looks_like_vin(vin)).collect();
+                        if let Some(vin) = invalid.first() {
+                            return Err(tr!("group-invalid-vin", vin = vin.as_str()).into());
+                        }
+                        let group = groups.entry(name)?;
+                        group.vins.extend(vins.iter().map(|vin| vin.to_uppercase()));
+                        if let Some((first, last)) = cohort {
+                            group.first_year = Some(first);
+                            group.last_year = Some(last);
+                        }
+                        if description.is_some() {
+                            group.description = description.clone();
+                        }
+                        let years = group.years();
+                        groups.save()?;
+                        if !vins.is_empty() || cohort.is_none() {
+                            outln!("{}", tr!("group-added", vehicles = vins.len(), group = name.as_str()));
+                        }
+                        if let (Some(years), Some(_)) = (years, cohort) {
+                            outln!("{}", tr!("group-cohort", group = name.as_str(), years = years));
+                        }
+                    },
+                    GroupCommand::Remove { name, vins } => {
+                        let removed = groups.remove(name, vins)?;
+                        groups.save()?;
+                        match vins.is_empty() {
+                            true => outln!("{}", tr!("group-deleted", group = name.as_str())),
+                            false => outln!("{}", tr!("group-removed", vehicles = removed, group = name.as_str())),
+                        }
+                    },
+                    GroupCommand::List => {
+                        let records = archive::Archive::open(&config.data_dir())?.load_all()?;
+                        groups::display(&groups, &records, SystemClock.now());
+                    },
+                }
+            },
+        },
+        Commands::Garage { action } => {
+            let config = session.config()?;
+            let message = match action {
+                GarageCommand::Unlink { scan_id } => relink_scan(config, scan_id, None)?,
+                GarageCommand::Move { scan_id, to } => relink_scan(config, scan_id, Some(to))?,
+            };
+            outln!("{}", message);
+        },
+        Commands::Usage { days } => {
+            let config = session.config()?;
+            let events = usage::load(&config.data_dir())?;
+            usage::display(&usage::summarize(&events, session.db()?, *days, SystemClock.now()), &config.data_dir());
+        },
+        Commands::Events { action } => {
+            let config = session.config()?;
+            let log = events::Log::open(&config.data_dir(), &config.events);
+            match action {
+                EventsCommand::Tail { lines, follow, jsonl } => events::tail(&log, *lines, *follow, *jsonl)?,
+                EventsCommand::Query { kind, action, vin, code, since, jsonl } => {
+                    let since = since.as_deref().map(archive::parse_period).transpose()?;
+                    let filter = events::Filter {
+                        kind: *kind,
+                        action: action.clone(),
+                        vin: vin.clone(),
+                        code: code.clone(),
+                        since: since.map(|seconds| SystemClock.now().saturating_sub(seconds)),
+                    };
+                    events::display(&events::query(&log, &filter)?, *jsonl);
+                },
+            }
+        },
+        Commands::Doctor => unreachable!("handled before the database is loaded"),
+        Commands::Db { action } => match action {
+            DbCommand::ExplainMerge { code } => {
+                explain_merge(session.shared_db()?, &session.config()?.merge, &code.to_uppercase());
+            },
+            DbCommand::Add { code, description, system, severity, causes, actions, difficulty, pack } => {
+                let mut builder = ErrorCodeBuilder::new(code)
+                    .description(description)
+                    .system(system)
+                    .causes(causes)
+                    .actions(actions);
+                if let Some(severity) = severity {
+                    builder = builder.severity(severity);
+                }
+                if let Some(difficulty) = difficulty {
+                    builder = builder.difficulty(DIFFICULTIES[*difficulty as usize]);
+                }
+                maintenance::add_code(session.shared_db()?, session.config()?, builder, pack.as_deref())?;
+            },
+            DbCommand::Validate => {
+                let failed = maintenance::validate(session.shared_db()?);
+                if failed > 0 {
+                    std::process::exit(1);
+                }
+            },
+            DbCommand::Sample { n, seed, conditions } => {
+                let seed = seed.unwrap_or_else(|| SystemClock.now() ^ u64::from(std::process::id()));
+                let (sample, total) = maintenance::sample(session.shared_db()?, *n, seed, conditions);
+                if sample.is_empty() {
+                    outln!("{}", tr!("db-sample-none"));
+                    return Ok(());
+                }
+                outln!("{}", tr!("db-sample-header", count = sample.len(), total = total, seed = seed).styled(Style::Muted));
+                for error in sample {
+                    display_error(error);
+                }
+            },
+            DbCommand::Remove { code, pack, safety } => {
+                maintenance::remove_code(session.config()?, code, pack.as_deref(), safety)?;
+            },
+            DbCommand::Import { file, sqlite: Some(output), refresh, safety, .. } => {
+                maintenance::convert_to_sqlite(session.config()?, file.as_deref(), output, *refresh, safety)?;
+            },
+            DbCommand::Import { file: Some(file), pack, safety, .. } => {
+                maintenance::import(session.config()?, file, pack.as_deref(), safety)?;
+            },
+            DbCommand::Import { file: None, .. } => unreachable!("the file is required without --refresh"),
+            DbCommand::Migrate { pack, safety } => {
+                maintenance::migrate(session.config()?, pack.as_deref(), safety)?;
+            },
+            DbCommand::Update { check: true, .. } => {
+                if !maintenance::check_freshness(session.config()?, SystemClock.now()).is_empty() {
+                    std::process::exit(1);
+                }
+            },
+            DbCommand::Update { check: false, pack } => {
+                maintenance::update(session.config()?, pack.as_deref(), SystemClock.now())?;
+            },
+            DbCommand::Export { output, canonical, pack } => {
+                let format = match (format, output) {
+                    (OutputFormat::Json, _) => ExportFormat::Json,
+                    (OutputFormat::Text, Some(output)) if output.to_lowercase().ends_with(".json") => ExportFormat::Json,
+                    _ => ExportFormat::Csv,
+                };
+                maintenance::export(session.config()?, pack.as_deref(), format, *canonical, output.as_deref())?;
+            },
+            DbCommand::Contribute { output, against, pack } => {
+                let against = against.as_deref().unwrap_or(DATABASE_FILE);
+                contribute::contribute(session.shared_db()?, session.config()?, against, pack, output)?;
+            },
+        },
+        Commands::Config { action: ConfigCommand::Show { resolved } } => {
+            show_config(*resolved)?;
+        },
+        Commands::Docs { action } => match action {
+            DocsCommand::Man { out_dir } => {
+                for file in help::write_man_pages(Cli::command(), Path::new(out_dir))? {
+                    outln!("{}", tr!("file-written", path = file));
+                }
+            },
         },
     }
     
     Ok(())
-}
\ No newline at end of file
+}
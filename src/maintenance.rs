@@ -0,0 +1,774 @@
+// Editing and checking the error code database files (`db add`, `db remove`, `db validate`,
+// `db sample`, `db export`, `db import`, `db migrate`, `db update`)
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use csv::{QuoteStyle, StringRecord, Terminator, WriterBuilder};
+use crate::builder::{self, ErrorCodeBuilder, FieldError};
+use crate::config::Config;
+use crate::events::{self, Event, Kind};
+use crate::freshness::{self, Dated, Fingerprint};
+use crate::i18n::tr;
+use crate::merge;
+use crate::presentation::{Style, Styled};
+use crate::safety::SafetyArgs;
+use crate::schema;
+use crate::sqlite;
+use crate::severity::SeverityModel;
+use crate::transcript::outln;
+use crate::{looks_like_code, DiagnosticsDatabase, ErrorCode, Source, DATABASE_FILE, DIFFICULTIES, SEVERITIES};
+
+// A suggestion that disagrees with a code's severity is reported when at least
+// this share of the similar codes agree with each other
+const INCONSISTENT_CONFIDENCE: f64 = 0.6;
+
+// A `db sample --where` condition: the codes whose system or severity is the value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    System(String),
+    Severity(String),
+}
+
+impl Condition {
+    fn field(&self) -> &'static str {
+        match self {
+            Condition::System(_) => "system",
+            Condition::Severity(_) => "severity",
+        }
+    }
+
+    fn matches(&self, error: &ErrorCode) -> bool {
+        match self {
+            Condition::System(system) => error.system.eq_ignore_ascii_case(system),
+            Condition::Severity(severity) => error.severity.eq_ignore_ascii_case(severity),
+        }
+    }
+}
+
+// Parse "field=value" for --where
+pub fn parse_condition(text: &str) -> Result<Condition, String> {
+    let (field, value) = text.split_once('=').ok_or_else(|| tr!("db-sample-bad-where", condition = text))?;
+    let value = value.trim().to_string();
+    match field.trim().to_lowercase().as_str() {
+        "system" => Ok(Condition::System(value)),
+        "severity" => Ok(Condition::Severity(value)),
+        _ => Err(tr!("db-sample-bad-where", condition = text)),
+    }
+}
+
+// SplitMix64: the same seed gives the same sample on every platform and release
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut x = self.0;
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^ (x >> 31)
+    }
+
+    // A number below `bound`; the bias of the modulo is negligible for databases this size
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+// `count` codes picked at random among those matching the conditions, in
+// the order picked, and how many matched. A condition on the same field as
+// another is an alternative to it; conditions on different fields must all hold.
+pub fn sample<'a>(db: &'a DiagnosticsDatabase, count: usize, seed: u64, conditions: &[Condition]) -> (Vec<&'a ErrorCode>, usize) {
+    let mut matching: Vec<&ErrorCode> = db.records()
+        .filter(|error| ["system", "severity"].iter().all(|field| {
+            let mut on_field = conditions.iter().filter(|condition| condition.field() == *field).peekable();
+            on_field.peek().is_none() || on_field.any(|condition| condition.matches(error))
+        }))
+        .collect();
+    // The database is a hash map; sorting first makes the seed the only input
+    matching.sort_by(|a, b| a.code.cmp(&b.code));
+    let total = matching.len();
+    let count = count.min(total);
+    let mut random = Random(seed);
+    for index in 0..count {
+        let pick = index + random.below(total - index);
+        matching.swap(index, pick);
+    }
+    matching.truncate(count);
+    (matching, total)
+}
+
+// The CSV file to edit: the named pack, or the bundled database
+fn database_path(config: &Config, pack: Option<&str>) -> Result<String, Box<dyn Error>> {
+    match pack {
+        Some(name) => config.packs.iter()
+            .find(|pack| pack.display_name().eq_ignore_ascii_case(name))
+            .map(|pack| pack.path.clone())
+            .ok_or_else(|| tr!("pack-not-found", pack = name).into()),
+        None => Ok(DATABASE_FILE.to_string()),
+    }
+}
+
+// A database file as read: its schema and updated lines, if it has them, the header row and the rows
+struct Table {
+    version: Option<u32>,
+    updated: Option<u64>,
+    headers: StringRecord,
+    records: Vec<StringRecord>,
+}
+
+fn read_file(path: &str) -> Result<Table, Box<dyn Error>> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let version = schema::declared(&content).map_err(|e| format!("{}: {}", path, e))?;
+    let updated = freshness::stamped(&content).map_err(|e| format!("{}: {}", path, e))?;
+    let mut reader = schema::reader(&content);
+    let headers = reader.headers()?.clone();
+    let records = reader.records().collect::<Result<Vec<StringRecord>, _>>()?;
+    Ok(Table { version, updated, headers, records })
+}
+
+// Rewrite a database file, starting with the schema line of `version` and the updated line
+// when there are ones
+fn write_file(path: &str, version: Option<u32>, updated: Option<u64>, headers: &StringRecord, records: &[StringRecord]) -> Result<(), Box<dyn Error>> {
+    let mut file = fs::File::create(path)?;
+    if let Some(version) = version {
+        writeln!(file, "{}", schema::line(version))?;
+    }
+    if let Some(updated) = updated {
+        writeln!(file, "{}", freshness::line(updated))?;
+    }
+    let mut writer = csv::Writer::from_writer(file);
+    writer.write_record(headers)?;
+    for record in records {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Add a code to the bundled database or a pack, suggesting a severity when none is given
+pub fn add_code(db: &DiagnosticsDatabase, config: &Config, mut builder: ErrorCodeBuilder, pack: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let path = database_path(config, pack)?;
+    let draft = builder.draft();
+    if !looks_like_code(&draft.code) {
+        return Err(FieldError::Code(draft.code).to_string().into());
+    }
+
+    let Table { headers, records, .. } = read_file(&path)?;
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let code_column = column("code").ok_or_else(|| format!("{} has no 'code' column", path))?;
+    if records.iter().any(|record| record.get(code_column).is_some_and(|code| code.trim().eq_ignore_ascii_case(&draft.code))) {
+        return Err(tr!("db-add-exists", code = draft.code.as_str(), path = path.as_str()).into());
+    }
+
+    if !builder.has_severity() {
+        let suggestion = SeverityModel::new(db).suggest(&draft)
+            .ok_or_else(|| tr!("db-add-no-suggestion", code = draft.code.as_str()))?;
+        let similar: Vec<String> = suggestion.similar.iter().map(|(code, severity, _)| format!("{} ({})", code, severity)).collect();
+        outln!("{}", tr!(
+            "db-add-suggested",
+            severity = crate::presentation::severity_text(&suggestion.severity).styled(Style::for_severity(&suggestion.severity).unwrap_or(Style::Plain)),
+            confidence = format!("{:.0}", suggestion.confidence * 100.0),
+            similar = similar.join(", ")
+        ));
+        builder = builder.severity(&suggestion.severity);
+    }
+    let error = builder.build().map_err(|e| e.to_string())?;
+
+    // Write the fields in the file's own column order
+    let record: Vec<String> = headers.iter().map(|header| match header {
+        "code" => error.code.clone(),
+        "description" => error.description.clone(),
+        "severity" => error.severity.clone(),
+        "system" => error.system.clone(),
+        "possible_causes" => error.possible_causes.join("|"),
+        "recommended_actions" => error.recommended_actions.join("|"),
+        "difficulty" => error.difficulty.clone(),
+        _ => String::new(),
+    }).collect();
+
+    let needs_newline = fs::read(&path)?.last().is_some_and(|byte| *byte != b'\n');
+    let mut file = OpenOptions::new().append(true).open(&path)?;
+    if needs_newline {
+        file.write_all(b"\n")?;
+    }
+    let mut writer = WriterBuilder::new().has_headers(false).from_writer(file);
+    writer.write_record(&record)?;
+    writer.flush()?;
+    events::record(Event::new(Kind::Database, "db add").codes(std::slice::from_ref(&error.code)).path(&path).detail(error.severity.as_str()));
+    outln!("{}", tr!("db-add-done", code = error.code.as_str(), severity = error.severity.as_str(), path = path.as_str()));
+    Ok(())
+}
+
+// Remove a code from the bundled database or a pack by rewriting its CSV file
+pub fn remove_code(config: &Config, code: &str, pack: Option<&str>, safety: &SafetyArgs) -> Result<(), Box<dyn Error>> {
+    let path = database_path(config, pack)?;
+    let Table { version, updated, headers, records } = read_file(&path)?;
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let code_column = column("code").ok_or_else(|| format!("{} has no 'code' column", path))?;
+    let (removed, kept): (Vec<StringRecord>, Vec<StringRecord>) = records
+        .into_iter()
+        .partition(|record| record.get(code_column).is_some_and(|value| value.trim().eq_ignore_ascii_case(code)));
+
+    let Some(record) = removed.first() else {
+        outln!("{}", tr!("db-remove-missing", code = code, path = path.as_str()));
+        return Ok(());
+    };
+    let description = column("description").and_then(|index| record.get(index)).unwrap_or_default();
+    let action = tr!("db-remove-action", code = code.to_uppercase(), description = description, path = path.as_str());
+    if !safety.confirm(&action)? {
+        return Ok(());
+    }
+
+    write_file(&path, version, updated, &headers, &kept)?;
+    events::record(Event::new(Kind::Database, "db remove").codes(&[code.to_uppercase()]).path(&path));
+    outln!("{}", tr!("db-remove-done", code = code.to_uppercase(), path = path.as_str()));
+    Ok(())
+}
+
+// Check every code and report problems; returns the number of errors found.
+// Severities that disagree with similar codes are reported as warnings.
+pub fn validate(db: &DiagnosticsDatabase) -> usize {
+    let model = SeverityModel::new(db);
+    let mut errors: Vec<&ErrorCode> = db.records().collect();
+    errors.sort_by(|a, b| a.code.cmp(&b.code));
+
+    let mut problems = 0;
+    let mut warnings = 0;
+    let report = |code: &str, message: String, is_error: bool| {
+        let label = if is_error { "[FAIL]".styled(Style::Error) } else { "[WARN]".styled(Style::Warning) };
+        outln!("{} {:<7} {}", label, code, message);
+    };
+
+    for error in &errors {
+        let field_errors = builder::check(error);
+        for problem in &field_errors {
+            report(&error.code, problem.to_string(), true);
+        }
+        problems += field_errors.len();
+        if field_errors.iter().any(|problem| matches!(problem, FieldError::Severity(_))) {
+            continue;
+        }
+
+        let Some(suggestion) = model.suggest(error) else { continue };
+        let agreeing = suggestion.similar.iter().filter(|(_, severity, _)| severity.eq_ignore_ascii_case(&suggestion.severity)).count();
+        if !suggestion.severity.eq_ignore_ascii_case(&error.severity) && suggestion.confidence >= INCONSISTENT_CONFIDENCE && agreeing >= 2 {
+            let similar: Vec<&str> = suggestion.similar.iter()
+                .filter(|(_, severity, _)| severity.eq_ignore_ascii_case(&suggestion.severity))
+                .map(|(code, _, _)| code.as_str())
+                .collect();
+            report(&error.code, tr!(
+                "db-validate-inconsistent",
+                severity = error.severity.as_str(),
+                suggested = suggestion.severity.as_str(),
+                similar = similar.join(", ")
+            ), false);
+            warnings += 1;
+        }
+    }
+
+    outln!("\n{}", tr!("db-validate-summary", codes = errors.len(), errors = problems, warnings = warnings));
+    problems
+}
+
+// Columns of free text, which canonical CSV always quotes as the bundled database does
+const PROSE_COLUMNS: &[&str] = &["description", "possible_causes", "recommended_actions", "set_conditions"];
+
+// How `db export` writes a file, from the global --format or the output file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+// A database file in canonical form, so that edits show up as the smallest possible diff:
+// rows sorted by code, the known columns in schema order followed by any others, every
+// value trimmed, lists without blank entries or spaces around '|', codes in upper case and
+// severities and warranty flags spelled one way
+pub fn canonicalize(headers: &StringRecord, records: &[StringRecord]) -> (StringRecord, Vec<StringRecord>) {
+    let known: Vec<&str> = std::iter::once("code").chain(merge::field_names()).collect();
+    let mut order: Vec<usize> = known.iter().filter_map(|name| headers.iter().position(|header| header.trim() == *name)).collect();
+    let others: Vec<usize> = (0..headers.len()).filter(|index| !order.contains(index)).collect();
+    order.extend(others);
+
+    let canonical_headers: StringRecord = order.iter().map(|index| headers[*index].trim()).collect();
+    let mut rows: Vec<StringRecord> = records.iter().map(|record| {
+        canonical_headers.iter().zip(&order).map(|(column, index)| {
+            canonical_value(column, record.get(*index).unwrap_or_default())
+        }).collect()
+    }).collect();
+    rows.sort_by(|a, b| a.get(0).cmp(&b.get(0)));
+    (canonical_headers, rows)
+}
+
+fn canonical_value(column: &str, value: &str) -> String {
+    let value = value.trim();
+    match column {
+        "code" => value.to_uppercase(),
+        "severity" => SEVERITIES.iter()
+            .find(|severity| severity.eq_ignore_ascii_case(value))
+            .map(|severity| severity.to_string())
+            .unwrap_or_else(|| value.to_string()),
+        "difficulty" => DIFFICULTIES.iter()
+            .find(|difficulty| difficulty.eq_ignore_ascii_case(value))
+            .map(|difficulty| difficulty.to_string())
+            .unwrap_or_else(|| value.to_string()),
+        "emissions_warranty" => value.to_ascii_lowercase(),
+        column if merge::is_list(column) => crate::pipe_list(value).collect::<Vec<_>>().join("|"),
+        _ => value.lines().map(str::trim_end).collect::<Vec<_>>().join("\n"),
+    }
+}
+
+// Canonical CSV: prose columns always quoted, others only when they must be, '\n' line ends
+pub fn canonical_csv(headers: &StringRecord, records: &[StringRecord]) -> String {
+    let field = |column: &str, value: &str| {
+        let must = value.contains([',', '"', '\n', '\r']) || value.starts_with(' ') || value.ends_with(' ');
+        if must || (!value.is_empty() && PROSE_COLUMNS.contains(&column)) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let header: Vec<String> = headers.iter().map(|column| field("", column)).collect();
+    let mut csv = format!("{}\n", header.join(","));
+    for record in records {
+        let line: Vec<String> = headers.iter().zip(record.iter()).map(|(column, value)| field(column, value)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// Write the bundled database or a pack as CSV or JSON, to a file or standard output
+pub fn export(config: &Config, pack: Option<&str>, format: ExportFormat, canonical: bool, output: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let path = database_path(config, pack)?;
+    let Table { version, updated, mut headers, mut records } = read_file(&path)?;
+    if canonical {
+        (headers, records) = canonicalize(&headers, &records);
+    }
+
+    let mut text = Vec::new();
+    if let (Some(version), ExportFormat::Csv) = (version, format) {
+        text.extend(format!("{}\n", schema::line(version)).into_bytes());
+    }
+    if let (Some(updated), ExportFormat::Csv) = (updated, format) {
+        text.extend(format!("{}\n", freshness::line(updated)).into_bytes());
+    }
+    match format {
+        ExportFormat::Csv if canonical => text.extend(canonical_csv(&headers, &records).into_bytes()),
+        ExportFormat::Csv => {
+            let mut writer = WriterBuilder::new()
+                .quote_style(QuoteStyle::Necessary)
+                .terminator(Terminator::Any(b'\n'))
+                .from_writer(&mut text);
+            writer.write_record(&headers)?;
+            for record in &records {
+                writer.write_record(record)?;
+            }
+            writer.flush()?;
+        },
+        ExportFormat::Json => {
+            let rows: Vec<serde_json::Map<String, serde_json::Value>> = records.iter().map(|record| {
+                headers.iter().zip(record.iter()).map(|(column, value)| (column.to_string(), value.into())).collect()
+            }).collect();
+            serde_json::to_writer_pretty(&mut text, &rows)?;
+            text.push(b'\n');
+        },
+    }
+
+    match output {
+        Some(output) => {
+            fs::write(output, &text)?;
+            eprintln!("{}", tr!("db-export-done", codes = records.len(), path = output));
+        },
+        None => io::stdout().write_all(&text)?,
+    }
+    Ok(())
+}
+
+// Codes listed for each kind of change in an import preview
+const IMPORT_SAMPLE: usize = 5;
+
+// What importing a file would do to a database file, worked out before anything is written
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    // The database file's rows after the import
+    pub records: Vec<StringRecord>,
+    // New codes with their descriptions
+    pub added: Vec<(String, String)>,
+    // Existing codes with the columns that change
+    pub updated: Vec<(String, Vec<String>)>,
+    pub unchanged: usize,
+    // Codes the file defines more than once in different ways; none of their rows is used
+    pub conflicts: Vec<String>,
+    // Rows left out and columns ignored, as (code or column, message)
+    pub warnings: Vec<(String, String)>,
+}
+
+// Read a file to import: JSON as written by `db export --format json`, anything else as CSV
+fn read_import(path: &str) -> Result<(StringRecord, Vec<StringRecord>), Box<dyn Error>> {
+    if !path.to_lowercase().ends_with(".json") {
+        return read_file(path).map(|table| (table.headers, table.records));
+    }
+    let content = fs::read_to_string(path)?;
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid import file {}: {}", path, e))?;
+    let mut headers: Vec<String> = Vec::new();
+    for column in rows.iter().flat_map(|row| row.keys()) {
+        if !headers.contains(column) {
+            headers.push(column.clone());
+        }
+    }
+    let records = rows.iter().map(|row| {
+        headers.iter().map(|column| match row.get(column) {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(serde_json::Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        }).collect()
+    }).collect();
+    Ok((headers.iter().collect(), records))
+}
+
+// Work out the rows of a database file after importing `rows`: new codes are added, existing
+// ones replaced column by column (columns the file lacks keep their values), and rows that
+// would not load or fail validation are left out with a warning
+pub fn plan_import(headers: &StringRecord, records: &[StringRecord], import_headers: &StringRecord, rows: &[StringRecord]) -> Result<ImportPlan, Box<dyn Error>> {
+    let code_column = headers.iter().position(|header| header.trim() == "code").ok_or("The database has no 'code' column")?;
+    let import_code = import_headers.iter().position(|header| header.trim() == "code").ok_or("The import file has no 'code' column")?;
+    let mut plan = ImportPlan { records: records.to_vec(), ..ImportPlan::default() };
+    for column in import_headers.iter().filter(|column| !headers.iter().any(|header| header.trim() == column.trim())) {
+        plan.warnings.push((column.to_string(), tr!("db-import-unknown-column")));
+    }
+
+    // Rows of the file by code, in file order; a code defined twice the same way counts once
+    let mut incoming: Vec<(String, &StringRecord)> = Vec::new();
+    for row in rows {
+        let code = row.get(import_code).unwrap_or_default().trim().to_uppercase();
+        let same = |other: &StringRecord| import_headers.iter().enumerate().all(|(index, column)| {
+            canonical_value(column.trim(), row.get(index).unwrap_or_default()) == canonical_value(column.trim(), other.get(index).unwrap_or_default())
+        });
+        match incoming.iter().find(|(seen, _)| *seen == code) {
+            Some((_, other)) if same(other) => {},
+            Some(_) => {
+                if !plan.conflicts.contains(&code) {
+                    plan.conflicts.push(code);
+                }
+            },
+            None => incoming.push((code, row)),
+        }
+    }
+
+    for (code, row) in incoming.into_iter().filter(|(code, _)| !plan.conflicts.contains(code)) {
+        let existing = plan.records.iter().position(|record| record.get(code_column).is_some_and(|value| value.trim().eq_ignore_ascii_case(&code)));
+        let value = |column: &str| import_headers.iter().position(|header| header.trim() == column.trim()).map(|index| row.get(index).unwrap_or_default().trim());
+        let merged: StringRecord = headers.iter().enumerate().map(|(index, column)| match (value(column), existing) {
+            _ if index == code_column => code.clone(),
+            (Some(value), _) => value.to_string(),
+            (None, Some(existing)) => plan.records[existing].get(index).unwrap_or_default().to_string(),
+            (None, None) => String::new(),
+        }).collect();
+
+        let error: ErrorCode = match merged.deserialize(Some(headers)) {
+            Ok(error) => error,
+            Err(e) => {
+                plan.warnings.push((code, e.to_string()));
+                continue;
+            },
+        };
+        let problems = builder::check(&error);
+        if !problems.is_empty() {
+            let messages: Vec<String> = problems.iter().map(FieldError::to_string).collect();
+            plan.warnings.push((code, messages.join("; ")));
+            continue;
+        }
+
+        match existing {
+            None => {
+                plan.added.push((code, error.description));
+                plan.records.push(merged);
+            },
+            Some(existing) => {
+                let changed: Vec<String> = headers.iter().enumerate()
+                    .filter(|(index, column)| canonical_value(column.trim(), plan.records[existing].get(*index).unwrap_or_default()) != canonical_value(column.trim(), merged.get(*index).unwrap_or_default()))
+                    .map(|(_, column)| column.trim().to_string())
+                    .collect();
+                if changed.is_empty() {
+                    plan.unchanged += 1;
+                } else {
+                    plan.updated.push((code, changed));
+                    plan.records[existing] = merged;
+                }
+            },
+        }
+    }
+    Ok(plan)
+}
+
+// Import codes from CSV or JSON into the bundled database or a pack, after a preview of the
+// changes; with --dry-run only the preview is shown
+pub fn import(config: &Config, file: &str, pack: Option<&str>, safety: &SafetyArgs) -> Result<(), Box<dyn Error>> {
+    let path = database_path(config, pack)?;
+    let Table { version, updated, headers, records } = read_file(&path)?;
+    let (import_headers, rows) = read_import(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+    let plan = plan_import(&headers, &records, &import_headers, &rows)?;
+
+    outln!("{}", tr!(
+        "db-import-summary",
+        file = file,
+        path = path.as_str(),
+        added = plan.added.len().styled(Style::Success),
+        updated = plan.updated.len().styled(Style::Warning),
+        unchanged = plan.unchanged,
+        conflicts = plan.conflicts.len().styled(Style::Error)
+    ));
+    let more = |count: usize| {
+        if count > IMPORT_SAMPLE {
+            outln!("    {}", tr!("db-import-more", count = count - IMPORT_SAMPLE));
+        }
+    };
+    for (code, description) in plan.added.iter().take(IMPORT_SAMPLE) {
+        outln!("  {} {:<7} {}", "+".styled(Style::Success), code.styled(Style::Code), description);
+    }
+    more(plan.added.len());
+    for (code, columns) in plan.updated.iter().take(IMPORT_SAMPLE) {
+        outln!("  {} {:<7} {}", "~".styled(Style::Warning), code.styled(Style::Code), columns.join(", "));
+    }
+    more(plan.updated.len());
+    for code in &plan.conflicts {
+        outln!("  {} {:<7} {}", "!".styled(Style::Error), code.styled(Style::Code), tr!("db-import-conflict"));
+    }
+    for (subject, message) in &plan.warnings {
+        outln!("{} {:<7} {}", "[WARN]".styled(Style::Warning), subject, message);
+    }
+
+    if plan.added.is_empty() && plan.updated.is_empty() {
+        outln!("{}", tr!("db-import-nothing", path = path.as_str()));
+        return Ok(());
+    }
+    let action = tr!("db-import-action", added = plan.added.len(), updated = plan.updated.len(), path = path.as_str());
+    if !safety.confirm(&action)? {
+        return Ok(());
+    }
+
+    write_file(&path, version, updated, &headers, &plan.records)?;
+    let codes: Vec<String> = plan.added.iter().map(|(code, _)| code).chain(plan.updated.iter().map(|(code, _)| code)).cloned().collect();
+    let detail = format!("{} added, {} updated from {}", plan.added.len(), plan.updated.len(), file);
+    events::record(Event::new(Kind::Database, "db import").codes(&codes).path(&path).detail(detail));
+    outln!("{}", tr!("db-import-done", added = plan.added.len(), updated = plan.updated.len(), path = path.as_str()));
+    Ok(())
+}
+
+// Convert a CSV or JSON database file, with the configured packs layered over it and merged as
+// when loading them, to a SQLite file at `output` for the `database` setting. The file is dated
+// by the oldest of them, so it goes stale when they would, and the content hash of each is
+// recorded, so commands can tell when one changed. With `refresh`, `output` is converted again
+// from the file it was converted from, unless another is given, and only when one changed.
+pub fn convert_to_sqlite(config: &Config, file: Option<&str>, output: &str, refresh: bool, safety: &SafetyArgs) -> Result<(), Box<dyn Error>> {
+    let recorded = if refresh { sqlite::sources(output)? } else { Vec::new() };
+    let file = match file.or(recorded.first().map(|source| source.path.as_str())) {
+        Some(file) => file,
+        None => return Err(tr!("db-sqlite-no-sources", path = output).into()),
+    };
+    // Hashed before reading, so an edit made meanwhile shows as a change; recorded by their full
+    // path, so the warning and --refresh find them from any directory
+    let files: Vec<&str> = std::iter::once(file).chain(config.packs.iter().map(|pack| pack.path.as_str())).collect();
+    let mut sources = files.iter().map(|path| Fingerprint::read(path)).collect::<Result<Vec<_>, _>>()?;
+    for source in &mut sources {
+        if let Ok(full) = fs::canonicalize(&source.path) {
+            source.path = full.to_string_lossy().into_owned();
+        }
+    }
+    if refresh && sources == recorded {
+        outln!("{}", tr!("db-sqlite-current", path = output));
+        return Ok(());
+    }
+
+    let mut db = DiagnosticsDatabase::new();
+    if file.to_lowercase().ends_with(".json") {
+        let (headers, rows) = read_import(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+        let mut content = format!("{}\n", schema::line(schema::CURRENT)).into_bytes();
+        {
+            let mut writer = csv::Writer::from_writer(&mut content);
+            writer.write_record(&headers)?;
+            for row in &rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+        }
+        db.read_records(content.as_slice(), Source::bundled(file))?;
+    } else {
+        db.read_csv(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+    }
+    for (position, pack) in config.packs.iter().enumerate() {
+        db.load_pack(pack, pack.source(position))?;
+    }
+    if !config.packs.is_empty() {
+        db.apply_merge_policy(&config.merge);
+    }
+    let updated = files.iter().filter_map(|path| freshness::dated(String::new(), path).ok()).map(|dated| dated.updated).min();
+
+    let count = db.records().count();
+    let action = tr!("db-sqlite-action", count = count, file = file, path = output);
+    if !safety.confirm(&action)? {
+        return Ok(());
+    }
+    db.save_to_sqlite(output, updated, &sources)?;
+    events::record(Event::new(Kind::Database, "db import").path(output).detail(format!("{} codes from {}", count, file)));
+    outln!("{}", tr!("db-sqlite-done", count = count, path = output));
+    Ok(())
+}
+
+// Upgrade the bundled database or a pack to the current schema, after showing what changes;
+// with --dry-run only the steps are shown
+pub fn migrate(config: &Config, pack: Option<&str>, safety: &SafetyArgs) -> Result<(), Box<dyn Error>> {
+    let path = database_path(config, pack)?;
+    let Table { version, updated, headers, records } = read_file(&path)?;
+    if version == Some(schema::CURRENT) {
+        outln!("{}", tr!("db-migrate-current", path = path.as_str(), version = schema::CURRENT));
+        return Ok(());
+    }
+
+    let from = version.unwrap_or_else(|| schema::dated(&headers));
+    outln!("{}", tr!("db-migrate-plan", path = path.as_str(), from = from, to = schema::CURRENT));
+    if version.is_none() {
+        outln!("  {}", tr!("db-migrate-line", line = schema::line(schema::CURRENT)));
+    }
+    for migration in schema::pending(from) {
+        let added: Vec<&str> = migration.columns.iter().copied().filter(|column| !headers.iter().any(|header| header == *column)).collect();
+        if !added.is_empty() {
+            outln!("  {}", tr!("db-migrate-step", version = migration.to, columns = added.join(", ")));
+        }
+    }
+
+    let action = tr!("db-migrate-action", path = path.as_str(), version = schema::CURRENT);
+    if !safety.confirm(&action)? {
+        return Ok(());
+    }
+    let (headers, records) = schema::migrate(from, &headers, &records);
+    write_file(&path, Some(schema::CURRENT), updated, &headers, &records)?;
+    events::record(Event::new(Kind::Database, "db migrate").path(&path).detail(format!("schema {} to {}", from, schema::CURRENT)));
+    outln!("{}", tr!("db-migrate-done", path = path.as_str(), version = schema::CURRENT, codes = records.len()));
+    Ok(())
+}
+
+// Record that the bundled database or a pack was brought up to date on the day of `now`
+pub fn update(config: &Config, pack: Option<&str>, now: u64) -> Result<(), Box<dyn Error>> {
+    let path = database_path(config, pack)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let previous = freshness::stamped(&content).map_err(|e| format!("{}: {}", path, e))?;
+    fs::write(&path, freshness::restamp(&content, now))?;
+    let detail = match previous {
+        Some(previous) => format!("updated {} to {}", freshness::date(previous), freshness::date(now)),
+        None => format!("updated {}", freshness::date(now)),
+    };
+    events::record(Event::new(Kind::Database, "db update").path(&path).detail(detail));
+    outln!("{}", tr!("db-update-done", path = path.as_str(), date = freshness::date(now)));
+    Ok(())
+}
+
+// Show when the bundled database and every pack were last brought up to date, for
+// `db update --check`; returns the stale files
+pub fn check_freshness(config: &Config, now: u64) -> Vec<Dated> {
+    let files = freshness::survey(config);
+    for file in &files {
+        let mut line = freshness::describe(file, now);
+        if !file.stamped {
+            line = format!("{} {}", line, tr!("db-update-modified").styled(Style::Muted));
+        }
+        if file.is_stale(&config.freshness, now) {
+            outln!("  {} {}", "[STALE]".styled(Style::Warning), line);
+        } else {
+            outln!("  {} {}", "[ OK  ]".styled(Style::Success), line);
+        }
+    }
+    let stale: Vec<Dated> = files.into_iter().filter(|file| file.is_stale(&config.freshness, now)).collect();
+    match config.freshness.max_age() {
+        None => outln!("{}", tr!("db-update-never-stale").styled(Style::Muted)),
+        Some(_) if stale.is_empty() => outln!("{}", tr!("db-update-fresh", max_age = config.freshness.max_age.as_str()).styled(Style::Success)),
+        Some(_) => {
+            outln!("{}", tr!("db-update-stale", count = stale.len(), max_age = config.freshness.max_age.as_str()).styled(Style::Warning));
+            outln!("{}", tr!("db-update-hint").styled(Style::Hint));
+        },
+    }
+    stale
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::StringRecord;
+    use crate::ErrorCode;
+    use crate::testing::{init, sample_database};
+    use super::{canonical_csv, canonicalize, plan_import, Condition};
+
+    #[test]
+    fn canonical_export_sorts_and_normalizes_rows() {
+        let headers = StringRecord::from(vec!["notes", " code", "severity", "description", "system", "possible_causes", "recommended_actions"]);
+        let records = [
+            StringRecord::from(vec!["keep me ", "p1999 ", "medium", " Shop, fault  ", "Engine", "Wiring |  | Ground ", "Check"]),
+            StringRecord::from(vec!["", "P0001", "HIGH", "Say \"hi\"", "Fuel", "a", ""]),
+        ];
+
+        let (headers, rows) = canonicalize(&headers, &records);
+        assert_eq!(canonical_csv(&headers, &rows), "\
+code,description,severity,system,possible_causes,recommended_actions,notes
+P0001,\"Say \"\"hi\"\"\",High,Fuel,\"a\",,
+P1999,\"Shop, fault\",Medium,Engine,\"Wiring|Ground\",\"Check\",keep me
+");
+        // Exporting canonical output again changes nothing
+        assert_eq!(canonicalize(&headers, &rows), (headers.clone(), rows.clone()));
+    }
+
+    #[test]
+    fn import_plan_adds_updates_and_skips_conflicting_or_invalid_rows() {
+        init();
+        let headers = StringRecord::from(vec!["code", "description", "severity", "system", "possible_causes", "recommended_actions", "monitors"]);
+        let records = vec![
+            StringRecord::from(vec!["P0420", "Catalyst Efficiency Below Threshold", "Medium", "Emissions", "Worn catalyst", "Replace catalyst", "catalyst"]),
+            StringRecord::from(vec!["P0171", "System Too Lean", "Medium", "Fuel", "Vacuum leak", "Smoke test", ""]),
+        ];
+        let import_headers = StringRecord::from(vec!["code", "description", "severity", "system", "possible_causes", "recommended_actions", "notes"]);
+        let rows = vec![
+            StringRecord::from(vec!["p0420", "Catalyst Efficiency Below Threshold", "High", "Emissions", "Worn catalyst", "Replace catalyst", ""]),
+            StringRecord::from(vec!["P0171", " System Too Lean ", "medium", "Fuel", "Vacuum leak", "Smoke test", ""]),
+            StringRecord::from(vec!["P1234", "Custom", "Low", "Engine", "", "", ""]),
+            StringRecord::from(vec!["P1235", "Twice", "Low", "Engine", "", "", ""]),
+            StringRecord::from(vec!["P1235", "Twice, differently", "Low", "Engine", "", "", ""]),
+            StringRecord::from(vec!["P1236", "Bad", "Urgent", "Engine", "", "", ""]),
+        ];
+        let plan = plan_import(&headers, &records, &import_headers, &rows).unwrap();
+        assert_eq!(plan.added, [("P1234".to_string(), "Custom".to_string())]);
+        assert_eq!(plan.updated, [("P0420".to_string(), vec!["severity".to_string()])]);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.conflicts, ["P1235"]);
+        assert_eq!(plan.warnings.iter().map(|(subject, _)| subject.as_str()).collect::<Vec<_>>(), ["notes", "P1236"]);
+        // The monitors column, missing from the file, keeps its value
+        assert_eq!(&plan.records[0], &StringRecord::from(vec!["P0420", "Catalyst Efficiency Below Threshold", "High", "Emissions", "Worn catalyst", "Replace catalyst", "catalyst"]));
+        assert_eq!(plan.records.len(), 3);
+    }
+
+    #[test]
+    fn db_sample_is_repeatable_and_respects_where() {
+        let db = sample_database();
+        let codes = |sample: Vec<&ErrorCode>| sample.iter().map(|error| error.code.clone()).collect::<Vec<_>>();
+        let (first, total) = super::sample(&db, 3, 42, &[]);
+        assert_eq!(total, 4);
+        assert_eq!(codes(first.clone()), codes(super::sample(&db, 3, 42, &[]).0));
+        assert!((0..20).any(|seed| codes(super::sample(&db, 3, seed, &[]).0) != codes(first.clone())));
+
+        // More than match gives every match once
+        let (all, _) = super::sample(&db, 10, 7, &[]);
+        let mut all = codes(all);
+        all.sort();
+        assert_eq!(all, vec!["B0001", "C0035", "P0101", "P0300"]);
+
+        // The same field twice is either value; different fields must both hold
+        let either = [super::parse_condition("severity=high").unwrap(), super::parse_condition("Severity = Low").unwrap()];
+        assert_eq!(either[1], Condition::Severity("Low".to_string()));
+        let (found, total) = super::sample(&db, 10, 1, &either);
+        assert_eq!(total, 2);
+        assert!(found.iter().all(|error| ["P0300", "C0035"].contains(&error.code.as_str())));
+        let both = [either[0].clone(), Condition::System("abs".to_string())];
+        assert_eq!(super::sample(&db, 10, 1, &both).1, 0);
+        assert!(super::parse_condition("make=Ford").is_err());
+        assert!(super::parse_condition("Engine").is_err());
+    }
+}
@@ -0,0 +1,299 @@
+// Merging of the definitions of a code from layered databases.
+//
+// Definitions are ordered by the precedence of their source (the bundled
+// database is 0, packs default to their position in the configuration) and
+// each field is then taken according to the [merge] policy. The result only
+// depends on the configuration and the files, never on load timing.
+use std::fmt;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::{pipe_list, ErrorCode};
+
+// How one field is chosen among the definitions
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Strategy {
+    // The value from the highest-precedence definition that sets the field
+    #[default]
+    Highest,
+    // The value from the named pack when it sets the field, otherwise as `Highest`
+    Prefer(String),
+    // Every entry of a '|' list, highest precedence first, without duplicates
+    Union,
+}
+
+impl TryFrom<String> for Strategy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "highest" => Ok(Strategy::Highest),
+            "union" => Ok(Strategy::Union),
+            _ => match value.strip_prefix("prefer:") {
+                Some(pack) if !pack.trim().is_empty() => Ok(Strategy::Prefer(pack.trim().to_string())),
+                _ => Err(format!("unknown merge strategy '{}': use \"highest\", \"union\" or \"prefer:<pack>\"", value)),
+            },
+        }
+    }
+}
+
+impl From<Strategy> for String {
+    fn from(strategy: Strategy) -> Self {
+        strategy.to_string()
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Highest => write!(f, "highest"),
+            Strategy::Prefer(pack) => write!(f, "prefer:{}", pack),
+            Strategy::Union => write!(f, "union"),
+        }
+    }
+}
+
+// The `[merge]` table: a strategy per field, `highest` when not set
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MergePolicy {
+    pub description: Strategy,
+    pub severity: Strategy,
+    pub system: Strategy,
+    pub possible_causes: Strategy,
+    pub recommended_actions: Strategy,
+    pub emissions_warranty: Strategy,
+    pub service_campaigns: Strategy,
+    pub campaign_links: Strategy,
+    pub related_parts: Strategy,
+    pub set_conditions: Strategy,
+    pub monitors: Strategy,
+    pub aliases: Strategy,
+    pub difficulty: Strategy,
+    pub safety_warnings: Strategy,
+}
+
+// A mergeable field of ErrorCode, read and written as text
+struct Field {
+    name: &'static str,
+    // Whether the field is a '|' list that `union` can combine
+    list: bool,
+    get: fn(&ErrorCode) -> String,
+    set: fn(&mut ErrorCode, String),
+}
+
+const FIELDS: [Field; 14] = [
+    Field { name: "description", list: false, get: |e| e.description.clone(), set: |e, v| e.description = v },
+    Field { name: "severity", list: false, get: |e| e.severity.clone(), set: |e, v| e.severity = v },
+    Field { name: "system", list: false, get: |e| e.system.clone(), set: |e, v| e.system = v },
+    Field {
+        name: "possible_causes",
+        list: true,
+        get: |e| e.possible_causes.join("|"),
+        set: |e, v| e.possible_causes = pipe_list(&v).map(str::to_string).collect(),
+    },
+    Field {
+        name: "recommended_actions",
+        list: true,
+        get: |e| e.recommended_actions.join("|"),
+        set: |e, v| e.recommended_actions = pipe_list(&v).map(str::to_string).collect(),
+    },
+    Field {
+        name: "emissions_warranty",
+        list: false,
+        get: |e| e.emissions_warranty.map(|covered| covered.to_string()).unwrap_or_default(),
+        set: |e, v| e.emissions_warranty = v.parse().ok(),
+    },
+    Field { name: "service_campaigns", list: true, get: |e| e.service_campaigns.clone(), set: |e, v| e.service_campaigns = v },
+    Field { name: "campaign_links", list: true, get: |e| e.campaign_links.clone(), set: |e, v| e.campaign_links = v },
+    Field { name: "related_parts", list: true, get: |e| e.related_parts.clone(), set: |e, v| e.related_parts = v },
+    Field { name: "set_conditions", list: false, get: |e| e.set_conditions.clone(), set: |e, v| e.set_conditions = v },
+    Field { name: "monitors", list: true, get: |e| e.monitors.clone(), set: |e, v| e.monitors = v },
+    Field { name: "aliases", list: true, get: |e| e.aliases.clone(), set: |e, v| e.aliases = v },
+    Field { name: "difficulty", list: false, get: |e| e.difficulty.clone(), set: |e, v| e.difficulty = v },
+    Field { name: "safety_warnings", list: true, get: |e| e.safety_warnings.clone(), set: |e, v| e.safety_warnings = v },
+];
+
+impl MergePolicy {
+    fn strategy(&self, field: &str) -> &Strategy {
+        match field {
+            "description" => &self.description,
+            "severity" => &self.severity,
+            "system" => &self.system,
+            "possible_causes" => &self.possible_causes,
+            "recommended_actions" => &self.recommended_actions,
+            "emissions_warranty" => &self.emissions_warranty,
+            "service_campaigns" => &self.service_campaigns,
+            "campaign_links" => &self.campaign_links,
+            "set_conditions" => &self.set_conditions,
+            "monitors" => &self.monitors,
+            "aliases" => &self.aliases,
+            "difficulty" => &self.difficulty,
+            "safety_warnings" => &self.safety_warnings,
+            _ => &self.related_parts,
+        }
+    }
+
+    // Reject strategies that cannot apply to their field
+    pub fn validate(&self) -> Result<(), String> {
+        for field in FIELDS.iter().filter(|field| !field.list) {
+            if *self.strategy(field.name) == Strategy::Union {
+                return Err(format!("merge.{}: \"union\" only applies to list fields", field.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Names of the columns after `code`, in the order of the database file
+pub fn field_names() -> impl Iterator<Item = &'static str> {
+    FIELDS.iter().map(|field| field.name)
+}
+
+// The text of a column of the database file, `code` included; None for unknown columns
+pub fn value(error: &ErrorCode, name: &str) -> Option<String> {
+    match name {
+        "code" => Some(error.code.clone()),
+        _ => FIELDS.iter().find(|field| field.name == name).map(|field| (field.get)(error)),
+    }
+}
+
+// Whether a column holds a '|' separated list
+pub fn is_list(name: &str) -> bool {
+    FIELDS.iter().any(|field| field.name == name && field.list)
+}
+
+// The fields that differ between two definitions of a code, as (field, from, to)
+pub fn differences(from: &ErrorCode, to: &ErrorCode) -> Vec<(&'static str, String, String)> {
+    FIELDS.iter()
+        .map(|field| (field.name, (field.get)(from), (field.get)(to)))
+        .filter(|(_, from, to)| from != to)
+        .collect()
+}
+
+// How one field of a merged definition was chosen
+pub struct Decision {
+    pub field: &'static str,
+    pub strategy: Strategy,
+    // Positions in the precedence order of the definitions that supplied the value
+    pub contributors: Vec<usize>,
+    pub value: String,
+}
+
+// Definitions ordered by precedence, highest first; later loads win ties
+pub fn by_precedence(definitions: &[Arc<ErrorCode>]) -> Vec<&Arc<ErrorCode>> {
+    let mut ordered: Vec<(usize, &Arc<ErrorCode>)> = definitions.iter().enumerate().collect();
+    ordered.sort_by(|(a_index, a), (b_index, b)| {
+        let precedence = |error: &ErrorCode| error.source.as_ref().map(|source| source.precedence).unwrap_or_default();
+        precedence(b).cmp(&precedence(a)).then(b_index.cmp(a_index))
+    });
+    ordered.into_iter().map(|(_, error)| error).collect()
+}
+
+// Merge the definitions of one code; returns None when there are none. When the merge
+// changes nothing, as for most codes with a single definition, the highest definition
+// itself is returned rather than a copy.
+pub fn merge(definitions: &[Arc<ErrorCode>], policy: &MergePolicy) -> Option<(Arc<ErrorCode>, Vec<Decision>)> {
+    let ordered = by_precedence(definitions);
+    let top = *ordered.first()?;
+    let mut merged = ErrorCode::clone(top);
+    let mut decisions = Vec::new();
+
+    for field in &FIELDS {
+        let strategy = policy.strategy(field.name).clone();
+        let values: Vec<String> = ordered.iter().map(|error| (field.get)(error)).collect();
+        let highest = || values.iter().position(|value| !value.is_empty());
+
+        let (contributors, value) = match &strategy {
+            Strategy::Highest => pick(&values, highest()),
+            Strategy::Prefer(pack) => {
+                let preferred = ordered.iter().zip(&values).position(|(error, value)| {
+                    !value.is_empty() && error.source.as_ref().is_some_and(|source| source.pack == *pack)
+                });
+                pick(&values, preferred.or_else(highest))
+            },
+            Strategy::Union => union(&values),
+        };
+
+        (field.set)(&mut merged, value.clone());
+        decisions.push(Decision { field: field.name, strategy, contributors, value });
+    }
+    match differences(top, &merged).is_empty() {
+        true => Some((Arc::clone(top), decisions)),
+        false => Some((Arc::new(merged), decisions)),
+    }
+}
+
+fn pick(values: &[String], position: Option<usize>) -> (Vec<usize>, String) {
+    match position {
+        Some(position) => (vec![position], values[position].clone()),
+        None => (Vec::new(), String::new()),
+    }
+}
+
+fn union(values: &[String]) -> (Vec<usize>, String) {
+    let mut entries: Vec<&str> = Vec::new();
+    let mut contributors = Vec::new();
+    for (position, value) in values.iter().enumerate() {
+        let before = entries.len();
+        for entry in pipe_list(value) {
+            if !entries.iter().any(|existing| existing.eq_ignore_ascii_case(entry)) {
+                entries.push(entry);
+            }
+        }
+        if entries.len() > before {
+            contributors.push(position);
+        }
+    }
+    (contributors, entries.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::testing::{sample_database, init};
+    use crate::Source;
+
+    #[test]
+    fn merge_policy_picks_fields_by_precedence() {
+        init();
+        let mut db = sample_database();
+        let pack = Source { pack: "toyota".to_string(), file: "toyota.csv".to_string(), version: None, precedence: 1 };
+        let csv = "code,description,severity,system,possible_causes,recommended_actions\nP0300,Toyota misfire,Medium,Engine,Coil problems|spark plug issues,Replace coils\n";
+        db.read_records(csv.as_bytes(), pack).unwrap();
+
+        let policy: super::MergePolicy = toml::from_str("description = \"prefer:fixture\"\npossible_causes = \"union\"").unwrap();
+        db.apply_merge_policy(&policy);
+        let merged = db.lookup_error("P0300").unwrap();
+        assert_eq!(merged.description, "Random/Multiple Cylinder Misfire Detected");
+        assert_eq!(merged.severity, "Medium");
+        assert_eq!(merged.possible_causes, ["Coil problems", "spark plug issues", "Ignition coil problems"]);
+        // Fields the pack leaves empty come from the bundled definition
+        assert_eq!(merged.related_parts, "Spark plug:90919-01253|Ignition coil:90919-02258");
+    }
+
+    #[test]
+    fn records_are_shared_until_a_merge_or_policy_changes_them() {
+        init();
+        let mut db = sample_database();
+        let pack = Source { pack: "toyota".to_string(), file: "toyota.csv".to_string(), version: None, precedence: 1 };
+        let csv = "code,description,severity,system,possible_causes,recommended_actions\nP0300,Toyota misfire,High,Engine,Coils,Replace coils\n";
+        db.read_records(csv.as_bytes(), pack).unwrap();
+        let policy: super::MergePolicy = toml::from_str("possible_causes = \"union\"").unwrap();
+        db.apply_merge_policy(&policy);
+
+        // A code with one definition keeps it; lookups and handles point at the same record
+        assert!(Arc::ptr_eq(&db.errors["P0101"], &db.definitions_of("P0101")[0]));
+        let handle = db.shared_error("P0101").unwrap();
+        assert!(std::ptr::eq(handle.as_ref(), db.lookup_error("P0101").unwrap()));
+        // Merged fields make a record of their own, and the definitions stay as loaded
+        assert!(db.definitions_of("P0300").iter().all(|definition| !Arc::ptr_eq(&db.errors["P0300"], definition)));
+        assert_eq!(db.definitions_of("P0300")[1].possible_causes, ["Coils"]);
+
+        let rules: crate::policy::SeverityPolicy = toml::from_str("[[rule]]\ncodes = [\"P0101\"]\nseverity = \"Critical\"\nreason = \"Fleet rule\"").unwrap();
+        assert_eq!(rules.apply(&mut db), 1);
+        assert_eq!(db.lookup_error("P0101").unwrap().severity, "Critical");
+        assert_eq!(handle.severity, "Medium");
+        assert_eq!(db.definitions_of("P0101")[0].severity, "Medium");
+    }
+}
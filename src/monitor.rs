@@ -0,0 +1,682 @@
+// Live data from a connected vehicle, with alert thresholds.
+//
+// `monitor` reads a set of PIDs at a fixed interval and prints one line per
+// sample. Thresholds from the `[monitor]` table raise an alert when a value
+// crosses its limit and a notice when it comes back; each of these is shown
+// in the terminal, appended to <data_dir>/monitor.log and, when configured,
+// posted to a webhook and published over MQTT. An alert is sent once per
+// crossing, not on every sample.
+//
+// A session can be time-boxed (`--duration 10m`). With `--summary`, the
+// minimum, average, maximum and standard deviation of each PID are printed
+// at the end, with every threshold crossing and the trouble codes that
+// appeared or cleared meanwhile, and the summary is saved to the scan
+// archive with the codes read at the end.
+//
+// On a SocketCAN interface, `--dbc` and `--signal` show manufacturer signals
+// decoded from the frames on the bus instead of PIDs. Thresholds apply to
+// PIDs only, and no codes are read.
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::adapter::Adapter;
+use crate::archive::{Archive, Clock, ScanRecord, SystemClock, UNKNOWN_VIN};
+#[cfg(feature = "adapter")]
+use crate::dbc::{Message, Signal};
+use crate::i18n::tr;
+use crate::interrupt;
+use crate::net::{self, Client};
+use crate::pids::{self, Pid};
+use crate::presentation::{Style, Styled};
+#[cfg(feature = "adapter")]
+use crate::socketcan::CanSocket;
+use crate::time;
+use crate::transcript::outln;
+
+// The `[monitor]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitorSettings {
+    // PIDs shown when none are given on the command line
+    pub pids: Vec<String>,
+
+    // Time between samples
+    pub interval_ms: u64,
+
+    // `[[monitor.thresholds]]` entries
+    pub thresholds: Vec<Threshold>,
+
+    // URL receiving a JSON POST for every alert
+    pub webhook: Option<String>,
+
+    // mqtt://host[:port]/topic receiving the same JSON
+    pub mqtt: Option<String>,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        MonitorSettings {
+            pids: ["rpm", "speed", "coolant", "voltage"].map(str::to_string).to_vec(),
+            interval_ms: 1000,
+            thresholds: Vec::new(),
+            webhook: None,
+            mqtt: None,
+        }
+    }
+}
+
+// A limit on one PID, e.g. coolant above 110 or voltage below 12.0
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Threshold {
+    pub pid: String,
+    pub above: Option<f64>,
+    pub below: Option<f64>,
+}
+
+impl MonitorSettings {
+    // Check PID names, that each threshold has a limit, and the MQTT URL
+    pub fn validate(&self) -> Result<(), String> {
+        for name in &self.pids {
+            pids::find(name).ok_or_else(|| format!("monitor.pids: unknown PID '{}'", name))?;
+        }
+        for threshold in &self.thresholds {
+            pids::find(&threshold.pid).ok_or_else(|| format!("monitor.thresholds: unknown PID '{}'", threshold.pid))?;
+            if threshold.above.is_none() && threshold.below.is_none() {
+                return Err(format!("monitor.thresholds: '{}' needs 'above' or 'below'", threshold.pid));
+            }
+        }
+        if let Some(url) = &self.mqtt {
+            net::parse_mqtt_url(url)?;
+        }
+        Ok(())
+    }
+}
+
+// A threshold crossing, or a value returning within its limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub pid: &'static str,
+    pub value: f64,
+    pub unit: &'static str,
+    // The limit crossed; None when the value is back within every limit
+    pub limit: Option<Limit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Above(f64),
+    Below(f64),
+}
+
+impl Event {
+    pub fn message(&self) -> String {
+        let value = format!("{:.1} {}", self.value, self.unit);
+        match self.limit {
+            Some(Limit::Above(limit)) => tr!("monitor-alert-above", pid = self.pid, value = value, limit = format!("{} {}", limit, self.unit)),
+            Some(Limit::Below(limit)) => tr!("monitor-alert-below", pid = self.pid, value = value, limit = format!("{} {}", limit, self.unit)),
+            None => tr!("monitor-alert-cleared", pid = self.pid, value = value),
+        }
+    }
+
+    fn to_json(&self, timestamp: u64) -> serde_json::Value {
+        let (state, limit) = match self.limit {
+            Some(Limit::Above(limit)) => ("above", Some(limit)),
+            Some(Limit::Below(limit)) => ("below", Some(limit)),
+            None => ("cleared", None),
+        };
+        json!({
+            "time": time::iso8601(timestamp),
+            "pid": self.pid,
+            "value": self.value,
+            "unit": self.unit,
+            "state": state,
+            "limit": limit,
+        })
+    }
+}
+
+// When a session ends without Enter being pressed; unlimited by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Window {
+    pub samples: Option<usize>,
+    pub duration: Option<Duration>,
+}
+
+// One PID over a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PidStats {
+    pub pid: String,
+    pub unit: String,
+    pub samples: usize,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    // Population standard deviation
+    pub stddev: f64,
+}
+
+// A threshold crossing during a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub time: u64,
+    pub pid: String,
+    pub value: f64,
+    pub unit: String,
+    // "above" or "below"
+    pub state: String,
+    pub limit: f64,
+}
+
+// What a session saw, kept in its scan record
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub started: u64,
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub ended: u64,
+    pub samples: usize,
+    pub pids: Vec<PidStats>,
+    pub violations: Vec<Violation>,
+    // Stored or pending codes that were not there at the start, and those that went away
+    pub codes_added: Vec<String>,
+    pub codes_cleared: Vec<String>,
+}
+
+// Collects the values and crossings of a session for its summary
+pub struct Recorder {
+    started: u64,
+    samples: usize,
+    // Name and unit of each PID or signal, with its values
+    values: Vec<(String, String, Vec<f64>)>,
+    violations: Vec<Violation>,
+}
+
+impl Recorder {
+    pub fn new(started: u64) -> Self {
+        Recorder { started, samples: 0, values: Vec::new(), violations: Vec::new() }
+    }
+
+    pub fn value(&mut self, name: &str, unit: &str, value: f64) {
+        match self.values.iter_mut().find(|(known, _, _)| known == name) {
+            Some((_, _, values)) => values.push(value),
+            None => self.values.push((name.to_string(), unit.to_string(), vec![value])),
+        }
+    }
+
+    // Keep a crossing; values returning within limits are not violations
+    pub fn event(&mut self, event: &Event, time: u64) {
+        let (state, limit) = match event.limit {
+            Some(Limit::Above(limit)) => ("above", limit),
+            Some(Limit::Below(limit)) => ("below", limit),
+            None => return,
+        };
+        self.violations.push(Violation {
+            time,
+            pid: event.pid.to_string(),
+            value: event.value,
+            unit: event.unit.to_string(),
+            state: state.to_string(),
+            limit,
+        });
+    }
+
+    pub fn sample_taken(&mut self) {
+        self.samples += 1;
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // The summary, given the codes read before and after the session
+    pub fn finish(self, ended: u64, codes_before: &[String], codes_after: &[String]) -> Summary {
+        let pids = self.values
+            .into_iter()
+            .map(|(pid, unit, values)| {
+                let count = values.len() as f64;
+                let avg = values.iter().sum::<f64>() / count;
+                let variance = values.iter().map(|value| (value - avg).powi(2)).sum::<f64>() / count;
+                PidStats {
+                    pid,
+                    unit,
+                    samples: values.len(),
+                    min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                    avg,
+                    max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                    stddev: variance.sqrt(),
+                }
+            })
+            .collect();
+        let missing = |codes: &[String], from: &[String]| codes.iter().filter(|code| !from.contains(code)).cloned().collect();
+        Summary {
+            started: self.started,
+            ended,
+            samples: self.samples,
+            pids,
+            violations: self.violations,
+            codes_added: missing(codes_after, codes_before),
+            codes_cleared: missing(codes_before, codes_after),
+        }
+    }
+}
+
+impl Violation {
+    fn message(&self) -> String {
+        let value = format!("{:.1} {}", self.value, self.unit);
+        let limit = format!("{} {}", self.limit, self.unit);
+        match self.state.as_str() {
+            "below" => tr!("monitor-alert-below", pid = self.pid.as_str(), value = value, limit = limit),
+            _ => tr!("monitor-alert-above", pid = self.pid.as_str(), value = value, limit = limit),
+        }
+    }
+}
+
+// Parse a session length such as "90s", "10m" or "1h"
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| format!("Invalid duration '{}'", text))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => return Err(format!("Invalid duration '{}': use a number followed by s, m or h", text)),
+    };
+    amount.checked_mul(unit_seconds).map(Duration::from_secs).ok_or_else(|| format!("Invalid duration '{}'", text))
+}
+
+// The stored and pending codes, sorted, for comparing the start and end of a session
+pub fn read_codes(adapter: &mut Adapter) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut codes = adapter.read_codes(0x03)?;
+    codes.extend(adapter.read_codes(0x07)?);
+    codes.sort();
+    codes.dedup();
+    Ok(codes)
+}
+
+// Tracks which thresholds are currently exceeded so each crossing is reported once
+pub struct Guard<'a> {
+    thresholds: &'a [Threshold],
+    active: Vec<&'static str>,
+}
+
+impl<'a> Guard<'a> {
+    pub fn new(thresholds: &'a [Threshold]) -> Self {
+        Guard { thresholds, active: Vec::new() }
+    }
+
+    // Compare a new value with the PID's thresholds
+    pub fn check(&mut self, pid: &'static Pid, value: f64) -> Option<Event> {
+        let crossed = self.thresholds
+            .iter()
+            .filter(|threshold| threshold.pid.eq_ignore_ascii_case(pid.name))
+            .find_map(|threshold| match (threshold.above, threshold.below) {
+                (Some(above), _) if value > above => Some(Limit::Above(above)),
+                (_, Some(below)) if value < below => Some(Limit::Below(below)),
+                _ => None,
+            });
+        let was_active = self.active.contains(&pid.name);
+        match (crossed, was_active) {
+            (Some(limit), false) => {
+                self.active.push(pid.name);
+                Some(Event { pid: pid.name, value, unit: pid.unit, limit: Some(limit) })
+            },
+            (None, true) => {
+                self.active.retain(|name| *name != pid.name);
+                Some(Event { pid: pid.name, value, unit: pid.unit, limit: None })
+            },
+            _ => None,
+        }
+    }
+}
+
+// Where alerts go besides the terminal
+pub struct Notifier<'a> {
+    pub log: PathBuf,
+    pub webhook: Option<String>,
+    pub mqtt: Option<String>,
+    pub client: Option<&'a Client>,
+}
+
+impl Notifier<'_> {
+    // Show an event and deliver it
+    fn send(&self, event: &Event, timestamp: u64) {
+        let label = match event.limit {
+            Some(_) => tr!("monitor-alert").styled(Style::Error),
+            None => tr!("monitor-ok").styled(Style::Success),
+        };
+        outln!("{} {}", label, event.message());
+        for failure in self.deliver(event, timestamp) {
+            outln!("{}: {}", tr!("error-prefix").styled(Style::Error), failure);
+        }
+    }
+
+    // Log an event and pass it on; returns what failed, as a failing channel
+    // does not stop the monitor
+    pub fn deliver(&self, event: &Event, timestamp: u64) -> Vec<String> {
+        let mut failures = Vec::new();
+        let payload = event.to_json(timestamp).to_string();
+        let logged = fs::create_dir_all(self.log.parent().unwrap_or(&self.log))
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&self.log))
+            .and_then(|mut file| writeln!(file, "{} {}", time::iso8601(timestamp), event.message()));
+        if let Err(e) = logged {
+            failures.push(tr!("monitor-log-failed", path = self.log.display().to_string(), error = e.to_string()));
+        }
+        if let (Some(url), Some(client)) = (&self.webhook, self.client) {
+            if let Err(e) = client.post_json(url, &payload) {
+                failures.push(e.to_string());
+            }
+        }
+        if let Some(url) = &self.mqtt {
+            if let Err(e) = net::publish_mqtt(url, &payload) {
+                failures.push(e.to_string());
+            }
+        }
+        failures
+    }
+}
+
+// Ends a session when Enter or Ctrl-C is pressed; only a terminal can stop it with Enter
+pub struct Stop {
+    enter: Arc<AtomicBool>,
+}
+
+impl Stop {
+    pub fn requested(&self) -> bool {
+        self.enter.load(Ordering::Relaxed) || interrupt::interrupted()
+    }
+
+    // Sleep until the next sample, waking early when the session is stopped
+    pub fn wait(&self, duration: Duration) {
+        let end = Instant::now() + duration;
+        while !self.requested() && Instant::now() < end {
+            thread::sleep(end.saturating_duration_since(Instant::now()).min(Duration::from_millis(100)));
+        }
+    }
+}
+
+impl Drop for Stop {
+    // Lets the thread waiting for Enter finish instead of taking the next line typed
+    fn drop(&mut self) {
+        self.enter.store(true, Ordering::Relaxed);
+    }
+}
+
+// Whether a line can be read from stdin within `timeout`
+#[cfg(target_os = "linux")]
+pub fn stdin_ready(timeout: Duration) -> bool {
+    let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    // SAFETY: polls one valid pollfd structure
+    unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as libc::c_int) > 0 }
+}
+
+// Elsewhere the thread blocks until Enter, as there is no portable way to wait on stdin
+#[cfg(not(target_os = "linux"))]
+pub fn stdin_ready(_timeout: Duration) -> bool {
+    true
+}
+
+pub fn stop_on_enter() -> Stop {
+    let enter = Arc::new(AtomicBool::new(false));
+    if io::stdin().is_terminal() {
+        let flag = Arc::clone(&enter);
+        thread::spawn(move || {
+            while !flag.load(Ordering::Relaxed) {
+                if stdin_ready(Duration::from_millis(100)) {
+                    let mut line = String::new();
+                    let _ = io::stdin().read_line(&mut line);
+                    flag.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+    Stop { enter }
+}
+
+// One reading of every PID: the value of each, n/a when the vehicle gives
+// none, and the thresholds crossed or cleared
+pub struct Sample {
+    pub timestamp: u64,
+    pub values: Vec<(&'static Pid, Option<f64>)>,
+    pub events: Vec<Event>,
+}
+
+// Read the PIDs once, keeping the values and crossings in `recorder`; None
+// when Ctrl-C was pressed in the middle. `monitor` prints the sample as a
+// line, `monitor --tui` draws it as gauges.
+pub fn sample(adapter: &mut Adapter, pids: &[&'static Pid], guard: &mut Guard, recorder: &mut Recorder) -> Result<Option<Sample>, Box<dyn Error>> {
+    let timestamp = SystemClock.now();
+    let mut values = Vec::new();
+    let mut events = Vec::new();
+    for &pid in pids {
+        let value = match adapter.read_pid(pid) {
+            Err(_) if interrupt::interrupted() => return Ok(None),
+            result => result?,
+        };
+        if let Some(value) = value {
+            recorder.value(pid.name, pid.unit, value);
+            events.extend(guard.check(pid, value));
+        }
+        values.push((pid, value));
+    }
+    for event in &events {
+        recorder.event(event, timestamp);
+    }
+    recorder.sample_taken();
+    Ok(Some(Sample { timestamp, values, events }))
+}
+
+// Read the PIDs until Enter is pressed or the window closes
+pub fn run(adapter: &mut Adapter, settings: &MonitorSettings, pids: &[&'static Pid], notifier: &Notifier, window: Window) -> Result<Recorder, Box<dyn Error>> {
+    let names: Vec<&str> = pids.iter().map(|pid| pid.name).collect();
+    outln!("{}", tr!("monitor-started", pids = names.join(", "), interval = settings.interval_ms));
+    let stop = stop_on_enter();
+    let mut guard = Guard::new(&settings.thresholds);
+    let mut recorder = Recorder::new(SystemClock.now());
+    let deadline = window.duration.map(|duration| Instant::now() + duration);
+
+    while !stop.requested() && window.samples.is_none_or(|samples| recorder.samples() < samples) {
+        // Ctrl-C in the middle of a sample ends the session like Enter does
+        let Some(sample) = sample(adapter, pids, &mut guard, &mut recorder)? else { break };
+        let mut line = vec![time::format_local(sample.timestamp, "%H:%M:%S").styled(Style::Muted).to_string()];
+        for (pid, value) in &sample.values {
+            match value {
+                Some(value) => line.push(format!("{} {:.1} {}", pid.name.styled(Style::Label), value, pid.unit)),
+                None => line.push(format!("{} {}", pid.name.styled(Style::Label), "n/a".styled(Style::Muted))),
+            }
+        }
+        outln!("{}", line.join("  "));
+        for event in &sample.events {
+            notifier.send(event, sample.timestamp);
+        }
+
+        if window.samples.is_some_and(|samples| recorder.samples() >= samples) {
+            break;
+        }
+        let pause = Duration::from_millis(settings.interval_ms);
+        match deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())) {
+            Some(remaining) if remaining <= pause => {
+                stop.wait(remaining);
+                break;
+            },
+            _ => stop.wait(pause),
+        }
+    }
+    if interrupt::interrupted() {
+        adapter.close();
+        interrupt::handled();
+    }
+    outln!("{}", tr!("monitor-stopped", samples = recorder.samples()));
+    Ok(recorder)
+}
+
+// Decode the named DBC signals from every frame on a SocketCAN interface, showing the latest
+// value of each once per interval until Enter is pressed or the window closes
+#[cfg(feature = "adapter")]
+pub fn run_signals(socket: &CanSocket, settings: &MonitorSettings, signals: &[(&Message, &Signal)], window: Window) -> Result<Recorder, Box<dyn Error>> {
+    let names: Vec<&str> = signals.iter().map(|(_, signal)| signal.name.as_str()).collect();
+    outln!("{}", tr!("monitor-started", pids = names.join(", "), interval = settings.interval_ms));
+    let stop = stop_on_enter();
+    let mut recorder = Recorder::new(SystemClock.now());
+    let deadline = window.duration.map(|duration| Instant::now() + duration);
+    let mut latest: Vec<Option<f64>> = vec![None; signals.len()];
+
+    while !stop.requested() && window.samples.is_none_or(|samples| recorder.samples() < samples) {
+        let mut end = Instant::now() + Duration::from_millis(settings.interval_ms);
+        if let Some(deadline) = deadline {
+            end = end.min(deadline);
+        }
+        while Instant::now() < end && !stop.requested() {
+            let Some(frame) = socket.read_frame()? else { continue };
+            for ((message, signal), value) in signals.iter().zip(latest.iter_mut()) {
+                if message.matches(frame.id) {
+                    *value = message.decode(signal, &frame.data).or(*value);
+                }
+            }
+        }
+
+        let timestamp = SystemClock.now();
+        let mut line = vec![time::format_local(timestamp, "%H:%M:%S").styled(Style::Muted).to_string()];
+        for ((_, signal), value) in signals.iter().zip(&latest) {
+            match value {
+                Some(value) => {
+                    line.push(format!("{} {:.1} {}", signal.name.styled(Style::Label), value, signal.unit));
+                    recorder.value(&signal.name, &signal.unit, *value);
+                },
+                None => line.push(format!("{} {}", signal.name.styled(Style::Label), "n/a".styled(Style::Muted))),
+            }
+        }
+        outln!("{}", line.join("  "));
+        recorder.sample_taken();
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+    }
+    interrupt::handled();
+    outln!("{}", tr!("monitor-stopped", samples = recorder.samples()));
+    Ok(recorder)
+}
+
+// Print the statistics of each PID, the threshold crossings and the code changes
+pub fn display(summary: &Summary) {
+    let minutes = summary.ended.saturating_sub(summary.started) / 60;
+    let seconds = summary.ended.saturating_sub(summary.started) % 60;
+    outln!();
+    outln!("{}", tr!("monitor-summary-header", samples = summary.samples, minutes = minutes, seconds = seconds).styled(Style::Heading));
+    for stats in &summary.pids {
+        outln!(
+            "  {:<10} {} {:.1}  {} {:.1}  {} {:.1}  {} {:.2}  {}",
+            stats.pid.styled(Style::Label),
+            tr!("monitor-summary-min"), stats.min,
+            tr!("monitor-summary-avg"), stats.avg,
+            tr!("monitor-summary-max"), stats.max,
+            tr!("monitor-summary-stddev"), stats.stddev,
+            stats.unit.styled(Style::Muted)
+        );
+    }
+
+    outln!("{}", tr!("monitor-summary-violations", count = summary.violations.len()).styled(Style::Section));
+    for violation in &summary.violations {
+        outln!("  {} {}", time::format_local(violation.time, "%H:%M:%S").styled(Style::Muted), violation.message().styled(Style::Warning));
+    }
+
+    outln!("{}", tr!("monitor-summary-codes").styled(Style::Section));
+    if summary.codes_added.is_empty() && summary.codes_cleared.is_empty() {
+        outln!("  {}", tr!("monitor-summary-codes-unchanged"));
+    }
+    for code in &summary.codes_added {
+        outln!("  + {} {}", code.styled(Style::Code), tr!("monitor-summary-code-added").styled(Style::Error));
+    }
+    for code in &summary.codes_cleared {
+        outln!("  - {} {}", code.styled(Style::Code), tr!("monitor-summary-code-cleared").styled(Style::Success));
+    }
+}
+
+// Keep the summary in the scan archive with the codes read at the end of the session
+pub fn save(summary: &Summary, vin: Option<String>, codes: &[String], data_dir: &std::path::Path) -> Result<String, Box<dyn Error>> {
+    let record = ScanRecord {
+        id: SystemClock.batch_id(),
+        vin: vin.unwrap_or_else(|| UNKNOWN_VIN.to_string()),
+        timestamp: summary.ended,
+        source: "monitor".to_string(),
+        codes: codes.to_vec(),
+        summary: Some(summary.clone()),
+    };
+    Archive::open(data_dir)?.save(&record)?;
+    Ok(record.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::testing::{init, FIXED_TIME};
+    use super::{parse_duration, Guard, Limit, Recorder, Threshold};
+
+    #[test]
+    fn durations_are_read_in_seconds() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 10m "), Ok(Duration::from_secs(10 * 60)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(60 * 60)));
+        assert!(parse_duration("2d").unwrap_err().contains("s, m or h"));
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn a_duration_too_long_to_count_is_invalid_rather_than_wrapped() {
+        assert_eq!(parse_duration("999999999999999999h"), Err("Invalid duration '999999999999999999h'".to_string()));
+        assert_eq!(parse_duration("99999999999999999999s"), Err("Invalid duration '99999999999999999999s'".to_string()));
+        assert_eq!(parse_duration(&format!("{}m", u64::MAX / 60)), Ok(Duration::from_secs(u64::MAX / 60 * 60)));
+    }
+
+    #[test]
+    fn monitor_alerts_once_per_crossing() {
+        init();
+        let coolant = crate::pids::find("coolant").unwrap();
+        let thresholds = [Threshold { pid: "coolant".to_string(), above: Some(110.0), below: None }];
+        let mut guard = Guard::new(&thresholds);
+        assert_eq!(guard.check(coolant, 95.0), None);
+        assert_eq!(guard.check(coolant, 112.0).unwrap().limit, Some(Limit::Above(110.0)));
+        assert_eq!(guard.check(coolant, 115.0), None);
+        assert_eq!(guard.check(coolant, 100.0).unwrap().limit, None);
+        assert!(crate::net::parse_mqtt_url("mqtt://broker/shop/dyno").unwrap().0.ends_with(":1883"));
+    }
+
+    #[test]
+    fn monitor_summary_has_statistics_violations_and_code_changes() {
+        init();
+        let coolant = crate::pids::find("coolant").unwrap();
+        let thresholds = [Threshold { pid: "coolant".to_string(), above: Some(110.0), below: None }];
+        let mut guard = Guard::new(&thresholds);
+        let mut recorder = Recorder::new(FIXED_TIME);
+        for (second, value) in [90.0, 100.0, 112.0, 98.0].into_iter().enumerate() {
+            recorder.value(coolant.name, coolant.unit, value);
+            if let Some(event) = guard.check(coolant, value) {
+                recorder.event(&event, FIXED_TIME + second as u64);
+            }
+            recorder.sample_taken();
+        }
+
+        let codes = |codes: &[&str]| codes.iter().map(|code| code.to_string()).collect::<Vec<_>>();
+        let summary = recorder.finish(FIXED_TIME + 600, &codes(&["P0128", "P0420"]), &codes(&["P0217", "P0420"]));
+        assert_eq!(summary.samples, 4);
+        let stats = &summary.pids[0];
+        assert_eq!((stats.min, stats.avg, stats.max), (90.0, 100.0, 112.0));
+        assert!((stats.stddev - 62.0_f64.sqrt()).abs() < 1e-9);
+        assert_eq!(summary.violations.len(), 1);
+        assert_eq!((summary.violations[0].time, summary.violations[0].state.as_str()), (FIXED_TIME + 2, "above"));
+        assert_eq!((summary.codes_added, summary.codes_cleared), (codes(&["P0217"]), codes(&["P0128"])));
+
+        assert_eq!(super::parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert!(super::parse_duration("10").is_err());
+    }
+}
@@ -0,0 +1,314 @@
+// Shared HTTP client for every online feature.
+//
+// Requests go through `Client`, which caches responses under
+// <data_dir>/http-cache, spaces out requests to the same host, retries
+// failures with exponential backoff and honours a configured proxy.
+// `--offline` makes any request that would reach the network fail before a
+// connection is attempted; cached responses are still served. A build
+// without the online feature behaves the same way, whatever the flags.
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+#[cfg(feature = "online")]
+use std::{collections::HashMap, sync::Mutex, thread, time::Instant};
+use serde::{Deserialize, Serialize};
+use crate::archive::{Clock, SystemClock};
+use crate::i18n::tr;
+#[cfg(feature = "online")]
+use crate::interrupt;
+
+// Set by --offline
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+// Refuse every network request from now on
+pub fn set_offline() {
+    OFFLINE.store(true, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+// The `[network]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSettings {
+    // Proxy URL such as http://proxy:3128 or socks5://proxy:1080;
+    // HTTPS_PROXY, HTTP_PROXY and ALL_PROXY are used when not set
+    pub proxy: Option<String>,
+
+    // Shortest time between two requests to the same host
+    pub min_interval_ms: u64,
+
+    // Attempts after the first one for timeouts, HTTP 429 and server errors
+    pub retries: u32,
+
+    pub timeout_secs: u64,
+
+    // How long a cached response is used before fetching it again
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            proxy: None,
+            min_interval_ms: 1000,
+            retries: 3,
+            timeout_secs: 15,
+            cache_ttl_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+// A request was refused because of --offline
+#[derive(Debug)]
+pub struct Offline(pub String);
+
+impl fmt::Display for Offline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", tr!("net-offline", url = self.0.as_str()))
+    }
+}
+
+impl Error for Offline {}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    #[serde(with = "crate::time::iso8601_serde")]
+    fetched_at: u64,
+    body: String,
+}
+
+pub struct Client {
+    #[cfg(feature = "online")]
+    agent: ureq::Agent,
+    settings: NetworkSettings,
+    cache_dir: PathBuf,
+    #[cfg(feature = "online")]
+    last_request: Mutex<HashMap<String, Instant>>,
+}
+
+// Extract the host name from an http(s) URL
+pub fn url_host(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+// FNV-1a, stable across builds so cache file names survive upgrades
+fn cache_key(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+impl Client {
+    pub fn new(settings: &NetworkSettings, data_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        #[cfg(feature = "online")]
+        let builder = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            .user_agent(concat!("cars-scanner/", env!("CARGO_PKG_VERSION")));
+        #[cfg(feature = "online")]
+        let builder = match &settings.proxy {
+            Some(proxy) => builder.proxy(ureq::Proxy::new(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?),
+            None => builder.try_proxy_from_env(true),
+        };
+        Ok(Client {
+            #[cfg(feature = "online")]
+            agent: builder.build(),
+            settings: settings.clone(),
+            cache_dir: data_dir.join("http-cache"),
+            #[cfg(feature = "online")]
+            last_request: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Fetch a URL as text, from the cache when a fresh copy is there
+    pub fn get_text(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        let cache_file = self.cache_dir.join(format!("{}.json", cache_key(url)));
+        let cached = fs::read_to_string(&cache_file)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheEntry>(&content).ok())
+            .filter(|entry| entry.url == url);
+        if let Some(entry) = &cached {
+            let age = SystemClock.now().saturating_sub(entry.fetched_at);
+            if age < self.settings.cache_ttl_secs || is_offline() {
+                return Ok(entry.body.clone());
+            }
+        }
+
+        let body = self.fetch(url)?;
+        let entry = CacheEntry { url: url.to_string(), fetched_at: SystemClock.now(), body };
+        // A cache that cannot be written only costs a refetch next time
+        let _ = fs::create_dir_all(&self.cache_dir)
+            .and_then(|_| fs::write(&cache_file, serde_json::to_string(&entry).unwrap_or_default()));
+        Ok(entry.body)
+    }
+
+    // Fetch a URL from the network, retrying failures that may be temporary
+    #[cfg(feature = "online")]
+    fn fetch(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        if is_offline() {
+            return Err(Box::new(Offline(url.to_string())));
+        }
+        let host = url_host(url).ok_or_else(|| format!("Not an http(s) URL: {}", url))?;
+
+        let mut attempt = 0;
+        loop {
+            interrupt::check()?;
+            self.wait_turn(&host);
+            let backoff = Duration::from_millis(500 << attempt.min(6));
+            let (error, delay): (Box<dyn Error>, Duration) = match self.agent.get(url).call() {
+                Ok(response) => return Ok(response.into_string()?),
+                Err(ureq::Error::Status(status, response)) if status == 429 || status >= 500 => {
+                    let retry_after = response.header("Retry-After").and_then(|value| value.trim().parse::<u64>().ok());
+                    let delay = retry_after.map(Duration::from_secs).unwrap_or(backoff);
+                    (format!("{} returned HTTP {}", url, status).into(), delay)
+                },
+                Err(ureq::Error::Status(status, _)) => return Err(format!("{} returned HTTP {}", url, status).into()),
+                Err(error) => (error.into(), backoff),
+            };
+            if attempt >= self.settings.retries {
+                return Err(error);
+            }
+            attempt += 1;
+            if !interrupt::sleep(delay) {
+                return Err(Box::new(interrupt::Interrupted));
+            }
+        }
+    }
+
+    // POST a JSON body, e.g. to a webhook; nothing is cached or retried
+    #[cfg(feature = "online")]
+    pub fn post_json(&self, url: &str, body: &str) -> Result<(), Box<dyn Error>> {
+        if is_offline() {
+            return Err(Box::new(Offline(url.to_string())));
+        }
+        let host = url_host(url).ok_or_else(|| format!("Not an http(s) URL: {}", url))?;
+        self.wait_turn(&host);
+        match self.agent.post(url).set("Content-Type", "application/json").send_string(body) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, _)) => Err(format!("{} returned HTTP {}", url, status).into()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    // Without the online feature nothing reaches the network; cached
+    // responses are still served
+    #[cfg(not(feature = "online"))]
+    fn fetch(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        match is_offline() {
+            true => Err(Box::new(Offline(url.to_string()))),
+            false => Err(tr!("feature-missing", feature = "online").into()),
+        }
+    }
+
+    #[cfg(not(feature = "online"))]
+    pub fn post_json(&self, url: &str, _body: &str) -> Result<(), Box<dyn Error>> {
+        self.fetch(url).map(|_| ())
+    }
+
+    // Sleep until the host may be contacted again
+    #[cfg(feature = "online")]
+    fn wait_turn(&self, host: &str) {
+        let interval = Duration::from_millis(self.settings.min_interval_ms);
+        let mut last_request = self.last_request.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(last) = last_request.get(host) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        last_request.insert(host.to_string(), Instant::now());
+    }
+}
+
+// Split mqtt://host[:port]/topic into an address and a topic
+pub fn parse_mqtt_url(url: &str) -> Result<(String, String), String> {
+    let rest = url.strip_prefix("mqtt://").ok_or_else(|| format!("Not an mqtt:// URL: {}", url))?;
+    let (authority, topic) = rest.split_once('/').ok_or_else(|| format!("No topic in {}", url))?;
+    if authority.is_empty() || topic.is_empty() {
+        return Err(format!("Expected mqtt://host[:port]/topic, got {}", url));
+    }
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{}:1883", authority) };
+    Ok((address, topic.to_string()))
+}
+
+// MQTT packets carry their remaining length as a base-128 varint
+fn mqtt_packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn mqtt_string(text: &str) -> Vec<u8> {
+    let mut bytes = (text.len() as u16).to_be_bytes().to_vec();
+    bytes.extend_from_slice(text.as_bytes());
+    bytes
+}
+
+// Publish one message at QoS 0 over MQTT 3.1.1: connect, publish, disconnect
+pub fn publish_mqtt(url: &str, payload: &str) -> Result<(), Box<dyn Error>> {
+    if is_offline() {
+        return Err(Box::new(Offline(url.to_string())));
+    }
+    let (address, topic) = parse_mqtt_url(url)?;
+    let mut stream = TcpStream::connect(&address).map_err(|e| format!("Could not connect to {}: {}", address, e))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    // Protocol name and level 4, clean session, 60 s keep-alive, then the client id
+    let mut connect = mqtt_string("MQTT");
+    connect.extend_from_slice(&[4, 0x02, 0, 60]);
+    connect.extend(mqtt_string(&format!("cars-scanner-{}", std::process::id())));
+    stream.write_all(&mqtt_packet(0x10, &connect))?;
+
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack)?;
+    if connack[0] != 0x20 || connack[3] != 0 {
+        return Err(format!("{} refused the MQTT connection (code {})", address, connack[3]).into());
+    }
+
+    let mut publish = mqtt_string(&topic);
+    publish.extend_from_slice(payload.as_bytes());
+    stream.write_all(&mqtt_packet(0x30, &publish))?;
+    stream.write_all(&mqtt_packet(0xE0, &[]))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{init, TempDir};
+
+    #[test]
+    fn offline_refuses_uncached_requests() {
+        init();
+        assert_eq!(super::url_host("https://user@parts.example.com:8443/search?pn=1").as_deref(), Some("parts.example.com"));
+
+        let temp = TempDir::new("net");
+        let dir = temp.join("cache");
+        let client = super::Client::new(&Default::default(), &dir).unwrap();
+        super::set_offline();
+        let error = client.get_text("http://192.0.2.1/never-fetched").unwrap_err();
+        assert!(error.is::<super::Offline>());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,303 @@
+// Node.js bindings (feature "node"), built with napi-rs.
+//
+// A `Database` object wraps one loaded database and labor guide, so a server
+// loads them once and answers lookups, searches and reports in-process:
+//
+//   const { Database } = require('./cars-scanner.node');
+//   const db = new Database('src/data/error_codes.csv');
+//   db.lookup('P0300');
+//   db.search('misfire', 0, 10);
+//   db.report('P0300', { html: true, customer: 'J. Smith' });
+//
+// Search pages are cached per query (see src/cache.rs). `reload()` reads the
+// database file again and empties the cache; `cacheStats()` returns the hit
+// and miss counters for a server's metrics endpoint. A server keeps answering
+// from the codes it read until then, even after the file is edited. So once
+// the file's content hash differs from the one read, the next lookup, search
+// or report prints a warning to standard error, once for each new content;
+// `isStale()` and `staleWarning()` let a health check ask directly.
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use crate::branding::Letterhead;
+use crate::cache::{self, QueryCache};
+use crate::freshness::{Fingerprint, Watched};
+use crate::labor::LaborGuide;
+use crate::repair_order::RepairOrder;
+use crate::search::SearchOptions;
+use crate::i18n::tr;
+use crate::{i18n, render_report, DiagnosticsDatabase, ErrorCode, Source};
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct JsErrorCode {
+    pub code: String,
+    pub description: String,
+    pub severity: String,
+    pub system: String,
+    pub possible_causes: Vec<String>,
+    pub recommended_actions: Vec<String>,
+    pub emissions_warranty: Option<bool>,
+    pub difficulty: String,
+    pub safety_warnings: Vec<String>,
+}
+
+impl From<&ErrorCode> for JsErrorCode {
+    fn from(error: &ErrorCode) -> Self {
+        JsErrorCode {
+            code: error.code.clone(),
+            description: error.description.clone(),
+            severity: error.severity.clone(),
+            system: error.system.clone(),
+            possible_causes: error.possible_causes.clone(),
+            recommended_actions: error.recommended_actions.clone(),
+            emissions_warranty: error.emissions_warranty,
+            difficulty: error.difficulty.clone(),
+            safety_warnings: crate::pipe_list(&error.safety_warnings).map(str::to_string).collect(),
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct JsSearchPage {
+    pub total: u32,
+    pub offset: u32,
+    pub results: Vec<JsErrorCode>,
+}
+
+#[napi(object)]
+pub struct JsCacheStats {
+    pub hits: i64,
+    pub misses: i64,
+    pub entries: u32,
+}
+
+#[napi(object)]
+pub struct DatabaseOptions {
+    // Labor guide CSV for the labor section of reports
+    pub labor_guide: Option<String>,
+    // Language for report headings, e.g. "sv"; the system locale by default
+    pub lang: Option<String>,
+    // Search pages to keep; 0 disables the cache
+    pub cache_size: Option<u32>,
+    // Seconds a cached search page stays valid
+    pub cache_ttl_secs: Option<u32>,
+}
+
+// Defaults for the search cache
+const CACHE_SIZE: u32 = 256;
+const CACHE_TTL_SECS: u32 = 300;
+
+#[napi(object)]
+pub struct ReportOptions {
+    pub html: Option<bool>,
+    pub customer: Option<String>,
+    pub repair_order: Option<String>,
+    pub odometer: Option<u32>,
+}
+
+#[napi]
+pub struct Database {
+    path: String,
+    // The file as the codes were read from it
+    read: Mutex<Watched>,
+    db: DiagnosticsDatabase,
+    labor: LaborGuide,
+    searches: Mutex<QueryCache<JsSearchPage>>,
+}
+
+// The database and the file as it was read
+fn read_database(path: &str) -> Result<(DiagnosticsDatabase, Watched)> {
+    let content = fs::read(path).map_err(|e| js_error(format!("Could not open {}: {}", path, e)))?;
+    let mut db = DiagnosticsDatabase::new();
+    db.read_records(content.as_slice(), Source::bundled(path)).map_err(|e| js_error(format!("Could not open {}: {}", path, e)))?;
+    Ok((db, Watched::new(Fingerprint::new(path, &content))))
+}
+
+fn js_error(error: impl std::fmt::Display) -> Error {
+    Error::from_reason(error.to_string())
+}
+
+#[napi]
+impl Database {
+    #[napi(constructor)]
+    pub fn new(path: String, options: Option<DatabaseOptions>) -> Result<Self> {
+        let options = options.unwrap_or(DatabaseOptions { labor_guide: None, lang: None, cache_size: None, cache_ttl_secs: None });
+        i18n::init(options.lang.as_deref());
+        let (db, read) = read_database(&path)?;
+        let labor = match &options.labor_guide {
+            Some(labor_guide) => LaborGuide::load_from_csv(labor_guide).map_err(js_error)?,
+            None => LaborGuide::default(),
+        };
+        let searches = QueryCache::new(
+            options.cache_size.unwrap_or(CACHE_SIZE) as usize,
+            Duration::from_secs(options.cache_ttl_secs.unwrap_or(CACHE_TTL_SECS).into()),
+        );
+        Ok(Database { path, read: Mutex::new(read), db, labor, searches: Mutex::new(searches) })
+    }
+
+    // Read the database file again, e.g. after it was edited, and empty the search cache
+    #[napi]
+    pub fn reload(&mut self) -> Result<()> {
+        let (db, read) = read_database(&self.path)?;
+        self.db = db;
+        *self.read.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()) = read;
+        self.searches.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+        Ok(())
+    }
+
+    // Whether the database file no longer holds what was read from it, so lookups and
+    // searches answer from old codes until `reload()`; a file that cannot be read counts
+    #[napi]
+    pub fn is_stale(&self) -> bool {
+        self.read.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).changed()
+    }
+
+    // The warning that the database file changed since it was read, the first time it is
+    // asked for after each change; null otherwise
+    #[napi]
+    pub fn stale_warning(&self) -> Option<String> {
+        let newly_changed = self.read.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).newly_changed();
+        newly_changed.then(|| tr!("node-stale", path = self.path.as_str()))
+    }
+
+    // Print the stale warning to standard error, where a server's logs collect it
+    fn warn_if_stale(&self) {
+        if let Some(warning) = self.stale_warning() {
+            eprintln!("{}: {}", tr!("warning-prefix"), warning);
+        }
+    }
+
+    // Search cache hits and misses since the database was opened
+    #[napi]
+    pub fn cache_stats(&self) -> JsCacheStats {
+        let stats = self.searches.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).stats();
+        JsCacheStats { hits: stats.hits as i64, misses: stats.misses as i64, entries: stats.entries as u32 }
+    }
+
+    // The code's record, or null when it is unknown
+    #[napi]
+    pub fn lookup(&self, code: String) -> Option<JsErrorCode> {
+        self.warn_if_stale();
+        self.db.lookup_error(&code.trim().to_uppercase()).map(JsErrorCode::from)
+    }
+
+    // One page of matches, most relevant first
+    #[napi]
+    pub fn search(&self, keyword: String, offset: Option<u32>, limit: Option<u32>) -> JsSearchPage {
+        self.warn_if_stale();
+        let options = SearchOptions {
+            offset: offset.unwrap_or_default() as usize,
+            limit: limit.map(|limit| limit as usize),
+            ..Default::default()
+        };
+        // Searched as cached, so keywords sharing an entry share their results
+        let keyword = cache::normalize(&keyword);
+        let query = format!("{}\u{0}{}\u{0}{:?}", keyword, options.offset, options.limit);
+        let mut searches = self.searches.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        searches.get_or_insert_with(&query, || {
+            let page = self.db.search(&keyword, &options);
+            JsSearchPage {
+                total: page.total as u32,
+                offset: page.offset as u32,
+                results: page.hits.iter().map(|hit| JsErrorCode::from(hit.error.as_ref())).collect(),
+            }
+        })
+    }
+
+    // The same report as `cars-scanner report`, as text or HTML
+    #[napi]
+    pub fn report(&self, code: String, options: Option<ReportOptions>) -> Result<String> {
+        self.warn_if_stale();
+        let error = self.db.shared_error(&code.trim().to_uppercase())
+            .ok_or_else(|| js_error(format!("Error code {} not found", code)))?;
+        let options = options.unwrap_or(ReportOptions { html: None, customer: None, repair_order: None, odometer: None });
+        let order = RepairOrder {
+            customer: options.customer,
+            number: options.repair_order,
+            odometer: options.odometer.map(u64::from),
+            date: None,
+        };
+        Ok(render_report(&error, &self.labor, &Letterhead::default(), &order, options.html.unwrap_or(false)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::testing::{init, TempDir, SAMPLE_DATABASE};
+    use super::{Database, DatabaseOptions, JsSearchPage, ReportOptions};
+
+    #[test]
+    fn a_database_edited_under_a_handle_is_warned_about_once_per_change() {
+        let dir = TempDir::new("node");
+        let path = dir.file("codes.csv");
+        fs::write(&path, SAMPLE_DATABASE).unwrap();
+        let mut db = Database::new(path.clone(), None).unwrap();
+        assert_eq!(db.stale_warning(), None);
+
+        fs::write(&path, format!("{}P0420,Catalyst Efficiency Below Threshold,Medium,Engine,,,,,,\n", SAMPLE_DATABASE)).unwrap();
+        let warning = db.stale_warning();
+        let again = db.stale_warning();
+        let stale = db.is_stale();
+        db.reload().unwrap();
+        let reloaded = (db.stale_warning(), db.is_stale(), db.lookup("P0420".to_string()).is_some());
+
+        assert!(warning.is_some_and(|warning| warning.contains("codes.csv") && warning.contains("reload()")));
+        assert_eq!(again, None);
+        assert!(stale);
+        assert_eq!(reloaded, (None, false, true));
+    }
+
+    #[test]
+    fn keywords_sharing_a_cache_entry_are_searched_alike() {
+        let dir = TempDir::new("node-search");
+        let path = dir.file("codes.csv");
+        fs::write(&path, SAMPLE_DATABASE).unwrap();
+        let cached = Database::new(path.clone(), None).unwrap();
+        let uncached = Database::new(path, Some(DatabaseOptions { labor_guide: None, lang: None, cache_size: Some(0), cache_ttl_secs: None })).unwrap();
+
+        let codes = |page: JsSearchPage| page.results.into_iter().map(|error| error.code).collect::<Vec<_>>();
+        for keyword in ["  Wheel   SPEED ", "wheel speed"] {
+            assert_eq!(codes(cached.search(keyword.to_string(), None, None)), codes(uncached.search(keyword.to_string(), None, None)), "{:?}", keyword);
+        }
+        assert_eq!(cached.cache_stats().hits, 1);
+    }
+
+    #[test]
+    fn node_database_reports() {
+        init();
+        let db = Database::new(crate::DATABASE_FILE.to_string(), None).unwrap();
+        assert_eq!(db.lookup("p0300".to_string()).unwrap().possible_causes[0], "Spark plug issues");
+        assert_eq!(db.search("misfire".to_string(), None, Some(2)).results.len(), 2);
+        let options = ReportOptions { html: Some(true), customer: Some("J. Smith".to_string()), repair_order: None, odometer: None };
+        assert!(db.report("P0300".to_string(), Some(options)).unwrap().contains("J. Smith"));
+        assert!(db.report("P9999".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn node_database_notices_when_the_file_is_edited_under_it() {
+        init();
+        let dir = TempDir::new("stale");
+        let path = dir.file("codes.csv");
+        fs::write(&path, SAMPLE_DATABASE).unwrap();
+        let mut db = Database::new(path.clone(), None).unwrap();
+        assert!(!db.is_stale());
+
+        // Touching the file without changing it is not a change
+        fs::write(&path, SAMPLE_DATABASE).unwrap();
+        assert!(!db.is_stale());
+        fs::write(&path, SAMPLE_DATABASE.replace("Random/Multiple", "Multiple")).unwrap();
+        assert!(db.is_stale());
+        assert_eq!(db.lookup("P0300".to_string()).unwrap().description, "Random/Multiple Cylinder Misfire Detected");
+        db.reload().unwrap();
+        assert!(!db.is_stale());
+        assert_eq!(db.lookup("P0300".to_string()).unwrap().description, "Multiple Cylinder Misfire Detected");
+
+        fs::remove_file(&path).unwrap();
+        assert!(db.is_stale());
+    }
+}
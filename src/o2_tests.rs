@@ -0,0 +1,330 @@
+// Oxygen sensor and catalyst monitor test results.
+//
+// Older protocols report oxygen sensor tests through mode 05: one request
+// per test and sensor, with 8-bit values. CAN vehicles moved these results
+// to mode 06 ("on-board monitoring"), where each monitor ID (MID) carries
+// several tests with 16-bit values, limits and a unit-and-scaling ID. Both
+// are decoded into the same list of results, grouped by bank and sensor, and
+// each failed test is tied to the code the engine computer sets for it.
+use std::error::Error;
+use crate::adapter::Adapter;
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+use crate::DiagnosticsDatabase;
+
+// What a group of tests belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Sensor { bank: u8, sensor: u8 },
+    Catalyst { bank: u8 },
+}
+
+impl Location {
+    fn label(&self) -> String {
+        match *self {
+            Location::Sensor { bank, sensor } => tr!("o2-location-sensor", bank = bank, sensor = sensor),
+            Location::Catalyst { bank } => tr!("o2-location-catalyst", bank = bank),
+        }
+    }
+}
+
+// Values compared with the limits: voltages, times or plain counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Voltage,
+    Time,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub location: Location,
+    pub test: u8,
+    pub value: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub unit: &'static str,
+}
+
+impl TestResult {
+    // None when the test has no limits to compare with
+    pub fn passed(&self) -> Option<bool> {
+        match (self.min, self.max) {
+            (None, None) => None,
+            (min, max) => Some(min.is_none_or(|min| self.value >= min) && max.is_none_or(|max| self.value <= max)),
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self.unit {
+            "V" => Kind::Voltage,
+            "s" => Kind::Time,
+            _ => Kind::Other,
+        }
+    }
+
+    // The code set when this test fails, e.g. P0133 for a slow bank 1 sensor 1
+    pub fn related_code(&self) -> Option<String> {
+        if self.passed() != Some(false) {
+            return None;
+        }
+        match self.location {
+            Location::Catalyst { bank } if bank <= 2 => Some(format!("P04{}0", bank + 1)),
+            Location::Sensor { bank, sensor } if bank <= 2 && sensor <= 3 => {
+                let base = 130 + u32::from(bank - 1) * 20 + u32::from(sensor - 1) * 6;
+                let offset = match self.kind() {
+                    Kind::Voltage if self.min.is_some_and(|min| self.value < min) => 1,
+                    Kind::Voltage => 2,
+                    Kind::Time | Kind::Other => 3,
+                };
+                Some(format!("P0{}", base + offset))
+            },
+            _ => None,
+        }
+    }
+}
+
+// Names of the standardized test IDs
+fn test_name(test: u8) -> String {
+    match test {
+        0x01 => tr!("o2-test-rich-lean-threshold"),
+        0x02 => tr!("o2-test-lean-rich-threshold"),
+        0x03 => tr!("o2-test-low-switch-voltage"),
+        0x04 => tr!("o2-test-high-switch-voltage"),
+        0x05 => tr!("o2-test-rich-lean-time"),
+        0x06 => tr!("o2-test-lean-rich-time"),
+        0x07 => tr!("o2-test-min-voltage"),
+        0x08 => tr!("o2-test-max-voltage"),
+        0x09 => tr!("o2-test-transition-time"),
+        0x0A => tr!("o2-test-period"),
+        _ => tr!("o2-test-manufacturer", test = format!("${:02X}", test)),
+    }
+}
+
+// Bank and sensor for mode 05 sensor numbers and mode 06 MIDs $01-$08
+fn sensor_location(number: u8) -> Location {
+    Location::Sensor { bank: (number - 1) / 4 + 1, sensor: (number - 1) % 4 + 1 }
+}
+
+// Mode 05 scaling by test ID: voltages in 5 mV steps, times in 4 or 40 ms steps
+fn mode05_scale(test: u8) -> (f64, &'static str) {
+    match test {
+        0x05 | 0x06 => (0.004, "s"),
+        0x09 | 0x0A => (0.04, "s"),
+        0x01..=0x08 => (0.005, "V"),
+        _ => (1.0, ""),
+    }
+}
+
+// Mode 06 unit and scaling IDs used by oxygen sensor and catalyst monitors
+fn mode06_scale(uasid: u8) -> (f64, &'static str, bool) {
+    match uasid {
+        0x0A => (0.000122, "V", false),
+        0x0B => (0.001, "V", false),
+        0x0C => (0.01, "V", false),
+        0x10 => (0.001, "s", false),
+        0x11 => (0.1, "s", false),
+        0x12 => (1.0, "s", false),
+        0x8A => (0.000122, "V", true),
+        0x8B => (0.001, "V", true),
+        0x90 => (0.001, "s", true),
+        uasid => (1.0, "", uasid & 0x80 != 0),
+    }
+}
+
+// Decode a mode 05 answer: 45 TID sensor value [min max]
+pub fn decode_mode05(data: &[u8]) -> Option<TestResult> {
+    let [0x45, test, sensor, value, ref limits @ ..] = *data else { return None };
+    if !(1..=8).contains(&sensor) {
+        return None;
+    }
+    let (scale, unit) = mode05_scale(test);
+    let limit = |byte: Option<&u8>| byte.map(|&byte| f64::from(byte) * scale);
+    Some(TestResult {
+        location: sensor_location(sensor),
+        test,
+        value: f64::from(value) * scale,
+        min: limit(limits.first()),
+        max: limit(limits.get(1)),
+        unit,
+    })
+}
+
+// Decode a mode 06 answer: 46 followed by 9-byte records of MID TID UASID value min max
+pub fn decode_mode06(data: &[u8]) -> Vec<TestResult> {
+    let Some((&0x46, records)) = data.split_first() else { return Vec::new() };
+    records
+        .chunks_exact(9)
+        .filter_map(|record| {
+            let location = match record[0] {
+                mid @ 0x01..=0x08 => sensor_location(mid),
+                0x21 => Location::Catalyst { bank: 1 },
+                0x22 => Location::Catalyst { bank: 2 },
+                _ => return None,
+            };
+            let (scale, unit, signed) = mode06_scale(record[2]);
+            let number = |high: u8, low: u8| match signed {
+                true => f64::from(i16::from_be_bytes([high, low])) * scale,
+                false => f64::from(u16::from_be_bytes([high, low])) * scale,
+            };
+            Some(TestResult {
+                location,
+                test: record[1],
+                value: number(record[3], record[4]),
+                min: Some(number(record[5], record[6])),
+                max: Some(number(record[7], record[8])),
+                unit,
+            })
+        })
+        .collect()
+}
+
+// MIDs a vehicle supports from a bitmap answer to 06 00, 06 20, ...
+fn supported(data: &[u8], base: u8) -> Vec<u8> {
+    let Some(bits) = data.get(2..6) else { return Vec::new() };
+    let bits = u32::from_be_bytes([bits[0], bits[1], bits[2], bits[3]]);
+    (1..=32u8).filter(|n| bits & (1 << (32 - u32::from(*n))) != 0).map(|n| base + n).collect()
+}
+
+// Read every oxygen sensor and catalyst test result the vehicle keeps
+pub fn read(adapter: &mut Adapter) -> Result<Vec<TestResult>, Box<dyn Error>> {
+    let mut results = Vec::new();
+    if adapter.uses_can() && !adapter.capabilities.mode06 {
+        return Err(tr!("o2-no-mode06").into());
+    }
+    if adapter.uses_can() {
+        let mut mids = Vec::new();
+        for base in [0x00, 0x20] {
+            for response in adapter.request(&format!("06{:02X}", base))? {
+                if response.data.get(1) == Some(&base) {
+                    mids.extend(supported(&response.data, base));
+                }
+            }
+        }
+        mids.sort();
+        mids.dedup();
+        for mid in mids.into_iter().filter(|mid| matches!(mid, 0x01..=0x08 | 0x21 | 0x22)) {
+            for response in adapter.request(&format!("06{:02X}", mid))? {
+                results.extend(decode_mode06(&response.data));
+            }
+        }
+    } else {
+        for sensor in 1..=8u8 {
+            for test in 1..=0x0Au8 {
+                let responses = adapter.request(&format!("05{:02X}{:02X}", test, sensor))?;
+                let found: Vec<TestResult> = responses.iter().filter_map(|response| decode_mode05(&response.data)).collect();
+                // A sensor that does not answer the first test is not fitted
+                if test == 1 && found.is_empty() {
+                    break;
+                }
+                results.extend(found);
+            }
+        }
+    }
+    Ok(results)
+}
+
+fn format_value(value: f64, unit: &str) -> String {
+    match unit {
+        "V" => format!("{:.3} V", value),
+        "s" => format!("{:.3} s", value),
+        _ => format!("{}", value),
+    }
+}
+
+// Print the results per bank and sensor, then the codes the failures point to
+pub fn display(results: &[TestResult], db: &DiagnosticsDatabase) {
+    if results.is_empty() {
+        outln!("{}", tr!("o2-none"));
+        return;
+    }
+    outln!("{}", tr!("o2-header").styled(Style::Heading));
+    let mut locations: Vec<Location> = Vec::new();
+    for result in results {
+        if !locations.contains(&result.location) {
+            locations.push(result.location);
+        }
+    }
+    for location in locations {
+        outln!();
+        outln!("{}", location.label().styled(Style::Section));
+        for result in results.iter().filter(|result| result.location == location) {
+            let limits = match (result.min, result.max) {
+                (Some(min), Some(max)) => format!("{} - {}", format_value(min, result.unit), format_value(max, result.unit)),
+                (Some(min), None) => format!(">= {}", format_value(min, result.unit)),
+                (None, Some(max)) => format!("<= {}", format_value(max, result.unit)),
+                (None, None) => String::new(),
+            };
+            let verdict = match result.passed() {
+                Some(true) => tr!("o2-pass").styled(Style::Success),
+                Some(false) => tr!("o2-fail").styled(Style::Error),
+                None => "-".styled(Style::Muted),
+            };
+            outln!("  {:<36} {:>10}  {:<22} {}", test_name(result.test), format_value(result.value, result.unit), limits, verdict);
+        }
+    }
+
+    let mut related: Vec<String> = Vec::new();
+    for code in results.iter().filter_map(TestResult::related_code) {
+        if !related.contains(&code) {
+            related.push(code);
+        }
+    }
+    outln!();
+    if related.is_empty() {
+        outln!("{}", tr!("o2-all-passed").styled(Style::Success));
+        return;
+    }
+    outln!("{}", tr!("o2-related").styled(Style::Section));
+    for code in related {
+        let description = db.lookup_error(&code).map(|error| error.description.clone()).unwrap_or_else(|| generic_title(&code));
+        outln!("  {}  {}", code.styled(Style::Code), description);
+    }
+}
+
+// The SAE title of a code from `related_code`, for codes missing from the database
+fn generic_title(code: &str) -> String {
+    let Ok(number) = code.trim_start_matches('P').parse::<u32>() else { return String::new() };
+    if number >= 400 {
+        return tr!("o2-code-catalyst", bank = (number - 410) / 10);
+    }
+    let index = number - 130;
+    let (bank, sensor) = (index / 20 + 1, index % 20 / 6 + 1);
+    match index % 20 % 6 {
+        1 => tr!("o2-code-low", bank = bank, sensor = sensor),
+        2 => tr!("o2-code-high", bank = bank, sensor = sensor),
+        _ => tr!("o2-code-slow", bank = bank, sensor = sensor),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::adapter::Adapter;
+    use crate::testing::{init, FakeElm};
+    use super::Location;
+
+    #[test]
+    fn o2_tests_decode_mode_06_and_point_to_codes() {
+        init();
+        // B1S1 rich to lean switch time 200 ms against 0-150 ms; catalyst bank 1 count 80 against 0-64
+        let elm = FakeElm::car()
+            .reply("0600", "7E8064600C0000001")
+            .reply("0620", "7E806462080000000")
+            .reply("0601", "7E8100A4601051000C8\r7E82100000096000000")
+            .reply("0602", "NO DATA")
+            .reply("0621", "7E8100A462180240050\r7E82100000040000000");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        let results = super::read(&mut adapter).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].location, Location::Sensor { bank: 1, sensor: 1 });
+        assert!((results[0].value - 0.2).abs() < 1e-9);
+        let codes: Vec<String> = results.iter().filter_map(|result| result.related_code()).collect();
+        assert_eq!(codes, ["P0133", "P0420"]);
+
+        let legacy = super::decode_mode05(&[0x45, 0x01, 0x05, 0x5A]).unwrap();
+        assert_eq!(legacy.location, Location::Sensor { bank: 2, sensor: 1 });
+        assert_eq!(legacy.passed(), None);
+    }
+}
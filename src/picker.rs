@@ -0,0 +1,142 @@
+// Find-as-you-type code picker (`pick`), in the spirit of fzf.
+//
+// Every keystroke filters all codes again: the query is split into
+// fragments, and a code is kept when each fragment appears, in order but not
+// necessarily together, in "CODE description". Matches at the start of a
+// word and runs of consecutive characters rank higher, so "p03" finds the
+// P03xx misfire codes first and "cat eff" finds "Catalyst ... Efficiency".
+// Up and down move the selection, Enter shows it and Escape or Ctrl-C leave
+// without choosing. Without the tui feature `pick` only says so.
+use std::error::Error;
+#[cfg(feature = "tui")]
+use console::{Key, Term};
+use crate::i18n::tr;
+#[cfg(feature = "tui")]
+use crate::presentation::{Style, Styled};
+use crate::{DiagnosticsDatabase, ErrorCode};
+
+// Most matches listed under the query line
+#[cfg(feature = "tui")]
+const MAX_ROWS: usize = 10;
+
+// Score of `pattern` as a case-insensitive subsequence of `text`; None when it is not one
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn fuzzy_score(text: &str, pattern: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in pattern.chars().flat_map(char::to_lowercase) {
+        let found = position + text[position..].iter().position(|c| *c == wanted)?;
+        score += 16;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 12;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        score -= previous.map(|previous| (found - previous - 1).min(8) as i64).unwrap_or(found.min(8) as i64);
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+// The codes matching every fragment of the query, best first; every code, sorted, for an empty query
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn filter<'a>(db: &'a DiagnosticsDatabase, query: &str) -> Vec<&'a ErrorCode> {
+    let mut matches: Vec<(i64, &ErrorCode)> = db.records()
+        .filter_map(|error| {
+            let haystack = format!("{} {}", error.code, error.description);
+            let scores: Option<Vec<i64>> = query.split_whitespace().map(|fragment| fuzzy_score(&haystack, fragment)).collect();
+            scores.map(|scores| (scores.iter().sum(), error))
+        })
+        .collect();
+    matches.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then_with(|| a.code.cmp(&b.code)));
+    matches.into_iter().map(|(_, error)| error).collect()
+}
+
+// Draw the query line and the visible matches; returns the number of lines written
+#[cfg(feature = "tui")]
+fn render(term: &Term, query: &str, matches: &[&ErrorCode], total: usize, selected: usize, rows: usize) -> std::io::Result<usize> {
+    let width = term.size().1 as usize;
+    term.write_line(&format!("{} {}{}", tr!("pick-prompt").styled(Style::Hint), query, "_".styled(Style::Muted)))?;
+    let first = selected.saturating_sub(rows - 1);
+    for (index, error) in matches.iter().enumerate().skip(first).take(rows) {
+        let line: String = format!("{:<7} {}", error.code, error.description).chars().take(width.saturating_sub(2)).collect();
+        match index == selected {
+            true => term.write_line(&format!("{} {}", ">".styled(Style::Hint), line.styled(Style::Match)))?,
+            false => term.write_line(&format!("  {}", line))?,
+        }
+    }
+    term.write_line(&tr!("pick-count", matches = matches.len(), total = total).styled(Style::Muted).to_string())?;
+    Ok(matches.len().saturating_sub(first).min(rows) + 2)
+}
+
+// Let the user pick a code; None when they cancel
+#[cfg(feature = "tui")]
+pub fn pick(db: &DiagnosticsDatabase) -> Result<Option<&ErrorCode>, Box<dyn Error>> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return Err(tr!("pick-needs-terminal").into());
+    }
+    let rows = MAX_ROWS.min((term.size().0 as usize).saturating_sub(3)).max(1);
+    let mut query = String::new();
+    let mut selected = 0;
+    let mut matches = filter(db, &query);
+    term.hide_cursor()?;
+
+    let choice = loop {
+        let drawn = render(&term, &query, &matches, db.errors.len(), selected, rows)?;
+        let key = term.read_key_raw();
+        term.clear_last_lines(drawn)?;
+        // A terminal that stops answering cancels the pick
+        let Ok(key) = key else { break None };
+        match key {
+            Key::Enter => break matches.get(selected).copied(),
+            Key::Escape | Key::CtrlC => break None,
+            Key::ArrowUp => selected = selected.saturating_sub(1),
+            Key::ArrowDown => selected = (selected + 1).min(matches.len().saturating_sub(1)),
+            Key::Backspace => {
+                query.pop();
+                matches = filter(db, &query);
+                selected = 0;
+            },
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                matches = filter(db, &query);
+                selected = 0;
+            },
+            _ => {},
+        }
+    };
+    term.show_cursor()?;
+    Ok(choice)
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn pick(_db: &DiagnosticsDatabase) -> Result<Option<&ErrorCode>, Box<dyn Error>> {
+    Err(tr!("feature-missing", feature = "tui").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::sample_database;
+    use super::{filter, fuzzy_score};
+
+    #[test]
+    fn picker_filters_by_fuzzy_fragments() {
+        assert!(fuzzy_score("Catalyst Efficiency", "cateff").is_some());
+        assert!(fuzzy_score("Catalyst", "tac").is_none());
+        assert!(fuzzy_score("misfire", "mis") > fuzzy_score("m-i-s", "mis"));
+
+        let db = sample_database();
+        let codes = |query: &str| filter(&db, query).iter().map(|error| error.code.as_str()).collect::<Vec<_>>();
+        assert_eq!(codes(""), ["B0001", "C0035", "P0101", "P0300"]);
+        assert_eq!(codes("air bag"), ["B0001"]);
+        assert_eq!(codes("p03"), ["P0300"]);
+        let mut sensors = codes("sensor")[..2].to_vec();
+        sensors.sort();
+        assert_eq!(sensors, ["C0035", "P0101"]);
+    }
+}
@@ -0,0 +1,98 @@
+// Mode 01 parameters (PIDs) that can be read live from the engine computer.
+//
+// Each entry knows its SAE J1979 number, unit and how to turn the data
+// bytes of a response into a value, and back for the emulator. Names are
+// short and stable because they are used in cars-scanner.toml and on the
+// command line.
+
+pub struct Pid {
+    pub name: &'static str,
+    pub number: u8,
+    pub unit: &'static str,
+    decode: fn(&[u8]) -> f64,
+    #[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+    encode: fn(f64) -> Vec<u8>,
+}
+
+fn a(data: &[u8]) -> f64 {
+    f64::from(data.first().copied().unwrap_or_default())
+}
+
+fn ab(data: &[u8]) -> f64 {
+    a(data) * 256.0 + f64::from(data.get(1).copied().unwrap_or_default())
+}
+
+fn percent(data: &[u8]) -> f64 {
+    a(data) * 100.0 / 255.0
+}
+
+fn temperature(data: &[u8]) -> f64 {
+    a(data) - 40.0
+}
+
+fn fuel_trim(data: &[u8]) -> f64 {
+    (a(data) - 128.0) * 100.0 / 128.0
+}
+
+fn to_a(value: f64) -> Vec<u8> {
+    vec![value.round().clamp(0.0, 255.0) as u8]
+}
+
+fn to_ab(value: f64) -> Vec<u8> {
+    (value.round().clamp(0.0, 65535.0) as u16).to_be_bytes().to_vec()
+}
+
+fn to_percent(value: f64) -> Vec<u8> {
+    to_a(value * 255.0 / 100.0)
+}
+
+fn to_temperature(value: f64) -> Vec<u8> {
+    to_a(value + 40.0)
+}
+
+fn to_fuel_trim(value: f64) -> Vec<u8> {
+    to_a(value * 128.0 / 100.0 + 128.0)
+}
+
+pub const PIDS: &[Pid] = &[
+    Pid { name: "load", number: 0x04, unit: "%", decode: percent, encode: to_percent },
+    Pid { name: "coolant", number: 0x05, unit: "°C", decode: temperature, encode: to_temperature },
+    Pid { name: "stft1", number: 0x06, unit: "%", decode: fuel_trim, encode: to_fuel_trim },
+    Pid { name: "ltft1", number: 0x07, unit: "%", decode: fuel_trim, encode: to_fuel_trim },
+    Pid { name: "stft2", number: 0x08, unit: "%", decode: fuel_trim, encode: to_fuel_trim },
+    Pid { name: "ltft2", number: 0x09, unit: "%", decode: fuel_trim, encode: to_fuel_trim },
+    Pid { name: "map", number: 0x0B, unit: "kPa", decode: a, encode: to_a },
+    Pid { name: "rpm", number: 0x0C, unit: "rpm", decode: |data| ab(data) / 4.0, encode: |value| to_ab(value * 4.0) },
+    Pid { name: "speed", number: 0x0D, unit: "km/h", decode: a, encode: to_a },
+    Pid { name: "intake", number: 0x0F, unit: "°C", decode: temperature, encode: to_temperature },
+    Pid { name: "maf", number: 0x10, unit: "g/s", decode: |data| ab(data) / 100.0, encode: |value| to_ab(value * 100.0) },
+    Pid { name: "throttle", number: 0x11, unit: "%", decode: percent, encode: to_percent },
+    Pid { name: "mil_distance", number: 0x21, unit: "km", decode: ab, encode: to_ab },
+    Pid { name: "fuel", number: 0x2F, unit: "%", decode: percent, encode: to_percent },
+    Pid { name: "clear_distance", number: 0x31, unit: "km", decode: ab, encode: to_ab },
+    Pid { name: "voltage", number: 0x42, unit: "V", decode: |data| ab(data) / 1000.0, encode: |value| to_ab(value * 1000.0) },
+    Pid { name: "mil_time", number: 0x4D, unit: "min", decode: ab, encode: to_ab },
+    Pid { name: "clear_time", number: 0x4E, unit: "min", decode: ab, encode: to_ab },
+    Pid { name: "oil", number: 0x5C, unit: "°C", decode: temperature, encode: to_temperature },
+];
+
+pub fn find(name: &str) -> Option<&'static Pid> {
+    PIDS.iter().find(|pid| pid.name.eq_ignore_ascii_case(name))
+}
+
+impl Pid {
+    // The mode 01 request for this PID, e.g. "0105"
+    pub fn request(&self) -> String {
+        format!("01{:02X}", self.number)
+    }
+
+    pub fn decode(&self, data: &[u8]) -> f64 {
+        (self.decode)(data)
+    }
+
+    // The data bytes reporting `value`, rounded and clamped to what the PID can carry
+    #[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+    pub fn encode(&self, value: f64) -> Vec<u8> {
+        (self.encode)(value)
+    }
+}
@@ -0,0 +1,168 @@
+// Severities a fleet rates differently from the shared database (`severity_policy`).
+//
+// A delivery fleet shrugs at a loose fuel cap but cannot have a van drop off
+// the network. A policy file, named by severity_policy in cars-scanner.toml,
+// holds the fleet's own ratings as rules:
+//
+//   [[rule]]
+//   codes = ["P044*", "P045*"]      # EVAP; * matches any characters
+//   severity = "Low"
+//   reason = "EVAP faults do not take a van off the road"
+//
+//   [[rule]]
+//   system = "Network"
+//   severity = "High"
+//
+// A rule matches by code pattern, by system, or by both; the first rule
+// that matches a code decides its severity. Rules are applied to the codes
+// as they are loaded, so every lookup, list and report sees the fleet's
+// severity, and the code records the one it replaced: wherever it is shown
+// in full, it says the severity was adjusted by policy, and why. The
+// database and packs are never changed, and the `db` commands that edit or
+// compare them work with the severities as written.
+use std::error::Error;
+use std::fs;
+use std::sync::Arc;
+use serde::Deserialize;
+use crate::{DiagnosticsDatabase, SEVERITIES};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(default)]
+    pub codes: Vec<String>,
+    pub system: Option<String>,
+    pub severity: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeverityPolicy {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+// A severity set by policy: the one the database gives, and why it was changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adjustment {
+    pub original: String,
+    pub reason: Option<String>,
+}
+
+// Whether `code` fits `pattern`, where * stands for any characters
+fn matches_pattern(pattern: &str, code: &str) -> bool {
+    let pattern = pattern.to_uppercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = code.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (index, part) in parts.iter().enumerate() {
+        if index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+impl Rule {
+    fn matches(&self, code: &str, system: &str) -> bool {
+        (!self.codes.is_empty() || self.system.is_some())
+            && (self.codes.is_empty() || self.codes.iter().any(|pattern| matches_pattern(pattern, code)))
+            && self.system.as_ref().is_none_or(|wanted| wanted.eq_ignore_ascii_case(system))
+    }
+}
+
+impl SeverityPolicy {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read severity policy {}: {}", path, e))?;
+        let mut policy: SeverityPolicy = toml::from_str(&content).map_err(|e| format!("Invalid severity policy {}: {}", path, e))?;
+        for (index, rule) in policy.rules.iter_mut().enumerate() {
+            let Some(severity) = SEVERITIES.iter().find(|severity| severity.eq_ignore_ascii_case(&rule.severity)) else {
+                return Err(format!(
+                    "Invalid severity policy {}: rule {} has severity '{}'; use one of {}",
+                    path, index + 1, rule.severity, SEVERITIES.join(", ")
+                ).into());
+            };
+            if rule.codes.is_empty() && rule.system.is_none() {
+                return Err(format!("Invalid severity policy {}: rule {} needs codes, a system or both", path, index + 1).into());
+            }
+            rule.severity = severity.to_string();
+        }
+        Ok(policy)
+    }
+
+    // The first rule that matches a code
+    pub fn rule_for(&self, code: &str, system: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(code, system))
+    }
+
+    // Give every matching code the fleet's severity; returns how many changed
+    pub fn apply(&self, db: &mut DiagnosticsDatabase) -> usize {
+        let mut adjusted = 0;
+        for error in db.errors.values_mut() {
+            let Some(rule) = self.rule_for(&error.code, &error.system) else { continue };
+            if rule.severity.eq_ignore_ascii_case(&error.severity) {
+                continue;
+            }
+            // Only the adjusted codes are copied; the others stay shared with their definitions
+            let error = Arc::make_mut(error);
+            let original = std::mem::replace(&mut error.severity, rule.severity.clone());
+            error.adjusted = Some(Adjustment { original, reason: rule.reason.clone() });
+            adjusted += 1;
+        }
+        adjusted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::DiagnosticsDatabase;
+    use crate::testing::{init, sample_database, TempDir};
+    use super::SeverityPolicy;
+
+    #[test]
+    fn severity_policy_rates_codes_for_the_fleet_without_changing_the_database() {
+        init();
+        let dir = TempDir::new("policy");
+        let path = dir.join("policy.toml");
+        fs::write(&path, "\
+[[rule]]
+codes = [\"p03*\"]
+severity = \"low\"
+reason = \"Misfires are logged, not acted on\"
+
+[[rule]]
+system = \"ABS\"
+severity = \"High\"
+
+[[rule]]
+codes = [\"P0*\"]
+severity = \"Critical\"
+").unwrap();
+        let policy = SeverityPolicy::load(path.to_str().unwrap()).unwrap();
+
+        let mut db = sample_database();
+        assert_eq!(policy.apply(&mut db), 3);
+        let severity = |db: &DiagnosticsDatabase, code: &str| db.lookup_error(code).unwrap().severity.clone();
+        assert_eq!(severity(&db, "P0300"), "Low");
+        assert_eq!(severity(&db, "C0035"), "High");
+        assert_eq!(severity(&db, "P0101"), "Critical");
+        assert_eq!(severity(&db, "B0001"), "Critical");
+        assert!(db.lookup_error("B0001").unwrap().adjusted.is_none());
+        assert!(db.lookup_error("P0300").unwrap().to_text().contains("Severity: Low (adjusted by policy from High: Misfires are logged, not acted on)"));
+        assert!(db.lookup_error("C0035").unwrap().to_text().contains("Severity: High (adjusted by policy from Low)\n"));
+        assert_eq!(severity(&sample_database(), "P0300"), "High");
+
+        fs::write(&path, "[[rule]]\ncodes = [\"U*\"]\nseverity = \"Severe\"\n").unwrap();
+        let error = SeverityPolicy::load(path.to_str().unwrap()).unwrap_err().to_string();
+        assert!(error.contains("rule 1 has severity 'Severe'"));
+        fs::write(&path, "[[rule]]\nseverity = \"Low\"\n").unwrap();
+        assert!(SeverityPolicy::load(path.to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+// Machine-readable output for shell pipelines, selected with --porcelain.
+//
+// Every result is printed as one line of tab-separated fields, without
+// colors, headings, counts or hints. The first field names the record type:
+//
+//   code     code, severity, system, description, safety warnings (joined by " | ")
+//   missing  code
+//   part     code, part name, OEM part number (may be empty)
+//   spn      SPN, FMI
+//   setting  key, value, origin
+//
+// This format is a promise to scripts: within a major version a record keeps
+// its type and field order, and new fields are only ever added at the end.
+// Tabs and line breaks inside a field are replaced by spaces.
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::progress;
+use crate::ErrorCode;
+
+// Set by --porcelain
+static PORCELAIN: AtomicBool = AtomicBool::new(false);
+
+// Switch to porcelain output: no colors and no progress on stderr either
+pub fn enable() {
+    PORCELAIN.store(true, Ordering::Relaxed);
+    colored::control::set_override(false);
+    progress::disable_progress();
+}
+
+pub fn enabled() -> bool {
+    PORCELAIN.load(Ordering::Relaxed)
+}
+
+// One record with its fields joined by tabs
+pub fn line(kind: &str, fields: &[&str]) -> String {
+    let mut line = String::from(kind);
+    for field in fields {
+        line.push('\t');
+        line.extend(field.chars().map(|c| if matches!(c, '\t' | '\n' | '\r') { ' ' } else { c }));
+    }
+    line
+}
+
+pub fn code(error: &ErrorCode) -> String {
+    let warnings = crate::pipe_list(&error.safety_warnings).collect::<Vec<_>>().join(" | ");
+    line("code", &[&error.code, &error.severity, &error.system, &error.description, &warnings])
+}
+
+pub fn missing(code: &str) -> String {
+    line("missing", &[code])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::sample_database;
+    use crate::ErrorCode;
+
+    #[test]
+    fn porcelain_records_are_single_tab_separated_lines() {
+        let db = sample_database();
+        assert_eq!(super::code(db.lookup_error("P0300").unwrap()), "code\tP0300\tHigh\tEngine\tRandom/Multiple Cylinder Misfire Detected\t");
+        assert_eq!(super::missing("P9999"), "missing\tP9999");
+
+        let mut error = ErrorCode::fixture_with("P0101", "Medium", "Engine");
+        error.description = "Line one\nline\ttwo".to_string();
+        assert_eq!(super::code(&error), "code\tP0101\tMedium\tEngine\tLine one line two\t");
+    }
+}
@@ -0,0 +1,337 @@
+// Semantic text styles shared by every frontend.
+//
+// Output code asks for a style by meaning (`Style::Heading`, `Style::Code`)
+// instead of naming a color, so the terminal and HTML renderings stay in step
+// and a theme only has to change this file. Terminal colors come from
+// `colored`, which already honors NO_COLOR, CLICOLOR and CLICOLOR_FORCE.
+//
+// The colorblind theme (`--theme colorblind` or `theme = "colorblind"`)
+// replaces red and green with blue, orange and vermillion from the Okabe-Ito
+// palette, and prefixes every severity with a marker from [-] to [!!!], so
+// the level can be read without telling colors apart at all.
+//
+// HTML reports open in the reader's color scheme and have a button to switch
+// between light and dark, remembered by the browser; printing always uses
+// black on white, with each code starting on a new page.
+use std::fmt::Display;
+use std::sync::OnceLock;
+use clap::ValueEnum;
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Default,
+    // Colors that stay distinct with red-green color blindness, and severity markers
+    Colorblind,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+// Choose the theme; the first choice wins, so --theme overrides the configuration
+pub fn set_theme(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+pub fn theme() -> Theme {
+    THEME.get().copied().unwrap_or_default()
+}
+
+// The marker for a severity level in the colorblind theme
+pub fn severity_marker(severity: &str) -> Option<&'static str> {
+    match severity.to_ascii_lowercase().as_str() {
+        "low" => Some("[-]"),
+        "medium" => Some("[!]"),
+        "high" => Some("[!!]"),
+        "critical" => Some("[!!!]"),
+        _ => None,
+    }
+}
+
+// A severity as shown to people: with its marker when the theme asks for one
+pub fn severity_text(severity: &str) -> String {
+    severity_text_in(theme(), severity)
+}
+
+// As `severity_text`, for a given theme
+pub fn severity_text_in(theme: Theme, severity: &str) -> String {
+    match (theme, severity_marker(severity)) {
+        (Theme::Colorblind, Some(marker)) => format!("{} {}", marker, severity),
+        _ => severity.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    // No styling
+    Plain,
+    // Banners, dividers and summary lines
+    Banner,
+    // Headings of reports and lists
+    Heading,
+    // Section headings within a record
+    Section,
+    // Field labels such as "Severity:"
+    Label,
+    // Error codes and part numbers
+    Code,
+    // Systems, operation codes and other categories
+    Category,
+    SeverityLow,
+    SeverityMedium,
+    SeverityHigh,
+    SeverityCritical,
+    Success,
+    Warning,
+    Error,
+    // Hints such as suggested fixes and the prompt
+    Hint,
+    // Command names in help text
+    Command,
+    // Command arguments in help text
+    Argument,
+    Link,
+    // Secondary details
+    Muted,
+    // Search matches
+    Match,
+}
+
+impl Style {
+    // The style for a severity level, if the level is known
+    pub fn for_severity(severity: &str) -> Option<Self> {
+        match severity.to_ascii_lowercase().as_str() {
+            "low" => Some(Style::SeverityLow),
+            "medium" => Some(Style::SeverityMedium),
+            "high" => Some(Style::SeverityHigh),
+            "critical" => Some(Style::SeverityCritical),
+            _ => None,
+        }
+    }
+
+    // Render text for the terminal
+    pub fn paint(self, text: &str) -> ColoredString {
+        self.paint_in(theme(), text)
+    }
+
+    // As `paint`, for a given theme
+    pub fn paint_in(self, theme: Theme, text: &str) -> ColoredString {
+        if theme == Theme::Colorblind {
+            match self {
+                Style::SeverityLow | Style::Success | Style::Command => return text.bright_blue(),
+                Style::SeverityHigh | Style::Error => return text.bright_magenta(),
+                Style::SeverityCritical => return text.on_magenta().bright_white().bold(),
+                _ => {},
+            }
+        }
+        match self {
+            Style::Plain => text.normal(),
+            Style::Banner => text.bright_blue(),
+            Style::Heading | Style::Label | Style::Argument | Style::Warning => text.bright_yellow(),
+            Style::Section | Style::Category | Style::Hint => text.bright_cyan(),
+            Style::Code => text.bright_white(),
+            Style::SeverityLow | Style::Success | Style::Command => text.bright_green(),
+            Style::SeverityMedium => text.bright_yellow(),
+            Style::SeverityHigh | Style::Error => text.bright_red(),
+            Style::SeverityCritical => text.on_red().bright_white(),
+            Style::Link => text.underline(),
+            Style::Muted => text.dimmed(),
+            Style::Match => text.black().on_bright_yellow(),
+        }
+    }
+
+    // CSS class used for this style in HTML output
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Style::Plain => "plain",
+            Style::Banner => "banner",
+            Style::Heading => "heading",
+            Style::Section => "section",
+            Style::Label => "label",
+            Style::Code => "code",
+            Style::Category => "category",
+            Style::SeverityLow => "severity-low",
+            Style::SeverityMedium => "severity-medium",
+            Style::SeverityHigh => "severity-high",
+            Style::SeverityCritical => "severity-critical",
+            Style::Success => "success",
+            Style::Warning => "warning",
+            Style::Error => "error",
+            Style::Hint => "hint",
+            Style::Command => "command",
+            Style::Argument => "argument",
+            Style::Link => "link",
+            Style::Muted => "muted",
+            Style::Match => "match",
+        }
+    }
+
+    // The text colour as #rrggbb, for charts; None for styles without one
+    pub fn color(self) -> Option<&'static str> {
+        let css = self.css();
+        let start = css.find("color: #")? + "color: ".len();
+        css.get(start..start + 7)
+    }
+
+    // CSS declarations matching the terminal rendering
+    fn css(self) -> &'static str {
+        self.css_in(theme())
+    }
+
+    // As `css`, for a given theme
+    pub fn css_in(self, theme: Theme) -> &'static str {
+        if theme == Theme::Colorblind {
+            match self {
+                Style::SeverityLow | Style::Success | Style::Command => return "color: #0072b2;",
+                Style::SeverityMedium => return "color: #e69f00;",
+                Style::SeverityHigh | Style::Error => return "color: #d55e00;",
+                Style::SeverityCritical => return "color: #d55e00; font-weight: bold; text-decoration: underline;",
+                _ => {},
+            }
+        }
+        match self {
+            Style::Plain => "",
+            Style::Banner => "color: #337ab7;",
+            Style::Heading | Style::Label | Style::Argument | Style::Warning => "color: #b8860b;",
+            Style::Section | Style::Category | Style::Hint => "color: #31a5c4;",
+            Style::Code => "font-family: monospace; font-weight: bold;",
+            Style::SeverityLow | Style::Success | Style::Command => "color: #3c9a3c;",
+            Style::SeverityMedium => "color: #d4a017;",
+            Style::SeverityHigh | Style::Error => "color: #d9534f;",
+            Style::SeverityCritical => "color: #d9534f; font-weight: bold;",
+            Style::Link => "text-decoration: underline;",
+            Style::Muted => "color: #888;",
+            Style::Match => "background-color: #fff176;",
+        }
+    }
+
+    const ALL: [Style; 20] = [
+        Style::Plain, Style::Banner, Style::Heading, Style::Section, Style::Label, Style::Code,
+        Style::Category, Style::SeverityLow, Style::SeverityMedium, Style::SeverityHigh,
+        Style::SeverityCritical, Style::Success, Style::Warning, Style::Error, Style::Hint,
+        Style::Command, Style::Argument, Style::Link, Style::Muted, Style::Match,
+    ];
+}
+
+// Render any value with a semantic style: `tr!("quote-total").styled(Style::Success)`
+pub trait Styled {
+    fn styled(&self, style: Style) -> ColoredString;
+}
+
+impl<T: Display + ?Sized> Styled for T {
+    fn styled(&self, style: Style) -> ColoredString {
+        style.paint(&self.to_string())
+    }
+}
+
+// Wrap text (already escaped) in a span carrying the style's CSS class
+pub fn html_span(text: &str, style: Style) -> String {
+    format!("<span class='{}'>{}</span>", style.css_class(), text)
+}
+
+// CSS rules for every style, for embedding in HTML reports
+pub fn stylesheet() -> String {
+    let mut css = String::new();
+    for style in Style::ALL.into_iter().filter(|style| !style.css().is_empty()) {
+        css.push_str(&format!(".{} {{ {} }}\n", style.css_class(), style.css()));
+    }
+    css.push_str(&format!("mark {{ {} }}\n", Style::Match.css()));
+    css
+}
+
+// Dark colors for `<html data-theme='dark'>` on screen, and the print layout
+const PAGE_STYLESHEET: &str = "\
+.theme-toggle { float: right; font: inherit; padding: 4px 10px; cursor: pointer; }
+@media screen {
+  [data-theme='dark'] body { background: #1e1f22; color: #dcdcdc; }
+  [data-theme='dark'] .error-code, [data-theme='dark'] th, [data-theme='dark'] td { border-color: #44464b; }
+  [data-theme='dark'] .safety-warnings { background: #3a2323; }
+  [data-theme='dark'] .letterhead, [data-theme='dark'] .disclaimer { border-color: #44464b; }
+  [data-theme='dark'] .letterhead address, [data-theme='dark'] .disclaimer { color: #a8a8a8; }
+  [data-theme='dark'] mark { color: #1e1f22; }
+  [data-theme='dark'] a { color: #8ab4f8; }
+  [data-theme='dark'] svg text:not([fill]) { fill: #dcdcdc; }
+  [data-theme='dark'] .theme-toggle { background: #2b2d31; color: #dcdcdc; border: 1px solid #44464b; }
+}
+@media print {
+  body { background: #fff; color: #000; margin: 0; }
+  .theme-toggle { display: none; }
+  .error-code, .safety-warnings, .repair-order, svg { break-inside: avoid; }
+  .error-code ~ .error-code { break-before: page; }
+  h2, h3 { break-after: avoid; }
+}
+";
+
+// Picks the reader's color scheme before the page is drawn, unless they chose one here before
+const THEME_SCRIPT: &str = "\
+<script>
+(function () {
+  var theme;
+  try { theme = localStorage.getItem('cars-scanner-theme'); } catch (e) {}
+  if (!theme && window.matchMedia && matchMedia('(prefers-color-scheme: dark)').matches) theme = 'dark';
+  document.documentElement.dataset.theme = theme || 'light';
+})();
+function toggleTheme() {
+  var root = document.documentElement;
+  root.dataset.theme = root.dataset.theme === 'dark' ? 'light' : 'dark';
+  try { localStorage.setItem('cars-scanner-theme', root.dataset.theme); } catch (e) {}
+}
+</script>
+";
+
+// CSS rules of an HTML report page besides those of the styles: dark mode and printing
+pub fn page_stylesheet() -> &'static str {
+    PAGE_STYLESHEET
+}
+
+// The script an HTML report page needs in its head for the dark mode button
+pub fn theme_script() -> &'static str {
+    THEME_SCRIPT
+}
+
+// The button switching an HTML report page between light and dark, labelled `label`
+pub fn theme_toggle(label: &str) -> String {
+    format!("<button type='button' class='theme-toggle' onclick='toggleTheme()'>{}</button>\n", label)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::branding::Letterhead;
+    use crate::search::SearchOptions;
+    use crate::write_search_results;
+    use crate::testing::{init, sample_database, streamed};
+    use super::{severity_text_in, Style, Theme};
+
+    #[test]
+    fn colorblind_theme_marks_severities_without_red_or_green() {
+        assert_eq!(severity_text_in(Theme::Default, "High"), "High");
+        let marked: Vec<String> = crate::SEVERITIES.iter().map(|severity| severity_text_in(Theme::Colorblind, severity)).collect();
+        assert_eq!(marked, ["[-] Low", "[!] Medium", "[!!] High", "[!!!] Critical"]);
+        assert_eq!(severity_text_in(Theme::Colorblind, "Unknown"), "Unknown");
+
+        for style in [Style::SeverityLow, Style::SeverityHigh, Style::SeverityCritical, Style::Success, Style::Error] {
+            let css = style.css_in(Theme::Colorblind);
+            assert!(!css.contains("#3c9a3c") && !css.contains("#d9534f"), "{:?} uses red or green: {}", style, css);
+        }
+    }
+
+    #[test]
+    fn html_reports_switch_to_dark_on_screen_and_print_a_code_per_page() {
+        init();
+        let db = sample_database();
+        let page = db.search("sensor", &SearchOptions::default());
+        let html = streamed(|out| write_search_results(&page.hits, &Letterhead::default(), true, out));
+
+        let (head, body) = html.split_once("<body>").unwrap();
+        assert!(head.contains("function toggleTheme()"));
+        assert_eq!(body.matches("onclick='toggleTheme()'").count(), 1);
+        assert_eq!(body.matches("<div class='error-code'>").count(), 2);
+        // Dark colors never reach paper, and codes after the first start a page
+        let (screen, print) = head.split_once("@media print").unwrap();
+        assert!(screen.contains("[data-theme='dark'] body") && !print.contains("data-theme"));
+        assert!(print.contains(".theme-toggle { display: none; }"));
+        assert!(print.contains(".error-code ~ .error-code { break-before: page; }"));
+    }
+}
@@ -0,0 +1,92 @@
+// Progress bars and spinners for slow operations.
+//
+// They are drawn on stderr, so they never end up in redirected output or in
+// transcripts, and only when stderr is a terminal. `--no-progress` turns
+// them off everywhere, e.g. for screen readers or CI logs. A build without
+// the tui feature has only hidden ones.
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "tui")]
+use std::io::{self, IsTerminal};
+#[cfg(feature = "tui")]
+use std::time::Duration;
+#[cfg(feature = "tui")]
+use indicatif::{MultiProgress, ProgressDrawTarget, ProgressStyle};
+#[cfg(feature = "tui")]
+pub use indicatif::ProgressBar;
+
+// Set by --no-progress
+static PROGRESS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn disable_progress() {
+    PROGRESS_DISABLED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(feature = "tui")]
+fn visible() -> bool {
+    !PROGRESS_DISABLED.load(Ordering::Relaxed) && io::stderr().is_terminal()
+}
+
+// A spinner for work of unknown length; clear it with `finish_and_clear`
+#[cfg(feature = "tui")]
+pub fn spinner(message: String) -> ProgressBar {
+    if !visible() {
+        return ProgressBar::hidden();
+    }
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.set_style(ProgressStyle::with_template("{spinner} {msg} {elapsed:.dim}").expect("valid template"));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+// One spinner per message, drawn together so several jobs can report at once
+#[cfg(feature = "tui")]
+pub fn spinners(messages: Vec<String>) -> Vec<ProgressBar> {
+    let multi = match visible() {
+        true => MultiProgress::new(),
+        false => MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+    };
+    messages.into_iter().map(|message| multi.add(spinner(message))).collect()
+}
+
+// A bar counting `total` items
+#[cfg(feature = "tui")]
+pub fn bar(total: u64, message: String) -> ProgressBar {
+    if !visible() {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(total).with_message(message);
+    bar.set_style(
+        ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} {eta}")
+            .expect("valid template")
+            .progress_chars("=> "),
+    );
+    bar
+}
+
+// Stands in for indicatif's bar when it is not built in; nothing is drawn
+#[cfg(not(feature = "tui"))]
+pub struct ProgressBar;
+
+#[cfg(not(feature = "tui"))]
+impl ProgressBar {
+    pub fn set_message(&self, _message: String) {}
+
+    pub fn inc(&self, _delta: u64) {}
+
+    pub fn finish_and_clear(&self) {}
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn spinner(_message: String) -> ProgressBar {
+    ProgressBar
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn spinners(messages: Vec<String>) -> Vec<ProgressBar> {
+    messages.into_iter().map(spinner).collect()
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn bar(_total: u64, _message: String) -> ProgressBar {
+    ProgressBar
+}
@@ -0,0 +1,590 @@
+// A subset of SQL over the loaded codes (`query`).
+//
+//   SELECT code, severity FROM codes
+//   WHERE system = 'Engine' AND severity IN ('High', 'Critical')
+//   ORDER BY code LIMIT 20
+//
+// The one table is `codes`, with the columns of the database file. A
+// condition compares a column with a quoted value or a number using =, !=
+// (or <>), <, <=, >, >=, LIKE (% for any text, _ for one character),
+// IN (...) or IS [NOT] NULL, which holds for empty values; conditions combine
+// with NOT, AND, OR and parentheses. Keywords and column names ignore case,
+// and so do comparisons, as they do in `list`. Severity and difficulty
+// compare by rank, Low below Critical and DIY below Professional; numbers
+// compare as numbers and other values as text, with empty values last.
+use std::cmp::Ordering;
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::sqlite::{Column, Filter};
+use crate::transcript::outln;
+use crate::{merge, porcelain, DiagnosticsDatabase, ErrorCode, DIFFICULTIES, SEVERITIES};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // A keyword, column or table name
+    Word(String),
+    // A quoted value, without its quotes
+    Text(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Compare { column: String, operator: Operator, value: String },
+    Like { column: String, pattern: String, negated: bool },
+    In { column: String, values: Vec<String>, negated: bool },
+    Null { column: String, negated: bool },
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+// A parsed `SELECT`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub columns: Vec<String>,
+    filter: Option<Condition>,
+    // Columns to sort by, and whether descending
+    order: Vec<(String, bool)>,
+    limit: Option<usize>,
+}
+
+// The columns of the `codes` table, in the order of the database file
+fn column_names() -> Vec<&'static str> {
+    std::iter::once("code").chain(merge::field_names()).collect()
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote stands for the quote itself, as in SQL
+                        Some(quote) if quote == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            text.push(c);
+                        },
+                        Some(quote) if quote == c => break,
+                        Some(other) => text.push(other),
+                        None => return Err(tr!("query-unterminated")),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            },
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(next) = chars.next_if(|next| next.is_ascii_digit() || *next == '.') {
+                    number.push(next);
+                }
+                tokens.push(Token::Number(number));
+            },
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.next_if(|next| next.is_alphanumeric() || *next == '_') {
+                    word.push(next);
+                }
+                tokens.push(Token::Word(word));
+            },
+            _ => {
+                let next = chars.peek().copied();
+                let symbol = match (c, next) {
+                    ('!', Some('=')) | ('<', Some('>')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    ('=', _) => "=",
+                    (',', _) => ",",
+                    ('(', _) => "(",
+                    (')', _) => ")",
+                    ('*', _) => "*",
+                    (';', _) => ";",
+                    _ => return Err(tr!("query-unexpected", found = c.to_string())),
+                };
+                if symbol.len() == 2 {
+                    chars.next();
+                }
+                tokens.push(Token::Symbol(symbol));
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    // The next token as the error message names it
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(Token::Word(word) | Token::Number(word)) => format!("'{}'", word),
+            Some(Token::Text(text)) => format!("'{}'", text.replace('\'', "''")),
+            Some(Token::Symbol(symbol)) => format!("'{}'", symbol),
+            None => tr!("query-end"),
+        }
+    }
+
+    fn expected(&self, expected: &str) -> String {
+        tr!("query-expected", expected = expected, found = self.found())
+    }
+
+    // Take the keyword if it comes next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let next = matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if next {
+            self.position += 1;
+        }
+        next
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        match self.keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.expected(keyword)),
+        }
+    }
+
+    // Take the symbol if it comes next
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let next = matches!(self.peek(), Some(Token::Symbol(next)) if *next == symbol);
+        if next {
+            self.position += 1;
+        }
+        next
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        match self.symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.expected(&format!("'{}'", symbol))),
+        }
+    }
+
+    fn column(&mut self) -> Result<String, String> {
+        let Some(Token::Word(word)) = self.peek() else { return Err(self.expected(&tr!("query-a-column"))) };
+        let column = word.to_lowercase();
+        if !column_names().contains(&column.as_str()) {
+            return Err(tr!("query-unknown-column", column = word.as_str(), columns = column_names().join(", ")));
+        }
+        self.position += 1;
+        Ok(column)
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Text(value) | Token::Number(value)) => {
+                let value = value.clone();
+                self.position += 1;
+                Ok(value)
+            },
+            _ => Err(self.expected(&tr!("query-a-value"))),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        match self.peek() {
+            Some(Token::Number(number)) if number.parse::<usize>().is_ok() => {
+                let number = number.parse().unwrap_or_default();
+                self.position += 1;
+                Ok(number)
+            },
+            _ => Err(self.expected(&tr!("query-a-number"))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.keyword("OR") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.not()?;
+        while self.keyword("AND") {
+            condition = Condition::And(Box::new(condition), Box::new(self.not()?));
+        }
+        Ok(condition)
+    }
+
+    fn not(&mut self) -> Result<Condition, String> {
+        match self.keyword("NOT") {
+            true => Ok(Condition::Not(Box::new(self.not()?))),
+            false => self.predicate(),
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Condition, String> {
+        if self.symbol("(") {
+            let condition = self.or()?;
+            self.expect_symbol(")")?;
+            return Ok(condition);
+        }
+
+        let column = self.column()?;
+        let operator = match self.peek() {
+            Some(Token::Symbol("=")) => Some(Operator::Equal),
+            Some(Token::Symbol("!=")) => Some(Operator::NotEqual),
+            Some(Token::Symbol("<")) => Some(Operator::Less),
+            Some(Token::Symbol("<=")) => Some(Operator::LessOrEqual),
+            Some(Token::Symbol(">")) => Some(Operator::Greater),
+            Some(Token::Symbol(">=")) => Some(Operator::GreaterOrEqual),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            self.position += 1;
+            return Ok(Condition::Compare { column, operator, value: self.value()? });
+        }
+
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Condition::Null { column, negated });
+        }
+        let negated = self.keyword("NOT");
+        if self.keyword("LIKE") {
+            return Ok(Condition::Like { column, pattern: self.value()?, negated });
+        }
+        if self.keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut values = vec![self.value()?];
+            while self.symbol(",") {
+                values.push(self.value()?);
+            }
+            self.expect_symbol(")")?;
+            return Ok(Condition::In { column, values, negated });
+        }
+        Err(self.expected(&tr!("query-a-comparison")))
+    }
+}
+
+impl Query {
+    pub fn parse(sql: &str) -> Result<Query, String> {
+        let mut parser = Parser { tokens: tokenize(sql)?, position: 0 };
+        parser.expect_keyword("SELECT")?;
+        let columns = match parser.symbol("*") {
+            true => column_names().into_iter().map(str::to_string).collect(),
+            false => {
+                let mut columns = vec![parser.column()?];
+                while parser.symbol(",") {
+                    columns.push(parser.column()?);
+                }
+                columns
+            },
+        };
+
+        parser.expect_keyword("FROM")?;
+        match parser.peek() {
+            Some(Token::Word(table)) if table.eq_ignore_ascii_case("codes") => parser.position += 1,
+            Some(Token::Word(table)) => return Err(tr!("query-unknown-table", table = table.as_str())),
+            _ => return Err(parser.expected("codes")),
+        }
+
+        let filter = match parser.keyword("WHERE") {
+            true => Some(parser.or()?),
+            false => None,
+        };
+
+        let mut order = Vec::new();
+        if parser.keyword("ORDER") {
+            parser.expect_keyword("BY")?;
+            loop {
+                let column = parser.column()?;
+                let descending = parser.keyword("DESC");
+                if !descending {
+                    parser.keyword("ASC");
+                }
+                order.push((column, descending));
+                if !parser.symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let limit = match parser.keyword("LIMIT") {
+            true => Some(parser.number()?),
+            false => None,
+        };
+        parser.symbol(";");
+        if parser.peek().is_some() {
+            return Err(parser.expected(&tr!("query-end")));
+        }
+        Ok(Query { columns, filter, order, limit })
+    }
+
+    // The matching codes, sorted and limited; in code order where the ORDER BY leaves a tie
+    pub fn run<'a>(&self, db: &'a DiagnosticsDatabase) -> Vec<&'a ErrorCode> {
+        let mut errors: Vec<&ErrorCode> = db.records()
+            .filter(|error| self.filter.as_ref().is_none_or(|filter| filter.matches(error)))
+            .collect();
+        errors.sort_by(|a, b| a.code.cmp(&b.code));
+        errors.sort_by(|a, b| {
+            self.order.iter()
+                .map(|(column, descending)| {
+                    let ordering = compare(column, &text(a, column), &text(b, column));
+                    if *descending { ordering.reverse() } else { ordering }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+        if let Some(limit) = self.limit {
+            errors.truncate(limit);
+        }
+        errors
+    }
+
+    // A filter SQL answers that holds for at least the codes this query matches, for reading
+    // fewer codes from a SQLite database; conditions it cannot express hold for every code
+    pub fn narrowing(&self) -> Filter {
+        self.filter.as_ref().map_or(Filter::Any, Condition::narrowing)
+    }
+
+    // The selected columns of a code
+    pub fn row(&self, error: &ErrorCode) -> Vec<String> {
+        self.columns.iter().map(|column| text(error, column)).collect()
+    }
+}
+
+impl Condition {
+    fn matches(&self, error: &ErrorCode) -> bool {
+        match self {
+            Condition::Compare { column, operator, value } => {
+                let ordering = compare(column, &text(error, column), value);
+                match operator {
+                    Operator::Equal => ordering.is_eq(),
+                    Operator::NotEqual => ordering.is_ne(),
+                    Operator::Less => ordering.is_lt(),
+                    Operator::LessOrEqual => ordering.is_le(),
+                    Operator::Greater => ordering.is_gt(),
+                    Operator::GreaterOrEqual => ordering.is_ge(),
+                }
+            },
+            Condition::Like { column, pattern, negated } => like(&text(error, column), pattern) != *negated,
+            Condition::In { column, values, negated } => {
+                let actual = text(error, column);
+                values.iter().any(|value| compare(column, &actual, value).is_eq()) != *negated
+            },
+            Condition::Null { column, negated } => text(error, column).trim().is_empty() != *negated,
+            Condition::Not(condition) => !condition.matches(error),
+            Condition::And(first, second) => first.matches(error) && second.matches(error),
+            Condition::Or(first, second) => first.matches(error) || second.matches(error),
+        }
+    }
+}
+
+impl Condition {
+    fn narrowing(&self) -> Filter {
+        match self {
+            Condition::Compare { column, operator, value } => {
+                // Ranked names sort before every other value, so only the lower end is closed
+                let ranked = match operator {
+                    Operator::Equal => rank(column, value).map(|rank| rank..=rank),
+                    Operator::Less => rank(column, value).and_then(|rank| rank.checked_sub(1)).map(|rank| 0..=rank),
+                    Operator::LessOrEqual => rank(column, value).map(|rank| 0..=rank),
+                    _ => None,
+                };
+                match (ranked, operator) {
+                    (Some(ranks), _) => names(column, ranks),
+                    (None, Operator::Equal) => values(column, std::slice::from_ref(value)),
+                    _ => Filter::Any,
+                }
+            },
+            Condition::In { column, values: options, negated: false } => match options.iter().map(|value| rank(column, value)).collect::<Option<Vec<_>>>() {
+                Some(ranks) => ranks.into_iter().map(|rank| names(column, rank..=rank)).reduce(Filter::or).unwrap_or(Filter::Any),
+                None => values(column, options),
+            },
+            Condition::And(first, second) => first.narrowing().and(second.narrowing()),
+            Condition::Or(first, second) => first.narrowing().or(second.narrowing()),
+            _ => Filter::Any,
+        }
+    }
+}
+
+// The SQL column compared as the query compares `column`, for the columns it has
+fn sql_column(column: &str) -> Option<Column> {
+    match column {
+        "code" => Some(Column::Code),
+        "system" => Some(Column::System),
+        "severity" => Some(Column::Severity),
+        "difficulty" => Some(Column::Difficulty),
+        _ => None,
+    }
+}
+
+// The codes ranked within `ranks` on the scale of `column`
+fn names(column: &str, ranks: std::ops::RangeInclusive<usize>) -> Filter {
+    let scale = if column == "severity" { SEVERITIES } else { DIFFICULTIES };
+    match sql_column(column) {
+        Some(sql) => Filter::In(sql, scale.get(ranks).unwrap_or_default().iter().map(|name| name.to_string()).collect()),
+        None => Filter::Any,
+    }
+}
+
+// The codes whose text in `column` is one of `values`. SQL ignores case in ASCII only, and
+// numbers and padded text compare otherwise here, so those values leave the column open.
+fn values(column: &str, values: &[String]) -> Filter {
+    let plain = |value: &String| value.is_ascii() && !value.trim().is_empty() && value.trim() == value && value.parse::<f64>().is_err();
+    match sql_column(column) {
+        Some(sql) if values.iter().all(plain) => Filter::In(sql, values.to_vec()),
+        _ => Filter::Any,
+    }
+}
+
+fn text(error: &ErrorCode, column: &str) -> String {
+    merge::value(error, column).unwrap_or_default()
+}
+
+// The position of a severity or difficulty on its scale
+fn rank(column: &str, value: &str) -> Option<usize> {
+    let scale = match column {
+        "severity" => SEVERITIES,
+        "difficulty" => DIFFICULTIES,
+        _ => return None,
+    };
+    scale.iter().position(|name| name.eq_ignore_ascii_case(value))
+}
+
+// How a value sorts: ranked names, then numbers, then other text, then empty values
+fn sort_key(column: &str, value: &str) -> (u8, f64, String) {
+    let value = value.trim();
+    if let Some(rank) = rank(column, value) {
+        return (0, rank as f64, String::new());
+    }
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() => (1, number, String::new()),
+        _ if value.is_empty() => (3, 0.0, String::new()),
+        _ => (2, 0.0, value.to_lowercase()),
+    }
+}
+
+fn compare(column: &str, a: &str, b: &str) -> Ordering {
+    let (a, b) = (sort_key(column, a), sort_key(column, b));
+    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then_with(|| a.2.cmp(&b.2))
+}
+
+// Whether text fits a LIKE pattern, ignoring case
+fn like(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    // Which lengths of the text's start the pattern so far matches
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for wildcard in pattern.to_lowercase().chars() {
+        let mut next = vec![false; text.len() + 1];
+        match wildcard {
+            '%' => if let Some(first) = matched.iter().position(|matched| *matched) {
+                next[first..].fill(true);
+            },
+            _ => for (length, _) in matched.iter().enumerate().filter(|(_, matched)| **matched) {
+                if length < text.len() && (wildcard == '_' || text[length] == wildcard) {
+                    next[length + 1] = true;
+                }
+            },
+        }
+        matched = next;
+    }
+    matched[text.len()]
+}
+
+// Print the rows as a table, or as `row` records with --porcelain
+pub fn display(query: &Query, errors: &[&ErrorCode]) {
+    let rows: Vec<Vec<String>> = errors.iter().map(|error| query.row(error)).collect();
+    if porcelain::enabled() {
+        for row in &rows {
+            println!("{}", porcelain::line("row", &row.iter().map(String::as_str).collect::<Vec<_>>()));
+        }
+        return;
+    }
+    if rows.is_empty() {
+        outln!("{}", tr!("query-no-rows"));
+        return;
+    }
+
+    let widths: Vec<usize> = query.columns.iter().enumerate()
+        .map(|(index, column)| rows.iter().map(|row| row[index].chars().count()).chain([column.chars().count()]).max().unwrap_or_default())
+        .collect();
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+            .collect();
+        cells.join("  ").trim_end().to_string()
+    };
+    outln!("{}", line(&query.columns).styled(Style::Heading));
+    for row in &rows {
+        outln!("{}", line(row));
+    }
+    outln!("\n{}", tr!("query-rows", count = rows.len()).styled(Style::Muted));
+}
+
+// The rows as JSON objects of the selected columns, for --format json; values are the text
+// of the database file, list columns with their entries separated by '|'
+pub fn json_rows(query: &Query, errors: &[&ErrorCode]) -> Vec<serde_json::Map<String, serde_json::Value>> {
+    errors.iter().map(|error| query.columns.iter().cloned().zip(query.row(error).into_iter().map(serde_json::Value::from)).collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DiagnosticsDatabase;
+    use crate::testing::init;
+    use super::Query;
+
+    #[test]
+    fn query_selects_filters_orders_and_limits_codes() {
+        init();
+        let db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,difficulty\n\
+             P0300,Random Misfire,High,Engine,Coils|Plugs,Replace plugs,Intermediate\n\
+             P0301,Cylinder 1 Misfire,Critical,Engine,Coil 1,Swap coil,DIY\n\
+             P0171,System Too Lean,Medium,Engine,Vacuum leak,Smoke test,\n\
+             P0420,Catalyst Efficiency,High,Emissions,Catalyst,Replace,Professional\n\
+             B0001,Driver's Airbag,Critical,Airbag,Squib,Replace,Professional\n",
+        );
+        let rows = |sql: &str| {
+            let query = Query::parse(sql).unwrap();
+            query.run(&db).iter().map(|error| query.row(error).join(",")).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            rows("SELECT code, severity FROM codes WHERE system='Engine' AND severity IN ('High','Critical') ORDER BY code LIMIT 20"),
+            ["P0300,High", "P0301,Critical"],
+        );
+        // Severities sort by rank, not alphabetically; ties stay in code order
+        assert_eq!(rows("select code from codes where severity >= 'high' order by severity desc"), ["B0001", "P0301", "P0300", "P0420"]);
+        assert_eq!(rows("SELECT code FROM codes WHERE difficulty <= 'intermediate' OR difficulty IS NULL"), ["P0171", "P0300", "P0301"]);
+        assert_eq!(rows("SELECT code FROM codes WHERE possible_causes LIKE '%coil_%' AND NOT (code = 'P0301')"), ["P0300"]);
+        assert_eq!(rows("SELECT code FROM codes WHERE description NOT LIKE '%misfire%' AND system <> 'Airbag' ORDER BY difficulty LIMIT 1;"), ["P0420"]);
+        assert_eq!(rows("SELECT code FROM codes WHERE description = 'Driver''s Airbag'"), ["B0001"]);
+        assert_eq!(Query::parse("SELECT * FROM codes").unwrap().columns.len(), 15);
+
+        let error = |sql: &str| Query::parse(sql).unwrap_err();
+        assert_eq!(error("SELECT code FROM cars"), "Query error: unknown table 'cars'; the one table is codes");
+        assert!(error("SELECT colour FROM codes").starts_with("Query error: unknown column 'colour'; the columns are code, description,"));
+        assert_eq!(error("SELECT code FROM codes WHERE system 'Engine'"), "Query error: expected =, !=, <, <=, >, >=, LIKE, IN or IS NULL, found 'Engine'");
+        assert_eq!(error("SELECT code FROM codes LIMIT ten"), "Query error: expected a number, found 'ten'");
+        assert_eq!(error("SELECT code FROM codes WHERE system = 'Engine"), "Query error: a quoted value is not closed");
+        assert_eq!(error("SELECT code FROM codes ORDER code"), "Query error: expected BY, found 'code'");
+    }
+}
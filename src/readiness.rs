@@ -0,0 +1,188 @@
+// Readiness monitors, from mode 01 PID 01.
+//
+// The engine computer runs self-tests ("monitors") on the emission systems.
+// Clearing codes or disconnecting the battery resets them, and an inspection
+// station rejects a vehicle whose monitors have not completed. PID 01 reports
+// the MIL, the number of stored codes and, per monitor, whether it is
+// supported and whether it has completed since codes were last cleared.
+use std::error::Error;
+use crate::adapter::Adapter;
+
+// Which engines a monitor exists on
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ignition {
+    Both,
+    Spark,
+    Compression,
+}
+
+pub struct Monitor {
+    // Stable name used on the command line and in drive_cycles.yaml
+    pub id: &'static str,
+    pub name: &'static str,
+    ignition: Ignition,
+    // Continuous monitors use byte B (supported in bits 0-2, incomplete in 4-6);
+    // the others byte C (supported) and byte D (incomplete)
+    continuous: bool,
+    bit: u8,
+}
+
+// Entries are static, so two monitors are the same when they are the same entry
+impl PartialEq for Monitor {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl std::fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id)
+    }
+}
+
+const fn monitor(id: &'static str, name: &'static str, ignition: Ignition, continuous: bool, bit: u8) -> Monitor {
+    Monitor { id, name, ignition, continuous, bit }
+}
+
+pub const MONITORS: &[Monitor] = &[
+    monitor("misfire", "Misfire", Ignition::Both, true, 0),
+    monitor("fuel-system", "Fuel system", Ignition::Both, true, 1),
+    monitor("components", "Comprehensive components", Ignition::Both, true, 2),
+    monitor("catalyst", "Catalyst", Ignition::Spark, false, 0),
+    monitor("heated-catalyst", "Heated catalyst", Ignition::Spark, false, 1),
+    monitor("evap", "Evaporative system", Ignition::Spark, false, 2),
+    monitor("secondary-air", "Secondary air system", Ignition::Spark, false, 3),
+    monitor("ac-refrigerant", "A/C refrigerant", Ignition::Spark, false, 4),
+    monitor("o2-sensor", "Oxygen sensor", Ignition::Spark, false, 5),
+    monitor("o2-heater", "Oxygen sensor heater", Ignition::Spark, false, 6),
+    monitor("egr", "EGR system", Ignition::Spark, false, 7),
+    monitor("nmhc-catalyst", "NMHC catalyst", Ignition::Compression, false, 0),
+    monitor("nox", "NOx aftertreatment", Ignition::Compression, false, 1),
+    monitor("boost-pressure", "Boost pressure", Ignition::Compression, false, 3),
+    monitor("exhaust-gas-sensor", "Exhaust gas sensor", Ignition::Compression, false, 5),
+    monitor("particulate-filter", "Particulate filter", Ignition::Compression, false, 6),
+    monitor("egr", "EGR/VVT system", Ignition::Compression, false, 7),
+];
+
+pub fn find(id: &str) -> Option<&'static Monitor> {
+    MONITORS.iter().find(|monitor| monitor.id.eq_ignore_ascii_case(id))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorStatus {
+    pub monitor: &'static Monitor,
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Readiness {
+    pub mil: bool,
+    pub stored_codes: u8,
+    pub compression: bool,
+    // Supported monitors only, in table order
+    pub monitors: Vec<MonitorStatus>,
+}
+
+impl Readiness {
+    // Decode the four data bytes A-D of a PID 01 answer
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let &[a, b, c, d] = data.get(..4)? else { return None };
+        let compression = b & 0x08 != 0;
+        let monitors = MONITORS
+            .iter()
+            .filter(|monitor| match monitor.ignition {
+                Ignition::Both => true,
+                Ignition::Spark => !compression,
+                Ignition::Compression => compression,
+            })
+            .filter_map(|monitor| {
+                let (supported, incomplete) = match monitor.continuous {
+                    true => (b >> monitor.bit & 1, b >> (monitor.bit + 4) & 1),
+                    false => (c >> monitor.bit & 1, d >> monitor.bit & 1),
+                };
+                (supported == 1).then_some(MonitorStatus { monitor, complete: incomplete == 0 })
+            })
+            .collect();
+        Some(Readiness { mil: a & 0x80 != 0, stored_codes: a & 0x7F, compression, monitors })
+    }
+
+    // The four data bytes A-D of a PID 01 answer reporting this status, for the emulator
+    #[cfg_attr(not(feature = "adapter"), allow(dead_code))]
+    pub fn encode(&self) -> [u8; 4] {
+        let mut bytes = [(0x80 * u8::from(self.mil)) | self.stored_codes.min(0x7F), 0x08 * u8::from(self.compression), 0, 0];
+        for status in &self.monitors {
+            let monitor = status.monitor;
+            let incomplete = u8::from(!status.complete);
+            match monitor.continuous {
+                true => bytes[1] |= 1 << monitor.bit | incomplete << (monitor.bit + 4),
+                false => {
+                    bytes[2] |= 1 << monitor.bit;
+                    bytes[3] |= incomplete << monitor.bit;
+                },
+            }
+        }
+        bytes
+    }
+
+    // Combine the answers of several ECUs: a monitor is complete only when every ECU reporting it says so
+    fn merge(mut self, other: Readiness) -> Self {
+        self.mil |= other.mil;
+        self.stored_codes = self.stored_codes.saturating_add(other.stored_codes);
+        for status in other.monitors {
+            match self.monitors.iter_mut().find(|existing| existing.monitor == status.monitor) {
+                Some(existing) => existing.complete &= status.complete,
+                None => self.monitors.push(status),
+            }
+        }
+        self
+    }
+
+    pub fn incomplete(&self) -> impl Iterator<Item = &'static Monitor> + '_ {
+        self.monitors.iter().filter(|status| !status.complete).map(|status| status.monitor)
+    }
+
+    pub fn complete_count(&self) -> usize {
+        self.monitors.iter().filter(|status| status.complete).count()
+    }
+}
+
+// Read PID 01 from every ECU that answers
+pub fn read(adapter: &mut Adapter) -> Result<Readiness, Box<dyn Error>> {
+    adapter
+        .request("0101")?
+        .iter()
+        .filter(|response| response.data.starts_with(&[0x41, 0x01]))
+        .filter_map(|response| Readiness::decode(&response.data[2..]))
+        .reduce(Readiness::merge)
+        .ok_or_else(|| "The vehicle did not report its readiness monitors".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::adapter::Adapter;
+    use crate::testing::{init, FakeElm};
+    use super::Readiness;
+
+    #[test]
+    fn readiness_selects_incomplete_monitors_and_their_drive_cycles() {
+        init();
+        let mut adapter = Adapter::initialize(Box::new(FakeElm::car()), "fake", Duration::from_secs(1)).unwrap();
+        let status = super::read(&mut adapter).unwrap();
+        assert!(!status.mil);
+        assert_eq!(status.monitors.len(), 7);
+        let incomplete: Vec<&str> = status.incomplete().map(|monitor| monitor.id).collect();
+        assert_eq!(incomplete, ["catalyst", "evap"]);
+
+        // A diesel with the MIL on, two codes and its particulate filter monitor pending
+        let diesel = Readiness::decode(&[0x82, 0x0F, 0x41, 0x40]).unwrap();
+        assert!(diesel.compression && diesel.mil);
+        assert_eq!(diesel.stored_codes, 2);
+        assert_eq!(diesel.incomplete().map(|monitor| monitor.id).collect::<Vec<_>>(), ["particulate-filter"]);
+
+        let cycles = crate::drive_cycle::DriveCycles::load(crate::drive_cycle::DRIVE_CYCLES_FILE).unwrap();
+        let catalyst = super::find("catalyst").unwrap();
+        assert_eq!(cycles.steps(catalyst, Some("toyota")).unwrap().make, Some("Toyota"));
+        assert_eq!(cycles.steps(catalyst, Some("Saab")).unwrap().make, None);
+    }
+}
@@ -0,0 +1,98 @@
+// Reports written again only when what they are made from changed.
+//
+// A nightly pipeline runs `report` and `fleet report` whether or not
+// anything happened that day. Each of them first reduces its inputs (the
+// code's definition and the database or pack it came from, the archive
+// entries, the branding, layout and stylesheet, the repair order details,
+// the version of cars-scanner and the language) to a tag, much like an HTTP
+// ETag. <data_dir>/report-state.json keeps the tag each output file was
+// written from; when the tag is the same and the file is still there, the
+// report is left as it is and its modification time does not change. The
+// date printed on a report is not an input. `--force` writes it anyway.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{self, Path, PathBuf};
+use crate::anonymize::fnv1a;
+use crate::{i18n, presentation};
+
+pub const STATE_FILE: &str = "report-state.json";
+
+// The tag of a report made from `inputs`
+pub fn tag(inputs: &[&str]) -> String {
+    let mut bytes = Vec::new();
+    for input in [env!("CARGO_PKG_VERSION"), i18n::language(), presentation::stylesheet().as_str()].iter().chain(inputs) {
+        bytes.extend_from_slice(input.as_bytes());
+        bytes.push(0);
+    }
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+// The tags of the reports written so far, by output file
+pub struct ReportState {
+    path: PathBuf,
+    tags: BTreeMap<String, String>,
+}
+
+// The same file however it was named on the command line
+fn key(output: &Path) -> String {
+    path::absolute(output).unwrap_or_else(|_| output.to_path_buf()).display().to_string()
+}
+
+impl ReportState {
+    pub fn open(data_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = data_dir.join(STATE_FILE);
+        let tags = match path.exists() {
+            true => serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+            false => BTreeMap::new(),
+        };
+        Ok(ReportState { path, tags })
+    }
+
+    // Whether `output` exists and was written from these inputs
+    pub fn is_current(&self, output: &Path, tag: &str) -> bool {
+        output.is_file() && self.tags.get(&key(output)).is_some_and(|written| written == tag)
+    }
+
+    // Remember that `output` was just written from these inputs
+    pub fn record(&mut self, output: &Path, tag: &str) -> Result<(), Box<dyn Error>> {
+        self.tags.insert(key(output), tag.to_string());
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.tags)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::testing::{init, TempDir};
+    use super::ReportState;
+
+    #[test]
+    fn reports_are_current_until_an_input_changes() {
+        init();
+        let temp = TempDir::new("regenerate");
+        let dir = temp.join("data");
+        let output = dir.join("report.html");
+        let tag = super::tag(&["P0420", "Main Street Auto"]);
+        assert_eq!(tag, super::tag(&["P0420", "Main Street Auto"]));
+        assert_ne!(tag, super::tag(&["P0420", "Main Street Auto Ltd"]));
+        assert_ne!(super::tag(&["ab", "c"]), super::tag(&["a", "bc"]));
+
+        let mut state = ReportState::open(&dir).unwrap();
+        assert!(!state.is_current(&output, &tag));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&output, "report").unwrap();
+        state.record(&output, &tag).unwrap();
+
+        let state = ReportState::open(&dir).unwrap();
+        assert!(state.is_current(&output, &tag));
+        assert!(!state.is_current(&output, &super::tag(&["P0430", "Main Street Auto"])));
+        fs::remove_file(&output).unwrap();
+        assert!(!state.is_current(&output, &tag));
+    }
+}
@@ -0,0 +1,114 @@
+// Customer and repair-order details printed in the header of a report.
+//
+// `report` takes them as flags; in a terminal it asks for any that are
+// missing, and an empty answer leaves the field out.
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use clap::Args;
+use crate::archive::Clock;
+use crate::escape_html;
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+
+#[derive(Args, Debug, Clone)]
+pub struct RepairOrderArgs {
+    /// Customer name printed on the report
+    #[arg(long)]
+    pub customer: Option<String>,
+
+    /// Repair order number
+    #[arg(long)]
+    pub ro: Option<String>,
+
+    /// Odometer reading when the vehicle came in
+    #[arg(long)]
+    pub odometer: Option<u64>,
+}
+
+// The details of one repair order; fields left empty are not printed
+#[derive(Debug, Clone, Default)]
+pub struct RepairOrder {
+    pub customer: Option<String>,
+    pub number: Option<String>,
+    pub odometer: Option<u64>,
+    // Seconds since the Unix epoch
+    pub date: Option<u64>,
+}
+
+impl RepairOrderArgs {
+    // Fill in the details, asking for missing ones when stdin is a terminal
+    pub fn resolve(&self, clock: &dyn Clock) -> Result<RepairOrder, Box<dyn Error>> {
+        let interactive = io::stdin().is_terminal();
+        let ask_missing = |value: &Option<String>, label: String| -> Result<Option<String>, Box<dyn Error>> {
+            match value {
+                Some(value) => Ok(Some(value.clone())),
+                None if interactive => prompt(&label),
+                None => Ok(None),
+            }
+        };
+
+        let customer = ask_missing(&self.customer, tr!("label-customer"))?;
+        let number = ask_missing(&self.ro, tr!("label-repair-order"))?;
+        let odometer = match self.odometer {
+            Some(odometer) => Some(odometer),
+            None if interactive => prompt(&tr!("label-odometer"))?
+                .map(|answer| answer.replace([',', ' '], "").parse::<u64>().map_err(|_| tr!("repair-order-bad-odometer", value = answer)))
+                .transpose()?,
+            None => None,
+        };
+        Ok(RepairOrder { customer, number, odometer, date: Some(clock.now()) })
+    }
+}
+
+fn prompt(label: &str) -> Result<Option<String>, Box<dyn Error>> {
+    print!("{} ", label.styled(Style::Hint));
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok((!answer.is_empty()).then(|| answer.to_string()))
+}
+
+impl RepairOrder {
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        if let Some(customer) = &self.customer {
+            fields.push((tr!("label-customer"), customer.clone()));
+        }
+        if let Some(number) = &self.number {
+            fields.push((tr!("label-repair-order"), number.clone()));
+        }
+        if let Some(odometer) = self.odometer {
+            fields.push((tr!("label-odometer"), odometer.to_string()));
+        }
+        if let Some(date) = self.date {
+            fields.push((tr!("label-date"), crate::time::format_date(date)));
+        }
+        fields
+    }
+
+    // One line per field above a plain text report
+    pub fn to_text(&self) -> String {
+        let fields = self.fields();
+        if fields.is_empty() {
+            return String::new();
+        }
+        let mut text: String = fields.iter().map(|(label, value)| format!("{} {}\n", label, value)).collect();
+        text.push('\n');
+        text
+    }
+
+    // A table of the fields above an HTML report
+    pub fn to_html(&self) -> String {
+        let fields = self.fields();
+        if fields.is_empty() {
+            return String::new();
+        }
+        let mut html = String::from("<table class='repair-order'>\n");
+        for (label, value) in fields {
+            html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", escape_html(label.trim_end_matches(':')), escape_html(&value)));
+        }
+        html.push_str("</table>\n");
+        html
+    }
+}
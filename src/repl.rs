@@ -0,0 +1,723 @@
+// Interactive mode: the prompt, its commands and the state kept between them
+use std::error::Error;
+use std::io::{self, BufRead};
+use rustyline::config::{self as line_config, CompletionType};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use crate::adapter::Adapter;
+use crate::archive::{Clock, SystemClock};
+use crate::config::{self, Config};
+use crate::i18n::{self, tr};
+use crate::presentation::{Style, Styled};
+use crate::search::SearchOptions;
+use crate::transcript::{self, outln};
+use crate::vehicle::Vehicle;
+use crate::workspace::{Note, Workspace, Workspaces};
+use crate::commands::{archived_scans, connect_adapter, display_adapter_status, display_comparison, display_error, display_error_highlighted, display_error_paged, display_notes, display_parts, display_results, display_reverse, display_workspaces, last_seen_line, lookup_codes, monitor_vehicle, relink_scan, show_drive_cycle, troubleshoot_code};
+use crate::{compare, completion, fuel_trim, help, idle, interrupt, looks_like_code, monitor, net, o2_tests, picker, scan, time, usage, DiagnosticsDatabase};
+
+// Where interactive mode reads its commands: the line editor, or a script
+// whose commands are echoed after the prompt as if they had been typed
+pub enum ReplInput<'a> {
+    Editor(Box<Editor<completion::ReplHelper<'a>, DefaultHistory>>),
+    Script(Box<dyn io::BufRead>),
+    // Locked per line, so commands such as `troubleshoot` can read their answers from the following lines
+    Stdin,
+}
+
+impl<'a> ReplInput<'a> {
+    pub fn editor(db: &'a DiagnosticsDatabase) -> Result<Self, Box<dyn Error>> {
+        let editor_config = line_config::Config::builder()
+            .completion_type(CompletionType::Circular)
+            .build();
+        let mut editor: Editor<completion::ReplHelper, DefaultHistory> = Editor::with_config(editor_config)?;
+        editor.set_helper(Some(completion::ReplHelper::new(db)));
+        Ok(ReplInput::Editor(Box::new(editor)))
+    }
+
+    // Prints above the prompt from another thread; only the line editor has one
+    fn printer(&mut self) -> Option<Box<dyn rustyline::ExternalPrinter + Send>> {
+        match self {
+            ReplInput::Editor(editor) => editor.create_external_printer().ok().map(|printer| Box::new(printer) as Box<dyn rustyline::ExternalPrinter + Send>),
+            _ => None,
+        }
+    }
+
+    // Forget the commands typed so far but the one just read, so arrow-up
+    // does not bring back an earlier session's
+    fn forget_history(&mut self, kept: &str) -> Result<(), Box<dyn Error>> {
+        if let ReplInput::Editor(editor) = self {
+            editor.clear_history()?;
+            if !kept.trim().is_empty() {
+                editor.add_history_entry(kept.trim())?;
+            }
+        }
+        Ok(())
+    }
+
+    // The next command, or None at the end of the input; blank lines and # comments in scripts are skipped.
+    // The status line is drawn above the prompt when typing, and left out of scripts and transcripts.
+    fn next_line(&mut self, prompt: &str, status: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let mut line = String::new();
+        let read = match self {
+            ReplInput::Editor(editor) => {
+                println!("{}", status.styled(Style::Banner));
+                return match editor.readline(prompt) {
+                    Ok(line) => {
+                        if !line.trim().is_empty() {
+                            editor.add_history_entry(line.trim())?;
+                        }
+                        Ok(Some(line))
+                    },
+                    Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => Ok(None),
+                    Err(e) => Err(e.into()),
+                };
+            },
+            ReplInput::Script(reader) => reader.read_line(&mut line)?,
+            ReplInput::Stdin => io::stdin().lock().read_line(&mut line)?,
+        };
+        if read == 0 {
+            return Ok(None);
+        }
+        let command = line.trim();
+        if command.is_empty() || command.starts_with('#') {
+            return Ok(Some(String::new()));
+        }
+        println!("{}{}", prompt, command);
+        Ok(Some(command.to_string()))
+    }
+}
+
+// What interactive mode keeps between commands, shown in the status bar above the prompt
+#[derive(Default)]
+pub struct ReplState {
+    // The adapter attached with `connect`, kept until `disconnect` or exit
+    pub adapter: Option<Adapter>,
+    // The VIN the connected vehicle reported
+    pub vin: Option<String>,
+    // The vehicle set with `vehicle set`, and the database as it applies to it
+    pub vehicle: Option<Vehicle>,
+    pub view: Option<DiagnosticsDatabase>,
+    // The working result set: the codes the last lookup, search, list or scan showed,
+    // and the command that produced them
+    pub query: Option<String>,
+    pub results: Vec<String>,
+    pub notes: Vec<Note>,
+    // The workspace loaded or last saved, which a bare `workspace save` replaces
+    pub workspace: Option<String>,
+    // The scan last added to a vehicle history, which a `garage` command without an id corrects
+    pub last_scan: Option<String>,
+}
+
+impl ReplState {
+    // The VIN of the vehicle set with `vehicle set`, or else of the connected one
+    fn current_vin(&self) -> Option<&str> {
+        self.vehicle.as_ref().and_then(|vehicle| vehicle.vin.as_deref()).or(self.vin.as_deref())
+    }
+
+    // Database and code count, vehicle, adapter, language and configuration file on one line
+    pub fn status_line(&self, db: &DiagnosticsDatabase, config: &Config) -> String {
+        let adapter = match &self.adapter {
+            Some(adapter) => tr!("repl-status-adapter", port = adapter.port.as_str(), protocol = adapter.protocol.as_str()),
+            None => tr!("repl-status-disconnected"),
+        };
+        let profile = match config::path().exists() {
+            true => config::path().display().to_string(),
+            false => tr!("repl-status-defaults"),
+        };
+        let database = match config.packs.len() {
+            0 => tr!("repl-status-db", codes = db.errors.len()),
+            packs => tr!("repl-status-db-packs", codes = db.errors.len(), packs = packs),
+        };
+        let line = tr!("repl-status",
+            database = database,
+            vehicle = self.vehicle.as_ref().map(Vehicle::to_string).or_else(|| self.vin.clone()).unwrap_or_else(|| tr!("repl-status-no-vehicle")),
+            adapter = adapter,
+            language = i18n::language(),
+            profile = profile,
+        );
+        line.chars().take(compare::terminal_width()).collect()
+    }
+    
+    // Make `codes` the working result set
+    fn set_results(&mut self, query: &str, codes: impl IntoIterator<Item = String>) {
+        self.query = Some(query.to_string());
+        self.results = codes.into_iter().collect();
+    }
+
+    // The vehicle, result set and notes, for `workspace save`
+    fn workspace(&self, now: u64) -> Workspace {
+        Workspace {
+            saved: now,
+            vehicle: self.vehicle.clone(),
+            query: self.query.clone(),
+            results: self.results.clone(),
+            notes: self.notes.clone(),
+        }
+    }
+
+    // Whether there is anything worth saving
+    fn has_work(&self) -> bool {
+        self.vehicle.is_some() || !self.results.is_empty() || !self.notes.is_empty()
+    }
+
+    // Start over after the idle screen; the adapter stays attached
+    fn clear(&mut self) {
+        *self = ReplState { adapter: self.adapter.take(), vin: self.vin.take(), ..ReplState::default() };
+    }
+
+    // Pick up where a saved workspace left off; the adapter stays as it is
+    fn restore(&mut self, name: &str, workspace: Workspace, database: &DiagnosticsDatabase, config: &Config) {
+        self.view = workspace.vehicle.as_ref().map(|vehicle| vehicle.database(database, config));
+        self.vehicle = workspace.vehicle;
+        self.query = workspace.query;
+        self.results = workspace.results;
+        self.notes = workspace.notes;
+        self.workspace = Some(name.to_string());
+    }
+
+    // Add codes to the history of the vehicle being worked on, if any
+    fn record(&mut self, config: &Config, source: &str, codes: &[&str]) {
+        let Some(vehicle) = &self.vehicle else { return };
+        let codes: Vec<String> = codes.iter().map(|code| code.to_uppercase()).collect();
+        match vehicle.record(config, source, &codes) {
+            Ok(id) => {
+                outln!("{}", tr!("vehicle-recorded", vehicle = vehicle.history_key(), id = id.as_str()).styled(Style::Muted));
+                self.last_scan = Some(id);
+            },
+            Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("vehicle-record-failed", error = e)),
+        }
+    }
+}
+
+// Load a workspace into interactive mode and show what it holds
+fn load_workspace(state: &mut ReplState, workspaces: &Workspaces, name: &str, database: &DiagnosticsDatabase, config: &Config) {
+    match workspaces.load(name) {
+        Ok(workspace) => {
+            let saved = time::format_datetime(workspace.saved);
+            state.restore(name, workspace, database, config);
+            outln!("{}", tr!("workspace-loaded", name = name.styled(Style::Category), saved = saved));
+            let db = state.view.as_ref().unwrap_or(database);
+            if let Some(vehicle) = &state.vehicle {
+                outln!("{}", tr!("vehicle-current", vehicle = vehicle.to_string().styled(Style::Category), codes = db.errors.len()));
+            }
+            display_results(db, state.query.as_deref(), &state.results);
+            if !state.notes.is_empty() {
+                display_notes(&state.notes);
+            }
+        },
+        Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+    }
+}
+
+// Record an interactive command for `usage`
+fn record_usage(config: &Config, session: &str, command: &str, codes: &[&str]) {
+    let codes = codes.iter().map(|code| code.to_uppercase()).collect();
+    usage::record(config, &usage::Event { timestamp: SystemClock.now(), session: session.to_string(), command: command.to_string(), codes });
+}
+
+// Run interactive mode
+pub fn run_interactive_mode(database: &DiagnosticsDatabase, config: &Config, mut input: ReplInput) -> Result<(), Box<dyn Error>> {
+    outln!("{}", tr!("repl-banner").styled(Style::Banner));
+    outln!("{}", tr!("repl-intro", help = "help".styled(Style::Command), exit = "exit".styled(Style::Command)));
+    
+    let prompt = format!("{} ", ">".styled(Style::Hint));
+    transcript::start();
+    let mut state = ReplState::default();
+    let usage_session = SystemClock.batch_id();
+    record_usage(config, &usage_session, "interactive", &[]);
+    let watchdog = config.interactive.timeout().map(|timeout| {
+        let mut printer = input.printer();
+        let screen = format!(
+            "\x1b[2J\x1b[H{}\n\n{}\n",
+            tr!("repl-banner").styled(Style::Banner),
+            tr!("repl-idle-screen", minutes = timeout.as_secs() / 60, resume = "resume".styled(Style::Command))
+        );
+        idle::Watchdog::start(timeout, move || {
+            if let Some(printer) = &mut printer {
+                let _ = printer.print(screen.clone());
+            }
+        })
+    });
+    
+    loop {
+        // A command stopped with Ctrl-C can leave the adapter in the middle of a request
+        if interrupt::interrupted() {
+            if let Some(connected) = &mut state.adapter {
+                connected.close();
+            }
+        }
+        if let Some(watchdog) = &watchdog {
+            // Saved before every prompt, so walking away loses nothing
+            if state.has_work() {
+                let saved = Workspaces::open(&config.data_dir()).and_then(|workspaces| workspaces.save(&config.interactive.autosave, &state.workspace(SystemClock.now())));
+                if let Err(e) = saved {
+                    outln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("repl-autosave-failed", error = e.to_string()));
+                }
+            }
+            watchdog.waiting();
+        }
+        let status = state.status_line(state.view.as_ref().unwrap_or(database), config);
+        let Some(line) = input.next_line(&prompt, &status)? else { break };
+        if watchdog.as_ref().is_some_and(|watchdog| watchdog.answered()) {
+            // Nothing of the previous session is left to save or scroll back to
+            state.clear();
+            transcript::start();
+            input.forget_history(&line)?;
+            outln!("{}", tr!("repl-idle-new-session", name = config.interactive.autosave.as_str(), resume = "resume".styled(Style::Command)));
+        }
+        // With a vehicle set, only the codes that apply to it
+        let db = state.view.as_ref().unwrap_or(database);
+        let input = line.trim();
+        
+        if input.is_empty() {
+            continue;
+        }
+        // Ctrl-C stops the command rather than interactive mode
+        let _watch = interrupt::watch();
+        transcript::begin_command(input);
+        
+        let parts: Vec<&str> = input.split_whitespace().collect();
+        
+        // Bare codes are looked up directly: `P0420` or `P0171 P0174`
+        if parts.iter().all(|part| looks_like_code(part)) {
+            lookup_codes(db, &parts, &archived_scans(config), state.current_vin())?;
+            state.record(config, "lookup", &parts);
+            state.set_results(input, parts.iter().map(|code| code.to_uppercase()));
+            record_usage(config, &usage_session, "lookup", &parts);
+            continue;
+        }
+        
+        let command = parts[0].to_lowercase();
+        // `pick` is recorded once a code is chosen, and leaving when the session ends
+        if completion::COMMANDS.contains(&command.as_str()) && !matches!(command.as_str(), "pick" | "exit" | "quit") {
+            let codes = match command.as_str() {
+                "lookup" | "parts" | "compare" | "troubleshoot" => &parts[1..],
+                _ => &[],
+            };
+            record_usage(config, &usage_session, &command, codes);
+        }
+        
+        match command.as_str() {
+            "exit" | "quit" => break,
+            
+            "help" if parts.len() > 1 => {
+                let name = parts[1..].join(" ");
+                let command = help::find_command(&name);
+                if let Some(command) = command {
+                    help::display_command(command);
+                }
+                match help::find_topic(&name) {
+                    Some(topic) if command.is_some() => help::page(&format!("\n{}\n\n{}", topic.title, topic.body))?,
+                    Some(topic) => help::page(&format!("{}\n\n{}", topic.title, topic.body))?,
+                    None if command.is_none() => outln!("{}", tr!("repl-unknown-topic", topic = name.styled(Style::Error))),
+                    None => {},
+                }
+            },
+
+            "help" => help::display_commands(),
+            
+            "lookup" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "lookup".styled(Style::Command), "<code>...".styled(Style::Argument))));
+                    continue;
+                }
+                
+                lookup_codes(db, &parts[1..], &archived_scans(config), state.current_vin())?;
+                state.record(config, "lookup", &parts[1..]);
+                state.set_results(input, parts[1..].iter().map(|code| code.to_uppercase()));
+            },
+            
+            "transcript" => {
+                if parts.len() < 3 || !parts[1].eq_ignore_ascii_case("save") {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "transcript save".styled(Style::Command), "<file.md>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                match transcript::save(parts[2]) {
+                    Ok(count) => outln!("{}", tr!("transcript-saved", count = count, path = parts[2])),
+                    Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("transcript-failed", error = e)),
+                }
+            },
+            
+            "parts" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "parts".styled(Style::Command), "<code>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                let code = parts[1].to_uppercase();
+                match db.lookup_error(&code) {
+                    Some(error) => display_parts(error, config, None),
+                    None => outln!("{}", tr!("code-not-found", code = code.styled(Style::Error))),
+                }
+            },
+            
+            "compare" => {
+                if parts.len() != 3 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "compare".styled(Style::Command), "<code> <code>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                display_comparison(db, parts[1], parts[2]);
+            },
+            
+            "troubleshoot" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "troubleshoot".styled(Style::Command), "<code>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                if let Err(e) = troubleshoot_code(config, parts[1]) {
+                    outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e);
+                }
+            },
+            
+            "connect" => {
+                if let Some(connected) = &state.adapter {
+                    outln!("{}", tr!("adapter-already-connected", port = connected.port.as_str(), disconnect = "disconnect".styled(Style::Command)));
+                    continue;
+                }
+                let Some(port) = parts.get(1).map(|port| port.to_string()).or_else(|| config.adapter.port.clone()) else {
+                    outln!("{}", tr!("adapter-no-port"));
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "connect".styled(Style::Command), "<port> [baud]".styled(Style::Argument))));
+                    continue;
+                };
+                let mut settings = config.adapter.clone();
+                if let Some(baud) = parts.get(2) {
+                    match baud.parse() {
+                        Ok(baud) => settings.baud = baud,
+                        Err(_) => {
+                            outln!("{}", tr!("repl-usage", usage = format!("{} {}", "connect".styled(Style::Command), "<port> [baud]".styled(Style::Argument))));
+                            continue;
+                        },
+                    }
+                }
+                
+                match connect_adapter(&port, &settings) {
+                    Ok(mut connected) => {
+                        state.vin = connected.read_vin().unwrap_or_default();
+                        state.adapter = Some(connected);
+                    },
+                    Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                }
+            },
+            
+            "monitor" => {
+                let Some(connected) = &mut state.adapter else {
+                    outln!("{}", tr!("adapter-not-connected", connect = "connect".styled(Style::Command)));
+                    continue;
+                };
+                let client = match &config.monitor.webhook {
+                    Some(_) => Some(net::Client::new(&config.network, &config.data_dir())?),
+                    None => None,
+                };
+                let tui = parts[1..].contains(&"--tui");
+                let names: Vec<String> = parts[1..].iter().filter(|name| **name != "--tui").map(|name| name.to_string()).collect();
+                if let Err(e) = monitor_vehicle(connected, config, client.as_ref(), &names, monitor::Window::default(), false, tui.then_some(db)) {
+                    outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e);
+                }
+            },
+            
+            "scan" => {
+                let Some(connected) = &mut state.adapter else {
+                    outln!("{}", tr!("adapter-not-connected", connect = "connect".styled(Style::Command)));
+                    continue;
+                };
+                match scan::read(connected) {
+                    Ok(found) => {
+                        scan::display(&found, db, &config.health);
+                        let codes: Vec<&str> = found.codes.iter().map(|dtc| dtc.code.as_str()).collect();
+                        state.record(config, "scan", &codes);
+                        state.set_results(input, found.codes.into_iter().map(|dtc| dtc.code));
+                    },
+                    Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                }
+            },
+            
+            "o2-tests" => {
+                let Some(connected) = &mut state.adapter else {
+                    outln!("{}", tr!("adapter-not-connected", connect = "connect".styled(Style::Command)));
+                    continue;
+                };
+                match o2_tests::read(connected) {
+                    Ok(results) => o2_tests::display(&results, db),
+                    Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                }
+            },
+            
+            "analyze" => {
+                if parts.get(1).is_none_or(|what| !what.eq_ignore_ascii_case("fuel-trims")) {
+                    outln!("{}", tr!("repl-usage", usage = "analyze fuel-trims".styled(Style::Command)));
+                    continue;
+                }
+                let Some(connected) = &mut state.adapter else {
+                    outln!("{}", tr!("adapter-not-connected", connect = "connect".styled(Style::Command)));
+                    continue;
+                };
+                match fuel_trim::sample_live(connected, &mut io::stdin().lock(), 20) {
+                    Ok(samples) => fuel_trim::display(&fuel_trim::analyze(&samples), db),
+                    Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                }
+            },
+            
+            "drive-cycle" => {
+                let watch = state.adapter.is_some();
+                let make = parts.get(1).copied().or_else(|| state.vehicle.as_ref().and_then(|vehicle| vehicle.make.as_deref()));
+                if let Err(e) = show_drive_cycle(config, state.adapter.as_mut(), make, &[], watch) {
+                    outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e);
+                }
+            },
+            
+            "disconnect" => match state.adapter.take() {
+                Some(connected) => {
+                    state.vin = None;
+                    outln!("{}", tr!("adapter-disconnected", port = connected.port.as_str()));
+                },
+                None => outln!("{}", tr!("adapter-not-connected", connect = "connect".styled(Style::Command))),
+            },
+            
+            "pick" => match picker::pick(db) {
+                Ok(Some(error)) => {
+                    display_error_paged(error)?;
+                    if let Some(line) = last_seen_line(&archived_scans(config), &error.code, state.current_vin()) {
+                        outln!("{}", line.styled(Style::Muted));
+                    }
+                    let code = error.code.clone();
+                    record_usage(config, &usage_session, "pick", &[&code]);
+                    state.record(config, "lookup", &[&code]);
+                    state.set_results(&format!("lookup {}", code), [code]);
+                },
+                Ok(None) => {},
+                Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+            },
+            
+            "vehicle" => match parts.get(1).map(|action| action.to_lowercase()).as_deref() {
+                Some("set") => {
+                    let args: Vec<&str> = match (&parts[2..], &state.vin) {
+                        ([], Some(vin)) => vec![vin.as_str()],
+                        (args, _) => args.to_vec(),
+                    };
+                    if args.is_empty() {
+                        outln!("{}", tr!("repl-usage", usage = format!("{} {}", "vehicle set".styled(Style::Command), "<VIN | make [model] [year]>".styled(Style::Argument))));
+                        continue;
+                    }
+                    match Vehicle::parse(&args, SystemClock.now()) {
+                        Ok(vehicle) => {
+                            let view = vehicle.database(database, config);
+                            outln!("{}", tr!("vehicle-set", vehicle = vehicle.to_string().styled(Style::Category), codes = view.errors.len()));
+                            state.vehicle = Some(vehicle);
+                            state.view = Some(view);
+                        },
+                        Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                    }
+                },
+                Some("clear") => {
+                    state.vehicle = None;
+                    state.view = None;
+                    outln!("{}", tr!("vehicle-cleared"));
+                },
+                None => match &state.vehicle {
+                    Some(vehicle) => outln!("{}", tr!("vehicle-current", vehicle = vehicle.to_string().styled(Style::Category), codes = db.errors.len())),
+                    None => outln!("{}", tr!("vehicle-none", set = "vehicle set".styled(Style::Command))),
+                },
+                Some(_) => outln!("{}", tr!("repl-usage", usage = format!("{} | {} | {}", "vehicle".styled(Style::Command), "vehicle set <VIN | make [model] [year]>".styled(Style::Command), "vehicle clear".styled(Style::Command)))),
+            },
+            
+            "garage" => {
+                let usage = || tr!("repl-usage", usage = format!("{} | {}", "garage unlink [scan-id]".styled(Style::Command), "garage move [scan-id] --to <VIN>".styled(Style::Command)));
+                // Without an id, the scan this session last added to a vehicle history
+                let (id, rest) = match parts.get(2) {
+                    Some(id) if *id != "--to" => (Some(id.to_string()), &parts[3..]),
+                    _ => (state.last_scan.clone(), parts.get(2..).unwrap_or_default()),
+                };
+                let to = match (parts.get(1).map(|action| action.to_lowercase()).as_deref(), rest) {
+                    (Some("unlink"), []) => None,
+                    (Some("move"), ["--to", vin]) => Some(*vin),
+                    _ => {
+                        outln!("{}", usage());
+                        continue;
+                    },
+                };
+                let Some(id) = id else {
+                    outln!("{}: {}", tr!("error-prefix").styled(Style::Error), tr!("garage-no-scan"));
+                    continue;
+                };
+                match relink_scan(config, &id, to) {
+                    Ok(message) => outln!("{}", message),
+                    Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                }
+            },
+            
+            "status" => match &mut state.adapter {
+                Some(connected) => display_adapter_status(connected),
+                None => outln!("{}", tr!("adapter-not-connected", connect = "connect".styled(Style::Command))),
+            },
+            
+            "system" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "system".styled(Style::Command), "<system_name>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                let system = parts[1];
+                let errors = db.list_errors_by_system(system);
+                if errors.is_empty() {
+                    outln!("{}", tr!("none-system", system = system.styled(Style::Error)));
+                } else {
+                    outln!("{}", tr!("found-system", count = errors.len().styled(Style::Success), system = system.styled(Style::Category)));
+                    for error in &errors {
+                        display_error(error);
+                    }
+                    let codes: Vec<String> = errors.iter().map(|error| error.code.clone()).collect();
+                    state.set_results(input, codes);
+                }
+            },
+            
+            "severity" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "severity".styled(Style::Command), "<level>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                let severity = parts[1];
+                let errors = db.list_errors_by_severity(severity);
+                if errors.is_empty() {
+                    outln!("{}", tr!("none-severity", severity = severity.styled(Style::Error)));
+                } else {
+                    outln!("{}", tr!("found-severity", count = errors.len().styled(Style::Success), severity = severity.styled(Style::Category)));
+                    for error in &errors {
+                        display_error(error);
+                    }
+                    let codes: Vec<String> = errors.iter().map(|error| error.code.clone()).collect();
+                    state.set_results(input, codes);
+                }
+            },
+            
+            "search" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "search".styled(Style::Command), "<keyword>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                let keyword = parts[1];
+                let page = db.search(keyword, &SearchOptions::default());
+                if page.hits.is_empty() {
+                    outln!("{}", tr!("none-keyword", keyword = keyword.styled(Style::Error)));
+                } else {
+                    outln!("{}", tr!("found-keyword", count = page.total.styled(Style::Success), keyword = keyword.styled(Style::Category)));
+                    for hit in &page.hits {
+                        display_error_highlighted(&hit.error, &hit.highlights);
+                    }
+                    let codes: Vec<String> = page.hits.iter().map(|hit| hit.error.code.clone()).collect();
+                    state.set_results(input, codes);
+                }
+            },
+            
+            "reverse" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "reverse".styled(Style::Command), "<text>".styled(Style::Argument))));
+                    continue;
+                }
+                
+                let phrase = parts[1..].join(" ");
+                let page = db.reverse_lookup(&phrase).page(&SearchOptions { limit: Some(5), ..SearchOptions::default() });
+                display_reverse(&phrase, &page);
+                if !page.hits.is_empty() {
+                    let codes: Vec<String> = page.hits.iter().map(|hit| hit.error.code.clone()).collect();
+                    state.set_results(input, codes);
+                }
+            },
+
+            "results" => display_results(db, state.query.as_deref(), &state.results),
+
+            "note" => {
+                if parts.len() < 2 {
+                    outln!("{}", tr!("repl-usage", usage = format!("{} {}", "note".styled(Style::Command), "<text>".styled(Style::Argument))));
+                    continue;
+                }
+                state.notes.push(Note { timestamp: SystemClock.now(), text: parts[1..].join(" ") });
+                outln!("{}", tr!("note-added", count = state.notes.len()));
+            },
+
+            "notes" => display_notes(&state.notes),
+
+            "workspace" => {
+                let workspaces = match Workspaces::open(&config.data_dir()) {
+                    Ok(workspaces) => workspaces,
+                    Err(e) => {
+                        outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e);
+                        continue;
+                    },
+                };
+                match (parts.get(1).map(|action| action.to_lowercase()).as_deref(), parts.get(2)) {
+                    (Some("save"), name) => {
+                        let Some(name) = name.map(|name| name.to_string()).or_else(|| state.workspace.clone()) else {
+                            outln!("{}", tr!("repl-usage", usage = format!("{} {}", "workspace save".styled(Style::Command), "<name>".styled(Style::Argument))));
+                            continue;
+                        };
+                        match workspaces.save(&name, &state.workspace(SystemClock.now())) {
+                            Ok(()) => {
+                                outln!("{}", tr!("workspace-saved", name = name.as_str().styled(Style::Category), results = state.results.len(), notes = state.notes.len()));
+                                state.workspace = Some(name);
+                            },
+                            Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                        }
+                    },
+                    (Some("load"), Some(name)) => load_workspace(&mut state, &workspaces, name, database, config),
+                    (Some("list"), _) => match workspaces.list() {
+                        Ok(list) => display_workspaces(&list),
+                        Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+                    },
+                    _ => outln!("{}", tr!("repl-usage", usage = format!("{} | {} | {}", "workspace save [name]".styled(Style::Command), "workspace load <name>".styled(Style::Command), "workspace list".styled(Style::Command)))),
+                }
+            },
+            
+            "resume" => match Workspaces::open(&config.data_dir()) {
+                Ok(workspaces) => load_workspace(&mut state, &workspaces, &config.interactive.autosave, database, config),
+                Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+            },
+            
+            _ => outln!("{} {}", tr!("repl-unknown-command").styled(Style::Error), tr!("repl-help-hint", help = "help".styled(Style::Command))),
+        }
+    }
+    
+    record_usage(config, &usage_session, "exit", &[]);
+    outln!("{}", tr!("repl-exit"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::{fs, io};
+    use crate::adapter::Adapter;
+    use crate::testing::{assert_golden, init, sample_database, FakeElm, TempDir};
+
+    #[test]
+    fn repl_script_runs_commands_like_a_session() {
+        init();
+        let dir = TempDir::new("script");
+        let saved = dir.join("session.md");
+        let script = format!("# smoke test\nP0101 C0035\nseverity critical\n\nbogus\ntranscript save {}\nexit\nlookup P0300\n", saved.display());
+        let input = super::ReplInput::Script(Box::new(io::Cursor::new(script)));
+        super::run_interactive_mode(&sample_database(), &crate::config::Config::default(), input).unwrap();
+        let transcript = fs::read_to_string(&saved).unwrap();
+        assert_golden("repl-script.md", &transcript.replace(&saved.display().to_string(), "session.md"));
+    }
+
+    #[test]
+    fn repl_status_line_follows_the_session_state() {
+        init();
+        let db = sample_database();
+        let config = crate::config::Config::default();
+        let mut state = super::ReplState::default();
+        assert_eq!(state.status_line(&db, &config), "4 codes | no vehicle | no adapter | en | default settings");
+
+        let adapter = Adapter::initialize(Box::new(FakeElm::car()), "fake", Duration::from_secs(1)).unwrap();
+        state.adapter = Some(adapter);
+        state.vin = Some("1HGCM82633A004352".to_string());
+        assert_eq!(state.status_line(&db, &config), "4 codes | 1HGCM82633A004352 | fake: ISO 15765-4 (CAN 11/500) | en | default settings");
+    }
+}
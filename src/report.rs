@@ -0,0 +1,315 @@
+// Reports put together section by section (`ReportBuilder`).
+//
+// The commands write reports with fixed layouts. A program embedding
+// cars-scanner lays out its own: codes, archived scans, headings, text,
+// tables and bar charts, in any order, under the shop's letterhead:
+//
+//   let report = ReportBuilder::new("Pre-purchase inspection")
+//       .letterhead(letterhead)
+//       .scan(&record, &db)
+//       .heading("Road test")
+//       .text("No pulling under braking; slight wind noise at 100 km/h.")
+//       .codes(db.lookup_error("P0420"));
+//   report.write("inspection.html")?;
+//
+// Rendering goes through a `Format`: "text" and "html" are registered from
+// the start, and `register` adds another, e.g. Markdown, or replaces one.
+// `write` picks the format named like the file's extension and falls back
+// to text, as the commands do. `report` itself is a `ReportBuilder` with a
+// repair order, the code and its labor operations.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use crate::archive::ScanRecord;
+use crate::branding::Letterhead;
+use crate::charts::{self, Bar};
+use crate::i18n::tr;
+use crate::labor::{self, LaborOperation};
+use crate::repair_order::RepairOrder;
+use crate::{escape_html, html_document_end, html_document_start, time, DiagnosticsDatabase, ErrorCode};
+
+// Used for files whose extension names no registered format
+const FALLBACK_FORMAT: &str = "text";
+
+pub enum Section {
+    Heading(String),
+    // Paragraphs separated by blank lines
+    Text(String),
+    Codes(Vec<Arc<ErrorCode>>),
+    RepairOrder(RepairOrder),
+    Labor(Vec<LaborOperation>),
+    Table { caption: String, headers: Vec<String>, rows: Vec<Vec<String>> },
+    Chart { title: String, bars: Vec<Bar> },
+}
+
+// A way of writing a report; the parts are concatenated in order
+pub trait Format {
+    // Everything before the first section
+    fn start(&self, title: &str, letterhead: &Letterhead) -> String;
+    fn section(&self, section: &Section) -> String;
+    // Everything after the last section
+    fn end(&self, letterhead: &Letterhead) -> String;
+}
+
+pub struct Text;
+
+pub struct Html;
+
+impl Format for Text {
+    fn start(&self, _title: &str, letterhead: &Letterhead) -> String {
+        letterhead.text_header()
+    }
+
+    fn section(&self, section: &Section) -> String {
+        match section {
+            Section::Heading(heading) => format!("{}\n\n", heading),
+            Section::Text(text) => format!("{}\n\n", text.trim_end()),
+            Section::Codes(errors) => errors.iter().map(|error| error.to_text()).collect::<Vec<_>>().join("\n"),
+            Section::RepairOrder(order) => order.to_text(),
+            Section::Labor(operations) if operations.is_empty() => String::new(),
+            Section::Labor(operations) => labor::to_text(operations),
+            Section::Table { caption, headers, rows } => {
+                let columns = headers.len().max(rows.iter().map(Vec::len).max().unwrap_or_default());
+                let widths: Vec<usize> = (0..columns)
+                    .map(|column| std::iter::once(headers).chain(rows)
+                        .filter_map(|row| row.get(column))
+                        .map(|cell| cell.chars().count())
+                        .max()
+                        .unwrap_or_default())
+                    .collect();
+                let line = |row: &Vec<String>| {
+                    let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+                    format!("  {}\n", cells.join("  ").trim_end())
+                };
+                let mut text = format!("{}\n", caption);
+                if !headers.is_empty() {
+                    text.push_str(&line(headers));
+                }
+                text.extend(rows.iter().map(line));
+                text.push('\n');
+                text
+            },
+            Section::Chart { title, bars } => {
+                let width = bars.iter().map(|bar| bar.label.chars().count()).max().unwrap_or_default();
+                let mut text = format!("{}\n", title);
+                text.extend(bars.iter().map(|bar| format!("  {:<width$}  {}\n", bar.label, bar.value, width = width)));
+                text.push('\n');
+                text
+            },
+        }
+    }
+
+    fn end(&self, letterhead: &Letterhead) -> String {
+        letterhead.text_footer()
+    }
+}
+
+impl Format for Html {
+    fn start(&self, title: &str, letterhead: &Letterhead) -> String {
+        html_document_start(title, letterhead)
+    }
+
+    fn section(&self, section: &Section) -> String {
+        match section {
+            Section::Heading(heading) => format!("<h2>{}</h2>\n", escape_html(heading)),
+            Section::Text(text) => text.split("\n\n")
+                .filter(|paragraph| !paragraph.trim().is_empty())
+                .map(|paragraph| format!("<p>{}</p>\n", escape_html(paragraph.trim())))
+                .collect(),
+            Section::Codes(errors) => errors.iter().map(|error| error.to_html()).collect(),
+            Section::RepairOrder(order) => order.to_html(),
+            Section::Labor(operations) if operations.is_empty() => String::new(),
+            Section::Labor(operations) => labor::to_html(operations),
+            Section::Table { caption, headers, rows } => {
+                let cells = |row: &Vec<String>, tag: &str| row.iter().map(|cell| format!("<{}>{}</{}>", tag, escape_html(cell), tag)).collect::<String>();
+                let mut html = format!("<h3>{}</h3>\n<table>\n", escape_html(caption));
+                if !headers.is_empty() {
+                    html.push_str(&format!("<tr>{}</tr>\n", cells(headers, "th")));
+                }
+                html.extend(rows.iter().map(|row| format!("<tr>{}</tr>\n", cells(row, "td"))));
+                html.push_str("</table>\n");
+                html
+            },
+            Section::Chart { title, bars } => charts::bar_chart(title, bars).unwrap_or_default(),
+        }
+    }
+
+    fn end(&self, letterhead: &Letterhead) -> String {
+        html_document_end(letterhead)
+    }
+}
+
+pub struct ReportBuilder {
+    title: String,
+    letterhead: Letterhead,
+    sections: Vec<Section>,
+    formats: BTreeMap<String, Box<dyn Format>>,
+}
+
+impl ReportBuilder {
+    pub fn new(title: impl Into<String>) -> Self {
+        let mut formats: BTreeMap<String, Box<dyn Format>> = BTreeMap::new();
+        formats.insert("text".to_string(), Box::new(Text));
+        formats.insert("html".to_string(), Box::new(Html));
+        ReportBuilder { title: title.into(), letterhead: Letterhead::default(), sections: Vec::new(), formats }
+    }
+
+    pub fn letterhead(mut self, letterhead: Letterhead) -> Self {
+        self.letterhead = letterhead;
+        self
+    }
+
+    pub fn section(mut self, section: Section) -> Self {
+        self.sections.push(section);
+        self
+    }
+
+    pub fn repair_order(self, order: RepairOrder) -> Self {
+        self.section(Section::RepairOrder(order))
+    }
+
+    // Codes shown in full, as `lookup` shows them; nothing when there are none
+    pub fn codes(self, errors: impl IntoIterator<Item = Arc<ErrorCode>>) -> Self {
+        let errors: Vec<Arc<ErrorCode>> = errors.into_iter().collect();
+        match errors.is_empty() {
+            true => self,
+            false => self.section(Section::Codes(errors)),
+        }
+    }
+
+    pub fn labor(self, operations: &[LaborOperation]) -> Self {
+        self.section(Section::Labor(operations.to_vec()))
+    }
+
+    // For programs that build their own reports; the commands never do
+    pub fn heading(self, heading: impl Into<String>) -> Self {
+        self.section(Section::Heading(heading.into()))
+    }
+
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.section(Section::Text(text.into()))
+    }
+
+    pub fn table(self, caption: impl Into<String>, headers: &[&str], rows: Vec<Vec<String>>) -> Self {
+        let headers = headers.iter().map(|header| header.to_string()).collect();
+        self.section(Section::Table { caption: caption.into(), headers, rows })
+    }
+
+    pub fn chart(self, title: impl Into<String>, bars: Vec<Bar>) -> Self {
+        self.section(Section::Chart { title: title.into(), bars })
+    }
+
+    // An archived scan as a table of its codes, with the VIN and time as caption
+    pub fn scan(self, record: &ScanRecord, db: &DiagnosticsDatabase) -> Self {
+        let rows = record.codes.iter()
+            .map(|code| match db.lookup_error(code) {
+                Some(error) => vec![code.clone(), error.description.clone(), error.severity.clone(), error.system.clone()],
+                None => vec![code.clone(), String::new(), String::new(), String::new()],
+            })
+            .collect();
+        let caption = tr!("report-scan-caption", vin = record.vin.as_str(), time = time::format_datetime(record.timestamp));
+        let headers = [tr!("column-code"), tr!("column-description"), tr!("column-severity"), tr!("column-system")];
+        self.table(caption, &headers.iter().map(String::as_str).collect::<Vec<_>>(), rows)
+    }
+
+    // Add a format, or replace the one registered under the same name
+    pub fn register(mut self, name: &str, format: Box<dyn Format>) -> Self {
+        self.formats.insert(name.to_lowercase(), format);
+        self
+    }
+
+    pub fn render(&self, format: &str) -> Result<String, String> {
+        let format = self.formats.get(&format.to_lowercase()).ok_or_else(|| {
+            let known: Vec<&str> = self.formats.keys().map(String::as_str).collect();
+            tr!("report-unknown-format", format = format, known = known.join(", "))
+        })?;
+        let mut report = format.start(&self.title, &self.letterhead);
+        report.extend(self.sections.iter().map(|section| format.section(section)));
+        report.push_str(&format.end(&self.letterhead));
+        Ok(report)
+    }
+
+    // The format for a file: the one named like its extension, or text
+    pub fn format_for(&self, path: &Path) -> &str {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        match self.formats.get_key_value(&extension) {
+            Some((name, _)) => name,
+            None => FALLBACK_FORMAT,
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        fs::write(path, self.render(self.format_for(path))?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::branding::Letterhead;
+    use crate::charts::Bar;
+    use crate::testing::{init, sample_database, sample_letterhead, scan, TempDir};
+    use super::{Format, ReportBuilder, Section};
+
+    #[test]
+    fn report_builder_lays_out_custom_sections_in_any_registered_format() {
+        init();
+        struct Markdown;
+        impl Format for Markdown {
+            fn start(&self, title: &str, _letterhead: &Letterhead) -> String {
+                format!("# {}\n", title)
+            }
+
+            fn section(&self, section: &Section) -> String {
+                match section {
+                    Section::Heading(heading) => format!("\n## {}\n", heading),
+                    Section::Codes(errors) => errors.iter().map(|error| format!("\n- **{}** {}\n", error.code, error.description)).collect(),
+                    _ => String::new(),
+                }
+            }
+
+            fn end(&self, _letterhead: &Letterhead) -> String {
+                String::new()
+            }
+        }
+
+        let db = sample_database();
+        let report = ReportBuilder::new("Pre-purchase inspection")
+            .letterhead(sample_letterhead())
+            .scan(&scan("1", "1HGCM82633A004352", 0, &["P0300", "X9999"]), &db)
+            .heading("Road test")
+            .text("No pulling under braking.\n\nSlight <wind> noise.")
+            .table("Tyres", &["Wheel", "Tread"], vec![vec!["Front left".to_string(), "6 mm".to_string()]])
+            .chart("Codes by system", vec![Bar { label: "Engine".to_string(), value: 2, color: None }])
+            .codes(db.shared_error("C0035"))
+            .codes(None)
+            .register("md", Box::new(Markdown));
+
+        let text = report.render("text").unwrap();
+        assert!(text.starts_with("Main Street Auto & Tire\n"));
+        assert!(text.contains("  P0300  Random/Multiple Cylinder Misfire Detected  High      Engine\n  X9999\n\nRoad test\n"));
+        assert!(text.contains("\nRoad test\n\nNo pulling under braking.\n\nSlight <wind> noise.\n\n"));
+        assert!(text.contains("\nTyres\n  Wheel       Tread\n  Front left  6 mm\n\n"));
+        assert!(text.contains("\nCodes by system\n  Engine  2\n\nError Code: C0035\n"));
+
+        let html = report.render("HTML").unwrap();
+        assert!(html.contains("<h1>Pre-purchase inspection</h1>"));
+        assert!(html.contains("<p>Slight &lt;wind&gt; noise.</p>"));
+        assert!(html.contains("<tr><th>Wheel</th><th>Tread</th></tr>\n<tr><td>Front left</td><td>6 mm</td></tr>"));
+        assert!(html.contains("<svg class='chart'"));
+        assert!(html.contains("<tr><td>X9999</td><td></td><td></td><td></td></tr>"));
+
+        assert_eq!(report.render("md").unwrap(), "# Pre-purchase inspection\n\n## Road test\n\n- **C0035** Left Front Wheel Speed Sensor Circuit\n");
+        assert_eq!(report.format_for(std::path::Path::new("out/report.MD")), "md");
+        assert_eq!(report.format_for(std::path::Path::new("report.pdf")), "text");
+        assert!(report.render("pdf").unwrap_err().contains("known: html, md, text"));
+        let dir = TempDir::new("builder");
+        let path = dir.join("report.md");
+        report.write(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("# Pre-purchase inspection\n"));
+    }
+}
@@ -0,0 +1,49 @@
+// Shared safety layer for commands that change or erase data.
+//
+// Every destructive command flattens `SafetyArgs` into its arguments and
+// calls `confirm` before acting, so `--dry-run`, the y/N prompt and `--yes`
+// behave the same everywhere.
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use clap::Args;
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+
+#[derive(Args, Debug, Clone, Copy)]
+pub struct SafetyArgs {
+    /// Show what would happen without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Do not ask for confirmation (required when not running in a terminal)
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl SafetyArgs {
+    // Decide whether to carry out an action described in one line.
+    // Without --yes, a terminal user is asked; anywhere else the action is refused.
+    pub fn confirm(&self, action: &str) -> Result<bool, Box<dyn Error>> {
+        if self.dry_run {
+            outln!("{} {}", tr!("safety-dry-run").styled(Style::Warning), action);
+            return Ok(false);
+        }
+        if self.yes {
+            return Ok(true);
+        }
+        if !io::stdin().is_terminal() {
+            return Err(tr!("safety-needs-yes", action = action).into());
+        }
+
+        print!("{} {} ", action, tr!("safety-prompt").styled(Style::Warning));
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let confirmed = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+        if !confirmed {
+            outln!("{}", tr!("safety-cancelled"));
+        }
+        Ok(confirmed)
+    }
+}
@@ -0,0 +1,657 @@
+// Trouble codes read from a vehicle, with the MIL and the state of each code.
+//
+// A bare list of codes hides what changes the advice: whether the MIL is on,
+// how many codes the engine computer says it holds, and whether each code is
+// confirmed, only pending or permanent. Modes 03, 07 and 0A give those states
+// over any OBD-II protocol. On CAN, the UDS service 19 02 adds the ISO 14229
+// status byte of each code, with bits such as "test failed since last clear"
+// that OBD-II does not carry. For each such code, service 19 06 asks for
+// the extended data records most ECUs keep: how many operation cycles the
+// test failed on (the occurrence counter, record 01) and how many it has
+// passed on since (the aging counter, record 02). Together with "failed this
+// operation cycle" they tell a hard fault, failing now, from one that comes
+// and goes and from a one-off glitch that has not come back.
+//
+// PIDs 31 and 4E say how far the vehicle has been driven, and for how long
+// the engine has run, since the codes were last cleared; 21 and 4D say the
+// same for the MIL. Codes cleared a few kilometres ago suggest a fault was
+// erased to hide it, as before a sale, so the scan says so up front.
+//
+// `scan` takes several --connect targets for fleet gateways with many
+// vehicles plugged in: each adapter is connected and read on its own
+// thread, with a spinner per vehicle, and the results are shown together
+// once every vehicle is done.
+//
+// `scan --module` reads a single ECU, selected by name or CAN address (see
+// adapter.rs), so a module's codes can be looked at apart from the others.
+use std::error::Error;
+use std::thread;
+use crate::adapter::{self, Adapter, AdapterSettings, Module};
+use crate::archive::{Archive, Clock, ScanRecord, UNKNOWN_VIN};
+use crate::health::HealthWeights;
+use crate::i18n::tr;
+use crate::pids;
+use crate::presentation::{Style, Styled};
+use crate::progress;
+use crate::readiness;
+use crate::transcript::outln;
+use crate::DiagnosticsDatabase;
+
+// ISO 14229 DTC status bits
+const TEST_FAILED: u8 = 0x01;
+const TEST_FAILED_THIS_CYCLE: u8 = 0x02;
+const PENDING: u8 = 0x04;
+const CONFIRMED: u8 = 0x08;
+const TEST_FAILED_SINCE_CLEAR: u8 = 0x20;
+const WARNING_INDICATOR: u8 = 0x80;
+
+// Extended data records of UDS 19 06 holding the occurrence and aging counters. ISO 14229
+// leaves the numbering to the manufacturer; these are the ones most use.
+const OCCURRENCE_RECORD: u8 = 0x01;
+const AGING_RECORD: u8 = 0x02;
+
+// Less than this since the codes were cleared is too little for the monitors to have run:
+// a drive cycle or two, or a few hours of engine running
+const RECENTLY_CLEARED_KM: f64 = 200.0;
+const RECENTLY_CLEARED_MINUTES: f64 = 180.0;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dtc {
+    pub code: String,
+    // Listed by mode 03, or confirmed in the UDS status
+    pub confirmed: bool,
+    // Listed by mode 07, or pending in the UDS status
+    pub pending: bool,
+    // Listed by mode 0A
+    pub permanent: bool,
+    // UDS status byte, when an ECU answered service 19
+    pub status: Option<u8>,
+    // Operation cycles the test failed on, from the UDS occurrence counter
+    pub occurrences: Option<u8>,
+    // Operation cycles the test passed on since it last failed, from the UDS aging counter
+    pub aging: Option<u8>,
+}
+
+impl Dtc {
+    fn has(&self, bit: u8) -> bool {
+        self.status.is_some_and(|status| status & bit != 0)
+    }
+
+    // The states to show, most important first
+    fn states(&self) -> Vec<String> {
+        let mut states = Vec::new();
+        if self.confirmed {
+            states.push(tr!("scan-confirmed"));
+        }
+        if self.pending {
+            states.push(tr!("scan-pending"));
+        }
+        if self.permanent {
+            states.push(tr!("scan-permanent"));
+        }
+        if self.has(TEST_FAILED) {
+            states.push(tr!("scan-test-failed"));
+        }
+        if self.has(TEST_FAILED_THIS_CYCLE) {
+            states.push(tr!("scan-failed-this-cycle"));
+        }
+        if self.has(TEST_FAILED_SINCE_CLEAR) {
+            states.push(tr!("scan-failed-since-clear"));
+        }
+        if self.has(WARNING_INDICATOR) {
+            states.push(tr!("scan-warning-indicator"));
+        }
+        states
+    }
+
+    // The counters ECUs keep for the code, e.g. "failed on 3 drive cycles, passed on 12 since"
+    fn counters(&self) -> Option<String> {
+        let occurrences = self.occurrences.map(|count| tr!("scan-occurrences", count = count));
+        let aging = self.aging.map(|count| tr!("scan-aging", count = count));
+        let counters: Vec<String> = occurrences.into_iter().chain(aging).collect();
+        (!counters.is_empty()).then(|| counters.join(", "))
+    }
+
+    // Whether the fault is hard (failing now, on this drive cycle), intermittent (failed on
+    // several cycles but passing now) or a one-off glitch; None without the UDS status to tell
+    pub fn pattern(&self) -> Option<Pattern> {
+        if self.has(TEST_FAILED) && self.has(TEST_FAILED_THIS_CYCLE) {
+            return Some(Pattern::Hard);
+        }
+        match self.occurrences {
+            Some(0 | 1) => Some(Pattern::Once),
+            Some(_) => Some(Pattern::Intermittent),
+            None => None,
+        }
+    }
+
+    // What the combination of states means for the customer, when it is not the usual MIL-on fault
+    fn advice(&self, mil: bool) -> Option<String> {
+        match (self.confirmed, self.pending, self.permanent) {
+            (true, _, _) if !mil => Some(tr!("scan-advice-mil-off")),
+            (false, true, _) => Some(tr!("scan-advice-pending")),
+            (false, false, true) => Some(tr!("scan-advice-permanent")),
+            _ => None,
+        }
+    }
+}
+
+// How a fault has behaved, from its UDS status and counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    Hard,
+    Intermittent,
+    Once,
+}
+
+impl Pattern {
+    fn describe(self) -> String {
+        match self {
+            Pattern::Hard => tr!("scan-pattern-hard"),
+            Pattern::Intermittent => tr!("scan-pattern-intermittent"),
+            Pattern::Once => tr!("scan-pattern-once"),
+        }
+    }
+}
+
+// Distance driven and minutes of engine running; None when the vehicle does not report it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Counter {
+    pub km: Option<f64>,
+    pub minutes: Option<f64>,
+}
+
+impl Counter {
+    fn is_known(&self) -> bool {
+        self.km.is_some() || self.minutes.is_some()
+    }
+
+    // E.g. "5 km, 12 min of engine running"
+    fn describe(&self) -> String {
+        let km = self.km.map(|km| tr!("scan-distance", km = km));
+        let minutes = self.minutes.map(|minutes| tr!("scan-running-time", minutes = minutes));
+        km.into_iter().chain(minutes).collect::<Vec<_>>().join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scan {
+    pub mil: bool,
+    // Codes the ECUs count in PID 01; modules that do not list them can make this differ
+    pub reported: u8,
+    pub codes: Vec<Dtc>,
+    // Since the codes were last cleared (PIDs 31 and 4E)
+    pub since_clear: Counter,
+    // Since the MIL came on (PIDs 21 and 4D)
+    pub with_mil: Counter,
+}
+
+impl Scan {
+    fn entry(&mut self, code: &str) -> &mut Dtc {
+        match self.codes.iter().position(|dtc| dtc.code == code) {
+            Some(index) => &mut self.codes[index],
+            None => {
+                self.codes.push(Dtc { code: code.to_string(), ..Dtc::default() });
+                self.codes.last_mut().expect("just pushed")
+            },
+        }
+    }
+
+    // Combine the code lists of modes 03, 07 and 0A with the UDS status of each code
+    pub fn new(mil: bool, reported: u8, stored: &[String], pending: &[String], permanent: &[String], statuses: &[(String, u8)]) -> Self {
+        let mut scan = Scan { mil, reported, ..Scan::default() };
+        for code in stored {
+            scan.entry(code).confirmed = true;
+        }
+        for code in pending {
+            scan.entry(code).pending = true;
+        }
+        for code in permanent {
+            scan.entry(code).permanent = true;
+        }
+        for (code, status) in statuses {
+            let dtc = scan.entry(code);
+            dtc.status = Some(dtc.status.unwrap_or_default() | status);
+            dtc.confirmed |= status & CONFIRMED != 0;
+            dtc.pending |= status & PENDING != 0;
+        }
+        scan
+    }
+
+    // Whether the codes were cleared too recently for the monitors to have run since
+    pub fn recently_cleared(&self) -> bool {
+        match self.since_clear {
+            Counter { km: Some(km), .. } => km < RECENTLY_CLEARED_KM,
+            Counter { minutes: Some(minutes), .. } => minutes < RECENTLY_CLEARED_MINUTES,
+            _ => false,
+        }
+    }
+}
+
+// Decode a UDS 19 02 answer: 59 02, the status availability mask, then four bytes per code
+// (two for the code, the failure type, the status). Each code comes with its three bytes,
+// which ask for its extended data.
+pub fn decode_uds_status(data: &[u8]) -> Vec<(String, [u8; 3], u8)> {
+    let Some(records) = data.strip_prefix(&[0x59, 0x02]).and_then(|rest| rest.get(1..)) else { return Vec::new() };
+    records.chunks_exact(4).map(|record| (adapter::decode_dtc(record[0], record[1]), [record[0], record[1], record[2]], record[3])).collect()
+}
+
+// Decode a UDS 19 06 answer for one extended data record: 59 06, the three bytes of the
+// code, its status, the record number, then the record, here a one-byte counter
+pub fn decode_extended_record(data: &[u8], dtc: [u8; 3], record: u8) -> Option<u8> {
+    let rest = data.strip_prefix(&[0x59, 0x06])?.strip_prefix(&dtc)?.get(1..)?;
+    rest.strip_prefix(&[record])?.first().copied()
+}
+
+// A counter of one code from the ECUs that keep it; None when none answers with it
+fn read_extended_record(adapter: &mut Adapter, dtc: [u8; 3], record: u8) -> Result<Option<u8>, Box<dyn Error>> {
+    let request = format!("1906{:02X}{:02X}{:02X}{:02X}", dtc[0], dtc[1], dtc[2], record);
+    Ok(adapter.request(&request)?.iter().filter_map(|response| decode_extended_record(&response.data, dtc, record)).max())
+}
+
+fn read_counter(adapter: &mut Adapter, km: &str, minutes: &str) -> Result<Counter, Box<dyn Error>> {
+    let mut read = |name| pids::find(name).map_or(Ok(None), |pid| adapter.read_pid(pid));
+    Ok(Counter { km: read(km)?, minutes: read(minutes)? })
+}
+
+// Read the MIL, the code count, the stored, pending and permanent codes, and the
+// distance and time since the codes were cleared
+pub fn read(adapter: &mut Adapter) -> Result<Scan, Box<dyn Error>> {
+    let readiness = readiness::read(adapter)?;
+    let stored = adapter.read_codes(0x03)?;
+    let pending = adapter.read_codes(0x07)?;
+    let permanent = adapter.read_codes(0x0A)?;
+    let mut records = Vec::new();
+    if adapter.uses_can() {
+        // Every status bit; ECUs without UDS diagnostics stay silent or refuse
+        for response in adapter.request("1902FF")? {
+            records.extend(decode_uds_status(&response.data));
+        }
+    }
+    let statuses: Vec<(String, u8)> = records.iter().map(|(code, _, status)| (code.clone(), *status)).collect();
+    let mut scan = Scan::new(readiness.mil, readiness.stored_codes, &stored, &pending, &permanent, &statuses);
+    for (code, dtc, _) in records {
+        let occurrences = read_extended_record(adapter, dtc, OCCURRENCE_RECORD)?;
+        let aging = read_extended_record(adapter, dtc, AGING_RECORD)?;
+        let entry = scan.entry(&code);
+        // A code reported with several failure types counts its worst
+        entry.occurrences = entry.occurrences.max(occurrences);
+        entry.aging = match (entry.aging, aging) {
+            (Some(known), Some(aging)) => Some(known.min(aging)),
+            (known, aging) => known.or(aging),
+        };
+    }
+    scan.since_clear = read_counter(adapter, "clear_distance", "clear_time")?;
+    scan.with_mil = read_counter(adapter, "mil_distance", "mil_time")?;
+    Ok(scan)
+}
+
+// Print the MIL and the code count, then each code with its states and what they mean
+pub fn display(scan: &Scan, db: &DiagnosticsDatabase, weights: &HealthWeights) {
+    let mil = match scan.mil {
+        true => tr!("scan-mil-on").styled(Style::Error),
+        false => tr!("scan-mil-off").styled(Style::Success),
+    };
+    outln!("{}", tr!("scan-header").styled(Style::Heading));
+    outln!("{} {}   {} {}", tr!("scan-label-mil").styled(Style::Label), mil, tr!("scan-label-reported").styled(Style::Label), scan.reported);
+    if scan.since_clear.is_known() {
+        let since = scan.since_clear.describe();
+        let style = if scan.recently_cleared() { Style::Warning } else { Style::Plain };
+        outln!("{} {}", tr!("scan-label-cleared").styled(Style::Label), since.styled(style));
+    }
+    if scan.mil && scan.with_mil.is_known() {
+        outln!("{} {}", tr!("scan-label-mil-on-for").styled(Style::Label), scan.with_mil.describe());
+    }
+    if scan.recently_cleared() {
+        outln!("{}", tr!("scan-advice-recently-cleared").styled(Style::Warning));
+    }
+
+    if scan.codes.is_empty() {
+        outln!();
+        outln!("{}", tr!("scan-none").styled(Style::Success));
+    }
+    for dtc in &scan.codes {
+        outln!();
+        match db.lookup_error(&dtc.code) {
+            Some(error) => outln!("  {}  {}", dtc.code.styled(Style::Code), error.description),
+            None => outln!("  {}", dtc.code.styled(Style::Code)),
+        }
+        outln!("         {}", dtc.states().join(", ").styled(Style::Category));
+        if let Some(counters) = dtc.counters() {
+            outln!("         {}", counters);
+        }
+        if let Some(pattern) = dtc.pattern() {
+            outln!("         {}", pattern.describe().styled(Style::Hint));
+        }
+        if let Some(advice) = dtc.advice(scan.mil) {
+            outln!("         {}", advice.styled(Style::Hint));
+        }
+    }
+
+    let confirmed = scan.codes.iter().filter(|dtc| dtc.confirmed).count();
+    if scan.mil && confirmed == 0 {
+        outln!();
+        outln!("{}", tr!("scan-advice-mil-no-codes").styled(Style::Warning));
+    } else if usize::from(scan.reported) != confirmed {
+        outln!();
+        outln!("{}", tr!("scan-count-mismatch", reported = scan.reported, listed = confirmed).styled(Style::Warning));
+    }
+
+    let codes: Vec<String> = scan.codes.iter().map(|dtc| dtc.code.clone()).collect();
+    let health = weights.score(&codes, db);
+    outln!();
+    outln!("{}", tr!("health-line", score = health.score.styled(health.style), verdict = health.verdict));
+}
+
+// One vehicle of a scan over several adapters
+pub struct Target {
+    pub port: String,
+    pub vin: Option<String>,
+    // The one ECU read, with --module
+    pub module: Option<Module>,
+    // The error as text, since it comes back from another thread
+    pub result: Result<Scan, String>,
+}
+
+fn read_target(port: &str, settings: &AdapterSettings, module: Option<&Module>, spinner: &progress::ProgressBar) -> Result<(Option<String>, Scan), Box<dyn Error>> {
+    let mut adapter = Adapter::connect(port, settings)?;
+    spinner.set_message(tr!("scan-target-reading", port = port));
+    // Any ECU may have the VIN, so it is asked before a module is selected
+    let vin = adapter.read_vin().unwrap_or_default();
+    adapter.select(module)?;
+    let scan = read(&mut adapter);
+    // The adapter daemon keeps the adapter for other commands, which expect every ECU
+    adapter.select(None)?;
+    Ok((vin, scan?))
+}
+
+// What `clear` erased: the ECUs that cleared and the stored and pending codes they held
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cleared {
+    pub ecus: Vec<String>,
+    pub codes: Vec<String>,
+}
+
+// Clear the codes of one ECU, or of every ECU with None, noting first the codes that go
+// with them. The adapter talks to every ECU again afterwards.
+pub fn clear(adapter: &mut Adapter, module: Option<&Module>) -> Result<Cleared, Box<dyn Error>> {
+    adapter.select(module)?;
+    let cleared = read_and_clear(adapter);
+    adapter.select(None)?;
+    cleared
+}
+
+fn read_and_clear(adapter: &mut Adapter) -> Result<Cleared, Box<dyn Error>> {
+    let mut codes = adapter.read_codes(0x03)?;
+    for code in adapter.read_codes(0x07)? {
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    let ecus = adapter.clear_codes()?;
+    if ecus.is_empty() {
+        return Err(tr!("clear-no-answer").into());
+    }
+    Ok(Cleared { ecus, codes })
+}
+
+// Connect to every port and scan the vehicles concurrently, in the order of `ports`; with a
+// module, only that ECU of each vehicle is read
+pub fn read_all(ports: &[String], settings: &AdapterSettings, module: Option<&Module>) -> Vec<Target> {
+    let spinners = progress::spinners(ports.iter().map(|port| tr!("adapter-connecting", port = port.as_str())).collect());
+    thread::scope(|scope| {
+        let jobs: Vec<_> = ports
+            .iter()
+            .zip(&spinners)
+            .map(|(port, spinner)| {
+                scope.spawn(move || {
+                    let result = read_target(port, settings, module, spinner).map_err(|e| e.to_string());
+                    spinner.finish_and_clear();
+                    let module = module.cloned();
+                    match result {
+                        Ok((vin, scan)) => Target { port: port.clone(), vin, module, result: Ok(scan) },
+                        Err(e) => Target { port: port.clone(), vin: None, module, result: Err(e) },
+                    }
+                })
+            })
+            .collect();
+        jobs.into_iter()
+            .zip(ports)
+            .map(|(job, port)| {
+                job.join().unwrap_or_else(|_| Target { port: port.clone(), vin: None, module: module.cloned(), result: Err(tr!("scan-target-crashed")) })
+            })
+            .collect()
+    })
+}
+
+// Each vehicle's codes, then one line per vehicle
+pub fn display_all(targets: &[Target], db: &DiagnosticsDatabase, weights: &HealthWeights) {
+    for target in targets {
+        let vin = target.vin.as_deref().unwrap_or("?");
+        outln!("{}", tr!("scan-target-header", port = target.port.as_str(), vin = vin).styled(Style::Section));
+        if let Some(module) = &target.module {
+            outln!("{} {}", tr!("scan-label-module").styled(Style::Label), module.describe());
+        }
+        match &target.result {
+            Ok(scan) => display(scan, db, weights),
+            Err(e) => outln!("{}: {}", tr!("error-prefix").styled(Style::Error), e),
+        }
+        outln!();
+    }
+    if targets.len() < 2 {
+        return;
+    }
+
+    outln!("{}", tr!("scan-combined-header").styled(Style::Heading));
+    let width = targets.iter().map(|target| target.port.chars().count()).max().unwrap_or_default();
+    for target in targets {
+        let vin = target.vin.as_deref().unwrap_or("?");
+        let result = match &target.result {
+            Ok(scan) => {
+                let mil = match scan.mil {
+                    true => tr!("scan-mil-on").styled(Style::Error),
+                    false => tr!("scan-mil-off").styled(Style::Success),
+                };
+                let codes: Vec<&str> = scan.codes.iter().map(|dtc| dtc.code.as_str()).collect();
+                let cleared = match scan.recently_cleared() {
+                    true => format!("  {}", tr!("scan-recently-cleared").styled(Style::Warning)),
+                    false => String::new(),
+                };
+                format!("{} {}  {}{}", tr!("scan-label-mil").styled(Style::Label), mil, codes.join(" "), cleared)
+            },
+            Err(_) => tr!("scan-target-failed").styled(Style::Error).to_string(),
+        };
+        outln!("  {:<width$}  {:<17}  {}", target.port, vin, result, width = width);
+    }
+    let failed = targets.iter().filter(|target| target.result.is_err()).count();
+    outln!("{}", tr!("scan-combined-count", scanned = targets.len() - failed, failed = failed));
+}
+
+// Store each successful scan in the archive, as fleet import does; returns how many were saved
+pub fn save_all(targets: &[Target], clock: &dyn Clock, data_dir: &std::path::Path) -> Result<usize, Box<dyn Error>> {
+    let archive = Archive::open(data_dir)?;
+    let batch = clock.batch_id();
+    let mut saved = 0;
+    for (index, target) in targets.iter().enumerate() {
+        let Ok(scan) = &target.result else { continue };
+        archive.save(&ScanRecord {
+            id: format!("{}-{:05}", batch, index + 1),
+            vin: target.vin.clone().unwrap_or_else(|| UNKNOWN_VIN.to_string()),
+            timestamp: clock.now(),
+            source: match &target.module {
+                Some(module) => format!("scan:{}:{}", target.port, module.name),
+                None => format!("scan:{}", target.port),
+            },
+            codes: scan.codes.iter().map(|dtc| dtc.code.clone()).collect(),
+            summary: None,
+        })?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::fs;
+    use crate::adapter::Adapter;
+    #[cfg(feature = "adapter")]
+    use crate::adapter::AdapterSettings;
+    #[cfg(feature = "adapter")]
+    use crate::archive::Archive;
+    #[cfg(feature = "adapter")]
+    use crate::emulator::Elm327;
+    use crate::health::HealthWeights;
+    #[cfg(feature = "adapter")]
+    use crate::scenario::Scenario;
+    use crate::transcript;
+    #[cfg(feature = "adapter")]
+    use crate::testing::{FIXED_TIME, FixedClock};
+    use crate::testing::{init, sample_database, FakeElm, TempDir};
+    #[cfg(feature = "adapter")]
+    use super::Counter;
+    use super::{Dtc, Pattern};
+
+    #[test]
+    fn a_scan_ends_with_the_health_score_of_the_codes_read() {
+        init();
+        let elm = FakeElm::car()
+            .reply("0101", "7E806410181076505")
+            .reply("03", "7E8044301030000")
+            .reply("07", "NO DATA")
+            .reply("0A", "NO DATA")
+            .reply("1902FF", "NO DATA");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        let found = super::read(&mut adapter).unwrap();
+
+        let dir = TempDir::new("scan-health");
+        let path = dir.join("session.md");
+        transcript::start();
+        transcript::begin_command("scan");
+        super::display(&found, &sample_database(), &HealthWeights::default());
+        transcript::save(&path.to_string_lossy()).unwrap();
+        let shown = fs::read_to_string(&path).unwrap();
+
+        // P0300 is High, which costs 30 points
+        assert!(shown.contains("P0300"));
+        assert!(shown.trim_end().trim_end_matches("```").trim_end().ends_with("Health score: 70/100 - Fair: schedule a service visit"), "{}", shown);
+    }
+
+    #[test]
+    fn scan_combines_modes_and_uds_status() {
+        // MIL on with one stored code; P0171 is pending and UDS reports both as failed since clearing
+        let elm = FakeElm::car()
+            .reply("0101", "7E806410181076505")
+            .reply("03", "7E8044301042000")
+            .reply("07", "7E8044701017100")
+            .reply("0A", "7E8044A01042000")
+            .reply("1902FF", "7E80B5902FF0420002F01710024")
+            .reply("190604200001", "NO DATA")
+            .reply("190604200002", "NO DATA")
+            .reply("190601710001", "NO DATA")
+            .reply("190601710002", "NO DATA");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        let found = super::read(&mut adapter).unwrap();
+        assert!(found.mil);
+        assert_eq!(found.reported, 1);
+        assert_eq!(found.codes, [
+            Dtc { code: "P0420".to_string(), confirmed: true, pending: true, permanent: true, status: Some(0x2F), occurrences: None, aging: None },
+            Dtc { code: "P0171".to_string(), confirmed: false, pending: true, permanent: false, status: Some(0x24), occurrences: None, aging: None },
+        ]);
+    }
+
+    #[cfg(feature = "adapter")]
+    #[test]
+    fn several_adapters_are_scanned_concurrently_in_order() {
+        let ports: Vec<String> = ["sim:catalyst", "sim:no-such-scenario", "sim:"].map(str::to_string).to_vec();
+        let targets = super::read_all(&ports, &AdapterSettings::default(), None);
+        assert_eq!(targets.iter().map(|target| target.port.as_str()).collect::<Vec<_>>(), ["sim:catalyst", "sim:no-such-scenario", "sim:"]);
+        let catalyst = targets[0].result.as_ref().unwrap();
+        assert!(catalyst.mil);
+        assert_eq!(catalyst.codes[0].code, "P0420");
+        assert_eq!(targets[0].vin.as_deref(), Some("1FTFW1ET5DFC10312"));
+        assert!(targets[1].result.as_ref().unwrap_err().contains("no-such-scenario"));
+        assert!(targets[2].result.as_ref().unwrap().codes.is_empty());
+
+        let dir = TempDir::new("multi-scan");
+        assert_eq!(super::save_all(&targets, &FixedClock(FIXED_TIME), &dir.join("data")).unwrap(), 2);
+        let records = Archive::open(&dir.join("data")).unwrap().load_all().unwrap();
+        assert_eq!(records.iter().map(|record| record.source.as_str()).collect::<Vec<_>>(), ["scan:sim:catalyst", "scan:sim:"]);
+    }
+
+    #[cfg(feature = "adapter")]
+    #[test]
+    fn scan_reads_distance_and_time_since_codes_were_cleared() {
+        assert!(crate::pids::find("clear_distance").is_some_and(|pid| pid.request() == "0131"));
+
+        let mut adapter = Adapter::connect("sim:recently-cleared", &AdapterSettings::default()).unwrap();
+        let found = super::read(&mut adapter).unwrap();
+        assert_eq!(found.since_clear, Counter { km: Some(6.0), minutes: Some(14.0) });
+        assert!(found.recently_cleared() && !found.mil);
+
+        // A vehicle that does not report the counters is not taken for a recent clear
+        assert!(!super::Scan::default().recently_cleared());
+        let mut catalyst = Adapter::connect("sim:catalyst", &AdapterSettings::default()).unwrap();
+        let found = super::read(&mut catalyst).unwrap();
+        assert!(!found.recently_cleared());
+        assert_eq!(found.with_mil, Counter { km: Some(930.0), minutes: Some(1105.0) });
+
+        // Clearing the codes starts the counters again
+        catalyst.request("04").unwrap();
+        let found = super::read(&mut catalyst).unwrap();
+        assert_eq!(found.since_clear, Counter { km: Some(0.0), minutes: Some(0.0) });
+        assert!(found.recently_cleared());
+    }
+
+    #[cfg(feature = "adapter")]
+    #[test]
+    fn a_module_is_cleared_alone_and_its_codes_are_noted_first() {
+        init();
+        let settings: AdapterSettings = toml::from_str("modules = { abs = \"7b0\" }").unwrap();
+        settings.validate().unwrap();
+        let abs = settings.module("ABS").unwrap();
+        assert_eq!((abs.request, abs.response), (0x7B0, 0x7B8));
+        assert_eq!(settings.module("engine").unwrap().describe(), "engine (7E0)");
+        assert_eq!(settings.module("7E2").unwrap().response, 0x7EA);
+        assert!(settings.module("brakes").is_err());
+        assert!(toml::from_str::<AdapterSettings>("modules = { abs = \"7FC\" }").unwrap().validate().is_err());
+
+        let scenario: Scenario = serde_yaml::from_str("stored: [P0171]\npending: [P0300]\npids: { rpm: 812 }").unwrap();
+        let mut adapter = Adapter::initialize(Box::new(Elm327::new(scenario)), "emulator", Duration::from_secs(1)).unwrap();
+        // No ECU answers on the ABS address, so nothing is cleared, on the engine either
+        assert!(super::clear(&mut adapter, Some(&abs)).is_err());
+        assert_eq!(adapter.read_codes(0x03).unwrap(), ["P0171"]);
+
+        let cleared = super::clear(&mut adapter, Some(&settings.module("engine").unwrap())).unwrap();
+        assert_eq!(cleared.ecus, ["7E8"]);
+        assert_eq!(cleared.codes, ["P0171", "P0300"]);
+        assert!(adapter.read_codes(0x03).unwrap().is_empty());
+    }
+
+    #[test]
+    fn uds_counters_tell_hard_faults_from_intermittent_ones_and_glitches() {
+        init();
+        // P0420 is failing now; P0171 failed on 4 cycles and has passed on 2 since; P0300 failed once
+        let elm = FakeElm::car()
+            .reply("0101", "7E806410183076505")
+            .reply("03", "7E8084303042001710300")
+            .reply("07", "NO DATA")
+            .reply("0A", "NO DATA")
+            .reply("1902FF", "7E80F5902FF0420000B0171002803000028")
+            .reply("190604200001", "7E80859060420000B0103")
+            .reply("190604200002", "7E80859060420000B0200")
+            .reply("190601710001", "7E8085906017100280104")
+            .reply("190601710002", "7E8085906017100280202")
+            .reply("190603000001", "7E8085906030000280101")
+            .reply("190603000002", "NO DATA");
+        let mut adapter = Adapter::initialize(Box::new(elm), "fake", Duration::from_secs(1)).unwrap();
+        let found = super::read(&mut adapter).unwrap();
+        let summary: Vec<_> = found.codes.iter().map(|dtc| (dtc.code.as_str(), dtc.occurrences, dtc.aging, dtc.pattern())).collect();
+        assert_eq!(summary, [
+            ("P0420", Some(3), Some(0), Some(Pattern::Hard)),
+            ("P0171", Some(4), Some(2), Some(Pattern::Intermittent)),
+            ("P0300", Some(1), None, Some(Pattern::Once)),
+        ]);
+        assert_eq!(super::decode_extended_record(&[0x59, 0x06, 0x03, 0x00, 0x00, 0x28, 0x02, 0x07], [0x03, 0x00, 0x00], 0x01), None);
+    }
+}
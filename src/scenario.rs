@@ -0,0 +1,296 @@
+// Vehicles for the simulator, described in YAML.
+//
+// A scenario drives both `emulate` and the `sim:` adapter port (`connect
+// sim:misfire`, `--port sim:vehicle.yaml`). Bundled scenarios live in
+// src/data/scenarios and are picked by name; anything else is a file path.
+//
+//   vin: 1FTFW1ET5DFC10312
+//   stored: [P0420]                 # codes set at the start: stored, pending
+//   pending: []                     # and permanent; the MIL is on when any
+//   permanent: [P0420]              # code is stored unless `mil` says otherwise
+//   incomplete: [catalyst]          # readiness monitors not yet complete
+//   freeze_frame:                   # mode 02 data kept with a code
+//     code: P0420
+//     pids: { rpm: 2150, speed: 88, coolant: 92 }
+//   pids:                           # mode 01 values: a number, or a generator
+//     coolant: 90
+//     rpm: { sine: { min: 720, max: 860, period_secs: 6 } }
+//     speed: { ramp: { from: 0, to: 90, secs: 30 } }
+//     throttle: { steps: { values: [12, 40, 18], every_secs: 5 } }
+//     load: { random: { min: 20, max: 35 } }
+//   faults:                         # codes appearing while the engine runs
+//     - { code: P0300, after_secs: 20, pending: true }
+//   unsupported: [ATRV, ATDPN]      # AT commands the adapter answers '?' to, as clones do
+//   clear:                          # what mode 04 does
+//     refuse: false                 # answer "conditions not correct" instead
+//     reset_monitors: true          # every monitor incomplete afterwards
+//     faults_return: true           # faults appear again, timed from the clear
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::adapter;
+use crate::pids;
+use crate::readiness;
+
+// Directory of the bundled scenarios, named by file stem
+pub const SCENARIOS_DIR: &str = "src/data/scenarios";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    // One line shown by `emulate` when the scenario starts
+    pub description: Option<String>,
+    pub vin: Option<String>,
+    pub stored: Vec<String>,
+    pub pending: Vec<String>,
+    pub permanent: Vec<String>,
+    // On by default when there are stored codes
+    pub mil: Option<bool>,
+    // Readiness monitors that have not completed, by the names `drive-cycle` uses
+    pub incomplete: Vec<String>,
+    pub freeze_frame: Option<FreezeFrame>,
+    // Mode 01 values by PID name
+    pub pids: BTreeMap<String, Signal>,
+    // Battery voltage reported by ATRV
+    pub battery: f64,
+    pub faults: Vec<Fault>,
+    pub clear: ClearBehavior,
+    // AT commands the emulated adapter does not know, e.g. ATRV
+    pub unsupported: Vec<String>,
+}
+
+// The PID values an ECU kept when it stored a code
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FreezeFrame {
+    pub code: String,
+    pub pids: BTreeMap<String, f64>,
+}
+
+// A fixed value, or one that changes with the seconds since the simulation started
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Signal {
+    Constant(f64),
+    Generator(Generator),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum Generator {
+    // Swings between `min` and `max`, starting halfway up
+    Sine { min: f64, max: f64, period_secs: f64 },
+    // Moves from `from` to `to`, then holds
+    Ramp { from: f64, to: f64, secs: f64 },
+    // Each value in turn, over and over
+    Steps { values: Vec<f64>, every_secs: f64 },
+    // Anywhere between `min` and `max`, changing every tenth of a second
+    Random { min: f64, max: f64 },
+}
+
+// A code that sets `after_secs` into the simulation, stored with the MIL unless `pending`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fault {
+    pub code: String,
+    pub after_secs: f64,
+    #[serde(default)]
+    pub pending: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClearBehavior {
+    // Refuse mode 04, as many ECUs do with the engine running
+    pub refuse: bool,
+    pub reset_monitors: bool,
+    // Clearing does not repair anything: faults set again, timed from the clear
+    pub faults_return: bool,
+}
+
+impl Default for ClearBehavior {
+    fn default() -> Self {
+        ClearBehavior { refuse: false, reset_monitors: true, faults_return: true }
+    }
+}
+
+impl Default for Scenario {
+    // A healthy car idling when warm
+    fn default() -> Self {
+        let pids = [("rpm", 780.0), ("speed", 0.0), ("coolant", 90.0), ("load", 22.0), ("stft1", 1.6), ("ltft1", -2.3), ("voltage", 14.1)];
+        Scenario {
+            description: None,
+            vin: Some("1HGCM82633A004352".to_string()),
+            stored: Vec::new(),
+            pending: Vec::new(),
+            permanent: Vec::new(),
+            mil: None,
+            incomplete: Vec::new(),
+            freeze_frame: None,
+            pids: pids.into_iter().map(|(name, value)| (name.to_string(), Signal::Constant(value))).collect(),
+            battery: 14.1,
+            faults: Vec::new(),
+            clear: ClearBehavior::default(),
+            unsupported: Vec::new(),
+        }
+    }
+}
+
+// A value from 0 to 1 that looks random but is the same for the same inputs
+fn noise(seed: u64) -> f64 {
+    let mut x = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ 0xD1B5_4A32_D192_ED03;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x ^= x >> 33;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Signal {
+    // The value `secs` seconds into the simulation; `seed` keeps random signals apart
+    pub fn value(&self, secs: f64, seed: u64) -> f64 {
+        match self {
+            Signal::Constant(value) => *value,
+            Signal::Generator(Generator::Sine { min, max, period_secs }) => {
+                min + (max - min) * (1.0 + (2.0 * PI * secs / period_secs.max(f64::EPSILON)).sin()) / 2.0
+            },
+            Signal::Generator(Generator::Ramp { from, to, secs: length }) => {
+                from + (to - from) * (secs / length.max(f64::EPSILON)).clamp(0.0, 1.0)
+            },
+            Signal::Generator(Generator::Steps { values, every_secs }) => {
+                let step = (secs / every_secs.max(f64::EPSILON)) as usize;
+                values.get(step % values.len().max(1)).copied().unwrap_or_default()
+            },
+            Signal::Generator(Generator::Random { min, max }) => {
+                min + (max - min) * noise((secs * 10.0) as u64 ^ (seed << 32))
+            },
+        }
+    }
+}
+
+impl Scenario {
+    // A bundled scenario by name, a YAML file, or the default healthy car for an empty name
+    pub fn find(name: &str) -> Result<Self, Box<dyn Error>> {
+        if name.is_empty() {
+            return Ok(Scenario::default());
+        }
+        if Path::new(name).is_file() {
+            return Scenario::load(name);
+        }
+        let bundled = Path::new(SCENARIOS_DIR).join(format!("{}.yaml", name));
+        if bundled.is_file() {
+            return Scenario::load(&bundled.to_string_lossy());
+        }
+        Err(format!("No scenario '{}'; use a YAML file or one of {}", name, bundled_names().join(", ")).into())
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let scenario: Scenario = serde_yaml::from_str(&content).map_err(|e| format!("Invalid scenario {}: {}", path, e))?;
+        scenario.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(scenario)
+    }
+
+    // Check the codes, PID names, monitor names and generators
+    pub fn validate(&self) -> Result<(), String> {
+        let faults = self.faults.iter().map(|fault| &fault.code);
+        let frozen = self.freeze_frame.iter().map(|frame| &frame.code);
+        for code in self.stored.iter().chain(&self.pending).chain(&self.permanent).chain(faults).chain(frozen) {
+            adapter::encode_dtc(code).ok_or_else(|| format!("'{}' is not a trouble code", code))?;
+        }
+        let frozen_pids = self.freeze_frame.iter().flat_map(|frame| frame.pids.keys());
+        for name in self.pids.keys().chain(frozen_pids) {
+            pids::find(name).ok_or_else(|| format!("unknown PID '{}'", name))?;
+        }
+        for (name, signal) in &self.pids {
+            if let Signal::Generator(Generator::Steps { values, .. }) = signal {
+                if values.is_empty() {
+                    return Err(format!("pids.{}: steps need at least one value", name));
+                }
+            }
+        }
+        for name in &self.incomplete {
+            readiness::find(name).ok_or_else(|| format!("unknown monitor '{}'", name))?;
+        }
+        if self.vin.as_ref().is_some_and(|vin| vin.len() != 17) {
+            return Err("the VIN must have 17 characters".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Names of the bundled scenarios, sorted
+pub fn bundled_names() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(SCENARIOS_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "yaml"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(all(test, feature = "adapter"))]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+    use crate::adapter::Adapter;
+    use crate::emulator::Elm327;
+    use super::Scenario;
+
+    #[test]
+    fn scenario_faults_appear_on_time_and_return_after_a_clear() {
+        for name in super::bundled_names() {
+            Scenario::find(&name).unwrap();
+        }
+        assert!(Scenario::find("no-such-scenario").is_err());
+
+        let scenario: Scenario = serde_yaml::from_str("
+pids:
+  rpm: { steps: { values: [800, 2400], every_secs: 10 } }
+  speed: { ramp: { from: 0, to: 100, secs: 20 } }
+faults:
+  - { code: P0300, after_secs: 5, pending: true }
+  - { code: P0301, after_secs: 15 }
+").unwrap();
+        let secs = Arc::new(AtomicU64::new(0));
+        let clock = Arc::clone(&secs);
+        let elm = Elm327::with_clock(scenario, move || Duration::from_secs(clock.load(Ordering::SeqCst)));
+        let mut adapter = Adapter::initialize(Box::new(elm), "emulator", Duration::from_secs(1)).unwrap();
+        let rpm = crate::pids::find("rpm").unwrap();
+        assert_eq!(adapter.read_pid(rpm).unwrap(), Some(800.0));
+        assert!(adapter.read_codes(0x07).unwrap().is_empty());
+
+        secs.store(6, Ordering::SeqCst);
+        assert_eq!(adapter.read_codes(0x07).unwrap(), ["P0300"]);
+        assert!(adapter.read_codes(0x03).unwrap().is_empty());
+        assert!(adapter.request("020200").unwrap().is_empty());
+
+        // The stored code keeps the values of the moment it set
+        secs.store(15, Ordering::SeqCst);
+        assert_eq!(adapter.read_codes(0x03).unwrap(), ["P0301"]);
+        assert_eq!(adapter.read_pid(rpm).unwrap(), Some(2400.0));
+        assert_eq!(adapter.request("020200").unwrap()[0].data, [0x42, 0x02, 0x00, 0x03, 0x01]);
+        assert_eq!(adapter.request("020D00").unwrap()[0].data, [0x42, 0x0D, 0x00, 75]);
+
+        // Faults come back on the same schedule after a clear, with every monitor incomplete
+        secs.store(20, Ordering::SeqCst);
+        adapter.request("04").unwrap();
+        assert!(adapter.read_codes(0x03).unwrap().is_empty());
+        assert!(adapter.read_codes(0x07).unwrap().is_empty());
+        assert!(crate::readiness::read(&mut adapter).unwrap().incomplete().count() > 0);
+        secs.store(26, Ordering::SeqCst);
+        assert_eq!(adapter.read_codes(0x07).unwrap(), ["P0300"]);
+
+        let refusing: Scenario = serde_yaml::from_str("{ stored: [P0171], clear: { refuse: true } }").unwrap();
+        let mut adapter = Adapter::initialize(Box::new(Elm327::new(refusing)), "emulator", Duration::from_secs(1)).unwrap();
+        adapter.request("04").unwrap();
+        assert_eq!(adapter.read_codes(0x03).unwrap(), ["P0171"]);
+    }
+}
@@ -0,0 +1,124 @@
+// Versions of the error code database layout (`db migrate`).
+//
+// Database files and packs start with a line naming the layout they use,
+// before the header row:
+//
+//   # cars-scanner schema 5
+//   code,description,severity,system,...
+//
+// Each version added columns to the one before:
+//
+//   1  code, description, severity, system, possible_causes and
+//      recommended_actions, with the optional warranty, campaign and
+//      related_parts columns
+//   2  set_conditions and monitors
+//   3  aliases
+//   4  difficulty
+//   5  safety_warnings
+//
+// Files written before the line existed are dated by their columns. A file
+// from a newer version of the tool is refused with an error, rather than
+// read with columns it does not know about silently dropped; `db migrate`
+// rewrites an older file in the current layout.
+use csv::{Reader, ReaderBuilder, StringRecord};
+
+pub const CURRENT: u32 = 5;
+
+pub const MARKER: &str = "# cars-scanner schema ";
+
+// The step from the version before `to` up to it
+pub struct Migration {
+    pub to: u32,
+    // Columns added, empty in every existing row
+    pub columns: &'static [&'static str],
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { to: 2, columns: &["set_conditions", "monitors"] },
+    Migration { to: 3, columns: &["aliases"] },
+    Migration { to: 4, columns: &["difficulty"] },
+    Migration { to: 5, columns: &["safety_warnings"] },
+];
+
+// The line that starts a file in the layout of `version`
+pub fn line(version: u32) -> String {
+    format!("{}{}", MARKER, version)
+}
+
+// The version on a file's first line; None for files without the line
+pub fn declared(content: &str) -> Result<Option<u32>, String> {
+    let first = content.trim_start_matches('\u{feff}').lines().next().unwrap_or_default();
+    let Some(version) = first.strip_prefix(MARKER) else { return Ok(None) };
+    let version: u32 = version.trim().parse().map_err(|_| format!("invalid schema line '{}'", first.trim()))?;
+    if version > CURRENT {
+        return Err(format!(
+            "the file uses database schema {}, but this version of cars-scanner reads up to schema {}; update cars-scanner to use it",
+            version, CURRENT
+        ));
+    }
+    Ok(Some(version))
+}
+
+// The version of a file without a schema line: the newest whose columns it has
+pub fn dated(headers: &StringRecord) -> u32 {
+    MIGRATIONS
+        .iter()
+        .rev()
+        .find(|migration| migration.columns.iter().any(|column| headers.iter().any(|header| header == *column)))
+        .map_or(1, |migration| migration.to)
+}
+
+// A CSV reader for a database file's content; the schema line is skipped as a comment
+pub fn reader(content: &str) -> Reader<&[u8]> {
+    let content = content.trim_start_matches('\u{feff}');
+    ReaderBuilder::new().has_headers(true).comment(Some(b'#')).from_reader(content.as_bytes())
+}
+
+// The steps from `version` to the current layout
+pub fn pending(version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |migration| migration.to > version)
+}
+
+// Rows in the current layout: columns added since `version` are appended, empty
+pub fn migrate(version: u32, headers: &StringRecord, records: &[StringRecord]) -> (StringRecord, Vec<StringRecord>) {
+    let mut headers = headers.clone();
+    let mut records = records.to_vec();
+    for column in pending(version).flat_map(|migration| migration.columns) {
+        if headers.iter().any(|header| header == *column) {
+            continue;
+        }
+        headers.push_field(column);
+        for record in &mut records {
+            record.push_field("");
+        }
+    }
+    (headers, records)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DiagnosticsDatabase, Source};
+
+    #[test]
+    fn database_files_are_dated_migrated_and_refused_when_newer() {
+        let old = "code,description,severity,system,possible_causes,recommended_actions,monitors\nP0420,Cat,Medium,Emissions,a,b,catalyst\n";
+        assert_eq!(super::declared(old), Ok(None));
+        let mut reader = super::reader(old);
+        let headers = reader.headers().unwrap().clone();
+        let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(super::dated(&headers), 2);
+
+        let (headers, records) = super::migrate(1, &headers, &records);
+        assert_eq!(headers.iter().skip(6).collect::<Vec<_>>(), ["monitors", "set_conditions", "aliases", "difficulty", "safety_warnings"]);
+        assert_eq!(records[0].iter().skip(6).collect::<Vec<_>>(), ["catalyst", "", "", "", ""]);
+
+        let current = format!("{}\n{}", super::line(super::CURRENT), old);
+        assert_eq!(super::declared(&current), Ok(Some(super::CURRENT)));
+        let mut db = DiagnosticsDatabase::new();
+        assert_eq!(db.read_records(current.as_bytes(), Source::bundled("current.csv")).unwrap(), 1);
+        let newer = format!("{}\n{}", super::line(super::CURRENT + 1), old);
+        let error = db.read_records(newer.as_bytes(), Source::bundled("newer.csv")).unwrap_err().to_string();
+        assert!(error.contains(&format!("reads up to schema {}", super::CURRENT)));
+        assert!(super::declared("# cars-scanner schema three\ncode\n").is_err());
+    }
+}
@@ -0,0 +1,566 @@
+// Keyword search results with the position of every match.
+//
+// `DiagnosticsDatabase::search` returns one page of ranked hits together
+// with the total number of matches, so every front end pages and sorts the
+// same way. Hits borrow from the database; `SearchPage::into_owned` detaches
+// a page for callers that outlive it.
+//
+// `DiagnosticsDatabase::search_all` keeps every hit as `SearchResults`, which
+// `refine` narrows to the hits that also match another keyword. Only the
+// previous hits are searched again, so drilling down stays fast however large
+// the database is. `search --within-last` refines the result set of the
+// previous search, which is kept in the data directory as `LastSearch`.
+//
+// `search --format jsonl` does not wait for every hit: `stream` matches the
+// codes one at a time, in code order, and `write_jsonl` writes each hit as a
+// line of JSON as soon as it is found, so a downstream tool starts working
+// at once and memory does not grow with the number of hits.
+//
+// `SearchResults::phrase` is the reverse lookup: given text a customer reads
+// off a dashboard or an app, it ranks codes by how many of the phrase's words
+// their description contains, so the closest description comes first.
+//
+// Besides the English text, keywords are matched against a code's aliases
+// ("cat code") and its description in the language messages are shown in,
+// both of which weigh as much as the description. Matching ignores case in
+// every script, so "ÅTERFÖRING" finds "återföring", and a phrase drops the
+// filler words of that language as well as the English ones.
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use crate::i18n;
+use crate::json;
+use crate::presentation::{Style, Styled};
+use crate::{escape_html, pipe_list, ErrorCode, SEVERITIES};
+
+// Byte ranges of keyword matches within each searchable field; list fields
+// have one set of ranges per entry
+#[derive(Debug, Default, Clone)]
+pub struct Highlights {
+    pub description: Vec<Range<usize>>,
+    pub possible_causes: Vec<Vec<Range<usize>>>,
+    pub recommended_actions: Vec<Vec<Range<usize>>>,
+    // One set of ranges per alias
+    pub aliases: Vec<Vec<Range<usize>>>,
+    // Within the translated description
+    pub translation: Vec<Range<usize>>,
+}
+
+// An error code matched by a search, with the spans that matched
+#[derive(Debug, Clone)]
+pub struct SearchHit<'a> {
+    pub error: Cow<'a, ErrorCode>,
+    pub highlights: Highlights,
+    // Higher is more relevant
+    pub score: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    // Most matches first, description matches counting most
+    #[default]
+    Relevance,
+    Code,
+    // Most severe first
+    Severity,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    // Hits to skip before the page starts
+    pub offset: usize,
+    // Largest page; every remaining hit when unset
+    pub limit: Option<usize>,
+    pub sort: SortOrder,
+}
+
+// Every hit of a search and its refinements, unsorted and not yet paged
+#[derive(Debug, Clone, Default)]
+pub struct SearchResults<'a> {
+    // The keywords every hit matches, the first one broadest
+    pub keywords: Vec<String>,
+    pub hits: Vec<SearchHit<'a>>,
+}
+
+// File in the data directory holding the result set of the last search
+pub const LAST_SEARCH_FILE: &str = "last-search.json";
+
+// The keywords and codes of the last search with any hits, for `--within-last`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LastSearch {
+    pub keywords: Vec<String>,
+    pub codes: Vec<String>,
+}
+
+// Words too common to tell descriptions apart, ignored in a phrase, by language
+const FILLER_WORDS: &[(&str, &[&str])] = &[
+    ("en", &["a", "an", "and", "at", "for", "in", "is", "of", "on", "or", "the", "to", "with"]),
+    ("sv", &["av", "de", "den", "det", "en", "ett", "för", "i", "med", "och", "på", "som", "till", "vid", "är"]),
+    ("de", &["am", "auf", "bei", "das", "der", "die", "ein", "eine", "für", "im", "in", "ist", "mit", "und", "von", "zu"]),
+    ("es", &["a", "al", "con", "de", "del", "el", "en", "es", "la", "las", "los", "o", "para", "por", "un", "una", "y"]),
+    ("fr", &["à", "au", "avec", "de", "des", "du", "en", "est", "et", "la", "le", "les", "ou", "pour", "sur", "un", "une"]),
+];
+
+// Whether a word is filler in English or the language messages are shown in
+fn is_filler(word: &str) -> bool {
+    FILLER_WORDS
+        .iter()
+        .filter(|(language, _)| *language == "en" || *language == i18n::language())
+        .any(|(_, words)| words.contains(&word))
+}
+
+// One page of search results
+#[derive(Debug, Clone)]
+pub struct SearchPage<'a> {
+    // Matches across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    pub hits: Vec<SearchHit<'a>>,
+}
+
+impl SearchHit<'_> {
+    pub fn into_owned(self) -> SearchHit<'static> {
+        SearchHit { error: Cow::Owned(self.error.into_owned()), highlights: self.highlights, score: self.score }
+    }
+}
+
+impl<'a> SearchResults<'a> {
+    // The candidates that match `keyword`
+    pub fn new(keyword: &str, candidates: impl IntoIterator<Item = &'a ErrorCode>) -> Self {
+        let hits = candidates
+            .into_iter()
+            .map(|error| (error, Highlights::find(error, keyword)))
+            .filter(|(_, highlights)| !highlights.is_empty())
+            .map(|(error, highlights)| SearchHit { error: Cow::Borrowed(error), score: highlights.score(), highlights })
+            .collect();
+        SearchResults { keywords: vec![keyword.to_string()], hits }
+    }
+
+    // The candidates sharing a word with `phrase`. Each word found in the description
+    // counts three times as much as one only found in the causes or actions.
+    pub fn phrase(phrase: &str, candidates: impl IntoIterator<Item = &'a ErrorCode>) -> Self {
+        let mut keywords: Vec<String> = Vec::new();
+        for word in phrase.split_whitespace().map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()) {
+            if !word.is_empty() && !is_filler(&word) && !keywords.contains(&word) {
+                keywords.push(word);
+            }
+        }
+        let hits = candidates
+            .into_iter()
+            .filter_map(|error| {
+                let mut highlights = Highlights::default();
+                let mut score = 0;
+                for keyword in &keywords {
+                    let found = Highlights::find(error, keyword);
+                    score += match (found.headline_matches() == 0, found.is_empty()) {
+                        (false, _) => 3,
+                        (true, false) => 1,
+                        (true, true) => continue,
+                    };
+                    highlights = highlights.union(&found);
+                }
+                (score > 0).then_some(SearchHit { error: Cow::Borrowed(error), highlights, score })
+            })
+            .collect();
+        SearchResults { keywords, hits }
+    }
+
+    // The hits that also match `keyword`, highlighting the matches of every keyword
+    pub fn refine(&self, keyword: &str) -> SearchResults<'a> {
+        let hits = self.hits
+            .iter()
+            .filter_map(|hit| {
+                let found = Highlights::find(&hit.error, keyword);
+                if found.is_empty() {
+                    return None;
+                }
+                let highlights = hit.highlights.union(&found);
+                Some(SearchHit { error: hit.error.clone(), score: highlights.score(), highlights })
+            })
+            .collect();
+        let mut keywords = self.keywords.clone();
+        keywords.push(keyword.to_string());
+        SearchResults { keywords, hits }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    // The result set to keep for a later refinement
+    pub fn last_search(&self) -> LastSearch {
+        let mut codes: Vec<String> = self.hits.iter().map(|hit| hit.error.code.clone()).collect();
+        codes.sort();
+        LastSearch { keywords: self.keywords.clone(), codes }
+    }
+
+    pub fn page(self, options: &SearchOptions) -> SearchPage<'a> {
+        SearchPage::paginate(self.hits, options)
+    }
+}
+
+// Hits matching every keyword, found one at a time as the candidates are read, as
+// `SearchResults::new` followed by `refine` would find them but without collecting them
+pub fn stream<'a>(keywords: &'a [String], candidates: impl IntoIterator<Item = &'a ErrorCode> + 'a) -> impl Iterator<Item = SearchHit<'a>> + 'a {
+    candidates.into_iter().filter_map(move |error| {
+        let mut highlights = Highlights::default();
+        for keyword in keywords {
+            let found = Highlights::find(error, keyword);
+            if found.is_empty() {
+                return None;
+            }
+            highlights = highlights.union(&found);
+        }
+        Some(SearchHit { error: Cow::Borrowed(error), score: highlights.score(), highlights })
+    })
+}
+
+// A search result as printed by `search --format json` and `--format jsonl`
+#[derive(Debug, Serialize)]
+pub struct JsonHit<'a> {
+    #[serde(flatten)]
+    pub code: json::Code<'a>,
+    pub score: usize,
+    // Fields a keyword was found in
+    pub matched: Vec<&'static str>,
+}
+
+impl<'a> JsonHit<'a> {
+    pub fn new(hit: &'a SearchHit) -> Self {
+        let highlights = &hit.highlights;
+        let matched = [
+            ("description", !highlights.description.is_empty()),
+            ("possible_causes", Highlights::list_matches(&highlights.possible_causes) > 0),
+            ("recommended_actions", Highlights::list_matches(&highlights.recommended_actions) > 0),
+            ("aliases", Highlights::list_matches(&highlights.aliases) > 0),
+            ("translation", !highlights.translation.is_empty()),
+        ];
+        JsonHit {
+            code: json::Code::from(&*hit.error),
+            score: hit.score,
+            matched: matched.into_iter().filter(|(_, found)| *found).map(|(field, _)| field).collect(),
+        }
+    }
+}
+
+// Write the hits from `offset` on, at most `limit` of them, one JSON object per line as
+// each is found. Returns the codes of every hit, for `--within-last`. A reader that stops
+// early, such as `head`, ends the output but not the search.
+pub fn write_jsonl<'a>(hits: impl Iterator<Item = SearchHit<'a>>, offset: usize, limit: Option<usize>, mut out: impl Write) -> io::Result<Vec<String>> {
+    let mut codes = Vec::new();
+    let mut writing = true;
+    for (index, hit) in hits.enumerate() {
+        let shown = index >= offset && limit.is_none_or(|limit| index - offset < limit);
+        if writing && shown {
+            let mut line = serde_json::to_vec(&JsonHit::new(&hit))?;
+            line.push(b'\n');
+            match out.write_all(&line) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => writing = false,
+                result => result?,
+            }
+        }
+        codes.push(hit.error.code.clone());
+    }
+    match out.flush() {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(codes),
+        result => result.map(|_| codes),
+    }
+}
+
+impl LastSearch {
+    // The last result set, or None when no search has been kept yet
+    pub fn load(data_dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let path = data_dir.join(LAST_SEARCH_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let last = serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        Ok(Some(last))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(data_dir)?;
+        fs::write(data_dir.join(LAST_SEARCH_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl<'a> SearchPage<'a> {
+    // Rank, sort and cut one page out of every hit
+    pub fn paginate(mut hits: Vec<SearchHit<'a>>, options: &SearchOptions) -> Self {
+        let severity_rank = |hit: &SearchHit| {
+            SEVERITIES.iter().position(|severity| severity.eq_ignore_ascii_case(&hit.error.severity))
+        };
+        match options.sort {
+            SortOrder::Relevance => hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.error.code.cmp(&b.error.code))),
+            SortOrder::Code => hits.sort_by(|a, b| a.error.code.cmp(&b.error.code)),
+            SortOrder::Severity => hits.sort_by(|a, b| severity_rank(b).cmp(&severity_rank(a)).then_with(|| a.error.code.cmp(&b.error.code))),
+        }
+
+        let total = hits.len();
+        let hits = hits
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(usize::MAX))
+            .collect();
+        SearchPage { total, offset: options.offset, limit: options.limit, hits }
+    }
+
+    // Whether later pages hold more hits
+    pub fn has_more(&self) -> bool {
+        self.offset + self.hits.len() < self.total
+    }
+
+    // For callers that keep results after the database is gone; the CLI never does
+    pub fn into_owned(self) -> SearchPage<'static> {
+        SearchPage {
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+            hits: self.hits.into_iter().map(SearchHit::into_owned).collect(),
+        }
+    }
+}
+
+impl Highlights {
+    // Find every case-insensitive occurrence of a keyword in the searchable fields
+    pub fn find(error: &ErrorCode, keyword: &str) -> Self {
+        Highlights {
+            description: find_spans(&error.description, keyword),
+            possible_causes: error.possible_causes.iter().map(|cause| find_spans(cause, keyword)).collect(),
+            recommended_actions: error.recommended_actions.iter().map(|action| find_spans(action, keyword)).collect(),
+            aliases: pipe_list(&error.aliases).map(|alias| find_spans(alias, keyword)).collect(),
+            translation: error.translation.as_deref().map(|translation| find_spans(translation, keyword)).unwrap_or_default(),
+        }
+    }
+
+    // The ranges within entry `index` of a list field
+    pub fn item(spans: &[Vec<Range<usize>>], index: usize) -> &[Range<usize>] {
+        spans.get(index).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // The matches of both, with overlapping spans joined
+    pub fn union(&self, other: &Highlights) -> Highlights {
+        let lists = |a: &[Vec<Range<usize>>], b: &[Vec<Range<usize>>]| {
+            (0..a.len().max(b.len())).map(|index| join_spans(Self::item(a, index), Self::item(b, index))).collect()
+        };
+        Highlights {
+            description: join_spans(&self.description, &other.description),
+            possible_causes: lists(&self.possible_causes, &other.possible_causes),
+            recommended_actions: lists(&self.recommended_actions, &other.recommended_actions),
+            aliases: lists(&self.aliases, &other.aliases),
+            translation: join_spans(&self.translation, &other.translation),
+        }
+    }
+
+    fn list_matches(spans: &[Vec<Range<usize>>]) -> usize {
+        spans.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.score() == 0
+    }
+
+    // Matches in the description, its translation and the aliases, which name the fault itself
+    fn headline_matches(&self) -> usize {
+        self.description.len() + self.translation.len() + Self::list_matches(&self.aliases)
+    }
+
+    // Relevance of a hit: a match in the description outweighs one in a list
+    pub fn score(&self) -> usize {
+        self.headline_matches() * 3 + Self::list_matches(&self.possible_causes) + Self::list_matches(&self.recommended_actions)
+    }
+}
+
+// Byte ranges of every non-overlapping, case-insensitive occurrence of `keyword`
+pub fn find_spans(text: &str, keyword: &str) -> Vec<Range<usize>> {
+    if keyword.is_empty() {
+        return Vec::new();
+    }
+
+    // ASCII lowercasing keeps byte offsets identical to the original text
+    if text.is_ascii() && keyword.is_ascii() {
+        let haystack = text.to_ascii_lowercase();
+        let needle = keyword.to_ascii_lowercase();
+        return haystack
+            .match_indices(&needle)
+            .map(|(start, matched)| start..start + matched.len())
+            .collect();
+    }
+
+    // Other scripts can change length when lowercased, so each byte of the lowercased
+    // text remembers where its character started and ended in the original
+    let mut haystack = String::new();
+    let mut origin: Vec<Range<usize>> = Vec::new();
+    for (start, character) in text.char_indices() {
+        let end = start + character.len_utf8();
+        for lower in character.to_lowercase() {
+            haystack.push(lower);
+            origin.resize(haystack.len(), start..end);
+        }
+    }
+    let needle = keyword.to_lowercase();
+    haystack
+        .match_indices(&needle)
+        .map(|(start, matched)| origin[start].start..origin[start + matched.len() - 1].end)
+        .collect()
+}
+
+// Two sets of spans as one sorted set, merging spans that overlap or touch
+fn join_spans(a: &[Range<usize>], b: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut spans: Vec<Range<usize>> = a.iter().chain(b).cloned().collect();
+    spans.sort_by_key(|span| span.start);
+    let mut joined: Vec<Range<usize>> = Vec::new();
+    for span in spans {
+        match joined.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => joined.push(span),
+        }
+    }
+    joined
+}
+
+// Render text for the terminal with the given spans highlighted
+pub fn mark_terminal(text: &str, spans: &[Range<usize>]) -> String {
+    mark(text, spans, |plain| plain.to_string(), |matched| matched.styled(Style::Match).to_string())
+}
+
+// Render text as escaped HTML with the given spans wrapped in <mark>
+pub fn mark_html(text: &str, spans: &[Range<usize>]) -> String {
+    mark(text, spans, escape_html, |matched| format!("<mark>{}</mark>", escape_html(matched)))
+}
+
+fn mark(text: &str, spans: &[Range<usize>], plain: impl Fn(&str) -> String, marked: impl Fn(&str) -> String) -> String {
+    let mut output = String::new();
+    let mut position = 0;
+    for span in spans {
+        output.push_str(&plain(&text[position..span.start]));
+        output.push_str(&marked(&text[span.clone()]));
+        position = span.end;
+    }
+    output.push_str(&plain(&text[position..]));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::{DiagnosticsDatabase, ErrorCode};
+    use crate::testing::{init, sample_database};
+    use super::{find_spans, LastSearch, SearchOptions, SortOrder};
+
+    #[test]
+    fn search_pages_are_ranked() {
+        init();
+        let db = sample_database();
+        let everything = db.search("sensor", &SearchOptions::default());
+        assert!(everything.hits.windows(2).all(|pair| pair[0].score >= pair[1].score));
+
+        let page = db.search("sensor", &SearchOptions { offset: 1, limit: Some(1), sort: SortOrder::Code }).into_owned();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.hits.len(), 1);
+        assert_eq!(page.hits[0].error.code, "P0101");
+        assert!(!page.has_more());
+    }
+
+    #[test]
+    fn refined_search_keeps_hits_matching_every_keyword() {
+        init();
+        let db = sample_database();
+        let sensor = db.search_all("sensor");
+        let refined = sensor.refine("mass");
+        assert_eq!(refined.keywords, ["sensor", "mass"]);
+        assert_eq!(refined.last_search().codes, ["P0101"]);
+
+        // Spans of both keywords are highlighted, in order
+        let description = &refined.hits[0].error.description;
+        let marked: Vec<&str> = refined.hits[0].highlights.description.iter().map(|span| &description[span.clone()]).collect();
+        assert_eq!(marked, ["Mass", "Sensor"]);
+
+        // The kept result set gives the same hits without scanning the database
+        let again = db.search_within(&refined.last_search());
+        assert_eq!(again.last_search(), refined.last_search());
+        assert!(sensor.refine("xyzzy").is_empty());
+    }
+
+    #[test]
+    fn reverse_lookup_ranks_the_closest_description_first() {
+        let describe = |code: &str, description: &str| ErrorCode {
+            description: description.to_string(),
+            ..ErrorCode::fixture_with(code, "Medium", "Emissions")
+        };
+        let db = DiagnosticsDatabase::from_records([
+            describe("P0420", "Catalyst System Efficiency Below Threshold (Bank 1)"),
+            describe("P0430", "Catalyst System Efficiency Below Threshold (Bank 2)"),
+            describe("P0171", "System Too Lean (Bank 1)"),
+            describe("P0300", "Random/Multiple Cylinder Misfire Detected"),
+        ]);
+
+        let page = db.reverse_lookup("Catalyst efficiency below the threshold, bank 1").page(&SearchOptions::default());
+        let codes: Vec<&str> = page.hits.iter().map(|hit| hit.error.code.as_str()).collect();
+        assert_eq!(codes, ["P0420", "P0430", "P0171"]);
+        assert_eq!(page.hits[0].highlights.description.len(), 6);
+        assert!(db.reverse_lookup("the of and").is_empty());
+    }
+
+    #[test]
+    fn search_matches_aliases_and_translations_in_any_case() {
+        let mut db = DiagnosticsDatabase::from_csv_str(
+            "code,description,severity,system,possible_causes,recommended_actions,aliases\n\
+             P0455,EVAP Leak Detected (Gross Leak),Medium,Emissions,Loose cap,Tighten cap,gas cap code|large evap leak\n\
+             P0300,Random Misfire Detected,High,Engine,Spark plugs,Replace plugs,\n",
+        );
+        Arc::make_mut(db.errors.get_mut("P0300").unwrap()).translation = Some("Slumpmässig feltändning".to_string());
+
+        let page = db.search("GAS CAP", &SearchOptions::default());
+        assert_eq!(page.hits.iter().map(|hit| hit.error.code.as_str()).collect::<Vec<_>>(), ["P0455"]);
+        assert_eq!(page.hits[0].highlights.aliases.iter().map(Vec::len).collect::<Vec<_>>(), [1, 0]);
+        assert_eq!(page.hits[0].score, 3);
+
+        let page = db.search("FELTÄNDNING", &SearchOptions::default());
+        assert_eq!(page.hits.iter().map(|hit| hit.error.code.as_str()).collect::<Vec<_>>(), ["P0300"]);
+        let translation = page.hits[0].error.translation.as_deref().unwrap();
+        let marked: Vec<&str> = page.hits[0].highlights.translation.iter().map(|span| &translation[span.clone()]).collect();
+        assert_eq!(marked, ["feltändning"]);
+
+        // Byte ranges stay on the original text when lowercasing changes lengths
+        let text = "İstanbul Ölçer";
+        let marked: Vec<&str> = find_spans(text, "öl").into_iter().map(|span| &text[span]).collect();
+        assert_eq!(marked, ["Öl"]);
+        assert_eq!(find_spans("ÅTERFÖRING återföring", "återföring"), [0..12, 13..25]);
+    }
+
+    #[test]
+    fn jsonl_search_streams_the_hits_refine_would_find() {
+        init();
+        let db = sample_database();
+        let keywords = ["sensor".to_string(), "check".to_string()];
+        let mut refined: Vec<String> = db.search_all("sensor").refine("check").hits.iter().map(|hit| hit.error.code.clone()).collect();
+        refined.sort();
+
+        let mut out = Vec::new();
+        let codes = super::write_jsonl(db.search_stream(&keywords, None), 1, Some(5), &mut out).unwrap();
+        assert_eq!(codes, refined);
+        assert_eq!(codes, ["C0035", "P0101"]);
+        let lines: Vec<serde_json::Value> = String::from_utf8(out).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["code"], "P0101");
+        assert_eq!(lines[0]["possible_causes"][0], "Dirty MAF sensor");
+        assert_eq!(lines[0]["matched"], serde_json::json!(["description", "possible_causes", "recommended_actions"]));
+        let mut out = Vec::new();
+        super::write_jsonl(db.search_stream(&keywords, None), 1, Some(usize::MAX), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+
+        let last = LastSearch { keywords: vec!["wiring".to_string()], codes: vec!["B0001".to_string(), "C0035".to_string()] };
+        let keywords = ["wiring".to_string(), "airbag".to_string()];
+        let within: Vec<String> = db.search_stream(&keywords, Some(&last)).map(|hit| hit.error.code.to_string()).collect();
+        assert_eq!(within, ["B0001"]);
+    }
+}
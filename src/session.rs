@@ -0,0 +1,179 @@
+// Resources shared by the commands, loaded on first use.
+//
+// Arguments are parsed before anything is read from disk, and each command
+// only pays for the files it actually needs: `docs man` touches nothing,
+// `parts` skips the labor guide, and so on. With a SQLite database
+// configured, `db_subset` reads only the codes a command shows.
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use crate::branding::Letterhead;
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::labor::LaborGuide;
+use crate::net::Client;
+use crate::policy::SeverityPolicy;
+use crate::sqlite::{self, Column, Subset};
+use crate::transcript::outln;
+use crate::{porcelain, DiagnosticsDatabase, DATABASE_FILE};
+
+// The error code database file does not exist
+#[derive(Debug)]
+pub struct MissingDatabase;
+
+impl fmt::Display for MissingDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", tr!("db-missing", path = DATABASE_FILE))
+    }
+}
+
+impl Error for MissingDatabase {}
+
+#[derive(Default)]
+pub struct Session {
+    config: OnceCell<Config>,
+    db: OnceCell<DiagnosticsDatabase>,
+    shared_db: OnceCell<DiagnosticsDatabase>,
+    labor: OnceCell<LaborGuide>,
+    letterhead: OnceCell<Letterhead>,
+    client: OnceCell<Client>,
+}
+
+impl Session {
+    pub fn config(&self) -> Result<&Config, Box<dyn Error>> {
+        if let Some(config) = self.config.get() {
+            return Ok(config);
+        }
+        let config = Config::load()?;
+        // Times are formatted with the configured zone from here on
+        crate::time::init(&config.time)?;
+        crate::presentation::set_theme(config.theme);
+        crate::layout::init(&config.layout);
+        crate::events::init(&config.events, &config.data_dir());
+        #[cfg(feature = "adapter")]
+        crate::adapterd::init(&config.data_dir());
+        Ok(self.config.get_or_init(|| config))
+    }
+
+    // The configuration if a command has loaded it, without loading it otherwise
+    pub fn loaded_config(&self) -> Option<&Config> {
+        self.config.get()
+    }
+
+    // The codes as the fleet sees them, with the severity policy applied
+    pub fn db(&self) -> Result<&DiagnosticsDatabase, Box<dyn Error>> {
+        if let Some(db) = self.db.get() {
+            return Ok(db);
+        }
+        let mut db = self.load_db()?;
+        if let Some(path) = &self.config()?.severity_policy {
+            SeverityPolicy::load(path)?.apply(&mut db);
+        }
+        Ok(self.db.get_or_init(|| db))
+    }
+
+    // The codes of `subset` as `db` has them: only those are read from a SQLite database,
+    // through its indexes, unless every code is loaded already
+    pub fn db_subset(&self, subset: Subset) -> Result<Cow<'_, DiagnosticsDatabase>, Box<dyn Error>> {
+        let config = self.config()?;
+        let Some(path) = config.database.as_deref().filter(|_| self.db.get().is_none()) else {
+            return self.db().map(Cow::Borrowed);
+        };
+        // A severity policy can move a code to another severity than the one stored, and
+        // translations, which are not stored, are searched too
+        let unranked;
+        let subset = match subset {
+            Subset::Severity(_) if config.severity_policy.is_some() => Subset::All,
+            Subset::Where(filter) if config.severity_policy.is_some() => {
+                unranked = filter.without(Column::Severity);
+                Subset::Where(&unranked)
+            },
+            Subset::Matching(_) if crate::i18n::language() != "en" => Subset::All,
+            subset => subset,
+        };
+        let mut db = sqlite::read(path, subset)?;
+        if crate::i18n::language() != "en" {
+            db.load_translations(crate::i18n::language())?;
+        }
+        if let Some(path) = &config.severity_policy {
+            SeverityPolicy::load(path)?.apply(&mut db);
+        }
+        crate::freshness::warn(config);
+        Ok(Cow::Owned(db))
+    }
+
+    // The codes as the database and packs define them, for the commands that edit or compare those
+    pub fn shared_db(&self) -> Result<&DiagnosticsDatabase, Box<dyn Error>> {
+        if let Some(db) = self.shared_db.get() {
+            return Ok(db);
+        }
+        let db = self.load_db()?;
+        Ok(self.shared_db.get_or_init(|| db))
+    }
+
+    fn load_db(&self) -> Result<DiagnosticsDatabase, Box<dyn Error>> {
+        let config = self.config()?;
+        if let Some(path) = &config.database {
+            let mut db = DiagnosticsDatabase::new();
+            let count = db.load_from_sqlite(path)?;
+            loaded(tr!("db-loaded", count = count));
+            if crate::i18n::language() != "en" {
+                db.load_translations(crate::i18n::language())?;
+            }
+            crate::freshness::warn(config);
+            return Ok(db);
+        }
+        if !Path::new(DATABASE_FILE).exists() {
+            return Err(Box::new(MissingDatabase));
+        }
+        let mut db = DiagnosticsDatabase::new();
+        let count = db.load_from_csv(DATABASE_FILE)?;
+        loaded(tr!("db-loaded", count = count));
+        for (position, pack) in config.packs.iter().enumerate() {
+            let count = db.load_pack(pack, pack.source(position))?;
+            loaded(tr!("db-pack-loaded", count = count, pack = pack.display_name()));
+        }
+        if !config.packs.is_empty() {
+            db.apply_merge_policy(&config.merge);
+        }
+        if crate::i18n::language() != "en" {
+            db.load_translations(crate::i18n::language())?;
+        }
+        crate::freshness::warn(config);
+        Ok(db)
+    }
+
+    pub fn labor(&self) -> Result<&LaborGuide, Box<dyn Error>> {
+        if let Some(labor) = self.labor.get() {
+            return Ok(labor);
+        }
+        let labor = self.config()?.load_labor_guide()?;
+        Ok(self.labor.get_or_init(|| labor))
+    }
+
+    pub fn letterhead(&self) -> Result<&Letterhead, Box<dyn Error>> {
+        if let Some(letterhead) = self.letterhead.get() {
+            return Ok(letterhead);
+        }
+        let letterhead = self.config()?.branding.load()?;
+        Ok(self.letterhead.get_or_init(|| letterhead))
+    }
+
+    pub fn client(&self) -> Result<&Client, Box<dyn Error>> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        let config = self.config()?;
+        let client = Client::new(&config.network, &config.data_dir())?;
+        Ok(self.client.get_or_init(|| client))
+    }
+}
+
+// Say how many codes were loaded, unless standard output is for programs
+fn loaded(message: String) {
+    if !porcelain::enabled() {
+        outln!("{}", message);
+    }
+}
@@ -0,0 +1,144 @@
+// Severity suggestions from similar codes.
+//
+// Descriptions are compared by keyword similarity: words are weighted by how
+// rare they are across the database (TF-IDF) and codes are ranked by the
+// cosine similarity of those weights. The suggested severity is a vote among
+// the most similar codes, weighted by similarity.
+use std::collections::{HashMap, HashSet};
+use crate::{DiagnosticsDatabase, ErrorCode};
+
+// Number of similar codes that vote on a severity
+const NEIGHBORS: usize = 5;
+
+// Codes less similar than this do not vote
+const MIN_SIMILARITY: f64 = 0.15;
+
+// Words that say nothing about what failed
+const STOP_WORDS: &[&str] = &["a", "an", "and", "at", "for", "in", "of", "on", "or", "the", "to", "with", "circuit", "detected"];
+
+// A severity with the codes it was inferred from
+pub struct Suggestion {
+    pub severity: String,
+    // Share of the vote that went to `severity`, from 0 to 1
+    pub confidence: f64,
+    // The codes that voted, most similar first
+    pub similar: Vec<(String, String, f64)>,
+}
+
+// Keyword weights of every code in a database
+pub struct SeverityModel<'a> {
+    entries: Vec<(&'a ErrorCode, HashMap<String, f64>)>,
+    idf: HashMap<String, f64>,
+}
+
+fn tokens(error: &ErrorCode) -> Vec<String> {
+    let mut tokens: Vec<String> = error.description
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| word.len() > 1 && !STOP_WORDS.contains(&word.as_str()))
+        .collect();
+    if !error.system.is_empty() {
+        tokens.push(format!("system:{}", error.system.to_lowercase()));
+    }
+    tokens
+}
+
+impl<'a> SeverityModel<'a> {
+    pub fn new(db: &'a DiagnosticsDatabase) -> Self {
+        let mut errors: Vec<&ErrorCode> = db.records().collect();
+        errors.sort_by(|a, b| a.code.cmp(&b.code));
+
+        let mut document_frequency: HashMap<String, usize> = HashMap::new();
+        for error in &errors {
+            for token in tokens(error).into_iter().collect::<HashSet<_>>() {
+                *document_frequency.entry(token).or_default() += 1;
+            }
+        }
+        let count = errors.len() as f64;
+        let idf = document_frequency
+            .into_iter()
+            .map(|(token, frequency)| (token, (1.0 + count / frequency as f64).ln()))
+            .collect();
+
+        let mut model = SeverityModel { entries: Vec::new(), idf };
+        model.entries = errors.into_iter().map(|error| (error, model.weights(error))).collect();
+        model
+    }
+
+    fn weights(&self, error: &ErrorCode) -> HashMap<String, f64> {
+        let mut weights: HashMap<String, f64> = HashMap::new();
+        for token in tokens(error) {
+            // Words never seen in the database still count, as if they were unique
+            let idf = self.idf.get(&token).copied().unwrap_or((1.0 + self.entries.len() as f64).ln());
+            *weights.entry(token).or_default() += idf;
+        }
+        weights
+    }
+
+    // Suggest a severity for a code from the most similar other codes
+    pub fn suggest(&self, error: &ErrorCode) -> Option<Suggestion> {
+        let weights = self.weights(error);
+        let mut similar: Vec<(&ErrorCode, f64)> = self.entries
+            .iter()
+            .filter(|(other, _)| other.code != error.code && !other.severity.is_empty())
+            .map(|(other, other_weights)| (*other, cosine(&weights, other_weights)))
+            .filter(|(_, similarity)| *similarity >= MIN_SIMILARITY)
+            .collect();
+        similar.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.code.cmp(&b.0.code)));
+        similar.truncate(NEIGHBORS);
+
+        let mut votes: Vec<(&str, f64)> = Vec::new();
+        for (other, similarity) in &similar {
+            match votes.iter_mut().find(|(severity, _)| severity.eq_ignore_ascii_case(&other.severity)) {
+                Some((_, weight)) => *weight += similarity,
+                None => votes.push((&other.severity, *similarity)),
+            }
+        }
+        let total: f64 = votes.iter().map(|(_, weight)| weight).sum();
+        let (severity, weight) = votes.into_iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        Some(Suggestion {
+            severity: severity.to_string(),
+            confidence: weight / total,
+            similar: similar
+                .into_iter()
+                .map(|(other, similarity)| (other.code.clone(), other.severity.clone(), similarity))
+                .collect(),
+        })
+    }
+}
+
+fn cosine(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a.iter().filter_map(|(token, weight)| b.get(token).map(|other| weight * other)).sum();
+    let norm = |weights: &HashMap<String, f64>| weights.values().map(|weight| weight * weight).sum::<f64>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 { 0.0 } else { dot / denominator }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DiagnosticsDatabase, ErrorCode};
+
+    #[test]
+    fn severity_suggested_from_similar_descriptions() {
+        let describe = |code: &str, severity: &str, description: &str| ErrorCode {
+            description: description.to_string(),
+            ..ErrorCode::fixture_with(code, severity, "Engine")
+        };
+        let db = DiagnosticsDatabase::from_records([
+            describe("P0301", "High", "Cylinder 1 Misfire Detected"),
+            describe("P0302", "High", "Cylinder 2 Misfire Detected"),
+            describe("P0303", "High", "Cylinder 3 Misfire Detected"),
+            describe("P0420", "Medium", "Catalyst System Efficiency Below Threshold"),
+        ]);
+        let model = super::SeverityModel::new(&db);
+
+        let suggestion = model.suggest(&describe("P0304", "", "Cylinder 4 Misfire Detected")).unwrap();
+        assert_eq!(suggestion.severity, "High");
+        assert_eq!(suggestion.similar.len(), 3);
+
+        // A code is not compared with itself
+        let suggestion = model.suggest(&db.errors["P0420"]);
+        assert!(suggestion.is_none_or(|suggestion| suggestion.similar.iter().all(|(code, _, _)| code != "P0420")));
+    }
+}
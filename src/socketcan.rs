@@ -0,0 +1,105 @@
+// Raw CAN frames from a Linux SocketCAN interface (`monitor --can can0`).
+//
+// ELM327 adapters only answer requests; a SocketCAN interface (a USB-CAN
+// dongle, a PiCAN hat, or vcan0 for replaying a capture) sees every frame
+// broadcast on the bus, which is what the signals of a DBC file need. The
+// interface has to be up first, e.g. `ip link set can0 up type can bitrate 500000`.
+use std::error::Error;
+use std::time::Duration;
+
+// One frame as received; the id keeps SocketCAN's extended flag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub id: u32,
+    pub data: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+pub struct CanSocket {
+    fd: std::os::fd::OwnedFd,
+}
+
+#[cfg(target_os = "linux")]
+impl CanSocket {
+    // Bind a raw socket to the interface; each read waits at most `timeout`
+    pub fn open(interface: &str, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        use std::ffi::CString;
+        use std::io;
+        use std::mem;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        let name = CString::new(interface).map_err(|_| format!("Invalid CAN interface '{}'", interface))?;
+        // SAFETY: plain libc calls on a socket this function owns; every structure passed
+        // is fully initialized and sized with mem::size_of
+        unsafe {
+            let index = libc::if_nametoindex(name.as_ptr());
+            if index == 0 {
+                return Err(format!("No CAN interface '{}': {}", interface, io::Error::last_os_error()).into());
+            }
+            let raw = libc::socket(libc::PF_CAN, libc::SOCK_RAW, libc::CAN_RAW);
+            if raw < 0 {
+                return Err(format!("Could not open a CAN socket: {}", io::Error::last_os_error()).into());
+            }
+            let fd = OwnedFd::from_raw_fd(raw);
+
+            let mut address: libc::sockaddr_can = mem::zeroed();
+            address.can_family = libc::AF_CAN as libc::sa_family_t;
+            address.can_ifindex = index as libc::c_int;
+            let bound = libc::bind(
+                fd.as_raw_fd(),
+                &address as *const libc::sockaddr_can as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            );
+            if bound < 0 {
+                return Err(format!("Could not bind to {}: {}", interface, io::Error::last_os_error()).into());
+            }
+
+            let wait = libc::timeval { tv_sec: timeout.as_secs() as libc::time_t, tv_usec: timeout.subsec_micros() as libc::suseconds_t };
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &wait as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            );
+            Ok(CanSocket { fd })
+        }
+    }
+
+    // The next frame, or None when none arrived within the timeout
+    pub fn read_frame(&self) -> Result<Option<Frame>, Box<dyn Error>> {
+        use std::io::{self, ErrorKind};
+        use std::mem;
+        use std::os::fd::AsRawFd;
+
+        // SAFETY: reads at most size_of::<can_frame>() bytes into a zeroed can_frame
+        let (read, frame) = unsafe {
+            let mut frame: libc::can_frame = mem::zeroed();
+            let read = libc::read(self.fd.as_raw_fd(), &mut frame as *mut libc::can_frame as *mut libc::c_void, mem::size_of::<libc::can_frame>());
+            (read, frame)
+        };
+        if read < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted => Ok(None),
+                _ => Err(error.into()),
+            };
+        }
+        let length = usize::from(frame.can_dlc).min(frame.data.len());
+        Ok(Some(Frame { id: frame.can_id, data: frame.data[..length].to_vec() }))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct CanSocket;
+
+#[cfg(not(target_os = "linux"))]
+impl CanSocket {
+    pub fn open(_interface: &str, _timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        Err("SocketCAN is only available on Linux".into())
+    }
+
+    pub fn read_frame(&self) -> Result<Option<Frame>, Box<dyn Error>> {
+        Ok(None)
+    }
+}
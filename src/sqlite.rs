@@ -0,0 +1,517 @@
+// The error code database as a SQLite file (`db import --sqlite`, and
+// `database` in cars-scanner.toml).
+//
+// Parsing the CSV files on every run gets slow once a database holds tens of
+// thousands of codes. `db import <file> --sqlite codes.sqlite` converts a
+// database file once; with `database = "codes.sqlite"` set, `lookup`,
+// `list-by-system`, `list-by-severity`, `list` and `query` read only the
+// codes they show, selected in SQL through the indexes below, and `search`
+// only the codes holding its keyword, through the trigram index of `search`.
+// `list` and `query` pass a `Filter` of the conditions SQL answers and apply
+// theirs to what it reads; a query condition SQL cannot answer, such as a
+// LIKE, reads every code it may match. `reverse` and the other commands still
+// load all the codes into memory, only from this file instead of from the CSV
+// files. The bundled database and packs are not read while it is set;
+// commands warn when one of the files it was converted from changed since,
+// and `db import --sqlite codes.sqlite --refresh` converts them again.
+//
+//   CREATE TABLE codes (code PRIMARY KEY, system, severity, record,
+//                       pack, file, version, precedence, definition)
+//   CREATE TABLE definitions (code, position, record,
+//                             pack, file, version, precedence)
+//   CREATE TABLE properties (name PRIMARY KEY, value)
+//   CREATE TABLE sources (position PRIMARY KEY, path, hash)
+//   CREATE VIRTUAL TABLE search USING fts5 (code, text)
+//
+// `codes` holds the definition in effect of each code and `definitions`
+// every definition the packs gave it, in loading order, for `--all-sources`
+// and `db explain-merge`; `codes.definition` is the position of the one in
+// effect, or NULL when it was merged from several. `record` holds the code's columns as JSON, in the
+// form of the database file; `system` and `severity` are indexed and
+// compared ignoring case, as the list commands do, and the last four columns
+// say where the definition came from. The `updated` property is the day the
+// converted file was last brought up to date, for the staleness warning.
+// `sources` lists the database file and packs converted, in loading order,
+// with the content hash of each as it was read. `search` holds the text
+// keywords are searched in, lowercased; the translations are loaded from
+// their files, so in another language `search` reads every code.
+// The schema version of the CSV columns is the file's user_version, so a
+// newer file is refused as a newer CSV file is.
+//
+// SQLite is compiled in with the `sqlite` feature; without it, these
+// functions fail saying so.
+use std::error::Error;
+use crate::freshness::Fingerprint;
+use crate::i18n::tr;
+use crate::DiagnosticsDatabase;
+#[cfg(feature = "sqlite")]
+use std::fs;
+#[cfg(feature = "sqlite")]
+use std::sync::Arc;
+#[cfg(feature = "sqlite")]
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension, Row};
+#[cfg(feature = "sqlite")]
+use crate::{freshness, pipe_list, schema, ErrorCode, Source};
+
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+    CREATE TABLE codes (
+        code TEXT PRIMARY KEY,
+        system TEXT NOT NULL COLLATE NOCASE,
+        severity TEXT NOT NULL COLLATE NOCASE,
+        record TEXT NOT NULL,
+        pack TEXT,
+        file TEXT,
+        version TEXT,
+        precedence INTEGER,
+        definition INTEGER
+    );
+    CREATE INDEX codes_system ON codes (system);
+    CREATE INDEX codes_severity ON codes (severity);
+    CREATE TABLE definitions (
+        code TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        record TEXT NOT NULL,
+        pack TEXT,
+        file TEXT,
+        version TEXT,
+        precedence INTEGER,
+        PRIMARY KEY (code, position)
+    );
+    CREATE TABLE properties (
+        name TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE sources (
+        position INTEGER PRIMARY KEY,
+        path TEXT NOT NULL,
+        hash TEXT NOT NULL
+    );
+    CREATE VIRTUAL TABLE search USING fts5 (code UNINDEXED, text, tokenize = 'trigram case_sensitive 1');
+";
+
+// The codes a command reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subset<'a> {
+    All,
+    Code(&'a str),
+    System(&'a str),
+    Severity(&'a str),
+    // The codes whose text holds the keyword, as `search` matches it in English
+    Matching(&'a str),
+    // The codes the filter holds for
+    Where(&'a Filter),
+}
+
+// Conditions on a code's columns that SQL answers, for `list` and `query`. Values compare
+// ignoring case in ASCII, as `list` compares them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    // Every code
+    Any,
+    // Codes with one of the values in the column
+    In(Column, Vec<String>),
+    // Codes covered by the emissions warranty
+    WarrantyCovered,
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Code,
+    System,
+    Severity,
+    Difficulty,
+}
+
+impl Filter {
+    // Both conditions, leaving out one that holds for every code
+    pub fn and(self, other: Filter) -> Filter {
+        match (self, other) {
+            (Filter::Any, filter) | (filter, Filter::Any) => filter,
+            (first, second) => Filter::And(Box::new(first), Box::new(second)),
+        }
+    }
+
+    // Either condition
+    pub fn or(self, other: Filter) -> Filter {
+        match (self, other) {
+            (Filter::Any, _) | (_, Filter::Any) => Filter::Any,
+            (first, second) => Filter::Or(Box::new(first), Box::new(second)),
+        }
+    }
+
+    // The filter without its conditions on `column`, for when the stored values are not the ones compared
+    pub fn without(&self, column: Column) -> Filter {
+        match self {
+            Filter::In(condition, _) if *condition == column => Filter::Any,
+            Filter::And(first, second) => first.without(column).and(second.without(column)),
+            Filter::Or(first, second) => first.without(column).or(second.without(column)),
+            filter => filter.clone(),
+        }
+    }
+
+    // The filter as an SQL expression over `codes`, adding its values to `values`
+    #[cfg(feature = "sqlite")]
+    fn sql(&self, values: &mut Vec<String>) -> String {
+        match self {
+            Filter::Any => "1".to_string(),
+            Filter::In(column, options) => {
+                let placeholders: Vec<String> = options.iter()
+                    .map(|option| {
+                        values.push(option.clone());
+                        format!("?{}", values.len())
+                    })
+                    .collect();
+                format!("{} COLLATE NOCASE IN ({})", column.sql(), placeholders.join(", "))
+            },
+            Filter::WarrantyCovered => "json_extract(record, '$.emissions_warranty') = 1".to_string(),
+            Filter::And(first, second) => format!("({} AND {})", first.sql(values), second.sql(values)),
+            Filter::Or(first, second) => format!("({} OR {})", first.sql(values), second.sql(values)),
+        }
+    }
+}
+
+impl Column {
+    #[cfg(feature = "sqlite")]
+    fn sql(self) -> &'static str {
+        match self {
+            Column::Code => "code",
+            Column::System => "system",
+            Column::Severity => "severity",
+            // Not indexed; read from the record, trimmed as the levels are parsed
+            Column::Difficulty => "trim(json_extract(record, '$.difficulty'))",
+        }
+    }
+}
+
+// Write every code, with all its definitions, to a new SQLite file at `path`, replacing any
+// file there only once it is complete; `updated` is the day the codes were last brought up
+// to date and `sources` the files they were read from. Returns the number of codes.
+#[cfg(feature = "sqlite")]
+pub fn write(db: &DiagnosticsDatabase, path: &str, updated: Option<u64>, sources: &[Fingerprint]) -> Result<usize, Box<dyn Error>> {
+    let partial = format!("{}.partial", path);
+    let _ = fs::remove_file(&partial);
+    let mut connection = Connection::open(&partial).map_err(|e| tr!("sqlite-failed", path = path, error = e.to_string()))?;
+    connection.execute_batch(SCHEMA)?;
+    connection.pragma_update(None, "user_version", schema::CURRENT)?;
+    let mut codes: Vec<&ErrorCode> = db.records().collect();
+    codes.sort_by(|a, b| a.code.cmp(&b.code));
+    let transaction = connection.transaction()?;
+    {
+        let mut insert_code = transaction.prepare("INSERT INTO codes (code, system, severity, record, pack, file, version, precedence, definition) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")?;
+        let mut insert_definition = transaction.prepare("INSERT INTO definitions (code, position, record, pack, file, version, precedence) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+        let mut insert_text = transaction.prepare("INSERT INTO search (code, text) VALUES (?1, ?2)")?;
+        for error in &codes {
+            let definitions = db.definitions_of(&error.code);
+            let in_effect = definitions.iter().position(|definition| std::ptr::eq(definition.as_ref(), *error));
+            let source = error.source.as_deref();
+            insert_code.execute(params![
+                error.code,
+                error.system,
+                error.severity,
+                serde_json::to_string(error)?,
+                source.map(|source| &source.pack),
+                source.map(|source| &source.file),
+                source.and_then(|source| source.version.as_ref()),
+                source.map(|source| source.precedence),
+                in_effect,
+            ])?;
+            insert_text.execute(params![error.code, searchable(error)])?;
+            for (position, definition) in definitions.iter().enumerate() {
+                let source = definition.source.as_deref();
+                insert_definition.execute(params![
+                    error.code,
+                    position,
+                    serde_json::to_string(definition)?,
+                    source.map(|source| &source.pack),
+                    source.map(|source| &source.file),
+                    source.and_then(|source| source.version.as_ref()),
+                    source.map(|source| source.precedence),
+                ])?;
+            }
+        }
+        if let Some(updated) = updated {
+            transaction.execute("INSERT INTO properties (name, value) VALUES ('updated', ?1)", params![freshness::date(updated)])?;
+        }
+        let mut insert_source = transaction.prepare("INSERT INTO sources (position, path, hash) VALUES (?1, ?2, ?3)")?;
+        for (position, source) in sources.iter().enumerate() {
+            insert_source.execute(params![position, source.path, format!("{:016x}", source.hash)])?;
+        }
+    }
+    transaction.commit()?;
+    connection.close().map_err(|(_, e)| e)?;
+    fs::rename(&partial, path)?;
+    Ok(codes.len())
+}
+
+// The text `search` looks for keywords in, lowercased a character at a time as it compares them,
+// so every code it could match holds the lowercased keyword. Translations are not stored.
+#[cfg(feature = "sqlite")]
+fn searchable(error: &ErrorCode) -> String {
+    let fields = std::iter::once(error.description.as_str())
+        .chain(error.possible_causes.iter().map(String::as_str))
+        .chain(error.recommended_actions.iter().map(String::as_str))
+        .chain(pipe_list(&error.aliases));
+    let lines: Vec<String> = fields.map(|field| field.chars().flat_map(char::to_lowercase).collect()).collect();
+    lines.join("\n")
+}
+
+// Whether the file has the table; files converted by older versions lack the newer ones
+#[cfg(feature = "sqlite")]
+fn has_table(connection: &Connection, table: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(connection.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = ?1)", params![table], |row| row.get(0))?)
+}
+
+// Open a file written by `write` for reading, refusing one from a newer version
+#[cfg(feature = "sqlite")]
+fn open(path: &str) -> Result<Connection, Box<dyn Error>> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| tr!("sqlite-failed", path = path, error = e.to_string()))?;
+    let version: u32 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > schema::CURRENT {
+        return Err(tr!("sqlite-newer", path = path, version = version, supported = schema::CURRENT).into());
+    }
+    Ok(connection)
+}
+
+// Read the codes of `subset` from the SQLite file at `path`, with all their definitions
+#[cfg(feature = "sqlite")]
+pub fn read(path: &str, subset: Subset) -> Result<DiagnosticsDatabase, Box<dyn Error>> {
+    let connection = open(path)?;
+    let mut values = Vec::new();
+    let filter = match subset {
+        Subset::All => String::new(),
+        Subset::Code(code) => {
+            values.push(code.to_string());
+            "WHERE code = ?1".to_string()
+        },
+        Subset::System(system) => {
+            values.push(system.to_string());
+            "WHERE system = ?1".to_string()
+        },
+        Subset::Severity(severity) => {
+            values.push(severity.to_string());
+            "WHERE severity = ?1".to_string()
+        },
+        // Without the table every code is read and searched in memory
+        Subset::Matching(_) if !has_table(&connection, "search")? => String::new(),
+        Subset::Matching(keyword) => {
+            // The trigram index answers GLOB; its wildcards stand for themselves in brackets
+            let keyword: String = keyword.to_lowercase().chars()
+                .map(|c| if matches!(c, '*' | '?' | '[') { format!("[{}]", c) } else { c.to_string() })
+                .collect();
+            values.push(format!("*{}*", keyword));
+            "WHERE code IN (SELECT code FROM search WHERE text GLOB ?1)".to_string()
+        },
+        Subset::Where(filter) => format!("WHERE {}", filter.sql(&mut values)),
+    };
+    // Definitions from the same file share one source, as when read from CSV
+    let mut sources: Vec<Arc<Source>> = Vec::new();
+    let mut decode = |record: String, source: Option<Source>| -> Result<Arc<ErrorCode>, Box<dyn Error>> {
+        let mut error: ErrorCode = serde_json::from_str(&record).map_err(|e| tr!("sqlite-failed", path = path, error = e.to_string()))?;
+        error.source = source.map(|source| match sources.iter().find(|known| ***known == source) {
+            Some(known) => Arc::clone(known),
+            None => {
+                sources.push(Arc::new(source));
+                Arc::clone(sources.last().expect("just pushed"))
+            },
+        });
+        Ok(Arc::new(error))
+    };
+
+    let mut db = DiagnosticsDatabase::new();
+    let query = format!("SELECT code, record, pack, file, version, precedence FROM definitions WHERE code IN (SELECT code FROM codes {}) ORDER BY code, position", filter);
+    let mut statement = connection.prepare(&query)?;
+    for row in statement.query_map(params_from_iter(&values), definition_row)? {
+        let (code, record, source) = row?;
+        let definition = decode(record, source)?;
+        db.definitions.entry(code).or_default().push(definition);
+    }
+    let query = format!("SELECT code, record, pack, file, version, precedence, definition FROM codes {}", filter);
+    let mut statement = connection.prepare(&query)?;
+    let rows = statement.query_map(params_from_iter(&values), |row| Ok((definition_row(row)?, row.get::<_, Option<usize>>(6)?)))?;
+    for row in rows {
+        let ((code, record, source), in_effect) = row?;
+        // Shared with its definition unless it was merged from several
+        let error = match in_effect.and_then(|position| db.definitions_of(&code).get(position)) {
+            Some(definition) => Arc::clone(definition),
+            None => decode(record, source)?,
+        };
+        db.errors.insert(code, error);
+    }
+    Ok(db)
+}
+
+// The code, record and source of a row of `codes` or `definitions`
+#[cfg(feature = "sqlite")]
+fn definition_row(row: &Row) -> rusqlite::Result<(String, String, Option<Source>)> {
+    let source = match row.get::<_, Option<String>>(2)? {
+        Some(pack) => Some(Source { pack, file: row.get::<_, Option<String>>(3)?.unwrap_or_default(), version: row.get(4)?, precedence: row.get::<_, Option<i32>>(5)?.unwrap_or_default() }),
+        None => None,
+    };
+    Ok((row.get(0)?, row.get(1)?, source))
+}
+
+// The day the codes in the SQLite file at `path` were last brought up to date, when it was recorded
+#[cfg(feature = "sqlite")]
+pub fn updated(path: &str) -> Result<Option<u64>, Box<dyn Error>> {
+    let connection = open(path)?;
+    let day: Option<String> = connection.query_row("SELECT value FROM properties WHERE name = 'updated'", [], |row| row.get(0)).optional()?;
+    match day {
+        Some(day) => Ok(Some(freshness::parse_date(&day)?)),
+        None => Ok(None),
+    }
+}
+
+// The files the codes in the SQLite file at `path` were converted from, as they were then
+#[cfg(feature = "sqlite")]
+pub fn sources(path: &str) -> Result<Vec<Fingerprint>, Box<dyn Error>> {
+    let connection = open(path)?;
+    if !has_table(&connection, "sources")? {
+        return Ok(Vec::new());
+    }
+    let mut statement = connection.prepare("SELECT path, hash FROM sources ORDER BY position")?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    let mut sources = Vec::new();
+    for row in rows {
+        let (source, hash) = row?;
+        let hash = u64::from_str_radix(&hash, 16).map_err(|e| tr!("sqlite-failed", path = path, error = e.to_string()))?;
+        sources.push(Fingerprint { path: source, hash });
+    }
+    Ok(sources)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn write(_db: &DiagnosticsDatabase, _path: &str, _updated: Option<u64>, _sources: &[Fingerprint]) -> Result<usize, Box<dyn Error>> {
+    Err(tr!("sqlite-unavailable").into())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn read(_path: &str, _subset: Subset) -> Result<DiagnosticsDatabase, Box<dyn Error>> {
+    Err(tr!("sqlite-unavailable").into())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn updated(_path: &str) -> Result<Option<u64>, Box<dyn Error>> {
+    Err(tr!("sqlite-unavailable").into())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn sources(_path: &str) -> Result<Vec<Fingerprint>, Box<dyn Error>> {
+    Err(tr!("sqlite-unavailable").into())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use crate::merge::MergePolicy;
+    use crate::query::Query;
+    use crate::testing::{sample_database, TempDir, FIXED_TIME, SAMPLE_DATABASE};
+    use crate::{freshness, Difficulty, DiagnosticsDatabase, ListFilter, Source};
+    use super::*;
+
+    const PACK: &str = "\
+code,description,severity,system,possible_causes,recommended_actions
+P0300,\"Misfire, as the maker words it\",High,Engine,Worn spark plugs,Replace spark plugs
+";
+
+    fn codes(db: &DiagnosticsDatabase) -> Vec<String> {
+        let mut codes: Vec<String> = db.records().map(|error| error.code.clone()).collect();
+        codes.sort();
+        codes
+    }
+
+    fn packs(db: &DiagnosticsDatabase, code: &str) -> Vec<String> {
+        db.definitions_of(code).iter().map(|definition| definition.source.as_ref().unwrap().pack.clone()).collect()
+    }
+
+    #[test]
+    fn a_sqlite_database_reads_back_only_the_codes_asked_for_with_every_source() {
+        let dir = TempDir::new("sqlite");
+        let path = dir.file("codes.sqlite");
+        let mut db = sample_database();
+        let pack = Source { pack: "maker".to_string(), file: "maker.csv".to_string(), version: None, precedence: 1 };
+        db.read_records(PACK.as_bytes(), pack).unwrap();
+        let mut merged = db.clone();
+        merged.apply_merge_policy(&MergePolicy::default());
+        let converted = [Fingerprint::new("error_codes.csv", SAMPLE_DATABASE.as_bytes()), Fingerprint::new("maker.csv", PACK.as_bytes())];
+        assert_eq!(db.save_to_sqlite(&path, Some(FIXED_TIME), &converted).unwrap(), 4);
+        let merged_path = dir.file("merged.sqlite");
+        merged.save_to_sqlite(&merged_path, None, &[]).unwrap();
+
+        let all = read(&path, Subset::All).unwrap();
+        let one = read(&path, Subset::Code("P0300")).unwrap();
+        let engine = read(&path, Subset::System("engine")).unwrap();
+        let critical = read(&path, Subset::Severity("CRITICAL")).unwrap();
+        let merged_back = read(&merged_path, Subset::Code("P0300")).unwrap();
+        let stamped = updated(&path).unwrap();
+        let unstamped = updated(&merged_path).unwrap();
+        let recorded = sources(&path).unwrap();
+        let unrecorded = sources(&merged_path).unwrap();
+
+        for error in db.records() {
+            let read = all.shared_error(&error.code).unwrap();
+            assert_eq!(serde_json::to_value(&*read).unwrap(), serde_json::to_value(error).unwrap());
+            assert_eq!(read.source, error.source);
+        }
+        // Every definition is kept for --all-sources and explain-merge, and the one in effect is shared
+        assert_eq!(packs(&all, "P0300"), ["fixture", "maker"]);
+        assert_eq!(packs(&one, "P0300"), ["fixture", "maker"]);
+        assert!(Arc::ptr_eq(&one.shared_error("P0300").unwrap(), &one.definitions_of("P0300")[1]));
+        assert_eq!(one.lookup_error("P0300").unwrap().description, "Misfire, as the maker words it");
+        let merged_error = merged.lookup_error("P0300").unwrap();
+        assert_eq!(serde_json::to_value(merged_back.lookup_error("P0300").unwrap()).unwrap(), serde_json::to_value(merged_error).unwrap());
+        assert_eq!(packs(&merged_back, "P0300"), ["fixture", "maker"]);
+
+        // Reverse and the other commands read `Subset::All`; these read through the indexes
+        assert_eq!(codes(&one), ["P0300"]);
+        assert_eq!(codes(&engine), ["P0101", "P0300"]);
+        assert_eq!(packs(&engine, "P0101"), ["fixture"]);
+        assert_eq!(codes(&critical), ["B0001"]);
+
+        assert_eq!(stamped.map(freshness::date), Some(freshness::date(FIXED_TIME)));
+        assert_eq!(unstamped, None);
+        assert_eq!(recorded, converted);
+        assert!(unrecorded.is_empty());
+    }
+
+    #[test]
+    fn list_query_and_search_read_only_the_codes_they_can_show() {
+        let dir = TempDir::new("sqlite-subsets");
+        let path = dir.file("codes.sqlite");
+        let db = sample_database();
+        db.save_to_sqlite(&path, None, &[]).unwrap();
+        let list = |filter: ListFilter| {
+            let subset = read(&path, Subset::Where(&filter.narrowing())).unwrap();
+            assert_eq!(subset.list_errors(&filter).len(), db.list_errors(&filter).len());
+            codes(&subset)
+        };
+        let warranty = list(ListFilter { system: Some("ENGINE".to_string()), warranty_covered: true, ..ListFilter::default() });
+        let high = list(ListFilter { severity: Some("high".to_string()), ..ListFilter::default() });
+        let rated = list(ListFilter { max_difficulty: Some(Difficulty::Professional), ..ListFilter::default() });
+        let query = |sql: &str| {
+            let query = Query::parse(sql).unwrap();
+            let subset = read(&path, Subset::Where(&query.narrowing())).unwrap();
+            assert_eq!(query.run(&subset).len(), query.run(&db).len(), "{}", sql);
+            codes(&subset)
+        };
+        let milder = query("SELECT code FROM codes WHERE severity < 'High' AND description LIKE '%sensor%'");
+        let listed = query("SELECT code FROM codes WHERE code IN ('p0300', 'B0001')");
+        let either = query("SELECT code FROM codes WHERE system = 'abs' OR severity >= 'High'");
+        let search = |keyword: &str| codes(&read(&path, Subset::Matching(keyword)).unwrap());
+        let (misfire, wiring, wildcard) = (search("MISFIRE"), search("wiring"), search("*"));
+
+        assert_eq!(warranty, ["P0101", "P0300"]);
+        assert_eq!(high, ["P0300"]);
+        // The sample codes are unrated, which the difficulty filter leaves out
+        assert!(rated.is_empty());
+        assert_eq!(milder, ["C0035", "P0101"]);
+        assert_eq!(listed, ["B0001", "P0300"]);
+        // A condition SQL cannot answer reads every code, and the query filters them
+        assert_eq!(either.len(), 4);
+        assert_eq!(misfire, ["P0300"]);
+        assert_eq!(wiring, ["B0001", "C0035"]);
+        assert!(wildcard.is_empty());
+    }
+}
@@ -0,0 +1,292 @@
+// Fixtures for tests and for snapshot-testing exports.
+//
+// Compiled for `cargo test` and with `--features testing`. Everything here is
+// deterministic: fixed records, a fixed clock and English messages, so
+// rendered reports can be compared byte for byte with the golden files in
+// tests/golden. Run the tests with UPDATE_GOLDEN=1 to rewrite those files
+// after an intended change to the output.
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use crate::archive::{Clock, ScanRecord};
+use crate::branding::Letterhead;
+use crate::i18n;
+use crate::labor::{LaborGuide, LaborOperation};
+use crate::render::Stream;
+use crate::{DiagnosticsDatabase, ErrorCode, Source};
+
+// 2023-11-14 22:13:20 UTC
+pub const FIXED_TIME: u64 = 1_700_000_000;
+
+// A small database covering every severity and the optional columns
+pub const SAMPLE_DATABASE: &str = "\
+code,description,severity,system,possible_causes,recommended_actions,emissions_warranty,service_campaigns,campaign_links,related_parts
+P0101,\"Mass Air Flow Sensor Range/Performance\",Medium,Engine,\"Dirty MAF sensor|Vacuum leak\",\"Clean MAF sensor|Check for vacuum leaks\",true,,,MAF sensor:22204-0C020
+P0300,\"Random/Multiple Cylinder Misfire Detected\",High,Engine,\"Spark plug issues|Ignition coil problems\",\"Check spark plugs and wires|Test ignition coils\",true,\"Campaign 21V-123: coil pack replacement\",https://example.com/21V-123,Spark plug:90919-01253|Ignition coil:90919-02258
+C0035,\"Left Front Wheel Speed Sensor Circuit\",Low,ABS,\"Failed wheel speed sensor|Wiring issues\",\"Test wheel speed sensor|Check wiring and connectors\",,,,
+B0001,\"Driver's Airbag Circuit\",Critical,Airbag,\"Open or short in airbag circuit|Damaged wiring\",\"Check wiring and connectors|Professional diagnosis required\",,,,
+";
+
+// A clock that always reports the same time
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
+    }
+
+    fn batch_id(&self) -> String {
+        format!("{:x}", self.0)
+    }
+}
+
+impl ErrorCode {
+    // A fully populated record for P0300
+    pub fn fixture() -> Self {
+        ErrorCode {
+            code: "P0300".to_string(),
+            description: "Random/Multiple Cylinder Misfire Detected".to_string(),
+            severity: "High".to_string(),
+            system: "Engine".to_string(),
+            possible_causes: vec!["Spark plug issues".to_string(), "Ignition coil problems".to_string()],
+            recommended_actions: vec!["Check spark plugs and wires".to_string(), "Test ignition coils".to_string()],
+            emissions_warranty: Some(true),
+            service_campaigns: "Campaign 21V-123: coil pack replacement".to_string(),
+            campaign_links: "https://example.com/21V-123".to_string(),
+            related_parts: "Spark plug:90919-01253|Ignition coil:90919-02258".to_string(),
+            set_conditions: String::new(),
+            monitors: String::new(),
+            aliases: String::new(),
+            difficulty: String::new(),
+            safety_warnings: String::new(),
+            translation: None,
+            source: None,
+            adjusted: None,
+        }
+    }
+
+    // A minimal record with the given code, severity and system
+    pub fn fixture_with(code: &str, severity: &str, system: &str) -> Self {
+        ErrorCode {
+            code: code.to_string(),
+            description: format!("{} test fault", code),
+            severity: severity.to_string(),
+            system: system.to_string(),
+            possible_causes: vec!["First cause".to_string(), "Second cause".to_string()],
+            recommended_actions: vec!["First action".to_string(), "Second action".to_string()],
+            emissions_warranty: None,
+            service_campaigns: String::new(),
+            campaign_links: String::new(),
+            related_parts: String::new(),
+            set_conditions: String::new(),
+            monitors: String::new(),
+            aliases: String::new(),
+            difficulty: String::new(),
+            safety_warnings: String::new(),
+            translation: None,
+            source: None,
+            adjusted: None,
+        }
+    }
+}
+
+impl DiagnosticsDatabase {
+    // An in-memory database holding the given records
+    pub fn from_records(records: impl IntoIterator<Item = ErrorCode>) -> Self {
+        let mut db = DiagnosticsDatabase::new();
+        for record in records {
+            db.insert(Arc::new(record));
+        }
+        db
+    }
+
+    // An in-memory database parsed from CSV text in the database file format
+    pub fn from_csv_str(csv: &str) -> Self {
+        let mut db = DiagnosticsDatabase::new();
+        let source = Source { pack: "fixture".to_string(), file: "fixture.csv".to_string(), version: Some("1".to_string()), precedence: 0 };
+        db.read_records(csv.as_bytes(), source).expect("fixture CSV is valid");
+        db
+    }
+}
+
+// The database built from SAMPLE_DATABASE
+pub fn sample_database() -> DiagnosticsDatabase {
+    DiagnosticsDatabase::from_csv_str(SAMPLE_DATABASE)
+}
+
+// A labor guide with operations for P0300
+pub fn sample_labor_guide() -> LaborGuide {
+    let operation = |action: &str, op_code: &str, description: &str, hours: f64| LaborOperation {
+        code: "P0300".to_string(),
+        action: action.to_string(),
+        op_code: op_code.to_string(),
+        description: description.to_string(),
+        hours,
+    };
+    LaborGuide::from_operations([
+        operation("", "DIAG-ENG", "Engine performance diagnosis", 1.0),
+        operation("Test ignition coils", "COIL-TST", "Test ignition coils", 0.5),
+    ])
+}
+
+// A letterhead with every field set and a tiny SVG logo
+pub fn sample_letterhead() -> Letterhead {
+    Letterhead {
+        shop_name: Some("Main Street Auto & Tire".to_string()),
+        address: Some("12 Main Street\nSpringfield".to_string()),
+        logo: Some("data:image/svg+xml;base64,PHN2Zy8+".to_string()),
+        disclaimer: Some("Estimates are valid for 30 days.".to_string()),
+    }
+}
+
+// A scan record taken `days_ago` days before FIXED_TIME
+pub fn scan(id: &str, vin: &str, days_ago: u64, codes: &[&str]) -> ScanRecord {
+    ScanRecord {
+        id: id.to_string(),
+        vin: vin.to_string(),
+        timestamp: FIXED_TIME - days_ago * 24 * 60 * 60,
+        source: "fixture".to_string(),
+        codes: codes.iter().map(|code| code.to_string()).collect(),
+        summary: None,
+    }
+}
+
+// An ELM327 that answers each command from a table, for adapter tests.
+// Unknown commands get '?', like a real adapter.
+#[derive(Default)]
+pub struct FakeElm {
+    replies: HashMap<String, String>,
+    command: String,
+    output: Vec<u8>,
+}
+
+impl FakeElm {
+    pub fn reply(mut self, command: &str, reply: &str) -> Self {
+        self.replies.insert(command.to_string(), reply.to_string());
+        self
+    }
+
+    // A car on CAN with an engine and a transmission controller
+    pub fn car() -> Self {
+        FakeElm::default()
+            .reply("ATZ", "\r\rELM327 v1.5")
+            .reply("ATE0", "ATE0\rOK")
+            .reply("ATL0", "OK")
+            .reply("ATS0", "OK")
+            .reply("ATH1", "OK")
+            .reply("ATSP0", "OK")
+            .reply("0100", "SEARCHING...\r7E8064100BE3FA813\r7E906410088180010")
+            .reply("ATDPN", "A6")
+            .reply("ATDP", "AUTO, ISO 15765-4 (CAN 11/500)")
+            .reply("ATRV", "12.6V")
+            .reply("0105", "7E803410578")
+            .reply("010C", "7E804410C1AF8")
+            .reply("0142", "NO DATA")
+            .reply("0121", "NO DATA")
+            .reply("0131", "NO DATA")
+            .reply("014D", "NO DATA")
+            .reply("014E", "NO DATA")
+            .reply("0101", "7E806410100076505\r7E906410100040000")
+            .reply("0900", "7E806490040000000")
+    }
+}
+
+impl Write for FakeElm {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for &byte in bytes {
+            if byte != b'\r' {
+                self.command.push(byte as char);
+                continue;
+            }
+            let command = std::mem::take(&mut self.command);
+            let reply = self.replies.get(command.trim()).map(String::as_str).unwrap_or("?");
+            self.output.extend_from_slice(format!("{}\r\r>", reply).as_bytes());
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for FakeElm {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_empty() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        let count = buffer.len().min(self.output.len());
+        buffer[..count].copy_from_slice(&self.output[..count]);
+        self.output.drain(..count);
+        Ok(count)
+    }
+}
+
+// Use English messages and no colors, so output does not depend on the environment
+pub fn init() {
+    i18n::init(Some("en"));
+    let utc = crate::time::TimeSettings { timezone: Some("UTC".to_string()), ..Default::default() };
+    crate::time::init(&utc).unwrap();
+    colored::control::set_override(false);
+}
+
+// Compare output with tests/golden/<name>, or rewrite the file when UPDATE_GOLDEN is set
+pub fn assert_golden(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name].iter().collect();
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Could not read {} (run with UPDATE_GOLDEN=1 to create it): {}", path.display(), e));
+    assert!(
+        expected == actual,
+        "{} does not match the rendered output (run with UPDATE_GOLDEN=1 to update it)\n--- expected\n{}\n--- actual\n{}",
+        path.display(), expected, actual
+    );
+}
+
+// What a report writer streams, as text
+pub fn streamed(write: impl FnOnce(&mut Stream<Vec<u8>>) -> io::Result<()>) -> String {
+    let mut out = Stream::new(Vec::new(), None);
+    write(&mut out).unwrap();
+    String::from_utf8(out.finish().unwrap()).unwrap()
+}
+
+// A directory of its own for a test's files, removed with them when the test ends, even by panicking
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    // An empty directory named after the test, e.g. "events"; one left behind by an earlier run is emptied
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("cars-scanner-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    // The path of a file in the directory as text, for the functions that take one
+    pub fn file(&self, name: &str) -> String {
+        self.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,171 @@
+// Timestamps for scans, notes and reports.
+//
+// Times are kept as seconds since the Unix epoch. Files meant for programs
+// (the archive, CSV appendices) get ISO-8601 in UTC; anything meant for
+// people is shown in the configured time zone with the language's date
+// format, both of which the `[time]` table in cars-scanner.toml can override.
+use std::error::Error;
+use std::sync::OnceLock;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use crate::i18n::tr;
+
+// The `[time]` table; every setting is optional
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeSettings {
+    // IANA time zone such as "Europe/Stockholm"; the system time zone by default
+    pub timezone: Option<String>,
+
+    // strftime patterns; the language's own formats by default
+    pub date_format: Option<String>,
+    pub datetime_format: Option<String>,
+}
+
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+struct TimeFormat {
+    zone: Zone,
+    date_format: String,
+    datetime_format: String,
+}
+
+static FORMAT: OnceLock<TimeFormat> = OnceLock::new();
+
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+        return Err(format!("Invalid time format '{}'", pattern));
+    }
+    Ok(())
+}
+
+impl TimeSettings {
+    // Check the time zone name and the format patterns
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.timezone {
+            name.parse::<Tz>().map_err(|_| format!("Unknown time zone '{}'", name))?;
+        }
+        for pattern in [&self.date_format, &self.datetime_format].into_iter().flatten() {
+            check_pattern(pattern)?;
+        }
+        Ok(())
+    }
+}
+
+// Use these settings for every time shown from now on; the first call wins.
+// Messages must be initialized first so the language's formats are used.
+pub fn init(settings: &TimeSettings) -> Result<(), Box<dyn Error>> {
+    settings.validate()?;
+    let zone = match &settings.timezone {
+        Some(name) => Zone::Named(name.parse::<Tz>().map_err(|_| format!("Unknown time zone '{}'", name))?),
+        None => Zone::Local,
+    };
+    let _ = FORMAT.set(TimeFormat {
+        zone,
+        date_format: settings.date_format.clone().unwrap_or_else(|| tr!("time-date-format")),
+        datetime_format: settings.datetime_format.clone().unwrap_or_else(|| tr!("time-datetime-format")),
+    });
+    Ok(())
+}
+
+fn time_format() -> &'static TimeFormat {
+    FORMAT.get_or_init(|| TimeFormat {
+        zone: Zone::Local,
+        date_format: tr!("time-date-format"),
+        datetime_format: tr!("time-datetime-format"),
+    })
+}
+
+fn utc(timestamp: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default()
+}
+
+// Format a timestamp in the configured time zone with a strftime pattern
+pub fn format_local(timestamp: u64, pattern: &str) -> String {
+    let time = utc(timestamp);
+    match time_format().zone {
+        Zone::Local => time.with_timezone(&Local).format(pattern).to_string(),
+        Zone::Named(zone) => zone.from_utc_datetime(&time.naive_utc()).format(pattern).to_string(),
+    }
+}
+
+// The calendar date in the configured time zone, for people
+pub fn format_date(timestamp: u64) -> String {
+    format_local(timestamp, &time_format().date_format)
+}
+
+// Date and time in the configured time zone, for people
+pub fn format_datetime(timestamp: u64) -> String {
+    format_local(timestamp, &time_format().datetime_format)
+}
+
+// ISO-8601 in UTC, e.g. 2023-11-14T22:13:20Z, for files read by programs
+pub fn iso8601(timestamp: u64) -> String {
+    utc(timestamp).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+// Parse an ISO-8601 / RFC 3339 time with an offset into seconds since the epoch
+pub fn parse_iso8601(text: &str) -> Result<u64, String> {
+    let time = DateTime::parse_from_rfc3339(text.trim()).map_err(|e| format!("Invalid time '{}': {}", text, e))?;
+    u64::try_from(time.timestamp()).map_err(|_| format!("Time '{}' is before 1970", text))
+}
+
+// Serde adapter writing timestamps as ISO-8601; numbers written by older versions are still read
+pub mod iso8601_serde {
+    use std::fmt;
+    use super::*;
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(timestamp: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&iso8601(*timestamp))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        struct TimestampVisitor;
+
+        impl Visitor<'_> for TimestampVisitor {
+            type Value = u64;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an ISO-8601 time or seconds since the Unix epoch")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<u64, E> {
+                Ok(value)
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<u64, E> {
+                parse_iso8601(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::ScanRecord;
+    use crate::testing::{FIXED_TIME, init};
+
+    #[test]
+    fn timestamps_for_people_and_programs() {
+        init();
+        assert_eq!(super::format_date(0), "1970-01-01");
+        assert_eq!(super::format_date(951_782_400), "2000-02-29");
+        assert_eq!(super::format_datetime(FIXED_TIME), "2023-11-14 22:13 UTC");
+        assert_eq!(super::iso8601(FIXED_TIME), "2023-11-14T22:13:20Z");
+        assert_eq!(super::parse_iso8601("2023-11-15T00:13:20+02:00"), Ok(FIXED_TIME));
+
+        // Archives written before timestamps were ISO-8601 are still read
+        let legacy: ScanRecord = serde_json::from_str(r#"{"id": "a", "vin": "V", "timestamp": 1700000000, "source": "s", "codes": []}"#).unwrap();
+        assert_eq!(legacy.timestamp, FIXED_TIME);
+        assert!(serde_json::to_string(&legacy).unwrap().contains(r#""timestamp":"2023-11-14T22:13:20Z""#));
+    }
+}
@@ -0,0 +1,96 @@
+// Recording of interactive sessions, saved as Markdown by `transcript save`
+use std::cell::RefCell;
+use std::error::Error;
+use std::fs;
+
+struct Entry {
+    command: String,
+    output: String,
+}
+
+thread_local! {
+    static TRANSCRIPT: RefCell<Option<Vec<Entry>>> = const { RefCell::new(None) };
+}
+
+// Print a line and record it in the transcript when one is being kept
+#[macro_export]
+macro_rules! outln {
+    () => {
+        $crate::transcript::outln!("")
+    };
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        println!("{}", line);
+        $crate::transcript::record(&line);
+    }};
+}
+pub use outln;
+
+// Start recording everything shown from now on
+pub fn start() {
+    TRANSCRIPT.with(|transcript| *transcript.borrow_mut() = Some(Vec::new()));
+}
+
+// Begin a new transcript entry for a command typed at the prompt
+pub fn begin_command(command: &str) {
+    TRANSCRIPT.with(|transcript| {
+        if let Some(entries) = transcript.borrow_mut().as_mut() {
+            entries.push(Entry {
+                command: command.to_string(),
+                output: String::new(),
+            });
+        }
+    });
+}
+
+// Record output text under the current command, with colors stripped
+pub fn record(text: &str) {
+    TRANSCRIPT.with(|transcript| {
+        if let Some(entry) = transcript.borrow_mut().as_mut().and_then(|entries| entries.last_mut()) {
+            entry.output.push_str(&strip_ansi(text));
+            entry.output.push('\n');
+        }
+    });
+}
+
+// Write the transcript as Markdown; returns the number of commands written
+pub fn save(path: &str) -> Result<usize, Box<dyn Error>> {
+    let (markdown, count) = TRANSCRIPT.with(|transcript| {
+        let transcript = transcript.borrow();
+        let entries = transcript.as_deref().unwrap_or_default();
+
+        let mut markdown = String::from("# Interactive Session Transcript\n");
+        for entry in entries {
+            markdown.push_str(&format!("\n## `{}`\n\n", entry.command));
+            let output = entry.output.trim_end();
+            if !output.is_empty() {
+                markdown.push_str(&format!("```text\n{}\n```\n", output));
+            }
+        }
+        (markdown, entries.len())
+    });
+
+    fs::write(path, markdown)?;
+    Ok(count)
+}
+
+// Remove ANSI escape sequences such as color codes
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip "ESC [ parameters final-byte"
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
@@ -0,0 +1,229 @@
+// Decision trees that walk a technician through diagnosing a code.
+//
+// Trees are read from a YAML file keyed by code. Each tree is a list of
+// steps, starting with the first; a step either asks for a measurement and
+// branches on a threshold, asks a yes/no question, or ends with a conclusion.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::archive::{Clock, SystemClock};
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+
+// Default location of the bundled decision trees
+pub const DECISION_TREES_FILE: &str = "src/data/decision_trees.yaml";
+
+// Name of the notes file inside the data directory
+pub const NOTES_FILE: &str = "session-notes.md";
+
+// Longest walk before a tree is assumed to loop
+const MAX_STEPS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DecisionTree {
+    pub title: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Step {
+    // Ask for a reading and go to `below` or `otherwise` depending on the threshold
+    Measure {
+        id: String,
+        measure: String,
+        #[serde(default)]
+        unit: String,
+        threshold: f64,
+        below: String,
+        otherwise: String,
+    },
+    // Ask a yes/no question
+    Question {
+        id: String,
+        ask: String,
+        yes: String,
+        no: String,
+    },
+    // End of the walk
+    Conclusion {
+        id: String,
+        conclusion: String,
+    },
+}
+
+impl Step {
+    fn id(&self) -> &str {
+        match self {
+            Step::Measure { id, .. } | Step::Question { id, .. } | Step::Conclusion { id, .. } => id,
+        }
+    }
+
+    fn targets(&self) -> Vec<&str> {
+        match self {
+            Step::Measure { below, otherwise, .. } => vec![below, otherwise],
+            Step::Question { yes, no, .. } => vec![yes, no],
+            Step::Conclusion { .. } => Vec::new(),
+        }
+    }
+}
+
+// One step of a walk: what was asked and what the answer was
+pub struct PathEntry {
+    pub prompt: String,
+    pub answer: String,
+}
+
+// The steps taken through a tree and where the walk ended
+pub struct Walk {
+    pub path: Vec<PathEntry>,
+    // The conclusion reached, or None when the walk was abandoned
+    pub conclusion: Option<String>,
+}
+
+// Decision trees keyed by error code
+#[derive(Default)]
+pub struct DecisionTrees {
+    trees: BTreeMap<String, DecisionTree>,
+}
+
+impl DecisionTrees {
+    // Load and check a decision tree file
+    pub fn load(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("Could not open decision trees {}: {}", file_path, e))?;
+        let trees: BTreeMap<String, DecisionTree> = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid decision trees in {}: {}", file_path, e))?;
+
+        for (code, tree) in &trees {
+            if tree.steps.is_empty() {
+                return Err(format!("Decision tree for {} in {} has no steps", code, file_path).into());
+            }
+            for step in &tree.steps {
+                for target in step.targets() {
+                    if !tree.steps.iter().any(|candidate| candidate.id() == target) {
+                        return Err(format!("Decision tree for {} in {}: step '{}' refers to unknown step '{}'", code, file_path, step.id(), target).into());
+                    }
+                }
+            }
+        }
+
+        let trees = trees.into_iter().map(|(code, tree)| (code.to_uppercase(), tree)).collect();
+        Ok(DecisionTrees { trees })
+    }
+
+    pub fn get(&self, code: &str) -> Option<&DecisionTree> {
+        self.trees.get(&code.to_uppercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+}
+
+// Walk a tree, reading answers from `input`; an empty answer or "q" abandons the walk
+pub fn walk(tree: &DecisionTree, input: &mut impl BufRead) -> Result<Walk, Box<dyn Error>> {
+    let mut path = Vec::new();
+    let mut step = &tree.steps[0];
+
+    for _ in 0..MAX_STEPS {
+        let next = match step {
+            Step::Conclusion { conclusion, .. } => {
+                outln!("\n{} {}", tr!("troubleshoot-conclusion").styled(Style::Success), conclusion);
+                return Ok(Walk { path, conclusion: Some(conclusion.clone()) });
+            },
+            Step::Measure { measure, unit, threshold, below, otherwise, .. } => {
+                let prompt = if unit.is_empty() { measure.clone() } else { format!("{} ({})", measure, unit) };
+                let Some(value) = ask(&prompt, input, |answer| answer.replace(',', ".").parse::<f64>().ok())? else {
+                    return Ok(Walk { path, conclusion: None });
+                };
+                path.push(PathEntry { prompt, answer: format!("{} {}", value, unit).trim_end().to_string() });
+                if value < *threshold { below } else { otherwise }
+            },
+            Step::Question { ask: question, yes, no, .. } => {
+                let prompt = format!("{} {}", question, tr!("troubleshoot-yes-no"));
+                let Some(answer) = ask(&prompt, input, parse_yes_no)? else {
+                    return Ok(Walk { path, conclusion: None });
+                };
+                path.push(PathEntry { prompt: question.clone(), answer: if answer { tr!("troubleshoot-yes") } else { tr!("troubleshoot-no") } });
+                if answer { yes } else { no }
+            },
+        };
+        step = tree.steps.iter().find(|candidate| candidate.id() == next.as_str()).expect("targets are checked on load");
+    }
+
+    Err(tr!("troubleshoot-loop", steps = MAX_STEPS).into())
+}
+
+fn parse_yes_no(answer: &str) -> Option<bool> {
+    match answer.to_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+// Ask until `parse` accepts the answer; None when the user quits
+fn ask<T>(prompt: &str, input: &mut impl BufRead, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>, Box<dyn Error>> {
+    loop {
+        print!("{} ", format!("{}:", prompt).styled(Style::Hint));
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        let end_of_input = input.read_line(&mut line)? == 0;
+        let answer = line.trim();
+        crate::transcript::record(&format!("{}: {}", prompt, answer));
+        if end_of_input || answer.is_empty() || answer.eq_ignore_ascii_case("q") {
+            outln!("\n{}", tr!("troubleshoot-abandoned"));
+            return Ok(None);
+        }
+        match parse(answer) {
+            Some(value) => return Ok(Some(value)),
+            None => outln!("{}", tr!("troubleshoot-invalid")),
+        }
+    }
+}
+
+// Append the path taken to the session notes in the data directory
+pub fn record_notes(data_dir: &Path, code: &str, tree: &DecisionTree, walk: &Walk) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(data_dir)?;
+    let path = data_dir.join(NOTES_FILE);
+    let mut notes = format!("\n## {} - {} ({})\n\n", code, tree.title, crate::time::format_datetime(SystemClock.now()));
+    for (index, entry) in walk.path.iter().enumerate() {
+        notes.push_str(&format!("{}. {}: {}\n", index + 1, entry.prompt, entry.answer));
+    }
+    match &walk.conclusion {
+        Some(conclusion) => notes.push_str(&format!("\n{} {}\n", tr!("troubleshoot-conclusion"), conclusion)),
+        None => notes.push_str(&format!("\n{}\n", tr!("troubleshoot-abandoned"))),
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(notes.as_bytes())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::init;
+
+    #[test]
+    fn troubleshoot_follows_answers() {
+        init();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/data/decision_trees.yaml");
+        let trees = super::DecisionTrees::load(path).unwrap();
+        let tree = trees.get("p0171").unwrap();
+
+        let walk = super::walk(tree, &mut "4\n12,5\nmaybe\n250\n".as_bytes()).unwrap();
+        let answers: Vec<_> = walk.path.iter().map(|entry| entry.answer.as_str()).collect();
+        assert_eq!(answers, ["4 %", "12.5 %", "250 kPa"]);
+        assert_eq!(walk.conclusion.as_deref(), Some("Check the fuel filter, pump and pressure regulator"));
+    }
+}
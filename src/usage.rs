@@ -0,0 +1,231 @@
+// A local record of how the tool is used, summarized by `usage`.
+//
+// Shop owners want to know whether the tool is being picked up: how many
+// codes are looked up a day, which systems come up most, how long an
+// interactive session lasts. Each command appends one line to
+// <data_dir>/usage.jsonl with its time, name and the codes it looked up.
+// The file never leaves the machine: nothing here touches the network,
+// and `usage` only reads it. Turn recording off with:
+//
+//   [usage]
+//   record = false
+//
+// Commands that load no configuration (`docs`, `doctor`) are not recorded.
+// Interactive mode records `interactive` when it starts and `exit` when it
+// ends, which gives the length of the session.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::presentation::{Style, Styled};
+use crate::transcript::outln;
+use crate::DiagnosticsDatabase;
+
+const DAY: u64 = 24 * 60 * 60;
+
+// Width of the longest bar of lookups per day
+const BAR_WIDTH: usize = 30;
+
+// Systems and commands listed
+const TOP: usize = 5;
+
+// The events that start and end an interactive session
+const SESSION_START: &str = "interactive";
+const SESSION_END: &str = "exit";
+
+// The `[usage]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UsageSettings {
+    pub record: bool,
+}
+
+impl Default for UsageSettings {
+    fn default() -> Self {
+        UsageSettings { record: true }
+    }
+}
+
+// One command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub timestamp: u64,
+    // Shared by the commands of one interactive session
+    pub session: String,
+    // E.g. "lookup" or "fleet report"
+    pub command: String,
+    // Codes looked up, for lookups only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codes: Vec<String>,
+}
+
+pub fn log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("usage.jsonl")
+}
+
+// Append an event, unless recording is off; a failure only costs the statistics, never the command
+pub fn record(config: &Config, event: &Event) {
+    if !config.usage.record {
+        return;
+    }
+    let data_dir = config.data_dir();
+    let Ok(line) = serde_json::to_string(event) else { return };
+    let _ = fs::create_dir_all(&data_dir)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(log_path(&data_dir)))
+        .and_then(|mut file| writeln!(file, "{}", line));
+}
+
+// Every recorded event, oldest first; lines that cannot be read, such as one cut short, are skipped
+pub fn load(data_dir: &Path) -> Result<Vec<Event>, Box<dyn Error>> {
+    let path = log_path(data_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let mut events: Vec<Event> = content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    events.sort_by_key(|event| event.timestamp);
+    Ok(events)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    // Local dates, oldest first, with the number of lookups; days without any included
+    pub lookups_per_day: Vec<(String, usize)>,
+    // Systems of the codes looked up, most frequent first
+    pub systems: Vec<(String, usize)>,
+    pub commands: Vec<(String, usize)>,
+    // Interactive sessions, and their average length in seconds
+    pub sessions: usize,
+    pub average_session: Option<u64>,
+}
+
+// Most frequent first, ties by name
+fn top(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+    counts.truncate(TOP);
+    counts
+}
+
+// The events of the `days` days up to `now`
+pub fn summarize(events: &[Event], db: &DiagnosticsDatabase, days: u64, now: u64) -> Summary {
+    let since = now.saturating_sub(days.saturating_mul(DAY));
+    let events: Vec<&Event> = events.iter().filter(|event| event.timestamp > since && event.timestamp <= now).collect();
+
+    let mut lookups: HashMap<String, usize> = HashMap::new();
+    let mut systems: HashMap<String, usize> = HashMap::new();
+    let mut commands: HashMap<String, usize> = HashMap::new();
+    let mut sessions: HashMap<&str, (u64, u64)> = HashMap::new();
+    for event in &events {
+        if event.command != SESSION_END {
+            *commands.entry(event.command.clone()).or_default() += 1;
+        }
+        if !event.codes.is_empty() {
+            *lookups.entry(crate::time::format_date(event.timestamp)).or_default() += 1;
+        }
+        for error in event.codes.iter().filter_map(|code| db.lookup_error(code)) {
+            *systems.entry(error.system.clone()).or_default() += 1;
+        }
+        if event.command == SESSION_START {
+            sessions.insert(&event.session, (event.timestamp, event.timestamp));
+        } else if let Some(span) = sessions.get_mut(event.session.as_str()) {
+            span.1 = event.timestamp;
+        }
+    }
+
+    let lengths: Vec<u64> = sessions.values().map(|(start, end)| end - start).collect();
+    // No days before 1970, however far back was asked for
+    let lookups_per_day = (0..days.min(now / DAY + 1))
+        .rev()
+        .map(|day| crate::time::format_date(now - day * DAY))
+        .map(|date| {
+            let count = lookups.get(&date).copied().unwrap_or_default();
+            (date, count)
+        })
+        .collect();
+    Summary {
+        lookups_per_day,
+        systems: top(systems),
+        commands: top(commands),
+        sessions: lengths.len(),
+        average_session: (!lengths.is_empty()).then(|| lengths.iter().sum::<u64>() / lengths.len() as u64),
+    }
+}
+
+pub fn display(summary: &Summary, data_dir: &Path) {
+    outln!("{}", tr!("usage-header", days = summary.lookups_per_day.len()).styled(Style::Heading));
+    outln!("{}", tr!("usage-local", path = log_path(data_dir).display().to_string()).styled(Style::Muted));
+
+    outln!();
+    outln!("{}", tr!("usage-lookups-per-day").styled(Style::Section));
+    let max = summary.lookups_per_day.iter().map(|(_, count)| *count).max().unwrap_or_default().max(1);
+    for (date, count) in &summary.lookups_per_day {
+        let bar = "█".repeat((count * BAR_WIDTH).div_ceil(max));
+        outln!("  {}  {:>4} {}", date, count, bar.styled(Style::Hint));
+    }
+
+    for (title, counts) in [(tr!("usage-systems"), &summary.systems), (tr!("usage-commands"), &summary.commands)] {
+        outln!();
+        outln!("{}", title.styled(Style::Section));
+        if counts.is_empty() {
+            outln!("  {}", tr!("usage-nothing").styled(Style::Muted));
+        }
+        let width = counts.iter().map(|(name, _)| name.chars().count()).max().unwrap_or_default();
+        for (name, count) in counts {
+            outln!("  {:<width$}  {:>4}", name, count, width = width);
+        }
+    }
+
+    outln!();
+    match summary.average_session {
+        Some(average) => outln!("{}", tr!("usage-sessions", sessions = summary.sessions, minutes = average.div_ceil(60))),
+        None => outln!("{}", tr!("usage-no-sessions")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{init, sample_database, FIXED_TIME};
+    use super::Event;
+
+    #[test]
+    fn usage_counts_lookups_per_day_systems_and_session_length() {
+        init();
+        let db = sample_database();
+        let event = |secs_ago: u64, session: &str, command: &str, codes: &[&str]| Event {
+            timestamp: FIXED_TIME - secs_ago,
+            session: session.to_string(),
+            command: command.to_string(),
+            codes: codes.iter().map(|code| code.to_string()).collect(),
+        };
+        let events = [
+            event(3 * 86400, "old", "lookup", &["P0300"]),
+            event(86400, "a", "lookup", &["P0101"]),
+            event(1200, "b", "interactive", &[]),
+            event(1100, "b", "lookup", &["P0300", "C0035"]),
+            event(1000, "b", "search", &[]),
+            event(600, "b", "exit", &[]),
+            event(60, "c", "quote", &["P0300"]),
+        ];
+        let summary = super::summarize(&events, &db, 2, FIXED_TIME);
+        let lookups: Vec<usize> = summary.lookups_per_day.iter().map(|(_, count)| *count).collect();
+        assert_eq!(lookups, [1, 2]);
+        assert_eq!(summary.systems, [("Engine".to_string(), 3), ("ABS".to_string(), 1)]);
+        assert_eq!(summary.commands[0], ("lookup".to_string(), 2));
+        assert!(!summary.commands.iter().any(|(command, _)| command == "exit"));
+        assert_eq!((summary.sessions, summary.average_session), (1, Some(600)));
+        let everything = super::summarize(&events, &db, 999_999_999_999_999_999, FIXED_TIME);
+        assert_eq!(everything.lookups_per_day.len() as u64, FIXED_TIME / super::DAY + 1);
+        assert_eq!(everything.lookups_per_day.iter().map(|(_, count)| *count).sum::<usize>(), 4);
+
+        let line = serde_json::to_string(&events[5]).unwrap();
+        assert!(!line.contains("codes"));
+        assert_eq!(serde_json::from_str::<Event>(&line).unwrap(), events[5]);
+        assert_eq!(crate::i18n::tr!("usage-sessions", sessions = 1, minutes = 10), "One interactive session, 10 min on average");
+    }
+}
@@ -0,0 +1,163 @@
+// The vehicle interactive mode is working on (`vehicle set`).
+//
+// Once a vehicle is set, packs limited to other makes (`makes` in a
+// `[[packs]]` entry) drop out of lookups, searches and lists, and the codes
+// looked up or scanned are added to the vehicle's scan history, the same
+// archive `fleet history` reads. A vehicle is known by its VIN, by its make,
+// model and year, or by both; the model year is read from the VIN when it is
+// not given.
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::archive::{Archive, Clock, ScanRecord, SystemClock};
+use crate::config::{Config, PackConfig};
+use crate::i18n::tr;
+use crate::inspection;
+use crate::{merge, DiagnosticsDatabase};
+
+// Characters a VIN may contain; I, O and Q are never used
+const VIN_CHARACTERS: &str = "ABCDEFGHJKLMNPRSTUVWXYZ0123456789";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Vehicle {
+    pub vin: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub year: Option<u16>,
+}
+
+pub fn looks_like_vin(text: &str) -> bool {
+    text.len() == 17 && text.to_ascii_uppercase().chars().all(|c| VIN_CHARACTERS.contains(c))
+}
+
+impl Vehicle {
+    // Parse `vehicle set` arguments: a VIN, a four-digit year and the make followed by the model,
+    // in any order, e.g. `1HGCM82633A004352 Honda Accord` or `Toyota Corolla 2015`
+    pub fn parse(args: &[&str], now: u64) -> Result<Self, String> {
+        let mut vehicle = Vehicle::default();
+        let mut names = Vec::new();
+        for arg in args {
+            if looks_like_vin(arg) {
+                vehicle.vin = Some(arg.to_ascii_uppercase());
+            } else if let Some(year) = arg.parse::<u16>().ok().filter(|year| (1980..=2100).contains(year)) {
+                vehicle.year = Some(year);
+            } else if arg.len() == 17 && arg.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(tr!("vehicle-invalid-vin", vin = *arg));
+            } else {
+                names.push(*arg);
+            }
+        }
+        if let Some((make, model)) = names.split_first() {
+            vehicle.make = Some(make.to_string());
+            vehicle.model = (!model.is_empty()).then(|| model.join(" "));
+        }
+        if vehicle.year.is_none() {
+            vehicle.year = vehicle.vin.as_deref().and_then(|vin| inspection::model_year(vin, now));
+        }
+        if vehicle.vin.is_none() && vehicle.make.is_none() {
+            return Err(tr!("vehicle-set-nothing"));
+        }
+        Ok(vehicle)
+    }
+
+    // The key of the vehicle's scan history: the VIN, or the make, model and year
+    pub fn history_key(&self) -> String {
+        match &self.vin {
+            Some(vin) => vin.clone(),
+            None => self.description(),
+        }
+    }
+
+    // Make, model and year, as far as they are known
+    fn description(&self) -> String {
+        let year = self.year.map(|year| year.to_string());
+        [self.make.as_deref(), self.model.as_deref(), year.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(" ")
+    }
+
+    // Whether a pack's definitions apply: packs without `makes` apply to every vehicle,
+    // and so does every pack while the make is unknown
+    pub fn uses_pack(&self, pack: &PackConfig) -> bool {
+        match &self.make {
+            Some(make) => pack.makes.is_empty() || pack.makes.iter().any(|name| name.eq_ignore_ascii_case(make)),
+            None => true,
+        }
+    }
+
+    // The database as seen for this vehicle: definitions from packs for other makes are
+    // left out and every code is merged again from the rest
+    pub fn database(&self, db: &DiagnosticsDatabase, config: &Config) -> DiagnosticsDatabase {
+        let excluded: Vec<String> = config.packs.iter()
+            .filter(|pack| !self.uses_pack(pack))
+            .map(PackConfig::display_name)
+            .collect();
+        let mut view = DiagnosticsDatabase::new();
+        for (code, definitions) in &db.definitions {
+            let kept: Vec<_> = definitions.iter()
+                .filter(|definition| definition.source.as_ref().is_none_or(|source| !excluded.contains(&source.pack)))
+                .cloned()
+                .collect();
+            if let Some((merged, _)) = merge::merge(&kept, &config.merge) {
+                view.errors.insert(code.clone(), merged);
+                view.definitions.insert(code.clone(), kept);
+            }
+        }
+        view
+    }
+
+    // Add codes read or looked up in interactive mode to the vehicle's scan history;
+    // returns the id of the scan
+    pub fn record(&self, config: &Config, source: &str, codes: &[String]) -> Result<String, Box<dyn std::error::Error>> {
+        let clock = SystemClock;
+        let record = ScanRecord {
+            id: clock.batch_id(),
+            vin: self.history_key(),
+            timestamp: clock.now(),
+            source: format!("interactive:{}", source),
+            codes: codes.to_vec(),
+            summary: None,
+        };
+        Archive::open(&config.data_dir())?.save(&record)?;
+        Ok(record.id)
+    }
+}
+
+impl fmt::Display for Vehicle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.vin, self.make.is_some()) {
+            (Some(vin), true) => write!(f, "{} ({})", self.description(), vin),
+            (Some(vin), false) => write!(f, "{}", vin),
+            (None, _) => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DiagnosticsDatabase, ErrorCode};
+    use crate::testing::FIXED_TIME;
+    use super::Vehicle;
+
+    #[test]
+    fn vehicle_context_leaves_out_packs_for_other_makes() {
+        let honda = Vehicle::parse(&["1HGCM82633A004352", "Honda", "Accord", "EX"], FIXED_TIME).unwrap();
+        assert_eq!((honda.make.as_deref(), honda.model.as_deref(), honda.year), (Some("Honda"), Some("Accord EX"), Some(2003)));
+        assert_eq!(honda.to_string(), "Honda Accord EX 2003 (1HGCM82633A004352)");
+        assert_eq!(Vehicle::parse(&["toyota", "2015"], FIXED_TIME).unwrap().history_key(), "toyota 2015");
+        assert!(Vehicle::parse(&["1HGCM82633A0O4352"], FIXED_TIME).is_err());
+        assert!(Vehicle::parse(&[], FIXED_TIME).is_err());
+
+        let config: crate::config::Config = toml::from_str("[[packs]]\npath = \"toyota.csv\"\nmakes = [\"Toyota\"]\n").unwrap();
+        let pack = crate::Source { pack: "toyota".to_string(), file: "toyota.csv".to_string(), version: None, precedence: 1 };
+        let db = DiagnosticsDatabase::from_records([
+            ErrorCode::fixture_with("P0420", "Medium", "Emissions"),
+            ErrorCode { source: Some(std::sync::Arc::new(pack.clone())), ..ErrorCode::fixture_with("P0420", "High", "Emissions") },
+            ErrorCode { source: Some(std::sync::Arc::new(pack)), ..ErrorCode::fixture_with("P1349", "Medium", "Engine") },
+        ]);
+
+        let for_honda = honda.database(&db, &config);
+        assert_eq!(for_honda.lookup_error("P0420").unwrap().severity, "Medium");
+        assert!(for_honda.lookup_error("P1349").is_none());
+        let toyota = Vehicle::parse(&["Toyota"], FIXED_TIME).unwrap();
+        assert_eq!(toyota.database(&db, &config).lookup_error("P0420").unwrap().severity, "High");
+    }
+}
@@ -0,0 +1,300 @@
+// Warranty claims (`report --type warranty-claim`).
+//
+// A claim to the manufacturer carries the VIN, the mileage, each trouble code
+// with its state, the freeze frame and the technician's account of the
+// repair. Portals take them as files in their own layout, so the layout
+// comes from a template file keyed by name: CSV with a row per code or XML
+// with an element per field, and a placeholder for every value. The
+// bundled layouts are in src/data/warranty_templates.yaml; another portal
+// needs only a new entry there or in the file warranty_templates names.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use clap::ValueEnum;
+use serde::Deserialize;
+use crate::i18n::tr;
+use crate::repair_order::RepairOrder;
+use crate::scan::Scan;
+use crate::scenario::FreezeFrame;
+use crate::{escape_html, pids, time, DiagnosticsDatabase};
+
+// Default location of the bundled templates
+pub const WARRANTY_TEMPLATES_FILE: &str = "src/data/warranty_templates.yaml";
+
+// Layout used when no portal is chosen
+pub const DEFAULT_OEM: &str = "generic";
+
+const CLAIM_PLACEHOLDERS: &[&str] = &["vin", "mileage", "repair_order", "customer", "date", "narrative", "code_count"];
+const CODE_PLACEHOLDERS: &[&str] = &["code", "status", "description", "system", "severity", "freeze_frame"];
+
+// What `report` writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportType {
+    // One code, for the customer
+    #[default]
+    Code,
+    // The vehicle's codes, for the manufacturer's warranty portal
+    WarrantyClaim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimFormat {
+    Csv,
+    Xml,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimTemplate {
+    pub format: ClaimFormat,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    // [name, value] pairs, in the order they are written
+    pub claim: Vec<(String, String)>,
+    pub code: Vec<(String, String)>,
+    #[serde(default = "default_root")]
+    pub root: String,
+    #[serde(default = "default_codes_element")]
+    pub codes_element: String,
+    #[serde(default = "default_code_element")]
+    pub code_element: String,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_root() -> String {
+    "WarrantyClaim".to_string()
+}
+
+fn default_codes_element() -> String {
+    "Codes".to_string()
+}
+
+fn default_code_element() -> String {
+    "Code".to_string()
+}
+
+#[derive(Debug, Default)]
+pub struct ClaimTemplates {
+    templates: BTreeMap<String, ClaimTemplate>,
+}
+
+// Replace every {name} in `value` with what `lookup` gives for it
+fn fill(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut filled = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| format!("Unclosed placeholder in '{}'", value))? + start;
+        let name = &rest[start + 1..end];
+        filled.push_str(&rest[..start]);
+        filled.push_str(&lookup(name).ok_or_else(|| format!("Unknown placeholder {{{}}} in '{}'", name, value))?);
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+impl ClaimTemplate {
+    // Check that every value only uses the placeholders its place allows
+    fn validate(&self) -> Result<(), String> {
+        for (_, value) in &self.claim {
+            fill(value, |name| CLAIM_PLACEHOLDERS.contains(&name).then(String::new))?;
+        }
+        for (_, value) in &self.code {
+            fill(value, |name| (CODE_PLACEHOLDERS.contains(&name) || CLAIM_PLACEHOLDERS.contains(&name)).then(String::new))?;
+        }
+        Ok(())
+    }
+}
+
+impl ClaimTemplates {
+    pub fn load(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|e| format!("Could not open warranty templates {}: {}", file_path, e))?;
+        let templates: BTreeMap<String, ClaimTemplate> = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid warranty templates in {}: {}", file_path, e))?;
+        for (name, template) in &templates {
+            template.validate().map_err(|e| format!("Invalid warranty template '{}' in {}: {}", name, file_path, e))?;
+        }
+        let templates = templates.into_iter().map(|(name, template)| (name.to_lowercase(), template)).collect();
+        Ok(ClaimTemplates { templates })
+    }
+
+    pub fn get(&self, oem: &str) -> Result<&ClaimTemplate, String> {
+        self.templates.get(&oem.to_lowercase()).ok_or_else(|| {
+            let known: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+            tr!("warranty-unknown-oem", oem = oem, known = known.join(", "))
+        })
+    }
+}
+
+// One trouble code of a claim
+pub struct ClaimCode {
+    pub code: String,
+    // "confirmed", "pending" and/or "permanent"
+    pub status: String,
+    pub freeze_frame: Option<FreezeFrame>,
+}
+
+// Everything a claim is made from
+pub struct Claim {
+    pub vin: Option<String>,
+    pub order: RepairOrder,
+    pub codes: Vec<ClaimCode>,
+    pub narrative: String,
+}
+
+// E.g. "rpm=2150 rpm; speed=88 km/h", in the order of the PID table
+fn describe_frame(frame: &FreezeFrame) -> String {
+    pids::PIDS.iter()
+        .filter_map(|pid| frame.pids.get(pid.name).map(|value| format!("{}={} {}", pid.name, (value * 10.0).round() / 10.0, pid.unit)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl Claim {
+    // The codes of a scan with their states, the freeze frame going to the code that stored it
+    pub fn new(vin: Option<String>, order: RepairOrder, scan: &Scan, freeze_frame: Option<FreezeFrame>, narrative: String) -> Self {
+        let codes = scan.codes.iter()
+            .map(|dtc| {
+                let status: Vec<&str> = [(dtc.confirmed, "confirmed"), (dtc.pending, "pending"), (dtc.permanent, "permanent")]
+                    .into_iter()
+                    .filter_map(|(set, name)| set.then_some(name))
+                    .collect();
+                ClaimCode {
+                    code: dtc.code.clone(),
+                    status: status.join(" "),
+                    freeze_frame: freeze_frame.clone().filter(|frame| frame.code == dtc.code),
+                }
+            })
+            .collect();
+        Claim { vin, order, codes, narrative }
+    }
+
+    fn claim_value(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "vin" => self.vin.clone().unwrap_or_default(),
+            "mileage" => self.order.odometer.map(|odometer| odometer.to_string()).unwrap_or_default(),
+            "repair_order" => self.order.number.clone().unwrap_or_default(),
+            "customer" => self.order.customer.clone().unwrap_or_default(),
+            "date" => self.order.date.map(|date| time::format_local(date, "%Y-%m-%d")).unwrap_or_default(),
+            "narrative" => self.narrative.clone(),
+            "code_count" => self.codes.len().to_string(),
+            _ => return None,
+        })
+    }
+
+    fn code_value(&self, code: &ClaimCode, name: &str, db: &DiagnosticsDatabase) -> Option<String> {
+        let error = db.lookup_error(&code.code);
+        Some(match name {
+            "code" => code.code.clone(),
+            "status" => code.status.clone(),
+            "description" => error.map(|error| error.description.clone()).unwrap_or_default(),
+            "system" => error.map(|error| error.system.clone()).unwrap_or_default(),
+            "severity" => error.map(|error| error.severity.clone()).unwrap_or_default(),
+            "freeze_frame" => code.freeze_frame.as_ref().map(describe_frame).unwrap_or_default(),
+            _ => return self.claim_value(name),
+        })
+    }
+
+    // The claim in the template's layout
+    pub fn render(&self, template: &ClaimTemplate, db: &DiagnosticsDatabase) -> Result<String, Box<dyn Error>> {
+        let claim: Vec<(&str, String)> = template.claim.iter()
+            .map(|(name, value)| Ok((name.as_str(), fill(value, |placeholder| self.claim_value(placeholder))?)))
+            .collect::<Result<_, String>>()?;
+        let codes: Vec<Vec<(&str, String)>> = self.codes.iter()
+            .map(|code| template.code.iter()
+                .map(|(name, value)| Ok((name.as_str(), fill(value, |placeholder| self.code_value(code, placeholder, db))?)))
+                .collect::<Result<_, String>>())
+            .collect::<Result<_, String>>()?;
+        match template.format {
+            ClaimFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new().delimiter(template.delimiter as u8).from_writer(Vec::new());
+                writer.write_record(template.claim.iter().chain(&template.code).map(|(name, _)| name))?;
+                let empty: Vec<(&str, String)> = template.code.iter().map(|(name, _)| (name.as_str(), String::new())).collect();
+                let rows = match codes.is_empty() {
+                    true => vec![empty],
+                    false => codes,
+                };
+                for row in rows {
+                    writer.write_record(claim.iter().chain(&row).map(|(_, value)| value))?;
+                }
+                Ok(String::from_utf8(writer.into_inner()?)?)
+            },
+            ClaimFormat::Xml => {
+                let element = |indent: usize, (name, value): &(&str, String)| format!("{}<{}>{}</{}>\n", " ".repeat(indent), name, escape_html(value), name);
+                let mut xml = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<{}>\n", template.root);
+                xml.extend(claim.iter().map(|field| element(2, field)));
+                xml.push_str(&format!("  <{}>\n", template.codes_element));
+                for code in &codes {
+                    xml.push_str(&format!("    <{}>\n", template.code_element));
+                    xml.extend(code.iter().map(|field| element(6, field)));
+                    xml.push_str(&format!("    </{}>\n", template.code_element));
+                }
+                xml.push_str(&format!("  </{}>\n</{}>\n", template.codes_element, template.root));
+                Ok(xml)
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "adapter"))]
+mod tests {
+    use std::time::Duration;
+    use std::fs;
+    use crate::adapter::Adapter;
+    use crate::emulator::Elm327;
+    use crate::repair_order::RepairOrder;
+    use crate::scenario::Scenario;
+    use crate::testing::{init, sample_database, TempDir};
+    use super::{Claim, ClaimTemplates};
+
+    #[test]
+    fn warranty_claims_carry_code_states_and_the_freeze_frame_in_the_portal_layout() {
+        init();
+        let scenario: Scenario = serde_yaml::from_str("
+vin: 2T1BURHE5FC123456
+stored: [P0300]
+pending: [C0035]
+freeze_frame:
+  code: P0300
+  pids: { rpm: 2400, speed: 75, stft1: 0.78 }
+").unwrap();
+        let mut adapter = Adapter::initialize(Box::new(Elm327::new(scenario)), "emulator", Duration::from_secs(1)).unwrap();
+        let scan = crate::scan::read(&mut adapter).unwrap();
+        let frame = adapter.read_freeze_frame().unwrap().unwrap();
+        assert_eq!(frame.code, "P0300");
+        assert_eq!(frame.pids.get("speed"), Some(&75.0));
+
+        let order = RepairOrder { customer: None, number: Some("RO-7".to_string()), odometer: Some(84_512), date: None };
+        let narrative = "Misfire under load\nReplaced coil, \"cyl 2\"".to_string();
+        let claim = Claim::new(adapter.read_vin().unwrap(), order, &scan, Some(frame), narrative);
+        let templates = ClaimTemplates::load(super::WARRANTY_TEMPLATES_FILE).unwrap();
+        let db = sample_database();
+
+        let csv = claim.render(templates.get("Generic").unwrap(), &db).unwrap();
+        let mut rows = csv::Reader::from_reader(csv.as_bytes());
+        let rows: Vec<Vec<String>> = rows.records().map(|row| row.unwrap().iter().map(str::to_string).collect()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0..2], ["2T1BURHE5FC123456", "84512"]);
+        assert_eq!(&rows[0][4], "Misfire under load\nReplaced coil, \"cyl 2\"");
+        assert_eq!(&rows[0][5..7], ["P0300", "confirmed"]);
+        assert_eq!(&rows[0][8], "stft1=0.8 %; rpm=2400 rpm; speed=75 km/h");
+        assert_eq!(&rows[1][5..9], ["C0035", "pending", "Left Front Wheel Speed Sensor Circuit", ""]);
+
+        let xml = claim.render(templates.get("generic-xml").unwrap(), &db).unwrap();
+        assert!(xml.contains("  <Vin>2T1BURHE5FC123456</Vin>\n"));
+        assert!(xml.contains("<TechnicianNarrative>Misfire under load\nReplaced coil, &quot;cyl 2&quot;</TechnicianNarrative>"));
+        assert!(xml.contains("    <Dtc>\n      <Code>C0035</Code>\n      <Status>pending</Status>\n"));
+        assert!(templates.get("honda").unwrap_err().contains("known: generic, generic-xml"));
+
+        let dir = TempDir::new("warranty");
+        let path = dir.join("portal.yaml");
+        fs::write(&path, "portal:\n  format: csv\n  claim: [[Vin, \"{vin}\"], [Code, \"{code}\"]]\n  code: []\n").unwrap();
+        let error = ClaimTemplates::load(path.to_str().unwrap()).unwrap_err().to_string();
+        assert!(error.contains("Invalid warranty template 'portal'") && error.contains("Unknown placeholder {code}"));
+    }
+}
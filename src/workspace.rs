@@ -0,0 +1,130 @@
+// Interactive sessions kept under a name (`workspace save bay3`).
+//
+// A car can sit on the lift for days. A workspace holds what interactive
+// mode was working on: the vehicle set with `vehicle set`, the working
+// result set (the codes the last lookup, search, list or scan showed, with
+// the command that produced them) and the notes taken with `note`.
+// `workspace load <name>` brings it all back in a later session; the
+// adapter is not part of it and needs `connect` again. Workspaces are JSON
+// files under <data_dir>/workspaces, one per name, replaced on every save.
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::vehicle::Vehicle;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub timestamp: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    #[serde(with = "crate::time::iso8601_serde")]
+    pub saved: u64,
+    pub vehicle: Option<Vehicle>,
+    // The command that produced the result set, e.g. "search misfire"
+    pub query: Option<String>,
+    pub results: Vec<String>,
+    pub notes: Vec<Note>,
+}
+
+pub struct Workspaces {
+    dir: PathBuf,
+}
+
+// Names become file names, so they are kept to letters, digits, '-' and '_'
+fn check_name(name: &str) -> Result<(), String> {
+    match !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        true => Ok(()),
+        false => Err(format!("Invalid workspace name '{}'; use letters, digits, '-' and '_'", name)),
+    }
+}
+
+impl Workspaces {
+    // Open the workspaces inside a data directory, creating the directory when needed
+    pub fn open(data_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let dir = data_dir.join("workspaces");
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Could not create workspace directory {}: {}", dir.display(), e))?;
+        Ok(Workspaces { dir })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name.to_lowercase()))
+    }
+
+    // Store a workspace, replacing one saved under the same name
+    pub fn save(&self, name: &str, workspace: &Workspace) -> Result<(), Box<dyn Error>> {
+        check_name(name)?;
+        fs::write(self.path(name), serde_json::to_string_pretty(workspace)?)?;
+        Ok(())
+    }
+
+    pub fn load(&self, name: &str) -> Result<Workspace, Box<dyn Error>> {
+        check_name(name)?;
+        let path = self.path(name);
+        if !path.is_file() {
+            let names: Vec<String> = self.list()?.into_iter().map(|(name, _)| name).collect();
+            return match names.is_empty() {
+                true => Err(format!("No workspace '{}'; none has been saved yet", name).into()),
+                false => Err(format!("No workspace '{}'; saved: {}", name, names.join(", ")).into()),
+            };
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content).map_err(|e| format!("Invalid workspace {}: {}", path.display(), e))?)
+    }
+
+    // Every saved workspace by name, most recently saved first
+    pub fn list(&self) -> Result<Vec<(String, Workspace)>, Box<dyn Error>> {
+        let mut workspaces = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else { continue };
+            if path.extension().is_some_and(|extension| extension == "json") {
+                let content = fs::read_to_string(&path)?;
+                let workspace: Workspace = serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid workspace {}: {}", path.display(), e))?;
+                workspaces.push((name, workspace));
+            }
+        }
+        workspaces.sort_by(|(a_name, a), (b_name, b)| b.saved.cmp(&a.saved).then_with(|| a_name.cmp(b_name)));
+        Ok(workspaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vehicle::Vehicle;
+    use crate::testing::{init, TempDir, FIXED_TIME};
+    use super::{Note, Workspace, Workspaces};
+
+    #[test]
+    fn workspaces_keep_the_vehicle_results_and_notes_by_name() {
+        init();
+        let dir = TempDir::new("workspaces");
+        let workspaces = Workspaces::open(&dir.join("data")).unwrap();
+        assert!(workspaces.load("bay3").unwrap_err().to_string().contains("none has been saved"));
+
+        let bay3 = Workspace {
+            saved: FIXED_TIME,
+            vehicle: Some(Vehicle::parse(&["Toyota", "Corolla", "2015"], FIXED_TIME).unwrap()),
+            query: Some("search misfire".to_string()),
+            results: vec!["P0300".to_string(), "P0303".to_string()],
+            notes: vec![Note { timestamp: FIXED_TIME, text: "coil 3 swapped to 1".to_string() }],
+        };
+        workspaces.save("bay3", &bay3).unwrap();
+        workspaces.save("Bay-1", &Workspace { saved: FIXED_TIME + 60, ..Workspace::default() }).unwrap();
+        assert!(workspaces.save("../bay3", &bay3).is_err());
+
+        let loaded = workspaces.load("BAY3").unwrap();
+        let names: Vec<String> = workspaces.list().unwrap().into_iter().map(|(name, _)| name).collect();
+        let missing = workspaces.load("bay4").unwrap_err().to_string();
+        assert_eq!(loaded, bay3);
+        assert_eq!(names, ["bay-1", "bay3"]);
+        assert!(missing.contains("saved: bay-1, bay3"));
+    }
+}